@@ -15,6 +15,8 @@ pub enum CliRequest {
     // Since Zed CLI has to be installed separately, there can be situations when old CLI is
     // querying new Zed editors, support both formats by using `String` here and parsing it on Zed side later.
     Open { paths: Vec<String>, wait: bool },
+    // Lists the recent projects, the ones opened most often and most recently first.
+    ListRecentProjects,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
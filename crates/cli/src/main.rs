@@ -36,6 +36,10 @@ struct Args {
     /// Custom Zed.app path
     #[clap(short, long)]
     bundle_path: Option<PathBuf>,
+    /// Print the recent projects, the ones opened most often and most recently first.
+    /// Each project is printed on its own line, with its paths separated by tabs.
+    #[clap(long, conflicts_with = "paths_with_position")]
+    recent: bool,
 }
 
 fn parse_path_with_position(
@@ -74,20 +78,24 @@ fn main() -> Result<()> {
 
     let (tx, rx) = bundle.launch()?;
 
-    tx.send(CliRequest::Open {
-        paths: args
-            .paths_with_position
-            .into_iter()
-            .map(|path_with_position| {
-                let path_with_position = path_with_position.map_path_like(|path| {
-                    fs::canonicalize(&path)
-                        .with_context(|| format!("path {path:?} canonicalization"))
-                })?;
-                Ok(path_with_position.to_string(|path| path.display().to_string()))
-            })
-            .collect::<Result<_>>()?,
-        wait: args.wait,
-    })?;
+    if args.recent {
+        tx.send(CliRequest::ListRecentProjects)?;
+    } else {
+        tx.send(CliRequest::Open {
+            paths: args
+                .paths_with_position
+                .into_iter()
+                .map(|path_with_position| {
+                    let path_with_position = path_with_position.map_path_like(|path| {
+                        fs::canonicalize(&path)
+                            .with_context(|| format!("path {path:?} canonicalization"))
+                    })?;
+                    Ok(path_with_position.to_string(|path| path.display().to_string()))
+                })
+                .collect::<Result<_>>()?,
+            wait: args.wait,
+        })?;
+    }
 
     while let Ok(response) = rx.recv() {
         match response {
@@ -0,0 +1,212 @@
+use editor::{actions::PasteFromHistory, ClipboardHistoryEntry, Editor, EditorMode};
+use fuzzy::{match_strings, StringMatch, StringMatchCandidate};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, ParentElement, Render,
+    Styled, Task, View, ViewContext, VisualContext, WeakView,
+};
+use picker::{Picker, PickerDelegate};
+use std::sync::Arc;
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::{truncate_and_trailoff, ResultExt};
+use workspace::ModalView;
+
+/// The most characters of an entry that are shown in the picker.
+const MAX_PREVIEW_LEN: usize = 80;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(ClipboardHistoryPicker::register)
+        .detach();
+}
+
+pub struct ClipboardHistoryPicker {
+    picker: View<Picker<ClipboardHistoryDelegate>>,
+}
+
+impl ModalView for ClipboardHistoryPicker {}
+
+impl EventEmitter<DismissEvent> for ClipboardHistoryPicker {}
+
+impl FocusableView for ClipboardHistoryPicker {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl Render for ClipboardHistoryPicker {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl ClipboardHistoryPicker {
+    fn register(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+        if editor.mode() != EditorMode::Full {
+            return;
+        }
+        let handle = cx.view().downgrade();
+        editor.register_action(move |_: &PasteFromHistory, cx| {
+            let Some(editor) = handle.upgrade() else {
+                return;
+            };
+            let Some(workspace) = editor.read(cx).workspace() else {
+                return;
+            };
+            let entries = editor::clipboard_history(cx);
+            if entries.is_empty() {
+                return;
+            }
+            workspace.update(cx, |workspace, cx| {
+                workspace.toggle_modal(cx, move |cx| Self::new(entries, editor, cx));
+            })
+        });
+    }
+
+    fn new(
+        entries: Vec<ClipboardHistoryEntry>,
+        editor: View<Editor>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let candidates = entries
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| {
+                // Entries are shown on a single line, with their whitespace collapsed
+                let preview = entry.text.split_whitespace().collect::<Vec<_>>().join(" ");
+                StringMatchCandidate::new(id, truncate_and_trailoff(&preview, MAX_PREVIEW_LEN))
+            })
+            .collect();
+        let delegate = ClipboardHistoryDelegate {
+            picker: cx.view().downgrade(),
+            editor,
+            entries,
+            candidates,
+            matches: Vec::new(),
+            selected_index: 0,
+        };
+        let picker = cx.new_view(|cx| Picker::new(delegate, cx));
+        Self { picker }
+    }
+}
+
+pub struct ClipboardHistoryDelegate {
+    picker: WeakView<ClipboardHistoryPicker>,
+    editor: View<Editor>,
+    entries: Vec<ClipboardHistoryEntry>,
+    candidates: Vec<StringMatchCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl PickerDelegate for ClipboardHistoryDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        "Paste from clipboard history...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let background = cx.background_executor().clone();
+        let candidates = self.candidates.clone();
+        cx.spawn(|picker, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                match_strings(
+                    &candidates,
+                    &query,
+                    false,
+                    100,
+                    &Default::default(),
+                    background,
+                )
+                .await
+            };
+
+            picker
+                .update(&mut cx, |picker, cx| {
+                    let delegate = &mut picker.delegate;
+                    delegate.matches = matches;
+                    delegate.selected_index = delegate
+                        .selected_index
+                        .min(delegate.matches.len().saturating_sub(1));
+                    cx.notify();
+                })
+                .log_err();
+        })
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        if let Some(entry) = self
+            .matches
+            .get(self.selected_index)
+            .and_then(|mat| self.entries.get(mat.candidate_id))
+        {
+            let entry = entry.clone();
+            self.editor.update(cx, |editor, cx| {
+                editor.focus(cx);
+                editor.paste_from_history(entry, cx);
+            });
+        }
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let mat = self.matches.get(ix)?;
+        let entry = self.entries.get(mat.candidate_id)?;
+        let line_count = entry.text.trim_end_matches('\n').lines().count();
+        let mut details = entry.language.as_deref().unwrap_or_default().to_string();
+        if line_count > 1 {
+            if !details.is_empty() {
+                details.push_str(", ");
+            }
+            details.push_str(&format!("{line_count} lines"));
+        }
+
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(HighlightedLabel::new(
+                    mat.string.clone(),
+                    mat.positions.clone(),
+                ))
+                .end_slot((!details.is_empty()).then(|| {
+                    Label::new(details)
+                        .color(Color::Muted)
+                        .size(LabelSize::Small)
+                })),
+        )
+    }
+}
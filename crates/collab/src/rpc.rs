@@ -217,9 +217,22 @@ impl Server {
             .add_message_handler(update_diagnostic_summary)
             .add_message_handler(update_worktree_settings)
             .add_request_handler(forward_read_only_project_request::<proto::GetHover>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetSignatureHelp>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetSemanticTokens>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetLinkedEditingRanges>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetSelectionRanges>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetFoldingRanges>)
             .add_request_handler(forward_read_only_project_request::<proto::GetDefinition>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetDeclaration>)
             .add_request_handler(forward_read_only_project_request::<proto::GetTypeDefinition>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetImplementation>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetEnclosingSymbols>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetFunctionSymbol>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetDocumentSymbols>)
             .add_request_handler(forward_read_only_project_request::<proto::GetReferences>)
+            .add_request_handler(forward_read_only_project_request::<proto::PrepareCallHierarchy>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetIncomingCalls>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetOutgoingCalls>)
             .add_request_handler(forward_read_only_project_request::<proto::SearchProject>)
             .add_request_handler(forward_read_only_project_request::<proto::GetDocumentHighlights>)
             .add_request_handler(forward_read_only_project_request::<proto::GetProjectSymbols>)
@@ -237,6 +250,8 @@ impl Server {
             )
             .add_request_handler(forward_mutating_project_request::<proto::GetCodeActions>)
             .add_request_handler(forward_mutating_project_request::<proto::ApplyCodeAction>)
+            .add_request_handler(forward_read_only_project_request::<proto::GetCodeLenses>)
+            .add_request_handler(forward_mutating_project_request::<proto::ExecuteCodeLens>)
             .add_request_handler(forward_mutating_project_request::<proto::PrepareRename>)
             .add_request_handler(forward_mutating_project_request::<proto::PerformRename>)
             .add_request_handler(forward_mutating_project_request::<proto::ReloadBuffers>)
@@ -1411,6 +1411,8 @@ async fn test_mutual_editor_inlay_hint_cache_update(
                     show_type_hints: true,
                     show_parameter_hints: false,
                     show_other_hints: true,
+                    edit_debounce_ms: 0,
+                    scroll_debounce_ms: 0,
                 })
             });
         });
@@ -1423,6 +1425,8 @@ async fn test_mutual_editor_inlay_hint_cache_update(
                     show_type_hints: true,
                     show_parameter_hints: false,
                     show_other_hints: true,
+                    edit_debounce_ms: 0,
+                    scroll_debounce_ms: 0,
                 })
             });
         });
@@ -1677,6 +1681,8 @@ async fn test_inlay_hint_refresh_is_forwarded(
                     show_type_hints: false,
                     show_parameter_hints: false,
                     show_other_hints: false,
+                    edit_debounce_ms: 0,
+                    scroll_debounce_ms: 0,
                 })
             });
         });
@@ -1689,6 +1695,8 @@ async fn test_inlay_hint_refresh_is_forwarded(
                     show_type_hints: true,
                     show_parameter_hints: true,
                     show_other_hints: true,
+                    edit_debounce_ms: 0,
+                    scroll_debounce_ms: 0,
                 })
             });
         });
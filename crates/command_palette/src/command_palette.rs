@@ -1,3 +1,5 @@
+mod persistence;
+
 use std::{
     cmp::{self, Reverse},
     sync::Arc,
@@ -10,6 +12,7 @@ use gpui::{
     actions, Action, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView,
     ParentElement, Render, Styled, View, ViewContext, VisualContext, WeakView,
 };
+use persistence::COMMAND_PALETTE_DB;
 use picker::{Picker, PickerDelegate};
 
 use ui::{h_flex, prelude::*, v_flex, HighlightedLabel, KeyBinding, ListItem, ListItemSpacing};
@@ -17,11 +20,15 @@ use util::{
     channel::{parse_zed_link, ReleaseChannel, RELEASE_CHANNEL},
     ResultExt,
 };
-use workspace::{ModalView, Workspace};
+use workspace::{ModalView, Workspace, WorkspaceId};
 use zed_actions::OpenZedURL;
 
 actions!(command_palette, [Toggle]);
 
+/// The most commands whose invocations are remembered for each workspace, which are
+/// suggested before the others until a query is typed.
+const RECENT_COMMANDS_LIMIT: usize = 10;
+
 pub fn init(cx: &mut AppContext) {
     cx.set_global(HitCounts::default());
     cx.set_global(CommandPaletteFilter::default());
@@ -41,8 +48,9 @@ impl CommandPalette {
                 return;
             };
             let telemetry = workspace.client().telemetry().clone();
+            let workspace_id = workspace.database_id();
             workspace.toggle_modal(cx, move |cx| {
-                CommandPalette::new(previous_focus_handle, telemetry, cx)
+                CommandPalette::new(previous_focus_handle, telemetry, workspace_id, cx)
             });
         });
     }
@@ -50,6 +58,7 @@ impl CommandPalette {
     fn new(
         previous_focus_handle: FocusHandle,
         telemetry: Arc<Telemetry>,
+        workspace_id: WorkspaceId,
         cx: &mut ViewContext<Self>,
     ) -> Self {
         let filter = cx.try_global::<CommandPaletteFilter>();
@@ -79,6 +88,7 @@ impl CommandPalette {
             commands,
             telemetry,
             previous_focus_handle,
+            workspace_id,
         );
 
         let picker = cx.new_view(|cx| Picker::new(delegate, cx));
@@ -117,6 +127,10 @@ pub struct CommandPaletteDelegate {
     selected_ix: usize,
     telemetry: Arc<Telemetry>,
     previous_focus_handle: FocusHandle,
+    workspace_id: WorkspaceId,
+    /// The names of the actions last invoked from the palette in the workspace, the most
+    /// recent first.
+    recent_commands: Vec<String>,
 }
 
 struct Command {
@@ -145,7 +159,12 @@ impl CommandPaletteDelegate {
         commands: Vec<Command>,
         telemetry: Arc<Telemetry>,
         previous_focus_handle: FocusHandle,
+        workspace_id: WorkspaceId,
     ) -> Self {
+        let recent_commands = COMMAND_PALETTE_DB
+            .get_recent_commands(workspace_id)
+            .log_err()
+            .unwrap_or_default();
         Self {
             command_palette,
             all_commands: commands.clone(),
@@ -154,6 +173,8 @@ impl CommandPaletteDelegate {
             selected_ix: 0,
             telemetry,
             previous_focus_handle,
+            workspace_id,
+            recent_commands,
         }
     }
 }
@@ -183,11 +204,20 @@ impl PickerDelegate for CommandPaletteDelegate {
         cx: &mut ViewContext<Picker<Self>>,
     ) -> gpui::Task<()> {
         let mut commands = self.all_commands.clone();
+        let recent_commands = self.recent_commands.clone();
 
         cx.spawn(move |picker, mut cx| async move {
             cx.read_global::<HitCounts, _>(|hit_counts, _| {
                 commands.sort_by_key(|action| {
+                    // Until a query is typed, the commands recently invoked in the workspace
+                    // are suggested first
+                    let recent_ix = recent_commands
+                        .iter()
+                        .position(|name| *name == action.action.name())
+                        .filter(|_| query.is_empty())
+                        .unwrap_or(usize::MAX);
                     (
+                        recent_ix,
                         Reverse(hit_counts.0.get(&action.name).cloned()),
                         action.name.clone(),
                     )
@@ -302,6 +332,16 @@ impl PickerDelegate for CommandPaletteDelegate {
 
         self.telemetry
             .report_action_event("command palette", command.name.clone());
+        let workspace_id = self.workspace_id;
+        let action_name = command.action.name().to_string();
+        cx.background_executor()
+            .spawn(async move {
+                COMMAND_PALETTE_DB
+                    .save_command_invocation(workspace_id, action_name, RECENT_COMMANDS_LIMIT)
+                    .await
+                    .log_err()
+            })
+            .detach();
 
         self.matches.clear();
         self.commands.clear();
@@ -390,7 +430,7 @@ mod tests {
     use language::Point;
     use project::Project;
     use settings::KeymapFile;
-    use workspace::{AppState, Workspace};
+    use workspace::{AppState, Workspace, WORKSPACE_DB};
 
     #[test]
     fn test_humanize_action_name() {
@@ -480,6 +520,74 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_recent_commands(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        // Invocations are stored for the workspace's row in the database, which test
+        // workspaces don't have
+        let workspace_id = WORKSPACE_DB.next_id().await.unwrap();
+        let (workspace, cx) = cx.add_window_view(|cx| {
+            Workspace::new(workspace_id, project.clone(), app_state.clone(), cx)
+        });
+        let editor = cx.new_view(|cx| Editor::single_line(cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.add_item(Box::new(editor.clone()), cx);
+            editor.update(cx, |editor, cx| editor.focus(cx))
+        });
+
+        for name in [
+            "editor::Backspace",
+            "editor::SelectAll",
+            "editor::Backspace",
+        ] {
+            COMMAND_PALETTE_DB
+                .save_command_invocation(workspace_id, name.to_string(), RECENT_COMMANDS_LIMIT)
+                .await
+                .unwrap();
+        }
+
+        cx.simulate_keystrokes("cmd-shift-p");
+        let palette = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_modal::<CommandPalette>(cx)
+                .unwrap()
+                .read(cx)
+                .picker
+                .clone()
+        });
+        palette.update(cx, |palette, _| {
+            let names = palette
+                .delegate
+                .matches
+                .iter()
+                .take(2)
+                .map(|m| m.string.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["editor: backspace", "editor: select all"]);
+        });
+
+        // Only the most recent invocations are kept
+        for ix in 0..RECENT_COMMANDS_LIMIT {
+            COMMAND_PALETTE_DB
+                .save_command_invocation(
+                    workspace_id,
+                    format!("test::Command{ix}"),
+                    RECENT_COMMANDS_LIMIT,
+                )
+                .await
+                .unwrap();
+        }
+        let recent_commands = COMMAND_PALETTE_DB
+            .get_recent_commands(workspace_id)
+            .unwrap();
+        assert_eq!(recent_commands.len(), RECENT_COMMANDS_LIMIT);
+        assert_eq!(
+            recent_commands[0],
+            format!("test::Command{}", RECENT_COMMANDS_LIMIT - 1)
+        );
+    }
+
     #[gpui::test]
     async fn test_go_to_line(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
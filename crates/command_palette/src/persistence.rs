@@ -0,0 +1,58 @@
+use anyhow::Result;
+use db::{define_connection, query, sqlez_macros::sql};
+use workspace::{WorkspaceDb, WorkspaceId};
+
+define_connection! {
+    pub static ref COMMAND_PALETTE_DB: CommandPaletteDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE command_invocations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                workspace_id INTEGER NOT NULL,
+                command_name TEXT NOT NULL,
+                UNIQUE(workspace_id, command_name),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+            ) STRICT;
+        )];
+}
+
+impl CommandPaletteDb {
+    // Returns the names of the commands invoked in the workspace, the most recent first
+    query! {
+        pub fn get_recent_commands(workspace_id: WorkspaceId) -> Result<Vec<String>> {
+            SELECT command_name
+            FROM command_invocations
+            WHERE workspace_id = ?
+            ORDER BY id DESC
+        }
+    }
+
+    /// Records that the command was invoked in the workspace, forgetting the least recent
+    /// commands beyond the given limit.
+    pub async fn save_command_invocation(
+        &self,
+        workspace_id: WorkspaceId,
+        command_name: String,
+        limit: usize,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_command_invocation", || {
+                conn.exec_bound(sql!(
+                    INSERT OR REPLACE INTO command_invocations (workspace_id, command_name)
+                    VALUES (?, ?)
+                ))?((workspace_id, command_name.as_str()))?;
+                conn.exec_bound(sql!(
+                    DELETE FROM command_invocations
+                    WHERE workspace_id = ?1 AND id NOT IN (
+                        SELECT id FROM command_invocations
+                        WHERE workspace_id = ?1
+                        ORDER BY id DESC
+                        LIMIT ?2
+                    )
+                ))?((workspace_id, limit))?;
+                Ok(())
+            })
+        })
+        .await
+    }
+}
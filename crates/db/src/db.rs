@@ -1,4 +1,5 @@
 pub mod kvp;
+pub mod maintenance;
 pub mod query;
 
 // Re-export
@@ -54,8 +55,7 @@ pub async fn open_db<M: Migrator + 'static>(
         return open_fallback_db().await;
     }
 
-    let release_channel_name = release_channel.dev_name();
-    let main_db_dir = db_dir.join(Path::new(&format!("0-{}", release_channel_name)));
+    let main_db_dir = main_db_dir(db_dir, release_channel);
 
     let connection = async_maybe!({
         smol::fs::create_dir_all(&main_db_dir)
@@ -78,6 +78,10 @@ pub async fn open_db<M: Migrator + 'static>(
     open_fallback_db().await
 }
 
+fn main_db_dir(db_dir: &Path, release_channel: &ReleaseChannel) -> PathBuf {
+    db_dir.join(Path::new(&format!("0-{}", release_channel.dev_name())))
+}
+
 async fn open_main_db<M: Migrator>(db_path: &PathBuf) -> Option<ThreadSafeConnection<M>> {
     log::info!("Opening main db");
     ThreadSafeConnection::<M>::builder(db_path.to_string_lossy().as_ref(), true)
@@ -194,8 +198,8 @@ where
 mod tests {
     use std::thread;
 
-    use sqlez::domain::Domain;
-    use sqlez_macros::sql;
+    use sqlez::{connection::Connection, domain::Domain};
+    use sqlez_macros::{sql, Bind, Column, StaticColumnCount};
 
     use crate::open_db;
 
@@ -336,4 +340,50 @@ mod tests {
             assert!(guard.join().is_ok());
         }
     }
+
+    #[derive(Clone, Debug, PartialEq, Bind, Column, StaticColumnCount)]
+    struct Position(i32, i32);
+
+    #[derive(Clone, Debug, PartialEq, Bind, Column)]
+    struct Item {
+        name: String,
+        position: Option<Position>,
+        value: i64,
+    }
+
+    #[test]
+    fn test_derived_bindings_round_trip() {
+        let connection = Connection::open_memory(Some("derived_bindings_round_trip"));
+        connection
+            .exec(sql!(CREATE TABLE items(name TEXT, x INTEGER, y INTEGER, value INTEGER)))
+            .unwrap()()
+        .unwrap();
+
+        let items = vec![
+            Item {
+                name: "placed".to_string(),
+                position: Some(Position(1, 2)),
+                value: 3,
+            },
+            Item {
+                name: "unplaced".to_string(),
+                position: None,
+                value: 4,
+            },
+        ];
+        let mut insert = connection
+            .exec_bound::<Item>(sql!(INSERT INTO items VALUES (?, ?, ?, ?)))
+            .unwrap();
+        for item in &items {
+            insert(item.clone()).unwrap();
+        }
+
+        assert_eq!(
+            connection
+                .select::<Item>(sql!(SELECT * FROM items ORDER BY name))
+                .unwrap()()
+            .unwrap(),
+            items
+        );
+    }
 }
@@ -0,0 +1,176 @@
+use std::{fs, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use gpui::AppContext;
+use sqlez::connection::Connection;
+use util::{channel::ReleaseChannel, ResultExt};
+
+use crate::{kvp::KEY_VALUE_STORE, main_db_dir, DB_FILE_NAME, ZED_STATELESS};
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+// Leave startup to do its own reads and writes first
+const FIRST_MAINTENANCE_DELAY: Duration = Duration::from_secs(60 * 5);
+// How long checkpointing may take before optimizing is left for the next run
+const MAINTENANCE_BUDGET: Duration = Duration::from_secs(1);
+// Vacuuming rewrites the whole file, so it's only worth it once a lot of space is free
+const VACUUM_MIN_FREE_BYTES: u64 = 16 * 1024 * 1024;
+
+const RESET_MARKER_FILE_NAME: &str = "reset";
+
+/// Runs `maintain` on the database shortly after startup, and then daily.
+pub fn init(cx: &mut AppContext) {
+    if *ZED_STATELESS {
+        return;
+    }
+
+    let executor = cx.background_executor().clone();
+    cx.background_executor()
+        .spawn(async move {
+            executor.timer(FIRST_MAINTENANCE_DELAY).await;
+            loop {
+                // Every domain's connection shares the same file, so any queue will do
+                KEY_VALUE_STORE
+                    .write(maintain)
+                    .await
+                    .context("Database maintenance failed")
+                    .log_err();
+                executor.timer(MAINTENANCE_INTERVAL).await;
+            }
+        })
+        .detach();
+}
+
+/// Keeps the database fast and small, by running the connection's `maintenance` and then
+/// vacuuming when a large part of the file is free.
+pub fn maintain(connection: &Connection) -> Result<()> {
+    let report = connection.maintenance(MAINTENANCE_BUDGET)?;
+    if report.checkpoint.is_some_and(|checkpoint| checkpoint.busy) {
+        log::info!("Database checkpoint was blocked by another connection");
+    }
+
+    let size = connection.database_size()?;
+    if size.freelist_count * 4 > size.page_count && size.free_bytes() >= VACUUM_MIN_FREE_BYTES {
+        log::info!(
+            "Vacuuming database, {} of {} bytes are free",
+            size.free_bytes(),
+            size.bytes()
+        );
+        connection.vacuum()?;
+    }
+    Ok(())
+}
+
+/// Marks the database to be deleted the next time Zed starts. Connections to it are held
+/// for as long as Zed runs, so it can't be deleted sooner.
+pub fn request_reset(db_dir: &Path, release_channel: &ReleaseChannel) -> Result<()> {
+    let main_db_dir = main_db_dir(db_dir, release_channel);
+    fs::create_dir_all(&main_db_dir).context("Could not create db directory")?;
+    fs::write(main_db_dir.join(RESET_MARKER_FILE_NAME), "")
+        .context("Could not mark the database for reset")
+}
+
+/// Deletes the database if `request_reset` was called, returning whether it was. This must
+/// run before anything opens the database.
+pub fn reset_if_requested(db_dir: &Path, release_channel: &ReleaseChannel) -> Result<bool> {
+    let main_db_dir = main_db_dir(db_dir, release_channel);
+    let marker = main_db_dir.join(RESET_MARKER_FILE_NAME);
+    if !marker.exists() {
+        return Ok(false);
+    }
+
+    log::info!("Resetting database");
+    for suffix in ["", "-wal", "-shm"] {
+        let path = main_db_dir.join(format!("{DB_FILE_NAME}{suffix}"));
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Could not delete {path:?}"))?;
+        }
+    }
+    fs::remove_file(&marker).context("Could not remove the database reset marker")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlez::{connection::Connection, domain::Domain};
+    use sqlez_macros::sql;
+    use util::channel::ReleaseChannel;
+
+    use crate::{
+        maintenance::{maintain, request_reset, reset_if_requested},
+        open_db,
+    };
+
+    #[test]
+    fn test_maintain_vacuums_mostly_free_databases() {
+        let connection = Connection::open_memory(Some("test_maintain_vacuums"));
+        connection
+            .exec(sql!(CREATE TABLE test(value BLOB);))
+            .unwrap()()
+        .unwrap();
+        let mut insert = connection
+            .exec_bound::<Vec<u8>>(sql!(INSERT INTO test(value) VALUES (?)))
+            .unwrap();
+        for _ in 0..40 {
+            insert(vec![0; 1024 * 1024]).unwrap();
+        }
+
+        // Mostly used databases are only optimized
+        maintain(&connection).unwrap();
+        let size = connection.database_size().unwrap();
+        assert_eq!(size.freelist_count, 0);
+
+        connection
+            .exec(sql!(DELETE FROM test WHERE rowid > 5;))
+            .unwrap()()
+        .unwrap();
+        assert!(connection.database_size().unwrap().freelist_count > 0);
+        maintain(&connection).unwrap();
+        let vacuumed = connection.database_size().unwrap();
+        assert_eq!(vacuumed.freelist_count, 0);
+        assert!(vacuumed.page_count < size.page_count);
+    }
+
+    #[gpui::test]
+    async fn test_reset_deletes_the_database() {
+        enum TestDB {}
+
+        impl Domain for TestDB {
+            fn name() -> &'static str {
+                "db_tests"
+            }
+
+            fn migrations() -> &'static [&'static str] {
+                &[sql!(CREATE TABLE test(value);)]
+            }
+        }
+
+        let tempdir = tempfile::Builder::new()
+            .prefix("DbTests")
+            .tempdir()
+            .unwrap();
+        let release_channel = ReleaseChannel::Dev;
+        {
+            let db = open_db::<TestDB>(tempdir.path(), &release_channel).await;
+            db.write(|connection| {
+                connection
+                    .exec(sql!(INSERT INTO test(value) VALUES (1);))
+                    .unwrap()()
+                .unwrap()
+            })
+            .await;
+        }
+        assert!(!reset_if_requested(tempdir.path(), &release_channel).unwrap());
+
+        request_reset(tempdir.path(), &release_channel).unwrap();
+        assert!(reset_if_requested(tempdir.path(), &release_channel).unwrap());
+        assert!(!reset_if_requested(tempdir.path(), &release_channel).unwrap());
+
+        let db = open_db::<TestDB>(tempdir.path(), &release_channel).await;
+        assert_eq!(
+            db.select_row::<i64>(sql!(SELECT count(*) FROM test))
+                .unwrap()()
+            .unwrap(),
+            Some(0)
+        );
+    }
+}
@@ -70,6 +70,31 @@ pub struct FoldAt {
 pub struct UnfoldAt {
     pub buffer_row: u32,
 }
+
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct ToggleBookmarkAt {
+    pub buffer_row: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct ToggleHunkDiffAt {
+    pub buffer_row: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct DeployGutterContextMenu {
+    pub buffer_row: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct FoldAtLevel {
+    pub level: u32,
+}
+
+#[derive(PartialEq, Clone, Deserialize, Default)]
+pub struct ReplaceMisspelling {
+    pub replacement: String,
+}
 impl_actions!(
     editor,
     [
@@ -83,7 +108,12 @@ impl_actions!(
         ConfirmCompletion,
         ConfirmCodeAction,
         ToggleComments,
+        DeployGutterContextMenu,
         FoldAt,
+        FoldAtLevel,
+        ReplaceMisspelling,
+        ToggleBookmarkAt,
+        ToggleHunkDiffAt,
         UnfoldAt
     ]
 );
@@ -91,8 +121,10 @@ impl_actions!(
 gpui::actions!(
     editor,
     [
+        AddImport,
         AddSelectionAbove,
         AddSelectionBelow,
+        AddWordToDictionary,
         Backspace,
         Cancel,
         ConfirmRename,
@@ -108,7 +140,9 @@ gpui::actions!(
         ConvertToUpperCamelCase,
         ConvertToUpperCase,
         Copy,
+        CopyBreadcrumbs,
         CopyHighlightJson,
+        CopyPermalinkToLine,
         CopyPath,
         CopyRelativePath,
         Cut,
@@ -121,19 +155,27 @@ gpui::actions!(
         DeleteToNextWordEnd,
         DeleteToPreviousSubwordStart,
         DeleteToPreviousWordStart,
+        DeployCursorContextMenu,
         DisplayCursorNames,
         DuplicateLine,
         ExpandMacroRecursively,
         FindAllReferences,
         Fold,
+        FoldRecursive,
         FoldSelectedRanges,
         Format,
+        FormatSelection,
+        GenerateDocComment,
+        GoToDeclaration,
         GoToDefinition,
         GoToDefinitionSplit,
         GoToDiagnostic,
         GoToHunk,
+        GoToImplementation,
+        GoToNextHighlight,
         GoToPrevDiagnostic,
         GoToPrevHunk,
+        GoToPreviousHighlight,
         GoToTypeDefinition,
         GoToTypeDefinitionSplit,
         HalfPageDown,
@@ -165,16 +207,24 @@ gpui::actions!(
         NewlineBelow,
         NextScreen,
         OpenExcerpts,
+        OpenInTerminal,
+        OpenPeekedDefinitionInSplit,
+        OrganizeImports,
         Outdent,
         PageDown,
         PageUp,
         Paste,
+        PasteFromHistory,
+        PeekDefinition,
+        PeekNextDefinition,
+        PeekPreviousDefinition,
         Redo,
         RedoSelection,
         Rename,
         RestartLanguageServer,
         RevealInFinder,
         ReverseLines,
+        RunTestAtCursor,
         ScrollCursorBottom,
         ScrollCursorCenter,
         ScrollCursorTop,
@@ -187,6 +237,7 @@ gpui::actions!(
         SelectRight,
         SelectSmallerSyntaxNode,
         SelectToBeginning,
+        SelectToEnclosingBracket,
         SelectToEnd,
         SelectToEndOfParagraph,
         SelectToNextSubwordEnd,
@@ -195,19 +246,27 @@ gpui::actions!(
         SelectToPreviousWordStart,
         SelectToStartOfParagraph,
         SelectUp,
+        ShareSelectionAsSnippet,
+        ShowBlamedCommit,
+        ShowCallHierarchy,
         ShowCharacterPalette,
         ShowCompletions,
+        ShowSignatureHelp,
         ShuffleLines,
+        SignatureHelpNext,
+        SignatureHelpPrevious,
         SortLinesCaseInsensitive,
         SortLinesCaseSensitive,
         SplitSelectionIntoLines,
         Tab,
         TabPrev,
+        ToggleGitBlameInline,
         ToggleInlayHints,
         ToggleSoftWrap,
         Transpose,
         Undo,
         UndoSelection,
+        UnfoldAll,
         UnfoldLines,
     ]
 );
@@ -0,0 +1,252 @@
+use crate::{CopyPermalinkToLine, Editor, EditorMode, ShowBlamedCommit, ToggleGitBlameInline};
+use chrono::{Local, TimeZone};
+use git::{blame::BlameEntry, permalink::ParsedGitRemote};
+use gpui::{AppContext, ClipboardItem, Task, ViewContext};
+use language::{Point, ToPoint};
+use project::project_settings::ProjectSettings;
+use settings::Settings;
+use std::{ops::Range, time::Duration};
+use text::Anchor;
+use ui::utils::{format_distance_from_now, DateTimeType};
+
+/// How long to wait after an edit before blaming the buffer again.
+const BLAME_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The blame of the editor's buffer, which is shown inline for the newest cursor's line.
+#[derive(Default)]
+pub(crate) struct BlameState {
+    blame: Option<BufferBlame>,
+    /// Whether the inline blame was toggled on or off, overriding the `git.inline_blame`
+    /// setting.
+    show_inline: Option<bool>,
+    /// The `git.inline_blame` setting that the buffer was last blamed with, so that it's
+    /// only blamed again when the setting changes rather than on every settings change.
+    inline_blame_setting: Option<bool>,
+    refresh_task: Option<Task<()>>,
+}
+
+struct BufferBlame {
+    remote_url: Option<String>,
+    /// The blamed lines, anchored in the buffer as of the time it was blamed, so that they
+    /// follow later edits until the buffer is blamed again.
+    entries: Vec<(Range<Anchor>, BlameEntry)>,
+}
+
+pub fn toggle_git_blame_inline(
+    editor: &mut Editor,
+    _: &ToggleGitBlameInline,
+    cx: &mut ViewContext<Editor>,
+) {
+    editor.blame.show_inline = Some(!inline_blame_enabled(editor, cx));
+    refresh_blame(editor, false, cx);
+    cx.notify();
+}
+
+pub fn copy_permalink_to_line(
+    editor: &mut Editor,
+    _: &CopyPermalinkToLine,
+    cx: &mut ViewContext<Editor>,
+) {
+    if let Some(permalink) = permalink_at_cursor(editor, cx) {
+        cx.write_to_clipboard(ClipboardItem::new(permalink));
+    }
+}
+
+pub fn show_blamed_commit(editor: &mut Editor, _: &ShowBlamedCommit, cx: &mut ViewContext<Editor>) {
+    if let Some(url) = commit_url_at_cursor(editor, cx) {
+        cx.open_url(&url);
+    }
+}
+
+/// Whether the line of the newest cursor was blamed on a commit of a repository on a known
+/// hosting provider, which permalinks can be built for.
+pub(crate) fn has_permalink_at_cursor(editor: &Editor, cx: &AppContext) -> bool {
+    commit_url_at_cursor(editor, cx).is_some()
+}
+
+fn inline_blame_setting(cx: &AppContext) -> bool {
+    ProjectSettings::get_global(cx)
+        .git
+        .inline_blame
+        .map_or(false, |settings| settings.enabled)
+}
+
+fn inline_blame_enabled(editor: &Editor, cx: &AppContext) -> bool {
+    editor
+        .blame
+        .show_inline
+        .unwrap_or_else(|| inline_blame_setting(cx))
+}
+
+/// Blames the editor's buffer again if the settings changed whether the inline blame is
+/// shown.
+pub(crate) fn blame_settings_changed(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    if editor.blame.inline_blame_setting != Some(inline_blame_setting(cx)) {
+        refresh_blame(editor, false, cx);
+    }
+}
+
+/// Blames the editor's buffer again, unless the inline blame is turned off.
+pub(crate) fn refresh_blame(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    editor.blame.inline_blame_setting = Some(inline_blame_setting(cx));
+    if editor.mode != EditorMode::Full || !inline_blame_enabled(editor, cx) {
+        editor.blame.refresh_task = None;
+        if editor.blame.blame.take().is_some() {
+            cx.notify();
+        }
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+    if !project.read(cx).is_local() {
+        return None;
+    }
+
+    editor.blame.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        if debounce {
+            cx.background_executor().timer(BLAME_DEBOUNCE).await;
+        }
+
+        let Ok((snapshot, blame)) = project.update(&mut cx, |project, cx| {
+            let snapshot = buffer.read(cx).text_snapshot();
+            (snapshot, project.blame_buffer(&buffer, cx))
+        }) else {
+            return;
+        };
+        let blame = match blame.await {
+            Ok(blame) => Some(blame),
+            // Files outside of repositories can't be blamed, which isn't worth reporting
+            Err(error) => {
+                log::debug!("failed to blame buffer: {error:#}");
+                None
+            }
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                editor.blame.blame = blame.map(|blame| BufferBlame {
+                    remote_url: blame.remote_url,
+                    entries: blame
+                        .entries
+                        .into_iter()
+                        .map(|entry| {
+                            let start = snapshot.anchor_before(Point::new(entry.range.start, 0));
+                            let end = snapshot.anchor_before(Point::new(entry.range.end, 0));
+                            (start..end, entry)
+                        })
+                        .collect(),
+                });
+                cx.notify();
+            })
+            .ok();
+    }));
+    None
+}
+
+/// The text shown at the end of the newest cursor's line, such as
+/// `Jane Doe, 3 days ago • Fix the parser`.
+pub(crate) fn inline_blame_text(editor: &Editor, cx: &AppContext) -> Option<String> {
+    if !inline_blame_enabled(editor, cx) {
+        return None;
+    }
+    let (entry, _) = blame_entry_at_cursor(editor, cx)?;
+    if entry.is_uncommitted() {
+        return Some("You, Uncommitted changes".to_string());
+    }
+
+    let mut text = entry.author.clone().unwrap_or_default();
+    if let Some(time) = entry
+        .author_time
+        .and_then(|time| Local.timestamp_opt(time, 0).single())
+    {
+        text.push_str(", ");
+        text.push_str(&format_distance_from_now(
+            DateTimeType::Local(time),
+            false,
+            true,
+            true,
+        ));
+    }
+    if let Some(summary) = &entry.summary {
+        text.push_str(" • ");
+        text.push_str(summary);
+    }
+    Some(text)
+}
+
+/// The blame of the newest cursor's line, along with the line's row as of the commit.
+fn blame_entry_at_cursor<'a>(editor: &'a Editor, cx: &AppContext) -> Option<(&'a BlameEntry, u32)> {
+    let blame = editor.blame.blame.as_ref()?;
+    let head = editor.selections.newest_anchor().head();
+    let (buffer, position) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+    let snapshot = buffer.read(cx).snapshot();
+    let row = position.to_point(&snapshot).row;
+
+    let ix = blame
+        .entries
+        .partition_point(|(range, _)| range.start.to_point(&snapshot).row <= row);
+    let (range, entry) = blame.entries.get(ix.checked_sub(1)?)?;
+    // The last line's range ends on that line when it doesn't end with a newline
+    let end = range.end.to_point(&snapshot);
+    if row >= end.row && end != snapshot.max_point() {
+        return None;
+    }
+    let start_row = range.start.to_point(&snapshot).row;
+    Some((entry, entry.original_row + (row - start_row)))
+}
+
+fn permalink_at_cursor(editor: &Editor, cx: &AppContext) -> Option<String> {
+    let (entry, original_row) = blame_entry_at_cursor(editor, cx)?;
+    if entry.is_uncommitted() {
+        return None;
+    }
+    let remote = ParsedGitRemote::parse(editor.blame.blame.as_ref()?.remote_url.as_deref()?)?;
+    Some(remote.permalink(&entry.sha.to_string(), &entry.filename, original_row))
+}
+
+fn commit_url_at_cursor(editor: &Editor, cx: &AppContext) -> Option<String> {
+    let (entry, _) = blame_entry_at_cursor(editor, cx)?;
+    if entry.is_uncommitted() {
+        return None;
+    }
+    let remote = ParsedGitRemote::parse(editor.blame.blame.as_ref()?.remote_url.as_deref()?)?;
+    Some(remote.commit_url(&entry.sha.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::{init_test, update_test_language_settings, update_test_project_settings},
+        test::editor_test_context::EditorTestContext,
+    };
+    use project::project_settings::InlineBlameSettings;
+
+    #[gpui::test]
+    async fn test_blame_refreshes_only_when_its_setting_changes(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state("one\ntwˇo\n");
+        let is_refreshing = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, _| editor.blame.refresh_task.is_some())
+        };
+
+        // The inline blame is off by default
+        assert!(!is_refreshing(&mut cx));
+
+        update_test_project_settings(&mut cx.cx.cx, |settings| {
+            settings.git.inline_blame = Some(InlineBlameSettings { enabled: true });
+        });
+        assert!(is_refreshing(&mut cx));
+        cx.update_editor(|editor, _| editor.blame.refresh_task = None);
+
+        update_test_language_settings(&mut cx.cx.cx, |settings| {
+            settings.defaults.tab_size = std::num::NonZeroU32::new(8);
+        });
+        assert!(!is_refreshing(&mut cx));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    display_map::{DisplaySnapshot, ToDisplayPoint},
+    Anchor, Editor, ToggleBookmarkAt,
+};
+use gpui::ViewContext;
+use language::{Bias, Point};
+use multi_buffer::ToPoint as _;
+use std::ops::Range;
+
+/// The lines that were bookmarked from the gutter, which are marked in it until the
+/// bookmark is toggled off again.
+#[derive(Default)]
+pub(crate) struct Bookmarks {
+    /// Anchored at the start of each bookmarked line, so that bookmarks follow their lines
+    /// as the buffer is edited.
+    anchors: Vec<Anchor>,
+}
+
+pub fn toggle_bookmark_at(
+    editor: &mut Editor,
+    action: &ToggleBookmarkAt,
+    cx: &mut ViewContext<Editor>,
+) {
+    let buffer = editor.buffer.read(cx).snapshot(cx);
+    let row = action.buffer_row.min(buffer.max_point().row);
+    let bookmarks = &mut editor.bookmarks.anchors;
+    let len = bookmarks.len();
+    bookmarks.retain(|anchor| anchor.to_point(&buffer).row != row);
+    if bookmarks.len() == len {
+        bookmarks.push(buffer.anchor_before(buffer.clip_point(Point::new(row, 0), Bias::Left)));
+    }
+    cx.notify();
+}
+
+/// The display rows within `rows` whose lines are bookmarked, in order.
+pub(crate) fn bookmarked_display_rows(
+    editor: &Editor,
+    rows: Range<u32>,
+    snapshot: &DisplaySnapshot,
+) -> Vec<u32> {
+    let mut bookmarked_rows = editor
+        .bookmarks
+        .anchors
+        .iter()
+        .map(|anchor| anchor.to_display_point(snapshot).row())
+        .filter(|row| rows.contains(row))
+        .collect::<Vec<_>>();
+    bookmarked_rows.sort_unstable();
+    bookmarked_rows.dedup();
+    bookmarked_rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext, Newline};
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_toggle_bookmark(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            oneˇ
+            two
+            three
+        "});
+        let bookmarked_rows = |cx: &mut EditorTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.snapshot(cx);
+                bookmarked_display_rows(editor, 0..u32::MAX, &snapshot.display_snapshot)
+            })
+        };
+
+        cx.update_editor(|editor, cx| {
+            toggle_bookmark_at(editor, &ToggleBookmarkAt { buffer_row: 2 }, cx);
+            toggle_bookmark_at(editor, &ToggleBookmarkAt { buffer_row: 1 }, cx);
+        });
+        assert_eq!(bookmarked_rows(&mut cx), [1, 2]);
+
+        // Bookmarks follow their lines as lines are inserted above them
+        cx.update_editor(|editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([0..0]));
+            editor.newline(&Newline, cx);
+        });
+        assert_eq!(bookmarked_rows(&mut cx), [2, 3]);
+
+        cx.update_editor(|editor, cx| {
+            toggle_bookmark_at(editor, &ToggleBookmarkAt { buffer_row: 2 }, cx)
+        });
+        assert_eq!(bookmarked_rows(&mut cx), [3]);
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{scroll::Autoscroll, Editor};
+use anyhow::Result;
+use gpui::{
+    actions, px, AppContext, EventEmitter, FocusHandle, FocusableView, Model, ScrollHandle, View,
+    WeakView,
+};
+use language::{OffsetRangeExt, ToPoint};
+use project::{CallHierarchyCall, CallHierarchyItem, Location, Project};
+use ui::{prelude::*, ListItem};
+use util::post_inc;
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    Workspace,
+};
+
+actions!(call_hierarchy_panel, [ToggleFocus]);
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(|workspace, _: &ToggleFocus, cx| {
+            workspace.toggle_panel_focus::<CallHierarchyPanel>(cx);
+        });
+    })
+    .detach();
+}
+
+/// Whether the tree lists the callers of each item, or the items it calls.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum CallDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// A tree of the callers or callees of the symbols that Show Call Hierarchy was invoked on,
+/// which is expanded a level at a time.
+pub struct CallHierarchyPanel {
+    project: Model<Project>,
+    workspace: WeakView<Workspace>,
+    roots: Vec<CallHierarchyItem>,
+    direction: CallDirection,
+    /// The tree's visible entries, each followed by its expanded descendants.
+    entries: Vec<CallHierarchyEntry>,
+    next_entry_id: usize,
+    selected_ix: Option<usize>,
+    position: DockPosition,
+    width: Option<Pixels>,
+    height: Option<Pixels>,
+    focus_handle: FocusHandle,
+    scroll_handle: ScrollHandle,
+}
+
+struct CallHierarchyEntry {
+    id: usize,
+    item: CallHierarchyItem,
+    /// Where this entry's item calls its parent's, or is called by it. Empty for the roots.
+    call_sites: Vec<Location>,
+    depth: usize,
+    expansion: Expansion,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Expansion {
+    Collapsed,
+    Loading,
+    Expanded,
+}
+
+impl CallHierarchyPanel {
+    pub fn new(workspace: &Workspace, cx: &mut ViewContext<Self>) -> Self {
+        Self {
+            project: workspace.project().clone(),
+            workspace: workspace.weak_handle(),
+            roots: Vec::new(),
+            direction: CallDirection::Incoming,
+            entries: Vec::new(),
+            next_entry_id: 0,
+            selected_ix: None,
+            position: DockPosition::Left,
+            width: None,
+            height: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: ScrollHandle::new(),
+        }
+    }
+
+    /// Shows the calls of the given items, replacing the current tree.
+    pub fn set_roots(&mut self, roots: Vec<CallHierarchyItem>, cx: &mut ViewContext<Self>) {
+        self.roots = roots;
+        self.rebuild(cx);
+    }
+
+    fn set_direction(&mut self, direction: CallDirection, cx: &mut ViewContext<Self>) {
+        if self.direction != direction {
+            self.direction = direction;
+            self.rebuild(cx);
+        }
+    }
+
+    fn rebuild(&mut self, cx: &mut ViewContext<Self>) {
+        self.entries.clear();
+        for item in self.roots.clone() {
+            let id = post_inc(&mut self.next_entry_id);
+            self.entries.push(CallHierarchyEntry {
+                id,
+                item,
+                call_sites: Vec::new(),
+                depth: 0,
+                expansion: Expansion::Collapsed,
+            });
+        }
+        self.selected_ix = (!self.entries.is_empty()).then_some(0);
+        for ix in (0..self.entries.len()).rev() {
+            self.expand(ix, cx);
+        }
+        cx.notify();
+    }
+
+    fn expand(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let entry = &mut self.entries[ix];
+        if entry.expansion != Expansion::Collapsed {
+            return;
+        }
+        entry.expansion = Expansion::Loading;
+        let entry_id = entry.id;
+        let calls = self.project.update(cx, |project, cx| match self.direction {
+            CallDirection::Incoming => project.incoming_calls(&entry.item, cx),
+            CallDirection::Outgoing => project.outgoing_calls(&entry.item, cx),
+        });
+        cx.spawn(|this, mut cx| async move {
+            let calls = calls.await;
+            this.update(&mut cx, |this, cx| this.insert_calls(entry_id, calls, cx))?
+        })
+        .detach_and_log_err(cx);
+        cx.notify();
+    }
+
+    fn insert_calls(
+        &mut self,
+        entry_id: usize,
+        calls: Result<Vec<CallHierarchyCall>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Result<()> {
+        // The entry may have been collapsed, or the tree rebuilt, while the calls were loading
+        let Some(ix) = self.entries.iter().position(|entry| entry.id == entry_id) else {
+            return Ok(());
+        };
+        let parent = &mut self.entries[ix];
+        if parent.expansion != Expansion::Loading {
+            return Ok(());
+        }
+        let calls = match calls {
+            Ok(calls) => calls,
+            Err(error) => {
+                parent.expansion = Expansion::Collapsed;
+                cx.notify();
+                return Err(error);
+            }
+        };
+        parent.expansion = Expansion::Expanded;
+        let depth = parent.depth + 1;
+        let children = calls
+            .into_iter()
+            .map(|call| CallHierarchyEntry {
+                id: post_inc(&mut self.next_entry_id),
+                item: call.item,
+                call_sites: call.call_sites,
+                depth,
+                expansion: Expansion::Collapsed,
+            })
+            .collect::<Vec<_>>();
+        if let Some(selected_ix) = self.selected_ix.as_mut() {
+            if *selected_ix > ix {
+                *selected_ix += children.len();
+            }
+        }
+        self.entries.splice(ix + 1..ix + 1, children);
+        cx.notify();
+        Ok(())
+    }
+
+    fn collapse(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let depth = self.entries[ix].depth;
+        let end = self.entries[ix + 1..]
+            .iter()
+            .position(|entry| entry.depth <= depth)
+            .map_or(self.entries.len(), |len| ix + 1 + len);
+        self.entries.drain(ix + 1..end);
+        self.entries[ix].expansion = Expansion::Collapsed;
+        if let Some(selected_ix) = self.selected_ix.as_mut() {
+            if *selected_ix >= end {
+                *selected_ix -= end - ix - 1;
+            } else if *selected_ix > ix {
+                *selected_ix = ix;
+            }
+        }
+        cx.notify();
+    }
+
+    fn toggle_expanded(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        if self.entries[ix].expansion == Expansion::Collapsed {
+            self.expand(ix, cx);
+        } else {
+            self.collapse(ix, cx);
+        }
+    }
+
+    fn parent_ix(&self, ix: usize) -> Option<usize> {
+        let depth = self.entries[ix].depth;
+        self.entries[..ix]
+            .iter()
+            .rposition(|entry| entry.depth < depth)
+    }
+
+    fn select(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.selected_ix = Some(ix);
+        self.scroll_handle.scroll_to_item(ix);
+        cx.notify();
+    }
+
+    fn select_next(&mut self, _: &menu::SelectNext, cx: &mut ViewContext<Self>) {
+        if !self.entries.is_empty() {
+            let ix = self
+                .selected_ix
+                .map_or(0, |ix| (ix + 1) % self.entries.len());
+            self.select(ix, cx);
+        }
+    }
+
+    fn select_prev(&mut self, _: &menu::SelectPrev, cx: &mut ViewContext<Self>) {
+        let count = self.entries.len();
+        if count > 0 {
+            let ix = self
+                .selected_ix
+                .map_or(count - 1, |ix| (ix + count - 1) % count);
+            self.select(ix, cx);
+        }
+    }
+
+    /// Expands the selected entry, or selects its first child if it's already expanded.
+    fn select_child(&mut self, _: &menu::SelectChild, cx: &mut ViewContext<Self>) {
+        let Some(ix) = self.selected_ix else {
+            return;
+        };
+        match self.entries[ix].expansion {
+            Expansion::Collapsed => self.expand(ix, cx),
+            Expansion::Loading => {}
+            Expansion::Expanded => {
+                if self
+                    .entries
+                    .get(ix + 1)
+                    .map_or(false, |entry| entry.depth > self.entries[ix].depth)
+                {
+                    self.select(ix + 1, cx);
+                }
+            }
+        }
+    }
+
+    /// Collapses the selected entry, or selects its parent if it's already collapsed.
+    fn select_parent(&mut self, _: &menu::SelectParent, cx: &mut ViewContext<Self>) {
+        let Some(ix) = self.selected_ix else {
+            return;
+        };
+        if self.entries[ix].expansion == Expansion::Collapsed {
+            if let Some(parent_ix) = self.parent_ix(ix) {
+                self.select(parent_ix, cx);
+            }
+        } else {
+            self.collapse(ix, cx);
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        if let Some(ix) = self.selected_ix {
+            let entry = &self.entries[ix];
+            let location = entry
+                .call_sites
+                .first()
+                .unwrap_or(&entry.item.location)
+                .clone();
+            self.open_location(location, cx);
+        }
+    }
+
+    fn open_location(&mut self, location: Location, cx: &mut ViewContext<Self>) {
+        let range = location.range.to_offset(location.buffer.read(cx));
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let editor: View<Editor> = workspace.open_project_item(location.buffer, cx);
+                editor.update(cx, |editor, cx| {
+                    let range = editor.range_for_match(&range);
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([range])
+                    });
+                });
+            })
+            .ok();
+    }
+
+    fn render_entry(
+        &self,
+        ix: usize,
+        entry: &CallHierarchyEntry,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let call_sites = entry
+            .call_sites
+            .iter()
+            .enumerate()
+            .map(|(call_site_ix, location)| {
+                let row = location.range.start.to_point(location.buffer.read(cx)).row;
+                let location = location.clone();
+                Button::new(("call-site", call_site_ix), format!("{}", row + 1))
+                    .label_size(LabelSize::Small)
+                    .color(Color::Muted)
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.select(ix, cx);
+                        this.open_location(location.clone(), cx);
+                    }))
+            })
+            .collect::<Vec<_>>();
+
+        ListItem::new(("call-hierarchy-entry", entry.id))
+            .indent_level(entry.depth)
+            .indent_step_size(px(16.))
+            .selected(self.selected_ix == Some(ix))
+            .toggle(Some(entry.expansion != Expansion::Collapsed))
+            .on_toggle(cx.listener(move |this, _, cx| this.toggle_expanded(ix, cx)))
+            .on_click(cx.listener(move |this, _, cx| {
+                this.select(ix, cx);
+                this.confirm(&menu::Confirm, cx);
+            }))
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Label::new(entry.item.name.clone()))
+                    .children(entry.item.detail.clone().map(|detail| {
+                        Label::new(detail)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                    }))
+                    .when(entry.expansion == Expansion::Loading, |this| {
+                        this.child(
+                            Label::new("Loading…")
+                                .size(LabelSize::Small)
+                                .color(Color::Muted),
+                        )
+                    }),
+            )
+            .end_slot(h_flex().children(call_sites))
+    }
+}
+
+impl EventEmitter<PanelEvent> for CallHierarchyPanel {}
+
+impl FocusableView for CallHierarchyPanel {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Panel for CallHierarchyPanel {
+    fn persistent_name() -> &'static str {
+        "CallHierarchyPanel"
+    }
+
+    fn position(&self, _: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, _: DockPosition) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _: &WindowContext) -> Pixels {
+        match self.position {
+            DockPosition::Left | DockPosition::Right => self.width.unwrap_or(px(320.)),
+            DockPosition::Bottom => self.height.unwrap_or(px(240.)),
+        }
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        match self.position {
+            DockPosition::Left | DockPosition::Right => self.width = size,
+            DockPosition::Bottom => self.height = size,
+        }
+        cx.notify();
+    }
+
+    fn icon(&self, _: &WindowContext) -> Option<IconName> {
+        Some(IconName::FileTree)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("Call Hierarchy")
+    }
+
+    fn toggle_action(&self) -> Box<dyn gpui::Action> {
+        Box::new(ToggleFocus)
+    }
+}
+
+impl Render for CallHierarchyPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let entries = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(ix, entry)| self.render_entry(ix, entry, cx).into_any_element())
+            .collect::<Vec<_>>();
+        let direction = self.direction;
+
+        v_flex()
+            .key_context("CallHierarchyPanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::select_child))
+            .on_action(cx.listener(Self::select_parent))
+            .on_action(cx.listener(Self::confirm))
+            .size_full()
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .gap_1()
+                    .child(
+                        Button::new("incoming-calls", "Callers")
+                            .selected(direction == CallDirection::Incoming)
+                            .on_click(cx.listener(|this, _, cx| {
+                                this.set_direction(CallDirection::Incoming, cx)
+                            })),
+                    )
+                    .child(
+                        Button::new("outgoing-calls", "Callees")
+                            .selected(direction == CallDirection::Outgoing)
+                            .on_click(cx.listener(|this, _, cx| {
+                                this.set_direction(CallDirection::Outgoing, cx)
+                            })),
+                    ),
+            )
+            .child(
+                v_flex()
+                    .id("call-hierarchy-entries")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .children(entries),
+            )
+    }
+}
+
+/// Shows the call hierarchy of the given items in the workspace's call hierarchy panel,
+/// adding the panel if it isn't there yet.
+pub(crate) fn show_call_hierarchy(
+    workspace: &mut Workspace,
+    items: Vec<CallHierarchyItem>,
+    cx: &mut ViewContext<Workspace>,
+) -> View<CallHierarchyPanel> {
+    let panel = workspace
+        .panel::<CallHierarchyPanel>(cx)
+        .unwrap_or_else(|| {
+            let panel = cx.new_view(|cx| CallHierarchyPanel::new(workspace, cx));
+            workspace.add_panel(panel.clone(), cx);
+            panel
+        });
+    panel.update(cx, |panel, cx| panel.set_roots(items, cx));
+    workspace.focus_panel::<CallHierarchyPanel>(cx);
+    panel
+}
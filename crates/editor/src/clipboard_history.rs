@@ -0,0 +1,211 @@
+use crate::{persistence::DB, ClipboardSelection, Editor, EditorMode, EditorSettings, Paste};
+use gpui::{AppContext, ClipboardItem, ViewContext};
+use settings::Settings;
+use std::{collections::VecDeque, sync::Arc};
+use util::ResultExt;
+
+/// The most entries that are kept in the clipboard history.
+const CLIPBOARD_HISTORY_LIMIT: usize = 20;
+
+/// Text that was copied or cut from an editor, which can be pasted again from the
+/// clipboard history.
+#[derive(Clone)]
+pub struct ClipboardHistoryEntry {
+    pub text: String,
+    /// The selections that the text was copied from, which are used to re-indent it when
+    /// it's pasted.
+    pub selections: Vec<ClipboardSelection>,
+    /// The name of the language of the buffer that the text was copied from.
+    pub language: Option<Arc<str>>,
+}
+
+/// The text copied or cut from editors, the most recent first, which is loaded from the
+/// database the first time it's needed, unless `persist_clipboard_history` is off.
+#[derive(Default)]
+struct ClipboardHistory {
+    entries: Option<VecDeque<ClipboardHistoryEntry>>,
+}
+
+/// Returns the entries of the clipboard history, the most recent first.
+pub fn clipboard_history(cx: &mut AppContext) -> Vec<ClipboardHistoryEntry> {
+    entries(cx).iter().cloned().collect()
+}
+
+/// Adds the text that was just written to the clipboard from the editor to the clipboard
+/// history. Text copied from single line editors, such as search queries, isn't added.
+pub(crate) fn record_clipboard_entry(
+    editor: &Editor,
+    text: String,
+    selections: Vec<ClipboardSelection>,
+    cx: &mut AppContext,
+) {
+    if text.is_empty() || editor.mode != EditorMode::Full {
+        return;
+    }
+    let language = editor
+        .buffer
+        .read(cx)
+        .as_singleton()
+        .and_then(|buffer| buffer.read(cx).language())
+        .map(|language| language.name());
+    add_entry(
+        ClipboardHistoryEntry {
+            text,
+            selections,
+            language,
+        },
+        cx,
+    );
+}
+
+impl Editor {
+    /// Pastes a previous entry of the clipboard history, which becomes the clipboard's
+    /// contents and the most recent entry again.
+    pub fn paste_from_history(&mut self, entry: ClipboardHistoryEntry, cx: &mut ViewContext<Self>) {
+        cx.write_to_clipboard(
+            ClipboardItem::new(entry.text.clone()).with_metadata(entry.selections.clone()),
+        );
+        add_entry(entry, cx);
+        self.paste(&Paste, cx);
+    }
+}
+
+fn persist_clipboard_history(cx: &AppContext) -> bool {
+    EditorSettings::get_global(cx).persist_clipboard_history
+}
+
+fn entries(cx: &mut AppContext) -> &mut VecDeque<ClipboardHistoryEntry> {
+    let persist = persist_clipboard_history(cx);
+    cx.default_global::<ClipboardHistory>()
+        .entries
+        .get_or_insert_with(|| {
+            if !persist {
+                return VecDeque::new();
+            }
+            DB.get_clipboard_history()
+                .log_err()
+                .unwrap_or_default()
+                .into_iter()
+                .take(CLIPBOARD_HISTORY_LIMIT)
+                .filter_map(|(text, selections, language)| {
+                    Some(ClipboardHistoryEntry {
+                        text,
+                        selections: serde_json::from_str(&selections).log_err()?,
+                        language: language.map(Arc::from),
+                    })
+                })
+                .collect()
+        })
+}
+
+fn add_entry(entry: ClipboardHistoryEntry, cx: &mut AppContext) {
+    let Some(selections) = serde_json::to_string(&entry.selections).log_err() else {
+        return;
+    };
+    let text = entry.text.clone();
+    let language = entry.language.as_deref().map(str::to_string);
+
+    let entries = entries(cx);
+    entries.retain(|existing| existing.text != entry.text);
+    entries.push_front(entry);
+    entries.truncate(CLIPBOARD_HISTORY_LIMIT);
+
+    if !persist_clipboard_history(cx) {
+        return;
+    }
+    cx.background_executor()
+        .spawn(async move {
+            DB.save_clipboard_entry(text, selections, language, CLIPBOARD_HISTORY_LIMIT)
+                .await
+                .log_err()
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, test::editor_test_context::EditorTestContext, Copy, Cut, SelectAll,
+    };
+    use indoc::indoc;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_paste_from_history(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+
+        cx.set_state(indoc! {"
+            fn a() {
+                «b();ˇ»
+                c();
+            }
+        "});
+        cx.update_editor(|editor, cx| editor.copy(&Copy, cx));
+        cx.set_state(indoc! {"
+            fn a() {
+                b();
+                ˇc();
+            }
+        "});
+        cx.update_editor(|editor, cx| editor.cut(&Cut, cx));
+
+        let history = cx.update(clipboard_history);
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| entry.text.as_str())
+                .take(2)
+                .collect::<Vec<_>>(),
+            ["    c();\n", "b();"]
+        );
+
+        // Pasting an older entry makes it the most recent one
+        cx.set_state("let x = ˇ;");
+        cx.update_editor(|editor, cx| editor.paste_from_history(history[1].clone(), cx));
+        cx.assert_editor_state("let x = b();ˇ;");
+        let history = cx.update(clipboard_history);
+        assert_eq!(history[0].text, "b();");
+        assert_eq!(history[1].text, "    c();\n");
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_string()),
+            Some("b();".to_string())
+        );
+    }
+
+    #[gpui::test]
+    async fn test_clipboard_history_opt_out(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.persist_clipboard_history = Some(false);
+                });
+            });
+        });
+        let mut cx = EditorTestContext::new(cx).await;
+
+        // Text copied from single line editors, such as search queries, isn't recorded
+        let single_line = cx.update_editor(|_, cx| cx.new_view(Editor::single_line));
+        single_line.update(&mut cx.cx, |editor, cx| {
+            editor.set_text("single line query", cx);
+            editor.select_all(&SelectAll, cx);
+            editor.copy(&Copy, cx);
+        });
+        assert!(cx.update(clipboard_history).is_empty());
+
+        // Without persistence, copied text is only kept in memory
+        cx.set_state("«not persisted textˇ»");
+        cx.update_editor(|editor, cx| editor.copy(&Copy, cx));
+        cx.run_until_parked();
+        let history = cx.update(clipboard_history);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].text, "not persisted text");
+        assert!(!DB
+            .get_clipboard_history()
+            .unwrap()
+            .iter()
+            .any(|(text, _, _)| text == "not persisted text"));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    scroll::Autoscroll,
+    Editor, EditorMode, FindAllReferences,
+};
+use collections::HashSet;
+use gpui::{Model, Task, ViewContext};
+use language::{language_settings::language_settings, Buffer, Point, ToPoint};
+use project::CodeLens;
+use std::{sync::Arc, time::Duration};
+use ui::prelude::*;
+use util::ResultExt;
+
+/// How long to wait after an edit before requesting new code lenses.
+const CODE_LENS_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The code lenses shown above the lines of the editor's buffer.
+#[derive(Default)]
+pub(crate) struct CodeLensState {
+    blocks: HashSet<BlockId>,
+    refresh_task: Option<Task<()>>,
+}
+
+/// Requests the buffer's code lenses, replacing the ones shown once they arrive.
+pub(crate) fn refresh_code_lenses(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+    let enabled = {
+        let buffer = buffer.read(cx);
+        language_settings(buffer.language(), buffer.file(), cx).code_lens
+    };
+    if !enabled {
+        editor.code_lens.refresh_task = None;
+        clear_code_lenses(editor, cx);
+        return None;
+    }
+
+    editor.code_lens.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        if debounce {
+            cx.background_executor().timer(CODE_LENS_DEBOUNCE).await;
+        }
+
+        let Some(lenses) = project
+            .update(&mut cx, |project, cx| project.code_lenses(&buffer, cx))
+            .ok()
+        else {
+            return;
+        };
+        let Some(lenses) = lenses.await.log_err() else {
+            return;
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                show_code_lenses(editor, buffer, lenses, cx)
+            })
+            .log_err();
+    }));
+    None
+}
+
+fn clear_code_lenses(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let blocks = std::mem::take(&mut editor.code_lens.blocks);
+    if !blocks.is_empty() {
+        editor.remove_blocks(blocks, None, cx);
+    }
+}
+
+/// Shows the lenses for each line together, in a block above the line.
+fn show_code_lenses(
+    editor: &mut Editor,
+    buffer: Model<Buffer>,
+    mut lenses: Vec<CodeLens>,
+    cx: &mut ViewContext<Editor>,
+) {
+    clear_code_lenses(editor, cx);
+
+    let buffer_snapshot = buffer.read(cx).snapshot();
+    lenses.sort_by_key(|lens| lens.range.start.to_point(&buffer_snapshot));
+    let mut lines = Vec::<(u32, Vec<CodeLens>)>::new();
+    for lens in lenses {
+        let row = lens.range.start.to_point(&buffer_snapshot).row;
+        match lines.last_mut() {
+            Some((last_row, line_lenses)) if *last_row == row => line_lenses.push(lens),
+            _ => lines.push((row, vec![lens])),
+        }
+    }
+
+    let multibuffer_snapshot = editor.buffer.read(cx).snapshot(cx);
+    let blocks = lines.into_iter().map(|(row, lenses)| {
+        // Line the lenses up with the code they refer to
+        let indent = buffer_snapshot.indent_size_for_line(row).len;
+        let buffer = buffer.clone();
+        BlockProperties {
+            style: BlockStyle::Sticky,
+            position: multibuffer_snapshot.anchor_before(Point::new(row, indent)),
+            height: 1,
+            render: Arc::new(move |cx: &mut BlockContext| {
+                let editor = cx.view.clone();
+                h_flex()
+                    .id(cx.block_id)
+                    .h(cx.line_height)
+                    .pl(cx.anchor_x)
+                    .gap_2()
+                    .children(lenses.iter().enumerate().filter_map(|(ix, lens)| {
+                        let title = lens.lsp_lens.command.as_ref()?.title.clone();
+                        let editor = editor.clone();
+                        let buffer = buffer.clone();
+                        let lens = lens.clone();
+                        Some(
+                            Button::new(("code-lens", ix), title)
+                                .style(ButtonStyle::Transparent)
+                                .label_size(LabelSize::Small)
+                                .color(Color::Muted)
+                                .on_click(move |_, cx| {
+                                    editor.update(cx, |editor, cx| {
+                                        execute_code_lens(editor, buffer.clone(), lens.clone(), cx)
+                                    })
+                                }),
+                        )
+                    }))
+                    .into_any_element()
+            }),
+            disposition: BlockDisposition::Above,
+        }
+    });
+    editor.code_lens.blocks = editor.insert_blocks(blocks, None, cx).into_iter().collect();
+}
+
+fn execute_code_lens(
+    editor: &mut Editor,
+    buffer: Model<Buffer>,
+    lens: CodeLens,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(command) = lens.lsp_lens.command.as_ref() else {
+        return;
+    };
+
+    // Servers leave showing the references that a reference count lens counts to the client
+    if command.command.ends_with("showReferences") {
+        let position = lens.range.start.to_point(buffer.read(cx));
+        editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.select_ranges([position..position])
+        });
+        if let Some(task) = editor.find_all_references(&FindAllReferences, cx) {
+            task.detach_and_log_err(cx);
+        }
+        return;
+    }
+
+    let (Some(project), Some(workspace)) = (editor.project.clone(), editor.workspace()) else {
+        return;
+    };
+    let title = command.title.clone();
+    let transaction = project.update(cx, |project, cx| {
+        project.execute_code_lens(buffer, lens, cx)
+    });
+    cx.spawn(|editor, cx| async move {
+        let transaction = transaction.await?;
+        Editor::open_project_transaction(&editor, workspace.downgrade(), transaction, title, cx)
+            .await
+    })
+    .detach_and_log_err(cx);
+}
@@ -66,6 +66,10 @@ pub trait ToDisplayPoint {
 
 type TextHighlights = TreeMap<Option<TypeId>, Arc<(HighlightStyle, Vec<Range<Anchor>>)>>;
 type InlayHighlights = BTreeMap<TypeId, HashMap<InlayId, (HighlightStyle, InlayHighlight)>>;
+/// The styles of a buffer's semantic tokens, ordered by position.
+type SemanticHighlights = Arc<Vec<(Range<Anchor>, HighlightStyle)>>;
+/// The ranges that the language server suggests folding, ordered by their start.
+type LspFoldRanges = Arc<Vec<Range<Anchor>>>;
 
 pub struct DisplayMap {
     buffer: Model<MultiBuffer>,
@@ -77,6 +81,8 @@ pub struct DisplayMap {
     block_map: BlockMap,
     text_highlights: TextHighlights,
     inlay_highlights: InlayHighlights,
+    semantic_highlights: SemanticHighlights,
+    lsp_fold_ranges: LspFoldRanges,
     pub clip_at_line_ends: bool,
 }
 
@@ -109,6 +115,8 @@ impl DisplayMap {
             block_map,
             text_highlights: Default::default(),
             inlay_highlights: Default::default(),
+            semantic_highlights: Default::default(),
+            lsp_fold_ranges: Default::default(),
             clip_at_line_ends: false,
         }
     }
@@ -134,6 +142,8 @@ impl DisplayMap {
             block_snapshot,
             text_highlights: self.text_highlights.clone(),
             inlay_highlights: self.inlay_highlights.clone(),
+            semantic_highlights: self.semantic_highlights.clone(),
+            lsp_fold_ranges: self.lsp_fold_ranges.clone(),
             clip_at_line_ends: self.clip_at_line_ends,
         }
     }
@@ -258,6 +268,27 @@ impl DisplayMap {
         let highlights = self.text_highlights.get(&Some(type_id))?;
         Some((highlights.0, &highlights.1))
     }
+
+    /// Replaces the styles of the buffer's semantic tokens, which are layered under
+    /// the other text highlights and over syntax highlighting. The ranges must be
+    /// ordered and must not overlap.
+    pub fn set_semantic_highlights(&mut self, highlights: Vec<(Range<Anchor>, HighlightStyle)>) {
+        self.semantic_highlights = Arc::new(highlights);
+    }
+
+    /// Replaces the ranges that the language server suggests folding, which take precedence
+    /// over the ones found from the buffer's syntax and indentation. The ranges must be
+    /// ordered by their start.
+    pub fn set_lsp_fold_ranges(&mut self, ranges: Vec<Range<Anchor>>) {
+        self.lsp_fold_ranges = Arc::new(ranges);
+    }
+
+    pub fn clear_semantic_highlights(&mut self) -> bool {
+        let cleared = !self.semantic_highlights.is_empty();
+        self.semantic_highlights = Default::default();
+        cleared
+    }
+
     pub fn clear_highlights(&mut self, type_id: TypeId) -> bool {
         let mut cleared = self.text_highlights.remove(&Some(type_id)).is_some();
         cleared |= self.inlay_highlights.remove(&type_id).is_some();
@@ -329,6 +360,7 @@ impl DisplayMap {
 pub(crate) struct Highlights<'a> {
     pub text_highlights: Option<&'a TextHighlights>,
     pub inlay_highlights: Option<&'a InlayHighlights>,
+    pub semantic_highlights: Option<&'a [(Range<Anchor>, HighlightStyle)]>,
     pub inlay_highlight_style: Option<HighlightStyle>,
     pub suggestion_highlight_style: Option<HighlightStyle>,
 }
@@ -348,6 +380,8 @@ pub struct DisplaySnapshot {
     block_snapshot: block_map::BlockSnapshot,
     text_highlights: TextHighlights,
     inlay_highlights: InlayHighlights,
+    semantic_highlights: SemanticHighlights,
+    lsp_fold_ranges: LspFoldRanges,
     clip_at_line_ends: bool,
 }
 
@@ -515,6 +549,7 @@ impl DisplaySnapshot {
             Highlights {
                 text_highlights: Some(&self.text_highlights),
                 inlay_highlights: Some(&self.inlay_highlights),
+                semantic_highlights: Some(&self.semantic_highlights),
                 inlay_highlight_style,
                 suggestion_highlight_style,
             },
@@ -845,6 +880,54 @@ impl DisplaySnapshot {
     }
 
     pub fn is_foldable(self: &Self, buffer_row: u32) -> bool {
+        self.lsp_foldable_range(buffer_row).is_some()
+            || self.syntax_foldable_range(buffer_row).is_some()
+            || self.is_indent_foldable(buffer_row)
+    }
+
+    /// The range to fold from the row, which the language server suggests if it can, and
+    /// otherwise the contents of the brackets that open on the row, or the lines indented
+    /// below it.
+    pub fn foldable_range(self: &Self, buffer_row: u32) -> Option<Range<Point>> {
+        if self.is_line_folded(buffer_row) {
+            return None;
+        }
+        self.lsp_foldable_range(buffer_row)
+            .or_else(|| self.syntax_foldable_range(buffer_row))
+            .or_else(|| self.indent_foldable_range(buffer_row))
+    }
+
+    /// The largest range that the language server suggests folding from the row.
+    fn lsp_foldable_range(&self, buffer_row: u32) -> Option<Range<Point>> {
+        let buffer = &self.buffer_snapshot;
+        let first = self
+            .lsp_fold_ranges
+            .partition_point(|range| range.start.to_point(buffer).row < buffer_row);
+        self.lsp_fold_ranges[first..]
+            .iter()
+            .map(|range| range.to_point(buffer))
+            .take_while(|range| range.start.row == buffer_row)
+            .filter(|range| range.end.row > buffer_row)
+            .max_by_key(|range| range.end)
+    }
+
+    /// The contents of the outermost pair of brackets that opens on the row and closes on a
+    /// later one, according to the brackets query of the buffer's language.
+    fn syntax_foldable_range(&self, buffer_row: u32) -> Option<Range<Point>> {
+        let buffer = &self.buffer_snapshot;
+        let row_range =
+            Point::new(buffer_row, 0)..Point::new(buffer_row, buffer.line_len(buffer_row));
+        buffer
+            .bracket_ranges(row_range)?
+            .filter_map(|(open, close)| {
+                let start = buffer.offset_to_point(open.end);
+                let end = buffer.offset_to_point(close.start);
+                (start.row == buffer_row && end.row > buffer_row).then_some(start..end)
+            })
+            .max_by_key(|range| range.end)
+    }
+
+    fn is_indent_foldable(&self, buffer_row: u32) -> bool {
         let max_row = self.buffer_snapshot.max_buffer_row();
         if buffer_row >= max_row {
             return false;
@@ -867,9 +950,9 @@ impl DisplaySnapshot {
         false
     }
 
-    pub fn foldable_range(self: &Self, buffer_row: u32) -> Option<Range<Point>> {
+    fn indent_foldable_range(&self, buffer_row: u32) -> Option<Range<Point>> {
         let start = Point::new(buffer_row, self.buffer_snapshot.line_len(buffer_row));
-        if self.is_foldable(start.row) && !self.is_line_folded(start.row) {
+        if self.is_indent_foldable(start.row) {
             let (start_indent, _) = self.line_indent_for_buffer_row(buffer_row);
             let max_point = self.buffer_snapshot.max_point();
             let mut end = None;
@@ -900,6 +983,11 @@ impl DisplaySnapshot {
         self.text_highlights.get(&Some(type_id)).cloned()
     }
 
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn semantic_highlight_ranges(&self) -> &[(Range<Anchor>, HighlightStyle)] {
+        &self.semantic_highlights
+    }
+
     #[allow(unused)]
     #[cfg(any(test, feature = "test-support"))]
     pub(crate) fn inlay_highlights<Tag: ?Sized + 'static>(
@@ -186,10 +186,19 @@ pub struct InlayBufferRows<'a> {
 struct HighlightEndpoint {
     offset: InlayOffset,
     is_start: bool,
-    tag: Option<TypeId>,
+    tag: HighlightTag,
     style: HighlightStyle,
 }
 
+/// Identifies the highlight that an endpoint starts or ends. Each semantic token
+/// gets its own tag, so that adjacent tokens don't end each other's highlight.
+/// Semantic tokens order first, so other text highlights are applied over them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+enum HighlightTag {
+    SemanticToken(usize),
+    Text(Option<TypeId>),
+}
+
 impl PartialOrd for HighlightEndpoint {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         Some(self.cmp(other))
@@ -215,7 +224,7 @@ pub struct InlayChunks<'a> {
     inlay_highlight_style: Option<HighlightStyle>,
     suggestion_highlight_style: Option<HighlightStyle>,
     highlight_endpoints: Peekable<vec::IntoIter<HighlightEndpoint>>,
-    active_highlights: BTreeMap<Option<TypeId>, HighlightStyle>,
+    active_highlights: BTreeMap<HighlightTag, HighlightStyle>,
     highlights: Highlights<'a>,
     snapshot: &'a InlaySnapshot,
 }
@@ -1037,6 +1046,15 @@ impl InlaySnapshot {
                 cursor.seek(&range.start, Bias::Right, &());
             }
         }
+        if let Some(semantic_highlights) = highlights.semantic_highlights {
+            if !semantic_highlights.is_empty() {
+                self.apply_semantic_highlights(
+                    &range,
+                    semantic_highlights,
+                    &mut highlight_endpoints,
+                );
+            }
+        }
         highlight_endpoints.sort();
         let buffer_range = self.to_buffer_offset(range.start)..self.to_buffer_offset(range.end);
         let buffer_chunks = self.buffer.chunks(buffer_range, language_aware);
@@ -1100,13 +1118,13 @@ impl InlaySnapshot {
                     highlight_endpoints.push(HighlightEndpoint {
                         offset: self.to_inlay_offset(range.start.to_offset(&self.buffer)),
                         is_start: true,
-                        tag: *tag,
+                        tag: HighlightTag::Text(*tag),
                         style,
                     });
                     highlight_endpoints.push(HighlightEndpoint {
                         offset: self.to_inlay_offset(range.end.to_offset(&self.buffer)),
                         is_start: false,
-                        tag: *tag,
+                        tag: HighlightTag::Text(*tag),
                         style,
                     });
                 }
@@ -1116,6 +1134,43 @@ impl InlaySnapshot {
         }
     }
 
+    fn apply_semantic_highlights(
+        &self,
+        range: &Range<InlayOffset>,
+        semantic_highlights: &[(Range<Anchor>, HighlightStyle)],
+        highlight_endpoints: &mut Vec<HighlightEndpoint>,
+    ) {
+        let start = self.buffer.anchor_after(self.to_buffer_offset(range.start));
+        let end = self.buffer.anchor_before(self.to_buffer_offset(range.end));
+        let start_ix = match semantic_highlights.binary_search_by(|(probe, _)| {
+            match probe.end.cmp(&start, &self.buffer) {
+                cmp::Ordering::Greater => cmp::Ordering::Greater,
+                _ => cmp::Ordering::Less,
+            }
+        }) {
+            Ok(i) | Err(i) => i,
+        };
+        for (ix, (range, style)) in semantic_highlights.iter().enumerate().skip(start_ix) {
+            if range.start.cmp(&end, &self.buffer).is_ge() {
+                break;
+            }
+
+            let tag = HighlightTag::SemanticToken(ix);
+            highlight_endpoints.push(HighlightEndpoint {
+                offset: self.to_inlay_offset(range.start.to_offset(&self.buffer)),
+                is_start: true,
+                tag,
+                style: *style,
+            });
+            highlight_endpoints.push(HighlightEndpoint {
+                offset: self.to_inlay_offset(range.end.to_offset(&self.buffer)),
+                is_start: false,
+                tag,
+                style: *style,
+            });
+        }
+    }
+
     #[cfg(test)]
     pub fn text(&self) -> String {
         self.chunks(Default::default()..self.len(), false, Highlights::default())
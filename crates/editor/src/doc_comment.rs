@@ -0,0 +1,155 @@
+use crate::{Editor, GenerateDocComment, CODE_ACTIONS_DEBOUNCE_TIMEOUT};
+use gpui::{AppContext, Model, ViewContext};
+use language::{Buffer, LanguageScope, OffsetRangeExt, Point, ToOffset, ToPoint};
+use util::ResultExt;
+
+/// Looks up the function under the newest cursor, once the cursor has stopped moving, so
+/// that the context menu can tell whether there is anything to document.
+pub(crate) fn refresh_function_symbol(
+    editor: &mut Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    let project = editor.project.clone()?;
+    let head = editor.selections.newest_anchor().head();
+    let (buffer, position) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+
+    editor.function_symbol_task = Some(cx.spawn(|editor, mut cx| async move {
+        cx.background_executor()
+            .timer(CODE_ACTIONS_DEBOUNCE_TIMEOUT)
+            .await;
+
+        let symbol = if let Ok(symbol) = project.update(&mut cx, |project, cx| {
+            project.function_symbol(&buffer, position, cx)
+        }) {
+            symbol.await.log_err().flatten()
+        } else {
+            None
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                editor.function_symbol = symbol.map(|symbol| (buffer, symbol));
+                cx.notify();
+            })
+            .log_err();
+    }));
+    None
+}
+
+/// Whether the newest cursor is within a function that a doc comment can be generated for.
+pub(crate) fn can_generate_doc_comment(editor: &Editor, cx: &AppContext) -> bool {
+    doc_comment_for_cursor(editor, cx).is_some()
+}
+
+/// Inserts a doc comment skeleton, listing the function's parameters, above the function
+/// under the newest cursor.
+pub fn generate_doc_comment(
+    editor: &mut Editor,
+    _: &GenerateDocComment,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some((buffer, row, doc_comment)) = doc_comment_for_cursor(editor, cx) else {
+        return;
+    };
+
+    editor.transact(cx, |_, cx| {
+        buffer.update(cx, |buffer, cx| {
+            let position = Point::new(row, 0);
+            buffer.edit([(position..position, doc_comment)], None, cx);
+        });
+    });
+}
+
+/// Returns the doc comment for the function under the newest cursor, along with the buffer
+/// and row that it should be inserted above.
+fn doc_comment_for_cursor(
+    editor: &Editor,
+    cx: &AppContext,
+) -> Option<(Model<Buffer>, u32, String)> {
+    let (buffer, symbol) = editor.function_symbol.as_ref()?;
+    let head = editor.selections.newest_anchor().head();
+    let (cursor_buffer, cursor) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+    if cursor_buffer != *buffer {
+        return None;
+    }
+
+    let snapshot = buffer.read(cx).snapshot();
+    let range = symbol.range.to_offset(&snapshot);
+    let cursor = cursor.to_offset(&snapshot);
+    if cursor < range.start || cursor > range.end {
+        return None;
+    }
+
+    let row = symbol.range.start.to_point(&snapshot).row;
+    let indent = snapshot
+        .indent_size_for_line(row)
+        .chars()
+        .collect::<String>();
+    let scope = snapshot.language_scope_at(range.start)?;
+    let lines = doc_comment_lines(&scope, &symbol.name, &symbol.parameters)?;
+    let doc_comment = lines
+        .into_iter()
+        .map(|line| format!("{}{}\n", indent, line.trim_end()))
+        .collect();
+    Some((buffer.clone(), row, doc_comment))
+}
+
+/// Builds the lines of a doc comment in the convention of the language: `///` comments
+/// where the language uses them, block doc comments where it has `/* */` comments, and
+/// otherwise its ordinary line comments.
+fn doc_comment_lines(
+    scope: &LanguageScope,
+    name: &str,
+    parameters: &[String],
+) -> Option<Vec<String>> {
+    let summary = format!("Describe `{}`.", name);
+    let line_prefixes = scope.line_comment_prefixes().cloned().unwrap_or_default();
+
+    if line_prefixes
+        .iter()
+        .any(|prefix| prefix.trim_end() == "///")
+    {
+        let mut lines = vec![format!("/// {}", summary)];
+        if !parameters.is_empty() {
+            lines.extend([
+                "///".to_string(),
+                "/// # Arguments".to_string(),
+                "///".to_string(),
+            ]);
+            lines.extend(
+                parameters
+                    .iter()
+                    .map(|parameter| format!("/// * `{}`", parameter)),
+            );
+        }
+        return Some(lines);
+    }
+
+    if let Some((start, end)) = scope.block_comment_delimiters() {
+        if start.trim_end() == "/*" {
+            let mut lines = vec!["/**".to_string(), format!(" * {}", summary)];
+            if !parameters.is_empty() {
+                lines.push(" *".to_string());
+                lines.extend(
+                    parameters
+                        .iter()
+                        .map(|parameter| format!(" * @param {}", parameter)),
+                );
+            }
+            lines.push(format!(" {}", end.trim()));
+            return Some(lines);
+        }
+    }
+
+    let prefix = line_prefixes.first()?.trim_end().to_string();
+    let mut lines = vec![format!("{} {}", prefix, summary)];
+    if !parameters.is_empty() {
+        lines.push(prefix.clone());
+        lines.extend(
+            parameters
+                .iter()
+                .map(|parameter| format!("{} - {}", prefix, parameter)),
+        );
+    }
+    Some(lines)
+}
@@ -13,23 +13,43 @@
 //!
 //! If you're looking to improve Vim mode, you should check out Vim crate that wraps Editor and overrides it's behaviour.
 pub mod actions;
+mod blame;
 mod blink_manager;
+mod bookmarks;
+pub mod call_hierarchy_panel;
+mod clipboard_history;
+mod code_lens;
 pub mod display_map;
+mod doc_comment;
 mod editor_settings;
 mod element;
+mod file_state;
+mod folding_ranges;
 mod inlay_hint_cache;
 
 mod git;
 mod highlight_matching_bracket;
+mod hot_exit;
 mod hover_popover;
+mod hunk_diff;
 pub mod items;
 mod link_go_to_definition;
+mod linked_editing_ranges;
 mod mouse_context_menu;
 pub mod movement;
+mod peek_definition;
 mod persistence;
+pub mod references_panel;
+mod rename_preview;
+mod runnables;
 mod rust_analyzer_ext;
 pub mod scroll;
+mod selection_ranges;
 mod selections_collection;
+mod semantic_tokens;
+mod signature_help;
+mod snippet_sharing;
+mod spell_check;
 
 #[cfg(test)]
 mod editor_tests;
@@ -41,6 +61,7 @@ use aho_corasick::AhoCorasick;
 use anyhow::{anyhow, Context as _, Result};
 use blink_manager::BlinkManager;
 use client::{Collaborator, ParticipantIndex};
+pub use clipboard_history::{clipboard_history, ClipboardHistoryEntry};
 use clock::ReplicaId;
 use collections::{BTreeMap, Bound, HashMap, HashSet, VecDeque};
 use convert_case::{Case, Casing};
@@ -48,6 +69,7 @@ use copilot::Copilot;
 pub use display_map::DisplayPoint;
 use display_map::*;
 pub use editor_settings::EditorSettings;
+use editor_settings::ReferencesDisplay;
 use element::LineWithInvisibles;
 pub use element::{Cursor, EditorElement, HighlightedRange, HighlightedRangeLine};
 use futures::FutureExt;
@@ -74,6 +96,7 @@ use language::{
     CodeLabel, Completion, CursorShape, Diagnostic, Documentation, IndentKind, IndentSize,
     Language, LanguageServerName, OffsetRangeExt, Point, Selection, SelectionGoal, TransactionId,
 };
+pub use mouse_context_menu::register_context_menu_item;
 
 use link_go_to_definition::{GoToDefinitionLink, InlayHighlight, LinkGoToDefinitionState};
 use lsp::{DiagnosticSeverity, LanguageServerId};
@@ -86,8 +109,13 @@ pub use multi_buffer::{
 };
 use ordered_float::OrderedFloat;
 use parking_lot::RwLock;
-use project::{FormatTrigger, Location, Project, ProjectPath, ProjectTransaction};
+use project::{
+    DocumentHighlight, FormatTrigger, FunctionSymbol, Location, Project, ProjectPath,
+    ProjectTransaction,
+};
 use rand::prelude::*;
+use references_panel::ReferencesPanel;
+use rename_preview::RenamePreview;
 use rpc::proto::*;
 use scroll::{Autoscroll, OngoingScroll, ScrollAnchor, ScrollManager, ScrollbarAutoHide};
 use selections_collection::{resolve_multiple, MutableSelectionsCollection, SelectionsCollection};
@@ -102,7 +130,7 @@ use std::{
     mem,
     num::NonZeroU32,
     ops::{ControlFlow, Deref, DerefMut, Range, RangeInclusive},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
     sync::Weak,
     time::{Duration, Instant},
@@ -186,10 +214,24 @@ pub fn render_parsed_markdown(
     .on_click(link_ranges, move |clicked_range_ix, cx| {
         match &links[clicked_range_ix] {
             markdown::Link::Web { url } => cx.open_url(url),
-            markdown::Link::Path { path } => {
+            markdown::Link::Path { path, row } => {
                 if let Some(workspace) = &workspace {
+                    let row = *row;
                     _ = workspace.update(cx, |workspace, cx| {
-                        workspace.open_abs_path(path.clone(), false, cx).detach();
+                        let open = workspace.open_abs_path(path.clone(), false, cx);
+                        cx.spawn(|_, mut cx| async move {
+                            let item = open.await?;
+                            if let Some((row, editor)) = row.zip(item.downcast::<Editor>()) {
+                                editor.update(&mut cx, |editor, cx| {
+                                    let point = Point::new(row, 0);
+                                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                                        s.select_ranges([point..point])
+                                    });
+                                })?;
+                            }
+                            anyhow::Ok(())
+                        })
+                        .detach_and_log_err(cx);
                     });
                 }
             }
@@ -232,6 +274,16 @@ pub fn init(cx: &mut AppContext) {
     workspace::register_project_item::<Editor>(cx);
     workspace::register_followable_item::<Editor>(cx);
     workspace::register_deserializable_item::<Editor>(cx);
+    mouse_context_menu::init(cx);
+    hot_exit::init(cx);
+    references_panel::init(cx);
+    cx.on_app_quit(|_| {
+        DB.flush_queued_writes().map(|result| {
+            result.log_err();
+        })
+    })
+    .detach();
+    call_hierarchy_panel::init(cx);
     cx.observe_new_views(
         |workspace: &mut Workspace, _cx: &mut ViewContext<Workspace>| {
             workspace.register_action(Editor::new_file);
@@ -275,6 +327,10 @@ pub enum SelectPhase {
         position: DisplayPoint,
         goal_column: u32,
     },
+    BeginBlock {
+        position: DisplayPoint,
+        add: bool,
+    },
     Extend {
         position: DisplayPoint,
         click_count: usize,
@@ -293,6 +349,16 @@ pub(crate) enum SelectMode {
     Word(Range<Anchor>),
     Line(Range<Anchor>),
     All,
+    /// A click which becomes a column selection once it's dragged.
+    Block,
+}
+
+#[derive(Clone, Debug)]
+struct SelectionDrag {
+    selection: Selection<Anchor>,
+    /// Where the mouse went down. Releasing it without dragging places the cursor here.
+    origin: DisplayPoint,
+    drop_position: Option<DisplayPoint>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -355,6 +421,7 @@ pub struct Editor {
     pub selections: SelectionsCollection,
     pub scroll_manager: ScrollManager,
     columnar_selection_tail: Option<Anchor>,
+    selection_drag: Option<SelectionDrag>,
     add_selections_state: Option<AddSelectionsState>,
     select_next_state: Option<SelectNextState>,
     select_prev_state: Option<SelectNextState>,
@@ -362,6 +429,7 @@ pub struct Editor {
     autoclose_regions: Vec<AutocloseRegion>,
     snippet_stack: InvalidationStack<SnippetState>,
     select_larger_syntax_node_stack: Vec<Box<[Selection<usize>]>>,
+    selection_ranges_task: Option<Task<()>>,
     ime_transaction: Option<TransactionId>,
     active_diagnostics: Option<ActiveDiagnosticGroup>,
     soft_wrap_mode_override: Option<language_settings::SoftWrap>,
@@ -386,8 +454,22 @@ pub struct Editor {
     next_completion_id: CompletionId,
     available_code_actions: Option<(Model<Buffer>, Arc<[CodeAction]>)>,
     code_actions_task: Option<Task<()>>,
+    function_symbol: Option<(Model<Buffer>, FunctionSymbol)>,
+    function_symbol_task: Option<Task<()>>,
     document_highlights_task: Option<Task<()>>,
     pending_rename: Option<RenameState>,
+    peek: Option<peek_definition::PeekState>,
+    bookmarks: bookmarks::Bookmarks,
+    hunk_diffs: hunk_diff::HunkDiffs,
+    signature_help: signature_help::SignatureHelpState,
+    code_lens: code_lens::CodeLensState,
+    semantic_tokens: semantic_tokens::SemanticTokensState,
+    folding_ranges: folding_ranges::FoldingRangesState,
+    blame: blame::BlameState,
+    spell_check: spell_check::SpellCheckState,
+    file_state: file_state::FileState,
+    hot_exit: hot_exit::HotExitState,
+    linked_editing: linked_editing_ranges::LinkedEditingState,
     searchable: bool,
     cursor_shape: CursorShape,
     collapse_matches: bool,
@@ -397,6 +479,9 @@ pub struct Editor {
     input_enabled: bool,
     read_only: bool,
     leader_peer_id: Option<PeerId>,
+    // Set while the mouse context menu is open, so that moving the cursor to where
+    // the menu was deployed isn't shared with collaborators unless an item is chosen
+    pub(crate) defer_selection_broadcast: bool,
     remote_id: Option<ViewId>,
     hover_state: HoverState,
     gutter_hovered: bool,
@@ -406,6 +491,8 @@ pub struct Editor {
     next_inlay_id: usize,
     _subscriptions: Vec<Subscription>,
     pixel_position_of_newest_cursor: Option<gpui::Point<Pixels>>,
+    /// Where the editor was last laid out in its window.
+    last_bounds: Option<Bounds<Pixels>>,
     gutter_width: Pixels,
     style: Option<EditorStyle>,
     editor_actions: Vec<Box<dyn Fn(&mut ViewContext<Self>)>>,
@@ -1245,7 +1332,7 @@ struct ActiveDiagnosticGroup {
     is_valid: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ClipboardSelection {
     pub len: usize,
     pub is_entire_line: bool,
@@ -1262,7 +1349,9 @@ pub(crate) struct NavigationData {
 
 enum GotoDefinitionKind {
     Symbol,
+    Declaration,
     Type,
+    Implementation,
 }
 
 #[derive(Debug, Clone)]
@@ -1334,6 +1423,7 @@ impl Editor {
         clone.selections.clone_state(&self.selections);
         clone.scroll_manager.clone_state(&self.scroll_manager);
         clone.searchable = self.searchable;
+        clone.file_state = file_state::FileState::already_restored();
         clone
     }
 
@@ -1364,11 +1454,21 @@ impl Editor {
                         cx.emit(EditorEvent::TitleChanged);
                     }));
                 }
-                project_subscriptions.push(cx.subscribe(project, |editor, _, event, cx| {
-                    if let project::Event::RefreshInlayHints = event {
-                        editor.refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
-                    };
-                }));
+                project_subscriptions.push(cx.subscribe(
+                    project,
+                    |editor, _, event, cx| match event {
+                        project::Event::RefreshInlayHints => {
+                            editor
+                                .refresh_inlay_hints(InlayHintRefreshReason::RefreshRequested, cx);
+                        }
+                        project::Event::LanguageServerAdded(_) => {
+                            code_lens::refresh_code_lenses(editor, false, cx);
+                            semantic_tokens::refresh_semantic_tokens(editor, false, cx);
+                            folding_ranges::refresh_folding_ranges(editor, false, cx);
+                        }
+                        _ => {}
+                    },
+                ));
             }
         }
 
@@ -1390,6 +1490,7 @@ impl Editor {
             selections,
             scroll_manager: ScrollManager::new(),
             columnar_selection_tail: None,
+            selection_drag: None,
             add_selections_state: None,
             select_next_state: None,
             select_prev_state: None,
@@ -1397,6 +1498,7 @@ impl Editor {
             autoclose_regions: Default::default(),
             snippet_stack: Default::default(),
             select_larger_syntax_node_stack: Vec::new(),
+            selection_ranges_task: None,
             ime_transaction: Default::default(),
             active_diagnostics: None,
             soft_wrap_mode_override,
@@ -1420,8 +1522,22 @@ impl Editor {
             next_inlay_id: 0,
             available_code_actions: Default::default(),
             code_actions_task: Default::default(),
+            function_symbol: Default::default(),
+            function_symbol_task: Default::default(),
             document_highlights_task: Default::default(),
             pending_rename: Default::default(),
+            peek: None,
+            bookmarks: Default::default(),
+            hunk_diffs: Default::default(),
+            signature_help: Default::default(),
+            code_lens: Default::default(),
+            semantic_tokens: Default::default(),
+            folding_ranges: Default::default(),
+            blame: Default::default(),
+            spell_check: Default::default(),
+            file_state: Default::default(),
+            hot_exit: Default::default(),
+            linked_editing: Default::default(),
             searchable: true,
             cursor_shape: Default::default(),
             autoindent_mode: Some(AutoindentMode::EachLine),
@@ -1432,6 +1548,7 @@ impl Editor {
             read_only: false,
             use_autoclose: true,
             leader_peer_id: None,
+            defer_selection_broadcast: false,
             remote_id: None,
             hover_state: Default::default(),
             link_go_to_definition_state: Default::default(),
@@ -1439,6 +1556,7 @@ impl Editor {
             inlay_hint_cache: InlayHintCache::new(inlay_hint_settings),
             gutter_hovered: false,
             pixel_position_of_newest_cursor: None,
+            last_bounds: None,
             gutter_width: Default::default(),
             style: None,
             show_cursor_names: false,
@@ -1470,6 +1588,11 @@ impl Editor {
 
         this.end_selection(cx);
         this.scroll_manager.show_scrollbar(cx);
+        code_lens::refresh_code_lenses(&mut this, false, cx);
+        semantic_tokens::refresh_semantic_tokens(&mut this, false, cx);
+        folding_ranges::refresh_folding_ranges(&mut this, false, cx);
+        blame::refresh_blame(&mut this, false, cx);
+        spell_check::refresh_spell_check(&mut this, false, cx);
 
         if mode == EditorMode::Full {
             let should_auto_hide_scrollbars = cx.should_auto_hide_scrollbars();
@@ -1492,6 +1615,12 @@ impl Editor {
         if self.pending_rename.is_some() {
             key_context.add("renaming");
         }
+        if self.peek.is_some() {
+            key_context.add("peeking");
+        }
+        if self.signature_help.visible() {
+            key_context.add("showing_signature_help");
+        }
         if self.context_menu_visible() {
             match self.context_menu.read().as_ref() {
                 Some(ContextMenu::Completions(_)) => {
@@ -1720,13 +1849,8 @@ impl Editor {
         self.show_copilot_suggestions = show_copilot_suggestions;
     }
 
-    fn selections_did_change(
-        &mut self,
-        local: bool,
-        old_cursor_position: &Anchor,
-        cx: &mut ViewContext<Self>,
-    ) {
-        if self.focus_handle.is_focused(cx) && self.leader_peer_id.is_none() {
+    pub(crate) fn broadcast_selections(&mut self, cx: &mut ViewContext<Self>) {
+        if self.leader_peer_id.is_none() {
             self.buffer.update(cx, |buffer, cx| {
                 buffer.set_active_selections(
                     &self.selections.disjoint_anchors(),
@@ -1736,6 +1860,17 @@ impl Editor {
                 )
             });
         }
+    }
+
+    fn selections_did_change(
+        &mut self,
+        local: bool,
+        old_cursor_position: &Anchor,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.focus_handle.is_focused(cx) && !self.defer_selection_broadcast {
+            self.broadcast_selections(cx);
+        }
 
         let display_map = self
             .display_map
@@ -1813,6 +1948,7 @@ impl Editor {
             }
 
             hide_hover(self, cx);
+            self.write_primary_selection(cx);
 
             if old_cursor_position.to_display_point(&display_map).row()
                 != new_cursor_position.to_display_point(&display_map).row()
@@ -1820,11 +1956,15 @@ impl Editor {
                 self.available_code_actions.take();
             }
             self.refresh_code_actions(cx);
+            doc_comment::refresh_function_symbol(self, cx);
             self.refresh_document_highlights(cx);
             refresh_matching_bracket_highlights(self, cx);
+            signature_help::refresh_signature_help(self, cx);
+            linked_editing_ranges::refresh_linked_editing_ranges(self, cx);
             self.discard_copilot_suggestion(cx);
         }
 
+        file_state::queue_file_state_save(self, cx);
         self.blink_manager.update(cx, BlinkManager::pause_blinking);
         cx.emit(EditorEvent::SelectionsChanged { local });
 
@@ -1923,6 +2063,9 @@ impl Editor {
                 position,
                 goal_column,
             } => self.begin_columnar_selection(position, goal_column, cx),
+            SelectPhase::BeginBlock { position, add } => {
+                self.begin_block_selection(position, add, cx)
+            }
             SelectPhase::Extend {
                 position,
                 click_count,
@@ -1987,6 +2130,22 @@ impl Editor {
         let newest_selection = self.selections.newest_anchor().clone();
         let position = display_map.clip_point(position, Bias::Left);
 
+        // Adding a cursor where there already is one removes it instead, unless it's the last one
+        if add && click_count == 1 {
+            let point = position.to_point(&display_map);
+            let selections = self.selections.all::<Point>(cx);
+            if selections.len() > 1 {
+                if let Some(selection) = selections
+                    .iter()
+                    .find(|selection| selection.start <= point && point <= selection.end)
+                {
+                    let selection_id = selection.id;
+                    self.change_selections(None, cx, |s| s.delete(selection_id));
+                    return;
+                }
+            }
+        }
+
         let start;
         let end;
         let mode;
@@ -2027,10 +2186,14 @@ impl Editor {
             }
         }
 
+        // Each click of a multi-click replaces the selection added by the previous one
+        let replaces_newest = click_count > 2
+            || (newest_selection.start.to_display_point(&display_map) == position
+                && newest_selection.end.to_display_point(&display_map) == position);
         self.change_selections(auto_scroll.then(|| Autoscroll::newest()), cx, |s| {
             if !add {
                 s.clear_disjoint();
-            } else if click_count > 1 {
+            } else if click_count > 1 && replaces_newest {
                 s.delete(newest_selection.id)
             }
 
@@ -2061,6 +2224,18 @@ impl Editor {
         );
     }
 
+    fn begin_block_selection(
+        &mut self,
+        position: DisplayPoint,
+        add: bool,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.begin_selection(position, add, 1, cx);
+        if let Some(pending) = self.selections.pending_anchor() {
+            self.change_selections(None, cx, |s| s.set_pending(pending, SelectMode::Block));
+        }
+    }
+
     fn update_selection(
         &mut self,
         position: DisplayPoint,
@@ -2070,9 +2245,24 @@ impl Editor {
     ) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
 
+        let pending_block_tail = match self.selections.pending_mode() {
+            Some(SelectMode::Block) => self
+                .selections
+                .pending_anchor()
+                .map(|pending| pending.tail()),
+            _ => None,
+        };
+
         if let Some(tail) = self.columnar_selection_tail.as_ref() {
             let tail = tail.to_display_point(&display_map);
             self.select_columns(tail, position, goal_column, &display_map, cx);
+        } else if let Some(tail) = pending_block_tail {
+            // Dragging away from the click selects the same columns on every row
+            let tail_point = tail.to_display_point(&display_map);
+            if position != tail_point {
+                self.columnar_selection_tail = Some(tail);
+                self.select_columns(tail_point, position, goal_column, &display_map, cx);
+            }
         } else if let Some(mut pending) = self.selections.pending_anchor() {
             let buffer = self.buffer.read(cx).snapshot(cx);
             let head;
@@ -2088,17 +2278,12 @@ impl Editor {
                         ..original_range.end.to_display_point(&display_map);
                     let original_buffer_range = original_display_range.start.to_point(&display_map)
                         ..original_display_range.end.to_point(&display_map);
-                    if movement::is_inside_word(&display_map, position)
-                        || original_display_range.contains(&position)
-                    {
-                        let word_range = movement::surrounding_word(&display_map, position);
-                        if word_range.start < original_display_range.start {
-                            head = word_range.start.to_point(&display_map);
-                        } else {
-                            head = word_range.end.to_point(&display_map);
-                        }
+                    // Extend by whole words, including when the pointer is at a word's edge
+                    let word_range = movement::surrounding_word(&display_map, position);
+                    if word_range.start < original_display_range.start {
+                        head = word_range.start.to_point(&display_map);
                     } else {
-                        head = position.to_point(&display_map);
+                        head = word_range.end.to_point(&display_map);
                     }
 
                     if head <= original_buffer_range.start {
@@ -2131,7 +2316,7 @@ impl Editor {
                         tail = original_range.start;
                     }
                 }
-                SelectMode::All => {
+                SelectMode::All | SelectMode::Block => {
                     return;
                 }
             };
@@ -2209,6 +2394,88 @@ impl Editor {
         cx.notify();
     }
 
+    /// Starts dragging the selection under `position`, returning whether there was one.
+    fn begin_selection_drag(&mut self, position: DisplayPoint, cx: &mut ViewContext<Self>) -> bool {
+        if self.read_only(cx) {
+            return false;
+        }
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let offset = position.to_offset(&display_map, Bias::Left);
+        let Some(selection) = self
+            .selections
+            .disjoint_anchors()
+            .iter()
+            .find(|selection| {
+                let range = selection.range().to_offset(&display_map.buffer_snapshot);
+                range.start <= offset && offset < range.end
+            })
+            .cloned()
+        else {
+            return false;
+        };
+
+        if !self.focus_handle.is_focused(cx) {
+            cx.focus(&self.focus_handle);
+        }
+        self.selection_drag = Some(SelectionDrag {
+            selection,
+            origin: position,
+            drop_position: None,
+        });
+        true
+    }
+
+    fn update_selection_drag(&mut self, position: DisplayPoint, cx: &mut ViewContext<Self>) {
+        if let Some(drag) = self.selection_drag.as_mut() {
+            if drag.drop_position != Some(position) {
+                drag.drop_position = Some(position);
+                cx.notify();
+            }
+        }
+    }
+
+    /// Moves the dragged text to where it was dropped, or copies it there if `copy` is true.
+    fn end_selection_drag(&mut self, copy: bool, cx: &mut ViewContext<Self>) {
+        let Some(drag) = self.selection_drag.take() else {
+            return;
+        };
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
+        let range = drag.selection.range().to_offset(buffer);
+        let Some(drop_offset) = drag
+            .drop_position
+            .map(|position| position.to_offset(&display_map, Bias::Left))
+            .filter(|offset| *offset < range.start || *offset > range.end)
+        else {
+            // Releasing the mouse without dragging the text anywhere is a click
+            self.begin_selection(drag.origin, false, 1, cx);
+            self.end_selection(cx);
+            return;
+        };
+
+        let text = buffer.text_for_range(range.clone()).collect::<String>();
+        let dropped_range = buffer.anchor_before(drop_offset)..buffer.anchor_after(drop_offset);
+        let mut edits = vec![(drop_offset..drop_offset, text)];
+        if !copy {
+            edits.push((range, String::new()));
+        }
+        edits.sort_by_key(|(range, _)| range.start);
+
+        self.transact(cx, |this, cx| {
+            this.buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            this.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_anchor_ranges([dropped_range])
+            });
+        });
+    }
+
+    pub(crate) fn selection_drop_position(&self) -> Option<DisplayPoint> {
+        self.selection_drag.as_ref()?.drop_position
+    }
+
     pub fn has_pending_nonempty_selection(&self) -> bool {
         let pending_nonempty_selection = match self.selections.pending_anchor() {
             Some(Selection { start, end, .. }) => start != end,
@@ -2226,6 +2493,10 @@ impl Editor {
             return;
         }
 
+        if peek_definition::close_peek(self, cx) {
+            return;
+        }
+
         if hide_hover(self, cx) {
             return;
         }
@@ -2234,6 +2505,10 @@ impl Editor {
             return;
         }
 
+        if signature_help::hide_signature_help(self, cx) {
+            return;
+        }
+
         if self.discard_copilot_suggestion(cx) {
             return;
         }
@@ -2384,6 +2659,7 @@ impl Editor {
         }
 
         drop(snapshot);
+        let edits = linked_editing_ranges::with_linked_edits(self, edits, cx);
         self.transact(cx, |this, cx| {
             this.buffer.update(cx, |buffer, cx| {
                 buffer.edit(edits, this.autoindent_mode.clone(), cx);
@@ -2450,6 +2726,7 @@ impl Editor {
                 this.trigger_completion_on_input(&text, cx);
                 this.refresh_copilot_suggestions(true, cx);
             }
+            signature_help::trigger_signature_help_on_input(this, &text, cx);
         });
     }
 
@@ -2710,6 +2987,60 @@ impl Editor {
         });
     }
 
+    /// Makes the selected text available to middle-click paste, like other applications on Linux.
+    /// A selection which is still being made with the mouse is written once the mouse is
+    /// released, rather than on every step of the drag.
+    fn write_primary_selection(&self, cx: &mut ViewContext<Self>) {
+        if !cx.has_primary_selection()
+            || !self.focus_handle.is_focused(cx)
+            || self.selections.pending_anchor().is_some()
+        {
+            return;
+        }
+
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut text = String::new();
+        for selection in self.selections.disjoint_anchors().iter() {
+            let range = selection.range().to_offset(&buffer);
+            if range.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.extend(buffer.text_for_range(range));
+        }
+
+        if !text.is_empty() {
+            cx.write_to_primary(ClipboardItem::new(text));
+        }
+    }
+
+    /// Inserts `text` at `position`, moving the cursor there first, as middle-clicking with a
+    /// primary selection does.
+    pub fn paste_primary_selection_at(
+        &mut self,
+        position: DisplayPoint,
+        text: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self.read_only(cx) {
+            return;
+        }
+        if !self.focus_handle.is_focused(cx) {
+            cx.focus(&self.focus_handle);
+        }
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let point = display_map
+            .clip_point(position, Bias::Left)
+            .to_point(&display_map);
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.select_ranges([point..point])
+        });
+        self.insert(text, cx);
+    }
+
     pub fn insert(&mut self, text: &str, cx: &mut ViewContext<Self>) {
         self.insert_with_autoindent_mode(
             text,
@@ -2733,6 +3064,14 @@ impl Editor {
         let text: Arc<str> = text.into();
         self.transact(cx, |this, cx| {
             let old_selections = this.selections.all_adjusted(cx);
+            let edits = linked_editing_ranges::with_linked_edits(
+                this,
+                old_selections
+                    .iter()
+                    .map(|s| (s.start..s.end, text.clone()))
+                    .collect(),
+                cx,
+            );
             let selection_anchors = this.buffer.update(cx, |buffer, cx| {
                 let anchors = {
                     let snapshot = buffer.read(cx);
@@ -2744,13 +3083,7 @@ impl Editor {
                         })
                         .collect::<Vec<_>>()
                 };
-                buffer.edit(
-                    old_selections
-                        .iter()
-                        .map(|s| (s.start..s.end, text.clone())),
-                    autoindent_mode,
-                    cx,
-                );
+                buffer.edit(edits, autoindent_mode, cx);
                 anchors
             });
 
@@ -3407,6 +3740,112 @@ impl Editor {
         }))
     }
 
+    /// Applies the language server's quick fix for importing the unresolved symbol
+    /// under the cursor, if one is available.
+    pub fn add_import(
+        &mut self,
+        _: &AddImport,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let (buffer, action) = self.import_code_action(cx)?;
+        self.apply_code_action(buffer, action, cx)
+    }
+
+    /// Applies one of the language server's code actions for the given buffer.
+    pub(crate) fn apply_code_action(
+        &mut self,
+        buffer: Model<Buffer>,
+        action: CodeAction,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let title = action.lsp_action.title.clone();
+        let workspace = self.workspace()?;
+
+        let apply_code_action = workspace
+            .read(cx)
+            .project()
+            .clone()
+            .update(cx, |project, cx| {
+                project.apply_code_action(buffer, action, true, cx)
+            });
+        let workspace = workspace.downgrade();
+        Some(cx.spawn(|editor, cx| async move {
+            let project_transaction = apply_code_action.await?;
+            Self::open_project_transaction(&editor, workspace, project_transaction, title, cx).await
+        }))
+    }
+
+    /// Applies the language server's organize imports action to the whole document
+    /// under the cursor, sorting its imports and removing unused ones.
+    pub fn organize_imports(
+        &mut self,
+        _: &OrganizeImports,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let head = self.selections.newest_anchor().head();
+        let (buffer, _) = self.buffer.read(cx).text_anchor_for_position(head, cx)?;
+        let workspace = self.workspace()?;
+        let project = workspace.read(cx).project().clone();
+
+        let code_actions = project.update(cx, |project, cx| {
+            let len = buffer.read(cx).len();
+            project.code_actions(&buffer, 0..len, cx)
+        });
+        let workspace = workspace.downgrade();
+        Some(cx.spawn(|editor, mut cx| async move {
+            let Some(action) = code_actions
+                .await?
+                .into_iter()
+                .find(|action| is_organize_imports_action(&action.lsp_action))
+            else {
+                return Ok(());
+            };
+            let title = action.lsp_action.title.clone();
+            let project_transaction = project
+                .update(&mut cx, |project, cx| {
+                    project.apply_code_action(buffer, action, true, cx)
+                })?
+                .await?;
+            Self::open_project_transaction(&editor, workspace, project_transaction, title, cx).await
+        }))
+    }
+
+    /// Returns whether the code actions available at the cursor include one that
+    /// organizes imports.
+    pub(crate) fn has_organize_imports_action(&self) -> bool {
+        self.available_code_actions
+            .as_ref()
+            .map_or(false, |(_, actions)| {
+                actions
+                    .iter()
+                    .any(|action| is_organize_imports_action(&action.lsp_action))
+            })
+    }
+
+    /// Returns the quick fix that imports the symbol under the cursor. This is only
+    /// offered when the cursor is on an error diagnostic, so symbols that are
+    /// already in scope don't get one.
+    pub(crate) fn import_code_action(
+        &self,
+        cx: &AppContext,
+    ) -> Option<(Model<Buffer>, CodeAction)> {
+        let cursor = self.selections.newest::<usize>(cx).head();
+        let snapshot = self.buffer.read(cx).snapshot(cx);
+        let on_unresolved_symbol = snapshot
+            .diagnostics_in_range::<_, usize>(cursor..cursor, false)
+            .any(|entry| entry.diagnostic.severity == DiagnosticSeverity::ERROR);
+        if !on_unresolved_symbol {
+            return None;
+        }
+
+        let (buffer, actions) = self.available_code_actions.as_ref()?;
+        let action = actions.iter().find(|action| {
+            action.lsp_action.kind.as_ref() == Some(&lsp::CodeActionKind::QUICKFIX)
+                && action.lsp_action.title.to_lowercase().contains("import")
+        })?;
+        Some((buffer.clone(), action.clone()))
+    }
+
     async fn open_project_transaction(
         this: &WeakView<Editor>,
         workspace: WeakView<Workspace>,
@@ -3537,7 +3976,7 @@ impl Editor {
             return None;
         }
 
-        let project = self.project.clone()?;
+        let project = self.project.clone();
         let buffer = self.buffer.read(cx);
         let newest_selection = self.selections.newest_anchor().clone();
         let cursor_position = newest_selection.head();
@@ -3553,86 +3992,134 @@ impl Editor {
                 .timer(DOCUMENT_HIGHLIGHTS_DEBOUNCE_TIMEOUT)
                 .await;
 
-            let highlights = if let Some(highlights) = project
-                .update(&mut cx, |project, cx| {
-                    project.document_highlights(&cursor_buffer, cursor_buffer_position, cx)
-                })
-                .log_err()
-            {
+            let highlights = if let Some(highlights) = project.and_then(|project| {
+                project
+                    .update(&mut cx, |project, cx| {
+                        project.document_highlights(&cursor_buffer, cursor_buffer_position, cx)
+                    })
+                    .log_err()
+            }) {
                 highlights.await.log_err()
             } else {
                 None
             };
 
-            if let Some(highlights) = highlights {
-                this.update(&mut cx, |this, cx| {
-                    if this.pending_rename.is_some() {
-                        return;
-                    }
+            this.update(&mut cx, |this, cx| {
+                if this.pending_rename.is_some() {
+                    return;
+                }
 
-                    let buffer_id = cursor_position.buffer_id;
-                    let buffer = this.buffer.read(cx);
-                    if !buffer
-                        .text_anchor_for_position(cursor_position, cx)
-                        .map_or(false, |(buffer, _)| buffer == cursor_buffer)
-                    {
-                        return;
-                    }
+                let buffer_id = cursor_position.buffer_id;
+                let buffer = this.buffer.read(cx);
+                if !buffer
+                    .text_anchor_for_position(cursor_position, cx)
+                    .map_or(false, |(buffer, _)| buffer == cursor_buffer)
+                {
+                    return;
+                }
 
-                    let cursor_buffer_snapshot = cursor_buffer.read(cx);
-                    let mut write_ranges = Vec::new();
-                    let mut read_ranges = Vec::new();
-                    for highlight in highlights {
-                        for (excerpt_id, excerpt_range) in
-                            buffer.excerpts_for_buffer(&cursor_buffer, cx)
-                        {
-                            let start = highlight
-                                .range
-                                .start
-                                .max(&excerpt_range.context.start, cursor_buffer_snapshot);
-                            let end = highlight
-                                .range
-                                .end
-                                .min(&excerpt_range.context.end, cursor_buffer_snapshot);
-                            if start.cmp(&end, cursor_buffer_snapshot).is_ge() {
-                                continue;
-                            }
+                // Without language servers that provide highlights, highlight the
+                // identifiers with the same name instead
+                let highlights = match highlights {
+                    Some(highlights) if !highlights.is_empty() => highlights,
+                    _ => this.identifier_highlights_in_visible_range(
+                        &cursor_buffer,
+                        cursor_buffer_position,
+                        cx,
+                    ),
+                };
+                let buffer = this.buffer.read(cx);
+                let cursor_buffer_snapshot = cursor_buffer.read(cx);
+                let mut write_ranges = Vec::new();
+                let mut read_ranges = Vec::new();
+                for highlight in highlights {
+                    for (excerpt_id, excerpt_range) in
+                        buffer.excerpts_for_buffer(&cursor_buffer, cx)
+                    {
+                        let start = highlight
+                            .range
+                            .start
+                            .max(&excerpt_range.context.start, cursor_buffer_snapshot);
+                        let end = highlight
+                            .range
+                            .end
+                            .min(&excerpt_range.context.end, cursor_buffer_snapshot);
+                        if start.cmp(&end, cursor_buffer_snapshot).is_ge() {
+                            continue;
+                        }
 
-                            let range = Anchor {
-                                buffer_id,
-                                excerpt_id: excerpt_id.clone(),
-                                text_anchor: start,
-                            }..Anchor {
-                                buffer_id,
-                                excerpt_id,
-                                text_anchor: end,
-                            };
-                            if highlight.kind == lsp::DocumentHighlightKind::WRITE {
-                                write_ranges.push(range);
-                            } else {
-                                read_ranges.push(range);
-                            }
+                        let range = Anchor {
+                            buffer_id,
+                            excerpt_id: excerpt_id.clone(),
+                            text_anchor: start,
+                        }..Anchor {
+                            buffer_id,
+                            excerpt_id,
+                            text_anchor: end,
+                        };
+                        if highlight.kind == lsp::DocumentHighlightKind::WRITE {
+                            write_ranges.push(range);
+                        } else {
+                            read_ranges.push(range);
                         }
                     }
+                }
 
-                    this.highlight_background::<DocumentHighlightRead>(
-                        read_ranges,
-                        |theme| theme.editor_document_highlight_read_background,
-                        cx,
-                    );
-                    this.highlight_background::<DocumentHighlightWrite>(
-                        write_ranges,
-                        |theme| theme.editor_document_highlight_write_background,
-                        cx,
-                    );
-                    cx.notify();
-                })
-                .log_err();
-            }
+                this.highlight_background::<DocumentHighlightRead>(
+                    read_ranges,
+                    |theme| theme.editor_document_highlight_read_background,
+                    cx,
+                );
+                this.highlight_background::<DocumentHighlightWrite>(
+                    write_ranges,
+                    |theme| theme.editor_document_highlight_write_background,
+                    cx,
+                );
+                cx.notify();
+            })
+            .log_err();
         }));
         None
     }
 
+    /// The identifiers in the visible part of `buffer` that have the same name as the one
+    /// at `position`.
+    fn identifier_highlights_in_visible_range(
+        &self,
+        buffer: &Model<Buffer>,
+        position: text::Anchor,
+        cx: &AppContext,
+    ) -> Vec<DocumentHighlight> {
+        use language::ToOffset as _;
+
+        let multi_buffer = self.buffer.read(cx);
+        let multi_buffer_snapshot = multi_buffer.snapshot(cx);
+        let visible_start = self
+            .scroll_manager
+            .anchor()
+            .anchor
+            .to_point(&multi_buffer_snapshot);
+        let visible_end = multi_buffer_snapshot.clip_point(
+            visible_start + Point::new(self.visible_line_count().unwrap_or(0.).ceil() as u32, 0),
+            Bias::Left,
+        );
+
+        let snapshot = buffer.read(cx).snapshot();
+        let position = position.to_offset(&snapshot);
+        multi_buffer
+            .range_to_buffer_ranges(visible_start..visible_end, cx)
+            .into_iter()
+            .filter(|(excerpt_buffer, _, _)| excerpt_buffer == buffer)
+            .flat_map(|(_, visible_range, _)| {
+                snapshot.identifier_occurrences(position, visible_range)
+            })
+            .map(|range| DocumentHighlight {
+                range: snapshot.anchor_after(range.start)..snapshot.anchor_before(range.end),
+                kind: lsp::DocumentHighlightKind::TEXT,
+            })
+            .collect()
+    }
+
     fn refresh_copilot_suggestions(
         &mut self,
         debounce: bool,
@@ -5046,17 +5533,18 @@ impl Editor {
 
     pub fn cut(&mut self, _: &Cut, cx: &mut ViewContext<Self>) {
         let mut text = String::new();
-        let buffer = self.buffer.read(cx).snapshot(cx);
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
         let mut selections = self.selections.all::<Point>(cx);
         let mut clipboard_selections = Vec::with_capacity(selections.len());
         {
-            let max_point = buffer.max_point();
             let mut is_first = true;
             for selection in &mut selections {
                 let is_entire_line = selection.is_empty() || self.selections.line_mode;
                 if is_entire_line {
-                    selection.start = Point::new(selection.start.row, 0);
-                    selection.end = cmp::min(max_point, Point::new(selection.end.row + 1, 0));
+                    let lines = entire_lines(&display_map, selection.range());
+                    selection.start = lines.start;
+                    selection.end = lines.end;
                     selection.goal = SelectionGoal::None;
                 }
                 if is_first {
@@ -5082,26 +5570,33 @@ impl Editor {
                 s.select(selections);
             });
             this.insert("", cx);
+            clipboard_history::record_clipboard_entry(
+                this,
+                text.clone(),
+                clipboard_selections.clone(),
+                cx,
+            );
             cx.write_to_clipboard(ClipboardItem::new(text).with_metadata(clipboard_selections));
         });
     }
 
     pub fn copy(&mut self, _: &Copy, cx: &mut ViewContext<Self>) {
         let selections = self.selections.all::<Point>(cx);
-        let buffer = self.buffer.read(cx).read(cx);
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+        let buffer = &display_map.buffer_snapshot;
         let mut text = String::new();
 
         let mut clipboard_selections = Vec::with_capacity(selections.len());
         {
-            let max_point = buffer.max_point();
             let mut is_first = true;
             for selection in selections.iter() {
                 let mut start = selection.start;
                 let mut end = selection.end;
                 let is_entire_line = selection.is_empty() || self.selections.line_mode;
                 if is_entire_line {
-                    start = Point::new(start.row, 0);
-                    end = cmp::min(max_point, Point::new(end.row + 1, 0));
+                    let lines = entire_lines(&display_map, start..end);
+                    start = lines.start;
+                    end = lines.end;
                 }
                 if is_first {
                     is_first = false;
@@ -5121,6 +5616,12 @@ impl Editor {
             }
         }
 
+        clipboard_history::record_clipboard_entry(
+            self,
+            text.clone(),
+            clipboard_selections.clone(),
+            cx,
+        );
         cx.write_to_clipboard(ClipboardItem::new(text).with_metadata(clipboard_selections));
     }
 
@@ -6725,44 +7226,30 @@ impl Editor {
     ) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = self.buffer.read(cx).snapshot(cx);
-        let old_selections = self.selections.all::<usize>(cx).into_boxed_slice();
-
-        let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
-        let mut selected_larger_node = false;
-        let new_selections = old_selections
-            .iter()
-            .map(|selection| {
-                let old_range = selection.start..selection.end;
-                let mut new_range = old_range.clone();
-                while let Some(containing_range) =
-                    buffer.range_for_syntax_ancestor(new_range.clone())
+        let old_selections = self.selections.all::<usize>(cx);
+
+        let mut new_ranges = HashMap::default();
+        let mut unexpanded = Vec::new();
+        for selection in &old_selections {
+            let old_range = selection.start..selection.end;
+            let mut new_range = old_range.clone();
+            while let Some(containing_range) = buffer.range_for_syntax_ancestor(new_range.clone()) {
+                new_range = containing_range;
+                if !display_map.intersects_fold(new_range.start)
+                    && !display_map.intersects_fold(new_range.end)
                 {
-                    new_range = containing_range;
-                    if !display_map.intersects_fold(new_range.start)
-                        && !display_map.intersects_fold(new_range.end)
-                    {
-                        break;
-                    }
-                }
-
-                selected_larger_node |= new_range != old_range;
-                Selection {
-                    id: selection.id,
-                    start: new_range.start,
-                    end: new_range.end,
-                    goal: SelectionGoal::None,
-                    reversed: selection.reversed,
+                    break;
                 }
-            })
-            .collect::<Vec<_>>();
+            }
 
-        if selected_larger_node {
-            stack.push(old_selections);
-            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
-                s.select(new_selections);
-            });
+            if new_range == old_range {
+                unexpanded.push(selection.clone());
+            } else {
+                new_ranges.insert(selection.id, new_range);
+            }
         }
-        self.select_larger_syntax_node_stack = stack;
+
+        selection_ranges::select_larger_ranges(self, old_selections, new_ranges, unexpanded, cx);
     }
 
     pub fn select_smaller_syntax_node(
@@ -6770,6 +7257,7 @@ impl Editor {
         _: &SelectSmallerSyntaxNode,
         cx: &mut ViewContext<Self>,
     ) {
+        self.selection_ranges_task = None;
         let mut stack = mem::take(&mut self.select_larger_syntax_node_stack);
         if let Some(selections) = stack.pop() {
             self.change_selections(Some(Autoscroll::fit()), cx, |s| {
@@ -6786,55 +7274,28 @@ impl Editor {
     ) {
         self.change_selections(Some(Autoscroll::fit()), cx, |s| {
             s.move_offsets_with(|snapshot, selection| {
-                let Some(enclosing_bracket_ranges) =
-                    snapshot.enclosing_bracket_ranges(selection.start..selection.end)
-                else {
-                    return;
-                };
-
-                let mut best_length = usize::MAX;
-                let mut best_inside = false;
-                let mut best_in_bracket_range = false;
-                let mut best_destination = None;
-                for (open, close) in enclosing_bracket_ranges {
-                    let close = close.to_inclusive();
-                    let length = close.end() - open.start;
-                    let inside = selection.start >= open.end && selection.end <= *close.start();
-                    let in_bracket_range = open.to_inclusive().contains(&selection.head())
-                        || close.contains(&selection.head());
-
-                    // If best is next to a bracket and current isn't, skip
-                    if !in_bracket_range && best_in_bracket_range {
-                        continue;
-                    }
-
-                    // Prefer smaller lengths unless best is inside and current isn't
-                    if length > best_length && (best_inside || !inside) {
-                        continue;
-                    }
-
-                    best_length = length;
-                    best_inside = inside;
-                    best_in_bracket_range = in_bracket_range;
-                    best_destination = Some(
-                        if close.contains(&selection.start) && close.contains(&selection.end) {
-                            if inside {
-                                open.end
-                            } else {
-                                open.start
-                            }
-                        } else {
-                            if inside {
-                                *close.start()
-                            } else {
-                                *close.end()
-                            }
-                        },
-                    );
+                if let Some(destination) = enclosing_bracket_destination(
+                    snapshot,
+                    selection.start..selection.end,
+                    selection.head(),
+                ) {
+                    selection.collapse_to(destination, SelectionGoal::None);
                 }
+            })
+        });
+    }
 
-                if let Some(destination) = best_destination {
-                    selection.collapse_to(destination, SelectionGoal::None);
+    pub fn select_to_enclosing_bracket(
+        &mut self,
+        _: &SelectToEnclosingBracket,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+            s.move_offsets_with(|snapshot, selection| {
+                let head = selection.head();
+                if let Some(destination) = enclosing_bracket_destination(snapshot, head..head, head)
+                {
+                    selection.set_head(destination, SelectionGoal::None);
                 }
             })
         });
@@ -7049,10 +7510,58 @@ impl Editor {
         }
     }
 
+    fn go_to_next_highlight(&mut self, _: &GoToNextHighlight, cx: &mut ViewContext<Self>) {
+        self.go_to_highlight_impl(Direction::Next, cx)
+    }
+
+    fn go_to_previous_highlight(&mut self, _: &GoToPreviousHighlight, cx: &mut ViewContext<Self>) {
+        self.go_to_highlight_impl(Direction::Prev, cx)
+    }
+
+    /// Moves the cursor to the next or previous document highlight of the symbol under it,
+    /// cycling around at the ends.
+    fn go_to_highlight_impl(&mut self, direction: Direction, cx: &mut ViewContext<Self>) {
+        let buffer = self.buffer.read(cx).snapshot(cx);
+        let mut ranges = [
+            TypeId::of::<DocumentHighlightRead>(),
+            TypeId::of::<DocumentHighlightWrite>(),
+        ]
+        .iter()
+        .filter_map(|type_id| self.background_highlights.get(type_id))
+        .flat_map(|(_, ranges)| ranges.iter())
+        .map(|range| range.to_offset(&buffer))
+        .collect::<Vec<_>>();
+        ranges.sort_unstable_by_key(|range| range.start);
+        ranges.dedup();
+
+        let head = self.selections.newest::<usize>(cx).head();
+        let target = match direction {
+            Direction::Next => ranges
+                .iter()
+                .find(|range| range.start > head)
+                .or(ranges.first()),
+            Direction::Prev => ranges
+                .iter()
+                .rev()
+                .find(|range| range.end < head)
+                .or(ranges.last()),
+        };
+        if let Some(target) = target {
+            let position = target.start;
+            self.change_selections(Some(Autoscroll::fit()), cx, |s| {
+                s.select_ranges([position..position])
+            });
+        }
+    }
+
     pub fn go_to_definition(&mut self, _: &GoToDefinition, cx: &mut ViewContext<Self>) {
         self.go_to_definition_of_kind(GotoDefinitionKind::Symbol, false, cx);
     }
 
+    pub fn go_to_declaration(&mut self, _: &GoToDeclaration, cx: &mut ViewContext<Self>) {
+        self.go_to_definition_of_kind(GotoDefinitionKind::Declaration, false, cx);
+    }
+
     pub fn go_to_type_definition(&mut self, _: &GoToTypeDefinition, cx: &mut ViewContext<Self>) {
         self.go_to_definition_of_kind(GotoDefinitionKind::Type, false, cx);
     }
@@ -7069,6 +7578,10 @@ impl Editor {
         self.go_to_definition_of_kind(GotoDefinitionKind::Type, true, cx);
     }
 
+    pub fn go_to_implementation(&mut self, _: &GoToImplementation, cx: &mut ViewContext<Self>) {
+        self.go_to_definition_of_kind(GotoDefinitionKind::Implementation, false, cx);
+    }
+
     fn go_to_definition_of_kind(
         &mut self,
         kind: GotoDefinitionKind,
@@ -7089,7 +7602,9 @@ impl Editor {
         let project = workspace.read(cx).project().clone();
         let definitions = project.update(cx, |project, cx| match kind {
             GotoDefinitionKind::Symbol => project.definition(&buffer, head, cx),
+            GotoDefinitionKind::Declaration => project.declaration(&buffer, head, cx),
             GotoDefinitionKind::Type => project.type_definition(&buffer, head, cx),
+            GotoDefinitionKind::Implementation => project.implementation(&buffer, head, cx),
         });
 
         cx.spawn(|editor, mut cx| async move {
@@ -7134,7 +7649,10 @@ impl Editor {
                     editor.update(&mut cx, |editor, cx| {
                         let range = target.range.to_offset(target.buffer.read(cx));
                         let range = editor.range_for_match(&range);
-                        if Some(&target.buffer) == editor.buffer.read(cx).as_singleton().as_ref() {
+                        if !split
+                            && Some(&target.buffer)
+                                == editor.buffer.read(cx).as_singleton().as_ref()
+                        {
                             editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
                                 s.select_ranges([range]);
                             });
@@ -7312,15 +7830,61 @@ impl Editor {
                         )
                     })
                     .unwrap();
-                Self::open_locations_in_multibuffer(
-                    workspace, locations, replica_id, title, false, cx,
-                );
+                if EditorSettings::get_global(cx).references_display == ReferencesDisplay::Panel {
+                    Self::show_references_in_panel(workspace, locations, title, cx);
+                } else {
+                    Self::open_locations_in_multibuffer(
+                        workspace, locations, replica_id, title, false, cx,
+                    );
+                }
             })?;
 
             Ok(())
         }))
     }
 
+    pub fn show_call_hierarchy(
+        &mut self,
+        _: &ShowCallHierarchy,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let buffer = self.buffer.read(cx);
+        let head = self.selections.newest::<usize>(cx).head();
+        let (buffer, head) = buffer.text_anchor_for_position(head, cx)?;
+
+        let workspace = self.workspace()?;
+        let project = workspace.read(cx).project().clone();
+        let items = project.update(cx, |project, cx| {
+            project.prepare_call_hierarchy(&buffer, head, cx)
+        });
+        Some(cx.spawn(|_, mut cx| async move {
+            let items = items.await?;
+            if items.is_empty() {
+                return Ok(());
+            }
+
+            workspace.update(&mut cx, |workspace, cx| {
+                call_hierarchy_panel::show_call_hierarchy(workspace, items, cx);
+            })?;
+            Ok(())
+        }))
+    }
+
+    fn show_references_in_panel(
+        workspace: &mut Workspace,
+        locations: Vec<Location>,
+        title: String,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let panel = workspace.panel::<ReferencesPanel>(cx).unwrap_or_else(|| {
+            let panel = cx.new_view(|cx| ReferencesPanel::new(cx.view().downgrade(), cx));
+            workspace.add_panel(panel.clone(), cx);
+            panel
+        });
+        panel.update(cx, |panel, cx| panel.set_locations(title, locations, cx));
+        workspace.focus_panel::<ReferencesPanel>(cx);
+    }
+
     /// Opens a multibuffer with the given project locations in it
     pub fn open_locations_in_multibuffer(
         workspace: &mut Workspace,
@@ -7560,23 +8124,40 @@ impl Editor {
         let range = start..end;
         let old_name = rename.old_name;
         let new_name = rename.editor.read(cx).text(cx);
+        let title = format!("Rename: {} → {}", old_name, new_name);
 
-        let rename = workspace
-            .read(cx)
-            .project()
-            .clone()
-            .update(cx, |project, cx| {
-                project.perform_rename(buffer.clone(), range.start, new_name.clone(), true, cx)
-            });
+        let project = workspace.read(cx).project().clone();
+        let rename_edits = project.update(cx, |project, cx| {
+            project.rename_edits(buffer.clone(), range.start, new_name.clone(), cx)
+        });
         let workspace = workspace.downgrade();
 
         Some(cx.spawn(|editor, mut cx| async move {
-            let project_transaction = rename.await?;
+            let project_transaction = match rename_edits.await? {
+                // Renames across files are previewed, so that some occurrences can be left out
+                Some(edits) if edits.len() > 1 => {
+                    let preview_workspace = workspace.clone();
+                    workspace.update(&mut cx, |workspace, cx| {
+                        workspace.toggle_modal(cx, |cx| {
+                            RenamePreview::new(title, edits, editor, preview_workspace, cx)
+                        })
+                    })?;
+                    return Ok(());
+                }
+                Some(edits) => cx.update(|cx| rename_preview::apply_rename_edits(edits, cx))?,
+                None => {
+                    project
+                        .update(&mut cx, |project, cx| {
+                            project.perform_rename(buffer, range.start, new_name, true, cx)
+                        })?
+                        .await?
+                }
+            };
             Self::open_project_transaction(
                 &editor,
                 workspace,
                 project_transaction,
-                format!("Rename: {} → {}", old_name, new_name),
+                title,
                 cx.clone(),
             )
             .await?;
@@ -7643,17 +8224,56 @@ impl Editor {
         Some(self.perform_format(project, FormatTrigger::Manual, cx))
     }
 
+    /// Formats the selected text with the language server's range formatting. Empty
+    /// selections format the line they're on.
+    fn format_selection(
+        &mut self,
+        _: &FormatSelection,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let project = self.project.clone()?;
+        let multi_buffer = self.buffer.read(cx);
+        let snapshot = multi_buffer.snapshot(cx);
+        let mut ranges = HashMap::<_, Vec<_>>::default();
+        for selection in self.selections.all::<Point>(cx) {
+            let range = if selection.is_empty() {
+                let row = selection.head().row;
+                Point::new(row, 0)..Point::new(row, snapshot.line_len(row))
+            } else {
+                selection.range()
+            };
+            for (buffer, range, _) in multi_buffer.range_to_buffer_ranges(range, cx) {
+                let buffer_snapshot = buffer.read(cx);
+                let range = buffer_snapshot.anchor_before(range.start)
+                    ..buffer_snapshot.anchor_after(range.end);
+                ranges.entry(buffer).or_default().push(range);
+            }
+        }
+
+        let format = project.update(cx, |project, cx| project.format_ranges(ranges, true, cx));
+        Some(self.finish_format(format, cx))
+    }
+
     fn perform_format(
         &mut self,
         project: Model<Project>,
         trigger: FormatTrigger,
         cx: &mut ViewContext<Self>,
     ) -> Task<Result<()>> {
-        let buffer = self.buffer().clone();
-        let buffers = buffer.read(cx).all_buffers();
+        let buffers = self.buffer().read(cx).all_buffers();
+        let format = project.update(cx, |project, cx| project.format(buffers, true, trigger, cx));
+        self.finish_format(format, cx)
+    }
 
+    // Waits for the formatting to finish, giving up after `FORMAT_TIMEOUT`, and adds its
+    // edits to the multi-buffer's undo history.
+    fn finish_format(
+        &mut self,
+        format: Task<Result<ProjectTransaction>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let buffer = self.buffer().clone();
         let mut timeout = cx.background_executor().timer(FORMAT_TIMEOUT).fuse();
-        let format = project.update(cx, |project, cx| project.format(buffers, true, trigger, cx));
 
         cx.spawn(|_, mut cx| async move {
             let transaction = futures::select_biased! {
@@ -7898,6 +8518,58 @@ impl Editor {
         }
     }
 
+    pub fn fold_recursive(&mut self, _: &actions::FoldRecursive, cx: &mut ViewContext<Self>) {
+        let mut fold_ranges = Vec::new();
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+
+        let selections = self.selections.all_adjusted(cx);
+        for selection in selections {
+            let buffer_start_row = selection.range().sorted().start.row;
+            let enclosing_range = (0..=buffer_start_row).rev().find_map(|row| {
+                display_map
+                    .foldable_range(row)
+                    .filter(|fold_range| fold_range.end.row >= buffer_start_row)
+            });
+
+            if let Some(enclosing_range) = enclosing_range {
+                for row in enclosing_range.start.row + 1..enclosing_range.end.row {
+                    fold_ranges.extend(display_map.foldable_range(row));
+                }
+                fold_ranges.push(enclosing_range);
+            }
+        }
+
+        self.fold_ranges(fold_ranges, true, cx);
+    }
+
+    /// Folds every range nested at the given depth, where the outermost ranges are at level 1.
+    pub fn fold_at_level(&mut self, fold_at: &FoldAtLevel, cx: &mut ViewContext<Self>) {
+        let mut fold_ranges = Vec::new();
+
+        let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
+
+        let mut stack = vec![(0, display_map.max_buffer_row(), 1)];
+        while let Some((mut row, end_row, level)) = stack.pop() {
+            while row <= end_row {
+                if display_map.is_line_folded(row) {
+                    row = display_map.next_line_boundary(Point::new(row, 0)).0.row + 1;
+                } else if let Some(fold_range) = display_map.foldable_range(row) {
+                    if level < fold_at.level {
+                        stack.push((row + 1, fold_range.end.row, level + 1));
+                    } else if level == fold_at.level {
+                        fold_ranges.push(fold_range.clone());
+                    }
+                    row = fold_range.end.row + 1;
+                } else {
+                    row += 1;
+                }
+            }
+        }
+
+        self.fold_ranges(fold_ranges, true, cx);
+    }
+
     pub fn unfold_lines(&mut self, _: &UnfoldLines, cx: &mut ViewContext<Self>) {
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
         let buffer = &display_map.buffer_snapshot;
@@ -7935,6 +8607,11 @@ impl Editor {
         self.unfold_ranges(std::iter::once(intersection_range), true, autoscroll, cx)
     }
 
+    pub fn unfold_all(&mut self, _: &actions::UnfoldAll, cx: &mut ViewContext<Self>) {
+        let len = self.buffer.read(cx).len(cx);
+        self.unfold_ranges([0..len], true, true, cx);
+    }
+
     pub fn fold_selected_ranges(&mut self, _: &FoldSelectedRanges, cx: &mut ViewContext<Self>) {
         let selections = self.selections.all::<Point>(cx);
         let display_map = self.display_map.update(cx, |map, cx| map.snapshot(cx));
@@ -7965,6 +8642,7 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            file_state::queue_file_state_save(self, cx);
             cx.notify();
         }
     }
@@ -7984,6 +8662,7 @@ impl Editor {
                 self.request_autoscroll(Autoscroll::fit(), cx);
             }
 
+            file_state::queue_file_state_save(self, cx);
             cx.notify();
         }
     }
@@ -8176,6 +8855,20 @@ impl Editor {
         }
     }
 
+    pub fn open_in_terminal(&mut self, _: &OpenInTerminal, cx: &mut ViewContext<Self>) {
+        if let Some(working_directory) = self.local_file_directory(cx) {
+            cx.dispatch_action(workspace::OpenTerminal { working_directory }.boxed_clone());
+        }
+    }
+
+    /// Returns the directory containing this editor's file, if it is a saved file in a
+    /// local project.
+    pub(crate) fn local_file_directory(&self, cx: &AppContext) -> Option<PathBuf> {
+        let buffer = self.buffer().read(cx).as_singleton()?;
+        let file = buffer.read(cx).file()?.as_local()?;
+        Some(file.abs_path(cx).parent()?.to_path_buf())
+    }
+
     pub fn copy_path(&mut self, _: &CopyPath, cx: &mut ViewContext<Self>) {
         if let Some(buffer) = self.buffer().read(cx).as_singleton() {
             if let Some(file) = buffer.read(cx).file().and_then(|f| f.as_local()) {
@@ -8186,6 +8879,35 @@ impl Editor {
         }
     }
 
+    /// Copies the path of the file under the cursor, followed by the names of the
+    /// document symbols enclosing the cursor, e.g. `src/main.rs › mod app › fn run`.
+    pub fn copy_breadcrumbs(
+        &mut self,
+        _: &CopyBreadcrumbs,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<Task<Result<()>>> {
+        let project = self.project.clone()?;
+        let head = self.selections.newest_anchor().head();
+        let (buffer, position) = self.buffer.read(cx).text_anchor_for_position(head, cx)?;
+        let include_root = project.read(cx).visible_worktrees(cx).count() > 1;
+        let file_path = buffer
+            .read(cx)
+            .snapshot()
+            .resolve_file_path(cx, include_root)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|| "untitled".to_string());
+        let enclosing_symbols = project.update(cx, |project, cx| {
+            project.enclosing_symbols(&buffer, position, cx)
+        });
+        Some(cx.spawn(|_, mut cx| async move {
+            let breadcrumbs = std::iter::once(file_path)
+                .chain(enclosing_symbols.await?)
+                .collect::<Vec<_>>()
+                .join(" › ");
+            cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new(breadcrumbs)))
+        }))
+    }
+
     pub fn copy_relative_path(&mut self, _: &CopyRelativePath, cx: &mut ViewContext<Self>) {
         if let Some(buffer) = self.buffer().read(cx).as_singleton() {
             if let Some(file) = buffer.read(cx).file().and_then(|f| f.as_local()) {
@@ -8471,6 +9193,12 @@ impl Editor {
             } => {
                 self.refresh_active_diagnostics(cx);
                 self.refresh_code_actions(cx);
+                doc_comment::refresh_function_symbol(self, cx);
+                code_lens::refresh_code_lenses(self, true, cx);
+                semantic_tokens::refresh_semantic_tokens(self, true, cx);
+                folding_ranges::refresh_folding_ranges(self, true, cx);
+                blame::refresh_blame(self, true, cx);
+                spell_check::refresh_spell_check(self, true, cx);
                 if self.has_active_copilot_suggestion(cx) {
                     self.update_visible_copilot_suggestion(cx);
                 }
@@ -8521,18 +9249,32 @@ impl Editor {
                     excerpts: excerpts.clone(),
                 });
                 self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
+                spell_check::refresh_spell_check(self, true, cx);
             }
             multi_buffer::Event::ExcerptsRemoved { ids } => {
                 self.refresh_inlay_hints(InlayHintRefreshReason::ExcerptsRemoved(ids.clone()), cx);
                 cx.emit(EditorEvent::ExcerptsRemoved { ids: ids.clone() })
             }
-            multi_buffer::Event::Reparsed => cx.emit(EditorEvent::Reparsed),
+            multi_buffer::Event::Reparsed => {
+                // Which ranges are checked depends on the syntax tree
+                spell_check::refresh_spell_check(self, true, cx);
+                cx.emit(EditorEvent::Reparsed)
+            }
             multi_buffer::Event::DirtyChanged => cx.emit(EditorEvent::DirtyChanged),
-            multi_buffer::Event::Saved => cx.emit(EditorEvent::Saved),
+            multi_buffer::Event::Saved => {
+                code_lens::refresh_code_lenses(self, false, cx);
+                cx.emit(EditorEvent::Saved)
+            }
             multi_buffer::Event::FileHandleChanged | multi_buffer::Event::Reloaded => {
+                blame::refresh_blame(self, false, cx);
                 cx.emit(EditorEvent::TitleChanged)
             }
-            multi_buffer::Event::DiffBaseChanged => cx.emit(EditorEvent::DiffBaseChanged),
+            multi_buffer::Event::DiffBaseChanged => {
+                // The index changes when lines are committed, which changes their blame
+                blame::refresh_blame(self, false, cx);
+                hunk_diff::clear_hunk_diffs(self, cx);
+                cx.emit(EditorEvent::DiffBaseChanged)
+            }
             multi_buffer::Event::Closed => cx.emit(EditorEvent::Closed),
             multi_buffer::Event::DiagnosticsUpdated => {
                 self.refresh_active_diagnostics(cx);
@@ -8547,6 +9289,10 @@ impl Editor {
 
     fn settings_changed(&mut self, cx: &mut ViewContext<Self>) {
         self.refresh_copilot_suggestions(true, cx);
+        code_lens::refresh_code_lenses(self, false, cx);
+        semantic_tokens::refresh_semantic_tokens(self, false, cx);
+        blame::blame_settings_changed(self, cx);
+        spell_check::refresh_spell_check(self, false, cx);
         self.refresh_inlay_hints(
             InlayHintRefreshReason::SettingsChange(inlay_hint_settings(
                 self.selections.newest_anchor().head(),
@@ -8929,7 +9675,10 @@ impl Editor {
             self.show_cursor_names(cx);
             self.buffer.update(cx, |buffer, cx| {
                 buffer.finalize_last_transaction(cx);
-                if self.leader_peer_id.is_none() {
+                // Returning from a context menu that was dismissed without choosing an
+                // item keeps the cursor's move local
+                if !mem::take(&mut self.defer_selection_broadcast) && self.leader_peer_id.is_none()
+                {
                     buffer.set_active_selections(
                         &self.selections.disjoint_anchors(),
                         self.selections.line_mode,
@@ -8943,10 +9692,15 @@ impl Editor {
 
     pub fn handle_blur(&mut self, cx: &mut ViewContext<Self>) {
         self.blink_manager.update(cx, BlinkManager::disable);
-        self.buffer
-            .update(cx, |buffer, cx| buffer.remove_active_selections(cx));
+        if !self.defer_selection_broadcast {
+            self.buffer
+                .update(cx, |buffer, cx| buffer.remove_active_selections(cx));
+        }
         self.hide_context_menu(cx);
-        hide_hover(self, cx);
+        // Focus moves into the hover popover to scroll it
+        if !self.hover_state.focused(cx) {
+            hide_hover(self, cx);
+        }
         cx.emit(EditorEvent::Blurred);
         cx.notify();
     }
@@ -9075,6 +9829,18 @@ fn inlay_hint_settings(
         .inlay_hints
 }
 
+/// Returns whether `kind` is `source.organizeImports`, or one of its sub-kinds.
+pub(crate) fn is_organize_imports_kind(kind: &lsp::CodeActionKind) -> bool {
+    let organize_imports = lsp::CodeActionKind::SOURCE_ORGANIZE_IMPORTS.as_str();
+    kind.as_str()
+        .strip_prefix(organize_imports)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+fn is_organize_imports_action(action: &lsp::CodeAction) -> bool {
+    action.kind.as_ref().map_or(false, is_organize_imports_kind)
+}
+
 fn consume_contiguous_rows(
     contiguous_row_selections: &mut Vec<Selection<Point>>,
     selection: &Selection<Point>,
@@ -9096,6 +9862,14 @@ fn consume_contiguous_rows(
     (start_row, end_row)
 }
 
+/// The whole lines spanned by the given range, including the lines hidden by any folds on them.
+fn entire_lines(display_map: &DisplaySnapshot, range: Range<Point>) -> Range<Point> {
+    let start = display_map.prev_line_boundary(range.start).0;
+    let end = display_map.next_line_boundary(range.end).0;
+    let max_point = display_map.buffer_snapshot.max_point();
+    Point::new(start.row, 0)..cmp::min(max_point, Point::new(end.row + 1, 0))
+}
+
 fn ending_row(next_selection: &Selection<Point>, display_map: &DisplaySnapshot) -> u32 {
     if next_selection.end.column > 0 || next_selection.is_empty() {
         display_map.next_line_boundary(next_selection.end).0.row + 1
@@ -9104,6 +9878,58 @@ fn ending_row(next_selection: &Selection<Point>, display_map: &DisplaySnapshot)
     }
 }
 
+/// Where the cursor moves to in order to jump to the bracket matching the one it's
+/// next to, or to the other end of the innermost bracket pair it's in.
+fn enclosing_bracket_destination(
+    snapshot: &MultiBufferSnapshot,
+    range: Range<usize>,
+    head: usize,
+) -> Option<usize> {
+    let enclosing_bracket_ranges = snapshot.enclosing_bracket_ranges(range.clone())?;
+
+    let mut best_length = usize::MAX;
+    let mut best_inside = false;
+    let mut best_in_bracket_range = false;
+    let mut best_destination = None;
+    for (open, close) in enclosing_bracket_ranges {
+        let close = close.to_inclusive();
+        let length = close.end() - open.start;
+        let inside = range.start >= open.end && range.end <= *close.start();
+        let in_bracket_range = open.to_inclusive().contains(&head) || close.contains(&head);
+
+        // If best is next to a bracket and current isn't, skip
+        if !in_bracket_range && best_in_bracket_range {
+            continue;
+        }
+
+        // Prefer smaller lengths unless best is inside and current isn't
+        if length > best_length && (best_inside || !inside) {
+            continue;
+        }
+
+        best_length = length;
+        best_inside = inside;
+        best_in_bracket_range = in_bracket_range;
+        best_destination = Some(
+            if close.contains(&range.start) && close.contains(&range.end) {
+                if inside {
+                    open.end
+                } else {
+                    open.start
+                }
+            } else {
+                if inside {
+                    *close.start()
+                } else {
+                    *close.end()
+                }
+            },
+        );
+    }
+
+    best_destination
+}
+
 impl EditorSnapshot {
     pub fn remote_selections_in_range<'a>(
         &'a self,
@@ -9511,6 +10337,21 @@ impl ViewInputHandler for Editor {
         element_bounds: gpui::Bounds<Pixels>,
         cx: &mut ViewContext<Self>,
     ) -> Option<gpui::Bounds<Pixels>> {
+        let snapshot = self.snapshot(cx);
+        let start = OffsetUtf16(range_utf16.start).to_display_point(&snapshot);
+        Some(self.display_point_bounds(start, element_bounds, cx))
+    }
+}
+
+impl Editor {
+    /// The bounds of the character at `point` in the window, when the editor is laid out
+    /// within `element_bounds`.
+    fn display_point_bounds(
+        &mut self,
+        point: DisplayPoint,
+        element_bounds: gpui::Bounds<Pixels>,
+        cx: &mut ViewContext<Self>,
+    ) -> gpui::Bounds<Pixels> {
         let text_layout_details = self.text_layout_details(cx);
         let style = &text_layout_details.editor_style;
         let font_id = cx.text_system().resolve_font(&style.text.font());
@@ -9527,15 +10368,14 @@ impl ViewInputHandler for Editor {
         let scroll_position = snapshot.scroll_position();
         let scroll_left = scroll_position.x * em_width;
 
-        let start = OffsetUtf16(range_utf16.start).to_display_point(&snapshot);
-        let x = snapshot.x_for_display_point(start, &text_layout_details) - scroll_left
+        let x = snapshot.x_for_display_point(point, &text_layout_details) - scroll_left
             + self.gutter_width;
-        let y = line_height * (start.row() as f32 - scroll_position.y);
+        let y = line_height * (point.row() as f32 - scroll_position.y);
 
-        Some(Bounds {
-            origin: element_bounds.origin + point(x, y),
+        Bounds {
+            origin: element_bounds.origin + gpui::point(x, y),
             size: size(em_width, line_height),
-        })
+        }
     }
 }
 
@@ -1,3 +1,4 @@
+use gpui::Modifiers;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::Settings;
@@ -10,8 +11,33 @@ pub struct EditorSettings {
     pub show_completion_documentation: bool,
     pub use_on_type_format: bool,
     pub scrollbar: Scrollbar,
+    pub minimap: Minimap,
+    pub sticky_scroll: StickyScroll,
+    pub spell_check: SpellCheck,
     pub relative_line_numbers: bool,
     pub seed_search_query_from_cursor: SeedQuerySetting,
+    pub snippet_sharing_endpoint: Option<String>,
+    pub context_menu: Option<Vec<ContextMenuEntry>>,
+    pub multi_cursor_modifier: MultiCursorModifier,
+    pub references_display: ReferencesDisplay,
+    pub persist_clipboard_history: bool,
+}
+
+/// An item of the editor's context menu.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum ContextMenuEntry {
+    /// One of the built-in items, such as "cut" or "go_to_definition", or "separator".
+    /// Built-in items are only shown where they apply.
+    Item(String),
+    /// An item which dispatches any action, such as "editor::SortLinesCaseSensitive".
+    Action {
+        label: String,
+        action: String,
+        /// The action's arguments, as they would be written in a keymap.
+        #[serde(default)]
+        argument: Option<serde_json::Value>,
+    },
 }
 
 /// When to populate a new search's query based on the text under the cursor.
@@ -26,6 +52,53 @@ pub enum SeedQuerySetting {
     Never,
 }
 
+/// Where the results of Find All References are shown.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferencesDisplay {
+    /// In a multibuffer, with an excerpt around each reference.
+    #[default]
+    Multibuffer,
+    /// In the references panel, which stays open until the next search.
+    Panel,
+}
+
+/// The modifier to hold while clicking to add or remove cursors. Clicking while holding
+/// the other one goes to the definition of the clicked symbol.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MultiCursorModifier {
+    /// Alt, or option on macOS. Cmd-click goes to definition.
+    #[default]
+    Alt,
+    /// Cmd on macOS, and ctrl elsewhere. Alt-click goes to definition.
+    CmdOrCtrl,
+}
+
+impl MultiCursorModifier {
+    /// Whether clicking with these modifiers adds or removes a cursor.
+    pub fn adds_cursor(&self, modifiers: &Modifiers) -> bool {
+        match self {
+            Self::Alt => modifiers.alt,
+            Self::CmdOrCtrl => {
+                if cfg!(target_os = "macos") {
+                    modifiers.command
+                } else {
+                    modifiers.control
+                }
+            }
+        }
+    }
+
+    /// Whether clicking with these modifiers goes to the definition of the clicked symbol.
+    pub fn goes_to_definition(&self, modifiers: &Modifiers) -> bool {
+        match self {
+            Self::Alt => modifiers.command,
+            Self::CmdOrCtrl => modifiers.alt,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct Scrollbar {
     pub show: ShowScrollbar,
@@ -50,6 +123,39 @@ pub enum ShowScrollbar {
     Never,
 }
 
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct Minimap {
+    pub show: ShowMinimap,
+    pub width: f32,
+}
+
+/// When to show the minimap in the editor.
+///
+/// Default: never
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShowMinimap {
+    /// Show the minimap when the editor's content doesn't fit in it.
+    Auto,
+    /// Always show the minimap.
+    Always,
+    /// Never show the minimap.
+    Never,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct StickyScroll {
+    pub enabled: bool,
+    pub max_depth: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct SpellCheck {
+    pub enabled: bool,
+    pub language: String,
+    pub dictionary: Option<String>,
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct EditorSettingsContent {
     /// Whether the cursor blinks in the editor.
@@ -78,6 +184,12 @@ pub struct EditorSettingsContent {
     pub use_on_type_format: Option<bool>,
     /// Scrollbar related settings
     pub scrollbar: Option<ScrollbarContent>,
+    /// Minimap related settings
+    pub minimap: Option<MinimapContent>,
+    /// Sticky scroll related settings
+    pub sticky_scroll: Option<StickyScrollContent>,
+    /// Spell checking related settings
+    pub spell_check: Option<SpellCheckContent>,
     /// Whether the line numbers on editors gutter are relative or not.
     ///
     /// Default: false
@@ -86,6 +198,30 @@ pub struct EditorSettingsContent {
     ///
     /// Default: always
     pub seed_search_query_from_cursor: Option<SeedQuerySetting>,
+    /// The endpoint that "Share as Snippet" uploads selections to. When unset,
+    /// snippet sharing is unavailable.
+    ///
+    /// Default: null
+    pub snippet_sharing_endpoint: Option<String>,
+    /// The items of the editor's context menu, in the order they are shown. Items which
+    /// aren't listed are hidden. When unset, all of the built-in items are shown.
+    ///
+    /// Default: null
+    pub context_menu: Option<Vec<ContextMenuEntry>>,
+    /// The modifier to hold while clicking to add or remove cursors, or to add a
+    /// selection of the clicked word when double-clicking.
+    ///
+    /// Default: alt
+    pub multi_cursor_modifier: Option<MultiCursorModifier>,
+    /// Where the results of Find All References are shown.
+    ///
+    /// Default: multibuffer
+    pub references_display: Option<ReferencesDisplay>,
+    /// Whether the text copied or cut from editors is saved to the database, so that it
+    /// can be pasted from the clipboard history after restarting.
+    ///
+    /// Default: true
+    pub persist_clipboard_history: Option<bool>,
 }
 
 /// Scrollbar related settings
@@ -105,6 +241,52 @@ pub struct ScrollbarContent {
     pub selections: Option<bool>,
 }
 
+/// Minimap related settings
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct MinimapContent {
+    /// When to show the minimap in the editor.
+    ///
+    /// Default: never
+    pub show: Option<ShowMinimap>,
+    /// The width of the minimap, in pixels.
+    ///
+    /// Default: 100
+    pub width: Option<f32>,
+}
+
+/// Sticky scroll related settings
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct StickyScrollContent {
+    /// Whether to pin the first lines of the scopes that the top of the editor is
+    /// scrolled into, such as the functions and classes enclosing it.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The most lines to pin, of the innermost scopes.
+    ///
+    /// Default: 5
+    pub max_depth: Option<usize>,
+}
+
+/// Spell checking related settings
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct SpellCheckContent {
+    /// Whether to underline the misspelled words in comments, strings and prose.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The language of the Hunspell dictionary to check words against, which is looked
+    /// for in the system's dictionary directories.
+    ///
+    /// Default: en_US
+    pub language: Option<String>,
+    /// The path of a Hunspell dictionary to use instead, without its `.aff` or `.dic`
+    /// extension.
+    ///
+    /// Default: null
+    pub dictionary: Option<String>,
+}
+
 impl Settings for EditorSettings {
     const KEY: Option<&'static str> = None;
 
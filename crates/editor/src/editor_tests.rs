@@ -423,6 +423,380 @@ fn test_selection_with_mouse(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+fn test_multi_click_selection_with_mouse(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("one two three\nfour five\nsix\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    // Double-click selects a word, and dragging extends the selection by whole words
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(0, 5), false, 2, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 4)..DisplayPoint::new(0, 7)]
+        );
+
+        view.update_selection(
+            DisplayPoint::new(0, 9),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 4)..DisplayPoint::new(0, 13)]
+        );
+
+        view.update_selection(
+            DisplayPoint::new(0, 3),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 7)..DisplayPoint::new(0, 0)]
+        );
+        view.end_selection(cx);
+    });
+
+    // Triple-click selects a line, and dragging extends the selection by whole lines
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(1, 2), false, 3, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(1, 0)..DisplayPoint::new(2, 0)]
+        );
+
+        view.update_selection(
+            DisplayPoint::new(2, 1),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(1, 0)..DisplayPoint::new(3, 0)]
+        );
+        view.end_selection(cx);
+    });
+
+    // Quadruple-click selects everything, regardless of dragging
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(0, 2), false, 4, cx);
+        view.update_selection(
+            DisplayPoint::new(1, 1),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        view.end_selection(cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 0)..DisplayPoint::new(3, 0)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_adding_and_removing_cursors_with_mouse(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("aaaaaa\nbbbbbb\ncccccc\nddddddd\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(0, 1), false, 1, cx);
+        view.end_selection(cx);
+        view.begin_selection(DisplayPoint::new(2, 2), true, 1, cx);
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [
+            DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1),
+            DisplayPoint::new(2, 2)..DisplayPoint::new(2, 2),
+        ]
+    );
+
+    // Adding a cursor where there already is one removes it
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(2, 2), true, 1, cx);
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1)]
+    );
+
+    // ...unless it's the last one
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(0, 1), true, 1, cx);
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1)]
+    );
+
+    // Double-clicking adds a selection of the clicked word
+    _ = editor.update(cx, |view, cx| {
+        view.begin_selection(DisplayPoint::new(3, 2), true, 1, cx);
+        view.end_selection(cx);
+        view.begin_selection(DisplayPoint::new(3, 2), true, 2, cx);
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [
+            DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1),
+            DisplayPoint::new(3, 0)..DisplayPoint::new(3, 7),
+        ]
+    );
+}
+
+#[gpui::test]
+fn test_block_selection_with_mouse(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let editor = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("aaaaaa\nbbbbbb\ncc\ndddddd\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    // Clicking without dragging places a cursor
+    _ = editor.update(cx, |view, cx| {
+        view.begin_block_selection(DisplayPoint::new(0, 1), false, cx);
+        view.update_selection(
+            DisplayPoint::new(0, 1),
+            1,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [DisplayPoint::new(0, 1)..DisplayPoint::new(0, 1)]
+    );
+
+    // Dragging selects the same columns on every row, skipping rows that are too short
+    _ = editor.update(cx, |view, cx| {
+        view.update_selection(
+            DisplayPoint::new(3, 4),
+            4,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        view.end_selection(cx);
+    });
+    assert_eq!(
+        editor
+            .update(cx, |view, cx| view.selections.display_ranges(cx))
+            .unwrap(),
+        [
+            DisplayPoint::new(0, 1)..DisplayPoint::new(0, 4),
+            DisplayPoint::new(1, 1)..DisplayPoint::new(1, 4),
+            DisplayPoint::new(2, 1)..DisplayPoint::new(2, 2),
+            DisplayPoint::new(3, 1)..DisplayPoint::new(3, 4),
+        ]
+    );
+
+    _ = editor.update(cx, |view, cx| {
+        view.copy(&Copy, cx);
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("aaa\nbbb\nc\nddd".to_owned())
+        );
+
+        view.handle_input("X", cx);
+        assert_eq!(view.text(cx), "aXaa\nbXbb\ncX\ndXdd\n");
+    });
+}
+
+#[gpui::test]
+fn test_dragging_selections(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("one two three\nfour\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(0, 4)..DisplayPoint::new(0, 8)]);
+        });
+
+        // Releasing the mouse without dragging places the cursor
+        assert!(view.begin_selection_drag(DisplayPoint::new(0, 5), cx));
+        view.end_selection_drag(false, cx);
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 5)..DisplayPoint::new(0, 5)]
+        );
+        assert!(!view.begin_selection_drag(DisplayPoint::new(0, 5), cx));
+
+        // Dropping the text moves it, in a single transaction
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(0, 4)..DisplayPoint::new(0, 8)]);
+        });
+        assert!(view.begin_selection_drag(DisplayPoint::new(0, 5), cx));
+        view.update_selection_drag(DisplayPoint::new(0, 6), cx);
+        assert_eq!(
+            view.selection_drop_position(),
+            Some(DisplayPoint::new(0, 6))
+        );
+        view.update_selection_drag(DisplayPoint::new(1, 4), cx);
+        view.end_selection_drag(false, cx);
+        assert_eq!(view.text(cx), "one three\nfourtwo \n");
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(1, 4)..DisplayPoint::new(1, 8)]
+        );
+        assert_eq!(view.selection_drop_position(), None);
+
+        view.undo(&Undo, cx);
+        assert_eq!(view.text(cx), "one two three\nfour\n");
+
+        // Dropping the text with the copy modifier leaves the original in place
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(0, 4)..DisplayPoint::new(0, 8)]);
+        });
+        assert!(view.begin_selection_drag(DisplayPoint::new(0, 4), cx));
+        view.update_selection_drag(DisplayPoint::new(0, 0), cx);
+        view.end_selection_drag(true, cx);
+        assert_eq!(view.text(cx), "two one two three\nfour\n");
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 0)..DisplayPoint::new(0, 4)]
+        );
+    });
+}
+
+#[gpui::test]
+fn test_primary_selection(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple("one two\nthree\n", cx);
+        build_editor(buffer, cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.focus(cx);
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([
+                DisplayPoint::new(0, 0)..DisplayPoint::new(0, 3),
+                DisplayPoint::new(1, 0)..DisplayPoint::new(1, 5),
+            ]);
+        });
+        assert_eq!(
+            cx.read_from_primary().map(|item| item.text().to_owned()),
+            Some("one\nthree".to_owned())
+        );
+
+        // Empty selections leave the primary selection alone
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(0, 0)..DisplayPoint::new(0, 0)]);
+        });
+        assert_eq!(
+            cx.read_from_primary().map(|item| item.text().to_owned()),
+            Some("one\nthree".to_owned())
+        );
+
+        view.paste_primary_selection_at(DisplayPoint::new(0, 4), "two ", cx);
+        assert_eq!(view.text(cx), "one two two\nthree\n");
+        assert_eq!(
+            view.selections.display_ranges(cx),
+            [DisplayPoint::new(0, 8)..DisplayPoint::new(0, 8)]
+        );
+
+        // A selection made with the mouse is written once the mouse is released
+        view.begin_selection(DisplayPoint::new(1, 0), false, 1, cx);
+        view.update_selection(
+            DisplayPoint::new(1, 5),
+            0,
+            gpui::Point::<f32>::default(),
+            cx,
+        );
+        assert_eq!(
+            cx.read_from_primary().map(|item| item.text().to_owned()),
+            Some("one\nthree".to_owned())
+        );
+        view.end_selection(cx);
+        assert_eq!(
+            cx.read_from_primary().map(|item| item.text().to_owned()),
+            Some("three".to_owned())
+        );
+    });
+}
+
+#[gpui::test]
+fn test_apply_rename_edits(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let buffer_1 = cx
+        .new_model(|cx| language::Buffer::new(0, cx.entity_id().as_u64(), "fn one() {}\none();\n"));
+    let buffer_2 = cx.new_model(|cx| language::Buffer::new(0, cx.entity_id().as_u64(), "one();\n"));
+
+    let transaction = cx.update(|cx| {
+        let edits = [&buffer_1, &buffer_2]
+            .into_iter()
+            .map(|buffer| {
+                let snapshot = buffer.read(cx);
+                let edits = snapshot
+                    .text()
+                    .match_indices("one")
+                    .map(|(ix, _)| {
+                        (
+                            snapshot.anchor_before(ix)..snapshot.anchor_after(ix + 3),
+                            "two".to_string(),
+                        )
+                    })
+                    .collect();
+                (buffer.clone(), edits)
+            })
+            .collect();
+        rename_preview::apply_rename_edits(edits, cx)
+    });
+    assert_eq!(transaction.0.len(), 2);
+    assert_eq!(
+        buffer_1.read_with(cx, |buffer, _| buffer.text()),
+        "fn two() {}\ntwo();\n"
+    );
+    assert_eq!(
+        buffer_2.read_with(cx, |buffer, _| buffer.text()),
+        "two();\n"
+    );
+
+    // Each buffer's edits can be undone on their own
+    buffer_2.update(cx, |buffer, cx| buffer.undo(cx));
+    assert_eq!(
+        buffer_2.read_with(cx, |buffer, _| buffer.text()),
+        "one();\n"
+    );
+    assert_eq!(
+        buffer_1.read_with(cx, |buffer, _| buffer.text()),
+        "fn two() {}\ntwo();\n"
+    );
+}
+
 #[gpui::test]
 fn test_canceling_pending_selection(cx: &mut TestAppContext) {
     init_test(cx, |_| {});
@@ -764,19 +1138,222 @@ fn test_fold_action(cx: &mut TestAppContext) {
             .unindent(),
         );
 
-        view.fold(&Fold, cx);
+        view.fold(&Fold, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {⋯
+                }
+            "
+            .unindent(),
+        );
+
+        view.unfold_lines(&UnfoldLines, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {
+                    // Hello!
+
+                    fn a() {
+                        1
+                    }
+
+                    fn b() {⋯
+                    }
+
+                    fn c() {⋯
+                    }
+                }
+            "
+            .unindent(),
+        );
+
+        view.unfold_lines(&UnfoldLines, cx);
+        assert_eq!(view.display_text(cx), view.buffer.read(cx).read(cx).text());
+    });
+}
+
+#[gpui::test]
+fn test_fold_recursive_and_unfold_all(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(
+            &"
+                impl Foo {
+                    // Hello!
+
+                    fn a() {
+                        1
+                    }
+
+                    fn b() {
+                        2
+                    }
+
+                    fn c() {
+                        3
+                    }
+                }
+            "
+            .unindent(),
+            cx,
+        );
+        build_editor(buffer.clone(), cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(0, 0)..DisplayPoint::new(0, 0)]);
+        });
+        view.fold_recursive(&FoldRecursive, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {⋯
+                }
+            "
+            .unindent(),
+        );
+
+        // The nested ranges stay folded when the outer one is unfolded
+        view.unfold_lines(&UnfoldLines, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {
+                    // Hello!
+
+                    fn a() {⋯
+                    }
+
+                    fn b() {⋯
+                    }
+
+                    fn c() {⋯
+                    }
+                }
+            "
+            .unindent(),
+        );
+
+        view.unfold_all(&UnfoldAll, cx);
+        assert_eq!(view.display_text(cx), view.buffer.read(cx).read(cx).text());
+    });
+}
+
+#[gpui::test]
+fn test_fold_at_level(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(
+            &"
+                impl Foo {
+                    // Hello!
+
+                    fn a() {
+                        1
+                    }
+
+                    fn b() {
+                        2
+                    }
+
+                    fn c() {
+                        3
+                    }
+                }
+            "
+            .unindent(),
+            cx,
+        );
+        build_editor(buffer.clone(), cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.fold_at_level(&FoldAtLevel { level: 2 }, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {
+                    // Hello!
+
+                    fn a() {⋯
+                    }
+
+                    fn b() {⋯
+                    }
+
+                    fn c() {⋯
+                    }
+                }
+            "
+            .unindent(),
+        );
+
+        view.fold_at_level(&FoldAtLevel { level: 1 }, cx);
+        assert_eq!(
+            view.display_text(cx),
+            "
+                impl Foo {⋯
+                }
+            "
+            .unindent(),
+        );
+
+        view.unfold_all(&UnfoldAll, cx);
+        view.fold_at_level(&FoldAtLevel { level: 3 }, cx);
+        assert_eq!(view.display_text(cx), view.buffer.read(cx).read(cx).text());
+    });
+}
+
+#[gpui::test]
+fn test_copy_folded_line(cx: &mut TestAppContext) {
+    init_test(cx, |_| {});
+
+    let view = cx.add_window(|cx| {
+        let buffer = MultiBuffer::build_simple(
+            &"
+                impl Foo {
+                    // Hello!
+
+                    fn a() {
+                        1
+                    }
+
+                    fn b() {
+                        2
+                    }
+
+                    fn c() {
+                        3
+                    }
+                }
+            "
+            .unindent(),
+            cx,
+        );
+        build_editor(buffer.clone(), cx)
+    });
+
+    _ = view.update(cx, |view, cx| {
+        view.fold_at(&FoldAt { buffer_row: 7 }, cx);
+        view.change_selections(None, cx, |s| {
+            s.select_display_ranges([DisplayPoint::new(7, 4)..DisplayPoint::new(7, 4)]);
+        });
+
+        // Copying a folded line copies the lines hidden by the fold too
+        view.copy(&Copy, cx);
         assert_eq!(
-            view.display_text(cx),
-            "
-                impl Foo {⋯
-                }
-            "
-            .unindent(),
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("    fn b() {\n        2\n".to_owned())
         );
 
-        view.unfold_lines(&UnfoldLines, cx);
+        view.cut(&Cut, cx);
         assert_eq!(
-            view.display_text(cx),
+            view.text(cx),
             "
                 impl Foo {
                     // Hello!
@@ -785,18 +1362,15 @@ fn test_fold_action(cx: &mut TestAppContext) {
                         1
                     }
 
-                    fn b() {⋯
                     }
 
-                    fn c() {⋯
+                    fn c() {
+                        3
                     }
                 }
             "
             .unindent(),
         );
-
-        view.unfold_lines(&UnfoldLines, cx);
-        assert_eq!(view.display_text(cx), view.buffer.read(cx).read(cx).text());
     });
 }
 
@@ -5288,6 +5862,66 @@ async fn test_range_format_during_save(cx: &mut gpui::TestAppContext) {
     save.await;
 }
 
+#[gpui::test]
+async fn test_format_selection(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            document_range_formatting_provider: Some(lsp::OneOf::Left(true)),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+    cx.set_state(indoc! {"
+        fn   one() {}
+        «fn   two() {}ˇ»
+        fn   thˇree() {}
+    "});
+
+    let requested_ranges = Arc::new(Mutex::new(Vec::new()));
+    let mut requests = cx.handle_request::<lsp::request::RangeFormatting, _, _>({
+        let requested_ranges = requested_ranges.clone();
+        move |_, params, _| {
+            requested_ranges.lock().push(params.range);
+            let line = params.range.start.line;
+            async move {
+                Ok(Some(vec![lsp::TextEdit::new(
+                    lsp::Range::new(lsp::Position::new(line, 2), lsp::Position::new(line, 5)),
+                    " ".to_string(),
+                )]))
+            }
+        }
+    });
+    let format = cx
+        .update_editor(|editor, cx| editor.format_selection(&FormatSelection, cx))
+        .unwrap();
+    requests.next().await;
+    requests.next().await;
+    format.await.unwrap();
+
+    // Only the selected text, and the line of the empty selection, are formatted
+    assert_eq!(
+        requested_ranges.lock().as_slice(),
+        [
+            lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(1, 13)),
+            lsp::Range::new(lsp::Position::new(2, 0), lsp::Position::new(2, 15)),
+        ]
+    );
+    assert_eq!(
+        cx.buffer_text(),
+        "fn   one() {}\nfn two() {}\nfn three() {}\n"
+    );
+
+    // Both formatting edits are undone at once
+    cx.update_editor(|editor, cx| editor.undo(&Undo, cx));
+    assert_eq!(
+        cx.buffer_text(),
+        "fn   one() {}\nfn   two() {}\nfn   three() {}\n"
+    );
+}
+
 #[gpui::test]
 async fn test_document_format_manual_trigger(cx: &mut gpui::TestAppContext) {
     init_test(cx, |settings| {
@@ -5384,6 +6018,349 @@ async fn test_document_format_manual_trigger(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_code_lenses(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            code_lens_provider: Some(lsp::CodeLensOptions {
+                resolve_provider: Some(true),
+            }),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    cx.set_state(indoc! {"
+        fn one() {}
+        fn two() {}ˇ
+    "});
+
+    // Both lenses on the first line are shown in one block, once the unresolved one has
+    // been resolved.
+    cx.lsp
+        .handle_request::<lsp::request::CodeLensRequest, _, _>(|_, _| async move {
+            let range = lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(0, 6));
+            Ok(Some(vec![
+                lsp::CodeLens {
+                    range,
+                    command: Some(lsp::Command {
+                        title: "Run".into(),
+                        command: "run".into(),
+                        arguments: None,
+                    }),
+                    data: None,
+                },
+                lsp::CodeLens {
+                    range,
+                    command: None,
+                    data: Some(json!("references")),
+                },
+            ]))
+        });
+    cx.lsp
+        .handle_request::<lsp::request::CodeLensResolve, _, _>(|lens, _| async move {
+            assert_eq!(lens.data, Some(json!("references")));
+            Ok(lsp::CodeLens {
+                command: Some(lsp::Command {
+                    title: "2 references".into(),
+                    command: "showReferences".into(),
+                    arguments: None,
+                }),
+                ..lens
+            })
+        });
+
+    cx.update_editor(|editor, cx| {
+        code_lens::refresh_code_lenses(editor, false, cx);
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        assert_eq!(editor.code_lens.blocks.len(), 1);
+        assert_eq!(editor.display_text(cx), "\nfn one() {}\nfn two() {}\n");
+    });
+
+    // Lenses are hidden for languages with them disabled
+    cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.code_lens = Some(false);
+            });
+        });
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        assert!(editor.code_lens.blocks.is_empty());
+        assert_eq!(editor.display_text(cx), "fn one() {}\nfn two() {}\n");
+    });
+}
+
+#[gpui::test]
+async fn test_semantic_tokens(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |settings| {
+        settings.defaults.semantic_tokens = Some(true)
+    });
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            semantic_tokens_provider: Some(
+                lsp::SemanticTokensServerCapabilities::SemanticTokensOptions(
+                    lsp::SemanticTokensOptions {
+                        legend: lsp::SemanticTokensLegend {
+                            token_types: vec![
+                                lsp::SemanticTokenType::VARIABLE,
+                                lsp::SemanticTokenType::FUNCTION,
+                            ],
+                            token_modifiers: vec![
+                                lsp::SemanticTokenModifier::new("mutable"),
+                                lsp::SemanticTokenModifier::DEPRECATED,
+                            ],
+                        },
+                        full: Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                        ..Default::default()
+                    },
+                ),
+            ),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    cx.set_state("fn main() { let mut x = old(); }ˇ");
+
+    // A mutable variable, `x`, and a call of a deprecated function, `old`
+    cx.lsp
+        .handle_request::<lsp::request::SemanticTokensFullRequest, _, _>(|_, _| async move {
+            Ok(Some(lsp::SemanticTokensResult::Tokens(
+                lsp::SemanticTokens {
+                    result_id: Some("1".into()),
+                    data: vec![
+                        lsp::SemanticToken {
+                            delta_line: 0,
+                            delta_start: 20,
+                            length: 1,
+                            token_type: 0,
+                            token_modifiers_bitset: 0b01,
+                        },
+                        lsp::SemanticToken {
+                            delta_line: 0,
+                            delta_start: 4,
+                            length: 3,
+                            token_type: 1,
+                            token_modifiers_bitset: 0b10,
+                        },
+                    ],
+                },
+            )))
+        });
+    cx.update_editor(|editor, cx| {
+        semantic_tokens::refresh_semantic_tokens(editor, false, cx);
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        let highlights = snapshot.display_snapshot.semantic_highlight_ranges();
+        assert_eq!(
+            highlights
+                .iter()
+                .map(|(range, _)| range.to_offset(&snapshot.buffer_snapshot))
+                .collect::<Vec<_>>(),
+            vec![20..21, 24..27]
+        );
+        assert!(highlights[0].1.underline.is_some());
+        assert_eq!(highlights[1].1.fade_out, Some(0.3));
+    });
+
+    // The next request asks for the changes since the previous tokens
+    cx.lsp
+        .handle_request::<lsp::request::SemanticTokensFullDeltaRequest, _, _>(
+            |params, _| async move {
+                assert_eq!(params.previous_result_id, "1");
+                Ok(Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(
+                    lsp::SemanticTokensDelta {
+                        result_id: Some("2".into()),
+                        edits: vec![lsp::SemanticTokensEdit {
+                            start: 5,
+                            delete_count: 5,
+                            data: None,
+                        }],
+                    },
+                )))
+            },
+        );
+    cx.update_editor(|editor, cx| {
+        semantic_tokens::refresh_semantic_tokens(editor, false, cx);
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert_eq!(
+            snapshot
+                .display_snapshot
+                .semantic_highlight_ranges()
+                .iter()
+                .map(|(range, _)| range.to_offset(&snapshot.buffer_snapshot))
+                .collect::<Vec<_>>(),
+            vec![20..21]
+        );
+    });
+
+    // Tokens are cleared for languages with them disabled
+    cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings::<AllLanguageSettings>(cx, |settings| {
+                settings.defaults.semantic_tokens = Some(false);
+            });
+        });
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        let snapshot = editor.snapshot(cx);
+        assert!(snapshot
+            .display_snapshot
+            .semantic_highlight_ranges()
+            .is_empty());
+    });
+}
+
+#[gpui::test]
+async fn test_linked_editing_ranges(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            linked_editing_range_provider: Some(lsp::LinkedEditingRangeServerCapabilities::Simple(
+                true,
+            )),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    // The names of the opening and closing tags are linked
+    cx.lsp
+        .handle_request::<lsp::request::LinkedEditingRange, _, _>(|_, _| async move {
+            Ok(Some(lsp::LinkedEditingRanges {
+                ranges: vec![
+                    lsp::Range::new(lsp::Position::new(0, 1), lsp::Position::new(0, 4)),
+                    lsp::Range::new(lsp::Position::new(0, 7), lsp::Position::new(0, 10)),
+                ],
+                word_pattern: None,
+            }))
+        });
+    cx.set_state("<diˇv></div>");
+    cx.executor()
+        .advance_clock(linked_editing_ranges::LINKED_EDITING_DEBOUNCE);
+    cx.executor().run_until_parked();
+
+    cx.simulate_keystroke("x");
+    cx.assert_editor_state("<dixˇv></dixv>");
+    cx.update_editor(|editor, cx| editor.backspace(&Backspace, cx));
+    cx.assert_editor_state("<diˇv></div>");
+
+    // Typing whitespace ends linked editing
+    cx.simulate_keystroke(" ");
+    cx.assert_editor_state("<di ˇv></div>");
+    cx.simulate_keystroke("y");
+    cx.assert_editor_state("<di yˇv></div>");
+}
+
+#[gpui::test]
+async fn test_signature_help(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_rust(
+        lsp::ServerCapabilities {
+            signature_help_provider: Some(lsp::SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".into(), ",".into()]),
+                retrigger_characters: None,
+                work_done_progress_options: Default::default(),
+            }),
+            ..Default::default()
+        },
+        cx,
+    )
+    .await;
+
+    cx.set_state("fn main() { sumˇ }");
+
+    // The argument list starts at column 16, and its second argument at column 18.
+    cx.lsp
+        .handle_request::<lsp::request::SignatureHelpRequest, _, _>(|params, _| async move {
+            let column = params.text_document_position_params.position.character;
+            if column < 16 {
+                return Ok(None);
+            }
+            let signature = |label: &str, parameters: &[&str]| lsp::SignatureInformation {
+                label: label.into(),
+                documentation: None,
+                parameters: Some(
+                    parameters
+                        .iter()
+                        .map(|parameter| lsp::ParameterInformation {
+                            label: lsp::ParameterLabel::Simple(parameter.to_string()),
+                            documentation: None,
+                        })
+                        .collect(),
+                ),
+                active_parameter: None,
+            };
+            Ok(Some(lsp::SignatureHelp {
+                signatures: vec![
+                    signature("sum(a: u8, b: u8)", &["a: u8", "b: u8"]),
+                    signature("sum(a: u8, b: u8, c: u8)", &["a: u8", "b: u8", "c: u8"]),
+                ],
+                active_signature: Some(0),
+                active_parameter: Some(if column < 18 { 0 } else { 1 }),
+            }))
+        });
+
+    cx.simulate_keystroke("(");
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, _| {
+        let signature = editor.signature_help.active_signature().unwrap();
+        assert_eq!(signature.label, "sum(a: u8, b: u8)");
+        assert_eq!(signature.parameters, vec![4..9, 11..16]);
+        assert_eq!(signature.active_parameter, Some(0));
+    });
+
+    // The chosen overload is kept as the signature help updates
+    cx.update_editor(|editor, cx| {
+        signature_help::signature_help_next(editor, &SignatureHelpNext, cx);
+    });
+    cx.simulate_keystrokes(["1", ","]);
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, _| {
+        let signature = editor.signature_help.active_signature().unwrap();
+        assert_eq!(signature.label, "sum(a: u8, b: u8, c: u8)");
+        assert_eq!(signature.active_parameter, Some(1));
+    });
+
+    // Leaving the argument list hides the signature help
+    cx.update_editor(|editor, cx| {
+        editor.move_to_beginning_of_line(&MoveToBeginningOfLine, cx);
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, _| assert!(!editor.signature_help.visible()));
+
+    // So does escape
+    cx.update_editor(|editor, cx| {
+        editor.move_to_end_of_line(&MoveToEndOfLine, cx);
+        editor.move_left(&MoveLeft, cx);
+        signature_help::show_signature_help(editor, &ShowSignatureHelp, cx);
+    });
+    cx.executor().run_until_parked();
+    cx.update_editor(|editor, cx| {
+        assert!(editor.signature_help.visible());
+        editor.cancel(&Cancel, cx);
+        assert!(!editor.signature_help.visible());
+    });
+}
+
 #[gpui::test]
 async fn test_concurrent_format_requests(cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -6962,6 +7939,80 @@ async fn go_to_prev_overlapping_diagnostic(
     "});
 }
 
+#[gpui::test]
+async fn go_to_document_highlights(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorTestContext::new(cx).await;
+    cx.set_state(indoc! {"
+        let mut ˇa = 1;
+        a += b(a);
+        let c = a;
+    "});
+    let read_ranges = cx.ranges(indoc! {"
+        let mut a = 1;
+        a += b(«a»);
+        let c = «a»;
+    "});
+    let write_ranges = cx.ranges(indoc! {"
+        let mut «a» = 1;
+        «a» += b(a);
+        let c = a;
+    "});
+    cx.update_editor(|editor, cx| {
+        let buffer = editor.buffer.read(cx).snapshot(cx);
+        let to_anchors = |ranges: Vec<Range<usize>>| {
+            ranges
+                .into_iter()
+                .map(|range| buffer.anchor_after(range.start)..buffer.anchor_before(range.end))
+                .collect()
+        };
+        editor.highlight_background::<DocumentHighlightRead>(
+            to_anchors(read_ranges),
+            |theme| theme.editor_document_highlight_read_background,
+            cx,
+        );
+        editor.highlight_background::<DocumentHighlightWrite>(
+            to_anchors(write_ranges),
+            |theme| theme.editor_document_highlight_write_background,
+            cx,
+        );
+    });
+
+    // The read and write highlights are visited in order, cycling around at the ends
+    cx.update_editor(|editor, cx| editor.go_to_next_highlight(&GoToNextHighlight, cx));
+    cx.assert_editor_state(indoc! {"
+        let mut a = 1;
+        ˇa += b(a);
+        let c = a;
+    "});
+    cx.update_editor(|editor, cx| editor.go_to_next_highlight(&GoToNextHighlight, cx));
+    cx.assert_editor_state(indoc! {"
+        let mut a = 1;
+        a += b(ˇa);
+        let c = a;
+    "});
+    cx.update_editor(|editor, cx| editor.go_to_next_highlight(&GoToNextHighlight, cx));
+    cx.update_editor(|editor, cx| editor.go_to_next_highlight(&GoToNextHighlight, cx));
+    cx.assert_editor_state(indoc! {"
+        let mut ˇa = 1;
+        a += b(a);
+        let c = a;
+    "});
+    cx.update_editor(|editor, cx| editor.go_to_previous_highlight(&GoToPreviousHighlight, cx));
+    cx.assert_editor_state(indoc! {"
+        let mut a = 1;
+        a += b(a);
+        let c = ˇa;
+    "});
+    cx.update_editor(|editor, cx| editor.go_to_previous_highlight(&GoToPreviousHighlight, cx));
+    cx.assert_editor_state(indoc! {"
+        let mut a = 1;
+        a += b(ˇa);
+        let c = a;
+    "});
+}
+
 #[gpui::test]
 async fn go_to_hunk(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext) {
     init_test(cx, |_| {});
@@ -7173,6 +8224,29 @@ async fn test_move_to_enclosing_bracket(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_select_to_enclosing_bracket(cx: &mut gpui::TestAppContext) {
+    init_test(cx, |_| {});
+
+    let mut cx = EditorLspTestContext::new_typescript(Default::default(), cx).await;
+    let mut assert = |before, after| {
+        let _state_context = cx.set_state(before);
+        cx.update_editor(|editor, cx| {
+            editor.select_to_enclosing_bracket(&SelectToEnclosingBracket, cx)
+        });
+        cx.assert_editor_state(after);
+    };
+
+    // Selects up to the matching bracket, keeping the cursor's position as the tail
+    assert("console.logˇ(var);", "console.log«(var)ˇ»;");
+    assert("console.log(var)ˇ;", "console.log«ˇ(var)»;");
+    assert("console.log(ˇvar);", "console.log(«varˇ»);");
+
+    // Extends an existing selection from its head
+    assert("«consoleˇ».log(var);", "«consoleˇ».log(var);");
+    assert("«console.logˇ»(var);", "«console.log(var)ˇ»;");
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContext) {
     // flaky
@@ -1,36 +1,40 @@
 use crate::{
+    blame, bookmarks,
     display_map::{
         BlockContext, BlockStyle, DisplaySnapshot, FoldStatus, HighlightedChunk, ToDisplayPoint,
         TransformBlock,
     },
-    editor_settings::ShowScrollbar,
+    doc_comment,
+    editor_settings::{ShowMinimap, ShowScrollbar},
     git::{diff_hunk_to_display, DisplayDiffHunk},
     hover_popover::{
         self, hover_at, HOVER_POPOVER_GAP, MIN_POPOVER_CHARACTER_WIDTH, MIN_POPOVER_LINE_HEIGHT,
     },
+    hunk_diff,
     items::BufferSearchHighlights,
     link_go_to_definition::{
         go_to_fetched_definition, go_to_fetched_type_definition, show_link_definition,
         update_go_to_definition_link, update_inlay_link_and_hover_points, GoToDefinitionTrigger,
         LinkGoToDefinitionState,
     },
-    mouse_context_menu,
-    scroll::scroll_amount::ScrollAmount,
-    CursorShape, DisplayPoint, Editor, EditorMode, EditorSettings, EditorSnapshot, EditorStyle,
-    HalfPageDown, HalfPageUp, HoveredCursor, LineDown, LineUp, OpenExcerpts, PageDown, PageUp,
-    Point, SelectPhase, Selection, SoftWrap, ToPoint, CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
+    mouse_context_menu, peek_definition, runnables,
+    scroll::{scroll_amount::ScrollAmount, Autoscroll},
+    signature_help, snippet_sharing, spell_check, CursorShape, DeployGutterContextMenu,
+    DisplayPoint, Editor, EditorMode, EditorSettings, EditorSnapshot, EditorStyle, HalfPageDown,
+    HalfPageUp, HoveredCursor, LineDown, LineUp, OpenExcerpts, PageDown, PageUp, Point,
+    SelectPhase, Selection, SoftWrap, ToPoint, CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
 };
 use anyhow::Result;
 use collections::{BTreeMap, HashMap};
 use git::diff::DiffHunkStatus;
 use gpui::{
     div, fill, outline, overlay, point, px, quad, relative, size, transparent_black, Action,
-    AnchorCorner, AnyElement, AvailableSpace, Bounds, ContentMask, Corners, CursorStyle,
-    DispatchPhase, Edges, Element, ElementInputHandler, Entity, Hsla, InteractiveBounds,
-    InteractiveElement, IntoElement, ModifiersChangedEvent, MouseButton, MouseDownEvent,
-    MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, ScrollDelta, ScrollWheelEvent, ShapedLine,
-    SharedString, Size, StackingOrder, StatefulInteractiveElement, Style, Styled, TextRun,
-    TextStyle, View, ViewContext, WindowContext,
+    AnchorCorner, AnyElement, AppContext, AvailableSpace, Bounds, ContentMask, Corners,
+    CursorStyle, DispatchPhase, Edges, Element, ElementInputHandler, Entity, Hsla,
+    InteractiveBounds, InteractiveElement, IntoElement, ModifiersChangedEvent, MouseButton,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, ScrollDelta,
+    ScrollWheelEvent, ShapedLine, SharedString, Size, StackingOrder, StatefulInteractiveElement,
+    Style, Styled, TextRun, TextStyle, View, ViewContext, WindowContext,
 };
 use itertools::Itertools;
 use language::language_settings::ShowWhitespaceSetting;
@@ -57,6 +61,15 @@ use ui::{h_flex, ButtonLike, ButtonStyle, IconButton, Tooltip};
 use util::ResultExt;
 use workspace::item::Item;
 
+/// The height of a row of the minimap, in which each character is a pixel wide.
+const MINIMAP_ROW_HEIGHT: Pixels = px(2.);
+const MINIMAP_COLUMN_WIDTH: Pixels = px(1.);
+/// How much the text in the minimap is faded out, so that the visible part of the
+/// content and the search results stand out.
+const MINIMAP_TEXT_FADE: f32 = 0.4;
+/// The gap between the end of a line and its inline blame, in ems.
+const INLINE_BLAME_PADDING_EMS: f32 = 3.;
+
 struct SelectionLayout {
     head: DisplayPoint,
     cursor_shape: CursorShape,
@@ -251,20 +264,28 @@ impl EditorElement {
         register_action(view, cx, Editor::select_larger_syntax_node);
         register_action(view, cx, Editor::select_smaller_syntax_node);
         register_action(view, cx, Editor::move_to_enclosing_bracket);
+        register_action(view, cx, Editor::select_to_enclosing_bracket);
         register_action(view, cx, Editor::undo_selection);
         register_action(view, cx, Editor::redo_selection);
         register_action(view, cx, Editor::go_to_diagnostic);
         register_action(view, cx, Editor::go_to_prev_diagnostic);
         register_action(view, cx, Editor::go_to_hunk);
         register_action(view, cx, Editor::go_to_prev_hunk);
+        register_action(view, cx, Editor::go_to_next_highlight);
+        register_action(view, cx, Editor::go_to_previous_highlight);
         register_action(view, cx, Editor::go_to_definition);
         register_action(view, cx, Editor::go_to_definition_split);
+        register_action(view, cx, Editor::go_to_declaration);
         register_action(view, cx, Editor::go_to_type_definition);
         register_action(view, cx, Editor::go_to_type_definition_split);
+        register_action(view, cx, Editor::go_to_implementation);
         register_action(view, cx, Editor::fold);
         register_action(view, cx, Editor::fold_at);
+        register_action(view, cx, Editor::fold_recursive);
+        register_action(view, cx, Editor::fold_at_level);
         register_action(view, cx, Editor::unfold_lines);
         register_action(view, cx, Editor::unfold_at);
+        register_action(view, cx, Editor::unfold_all);
         register_action(view, cx, Editor::fold_selected_ranges);
         register_action(view, cx, Editor::show_completions);
         register_action(view, cx, Editor::toggle_code_actions);
@@ -272,7 +293,27 @@ impl EditorElement {
         register_action(view, cx, Editor::toggle_soft_wrap);
         register_action(view, cx, Editor::toggle_inlay_hints);
         register_action(view, cx, hover_popover::hover);
+        register_action(view, cx, mouse_context_menu::deploy_cursor_context_menu);
+        register_action(view, cx, mouse_context_menu::deploy_gutter_context_menu);
+        register_action(view, cx, snippet_sharing::share_selection_as_snippet);
+        register_action(view, cx, doc_comment::generate_doc_comment);
+        register_action(view, cx, runnables::run_test_at_cursor);
+        register_action(view, cx, blame::toggle_git_blame_inline);
+        register_action(view, cx, blame::copy_permalink_to_line);
+        register_action(view, cx, blame::show_blamed_commit);
+        register_action(view, cx, spell_check::replace_misspelling);
+        register_action(view, cx, spell_check::add_word_to_dictionary);
+        register_action(view, cx, bookmarks::toggle_bookmark_at);
+        register_action(view, cx, hunk_diff::toggle_hunk_diff_at);
+        register_action(view, cx, peek_definition::peek_definition);
+        register_action(view, cx, peek_definition::peek_next_definition);
+        register_action(view, cx, peek_definition::peek_previous_definition);
+        register_action(view, cx, peek_definition::open_peeked_definition_in_split);
+        register_action(view, cx, signature_help::show_signature_help);
+        register_action(view, cx, signature_help::signature_help_next);
+        register_action(view, cx, signature_help::signature_help_previous);
         register_action(view, cx, Editor::reveal_in_finder);
+        register_action(view, cx, Editor::open_in_terminal);
         register_action(view, cx, Editor::copy_path);
         register_action(view, cx, Editor::copy_relative_path);
         register_action(view, cx, Editor::copy_highlight_json);
@@ -283,6 +324,13 @@ impl EditorElement {
                 cx.propagate();
             }
         });
+        register_action(view, cx, |editor, action, cx| {
+            if let Some(task) = editor.format_selection(action, cx) {
+                task.detach_and_log_err(cx);
+            } else {
+                cx.propagate();
+            }
+        });
         register_action(view, cx, Editor::restart_language_server);
         register_action(view, cx, Editor::show_character_palette);
         register_action(view, cx, |editor, action, cx| {
@@ -299,6 +347,27 @@ impl EditorElement {
                 cx.propagate();
             }
         });
+        register_action(view, cx, |editor, action, cx| {
+            if let Some(task) = editor.copy_breadcrumbs(action, cx) {
+                task.detach_and_log_err(cx);
+            } else {
+                cx.propagate();
+            }
+        });
+        register_action(view, cx, |editor, action, cx| {
+            if let Some(task) = editor.add_import(action, cx) {
+                task.detach_and_log_err(cx);
+            } else {
+                cx.propagate();
+            }
+        });
+        register_action(view, cx, |editor, action, cx| {
+            if let Some(task) = editor.organize_imports(action, cx) {
+                task.detach_and_log_err(cx);
+            } else {
+                cx.propagate();
+            }
+        });
         register_action(view, cx, |editor, action, cx| {
             if let Some(task) = editor.rename(action, cx) {
                 task.detach_and_log_err(cx);
@@ -320,6 +389,13 @@ impl EditorElement {
                 cx.propagate();
             }
         });
+        register_action(view, cx, |editor, action, cx| {
+            if let Some(task) = editor.show_call_hierarchy(action, cx) {
+                task.detach_and_log_err(cx);
+            } else {
+                cx.propagate();
+            }
+        });
         register_action(view, cx, Editor::next_copilot_suggestion);
         register_action(view, cx, Editor::previous_copilot_suggestion);
         register_action(view, cx, Editor::copilot_suggest);
@@ -353,7 +429,8 @@ impl EditorElement {
         let pending_selection = editor.has_pending_selection();
 
         if let Some(point) = &editor.link_go_to_definition_state.last_trigger_point {
-            if event.command && !pending_selection {
+            let multi_cursor_modifier = EditorSettings::get_global(cx).multi_cursor_modifier;
+            if multi_cursor_modifier.goes_to_definition(event) && !pending_selection {
                 let point = point.clone();
                 let snapshot = editor.snapshot(cx);
                 let kind = point.definition_kind(event.shift);
@@ -405,6 +482,11 @@ impl EditorElement {
 
         let point_for_position = position_map.point_for_position(text_bounds, event.position);
         let position = point_for_position.previous_valid;
+        if click_count == 1 && !modifiers.shift && editor.begin_selection_drag(position, cx) {
+            cx.stop_propagation();
+            return;
+        }
+
         if modifiers.shift && modifiers.alt {
             editor.select(
                 SelectPhase::BeginColumnar {
@@ -413,6 +495,20 @@ impl EditorElement {
                 },
                 cx,
             );
+        } else if modifiers.alt
+            && !modifiers.shift
+            && !modifiers.control
+            && !modifiers.command
+            && click_count == 1
+        {
+            let multi_cursor_modifier = EditorSettings::get_global(cx).multi_cursor_modifier;
+            editor.select(
+                SelectPhase::BeginBlock {
+                    position,
+                    add: multi_cursor_modifier.adds_cursor(&modifiers),
+                },
+                cx,
+            );
         } else if modifiers.shift && !modifiers.control && !modifiers.alt && !modifiers.command {
             editor.select(
                 SelectPhase::Extend {
@@ -422,10 +518,11 @@ impl EditorElement {
                 cx,
             );
         } else {
+            let multi_cursor_modifier = EditorSettings::get_global(cx).multi_cursor_modifier;
             editor.select(
                 SelectPhase::Begin {
                     position,
-                    add: modifiers.alt,
+                    add: multi_cursor_modifier.adds_cursor(&modifiers),
                     click_count,
                 },
                 cx,
@@ -440,8 +537,22 @@ impl EditorElement {
         event: &MouseDownEvent,
         position_map: &PositionMap,
         text_bounds: Bounds<Pixels>,
+        gutter_bounds: Bounds<Pixels>,
         cx: &mut ViewContext<Editor>,
     ) {
+        if gutter_bounds.contains(&event.position) {
+            let point_for_position = position_map.point_for_position(text_bounds, event.position);
+            let buffer_row = DisplayPoint::new(point_for_position.previous_valid.row(), 0)
+                .to_point(&position_map.snapshot)
+                .row;
+            mouse_context_menu::deploy_gutter_context_menu(
+                editor,
+                &DeployGutterContextMenu { buffer_row },
+                cx,
+            );
+            cx.stop_propagation();
+            return;
+        }
         if !text_bounds.contains(&event.position) {
             return;
         }
@@ -455,6 +566,24 @@ impl EditorElement {
         cx.stop_propagation();
     }
 
+    fn mouse_middle_down(
+        editor: &mut Editor,
+        event: &MouseDownEvent,
+        position_map: &PositionMap,
+        text_bounds: Bounds<Pixels>,
+        cx: &mut ViewContext<Editor>,
+    ) {
+        if !text_bounds.contains(&event.position) {
+            return;
+        }
+        let Some(item) = cx.read_from_primary() else {
+            return;
+        };
+        let point_for_position = position_map.point_for_position(text_bounds, event.position);
+        editor.paste_primary_selection_at(point_for_position.previous_valid, item.text(), cx);
+        cx.stop_propagation();
+    }
+
     fn mouse_up(
         editor: &mut Editor,
         event: &MouseUpEvent,
@@ -464,6 +593,17 @@ impl EditorElement {
         stacking_order: &StackingOrder,
         cx: &mut ViewContext<Editor>,
     ) {
+        if editor.selection_drag.is_some() {
+            let copy = if cfg!(target_os = "macos") {
+                event.modifiers.alt
+            } else {
+                event.modifiers.control
+            };
+            editor.end_selection_drag(copy, cx);
+            cx.stop_propagation();
+            return;
+        }
+
         let end_selection = editor.has_pending_selection();
         let pending_nonempty_selections = editor.has_pending_nonempty_selection();
 
@@ -471,15 +611,16 @@ impl EditorElement {
             editor.select(SelectPhase::End, cx);
         }
 
+        let multi_cursor_modifier = EditorSettings::get_global(cx).multi_cursor_modifier;
         if interactive_bounds.visibly_contains(&event.position, cx)
             && !pending_nonempty_selections
-            && event.modifiers.command
+            && multi_cursor_modifier.goes_to_definition(&event.modifiers)
             && text_bounds.contains(&event.position)
             && cx.was_top_layer(&event.position, stacking_order)
         {
             let point = position_map.point_for_position(text_bounds, event.position);
             let could_be_inlay = point.as_valid().is_none();
-            let split = event.modifiers.alt;
+            let split = multi_cursor_modifier.adds_cursor(&event.modifiers);
             if event.modifiers.shift || could_be_inlay {
                 go_to_fetched_type_definition(editor, point, split, cx);
             } else {
@@ -501,11 +642,16 @@ impl EditorElement {
         _stacking_order: &StackingOrder,
         cx: &mut ViewContext<Editor>,
     ) {
+        let point_for_position = position_map.point_for_position(text_bounds, event.position);
+        if editor.selection_drag.is_some() {
+            editor.update_selection_drag(point_for_position.previous_valid, cx);
+            return;
+        }
+
         if !editor.has_pending_selection() {
             return;
         }
 
-        let point_for_position = position_map.point_for_position(text_bounds, event.position);
         let mut scroll_delta = gpui::Point::<f32>::default();
         let vertical_margin = position_map.line_height.min(text_bounds.size.height / 3.0);
         let top = text_bounds.origin.y + vertical_margin;
@@ -547,6 +693,9 @@ impl EditorElement {
         cx: &mut ViewContext<Editor>,
     ) {
         let modifiers = event.modifiers;
+        let go_to_definition_held = EditorSettings::get_global(cx)
+            .multi_cursor_modifier
+            .goes_to_definition(&modifiers);
         let text_hovered = text_bounds.contains(&event.position);
         let gutter_hovered = gutter_bounds.contains(&event.position);
         let was_top = cx.was_top_layer(&event.position, stacking_order);
@@ -562,7 +711,7 @@ impl EditorElement {
                     update_go_to_definition_link(
                         editor,
                         Some(GoToDefinitionTrigger::Text(point)),
-                        modifiers.command,
+                        go_to_definition_held,
                         modifiers.shift,
                         cx,
                     );
@@ -574,14 +723,14 @@ impl EditorElement {
                         &position_map.snapshot,
                         point_for_position,
                         editor,
-                        modifiers.command,
+                        go_to_definition_held,
                         modifiers.shift,
                         cx,
                     );
                 }
             }
         } else {
-            update_go_to_definition_link(editor, None, modifiers.command, modifiers.shift, cx);
+            update_go_to_definition_link(editor, None, go_to_definition_held, modifiers.shift, cx);
             hover_at(editor, None, cx);
             if gutter_hovered && was_top {
                 cx.stop_propagation();
@@ -738,6 +887,20 @@ impl EditorElement {
             Self::paint_diff_hunks(bounds, layout, cx);
         }
 
+        // Bookmarks are marked with a dot between the diff hunks and the line numbers
+        let bookmark_size = 0.4 * line_height;
+        for row in &layout.bookmarked_rows {
+            let y = *row as f32 * line_height - scroll_top + (line_height - bookmark_size) / 2.;
+            let origin = bounds.origin + point(0.35 * line_height, y);
+            cx.paint_quad(quad(
+                Bounds::new(origin, size(bookmark_size, bookmark_size)),
+                Corners::all(bookmark_size / 2.),
+                cx.theme().status().info,
+                Edges::default(),
+                transparent_black(),
+            ));
+        }
+
         for (ix, line) in layout.line_numbers.iter().enumerate() {
             if let Some(line) = line {
                 let line_origin = bounds.origin
@@ -1137,6 +1300,31 @@ impl EditorElement {
                     }
                 }
 
+                // Show where dragged text would be dropped
+                if let Some(drop_position) = self.editor.read(cx).selection_drop_position() {
+                    if layout
+                        .visible_display_row_range
+                        .contains(&drop_position.row())
+                    {
+                        let row_layout = &layout.position_map.line_layouts
+                            [(drop_position.row() - start_row) as usize]
+                            .line;
+                        let x = row_layout.x_for_index(drop_position.column() as usize)
+                            - layout.position_map.scroll_position.x;
+                        let y = drop_position.row() as f32 * layout.position_map.line_height
+                            - layout.position_map.scroll_position.y;
+                        cursors.push(Cursor {
+                            color: self.style.local_player.cursor,
+                            block_width: layout.position_map.em_width,
+                            origin: point(x, y),
+                            line_height: layout.position_map.line_height,
+                            shape: CursorShape::Bar,
+                            block_text: None,
+                            cursor_name: None,
+                        });
+                    }
+                }
+
                 for (ix, line_with_invisibles) in
                     layout.position_map.line_layouts.iter().enumerate()
                 {
@@ -1151,6 +1339,21 @@ impl EditorElement {
                     )
                 }
 
+                if let Some((row, line)) = layout.inline_blame.as_ref() {
+                    let line_layout =
+                        &layout.position_map.line_layouts[(*row - start_row) as usize].line;
+                    let origin = point(
+                        content_origin.x
+                            + line_layout.width
+                            + INLINE_BLAME_PADDING_EMS * layout.position_map.em_width
+                            - layout.position_map.scroll_position.x,
+                        content_origin.y + *row as f32 * layout.position_map.line_height
+                            - layout.position_map.scroll_position.y,
+                    );
+                    line.paint(origin, layout.position_map.line_height, cx)
+                        .log_err();
+                }
+
                 cx.with_z_index(0, |cx| {
                     for cursor in cursors {
                         cursor.paint(content_origin, cx);
@@ -1168,6 +1371,31 @@ impl EditorElement {
     ) {
         let content_origin = text_bounds.origin + point(layout.gutter_margin, Pixels::ZERO);
         let start_row = layout.visible_display_row_range.start;
+        if let Some((position, mut signature_help)) = layout.signature_help.take() {
+            let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
+            let signature_help_size = signature_help.measure(available_space, cx);
+
+            let cursor_row_layout =
+                &layout.position_map.line_layouts[(position.row() - start_row) as usize].line;
+            let x = cursor_row_layout.x_for_index(position.column() as usize)
+                - layout.position_map.scroll_position.x;
+            let y = position.row() as f32 * layout.position_map.line_height
+                - layout.position_map.scroll_position.y;
+            let mut origin = content_origin + point(x, y - signature_help_size.height);
+
+            if origin.x + signature_help_size.width > text_bounds.upper_right().x {
+                origin.x = (text_bounds.upper_right().x - signature_help_size.width)
+                    .max(text_bounds.origin.x);
+            }
+
+            // Show the signature help below the cursor when there's no room above it
+            if origin.y < text_bounds.origin.y {
+                origin.y += signature_help_size.height + layout.position_map.line_height;
+            }
+
+            cx.break_content_mask(|cx| signature_help.draw(origin, available_space, cx));
+        }
+
         if let Some((position, mut context_menu)) = layout.context_menu.take() {
             let available_space = size(AvailableSpace::MinContent, AvailableSpace::MinContent);
             let context_menu_size = context_menu.measure(available_space, cx);
@@ -1258,11 +1486,12 @@ impl EditorElement {
         }
 
         if let Some(mouse_context_menu) = self.editor.read(cx).mouse_context_menu.as_ref() {
+            // Open upward or leftward from the click when the menu would otherwise
+            // overflow the bottom or right edge of the window.
             let element = overlay()
                 .position(mouse_context_menu.position)
                 .child(mouse_context_menu.context_menu.clone())
-                .anchor(AnchorCorner::TopLeft)
-                .snap_to_window();
+                .anchor(AnchorCorner::TopLeft);
             element.into_any().draw(
                 gpui::Point::default(),
                 size(AvailableSpace::MinContent, AvailableSpace::MinContent),
@@ -1271,6 +1500,457 @@ impl EditorElement {
         }
     }
 
+    /// Lays out the first lines of the scopes, found by the language's outline query, that
+    /// contain the row at the top of the editor. A scope is only pinned while its first
+    /// line is scrolled out of view and its last line is still below the pinned lines.
+    fn layout_sticky_headers(
+        &self,
+        scroll_top: f32,
+        snapshot: &EditorSnapshot,
+        cx: &ViewContext<Editor>,
+    ) -> Vec<StickyHeader> {
+        let settings = EditorSettings::get_global(cx).sticky_scroll;
+        if snapshot.mode != EditorMode::Full || !settings.enabled || scroll_top <= 0. {
+            return Vec::new();
+        }
+
+        let display_snapshot = &snapshot.display_snapshot;
+        let top_row = scroll_top.ceil() as u32;
+        let top = DisplayPoint::new(top_row, 0).to_point(display_snapshot);
+        let Some((_, scopes)) = snapshot.buffer_snapshot.symbols_containing(top, None) else {
+            return Vec::new();
+        };
+
+        let mut rows = Vec::new();
+        for scope in scopes {
+            if rows.len() >= settings.max_depth {
+                break;
+            }
+            let row_below_headers = top_row + rows.len() as u32;
+            let start_row = scope.range.start.to_display_point(display_snapshot).row();
+            let end_row = scope.range.end.to_display_point(display_snapshot).row();
+            if start_row >= row_below_headers || end_row <= row_below_headers {
+                break;
+            }
+            rows.push((start_row, scope.range.start));
+        }
+
+        let font_size = self.style.text.font_size.to_pixels(cx.rem_size());
+        rows.into_iter()
+            .filter_map(|(row, target)| {
+                let line = self
+                    .layout_lines(row..row + 1, &[], snapshot, cx)
+                    .pop()?
+                    .line;
+                let line_number = snapshot.buffer_rows(row).next().flatten().and_then(|row| {
+                    let number = (row + 1).to_string();
+                    let run = TextRun {
+                        len: number.len(),
+                        font: self.style.text.font(),
+                        color: cx.theme().colors().editor_line_number,
+                        background_color: None,
+                        underline: None,
+                    };
+                    cx.text_system()
+                        .shape_line(number.into(), font_size, &[run])
+                        .log_err()
+                });
+                Some(StickyHeader {
+                    line,
+                    line_number,
+                    target,
+                })
+            })
+            .collect()
+    }
+
+    fn paint_sticky_headers(
+        &mut self,
+        gutter_bounds: Bounds<Pixels>,
+        text_bounds: Bounds<Pixels>,
+        layout: &LayoutState,
+        cx: &mut ElementContext,
+    ) {
+        if layout.sticky_headers.is_empty() {
+            return;
+        }
+
+        let line_height = layout.position_map.line_height;
+        let content_origin = text_bounds.origin + point(layout.gutter_margin, Pixels::ZERO);
+        let bounds = Bounds::from_corners(
+            gutter_bounds.origin,
+            point(
+                text_bounds.right(),
+                text_bounds.top() + line_height * layout.sticky_headers.len() as f32,
+            ),
+        );
+        cx.paint_quad(quad(
+            bounds,
+            Corners::default(),
+            cx.theme().colors().editor_background,
+            Edges {
+                top: Pixels::ZERO,
+                right: Pixels::ZERO,
+                bottom: px(1.),
+                left: Pixels::ZERO,
+            },
+            cx.theme().colors().border_variant,
+        ));
+
+        for (ix, header) in layout.sticky_headers.iter().enumerate() {
+            let y = text_bounds.top() + line_height * ix as f32;
+            if let Some(line_number) = &header.line_number {
+                let origin = point(
+                    gutter_bounds.right() - line_number.width - layout.gutter_padding,
+                    y,
+                );
+                line_number.paint(origin, line_height, cx).log_err();
+            }
+            cx.with_content_mask(
+                Some(ContentMask {
+                    bounds: text_bounds,
+                }),
+                |cx| {
+                    let origin = point(content_origin.x - layout.position_map.scroll_position.x, y);
+                    header.line.paint(origin, line_height, cx).log_err();
+                },
+            );
+        }
+
+        let interactive_bounds = InteractiveBounds {
+            bounds,
+            stacking_order: cx.stacking_order().clone(),
+        };
+        if interactive_bounds.visibly_contains(&cx.mouse_position(), cx) {
+            cx.set_cursor_style(CursorStyle::PointingHand);
+        }
+
+        let targets = layout
+            .sticky_headers
+            .iter()
+            .map(|header| header.target)
+            .collect::<Vec<_>>();
+        cx.on_mouse_event({
+            let editor = self.editor.clone();
+            move |event: &MouseDownEvent, phase, cx| {
+                if phase == DispatchPhase::Capture
+                    || event.button != MouseButton::Left
+                    || !interactive_bounds.visibly_contains(&event.position, cx)
+                {
+                    return;
+                }
+
+                let ix = ((event.position.y - bounds.top()) / line_height) as usize;
+                let Some(target) = targets.get(ix).copied() else {
+                    return;
+                };
+                editor.update(cx, |editor, cx| {
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([target..target])
+                    });
+                    cx.stop_propagation();
+                });
+            }
+        });
+    }
+
+    /// The width of the minimap, if it is shown in an editor of the given height.
+    fn minimap_width(
+        &self,
+        snapshot: &EditorSnapshot,
+        height_in_lines: f32,
+        cx: &AppContext,
+    ) -> Option<Pixels> {
+        if snapshot.mode != EditorMode::Full {
+            return None;
+        }
+
+        let settings = EditorSettings::get_global(cx).minimap;
+        let show = match settings.show {
+            ShowMinimap::Auto => snapshot.max_point().row() as f32 + 1. > height_in_lines,
+            ShowMinimap::Always => true,
+            ShowMinimap::Never => false,
+        };
+        show.then(|| px(settings.width.max(0.)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn layout_minimap(
+        &self,
+        bounds: Bounds<Pixels>,
+        width: Pixels,
+        scroll_top: f32,
+        height_in_lines: f32,
+        snapshot: &EditorSnapshot,
+        editor: &Editor,
+        cx: &ViewContext<Editor>,
+    ) -> MinimapLayout {
+        let right = bounds.right() - self.style.scrollbar_width;
+        let minimap_bounds = Bounds::from_corners(
+            point(right - width, bounds.top()),
+            point(right, bounds.bottom()),
+        );
+
+        // When the content doesn't fit in the minimap, the minimap scrolls along with the
+        // editor, reaching its top and bottom as the editor does.
+        let max_row = snapshot.max_point().row();
+        let row_count = ((bounds.size.height / MINIMAP_ROW_HEIGHT).floor() as u32).max(1);
+        let overflowing_rows = (max_row + 1).saturating_sub(row_count);
+        let start_row = if overflowing_rows > 0 {
+            let scroll_fraction = (scroll_top / max_row as f32).clamp(0., 1.);
+            (scroll_fraction * overflowing_rows as f32).round() as u32
+        } else {
+            0
+        };
+        let end_row = (start_row + row_count).min(max_row + 1);
+        let rows_per_pixel = if overflowing_rows > 0 && row_count > 1 {
+            max_row as f32 / ((row_count - 1) as f32 * f32::from(MINIMAP_ROW_HEIGHT))
+        } else {
+            1. / f32::from(MINIMAP_ROW_HEIGHT)
+        };
+        let y_for_row = |row: f32| bounds.top() + MINIMAP_ROW_HEIGHT * (row - start_row as f32);
+
+        // Lay out each run of non-whitespace characters as a block in the color of its text.
+        let max_columns = (width / MINIMAP_COLUMN_WIDTH).floor() as usize;
+        let mut text_blocks = Vec::<(Bounds<Pixels>, Hsla)>::new();
+        let mut push_block = |row: u32, columns: Range<usize>, color: Hsla| {
+            if columns.start >= max_columns {
+                return;
+            }
+            let top = y_for_row(row as f32);
+            let left = minimap_bounds.left() + MINIMAP_COLUMN_WIDTH * columns.start as f32;
+            let right =
+                minimap_bounds.left() + MINIMAP_COLUMN_WIDTH * columns.end.min(max_columns) as f32;
+            if let Some((last_bounds, last_color)) = text_blocks.last_mut() {
+                if *last_color == color && last_bounds.top() == top && last_bounds.right() == left {
+                    last_bounds.size.width = right - last_bounds.left();
+                    return;
+                }
+            }
+            text_blocks.push((
+                Bounds::from_corners(point(left, top), point(right, top + MINIMAP_ROW_HEIGHT)),
+                color,
+            ));
+        };
+        let mut row = start_row;
+        let mut column = 0;
+        for chunk in snapshot.highlighted_chunks(start_row..end_row, true, &self.style) {
+            let mut color = chunk
+                .style
+                .and_then(|style| style.color)
+                .unwrap_or(self.style.text.color);
+            color.fade_out(MINIMAP_TEXT_FADE);
+            for (ix, line) in chunk.chunk.split('\n').enumerate() {
+                if ix > 0 {
+                    row += 1;
+                    column = 0;
+                }
+                let mut block_start = None;
+                for character in line.chars() {
+                    if character.is_whitespace() {
+                        if let Some(start) = block_start.take() {
+                            push_block(row, start..column, color);
+                        }
+                    } else if block_start.is_none() {
+                        block_start = Some(column);
+                    }
+                    column += 1;
+                }
+                if let Some(start) = block_start {
+                    push_block(row, start..column, color);
+                }
+            }
+        }
+
+        let display_snapshot = &snapshot.display_snapshot;
+        let start_point = DisplayPoint::new(start_row, 0).to_point(display_snapshot);
+        let end_point = if end_row > max_row {
+            snapshot.buffer_snapshot.max_point()
+        } else {
+            DisplayPoint::new(end_row, 0).to_point(display_snapshot)
+        };
+        let row_bounds = |rows: Range<u32>| {
+            Bounds::from_corners(
+                point(minimap_bounds.left(), y_for_row(rows.start as f32)),
+                point(minimap_bounds.right(), y_for_row(rows.end as f32)),
+            )
+        };
+        let search_marks = editor
+            .background_highlight_row_ranges::<BufferSearchHighlights>(
+                snapshot.buffer_snapshot.anchor_before(start_point)
+                    ..snapshot.buffer_snapshot.anchor_after(end_point),
+                display_snapshot,
+                50000,
+            )
+            .into_iter()
+            .map(|range| row_bounds(range.start().row()..range.end().row() + 1))
+            .collect();
+
+        let diagnostic_marks = snapshot
+            .buffer_snapshot
+            .diagnostics_in_range::<_, Point>(start_point..end_point, false)
+            .filter(|entry| entry.diagnostic.is_primary)
+            .filter_map(|entry| {
+                let color = match entry.diagnostic.severity {
+                    lsp::DiagnosticSeverity::ERROR => cx.theme().status().error,
+                    lsp::DiagnosticSeverity::WARNING => cx.theme().status().warning,
+                    _ => return None,
+                };
+                let row = entry.range.start.to_display_point(display_snapshot).row();
+                let mut bounds = row_bounds(row..row + 1);
+                bounds.size.width = MINIMAP_COLUMN_WIDTH * 4.;
+                Some((bounds, color))
+            })
+            .collect();
+
+        let thumb_bounds = Bounds::from_corners(
+            point(
+                minimap_bounds.left(),
+                y_for_row(scroll_top).max(minimap_bounds.top()),
+            ),
+            point(
+                minimap_bounds.right(),
+                y_for_row(scroll_top + height_in_lines).min(minimap_bounds.bottom()),
+            ),
+        );
+
+        MinimapLayout {
+            bounds: minimap_bounds,
+            thumb_bounds,
+            start_row,
+            max_row,
+            height_in_lines,
+            rows_per_pixel,
+            text_blocks,
+            search_marks,
+            diagnostic_marks,
+        }
+    }
+
+    fn paint_minimap(&mut self, layout: &LayoutState, cx: &mut ElementContext) {
+        let Some(minimap) = layout.minimap.as_ref() else {
+            return;
+        };
+
+        // If a drag took place after we started dragging the minimap, cancel the minimap drag.
+        if cx.has_active_drag() {
+            self.editor.update(cx, |editor, cx| {
+                editor.scroll_manager.set_is_dragging_minimap(false, cx);
+            });
+        }
+
+        let background = cx.theme().colors().editor_background;
+        let border = cx.theme().colors().scrollbar_track_border;
+        let thumb = cx.theme().colors().scrollbar_thumb_background;
+        let mut search_color = cx.theme().status().info;
+        search_color.fade_out(0.5);
+        cx.paint_quad(quad(
+            minimap.bounds,
+            Corners::default(),
+            background,
+            Edges {
+                top: Pixels::ZERO,
+                right: Pixels::ZERO,
+                bottom: Pixels::ZERO,
+                left: px(1.),
+            },
+            border,
+        ));
+        cx.paint_quad(fill(minimap.thumb_bounds, thumb));
+        for bounds in &minimap.search_marks {
+            cx.paint_quad(fill(*bounds, search_color));
+        }
+        for (bounds, color) in &minimap.text_blocks {
+            cx.paint_quad(fill(*bounds, *color));
+        }
+        for (bounds, color) in &minimap.diagnostic_marks {
+            cx.paint_quad(fill(*bounds, *color));
+        }
+
+        let interactive_bounds = InteractiveBounds {
+            bounds: minimap.bounds,
+            stacking_order: cx.stacking_order().clone(),
+        };
+        if interactive_bounds.visibly_contains(&cx.mouse_position(), cx) {
+            cx.set_cursor_style(CursorStyle::Arrow);
+        }
+
+        if self.editor.read(cx).scroll_manager.is_dragging_minimap() {
+            let mut mouse_position = cx.mouse_position();
+            let rows_per_pixel = minimap.rows_per_pixel;
+            cx.on_mouse_event({
+                let editor = self.editor.clone();
+                move |event: &MouseMoveEvent, phase, cx| {
+                    if phase == DispatchPhase::Capture {
+                        return;
+                    }
+
+                    editor.update(cx, |editor, cx| {
+                        if event.pressed_button == Some(MouseButton::Left) {
+                            let mut position = editor.scroll_position(cx);
+                            position.y +=
+                                f32::from(event.position.y - mouse_position.y) * rows_per_pixel;
+                            if position.y < 0.0 {
+                                position.y = 0.0;
+                            }
+                            editor.set_scroll_position(position, cx);
+                            mouse_position = event.position;
+                            cx.stop_propagation();
+                        } else {
+                            editor.scroll_manager.set_is_dragging_minimap(false, cx);
+                        }
+                    })
+                }
+            });
+            cx.on_mouse_event({
+                let editor = self.editor.clone();
+                move |_: &MouseUpEvent, phase, cx| {
+                    if phase == DispatchPhase::Capture {
+                        return;
+                    }
+
+                    editor.update(cx, |editor, cx| {
+                        editor.scroll_manager.set_is_dragging_minimap(false, cx);
+                        cx.stop_propagation();
+                    });
+                }
+            });
+        } else {
+            let bounds = minimap.bounds;
+            let thumb_bounds = minimap.thumb_bounds;
+            let start_row = minimap.start_row;
+            let max_row = minimap.max_row;
+            let height_in_lines = minimap.height_in_lines;
+            cx.on_mouse_event({
+                let editor = self.editor.clone();
+                move |event: &MouseDownEvent, phase, cx| {
+                    if phase == DispatchPhase::Capture
+                        || event.button != MouseButton::Left
+                        || !interactive_bounds.visibly_contains(&event.position, cx)
+                    {
+                        return;
+                    }
+
+                    editor.update(cx, |editor, cx| {
+                        editor.scroll_manager.set_is_dragging_minimap(true, cx);
+
+                        // Clicking outside of the visible part of the content centers the
+                        // editor on the clicked row, after which the thumb can be dragged.
+                        if !thumb_bounds.contains(&event.position) {
+                            let row = start_row as f32
+                                + (event.position.y - bounds.top()) / MINIMAP_ROW_HEIGHT;
+                            let mut position = editor.scroll_position(cx);
+                            position.y = (row - height_in_lines / 2.).clamp(0., max_row as f32);
+                            editor.set_scroll_position(position, cx);
+                        }
+
+                        cx.stop_propagation();
+                    });
+                }
+            });
+        }
+    }
+
     fn scrollbar_left(&self, bounds: &Bounds<Pixels>) -> Pixels {
         bounds.upper_right().x - self.style.scrollbar_width
     }
@@ -1848,8 +2528,12 @@ impl EditorElement {
             let gutter_dimensions = snapshot.gutter_dimensions(font_id, font_size, em_width, self.max_line_number_width(&snapshot, cx), cx);
 
             editor.gutter_width = gutter_dimensions.width;
+            editor.last_bounds = Some(bounds);
 
-            let text_width = bounds.size.width - gutter_dimensions.width;
+            let minimap_width = self.minimap_width(&snapshot, bounds.size.height / line_height, cx);
+            let text_width = bounds.size.width
+                - gutter_dimensions.width
+                - minimap_width.map_or(Pixels::ZERO, |width| width + style.scrollbar_width);
             let overscroll = size(em_width, px(0.));
             let _snapshot = {
                 editor.set_visible_line_count((bounds.size.height / line_height).into(), cx);
@@ -1911,6 +2595,11 @@ impl EditorElement {
             let is_singleton = editor.is_singleton(cx);
 
             let highlighted_rows = editor.highlighted_rows();
+            let bookmarked_rows = bookmarks::bookmarked_display_rows(
+                editor,
+                start_row..end_row,
+                &snapshot.display_snapshot,
+            );
             let highlighted_ranges = editor.background_highlights_in_range(
                 start_anchor..end_anchor,
                 &snapshot.display_snapshot,
@@ -2127,6 +2816,20 @@ impl EditorElement {
                 snapshot = editor.snapshot(cx);
             }
 
+            let minimap = minimap_width.map(|width| {
+                self.layout_minimap(
+                    bounds,
+                    width,
+                    scroll_position.y,
+                    height_in_lines,
+                    &snapshot,
+                    editor,
+                    cx,
+                )
+            });
+
+            let sticky_headers = self.layout_sticky_headers(scroll_position.y, &snapshot, cx);
+
             let mut context_menu = None;
             let mut code_actions_indicator = None;
             if let Some(newest_selection_head) = newest_selection_head {
@@ -2167,7 +2870,30 @@ impl EditorElement {
                     .max(MIN_POPOVER_LINE_HEIGHT * line_height), // Apply minimum height of 4 lines
             );
 
-            let hover = if context_menu.is_some() {
+            let signature_help = newest_selection_head
+                .filter(|head| visible_rows.contains(&head.row()))
+                .and_then(|head| editor.signature_help.render(head, &style, max_size, cx));
+
+            let inline_blame = newest_selection_head
+                .filter(|head| visible_rows.contains(&head.row()) && editor.is_focused(cx))
+                .and_then(|head| {
+                    let text = blame::inline_blame_text(editor, cx)?;
+                    let run = TextRun {
+                        len: text.len(),
+                        font: self.style.text.font(),
+                        color: cx.theme().colors().text_muted,
+                        background_color: None,
+                        underline: None,
+                    };
+                    let line = cx
+                        .text_system()
+                        .shape_line(text.into(), font_size, &[run])
+                        .log_err()?;
+                    Some((head.row(), line))
+                });
+
+            // Hover popovers would cover the signature help, which is also shown above the cursor
+            let hover = if context_menu.is_some() || signature_help.is_some() {
                 None
             } else {
                 editor.hover_state.render(
@@ -2256,6 +2982,7 @@ impl EditorElement {
                 highlighted_ranges,
                 line_numbers,
                 display_hunks,
+                bookmarked_rows,
                 blocks,
                 selections,
                 context_menu,
@@ -2264,6 +2991,10 @@ impl EditorElement {
                 tab_invisible,
                 space_invisible,
                 hover_popovers: hover,
+                signature_help,
+                inline_blame,
+                sticky_headers,
+                minimap,
             }
         })
     }
@@ -2610,7 +3341,17 @@ impl EditorElement {
                             );
                         }),
                         MouseButton::Right => editor.update(cx, |editor, cx| {
-                            Self::mouse_right_down(editor, event, &position_map, text_bounds, cx);
+                            Self::mouse_right_down(
+                                editor,
+                                event,
+                                &position_map,
+                                text_bounds,
+                                gutter_bounds,
+                                cx,
+                            );
+                        }),
+                        MouseButton::Middle => editor.update(cx, |editor, cx| {
+                            Self::mouse_middle_down(editor, event, &position_map, text_bounds, cx);
                         }),
                         _ => {}
                     };
@@ -2996,6 +3737,8 @@ impl Element for EditorElement {
                             }
 
                             cx.with_z_index(1, |cx| {
+                                self.paint_sticky_headers(gutter_bounds, text_bounds, &layout, cx);
+                                self.paint_minimap(&layout, cx);
                                 self.paint_overlays(text_bounds, &mut layout, cx);
                             });
 
@@ -3036,6 +3779,8 @@ pub struct LayoutState {
     highlighted_rows: Option<Range<u32>>,
     line_numbers: Vec<Option<ShapedLine>>,
     display_hunks: Vec<DisplayDiffHunk>,
+    /// The visible display rows whose lines are bookmarked.
+    bookmarked_rows: Vec<u32>,
     blocks: Vec<BlockLayout>,
     highlighted_ranges: Vec<(Range<DisplayPoint>, Hsla)>,
     selections: Vec<(PlayerColor, Vec<SelectionLayout>)>,
@@ -3046,11 +3791,38 @@ pub struct LayoutState {
     context_menu: Option<(DisplayPoint, AnyElement)>,
     code_actions_indicator: Option<CodeActionsIndicator>,
     hover_popovers: Option<(DisplayPoint, Vec<AnyElement>)>,
+    signature_help: Option<(DisplayPoint, AnyElement)>,
+    /// The blame of the newest cursor's line, shown at the end of the line.
+    inline_blame: Option<(u32, ShapedLine)>,
+    sticky_headers: Vec<StickyHeader>,
+    minimap: Option<MinimapLayout>,
     fold_indicators: Vec<Option<IconButton>>,
     tab_invisible: ShapedLine,
     space_invisible: ShapedLine,
 }
 
+/// The first line of a scope that the top of the editor is scrolled into, which is pinned
+/// to the top of the editor.
+struct StickyHeader {
+    line: ShapedLine,
+    line_number: Option<ShapedLine>,
+    /// The start of the scope, which clicking the pinned line jumps to.
+    target: Anchor,
+}
+
+struct MinimapLayout {
+    bounds: Bounds<Pixels>,
+    thumb_bounds: Bounds<Pixels>,
+    start_row: u32,
+    max_row: u32,
+    height_in_lines: f32,
+    /// How many rows the editor scrolls by as the thumb is dragged by a pixel.
+    rows_per_pixel: f32,
+    text_blocks: Vec<(Bounds<Pixels>, Hsla)>,
+    search_marks: Vec<Bounds<Pixels>>,
+    diagnostic_marks: Vec<(Bounds<Pixels>, Hsla)>,
+}
+
 struct CodeActionsIndicator {
     row: u32,
     button: IconButton,
@@ -3433,12 +4205,14 @@ mod tests {
     use super::*;
     use crate::{
         display_map::{BlockDisposition, BlockProperties},
+        editor_settings::{MinimapContent, StickyScrollContent},
         editor_tests::{init_test, update_test_language_settings},
         Editor, MultiBuffer,
     };
     use gpui::TestAppContext;
-    use language::language_settings;
+    use language::{language_settings, Buffer, Language, LanguageConfig};
     use log::info;
+    use settings::SettingsStore;
     use std::{num::NonZeroU32, sync::Arc};
     use util::test::sample_text;
 
@@ -3732,6 +4506,197 @@ mod tests {
         .unwrap()
     }
 
+    #[gpui::test]
+    fn test_minimap_layout(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.minimap = Some(MinimapContent {
+                        show: Some(ShowMinimap::Always),
+                        width: Some(80.),
+                    });
+                });
+            })
+        });
+
+        let window = cx.add_window(|cx| {
+            let buffer = MultiBuffer::build_simple("fn main() {\n    let  a = 1;\n}", cx);
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style.clone());
+        let bounds = Bounds {
+            origin: point(px(0.), px(0.)),
+            size: size(px(500.), px(500.)),
+        };
+        let state = cx
+            .update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| element.compute_layout(bounds, cx))
+                })
+            })
+            .unwrap();
+
+        // The text is narrowed to make room for the minimap, which is next to the scrollbar
+        let minimap = state.minimap.as_ref().unwrap();
+        assert_eq!(minimap.bounds.size.width, px(80.));
+        assert_eq!(minimap.bounds.right(), px(500.) - style.scrollbar_width);
+        assert_eq!(
+            state.text_size.width,
+            px(500.) - state.gutter_size.width - px(80.) - style.scrollbar_width
+        );
+
+        // Each run of non-whitespace characters is laid out as a block on its row
+        let blocks = minimap
+            .text_blocks
+            .iter()
+            .map(|(bounds, _)| {
+                (
+                    ((bounds.top() - minimap.bounds.top()) / MINIMAP_ROW_HEIGHT) as u32,
+                    ((bounds.left() - minimap.bounds.left()) / MINIMAP_COLUMN_WIDTH) as usize
+                        ..((bounds.right() - minimap.bounds.left()) / MINIMAP_COLUMN_WIDTH)
+                            as usize,
+                )
+            })
+            .fold(
+                Vec::<(u32, Range<usize>)>::new(),
+                |mut blocks, (row, columns)| {
+                    // Adjacent blocks of different colors form a single run
+                    match blocks.last_mut() {
+                        Some((last_row, last_columns))
+                            if *last_row == row && last_columns.end == columns.start =>
+                        {
+                            last_columns.end = columns.end
+                        }
+                        _ => blocks.push((row, columns)),
+                    }
+                    blocks
+                },
+            );
+        assert_eq!(
+            blocks,
+            &[
+                (0, 0..2),
+                (0, 3..9),
+                (0, 10..11),
+                (1, 4..7),
+                (1, 9..10),
+                (1, 11..12),
+                (1, 13..15),
+                (2, 0..1),
+            ]
+        );
+
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.minimap = Some(MinimapContent {
+                        show: Some(ShowMinimap::Auto),
+                        width: None,
+                    });
+                });
+            })
+        });
+        let state = cx
+            .update_window(window.into(), |view, cx| {
+                cx.with_element_context(|cx| {
+                    cx.with_view_id(view.entity_id(), |cx| element.compute_layout(bounds, cx))
+                })
+            })
+            .unwrap();
+
+        // The content fits in the editor, so there is no need for a minimap
+        assert!(state.minimap.is_none());
+        assert_eq!(state.text_size.width, px(500.) - state.gutter_size.width);
+    }
+
+    #[gpui::test]
+    fn test_sticky_headers_layout(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.sticky_scroll = Some(StickyScrollContent {
+                        enabled: Some(true),
+                        max_depth: Some(5),
+                    });
+                });
+            })
+        });
+
+        let language = Arc::new(
+            Language::new(
+                LanguageConfig::default(),
+                Some(tree_sitter_rust::language()),
+            )
+            .with_outline_query(
+                r#"
+                (impl_item "impl" @context type: (_) @name) @item
+                (function_item "fn" @context name: (_) @name) @item
+                "#,
+            )
+            .unwrap(),
+        );
+        let mut text = "impl Foo {\n    fn bar() {\n".to_string();
+        for _ in 0..20 {
+            text.push_str("        baz();\n");
+        }
+        text.push_str("    }\n}\n");
+
+        let window = cx.add_window(|cx| {
+            let buffer = cx.new_model(|cx| {
+                Buffer::new(0, cx.entity_id().as_u64(), text).with_language(language, cx)
+            });
+            let buffer = cx.new_model(|cx| MultiBuffer::singleton(buffer, cx));
+            Editor::new(EditorMode::Full, buffer, None, cx)
+        });
+        cx.run_until_parked();
+        let editor = window.root(cx).unwrap();
+        let style = cx.update(|cx| editor.read(cx).style().unwrap().clone());
+        let mut element = EditorElement::new(&editor, style);
+        let mut sticky_rows = |scroll_top: f32, cx: &mut TestAppContext| {
+            window
+                .update(cx, |editor, cx| {
+                    editor.set_scroll_position(point(0., scroll_top), cx)
+                })
+                .unwrap();
+            let state = cx
+                .update_window(window.into(), |view, cx| {
+                    cx.with_element_context(|cx| {
+                        cx.with_view_id(view.entity_id(), |cx| {
+                            element.compute_layout(
+                                Bounds {
+                                    origin: point(px(0.), px(0.)),
+                                    size: size(px(500.), px(200.)),
+                                },
+                                cx,
+                            )
+                        })
+                    })
+                })
+                .unwrap();
+            cx.update(|cx| {
+                let snapshot = editor.read(cx).buffer.read(cx).snapshot(cx);
+                state
+                    .sticky_headers
+                    .iter()
+                    .map(|header| header.target.to_point(&snapshot).row)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // Nothing is pinned while the first lines of the scopes are visible
+        assert_eq!(sticky_rows(0., cx), Vec::<u32>::new());
+        assert_eq!(sticky_rows(1., cx), vec![0]);
+        assert_eq!(sticky_rows(5., cx), vec![0, 1]);
+
+        // A scope is unpinned once its last line reaches the pinned lines
+        assert_eq!(sticky_rows(20., cx), vec![0, 1]);
+        assert_eq!(sticky_rows(21., cx), vec![0]);
+    }
+
     #[gpui::test]
     fn test_all_invisibles_drawing(cx: &mut TestAppContext) {
         const TAB_SIZE: u32 = 4;
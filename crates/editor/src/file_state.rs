@@ -0,0 +1,219 @@
+use crate::{persistence::DB, scroll::ScrollAnchor, AnchorRangeExt as _, Editor, EditorMode};
+use gpui::{AppContext, ViewContext};
+use language::{Bias, Point, Selection, SelectionGoal};
+use multi_buffer::ToPoint as _;
+use std::path::PathBuf;
+use util::ResultExt;
+use workspace::WorkspaceId;
+
+/// The scroll position, selections and folds of the editor's file, which are queued to be
+/// saved for the workspace whenever they change and restored when the file is opened again.
+#[derive(Default)]
+pub(crate) struct FileState {
+    /// Whether the state was restored, or didn't have to be, which it's only saved after
+    /// so that it doesn't overwrite the saved state while the editor is being created.
+    restored: bool,
+    /// Whether only the file's folds are restored, as the editor restores its scroll
+    /// position from the state of its own item.
+    folds_only: bool,
+}
+
+impl FileState {
+    /// The state of an editor whose scroll position, selections and folds were copied from
+    /// another editor, rather than from the ones saved for its file.
+    pub(crate) fn already_restored() -> Self {
+        Self {
+            restored: true,
+            folds_only: false,
+        }
+    }
+
+    /// The state of an editor which was restored along with its workspace, whose scroll
+    /// position is restored from its item rather than from the last editor of its file.
+    pub(crate) fn folds_only() -> Self {
+        Self {
+            restored: false,
+            folds_only: true,
+        }
+    }
+}
+
+/// The workspace that the editor's file state is saved for, along with the file's path.
+/// Files are identified by their absolute path, as worktree ids don't persist across
+/// sessions of the workspace.
+fn file_state_key(editor: &Editor, cx: &AppContext) -> Option<(WorkspaceId, PathBuf)> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    let (_, workspace_id) = editor.workspace.as_ref()?;
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+    let file = buffer.read(cx).file()?.as_local()?;
+    Some((*workspace_id, file.abs_path(cx)))
+}
+
+/// Restores the state that was saved the last time the editor's file was open in the
+/// workspace, unless it was restored before.
+pub(crate) fn restore_file_state(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> Option<()> {
+    if std::mem::replace(&mut editor.file_state.restored, true) {
+        return None;
+    }
+    let (workspace_id, path) = file_state_key(editor, cx)?;
+    let (top_row, x, y) = DB
+        .get_file_scroll_position(workspace_id, &path)
+        .log_err()??;
+    let folds = DB.get_file_folds(workspace_id, &path).log_err()?;
+
+    // The file may have changed since its state was saved, so the offsets are clipped to
+    // its current contents
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let len = snapshot.len();
+    let fold_ranges = folds
+        .into_iter()
+        .filter(|(start, end)| start < end && *end <= len)
+        .map(|(start, end)| {
+            snapshot.clip_offset(start, Bias::Left)..snapshot.clip_offset(end, Bias::Right)
+        })
+        .collect::<Vec<_>>();
+    editor.fold_ranges(fold_ranges, false, cx);
+    if editor.file_state.folds_only {
+        return None;
+    }
+
+    let selections = DB.get_file_selections(workspace_id, &path).log_err()?;
+    if !selections.is_empty() {
+        editor.change_selections(None, cx, |s| {
+            let selections = selections
+                .into_iter()
+                .map(|(start, end, reversed)| Selection {
+                    id: s.new_selection_id(),
+                    start: snapshot.clip_offset(start.min(len), Bias::Left),
+                    end: snapshot.clip_offset(end.min(len), Bias::Right),
+                    reversed,
+                    goal: SelectionGoal::None,
+                })
+                .collect::<Vec<_>>();
+            s.select(selections);
+        });
+    }
+
+    let top_row = top_row.min(snapshot.max_point().row);
+    editor.set_scroll_anchor(
+        ScrollAnchor {
+            offset: gpui::Point::new(x, y),
+            anchor: snapshot.anchor_at(Point::new(top_row, 0), Bias::Left),
+        },
+        cx,
+    );
+    None
+}
+
+/// Queues the editor's scroll position, selections and folds to be saved for its file,
+/// replacing the state queued for the file before, so that frequent changes such as
+/// scrolling are coalesced into a single write.
+pub(crate) fn queue_file_state_save(
+    editor: &mut Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if !editor.file_state.restored {
+        return None;
+    }
+    let (workspace_id, path) = file_state_key(editor, cx)?;
+
+    let display_map = editor.display_map.update(cx, |map, cx| map.snapshot(cx));
+    let buffer = &display_map.buffer_snapshot;
+    let scroll_anchor = editor.scroll_manager.anchor();
+    let scroll_position = (
+        scroll_anchor.anchor.to_point(buffer).row,
+        scroll_anchor.offset.x,
+        scroll_anchor.offset.y,
+    );
+    let selections = editor
+        .selections
+        .all::<usize>(cx)
+        .into_iter()
+        .map(|selection| (selection.start, selection.end, selection.reversed))
+        .collect::<Vec<_>>();
+    let folds = display_map
+        .folds_in_range(0..buffer.len())
+        .map(|fold| {
+            let range = fold.range.to_offset(buffer);
+            (range.start, range.end)
+        })
+        .collect::<Vec<_>>();
+    DB.queue_file_state(workspace_id, path, scroll_position, selections, folds);
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_tests::init_test;
+    use gpui::VisualTestContext;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use workspace::{AppState, Workspace, WORKSPACE_DB};
+
+    #[gpui::test]
+    async fn test_file_state_is_restored_when_reopening_a_file(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let fs = FakeFs::new(cx.executor());
+        let text = (0..100)
+            .map(|row| format!("line {row}\n"))
+            .collect::<String>();
+        fs.insert_tree("/dir", json!({ "a.txt": text })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        // The state is saved for the workspace's row in the database, which test workspaces
+        // don't have
+        let workspace_id = WORKSPACE_DB.next_id().await.unwrap();
+        let (workspace, cx) = cx.add_window_view(|cx| {
+            Workspace::new(workspace_id, project.clone(), AppState::test(cx), cx)
+        });
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        let open_editor = |cx: &mut VisualTestContext| {
+            let item = workspace.update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "a.txt"), None, true, cx)
+            });
+            async move { item.await.unwrap().downcast::<Editor>().unwrap() }
+        };
+
+        let editor = open_editor(cx).await;
+        editor.update(cx, |editor, cx| {
+            editor.fold_ranges([Point::new(2, 0)..Point::new(5, 0)], false, cx);
+            editor.change_selections(None, cx, |s| {
+                s.select_ranges([Point::new(40, 1)..Point::new(40, 4)])
+            });
+            editor.set_scroll_position(gpui::Point::new(0., 30.), cx);
+        });
+        DB.flush_queued_writes().await.unwrap();
+
+        workspace
+            .update(cx, |workspace, cx| {
+                workspace.active_pane().update(cx, |pane, cx| {
+                    pane.close_active_item(&Default::default(), cx)
+                })
+            })
+            .unwrap()
+            .await
+            .unwrap();
+
+        let editor = open_editor(cx).await;
+        editor.update(cx, |editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            assert_eq!(
+                snapshot
+                    .folds_in_range(0..snapshot.buffer_snapshot.len())
+                    .map(|fold| fold.range.to_point(&snapshot.buffer_snapshot))
+                    .collect::<Vec<_>>(),
+                [Point::new(2, 0)..Point::new(5, 0)]
+            );
+            assert_eq!(
+                editor.selections.ranges::<Point>(cx),
+                [Point::new(40, 1)..Point::new(40, 4)]
+            );
+            assert_eq!(editor.scroll_position(cx), gpui::Point::new(0., 30.));
+        });
+    }
+}
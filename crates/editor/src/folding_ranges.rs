@@ -0,0 +1,130 @@
+use crate::{Editor, EditorMode};
+use gpui::{Task, ViewContext};
+use std::time::Duration;
+use util::ResultExt;
+
+/// How long to wait after an edit before requesting new folding ranges.
+const FOLDING_RANGES_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Default)]
+pub(crate) struct FoldingRangesState {
+    refresh_task: Option<Task<()>>,
+}
+
+/// Requests the ranges that the buffer's language server suggests folding, which the fold
+/// actions and the gutter use once they arrive.
+pub(crate) fn refresh_folding_ranges(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+
+    editor.folding_ranges.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        if debounce {
+            cx.background_executor()
+                .timer(FOLDING_RANGES_DEBOUNCE)
+                .await;
+        }
+
+        let Some(ranges) = project
+            .update(&mut cx, |project, cx| project.folding_ranges(&buffer, cx))
+            .ok()
+        else {
+            return;
+        };
+        let Some(mut ranges) = ranges.await.log_err() else {
+            return;
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                let snapshot = editor.buffer.read(cx).snapshot(cx);
+                let Some((&excerpt_id, _, buffer_snapshot)) = snapshot.as_singleton() else {
+                    return;
+                };
+                ranges.sort_by(|a, b| a.start.cmp(&b.start, buffer_snapshot));
+                let ranges = ranges
+                    .into_iter()
+                    .map(|range| {
+                        snapshot.anchor_in_excerpt(excerpt_id, range.start)
+                            ..snapshot.anchor_in_excerpt(excerpt_id, range.end)
+                    })
+                    .collect();
+                editor
+                    .display_map
+                    .update(cx, |map, _| map.set_lsp_fold_ranges(ranges));
+                cx.notify();
+            })
+            .log_err();
+    }));
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use futures::StreamExt as _;
+    use indoc::indoc;
+    use language::Point;
+
+    #[gpui::test]
+    async fn test_syntax_and_lsp_fold_ranges(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                folding_range_provider: Some(lsp::FoldingRangeProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        let mut requests =
+            cx.handle_request::<lsp::request::FoldingRangeRequest, _, _>(|_, _, _| async move {
+                Ok(Some(vec![lsp::FoldingRange {
+                    start_line: 1,
+                    end_line: 2,
+                    ..Default::default()
+                }]))
+            });
+        cx.run_until_parked();
+
+        cx.set_state(indoc! {"
+            fn a() {
+                b(
+                    c,
+                );
+            }ˇ
+        "});
+        let foldable_range = |row, cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| editor.snapshot(cx).display_snapshot.foldable_range(row))
+        };
+
+        // Without the language server's ranges, the brackets' contents are folded
+        assert_eq!(
+            foldable_range(0, &mut cx),
+            Some(Point::new(0, 8)..Point::new(4, 0))
+        );
+        assert_eq!(
+            foldable_range(1, &mut cx),
+            Some(Point::new(1, 6)..Point::new(3, 4))
+        );
+
+        cx.executor().advance_clock(FOLDING_RANGES_DEBOUNCE);
+        requests.next().await;
+        cx.run_until_parked();
+        assert_eq!(
+            foldable_range(1, &mut cx),
+            Some(Point::new(1, 6)..Point::new(2, 10))
+        );
+        assert_eq!(
+            foldable_range(0, &mut cx),
+            Some(Point::new(0, 8)..Point::new(4, 0))
+        );
+    }
+}
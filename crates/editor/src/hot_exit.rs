@@ -0,0 +1,217 @@
+use crate::{persistence::DB, Editor, EditorMode};
+use collections::HashMap;
+use futures::{future::join_all, Future};
+use gpui::{AppContext, Subscription, Task, ViewContext};
+use settings::Settings;
+use std::{path::PathBuf, time::Duration};
+use text::Rope;
+use util::ResultExt;
+use workspace::{ItemId, WorkspaceId, WorkspaceSettings};
+
+/// How long to wait after an edit before storing the editor's unsaved contents.
+const UNSAVED_CONTENTS_DEBOUNCE: Duration = Duration::from_millis(1000);
+
+/// The storing of the editor's unsaved contents, which are restored along with its
+/// workspace so that quitting doesn't lose them.
+#[derive(Default)]
+pub(crate) struct HotExitState {
+    store_task: Option<Task<()>>,
+    _release_subscription: Option<Subscription>,
+}
+
+/// The unsaved contents that are yet to be stored, by editor, which are all stored right
+/// away when the app quits. Editors without contents no longer have unsaved changes, so
+/// the contents stored for them are deleted instead.
+#[derive(Default)]
+struct PendingUnsavedContents {
+    contents: HashMap<(ItemId, WorkspaceId), Option<(Option<PathBuf>, Rope)>>,
+    /// Whether the app is quitting, in which case the contents of the editors that are
+    /// released along with their windows are kept.
+    quitting: bool,
+}
+
+pub(crate) fn init(cx: &mut AppContext) {
+    cx.on_app_quit(|cx| {
+        let pending = cx.default_global::<PendingUnsavedContents>();
+        pending.quitting = true;
+        let writes = pending
+            .contents
+            .drain()
+            .map(|(key, contents)| write_unsaved_contents(key, contents))
+            .collect::<Vec<_>>();
+        async move {
+            join_all(writes).await;
+        }
+    })
+    .detach();
+}
+
+/// The editor and workspace that the editor's unsaved contents are stored for, if they
+/// are stored at all.
+fn unsaved_contents_key(editor: &Editor, cx: &AppContext) -> Option<(ItemId, WorkspaceId)> {
+    if editor.mode != EditorMode::Full || !WorkspaceSettings::get_global(cx).hot_exit {
+        return None;
+    }
+    let (_, workspace_id) = editor.workspace.as_ref()?;
+    if !editor.project.as_ref()?.read(cx).is_local() {
+        return None;
+    }
+    editor.buffer.read(cx).as_singleton()?;
+    let item_id = editor.handle.entity_id().as_u64() as ItemId;
+    Some((item_id, *workspace_id))
+}
+
+/// Whether the editor's unsaved changes are kept across restarts.
+pub(crate) fn persists_unsaved_contents(editor: &Editor, cx: &AppContext) -> bool {
+    unsaved_contents_key(editor, cx).is_some()
+}
+
+/// Stores the editor's contents if it has unsaved changes, and deletes the ones stored
+/// before otherwise.
+pub(crate) fn store_unsaved_contents(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    let key = unsaved_contents_key(editor, cx)?;
+    let buffer = editor.buffer.read(cx).as_singleton()?.read(cx);
+    let contents = buffer.is_dirty().then(|| {
+        let path = buffer
+            .file()
+            .and_then(|file| file.as_local())
+            .map(|file| file.abs_path(cx));
+        (path, buffer.as_rope().clone())
+    });
+    cx.default_global::<PendingUnsavedContents>()
+        .contents
+        .insert(key, contents);
+
+    if !debounce {
+        editor.hot_exit.store_task = None;
+        let write = take_pending_write(key, cx)?;
+        cx.background_executor().spawn(write).detach();
+        return None;
+    }
+    editor.hot_exit.store_task = Some(cx.spawn(|_, mut cx| async move {
+        cx.background_executor()
+            .timer(UNSAVED_CONTENTS_DEBOUNCE)
+            .await;
+        if let Ok(Some(write)) = cx.update(|cx| take_pending_write(key, cx)) {
+            write.await;
+        }
+    }));
+    None
+}
+
+/// Deletes the editor's stored contents once it's released, unless that's because the
+/// app is quitting.
+pub(crate) fn forget_unsaved_contents_on_release(
+    editor: &mut Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    let key = unsaved_contents_key(editor, cx)?;
+    editor.hot_exit._release_subscription = Some(cx.on_release(move |_, _, cx| {
+        let pending = cx.default_global::<PendingUnsavedContents>();
+        if pending.quitting {
+            return;
+        }
+        pending.contents.remove(&key);
+        cx.background_executor()
+            .spawn(write_unsaved_contents(key, None))
+            .detach();
+    }));
+    None
+}
+
+/// Deletes the contents that were stored for an editor of a previous session, once they
+/// were restored in a new editor.
+pub(crate) fn delete_restored_unsaved_contents(
+    item_id: ItemId,
+    workspace_id: WorkspaceId,
+    cx: &AppContext,
+) {
+    cx.background_executor()
+        .spawn(write_unsaved_contents((item_id, workspace_id), None))
+        .detach();
+}
+
+fn take_pending_write(
+    key: (ItemId, WorkspaceId),
+    cx: &mut AppContext,
+) -> Option<impl Future<Output = ()>> {
+    let contents = cx
+        .default_global::<PendingUnsavedContents>()
+        .contents
+        .remove(&key)?;
+    Some(write_unsaved_contents(key, contents))
+}
+
+fn write_unsaved_contents(
+    (item_id, workspace_id): (ItemId, WorkspaceId),
+    contents: Option<(Option<PathBuf>, Rope)>,
+) -> impl Future<Output = ()> {
+    async move {
+        match contents {
+            Some((path, text)) => DB
+                .save_unsaved_contents(item_id, workspace_id, path, text.to_string())
+                .await
+                .log_err(),
+            None => DB
+                .delete_unsaved_contents(item_id, workspace_id)
+                .await
+                .log_err(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, Undo};
+    use gpui::{Entity as _, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use workspace::{AppState, Workspace, WORKSPACE_DB};
+
+    #[gpui::test]
+    async fn test_unsaved_contents_are_stored(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/dir", json!({ "a.txt": "one\n" })).await;
+        let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+        // The contents are stored for the workspace's row in the database, which test
+        // workspaces don't have
+        let workspace_id = WORKSPACE_DB.next_id().await.unwrap();
+        let (workspace, cx) = cx.add_window_view(|cx| {
+            Workspace::new(workspace_id, project.clone(), AppState::test(cx), cx)
+        });
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        let editor = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "a.txt"), None, true, cx)
+            })
+            .await
+            .unwrap()
+            .downcast::<Editor>()
+            .unwrap();
+        let item_id = editor.entity_id().as_u64() as ItemId;
+        let stored_contents = |cx: &mut VisualTestContext| {
+            cx.executor().advance_clock(UNSAVED_CONTENTS_DEBOUNCE);
+            cx.run_until_parked();
+            DB.get_unsaved_contents(item_id, workspace_id).unwrap()
+        };
+
+        editor.update(cx, |editor, cx| editor.insert("two ", cx));
+        assert_eq!(
+            stored_contents(cx),
+            Some((Some(PathBuf::from("/dir/a.txt")), "two one\n".to_string()))
+        );
+
+        // Once the buffer no longer has unsaved changes, there's nothing to restore
+        editor.update(cx, |editor, cx| editor.undo(&Undo, cx));
+        assert_eq!(stored_contents(cx), None);
+    }
+}
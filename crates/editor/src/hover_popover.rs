@@ -1,14 +1,15 @@
 use crate::{
     display_map::{InlayOffset, ToDisplayPoint},
     link_go_to_definition::{InlayHighlight, RangeInEditor},
-    Anchor, AnchorRangeExt, DisplayPoint, Editor, EditorSettings, EditorSnapshot, EditorStyle,
-    ExcerptId, Hover, RangeToAnchorExt,
+    scroll::scroll_amount::ScrollAmount,
+    Anchor, AnchorRangeExt, Cancel, DisplayPoint, Editor, EditorSettings, EditorSnapshot,
+    EditorStyle, ExcerptId, Hover, MoveDown, MovePageDown, MovePageUp, MoveUp, RangeToAnchorExt,
 };
 use futures::FutureExt;
 use gpui::{
-    div, px, AnyElement, CursorStyle, Hsla, InteractiveElement, IntoElement, Model, MouseButton,
-    ParentElement, Pixels, SharedString, Size, StatefulInteractiveElement, Styled, Task,
-    ViewContext, WeakView,
+    div, px, AnyElement, CursorStyle, FocusHandle, Hsla, InteractiveElement, IntoElement, Model,
+    MouseButton, ParentElement, Pixels, ScrollHandle, SharedString, Size,
+    StatefulInteractiveElement, Styled, Task, ViewContext, WeakView, WindowContext,
 };
 use language::{markdown, Bias, DiagnosticEntry, Language, LanguageRegistry, ParsedMarkdown};
 
@@ -27,8 +28,14 @@ pub const MIN_POPOVER_CHARACTER_WIDTH: f32 = 20.;
 pub const MIN_POPOVER_LINE_HEIGHT: Pixels = px(4.);
 pub const HOVER_POPOVER_GAP: Pixels = px(10.);
 
-/// Bindable action which uses the most recent selection head to trigger a hover
+/// Bindable action which uses the most recent selection head to trigger a hover,
+/// or focuses the hover popover if it's already shown so that it can be scrolled.
 pub fn hover(editor: &mut Editor, _: &Hover, cx: &mut ViewContext<Editor>) {
+    if let Some(info_popover) = editor.hover_state.info_popover.as_ref() {
+        cx.focus(&info_popover.focus_handle);
+        return;
+    }
+
     let head = editor.selections.newest_display(cx).head();
     show_hover(editor, head, true, cx);
 }
@@ -106,11 +113,14 @@ pub fn hover_at_inlay(editor: &mut Editor, inlay_hover: InlayHover, cx: &mut Vie
                 let blocks = vec![inlay_hover.tooltip];
                 let parsed_content = parse_blocks(&blocks, &language_registry, None).await;
 
+                let focus_handle = this.update(&mut cx, |_, cx| cx.focus_handle())?;
                 let hover_popover = InfoPopover {
                     project: project.clone(),
                     symbol_range: RangeInEditor::Inlay(inlay_hover.range.clone()),
                     blocks,
                     parsed_content,
+                    focus_handle,
+                    scroll_handle: ScrollHandle::new(),
                 };
 
                 this.update(&mut cx, |this, cx| {
@@ -137,6 +147,10 @@ pub fn hover_at_inlay(editor: &mut Editor, inlay_hover: InlayHover, cx: &mut Vie
 /// Triggered by the `Hover` action when the cursor is not over a symbol or when the
 /// selections changed.
 pub fn hide_hover(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> bool {
+    if editor.hover_state.focused(cx) {
+        editor.focus(cx);
+    }
+
     let did_hide = editor.hover_state.info_popover.take().is_some()
         | editor.hover_state.diagnostic_popover.take().is_some();
 
@@ -310,12 +324,15 @@ fn show_hover(
                     let blocks = hover_result.contents;
                     let language = hover_result.language;
                     let parsed_content = parse_blocks(&blocks, &language_registry, language).await;
+                    let focus_handle = this.update(&mut cx, |_, cx| cx.focus_handle())?;
 
                     Some(InfoPopover {
                         project: project.clone(),
                         symbol_range: RangeInEditor::Text(range),
                         blocks,
                         parsed_content,
+                        focus_handle,
+                        scroll_handle: ScrollHandle::new(),
                     })
                 }
 
@@ -435,6 +452,12 @@ impl HoverState {
         self.info_popover.is_some() || self.diagnostic_popover.is_some()
     }
 
+    pub fn focused(&self, cx: &WindowContext) -> bool {
+        self.info_popover.as_ref().map_or(false, |info_popover| {
+            info_popover.focus_handle.is_focused(cx)
+        })
+    }
+
     pub fn render(
         &mut self,
         snapshot: &EditorSnapshot,
@@ -478,12 +501,14 @@ impl HoverState {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct InfoPopover {
     pub project: Model<Project>,
     symbol_range: RangeInEditor,
     pub blocks: Vec<HoverBlock>,
     parsed_content: ParsedMarkdown,
+    focus_handle: FocusHandle,
+    scroll_handle: ScrollHandle,
 }
 
 impl InfoPopover {
@@ -494,8 +519,39 @@ impl InfoPopover {
         workspace: Option<WeakView<Workspace>>,
         cx: &mut ViewContext<Editor>,
     ) -> AnyElement {
+        let line_height = style.text.line_height_in_pixels(cx.rem_size());
+        let scroll = |amount: ScrollAmount| {
+            let scroll_handle = self.scroll_handle.clone();
+            move |cx: &mut WindowContext| {
+                let delta = match amount {
+                    ScrollAmount::Line(lines) => line_height * lines,
+                    ScrollAmount::Page(pages) => scroll_handle.bounds().size.height * pages,
+                };
+                // The offset is clamped to the popover's content when it's next painted
+                let mut offset = scroll_handle.offset();
+                offset.y -= delta;
+                scroll_handle.set_offset(offset);
+                cx.refresh();
+            }
+        };
+        let scroll_up = scroll(ScrollAmount::Line(-1.));
+        let scroll_down = scroll(ScrollAmount::Line(1.));
+        let scroll_page_up = scroll(ScrollAmount::Page(-1.));
+        let scroll_page_down = scroll(ScrollAmount::Page(1.));
+
         div()
             .id("info_popover")
+            .track_focus(&self.focus_handle)
+            .track_scroll(&self.scroll_handle)
+            // While focused, the popover's own scrolling takes precedence over the
+            // editor's cursor movement.
+            .on_action(move |_: &MoveUp, cx| scroll_up(cx))
+            .on_action(move |_: &MoveDown, cx| scroll_down(cx))
+            .on_action(move |_: &MovePageUp, cx| scroll_page_up(cx))
+            .on_action(move |_: &MovePageDown, cx| scroll_page_down(cx))
+            .on_action(cx.listener(|editor, _: &Cancel, cx| {
+                hide_hover(editor, cx);
+            }))
             .elevation_2(cx)
             .p_2()
             .overflow_y_scroll()
@@ -732,6 +788,20 @@ mod tests {
                 }]
             )
         });
+
+        // Hovering again focuses the popover, which stays open while focused
+        cx.update_editor(|editor, cx| hover(editor, &Hover, cx));
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| {
+            assert!(editor.hover_state.visible());
+            assert!(editor.hover_state.focused(cx));
+        });
+
+        // Dismissing the focused popover returns focus to the editor
+        cx.update_editor(|editor, cx| {
+            hide_hover(editor, cx);
+            assert!(editor.focus_handle.is_focused(cx));
+        });
     }
 
     #[gpui::test]
@@ -1067,6 +1137,8 @@ mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -0,0 +1,152 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    Anchor, Editor, ToggleHunkDiffAt,
+};
+use gpui::{AppContext, StyledText, ViewContext};
+use language::Point;
+use multi_buffer::ToPoint as _;
+use std::sync::Arc;
+use ui::prelude::*;
+
+/// The lines of the diff base that git hunks replaced, shown above the hunks whose diff was
+/// toggled on from the gutter.
+#[derive(Default)]
+pub(crate) struct HunkDiffs {
+    /// The block showing each hunk's replaced lines, along with the start of the hunk.
+    blocks: Vec<(Anchor, BlockId)>,
+}
+
+/// The first row of the hunk at `buffer_row` and the lines of the diff base that it
+/// replaced, if the row is in a hunk which replaced or removed any lines.
+pub(crate) fn hunk_diff_base_at(
+    editor: &Editor,
+    buffer_row: u32,
+    cx: &AppContext,
+) -> Option<(u32, String)> {
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+    let buffer = buffer.read(cx);
+    let diff_base = buffer.diff_base()?;
+    let hunk = buffer
+        .snapshot()
+        .git_diff_hunks_in_row_range(buffer_row..buffer_row + 1)
+        .find(|hunk| {
+            hunk.buffer_range.contains(&buffer_row) || hunk.buffer_range.start == buffer_row
+        })?;
+    let text = diff_base.get(hunk.diff_base_byte_range)?;
+    if text.is_empty() {
+        return None;
+    }
+    Some((
+        hunk.buffer_range.start,
+        text.trim_end_matches('\n').to_string(),
+    ))
+}
+
+pub fn toggle_hunk_diff_at(
+    editor: &mut Editor,
+    action: &ToggleHunkDiffAt,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some((start_row, diff_base_text)) = hunk_diff_base_at(editor, action.buffer_row, cx) else {
+        return;
+    };
+    let buffer = editor.buffer.read(cx).snapshot(cx);
+    if let Some(ix) = editor
+        .hunk_diffs
+        .blocks
+        .iter()
+        .position(|(start, _)| start.to_point(&buffer).row == start_row)
+    {
+        let (_, block_id) = editor.hunk_diffs.blocks.remove(ix);
+        editor.remove_blocks([block_id].into_iter().collect(), None, cx);
+        return;
+    }
+
+    let lines = diff_base_text
+        .lines()
+        .map(|line| SharedString::from(line.to_string()))
+        .collect::<Vec<_>>();
+    let start = buffer.anchor_before(Point::new(start_row, 0));
+    let block_id = editor.insert_blocks(
+        [BlockProperties {
+            style: BlockStyle::Flex,
+            position: start,
+            height: lines.len().min(u8::MAX as usize) as u8,
+            render: Arc::new(move |cx: &mut BlockContext| {
+                let line_height = cx.line_height;
+                let mut text_style = cx.editor_style.text.clone();
+                text_style.color = cx.theme().status().deleted;
+                v_flex()
+                    .id(cx.block_id)
+                    .w_full()
+                    .pl(cx.gutter_width)
+                    .bg(cx.theme().status().deleted_background)
+                    .children(lines.iter().map(|line| {
+                        div()
+                            .h(line_height)
+                            .child(StyledText::new(line.clone()).with_highlights(&text_style, None))
+                    }))
+                    .into_any_element()
+            }),
+            disposition: BlockDisposition::Above,
+        }],
+        None,
+        cx,
+    )[0];
+    editor.hunk_diffs.blocks.push((start, block_id));
+}
+
+/// Hides the diffs that were toggled on, such as when the diff base changed and they show
+/// lines that are no longer replaced.
+pub(crate) fn clear_hunk_diffs(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    let blocks = std::mem::take(&mut editor.hunk_diffs.blocks);
+    if !blocks.is_empty() {
+        editor.remove_blocks(blocks.into_iter().map(|(_, id)| id).collect(), None, cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_tests::init_test, test::editor_test_context::EditorTestContext};
+    use indoc::indoc;
+
+    #[gpui::test]
+    async fn test_toggle_hunk_diff(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            oneˇ
+            TWO
+            three
+        "});
+        cx.set_diff_base(Some(indoc! {"
+            one
+            two
+            three
+        "}));
+        cx.run_until_parked();
+
+        cx.update_editor(|editor, cx| {
+            assert_eq!(hunk_diff_base_at(editor, 0, cx), None);
+            assert_eq!(
+                hunk_diff_base_at(editor, 1, cx),
+                Some((1, "two".to_string()))
+            );
+
+            toggle_hunk_diff_at(editor, &ToggleHunkDiffAt { buffer_row: 0 }, cx);
+            assert!(editor.hunk_diffs.blocks.is_empty());
+            toggle_hunk_diff_at(editor, &ToggleHunkDiffAt { buffer_row: 1 }, cx);
+            assert_eq!(editor.hunk_diffs.blocks.len(), 1);
+            toggle_hunk_diff_at(editor, &ToggleHunkDiffAt { buffer_row: 1 }, cx);
+            assert!(editor.hunk_diffs.blocks.is_empty());
+
+            toggle_hunk_diff_at(editor, &ToggleHunkDiffAt { buffer_row: 1 }, cx);
+        });
+
+        // The diffs are hidden when the diff base changes
+        cx.set_diff_base(Some("one\n"));
+        cx.run_until_parked();
+        cx.update_editor(|editor, _| assert!(editor.hunk_diffs.blocks.is_empty()));
+    }
+}
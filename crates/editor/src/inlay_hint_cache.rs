@@ -37,9 +37,20 @@ pub struct InlayHintCache {
     version: usize,
     pub(super) enabled: bool,
     update_tasks: HashMap<ExcerptId, TasksForRanges>,
+    pending_refreshes: HashMap<ExcerptId, PendingRefresh>,
+    invalidate_debounce: Option<Duration>,
+    append_debounce: Option<Duration>,
     lsp_request_limiter: Arc<Semaphore>,
 }
 
+/// A refresh of an excerpt's hints, waiting for its debounce to pass before querying.
+/// Newer refreshes of the same excerpt replace it and restart the debounce, keeping its
+/// invalidation if they have none.
+struct PendingRefresh {
+    invalidate: InvalidationStrategy,
+    _task: Task<()>,
+}
+
 #[derive(Debug)]
 struct TasksForRanges {
     tasks: Vec<Task<()>>,
@@ -266,6 +277,9 @@ impl InlayHintCache {
             enabled: inlay_hint_settings.enabled,
             hints: HashMap::default(),
             update_tasks: HashMap::default(),
+            pending_refreshes: HashMap::default(),
+            invalidate_debounce: debounce_duration(inlay_hint_settings.edit_debounce_ms),
+            append_debounce: debounce_duration(inlay_hint_settings.scroll_debounce_ms),
             version: 0,
             lsp_request_limiter: Arc::new(Semaphore::new(MAX_CONCURRENT_LSP_REQUESTS)),
         }
@@ -281,6 +295,8 @@ impl InlayHintCache {
         visible_hints: Vec<Inlay>,
         cx: &mut ViewContext<Editor>,
     ) -> ControlFlow<Option<InlaySplice>> {
+        self.invalidate_debounce = debounce_duration(new_hint_settings.edit_debounce_ms);
+        self.append_debounce = debounce_duration(new_hint_settings.scroll_debounce_ms);
         let new_allowed_hint_kinds = new_hint_settings.enabled_inlay_hint_kinds();
         match (self.enabled, new_hint_settings.enabled) {
             (false, false) => {
@@ -329,6 +345,8 @@ impl InlayHintCache {
     /// To reduce inlay hint jumping, attempts to query a visible range of the editor(s) first,
     /// followed by the delayed queries of the same range above and below the visible one.
     /// This way, concequent refresh invocations are less likely to trigger LSP queries for the invisible ranges.
+    ///
+    /// Queries after edits and scrolls are debounced per excerpt, according to the settings.
     pub(super) fn spawn_hint_refresh(
         &mut self,
         reason: &'static str,
@@ -357,21 +375,45 @@ impl InlayHintCache {
         }
 
         let cache_version = self.version + 1;
-        cx.spawn(|editor, mut cx| async move {
-            editor
-                .update(&mut cx, |editor, cx| {
-                    spawn_new_update_tasks(
-                        editor,
-                        reason,
-                        excerpts_to_query,
-                        invalidate,
-                        cache_version,
-                        cx,
-                    )
-                })
-                .ok();
-        })
-        .detach();
+        for (excerpt_id, excerpt_to_query) in excerpts_to_query {
+            let invalidate = match self.pending_refreshes.remove(&excerpt_id) {
+                Some(pending) if !invalidate.should_invalidate() => pending.invalidate,
+                _ => invalidate,
+            };
+            let debounce = match invalidate {
+                InvalidationStrategy::RefreshRequested => None,
+                InvalidationStrategy::BufferEdited => self.invalidate_debounce,
+                InvalidationStrategy::None => self.append_debounce,
+            };
+            let task = cx.spawn(|editor, mut cx| async move {
+                if let Some(debounce) = debounce {
+                    cx.background_executor().timer(debounce).await;
+                }
+                editor
+                    .update(&mut cx, |editor, cx| {
+                        editor
+                            .inlay_hint_cache
+                            .pending_refreshes
+                            .remove(&excerpt_id);
+                        spawn_new_update_tasks(
+                            editor,
+                            reason,
+                            HashMap::from_iter([(excerpt_id, excerpt_to_query)]),
+                            invalidate,
+                            cache_version,
+                            cx,
+                        )
+                    })
+                    .ok();
+            });
+            self.pending_refreshes.insert(
+                excerpt_id,
+                PendingRefresh {
+                    invalidate,
+                    _task: task,
+                },
+            );
+        }
 
         if invalidated_hints.is_empty() {
             None
@@ -498,6 +540,7 @@ impl InlayHintCache {
         let mut to_remove = Vec::new();
         for excerpt_to_remove in excerpts_removed {
             self.update_tasks.remove(&excerpt_to_remove);
+            self.pending_refreshes.remove(&excerpt_to_remove);
             if let Some(cached_hints) = self.hints.remove(&excerpt_to_remove) {
                 let cached_hints = cached_hints.read();
                 to_remove.extend(cached_hints.ordered_hints.iter().copied());
@@ -519,6 +562,7 @@ impl InlayHintCache {
             self.version += 1;
         }
         self.update_tasks.clear();
+        self.pending_refreshes.clear();
         self.hints.clear();
     }
 
@@ -787,6 +831,14 @@ fn determine_query_ranges(
 const MAX_CONCURRENT_LSP_REQUESTS: usize = 5;
 const INVISIBLE_RANGES_HINTS_REQUEST_DELAY_MILLIS: u64 = 400;
 
+fn debounce_duration(debounce_ms: u64) -> Option<Duration> {
+    if debounce_ms > 0 {
+        Some(Duration::from_millis(debounce_ms))
+    } else {
+        None
+    }
+}
+
 fn new_update_task(
     query: ExcerptQuery,
     query_ranges: QueryRanges,
@@ -1264,6 +1316,8 @@ pub mod tests {
                 show_type_hints: allowed_hint_kinds.contains(&Some(InlayHintKind::Type)),
                 show_parameter_hints: allowed_hint_kinds.contains(&Some(InlayHintKind::Parameter)),
                 show_other_hints: allowed_hint_kinds.contains(&None),
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -1386,6 +1440,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -1497,6 +1553,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -1706,6 +1764,8 @@ pub mod tests {
                 show_type_hints: allowed_hint_kinds.contains(&Some(InlayHintKind::Type)),
                 show_parameter_hints: allowed_hint_kinds.contains(&Some(InlayHintKind::Parameter)),
                 show_other_hints: allowed_hint_kinds.contains(&None),
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -1864,6 +1924,8 @@ pub mod tests {
                     show_parameter_hints: new_allowed_hint_kinds
                         .contains(&Some(InlayHintKind::Parameter)),
                     show_other_hints: new_allowed_hint_kinds.contains(&None),
+                    edit_debounce_ms: 0,
+                    scroll_debounce_ms: 0,
                 })
             });
             cx.executor().run_until_parked();
@@ -1908,6 +1970,8 @@ pub mod tests {
                 show_parameter_hints: another_allowed_hint_kinds
                     .contains(&Some(InlayHintKind::Parameter)),
                 show_other_hints: another_allowed_hint_kinds.contains(&None),
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
         cx.executor().run_until_parked();
@@ -1964,6 +2028,8 @@ pub mod tests {
                 show_parameter_hints: final_allowed_hint_kinds
                     .contains(&Some(InlayHintKind::Parameter)),
                 show_other_hints: final_allowed_hint_kinds.contains(&None),
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
         cx.executor().run_until_parked();
@@ -2033,6 +2099,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -2159,6 +2227,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -2444,6 +2514,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -2784,6 +2856,8 @@ pub mod tests {
                 show_type_hints: false,
                 show_parameter_hints: false,
                 show_other_hints: false,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -2976,6 +3050,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
         cx.executor().run_until_parked();
@@ -3007,6 +3083,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -3090,6 +3168,88 @@ pub mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_edits_debounce_hint_queries(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |settings| {
+            settings.defaults.inlay_hints = Some(InlayHintSettings {
+                enabled: true,
+                show_type_hints: true,
+                show_parameter_hints: true,
+                show_other_hints: true,
+                edit_debounce_ms: 700,
+                scroll_debounce_ms: 0,
+            })
+        });
+
+        let (file_with_hints, editor, fake_server) = prepare_test_objects(cx).await;
+        let lsp_request_count = Arc::new(AtomicU32::new(0));
+        let closure_lsp_request_count = Arc::clone(&lsp_request_count);
+        fake_server
+            .handle_request::<lsp::request::InlayHintRequest, _, _>(move |params, _| {
+                let task_lsp_request_count = Arc::clone(&closure_lsp_request_count);
+                async move {
+                    assert_eq!(
+                        params.text_document.uri,
+                        lsp::Url::from_file_path(file_with_hints).unwrap(),
+                    );
+                    let i = Arc::clone(&task_lsp_request_count).fetch_add(1, Ordering::SeqCst) + 1;
+                    Ok(Some(vec![lsp::InlayHint {
+                        position: lsp::Position::new(0, i),
+                        label: lsp::InlayHintLabel::String(i.to_string()),
+                        kind: None,
+                        text_edits: None,
+                        tooltip: None,
+                        padding_left: None,
+                        padding_right: None,
+                        data: None,
+                    }]))
+                }
+            })
+            .next()
+            .await;
+        cx.executor().run_until_parked();
+        _ = editor.update(cx, |editor, cx| {
+            let expected_hints = vec!["1".to_string()];
+            assert_eq!(
+                expected_hints,
+                cached_hint_labels(editor),
+                "Should query for hints right away when opening the editor"
+            );
+            assert_eq!(expected_hints, visible_hint_labels(editor, cx));
+        });
+
+        for change in ["a", "b", "c"] {
+            _ = editor.update(cx, |editor, cx| {
+                editor.change_selections(None, cx, |s| s.select_ranges([13..13]));
+                editor.handle_input(change, cx);
+            });
+            cx.executor().advance_clock(Duration::from_millis(100));
+            cx.executor().run_until_parked();
+        }
+        assert_eq!(
+            lsp_request_count.load(Ordering::SeqCst),
+            1,
+            "Should not query for hints while the buffer is being edited"
+        );
+
+        cx.executor().advance_clock(Duration::from_millis(700));
+        cx.executor().run_until_parked();
+        assert_eq!(
+            lsp_request_count.load(Ordering::SeqCst),
+            2,
+            "Should query for hints once, after the last edit's debounce passes"
+        );
+        _ = editor.update(cx, |editor, cx| {
+            let expected_hints = vec!["2".to_string()];
+            assert_eq!(
+                expected_hints,
+                cached_hint_labels(editor),
+                "Should replace the invalidated hints with the new ones"
+            );
+            assert_eq!(expected_hints, visible_hint_labels(editor, cx));
+        });
+    }
+
     #[gpui::test]
     async fn test_toggle_inlay_hints(cx: &mut gpui::TestAppContext) {
         init_test(cx, |settings| {
@@ -3098,6 +3258,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -3168,6 +3330,8 @@ pub mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
         cx.executor().run_until_parked();
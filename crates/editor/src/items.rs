@@ -1,7 +1,12 @@
 use crate::{
-    editor_settings::SeedQuerySetting, link_go_to_definition::hide_link_definition,
-    persistence::DB, scroll::ScrollAnchor, Anchor, Autoscroll, Editor, EditorEvent, EditorSettings,
-    ExcerptId, ExcerptRange, MultiBuffer, MultiBufferSnapshot, NavigationData, ToPoint as _,
+    editor_settings::SeedQuerySetting,
+    file_state::{self, FileState},
+    hot_exit,
+    link_go_to_definition::hide_link_definition,
+    persistence::DB,
+    scroll::ScrollAnchor,
+    Anchor, Autoscroll, Editor, EditorEvent, EditorSettings, ExcerptId, ExcerptRange, MultiBuffer,
+    MultiBufferSnapshot, NavigationData, ToPoint as _,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::HashSet;
@@ -42,6 +47,7 @@ use workspace::{
     item::{FollowableItem, Item, ItemEvent, ItemHandle, ProjectItem},
     searchable::{Direction, SearchEvent, SearchableItem, SearchableItemHandle},
     ItemId, ItemNavHistory, Pane, ToolbarItemLocation, ViewId, Workspace, WorkspaceId,
+    WorkspaceSettings,
 };
 
 pub const MAX_TAB_TITLE_LEN: usize = 24;
@@ -683,6 +689,10 @@ impl Item for Editor {
         self.buffer().read(cx).read(cx).has_conflict()
     }
 
+    fn persists_unsaved_changes(&self, cx: &AppContext) -> bool {
+        hot_exit::persists_unsaved_contents(self, cx)
+    }
+
     fn can_save(&self, cx: &AppContext) -> bool {
         let buffer = &self.buffer().read(cx);
         if let Some(buffer) = buffer.as_singleton() {
@@ -828,6 +838,9 @@ impl Item for Editor {
         let workspace_id = workspace.database_id();
         let item_id = cx.view().item_id().as_u64() as ItemId;
         self.workspace = Some((workspace.weak_handle(), workspace.database_id()));
+        file_state::restore_file_state(self, cx);
+        hot_exit::forget_unsaved_contents_on_release(self, cx);
+        hot_exit::store_unsaved_contents(self, false, cx);
 
         fn serialize(
             buffer: Model<Buffer>,
@@ -853,13 +866,24 @@ impl Item for Editor {
 
             cx.subscribe(&buffer, |this, buffer, event, cx| {
                 if let Some((_, workspace_id)) = this.workspace.as_ref() {
-                    if let language::Event::FileHandleChanged = event {
-                        serialize(
-                            buffer,
-                            *workspace_id,
-                            cx.view().item_id().as_u64() as ItemId,
-                            cx,
-                        );
+                    match event {
+                        language::Event::FileHandleChanged => {
+                            serialize(
+                                buffer,
+                                *workspace_id,
+                                cx.view().item_id().as_u64() as ItemId,
+                                cx,
+                            );
+                        }
+                        language::Event::Edited => {
+                            hot_exit::store_unsaved_contents(this, true, cx);
+                        }
+                        language::Event::DirtyChanged
+                        | language::Event::Saved
+                        | language::Event::Reloaded => {
+                            hot_exit::store_unsaved_contents(this, false, cx);
+                        }
+                        _ => {}
                     }
                 }
             })
@@ -912,6 +936,28 @@ impl Item for Editor {
         item_id: ItemId,
         cx: &mut ViewContext<Pane>,
     ) -> Task<Result<View<Self>>> {
+        let unsaved_contents = if WorkspaceSettings::get_global(cx).hot_exit {
+            DB.get_unsaved_contents(item_id, workspace_id)
+                .log_err()
+                .flatten()
+        } else {
+            None
+        };
+
+        // Untitled editors only have their unsaved contents to be restored from
+        if let Some((None, contents)) = unsaved_contents {
+            let buffer = project.update(cx, |project, cx| project.create_buffer("", None, cx));
+            return match buffer {
+                Ok(buffer) => {
+                    buffer.update(cx, |buffer, cx| buffer.set_text(contents, cx));
+                    let editor = cx.new_view(|cx| Editor::for_buffer(buffer, Some(project), cx));
+                    hot_exit::delete_restored_unsaved_contents(item_id, workspace_id, cx);
+                    Task::ready(Ok(editor))
+                }
+                Err(error) => Task::ready(Err(error)),
+            };
+        }
+
         let project_item: Result<_> = project.update(cx, |project, cx| {
             // Look up the path with this key associated, create a self with that path
             let path = DB
@@ -937,9 +983,21 @@ impl Item for Editor {
                         .downcast::<Buffer>()
                         .map_err(|_| anyhow!("Project item at stored path was not a buffer"))?;
                     Ok(pane.update(&mut cx, |_, cx| {
+                        if let Some((_, contents)) = unsaved_contents {
+                            buffer.update(cx, |buffer, cx| {
+                                if buffer.text() != contents {
+                                    buffer.set_text(contents, cx);
+                                }
+                            });
+                            hot_exit::delete_restored_unsaved_contents(item_id, workspace_id, cx);
+                        }
                         cx.new_view(|cx| {
                             let mut editor = Editor::for_buffer(buffer, Some(project), cx);
 
+                            // The item's own scroll position takes precedence over the
+                            // last one of its file, which may be from an editor in another
+                            // pane, while the file's folds are restored with its state
+                            editor.file_state = FileState::folds_only();
                             editor.read_scroll_position_from_db(item_id, workspace_id, cx);
                             editor
                         })
@@ -616,12 +616,13 @@ mod tests {
         editor_tests::init_test,
         inlay_hint_cache::tests::{cached_hint_labels, visible_hint_labels},
         test::editor_lsp_test_context::EditorLspTestContext,
+        GoToDeclaration, GoToImplementation,
     };
     use futures::StreamExt;
     use gpui::{Modifiers, ModifiersChangedEvent};
     use indoc::indoc;
     use language::language_settings::InlayHintSettings;
-    use lsp::request::{GotoDefinition, GotoTypeDefinition};
+    use lsp::request::{GotoDeclaration, GotoDefinition, GotoImplementation, GotoTypeDefinition};
     use util::assert_set_eq;
 
     #[gpui::test]
@@ -740,6 +741,85 @@ mod tests {
         "});
     }
 
+    #[gpui::test]
+    async fn test_go_to_implementation(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                implementation_provider: Some(lsp::ImplementationProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            trait Tˇrait {}
+            struct A;
+            impl Trait for A {}
+        "});
+        let target_range = cx.lsp_range(indoc! {"
+            trait Trait {}
+            struct A;
+            impl «Trait for A» {}
+        "});
+
+        let mut requests =
+            cx.handle_request::<GotoImplementation, _, _>(move |url, _, _| async move {
+                Ok(Some(lsp::GotoImplementationResponse::Scalar(
+                    lsp::Location::new(url, target_range),
+                )))
+            });
+        cx.update_editor(|editor, cx| editor.go_to_implementation(&GoToImplementation, cx));
+        requests.next().await;
+        cx.background_executor.run_until_parked();
+
+        cx.assert_editor_state(indoc! {"
+            trait Trait {}
+            struct A;
+            impl «Trait for Aˇ» {}
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_go_to_declaration(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                declaration_provider: Some(lsp::DeclarationCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            extern \"C\" { fn work(); }
+            fn main() { unsafe { wˇork() } }
+        "});
+        let target_range = cx.lsp_range(indoc! {"
+            extern \"C\" { fn «work»(); }
+            fn main() { unsafe { work() } }
+        "});
+
+        let mut requests =
+            cx.handle_request::<GotoDeclaration, _, _>(move |url, _, _| async move {
+                Ok(Some(lsp::GotoDefinitionResponse::Scalar(
+                    lsp::Location::new(url, target_range),
+                )))
+            });
+        cx.update_editor(|editor, cx| editor.go_to_declaration(&GoToDeclaration, cx));
+        requests.next().await;
+        cx.background_executor.run_until_parked();
+
+        cx.assert_editor_state(indoc! {"
+            extern \"C\" { fn «workˇ»(); }
+            fn main() { unsafe { work() } }
+        "});
+    }
+
     #[gpui::test]
     async fn test_link_go_to_definition(cx: &mut gpui::TestAppContext) {
         init_test(cx, |_| {});
@@ -1086,6 +1166,8 @@ mod tests {
                 show_type_hints: true,
                 show_parameter_hints: true,
                 show_other_hints: true,
+                edit_debounce_ms: 0,
+                scroll_debounce_ms: 0,
             })
         });
 
@@ -0,0 +1,196 @@
+use crate::{Anchor, Editor, EditorMode};
+use gpui::{Task, ViewContext};
+use multi_buffer::{MultiBufferSnapshot, ToOffset};
+use regex::Regex;
+use std::{mem, ops::Range, sync::Arc, time::Duration};
+use util::ResultExt;
+
+/// How long the cursor has to rest before requesting the ranges linked to it.
+pub(crate) const LINKED_EDITING_DEBOUNCE: Duration = Duration::from_millis(75);
+
+enum LinkedEditingHighlights {}
+
+/// The ranges linked to the one the cursor is in, such as the names of an HTML
+/// element's opening and closing tags, which are edited together.
+#[derive(Default)]
+pub(crate) struct LinkedEditingState {
+    ranges: Vec<Range<Anchor>>,
+    word_pattern: Option<Regex>,
+    refresh_task: Option<Task<()>>,
+}
+
+/// Requests the ranges linked to the newest cursor, unless it is still within
+/// the current linked ranges.
+pub(crate) fn refresh_linked_editing_ranges(
+    editor: &mut Editor,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let selection = editor.selections.newest::<usize>(cx);
+    if editor.selections.count() == 1
+        && linked_range_containing(&editor.linked_editing.ranges, &selection.range(), &snapshot)
+            .is_some()
+    {
+        return None;
+    }
+
+    clear_linked_editing_ranges(editor, cx);
+    let head = editor.selections.newest_anchor().head();
+    let (buffer, buffer_position) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+    editor.linked_editing.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        cx.background_executor()
+            .timer(LINKED_EDITING_DEBOUNCE)
+            .await;
+
+        let Some(linked_ranges) = project
+            .update(&mut cx, |project, cx| {
+                project.linked_editing_ranges(&buffer, buffer_position, cx)
+            })
+            .ok()
+        else {
+            return;
+        };
+        let Some(linked_ranges) = linked_ranges.await.log_err() else {
+            return;
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                let snapshot = editor.buffer.read(cx).snapshot(cx);
+                let ranges = linked_ranges
+                    .ranges
+                    .into_iter()
+                    .map(|range| {
+                        snapshot.anchor_in_excerpt(head.excerpt_id, range.start)
+                            ..snapshot.anchor_in_excerpt(head.excerpt_id, range.end)
+                    })
+                    .collect::<Vec<_>>();
+                let cursor = editor.selections.newest::<usize>(cx).range();
+                if ranges.len() < 2
+                    || linked_range_containing(&ranges, &cursor, &snapshot).is_none()
+                {
+                    return;
+                }
+
+                editor.highlight_background::<LinkedEditingHighlights>(
+                    ranges.clone(),
+                    |colors| colors.editor_document_highlight_write_background,
+                    cx,
+                );
+                editor.linked_editing.ranges = ranges;
+                editor.linked_editing.word_pattern = linked_ranges.word_pattern;
+            })
+            .log_err();
+    }));
+    None
+}
+
+pub(crate) fn clear_linked_editing_ranges(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    editor.linked_editing.refresh_task = None;
+    editor.linked_editing.word_pattern = None;
+    if !mem::take(&mut editor.linked_editing.ranges).is_empty() {
+        editor.clear_background_highlights::<LinkedEditingHighlights>(cx);
+    }
+}
+
+/// Adds the edits that mirror `edits` in the linked ranges to them, ordered by
+/// position. Linked editing stops when an edit leaves a linked range with content
+/// that doesn't match the server's word pattern, or that contains whitespace.
+pub(crate) fn with_linked_edits<T: ToOffset>(
+    editor: &mut Editor,
+    edits: Vec<(Range<T>, Arc<str>)>,
+    cx: &mut ViewContext<Editor>,
+) -> Vec<(Range<usize>, Arc<str>)> {
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let mut edits = edits
+        .into_iter()
+        .map(|(range, text)| {
+            (
+                range.start.to_offset(&snapshot)..range.end.to_offset(&snapshot),
+                text,
+            )
+        })
+        .collect::<Vec<_>>();
+    if editor.linked_editing.ranges.is_empty() {
+        return edits;
+    }
+
+    let linked_ranges = editor
+        .linked_editing
+        .ranges
+        .iter()
+        .map(|range| range.start.to_offset(&snapshot)..range.end.to_offset(&snapshot))
+        .collect::<Vec<_>>();
+    let mut linked_edits = Vec::new();
+    let mut stop_linked_editing = false;
+    for (range, text) in &edits {
+        let Some(ix) = linked_range_ix(&linked_ranges, range) else {
+            stop_linked_editing |= linked_ranges
+                .iter()
+                .any(|linked| range.start < linked.end && linked.start < range.end);
+            continue;
+        };
+
+        let linked = &linked_ranges[ix];
+        let mut content = snapshot
+            .text_for_range(linked.start..range.start)
+            .collect::<String>();
+        content.push_str(text);
+        content.extend(snapshot.text_for_range(range.end..linked.end));
+        let is_valid = match &editor.linked_editing.word_pattern {
+            Some(pattern) => pattern
+                .find(&content)
+                .map_or(false, |word| word.range() == (0..content.len())),
+            None => !content.chars().any(char::is_whitespace),
+        };
+        if !is_valid {
+            stop_linked_editing = true;
+            break;
+        }
+
+        let start = range.start - linked.start;
+        let end = range.end - linked.start;
+        for (other_ix, other) in linked_ranges.iter().enumerate() {
+            let mirrored = other.start + start..other.start + end;
+            // Leave the ranges alone that are edited already, such as by another cursor
+            if other_ix == ix
+                || mirrored.end > other.end
+                || edits
+                    .iter()
+                    .any(|(range, _)| other.start <= range.start && range.end <= other.end)
+            {
+                continue;
+            }
+            linked_edits.push((mirrored, text.clone()));
+        }
+    }
+
+    if stop_linked_editing {
+        clear_linked_editing_ranges(editor, cx);
+        return edits;
+    }
+    edits.extend(linked_edits);
+    edits.sort_by_key(|(range, _)| range.start);
+    edits
+}
+
+fn linked_range_containing(
+    ranges: &[Range<Anchor>],
+    range: &Range<usize>,
+    snapshot: &MultiBufferSnapshot,
+) -> Option<usize> {
+    ranges.iter().position(|linked| {
+        linked.start.to_offset(snapshot) <= range.start
+            && range.end <= linked.end.to_offset(snapshot)
+    })
+}
+
+fn linked_range_ix(ranges: &[Range<usize>], range: &Range<usize>) -> Option<usize> {
+    ranges
+        .iter()
+        .position(|linked| linked.start <= range.start && range.end <= linked.end)
+}
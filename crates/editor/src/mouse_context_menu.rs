@@ -1,78 +1,1090 @@
+use std::rc::Rc;
+
 use crate::{
-    DisplayPoint, Editor, EditorMode, FindAllReferences, GoToDefinition, GoToTypeDefinition,
-    Rename, RevealInFinder, SelectMode, ToggleCodeActions,
+    blame,
+    display_map::{DisplaySnapshot, ToDisplayPoint},
+    doc_comment,
+    editor_settings::ContextMenuEntry,
+    hunk_diff, is_organize_imports_kind, runnables, snippet_sharing, spell_check, AddImport,
+    AddWordToDictionary, Anchor, Bias, Copy, CopyBreadcrumbs, CopyPath, CopyPermalinkToLine,
+    CopyRelativePath, Cut, DeployCursorContextMenu, DeployGutterContextMenu, DisplayPoint, Editor,
+    EditorMode, EditorSettings, FindAllReferences, FoldAt, Format, FormatSelection,
+    GenerateDocComment, GoToDeclaration, GoToDefinition, GoToHunk, GoToImplementation,
+    GoToPrevHunk, GoToTypeDefinition, MoveLineDown, MoveLineUp, OpenInTerminal, OrganizeImports,
+    Paste, PeekDefinition, Rename, ReplaceMisspelling, RevealInFinder, RunTestAtCursor, SelectAll,
+    SelectAllMatches, SelectMode, ShareSelectionAsSnippet, ShowBlamedCommit, ShowCallHierarchy,
+    ToggleBookmarkAt, ToggleCodeActions, ToggleComments, ToggleHunkDiffAt, ToggleInlayHints,
+    UnfoldAt,
+};
+use anyhow::Context as _;
+use gpui::{
+    AppContext, ClipboardItem, DismissEvent, Model, Pixels, Point, SharedString, Subscription,
+    Task, View, ViewContext, WeakView,
 };
-use gpui::{DismissEvent, Pixels, Point, Subscription, View, ViewContext};
+use language::{Buffer, CodeAction};
+use settings::Settings;
+use ui::{ContextMenu, IconName};
+use util::ResultExt;
 
+/// The editor's open context menu. It stays open until it is dismissed, however many
+/// times the editor is laid out again in the meantime.
 pub struct MouseContextMenu {
     pub(crate) position: Point<Pixels>,
     pub(crate) context_menu: View<ui::ContextMenu>,
+    // Anchored in the buffer, so that the point follows its text when edits, diagnostics
+    // or inlay hints change the editor's layout while the menu is open
+    deployed_at: Anchor,
+    // Fetches the code actions that are shown among the menu's items
+    code_actions_task: Option<Task<()>>,
     _subscription: Subscription,
 }
 
+impl MouseContextMenu {
+    /// The point in the buffer that the menu was deployed at, which its actions apply to.
+    pub fn deployed_at(&self, snapshot: &DisplaySnapshot) -> DisplayPoint {
+        self.deployed_at.to_display_point(snapshot)
+    }
+}
+
+type ContextMenuItemPredicate = Rc<dyn Fn(&Editor, DisplayPoint, &AppContext) -> bool>;
+type ContextMenuItemBuilder = Rc<dyn Fn(ContextMenu) -> ContextMenu>;
+
+const GO_TO_SUBMENU: &str = "Go To";
+
+/// The label of the item opening the code actions menu, before which the top few code
+/// actions are inserted once they have been fetched.
+const CODE_ACTIONS_LABEL: &str = "Code Actions";
+
+/// The most code actions shown in the menu itself, rather than only in the code actions
+/// menu opened by its "Code Actions" item.
+const MAX_CODE_ACTION_ITEMS: usize = 3;
+
+struct ContextMenuItem {
+    name: &'static str,
+    submenu: Option<&'static str>,
+    predicate: ContextMenuItemPredicate,
+    builder: ContextMenuItemBuilder,
+}
+
+/// The items shown by the editor's context menu, in the order they are shown unless the
+/// `context_menu` setting orders them.
+#[derive(Default)]
+struct ContextMenuItems(Vec<ContextMenuItem>);
+
+/// The code actions available where the menu was deployed, quick fixes first.
+struct MenuCodeActions {
+    editor: WeakView<Editor>,
+    buffer: Model<Buffer>,
+    actions: Vec<CodeAction>,
+}
+
+impl MenuCodeActions {
+    fn builders(&self) -> Vec<ContextMenuItemBuilder> {
+        self.actions
+            .iter()
+            .take(MAX_CODE_ACTION_ITEMS)
+            .map(|action| {
+                let editor = self.editor.clone();
+                let buffer = self.buffer.clone();
+                let action = action.clone();
+                let builder: ContextMenuItemBuilder = Rc::new(move |menu: ContextMenu| {
+                    let editor = editor.clone();
+                    let buffer = buffer.clone();
+                    let action = action.clone();
+                    menu.entry(action.lsp_action.title.clone(), None, move |cx| {
+                        editor
+                            .update(cx, |editor, cx| {
+                                if let Some(task) =
+                                    editor.apply_code_action(buffer.clone(), action.clone(), cx)
+                                {
+                                    task.detach_and_log_err(cx);
+                                }
+                            })
+                            .ok();
+                    })
+                });
+                builder
+            })
+            .collect()
+    }
+}
+
+pub fn init(cx: &mut AppContext) {
+    // Editor initialization can run more than once, but the built-in items should only
+    // be registered the first time.
+    if cx.has_global::<ContextMenuItems>() {
+        return;
+    }
+
+    register_context_menu_item(
+        "cut",
+        has_selection,
+        |menu| menu.action("Cut", Box::new(Cut)),
+        cx,
+    );
+    register_context_menu_item(
+        "cut",
+        |editor, point, cx| !has_selection(editor, point, cx),
+        |menu| menu.disabled_action("Cut", Box::new(Cut)),
+        cx,
+    );
+    register_context_menu_item(
+        "copy",
+        has_selection,
+        |menu| menu.action("Copy", Box::new(Copy)),
+        cx,
+    );
+    register_context_menu_item(
+        "copy",
+        |editor, point, cx| !has_selection(editor, point, cx),
+        |menu| menu.disabled_action("Copy", Box::new(Copy)),
+        cx,
+    );
+    register_context_menu_item(
+        "paste",
+        always,
+        |menu| menu.action("Paste", Box::new(Paste)),
+        cx,
+    );
+    register_context_menu_item(
+        "select_all",
+        always,
+        |menu| menu.action("Select All", Box::new(SelectAll)),
+        cx,
+    );
+    register_context_menu_item(
+        "select_all_matches",
+        |editor, point, cx| editor.mode == EditorMode::Full && has_selection(editor, point, cx),
+        |menu| {
+            menu.action(
+                "Select All Occurrences of Selection",
+                Box::new(SelectAllMatches),
+            )
+        },
+        cx,
+    );
+    register_context_menu_item(
+        "share_as_snippet",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && has_selection(editor, point, cx)
+                && snippet_sharing::snippet_sharing_enabled(cx)
+        },
+        |menu| menu.action("Share as Snippet", Box::new(ShareSelectionAsSnippet)),
+        cx,
+    );
+    register_context_menu_item("separator", has_project, ContextMenu::separator, cx);
+    register_context_menu_item(
+        "move_line_up",
+        has_project,
+        |menu| menu.action("Move Line Up", Box::new(MoveLineUp)),
+        cx,
+    );
+    register_context_menu_item(
+        "move_line_down",
+        has_project,
+        |menu| menu.action("Move Line Down", Box::new(MoveLineDown)),
+        cx,
+    );
+    register_context_menu_item(
+        "toggle_comments",
+        |editor, point, cx| has_project(editor, point, cx) && has_line_comments(editor, cx),
+        |menu| {
+            menu.action(
+                "Toggle Comment",
+                Box::new(ToggleComments {
+                    advance_downwards: false,
+                }),
+            )
+        },
+        cx,
+    );
+    register_context_menu_item(
+        "format",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.document_formatting_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action("Format Document", Box::new(Format)),
+        cx,
+    );
+    register_context_menu_item(
+        "format_selection",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && has_selection(editor, point, cx)
+                && editor
+                    .project
+                    .as_ref()
+                    .map_or(false, |project| project.read(cx).is_local())
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.document_range_formatting_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action("Format Selection", Box::new(FormatSelection)),
+        cx,
+    );
+    register_context_menu_item(
+        "organize_imports",
+        |editor, point, cx| {
+            advertises_organize_imports(editor, point, cx) && editor.has_organize_imports_action()
+        },
+        |menu| menu.action("Organize Imports", Box::new(OrganizeImports)),
+        cx,
+    );
+    register_context_menu_item(
+        "organize_imports",
+        |editor, point, cx| {
+            advertises_organize_imports(editor, point, cx) && !editor.has_organize_imports_action()
+        },
+        |menu| menu.disabled_action("Organize Imports", Box::new(OrganizeImports)),
+        cx,
+    );
+    register_context_menu_item("separator", has_project, ContextMenu::separator, cx);
+    register_context_menu_item_in(
+        Some(GO_TO_SUBMENU),
+        "go_to_definition",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.definition_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| {
+            menu.action_with_icon(
+                "Go to Definition",
+                IconName::ArrowUpRight,
+                Box::new(GoToDefinition),
+            )
+        },
+        cx,
+    );
+    register_context_menu_item_in(
+        Some(GO_TO_SUBMENU),
+        "peek_definition",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.definition_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action("Peek Definition", Box::new(PeekDefinition)),
+        cx,
+    );
+    register_context_menu_item_in(
+        Some(GO_TO_SUBMENU),
+        "go_to_declaration",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.declaration_provider,
+                        None | Some(lsp::DeclarationCapability::Simple(false))
+                    )
+                })
+        },
+        |menu| menu.action("Go to Declaration", Box::new(GoToDeclaration)),
+        cx,
+    );
+    register_context_menu_item_in(
+        Some(GO_TO_SUBMENU),
+        "go_to_type_definition",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.type_definition_provider,
+                        None | Some(lsp::TypeDefinitionProviderCapability::Simple(false))
+                    )
+                })
+        },
+        |menu| menu.action("Go to Type Definition", Box::new(GoToTypeDefinition)),
+        cx,
+    );
+    register_context_menu_item_in(
+        Some(GO_TO_SUBMENU),
+        "go_to_implementation",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.implementation_provider,
+                        None | Some(lsp::ImplementationProviderCapability::Simple(false))
+                    )
+                })
+        },
+        |menu| menu.action("Go to Implementation", Box::new(GoToImplementation)),
+        cx,
+    );
+    register_context_menu_item(
+        "find_all_references",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.references_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| {
+            menu.action_with_icon(
+                "Find All References",
+                IconName::MagnifyingGlass,
+                Box::new(FindAllReferences),
+            )
+        },
+        cx,
+    );
+    register_context_menu_item(
+        "show_call_hierarchy",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.call_hierarchy_provider,
+                        None | Some(lsp::CallHierarchyServerCapability::Simple(false))
+                    )
+                })
+        },
+        |menu| menu.action("Show Call Hierarchy", Box::new(ShowCallHierarchy)),
+        cx,
+    );
+    register_context_menu_item(
+        "go_to_changes",
+        |editor, point, cx| has_project(editor, point, cx) && has_git_changes(editor, cx),
+        |menu| {
+            menu.action("Go to Next Change", Box::new(GoToHunk))
+                .action("Go to Previous Change", Box::new(GoToPrevHunk))
+        },
+        cx,
+    );
+    register_context_menu_item(
+        "copy_permalink",
+        |editor, point, cx| {
+            has_project(editor, point, cx) && blame::has_permalink_at_cursor(editor, cx)
+        },
+        |menu| menu.action("Copy Permalink", Box::new(CopyPermalinkToLine)),
+        cx,
+    );
+    register_context_menu_item(
+        "show_commit",
+        |editor, point, cx| {
+            has_project(editor, point, cx) && blame::has_permalink_at_cursor(editor, cx)
+        },
+        |menu| menu.action("Show Commit", Box::new(ShowBlamedCommit)),
+        cx,
+    );
+    register_context_menu_item("separator", has_project, ContextMenu::separator, cx);
+    register_context_menu_item(
+        "rename",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.rename_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action_with_icon("Rename Symbol", IconName::Replace, Box::new(Rename)),
+        cx,
+    );
+    register_context_menu_item(
+        "add_import",
+        |editor, point, cx| {
+            has_project(editor, point, cx) && editor.import_code_action(cx).is_some()
+        },
+        |menu| menu.action("Add Import", Box::new(AddImport)),
+        cx,
+    );
+    register_context_menu_item(
+        "generate_doc_comment",
+        |editor, point, cx| {
+            has_project(editor, point, cx) && doc_comment::can_generate_doc_comment(editor, cx)
+        },
+        |menu| menu.action("Generate Doc Comment", Box::new(GenerateDocComment)),
+        cx,
+    );
+    register_context_menu_item(
+        "run_test",
+        |editor, _, cx| runnables::has_test_at_cursor(editor, cx),
+        |menu| menu.action("Run Test", Box::new(RunTestAtCursor)),
+        cx,
+    );
+    register_context_menu_item(
+        "code_actions",
+        has_project,
+        |menu| {
+            menu.action_with_icon(
+                CODE_ACTIONS_LABEL,
+                IconName::Bolt,
+                Box::new(ToggleCodeActions {
+                    deployed_from_indicator: false,
+                }),
+            )
+        },
+        cx,
+    );
+    register_context_menu_item("separator", has_project, ContextMenu::separator, cx);
+    register_context_menu_item(
+        "toggle_inlay_hints",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.inlay_hint_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action("Toggle Inlay Hints", Box::new(ToggleInlayHints)),
+        cx,
+    );
+    register_context_menu_item(
+        "copy_path",
+        has_local_file,
+        |menu| menu.action("Copy Path", Box::new(CopyPath)),
+        cx,
+    );
+    register_context_menu_item(
+        "copy_relative_path",
+        has_local_file,
+        |menu| menu.action("Copy Relative Path", Box::new(CopyRelativePath)),
+        cx,
+    );
+    register_context_menu_item(
+        "reveal_in_finder",
+        has_local_file,
+        |menu| menu.action("Reveal in Finder", Box::new(RevealInFinder)),
+        cx,
+    );
+    register_context_menu_item(
+        "open_in_terminal",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && editor
+                    .project
+                    .as_ref()
+                    .map_or(false, |project| project.read(cx).is_local())
+                && editor.local_file_directory(cx).is_some()
+        },
+        |menu| menu.action("Open Terminal Here", Box::new(OpenInTerminal)),
+        cx,
+    );
+    register_context_menu_item(
+        "copy_breadcrumbs",
+        |editor, point, cx| {
+            has_project(editor, point, cx)
+                && language_servers_support(editor, cx, |capabilities| {
+                    !matches!(
+                        capabilities.document_symbol_provider,
+                        None | Some(lsp::OneOf::Left(false))
+                    )
+                })
+        },
+        |menu| menu.action("Copy Breadcrumbs", Box::new(CopyBreadcrumbs)),
+        cx,
+    );
+}
+
+/// Appends an item to the editor's context menu. `predicate` is called with the editor
+/// and the clicked point each time the menu is deployed, and `builder` adds the item to
+/// the menu whenever it returns true. The `context_menu` setting refers to the item by
+/// `name`, which items shown in place of each other in different states can share.
+pub fn register_context_menu_item(
+    name: &'static str,
+    predicate: impl Fn(&Editor, DisplayPoint, &AppContext) -> bool + 'static,
+    builder: impl Fn(ContextMenu) -> ContextMenu + 'static,
+    cx: &mut AppContext,
+) {
+    register_context_menu_item_in(None, name, predicate, builder, cx)
+}
+
+// Registers an item which is shown in the submenu with the given label, along with the
+// items next to it which belong to the same submenu.
+fn register_context_menu_item_in(
+    submenu: Option<&'static str>,
+    name: &'static str,
+    predicate: impl Fn(&Editor, DisplayPoint, &AppContext) -> bool + 'static,
+    builder: impl Fn(ContextMenu) -> ContextMenu + 'static,
+    cx: &mut AppContext,
+) {
+    cx.default_global::<ContextMenuItems>()
+        .0
+        .push(ContextMenuItem {
+            name,
+            submenu,
+            predicate: Rc::new(predicate),
+            builder: Rc::new(builder),
+        });
+}
+
+fn always(_: &Editor, _: DisplayPoint, _: &AppContext) -> bool {
+    true
+}
+
+/// Whether the editor is a full editor with a project, which items that rely on the
+/// project or its language servers are restricted to.
+fn has_project(editor: &Editor, _: DisplayPoint, _: &AppContext) -> bool {
+    editor.mode() == EditorMode::Full && editor.project.is_some()
+}
+
+fn has_local_file(editor: &Editor, point: DisplayPoint, cx: &AppContext) -> bool {
+    has_project(editor, point, cx)
+        && editor
+            .buffer()
+            .read(cx)
+            .as_singleton()
+            .map_or(false, |buffer| {
+                buffer
+                    .read(cx)
+                    .file()
+                    .map_or(false, |file| file.as_local().is_some())
+            })
+}
+
+fn has_selection(editor: &Editor, _: DisplayPoint, cx: &AppContext) -> bool {
+    editor
+        .selections
+        .all::<usize>(cx)
+        .iter()
+        .any(|selection| !selection.is_empty())
+}
+
+fn advertises_organize_imports(editor: &Editor, point: DisplayPoint, cx: &AppContext) -> bool {
+    has_project(editor, point, cx)
+        && language_servers_support(editor, cx, |capabilities| {
+            match &capabilities.code_action_provider {
+                Some(lsp::CodeActionProviderCapability::Options(options)) => options
+                    .code_action_kinds
+                    .iter()
+                    .flatten()
+                    .any(is_organize_imports_kind),
+                _ => false,
+            }
+        })
+}
+
+fn has_line_comments(editor: &Editor, cx: &AppContext) -> bool {
+    let head = editor.selections.newest::<usize>(cx).head();
+    editor
+        .buffer
+        .read(cx)
+        .snapshot(cx)
+        .language_scope_at(head)
+        .and_then(|scope| {
+            scope
+                .line_comment_prefixes()
+                .map(|prefixes| !prefixes.is_empty())
+        })
+        .unwrap_or(false)
+}
+
+fn has_git_changes(editor: &Editor, cx: &AppContext) -> bool {
+    editor
+        .buffer
+        .read(cx)
+        .snapshot(cx)
+        .git_diff_hunks_in_range(0..u32::MAX)
+        .next()
+        .is_some()
+}
+
 pub fn deploy_context_menu(
     editor: &mut Editor,
     position: Point<Pixels>,
     point: DisplayPoint,
     cx: &mut ViewContext<Editor>,
 ) {
-    if !editor.is_focused(cx) {
-        editor.focus(cx);
+    move_cursor_for_context_menu(editor, point, cx);
+
+    let context_menu = build_context_menu(editor, point, cx);
+    show_context_menu(editor, position, point, context_menu, cx);
+    fetch_code_actions_for_context_menu(editor, point, cx);
+}
+
+fn build_context_menu(
+    editor: &Editor,
+    point: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) -> View<ContextMenu> {
+    let mut items = spelling_items(editor, cx);
+    items.extend(match &EditorSettings::get_global(cx).context_menu {
+        Some(entries) => configured_context_menu_items(entries, editor, point, cx),
+        None => cx
+            .try_global::<ContextMenuItems>()
+            .map(|items| {
+                items
+                    .0
+                    .iter()
+                    .filter(|item| (item.predicate)(editor, point, cx))
+                    .map(|item| (item.submenu, item.builder.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+    });
+    let builders = group_into_submenus(items);
+    ui::ContextMenu::build(cx, |menu, _cx| {
+        builders.iter().fold(menu, |menu, builder| builder(menu))
+    })
+}
+
+// Fetches the code actions available at the deployed point in the background, and then
+// inserts the top few of them into the open menu as items of their own, before the item
+// that opens the menu of all of them.
+fn fetch_code_actions_for_context_menu(
+    editor: &mut Editor,
+    point: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if !has_project(editor, point, cx) {
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let selection = editor.selections.newest_anchor().clone();
+    let multi_buffer = editor.buffer.read(cx);
+    let (buffer, start) = multi_buffer.text_anchor_for_position(selection.start, cx)?;
+    let (end_buffer, end) = multi_buffer.text_anchor_for_position(selection.end, cx)?;
+    if buffer != end_buffer {
+        return None;
     }
 
-    // Don't show context menu for inline editors
-    if editor.mode() != EditorMode::Full {
-        return;
+    let code_actions = project.update(cx, |project, cx| {
+        project.code_actions(&buffer, start..end, cx)
+    });
+    let task = cx.spawn(|editor, mut cx| async move {
+        let Some(mut actions) = code_actions.await.log_err() else {
+            return;
+        };
+        if actions.is_empty() {
+            return;
+        }
+        // Quick fixes first, and the ones that the language server prefers first among them
+        actions.sort_by_key(|action| {
+            (
+                action.lsp_action.kind.as_ref() != Some(&lsp::CodeActionKind::QUICKFIX),
+                action.lsp_action.is_preferred != Some(true),
+            )
+        });
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                let Some(context_menu) = editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .map(|menu| menu.context_menu.clone())
+                else {
+                    return;
+                };
+                let code_actions = MenuCodeActions {
+                    editor: cx.view().downgrade(),
+                    buffer,
+                    actions,
+                };
+                let builders = code_actions.builders();
+                context_menu.update(cx, |menu, cx| {
+                    let Some(ix) = menu
+                        .item_labels()
+                        .iter()
+                        .position(|label| label.as_deref() == Some(CODE_ACTIONS_LABEL))
+                    else {
+                        return;
+                    };
+                    menu.insert_items(
+                        ix,
+                        |menu, _| builders.iter().fold(menu, |menu, builder| builder(menu)),
+                        cx,
+                    );
+                });
+            })
+            .ok();
+    });
+    editor.mouse_context_menu.as_mut()?.code_actions_task = Some(task);
+    None
+}
+
+// Builds the suggestions for the misspelled word under the cursor, which are shown above
+// the other items whatever the `context_menu` setting lists.
+fn spelling_items(
+    editor: &Editor,
+    cx: &AppContext,
+) -> Vec<(Option<&'static str>, ContextMenuItemBuilder)> {
+    let Some((word, suggestions)) = spell_check::suggestions_at_cursor(editor, cx) else {
+        return Vec::new();
+    };
+    let mut items: Vec<(Option<&'static str>, ContextMenuItemBuilder)> = Vec::new();
+    if suggestions.is_empty() {
+        items.push((
+            None,
+            Rc::new(|menu: ContextMenu| menu.header("No Spelling Suggestions")),
+        ));
     }
+    for suggestion in suggestions {
+        items.push((
+            None,
+            Rc::new(move |menu: ContextMenu| {
+                menu.action(
+                    suggestion.clone(),
+                    Box::new(ReplaceMisspelling {
+                        replacement: suggestion.clone(),
+                    }),
+                )
+            }),
+        ));
+    }
+    items.push((
+        None,
+        Rc::new(move |menu: ContextMenu| {
+            menu.action(
+                format!("Add \"{word}\" to Dictionary"),
+                Box::new(AddWordToDictionary),
+            )
+        }),
+    ));
+    items.push((None, Rc::new(ContextMenu::separator)));
+    items
+}
 
-    // Don't show the context menu if there isn't a project associated with this editor
-    if editor.project.is_none() {
-        return;
+// Builds the items listed in the `context_menu` setting which apply at `point`. Separators
+// are only kept between items, so that hidden items don't leave them doubled up.
+fn configured_context_menu_items(
+    entries: &[ContextMenuEntry],
+    editor: &Editor,
+    point: DisplayPoint,
+    cx: &AppContext,
+) -> Vec<(Option<&'static str>, ContextMenuItemBuilder)> {
+    let registered = cx
+        .try_global::<ContextMenuItems>()
+        .map_or(&[][..], |items| items.0.as_slice());
+    let mut builders = Vec::new();
+    let mut pending_separator = false;
+    for entry in entries {
+        let mut entry_builders: Vec<(Option<&'static str>, ContextMenuItemBuilder)> = Vec::new();
+        match entry {
+            ContextMenuEntry::Item(name) if name == "separator" => {
+                pending_separator = true;
+                continue;
+            }
+            ContextMenuEntry::Item(name) => {
+                let mut items = registered
+                    .iter()
+                    .filter(|item| item.name == name.as_str())
+                    .peekable();
+                if items.peek().is_none() {
+                    log::error!("Unknown context menu item {name:?}");
+                }
+                entry_builders.extend(
+                    items
+                        .filter(|item| (item.predicate)(editor, point, cx))
+                        .map(|item| (item.submenu, item.builder.clone())),
+                );
+            }
+            ContextMenuEntry::Action {
+                label,
+                action,
+                argument,
+            } => {
+                let Some(action) = cx
+                    .build_action(action, argument.clone())
+                    .with_context(|| format!("Invalid action in context menu item {label:?}"))
+                    .log_err()
+                else {
+                    continue;
+                };
+                let label = SharedString::from(label.clone());
+                entry_builders.push((
+                    None,
+                    Rc::new(move |menu: ContextMenu| {
+                        menu.action(label.clone(), action.boxed_clone())
+                    }),
+                ));
+            }
+        }
+
+        if entry_builders.is_empty() {
+            continue;
+        }
+        if pending_separator && !builders.is_empty() {
+            builders.push((
+                None,
+                Rc::new(ContextMenu::separator) as ContextMenuItemBuilder,
+            ));
+        }
+        pending_separator = false;
+        builders.extend(entry_builders);
+    }
+    builders
+}
+
+// Wraps each run of items which belong to the same submenu in that submenu. A submenu of a
+// single item would only hide it, so lone items are left in the menu itself.
+fn group_into_submenus(
+    items: Vec<(Option<&'static str>, ContextMenuItemBuilder)>,
+) -> Vec<ContextMenuItemBuilder> {
+    let mut builders: Vec<ContextMenuItemBuilder> = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while let Some((submenu, builder)) = items.next() {
+        let Some(submenu) = submenu else {
+            builders.push(builder);
+            continue;
+        };
+        let mut group = vec![builder];
+        while let Some((_, builder)) = items.next_if(|(next, _)| *next == Some(submenu)) {
+            group.push(builder);
+        }
+        if group.len() == 1 {
+            builders.extend(group);
+        } else {
+            builders.push(Rc::new(move |menu: ContextMenu| {
+                let group = group.clone();
+                menu.submenu(submenu, move |menu, _| {
+                    group.iter().fold(menu, |menu, builder| builder(menu))
+                })
+            }));
+        }
     }
+    builders
+}
+
+/// Opens a menu holding only the code actions item at `position`, for when code actions
+/// are requested from an indicator rather than by right-clicking the text.
+pub fn deploy_code_actions_menu(
+    editor: &mut Editor,
+    position: Point<Pixels>,
+    point: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) {
+    move_cursor_for_context_menu(editor, point, cx);
 
-    // Move the cursor to the clicked location so that dispatched actions make sense
-    editor.change_selections(None, cx, |s| {
-        s.clear_disjoint();
-        s.set_pending_display_range(point..point, SelectMode::Character);
+    let context_menu = ui::ContextMenu::build(cx, |menu, _cx| {
+        menu.action_with_icon(
+            CODE_ACTIONS_LABEL,
+            IconName::Bolt,
+            Box::new(ToggleCodeActions {
+                deployed_from_indicator: true,
+            }),
+        )
     });
+    show_context_menu(editor, position, point, context_menu, cx);
+}
+
+/// Opens the menu for a line's gutter, which holds actions on the line as a whole.
+pub fn deploy_gutter_context_menu(
+    editor: &mut Editor,
+    action: &DeployGutterContextMenu,
+    cx: &mut ViewContext<Editor>,
+) {
+    if !editor.is_focused(cx) {
+        editor.focus(cx);
+    }
 
+    let buffer_row = action.buffer_row;
+    let display_map = editor.selections.display_map(cx);
+    let point = language::Point::new(buffer_row, 0).to_display_point(&display_map);
+    let fold_action: Option<(&str, Box<dyn gpui::Action>)> =
+        if display_map.is_line_folded(buffer_row) {
+            Some(("Unfold", Box::new(UnfoldAt { buffer_row })))
+        } else if display_map.is_foldable(buffer_row) {
+            Some(("Fold", Box::new(FoldAt { buffer_row })))
+        } else {
+            None
+        };
+
+    let has_hunk_diff = hunk_diff::hunk_diff_base_at(editor, buffer_row, cx).is_some();
+    // The menu's dismissal removes the selections broadcast to collaborators, unless an
+    // item was chosen, as it does for the other context menus
+    editor.defer_selection_broadcast = true;
+
+    let editor_view = cx.view().downgrade();
     let context_menu = ui::ContextMenu::build(cx, |menu, _cx| {
-        menu.action("Rename Symbol", Box::new(Rename))
-            .action("Go to Definition", Box::new(GoToDefinition))
-            .action("Go to Type Definition", Box::new(GoToTypeDefinition))
-            .action("Find All References", Box::new(FindAllReferences))
-            .action(
-                "Code Actions",
-                Box::new(ToggleCodeActions {
-                    deployed_from_indicator: false,
-                }),
+        let menu = menu
+            .entry("Copy Line Number", None, move |cx| {
+                cx.write_to_clipboard(ClipboardItem::new((buffer_row + 1).to_string()))
+            })
+            .action("Toggle Bookmark", Box::new(ToggleBookmarkAt { buffer_row }))
+            .entry("Select Line", None, move |cx| {
+                editor_view
+                    .update(cx, |editor, cx| {
+                        let buffer = editor.buffer.read(cx).snapshot(cx);
+                        let start = language::Point::new(buffer_row, 0);
+                        let end =
+                            buffer.clip_point(language::Point::new(buffer_row + 1, 0), Bias::Left);
+                        editor.change_selections(None, cx, |s| s.select_ranges([start..end]));
+                    })
+                    .ok();
+            });
+        let menu = if has_hunk_diff {
+            menu.action(
+                "Toggle Git Hunk Diff",
+                Box::new(ToggleHunkDiffAt { buffer_row }),
             )
-            .separator()
-            .action("Reveal in Finder", Box::new(RevealInFinder))
+        } else {
+            menu
+        };
+        match fold_action {
+            Some((label, action)) => menu.separator().action(label, action),
+            None => menu,
+        }
     });
+    show_context_menu(editor, cx.mouse_position(), point, context_menu, cx);
+}
+
+fn move_cursor_for_context_menu(
+    editor: &mut Editor,
+    point: DisplayPoint,
+    cx: &mut ViewContext<Editor>,
+) {
+    if !editor.is_focused(cx) {
+        editor.focus(cx);
+    }
+
+    // Move the cursor to the clicked location so that dispatched actions make sense,
+    // unless the click landed inside an existing selection or on one of the cursors, in
+    // which case all the selections are kept as-is.
+    let display_map = editor.selections.display_map(cx);
+    let clicked_offset = point.to_offset(&display_map, Bias::Left);
+    let clicked_inside_selection = editor
+        .selections
+        .all::<usize>(cx)
+        .iter()
+        .any(|selection| selection.start <= clicked_offset && clicked_offset <= selection.end);
+    editor.defer_selection_broadcast = true;
+    if !clicked_inside_selection {
+        editor.change_selections(None, cx, |s| {
+            s.clear_disjoint();
+            s.set_pending_display_range(point..point, SelectMode::Character);
+        });
+    }
+}
+
+fn show_context_menu(
+    editor: &mut Editor,
+    position: Point<Pixels>,
+    point: DisplayPoint,
+    context_menu: View<ContextMenu>,
+    cx: &mut ViewContext<Editor>,
+) {
+    let display_map = editor.display_map.update(cx, |map, cx| map.snapshot(cx));
+    let deployed_at = display_map
+        .buffer_snapshot
+        .anchor_before(point.to_point(&display_map));
+
     let context_menu_focus = context_menu.focus_handle(cx);
     cx.focus(&context_menu_focus);
 
-    let _subscription = cx.subscribe(&context_menu, move |this, _, _event: &DismissEvent, cx| {
-        this.mouse_context_menu.take();
-        if context_menu_focus.contains_focused(cx) {
-            this.focus(cx);
-        }
-    });
+    let _subscription = cx.subscribe(
+        &context_menu,
+        move |this, context_menu, _event: &DismissEvent, cx| {
+            this.mouse_context_menu.take();
+            let confirmed = context_menu.read(cx).confirmed();
+            if confirmed {
+                this.defer_selection_broadcast = false;
+                this.broadcast_selections(cx);
+            }
+            if context_menu_focus.contains_focused(cx) {
+                this.focus(cx);
+            } else if !confirmed {
+                this.defer_selection_broadcast = false;
+                this.buffer
+                    .update(cx, |buffer, cx| buffer.remove_active_selections(cx));
+            }
+        },
+    );
 
     editor.mouse_context_menu = Some(MouseContextMenu {
         position,
         context_menu,
+        deployed_at,
+        code_actions_task: None,
         _subscription,
     });
     cx.notify();
 }
 
+/// Returns whether a language server for the buffer under the newest cursor has the
+/// given capabilities. Guests can't see the host's servers, so they're assumed to.
+fn language_servers_support(
+    editor: &Editor,
+    cx: &AppContext,
+    supports: impl Fn(&lsp::ServerCapabilities) -> bool,
+) -> bool {
+    let Some(project) = editor.project.as_ref() else {
+        return false;
+    };
+    let project = project.read(cx);
+    if project.is_remote() {
+        return true;
+    }
+
+    let head = editor.selections.newest_anchor().head();
+    let Some((buffer, _)) = editor.buffer.read(cx).text_anchor_for_position(head, cx) else {
+        return false;
+    };
+    project
+        .language_servers_for_buffer(buffer.read(cx), cx)
+        .any(|(_, server)| supports(server.capabilities()))
+}
+
+/// Opens the context menu at the newest cursor, for keyboard-only invocation.
+pub fn deploy_cursor_context_menu(
+    editor: &mut Editor,
+    _: &DeployCursorContextMenu,
+    cx: &mut ViewContext<Editor>,
+) {
+    let point = editor.selections.newest_display(cx).head();
+    // The newest cursor's position is only known once it has been painted, which cursors
+    // scrolled out of view aren't, so the menu is otherwise opened at the newest
+    // selection's head, kept within the editor. An editor which was never painted has
+    // nowhere to open the menu.
+    let Some(position) = editor.pixel_position_of_newest_cursor.or_else(|| {
+        let bounds = editor.last_bounds?;
+        let cursor_bounds = editor.display_point_bounds(point, bounds, cx);
+        let center = cursor_bounds.center();
+        Some(gpui::Point::new(
+            center.x.clamp(bounds.left(), bounds.right()),
+            center.y.clamp(bounds.top(), bounds.bottom()),
+        ))
+    }) else {
+        return;
+    };
+    deploy_context_menu(editor, position, point, cx);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext};
+    use crate::{
+        editor_tests::init_test,
+        test::{
+            editor_lsp_test_context::EditorLspTestContext, editor_test_context::EditorTestContext,
+        },
+    };
+    use collections::HashMap;
+    use gpui::SharedString;
     use indoc::indoc;
+    use language::{Language, LanguageConfig};
+    use parking_lot::Mutex;
+    use settings::SettingsStore;
+    use std::{
+        cell::{Cell, RefCell},
+        ops::Range,
+        sync::Arc,
+    };
 
     #[gpui::test]
     async fn test_mouse_context_menu(cx: &mut gpui::TestAppContext) {
@@ -107,4 +1119,2026 @@ mod tests {
         "});
         cx.editor(|editor, _app| assert!(editor.mouse_context_menu.is_some()));
     }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_deployed_at(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn «testˇ»() {
+                do_work();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        assert_eq!(deployed_at(&mut cx), None);
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        assert_eq!(deployed_at(&mut cx), Some(point));
+    }
+
+    fn deployed_at(cx: &mut EditorTestContext) -> Option<DisplayPoint> {
+        cx.update_editor(|editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .map(|menu| menu.deployed_at(&snapshot.display_snapshot))
+        })
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_survives_editor_updates(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+
+        // Diagnostics arriving and the editor re-rendering leave the menu open.
+        cx.notify::<lsp::notification::PublishDiagnostics>(lsp::PublishDiagnosticsParams {
+            uri: cx.buffer_lsp_url.clone(),
+            version: None,
+            diagnostics: vec![lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(1, 4), lsp::Position::new(1, 11)),
+                severity: Some(lsp::DiagnosticSeverity::ERROR),
+                message: "cannot find function `do_work` in this scope".to_string(),
+                ..Default::default()
+            }],
+        });
+        cx.run_until_parked();
+        cx.update_editor(|_, cx| cx.notify());
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+        assert_eq!(deployed_at(&mut cx), Some(point));
+
+        // Edits elsewhere in the buffer move the deployed point along with its text.
+        let buffer =
+            cx.update_editor(|editor, cx| editor.buffer().read(cx).as_singleton().unwrap());
+        cx.update(|cx| buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx)));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+        assert_eq!(
+            deployed_at(&mut cx),
+            Some(DisplayPoint::new(point.row() + 1, point.column()))
+        );
+
+        // It only closes once it is dismissed.
+        cx.update_editor(|editor, cx| {
+            let context_menu = editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone();
+            context_menu.update(cx, |_, cx| cx.emit(DismissEvent));
+        });
+        assert_eq!(deployed_at(&mut cx), None);
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_preserves_selection(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn «test() {
+                do_work();
+                do_ˇ»work();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+                do_work();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+
+        cx.assert_editor_state(indoc! {"
+            fn «test() {
+                do_work();
+                do_ˇ»work();
+            }
+        "});
+        cx.editor(|editor, _app| assert!(editor.mouse_context_menu.is_some()));
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_preserves_multiple_selections(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        let state = indoc! {"
+            fn test() {
+                «do_workˇ»();
+                «do_workˇ»();
+                do_ˇwork();
+            }
+        "};
+        cx.set_state(state);
+        let inside_selection = cx.display_point(indoc! {"
+            fn test() {
+                do_work();
+                do_wˇork();
+                do_work();
+            }
+        "});
+        cx.update_editor(|editor, cx| {
+            deploy_context_menu(editor, Default::default(), inside_selection, cx)
+        });
+        cx.assert_editor_state(state);
+
+        // Actions run from the menu apply to every selection
+        cx.update_editor(|editor, cx| editor.copy(&Copy, cx));
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("do_work\ndo_work\n    do_work();\n".to_string())
+        );
+
+        // Clicking on one of the cursors keeps them all too
+        let on_cursor = cx.display_point(indoc! {"
+            fn test() {
+                do_work();
+                do_work();
+                do_ˇwork();
+            }
+        "});
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            deploy_context_menu(editor, Default::default(), on_cursor, cx)
+        });
+        cx.assert_editor_state(state);
+
+        // Clicking anywhere else only leaves a cursor at the click
+        let outside_selection = cx.display_point(indoc! {"
+            fn teˇst() {
+                do_work();
+                do_work();
+                do_work();
+            }
+        "});
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            deploy_context_menu(editor, Default::default(), outside_selection, cx)
+        });
+        cx.assert_editor_state(indoc! {"
+            fn teˇst() {
+                do_work();
+                do_work();
+                do_work();
+            }
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_type_ahead(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| {
+            deploy_cursor_context_menu(editor, &DeployCursorContextMenu, cx)
+        });
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        let selected_label = |cx: &mut EditorLspTestContext| {
+            cx.editor(|editor, cx| {
+                let menu = editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .read(cx);
+                menu.item_labels()[menu.selected_index()?].clone()
+            })
+        };
+
+        // Disabled items are skipped, and longer prefixes narrow the match
+        cx.simulate_keystrokes(["c"]);
+        assert_eq!(
+            selected_label(&mut cx),
+            Some(SharedString::from("Code Actions"))
+        );
+        cx.simulate_keystrokes(["o", "p", "y", "space", "r"]);
+        assert_eq!(
+            selected_label(&mut cx),
+            Some(SharedString::from("Copy Relative Path"))
+        );
+
+        // After a pause, typing starts a new prefix, and repeating a character cycles
+        // through the items starting with it
+        cx.executor().advance_clock(ui::TYPE_AHEAD_TIMEOUT);
+        cx.simulate_keystrokes(["c"]);
+        assert_eq!(
+            selected_label(&mut cx),
+            Some(SharedString::from("Code Actions"))
+        );
+        cx.simulate_keystrokes(["c"]);
+        assert_eq!(
+            selected_label(&mut cx),
+            Some(SharedString::from("Copy Path"))
+        );
+        cx.simulate_keystrokes(["c", "c"]);
+        assert_eq!(
+            selected_label(&mut cx),
+            Some(SharedString::from("Code Actions"))
+        );
+
+        // Typing doesn't reach the editor
+        cx.assert_editor_state(indoc! {"
+            fn teˇst() {}
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_cursor_context_menu(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.editor(|editor, _app| assert!(editor.mouse_context_menu.is_none()));
+        let (bounds, line_height) = cx.update_editor(|editor, cx| {
+            let line_height = editor
+                .style()
+                .unwrap()
+                .text
+                .line_height_in_pixels(cx.rem_size());
+            (editor.last_bounds.unwrap(), line_height)
+        });
+        let menu_position = |cx: &mut EditorLspTestContext| {
+            cx.editor(|editor, _app| editor.mouse_context_menu.as_ref().unwrap().position)
+        };
+
+        // The menu opens at the newest cursor, on its line rather than at the window's
+        // origin
+        cx.cx.cx.dispatch_action(DeployCursorContextMenu);
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let position = menu_position(&mut cx);
+        assert_eq!(
+            Some(position),
+            cx.editor(|editor, _app| editor.pixel_position_of_newest_cursor)
+        );
+        assert!(bounds.contains(&position));
+        assert!(bounds.top() + line_height < position.y);
+        assert!(position.y < bounds.top() + line_height * 2.);
+
+        // Before the cursor is painted, the menu opens at the newest selection's head
+        let fallback_position = cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.pixel_position_of_newest_cursor = None;
+            deploy_cursor_context_menu(editor, &DeployCursorContextMenu, cx);
+            editor.mouse_context_menu.as_ref().unwrap().position
+        });
+        assert_eq!(fallback_position.y, position.y);
+        assert!((fallback_position.x - position.x).abs() < line_height);
+
+        // Without knowing where the editor is, no menu is opened
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.pixel_position_of_newest_cursor = None;
+            editor.last_bounds = None;
+            deploy_cursor_context_menu(editor, &DeployCursorContextMenu, cx);
+            assert!(editor.mouse_context_menu.is_none());
+        });
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_clipboard_items(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                «do_workˇ»();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Cut").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Copy").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.change_selections(None, cx, |s| s.select_display_ranges([point..point]));
+        });
+        // Without a selection, Cut and Copy are shown but can't be used, and the clipboard
+        // items are separated from the rest.
+        let items = cx.update_editor(|editor, cx| {
+            deploy_context_menu(editor, Default::default(), point, cx);
+            let menu = editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .read(cx);
+            menu.item_labels()
+                .into_iter()
+                .zip(menu.item_disabled())
+                .take(5)
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            items,
+            [
+                (Some(SharedString::from("Cut")), true),
+                (Some(SharedString::from("Copy")), true),
+                (Some(SharedString::from("Paste")), false),
+                (Some(SharedString::from("Select All")), false),
+                (None, false),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_add_import(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            fn main() {
+                let map = HashˇMap::new();
+            }
+        "});
+        let unresolved_range = cx.lsp_range(indoc! {"
+            fn main() {
+                let map = «HashMap»::new();
+            }
+        "});
+        cx.handle_request::<lsp::request::CodeActionRequest, _, _>(|url, _, _| async move {
+            Ok(Some(vec![lsp::CodeActionOrCommand::CodeAction(
+                lsp::CodeAction {
+                    title: "Import `std::collections::HashMap`".to_string(),
+                    kind: Some(lsp::CodeActionKind::QUICKFIX),
+                    edit: Some(lsp::WorkspaceEdit {
+                        changes: Some(std::collections::HashMap::from_iter([(
+                            url,
+                            vec![lsp::TextEdit::new(
+                                lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 0)),
+                                "use std::collections::HashMap;\n\n".to_string(),
+                            )],
+                        )])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )]))
+        });
+        cx.notify::<lsp::notification::PublishDiagnostics>(lsp::PublishDiagnosticsParams {
+            uri: cx.buffer_lsp_url.clone(),
+            version: None,
+            diagnostics: vec![lsp::Diagnostic {
+                range: unresolved_range,
+                severity: Some(lsp::DiagnosticSeverity::ERROR),
+                message: "failed to resolve: use of undeclared type `HashMap`".to_string(),
+                ..Default::default()
+            }],
+        });
+        cx.run_until_parked();
+        cx.executor()
+            .advance_clock(crate::CODE_ACTIONS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+
+        let point = cx.display_point(indoc! {"
+            fn main() {
+                let map = HashˇMap::new();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Add Import").is_some());
+
+        let add_import = cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.add_import(&AddImport, cx).unwrap()
+        });
+        add_import.await.unwrap();
+        cx.assert_editor_state(indoc! {"
+            use std::collections::HashMap;
+
+            fn main() {
+                let map = HashˇMap::new();
+            }
+        "});
+
+        // Once the symbol is in scope, the item is no longer offered.
+        cx.notify::<lsp::notification::PublishDiagnostics>(lsp::PublishDiagnosticsParams {
+            uri: cx.buffer_lsp_url.clone(),
+            version: None,
+            diagnostics: Vec::new(),
+        });
+        cx.run_until_parked();
+        let point = cx.display_point(indoc! {"
+            use std::collections::HashMap;
+
+            fn main() {
+                let map = HashˇMap::new();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Add Import").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_code_actions(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn maˇin() {}
+        "});
+        cx.handle_request::<lsp::request::CodeActionRequest, _, _>(|_, _, _| async move {
+            let action = |title: &str, kind| {
+                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                    title: title.to_string(),
+                    kind: Some(kind),
+                    ..Default::default()
+                })
+            };
+            Ok(Some(vec![
+                action("Extract into Module", lsp::CodeActionKind::REFACTOR_EXTRACT),
+                action("Inline Function", lsp::CodeActionKind::REFACTOR_INLINE),
+                action("Add Missing Docs", lsp::CodeActionKind::QUICKFIX),
+                action("Rewrite as Closure", lsp::CodeActionKind::REFACTOR_REWRITE),
+            ]))
+        });
+
+        let point = cx.display_point(indoc! {"
+            fn maˇin() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        let labels = |cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| {
+                editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .read(cx)
+                    .item_labels()
+            })
+        };
+        let code_action_labels = |labels: Vec<Option<SharedString>>| {
+            let code_actions = labels
+                .iter()
+                .position(|label| {
+                    label
+                        .as_ref()
+                        .map_or(false, |label| label == "Code Actions")
+                })
+                .unwrap();
+            labels[code_actions.saturating_sub(MAX_CODE_ACTION_ITEMS)..=code_actions].to_vec()
+        };
+        assert!(!labels(&mut cx).contains(&Some("Add Missing Docs".into())));
+        let selected_label = |cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| {
+                let menu = editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .read(cx);
+                menu.item_labels()[menu.selected_index()?].clone()
+            })
+        };
+        let context_menu = cx.update_editor(|editor, cx| {
+            let context_menu = editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone();
+            context_menu.update(cx, |menu, _| menu.select_last());
+            context_menu
+        });
+        let selected_before = selected_label(&mut cx);
+        assert!(selected_before.is_some());
+
+        // Once fetched, the top code actions are shown before the item that opens all of
+        // them, with quick fixes first, in the menu that was already open and without
+        // moving its selection.
+        cx.run_until_parked();
+        assert_eq!(
+            code_action_labels(labels(&mut cx)),
+            [
+                Some("Add Missing Docs".into()),
+                Some("Extract into Module".into()),
+                Some("Inline Function".into()),
+                Some("Code Actions".into()),
+            ]
+        );
+        cx.update_editor(|editor, _| {
+            assert_eq!(
+                editor.mouse_context_menu.as_ref().unwrap().context_menu,
+                context_menu
+            );
+        });
+        assert_eq!(selected_label(&mut cx), selected_before);
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_organize_imports(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Options(
+                    lsp::CodeActionOptions {
+                        code_action_kinds: Some(vec![lsp::CodeActionKind::SOURCE_ORGANIZE_IMPORTS]),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        fn organize_imports_item(cx: &mut EditorLspTestContext) -> Option<bool> {
+            cx.update_editor(|editor, cx| {
+                let point = editor.selections.newest_display(cx).head();
+                deploy_context_menu(editor, Default::default(), point, cx);
+                let menu = editor.mouse_context_menu.take().unwrap().context_menu;
+                let menu = menu.read(cx);
+                menu.item_labels()
+                    .into_iter()
+                    .zip(menu.item_disabled())
+                    .find(|(label, _)| label.as_deref() == Some("Organize Imports"))
+                    .map(|(_, disabled)| !disabled)
+            })
+        }
+
+        // Until the server offers the action, the item is shown but can't be used.
+        cx.handle_request::<lsp::request::CodeActionRequest, _, _>(|_, _, _| async move {
+            Ok(Some(Vec::new()))
+        });
+        cx.set_state(indoc! {"
+            use std::fmt;
+            use std::collections::HashMap;
+
+            fn mainˇ() {}
+        "});
+        cx.executor()
+            .advance_clock(crate::CODE_ACTIONS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        assert_eq!(organize_imports_item(&mut cx), Some(false));
+
+        let requested_ranges = Arc::new(Mutex::new(Vec::new()));
+        cx.handle_request::<lsp::request::CodeActionRequest, _, _>({
+            let requested_ranges = requested_ranges.clone();
+            move |url, params, _| {
+                requested_ranges.lock().push(params.range);
+                async move {
+                    Ok(Some(vec![lsp::CodeActionOrCommand::CodeAction(
+                        lsp::CodeAction {
+                            title: "Organize imports".to_string(),
+                            kind: Some(lsp::CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+                            edit: Some(lsp::WorkspaceEdit {
+                                changes: Some(std::collections::HashMap::from_iter([(
+                                    url,
+                                    vec![lsp::TextEdit::new(
+                                        lsp::Range::new(
+                                            lsp::Position::new(0, 0),
+                                            lsp::Position::new(2, 0),
+                                        ),
+                                        "use std::collections::HashMap;\n".to_string(),
+                                    )],
+                                )])),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                    )]))
+                }
+            }
+        });
+        cx.set_state(indoc! {"
+            use std::fmt;
+            use std::collections::HashMap;
+
+            fn maˇin() {}
+        "});
+        cx.executor()
+            .advance_clock(crate::CODE_ACTIONS_DEBOUNCE_TIMEOUT);
+        cx.run_until_parked();
+        assert_eq!(organize_imports_item(&mut cx), Some(true));
+
+        requested_ranges.lock().clear();
+        let organize_imports =
+            cx.update_editor(|editor, cx| editor.organize_imports(&OrganizeImports, cx).unwrap());
+        organize_imports.await.unwrap();
+        cx.assert_editor_state(indoc! {"
+            use std::collections::HashMap;
+
+            fn maˇin() {}
+        "});
+        // The action is requested for the whole document, not just the cursor.
+        assert_eq!(
+            requested_ranges.lock().as_slice(),
+            &[lsp::Range::new(
+                lsp::Position::new(0, 0),
+                lsp::Position::new(4, 0)
+            )]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_stays_within_window(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let viewport_size = cx.update(|cx| cx.viewport_size());
+        let position = gpui::point(
+            viewport_size.width - gpui::px(10.),
+            viewport_size.height - gpui::px(10.),
+        );
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, position, point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+
+        // The menu flips to open upward and leftward from the click.
+        let first_item = cx.debug_bounds("MENU_ITEM-Paste").unwrap();
+        let last_item = cx.debug_bounds("MENU_ITEM-Reveal in Finder").unwrap();
+        assert!(first_item.top() >= gpui::Pixels::ZERO);
+        assert!(first_item.left() >= gpui::Pixels::ZERO);
+        assert!(last_item.bottom() <= position.y);
+        assert!(last_item.right() <= position.x);
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_taller_than_window(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.context_menu = Some(
+                        (0..100)
+                            .map(|ix| ContextMenuEntry::Action {
+                                label: format!("Item {ix}"),
+                                action: "editor::SelectAll".to_string(),
+                                argument: None,
+                            })
+                            .collect(),
+                    );
+                });
+            });
+        });
+        cx.set_state("ˇfn test() {}");
+        let point = cx.display_point("ˇfn test() {}");
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+
+        // The menu is as tall as the window, and its last items are scrolled out of view
+        let viewport_size = cx.update(|cx| cx.viewport_size());
+        let first_item = cx.debug_bounds("MENU_ITEM-Item 0").unwrap();
+        assert!(first_item.top() >= gpui::Pixels::ZERO);
+        let last_item = cx.debug_bounds("MENU_ITEM-Item 99").unwrap();
+        assert!(last_item.top() > viewport_size.height);
+
+        // Until they're selected with the keyboard
+        let menu = cx.update_editor(|editor, _| {
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone()
+        });
+        cx.update(|cx| {
+            menu.update(cx, |menu, cx| {
+                menu.select_last();
+                cx.notify();
+            })
+        });
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        let last_item = cx.debug_bounds("MENU_ITEM-Item 99").unwrap();
+        assert!(last_item.bottom() <= viewport_size.height);
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_navigation_capabilities(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                references_provider: Some(lsp::OneOf::Left(true)),
+                rename_provider: Some(lsp::OneOf::Left(true)),
+                type_definition_provider: Some(lsp::TypeDefinitionProviderCapability::Simple(true)),
+                implementation_provider: Some(lsp::ImplementationProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Find All References").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Rename Symbol").is_some());
+
+        // The navigation items are grouped in a submenu, which is only shown once opened.
+        assert!(cx.debug_bounds("MENU_ITEM-Go To").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Definition").is_none());
+        let context_menu = cx.editor(|editor, _| {
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone()
+        });
+        let submenu = cx.update(|cx| {
+            context_menu.update(cx, |menu, cx| {
+                let ix = menu
+                    .item_labels()
+                    .iter()
+                    .position(|label| label.as_deref() == Some(GO_TO_SUBMENU))
+                    .unwrap();
+                menu.open_submenu(ix, cx);
+                menu.submenu_view().unwrap()
+            })
+        });
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        cx.update(|cx| {
+            assert_eq!(
+                submenu.read(cx).item_labels(),
+                [
+                    Some(SharedString::from("Go to Definition")),
+                    Some(SharedString::from("Peek Definition")),
+                    Some(SharedString::from("Go to Type Definition")),
+                    Some(SharedString::from("Go to Implementation")),
+                ]
+            );
+            assert!(submenu.focus_handle(cx).is_focused(cx));
+        });
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Type Definition").is_some());
+
+        // Leaving the submenu closes it and refocuses the menu, which stays open.
+        cx.update(|cx| submenu.update(cx, |submenu, cx| submenu.cancel(&Default::default(), cx)));
+        cx.update(|cx| {
+            assert!(context_menu.read(cx).submenu_view().is_none());
+            assert!(context_menu.focus_handle(cx).is_focused(cx));
+        });
+        cx.editor(|editor, _| assert!(editor.mouse_context_menu.is_some()));
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_without_navigation_capabilities(
+        cx: &mut gpui::TestAppContext,
+    ) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        // Items which the language server can't act on are hidden, rather than doing nothing.
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Definition").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Type Definition").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Implementation").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Find All References").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Rename Symbol").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_move_lines(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                «first();
+                secondˇ»();
+                third();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                first();
+                secˇond();
+                third();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Move Line Up").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Move Line Down").is_some());
+
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.move_line_down(&MoveLineDown, cx);
+        });
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+                third();
+                «first();
+                secondˇ»();
+            }
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_custom_context_menu_item(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.update(|cx| {
+            register_context_menu_item(
+                "run_tests_here",
+                |_, point, _| point.row() == 1,
+                |menu| {
+                    menu.separator()
+                        .action("Run Tests Here", Box::new(crate::SelectAll))
+                },
+                cx,
+            )
+        });
+
+        cx.set_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        let labels = cx.editor(|editor, cx| {
+            let context_menu = &editor.mouse_context_menu.as_ref().unwrap().context_menu;
+            context_menu.read(cx).item_labels()
+        });
+        assert_eq!(
+            labels[labels.len() - 4..],
+            [
+                Some(SharedString::from("Reveal in Finder")),
+                Some(SharedString::from("Open Terminal Here")),
+                None,
+                Some(SharedString::from("Run Tests Here"))
+            ]
+        );
+
+        // The predicate is given the clicked point.
+        let point = cx.display_point(indoc! {"
+            fn tesˇt() {
+                do_work();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        let labels = cx.editor(|editor, cx| {
+            let context_menu = &editor.mouse_context_menu.as_ref().unwrap().context_menu;
+            context_menu.read(cx).item_labels()
+        });
+        assert_eq!(
+            labels.last(),
+            Some(&Some(SharedString::from("Open Terminal Here")))
+        );
+    }
+
+    #[gpui::test]
+    async fn test_configured_context_menu(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    let item = |name: &str| ContextMenuEntry::Item(name.to_string());
+                    let action = |label: &str, action: &str| ContextMenuEntry::Action {
+                        label: label.to_string(),
+                        action: action.to_string(),
+                        argument: None,
+                    };
+                    settings.context_menu = Some(vec![
+                        item("separator"),
+                        item("paste"),
+                        item("separator"),
+                        item("separator"),
+                        item("select_all_matches"),
+                        item("separator"),
+                        action("Select Everything", "editor::SelectAll"),
+                        action("Not an Action", "editor::NotAnAction"),
+                        item("not_an_item"),
+                        item("separator"),
+                    ]);
+                });
+            })
+        });
+
+        fn labels(point: DisplayPoint, cx: &mut EditorLspTestContext) -> Vec<Option<SharedString>> {
+            cx.update_editor(|editor, cx| {
+                deploy_context_menu(editor, Default::default(), point, cx);
+                let menu = editor.mouse_context_menu.take().unwrap().context_menu;
+                let menu = menu.read(cx);
+                menu.item_labels()
+            })
+        }
+
+        // Separators are only shown between the items which apply.
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        assert_eq!(
+            labels(point, &mut cx),
+            [
+                Some(SharedString::from("Paste")),
+                None,
+                Some(SharedString::from("Select Everything")),
+            ]
+        );
+
+        cx.set_state(indoc! {"
+            fn «teˇ»st() {}
+        "});
+        assert_eq!(
+            labels(point, &mut cx),
+            [
+                Some(SharedString::from("Paste")),
+                None,
+                Some(SharedString::from("Select All Occurrences of Selection")),
+                None,
+                Some(SharedString::from("Select Everything")),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    fn test_inline_editor_context_menu(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let build_editors: [fn(&mut ViewContext<Editor>) -> Editor; 2] =
+            [Editor::single_line, |cx| Editor::auto_height(3, cx)];
+        for build_editor in build_editors {
+            let editor = cx.add_window(|cx| {
+                let mut editor = build_editor(cx);
+                editor.set_text("one two", cx);
+                editor.select_all(&SelectAll, cx);
+                editor
+            });
+            _ = editor.update(cx, |editor, cx| {
+                deploy_context_menu(editor, Default::default(), DisplayPoint::new(0, 2), cx);
+                let labels = editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .read(cx)
+                    .item_labels();
+                assert_eq!(
+                    labels,
+                    [
+                        Some(SharedString::from("Cut")),
+                        Some(SharedString::from("Copy")),
+                        Some(SharedString::from("Paste")),
+                        Some(SharedString::from("Select All")),
+                    ]
+                );
+            });
+        }
+    }
+
+    fn rust_language_with_line_comments() -> Language {
+        Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                path_suffixes: vec!["rs".to_string()],
+                line_comments: vec!["// ".into()],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        )
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_comment_and_format(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new(
+            rust_language_with_line_comments(),
+            lsp::ServerCapabilities {
+                document_formatting_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Toggle Comment").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Format Document").is_some());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_without_formatting(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new(
+            rust_language_with_line_comments(),
+            lsp::ServerCapabilities::default(),
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Toggle Comment").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Format Document").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_git_changes(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+
+        cx.set_state(indoc! {"
+            ˇone
+            TWO
+            three
+            four
+            FIVE
+        "});
+        cx.set_diff_base(Some(indoc! {"
+            one
+            two
+            three
+            four
+            five
+        "}));
+        cx.run_until_parked();
+
+        let point = cx.display_point(indoc! {"
+            ˇone
+            TWO
+            three
+            four
+            FIVE
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Next Change").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Previous Change").is_some());
+
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.go_to_hunk(&GoToHunk, cx);
+        });
+        cx.assert_editor_state(indoc! {"
+            one
+            ˇTWO
+            three
+            four
+            FIVE
+        "});
+        cx.update_editor(|editor, cx| editor.go_to_hunk(&GoToHunk, cx));
+        cx.assert_editor_state(indoc! {"
+            one
+            TWO
+            three
+            four
+            ˇFIVE
+        "});
+        cx.update_editor(|editor, cx| editor.go_to_prev_hunk(&GoToPrevHunk, cx));
+        cx.assert_editor_state(indoc! {"
+            one
+            ˇTWO
+            three
+            four
+            FIVE
+        "});
+
+        // Without changes against the diff base, the items are hidden.
+        cx.set_diff_base(Some(indoc! {"
+            one
+            TWO
+            three
+            four
+            FIVE
+        "}));
+        cx.run_until_parked();
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Next Change").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Go to Previous Change").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_toggle_inlay_hints(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                inlay_hint_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Toggle Inlay Hints").is_some());
+
+        let enabled = cx.editor(|editor, _| editor.inlay_hints_enabled());
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.toggle_inlay_hints(&ToggleInlayHints, cx);
+        });
+        cx.editor(|editor, _| assert_eq!(editor.inlay_hints_enabled(), !enabled));
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_sections(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                references_provider: Some(lsp::OneOf::Left(true)),
+                rename_provider: Some(lsp::OneOf::Left(true)),
+                type_definition_provider: Some(lsp::TypeDefinitionProviderCapability::Simple(true)),
+                implementation_provider: Some(lsp::ImplementationProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state(indoc! {"
+            fn test() {
+                «do_workˇ»();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+
+        let context_menu = cx.editor(|editor, _app| {
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone()
+        });
+        cx.update(|cx| {
+            let labels = context_menu.read(cx).item_labels();
+            let labels = labels
+                .iter()
+                .map(|label| label.as_ref().map(|label| label.as_ref()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                labels,
+                [
+                    Some("Cut"),
+                    Some("Copy"),
+                    Some("Paste"),
+                    Some("Select All"),
+                    Some("Select All Occurrences of Selection"),
+                    None,
+                    Some("Move Line Up"),
+                    Some("Move Line Down"),
+                    None,
+                    Some("Go To"),
+                    Some("Find All References"),
+                    None,
+                    Some("Rename Symbol"),
+                    Some("Code Actions"),
+                    None,
+                    Some("Copy Path"),
+                    Some("Copy Relative Path"),
+                    Some("Reveal in Finder"),
+                    Some("Open Terminal Here"),
+                ]
+            );
+        });
+
+        // Keyboard navigation skips separators, and wraps around at either end.
+        cx.update(|cx| {
+            context_menu.update(cx, |menu, cx| {
+                menu.select_first(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                assert_eq!(menu.selected_index(), Some(3));
+                menu.select_next(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                assert_eq!(menu.selected_index(), Some(6));
+                menu.select_prev(&Default::default(), cx);
+                assert_eq!(menu.selected_index(), Some(4));
+
+                menu.select_first(&Default::default(), cx);
+                menu.select_prev(&Default::default(), cx);
+                assert_eq!(menu.selected_index(), Some(18));
+                menu.select_next(&Default::default(), cx);
+                assert_eq!(menu.selected_index(), Some(0));
+            })
+        });
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_icons(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                references_provider: Some(lsp::OneOf::Left(true)),
+                rename_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+
+        let context_menu = cx.editor(|editor, _app| {
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone()
+        });
+        let icons = cx.update(|cx| {
+            let menu = context_menu.read(cx);
+            menu.item_labels()
+                .into_iter()
+                .zip(menu.item_icons())
+                .filter_map(|(label, icon)| Some((label?.to_string(), icon)))
+                .collect::<HashMap<_, _>>()
+        });
+        assert_eq!(
+            icons["Find All References"],
+            Some(IconName::MagnifyingGlass)
+        );
+        assert_eq!(icons["Rename Symbol"], Some(IconName::Replace));
+        assert_eq!(icons["Code Actions"], Some(IconName::Bolt));
+        assert_eq!(icons["Paste"], None);
+
+        // Items without an icon are still rendered alongside the ones that have one.
+        assert!(cx.debug_bounds("MENU_ITEM-Paste").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Rename Symbol").is_some());
+
+        // Submenu items keep their icons.
+        let submenu = cx.update(|cx| {
+            context_menu.update(cx, |menu, cx| {
+                let ix = menu
+                    .item_labels()
+                    .iter()
+                    .position(|label| label.as_deref() == Some(GO_TO_SUBMENU))
+                    .unwrap();
+                menu.open_submenu(ix, cx);
+                menu.submenu_view().unwrap()
+            })
+        });
+        cx.update(|cx| {
+            let submenu = submenu.read(cx);
+            assert_eq!(
+                submenu
+                    .item_labels()
+                    .into_iter()
+                    .zip(submenu.item_icons())
+                    .collect::<Vec<_>>(),
+                [
+                    (
+                        Some(SharedString::from("Go to Definition")),
+                        Some(IconName::ArrowUpRight)
+                    ),
+                    (Some(SharedString::from("Peek Definition")), None),
+                ]
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_copy_breadcrumbs(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                document_symbol_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        #[allow(deprecated)]
+        fn symbol(
+            name: &str,
+            kind: lsp::SymbolKind,
+            lines: Range<u32>,
+            children: Vec<lsp::DocumentSymbol>,
+        ) -> lsp::DocumentSymbol {
+            let range = lsp::Range::new(
+                lsp::Position::new(lines.start, 0),
+                lsp::Position::new(lines.end, 1),
+            );
+            lsp::DocumentSymbol {
+                name: name.to_string(),
+                detail: None,
+                kind,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: Some(children),
+            }
+        }
+        let symbols = vec![
+            symbol(
+                "app",
+                lsp::SymbolKind::MODULE,
+                0..7,
+                vec![
+                    symbol("Runner", lsp::SymbolKind::STRUCT, 1..1, Vec::new()),
+                    symbol(
+                        "impl Runner",
+                        lsp::SymbolKind::OBJECT,
+                        2..6,
+                        vec![symbol("run", lsp::SymbolKind::METHOD, 3..5, Vec::new())],
+                    ),
+                ],
+            ),
+            symbol("main", lsp::SymbolKind::FUNCTION, 9..9, Vec::new()),
+        ];
+        let _symbol_requests =
+            cx.handle_request::<lsp::request::DocumentSymbolRequest, _, _>(move |_, _, _| {
+                let symbols = symbols.clone();
+                async move { Ok(Some(lsp::DocumentSymbolResponse::Nested(symbols))) }
+            });
+
+        cx.set_state(indoc! {"
+            mod app {
+                struct Runner;
+                impl Runner {
+                    fn run(&self) {
+                        ˇprintln!();
+                    }
+                }
+            }
+
+            fn main() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            mod app {
+                struct Runner;
+                impl Runner {
+                    fn run(&self) {
+                        pˇrintln!();
+                    }
+                }
+            }
+
+            fn main() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Copy Breadcrumbs").is_some());
+
+        cx.update_editor(|editor, cx| editor.copy_breadcrumbs(&CopyBreadcrumbs, cx).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("dir/file.rs › app › impl Runner › run".to_string())
+        );
+
+        // Outside of any symbol, only the file path is copied.
+        cx.set_state(indoc! {"
+            mod app {
+                struct Runner;
+                impl Runner {
+                    fn run(&self) {
+                        println!();
+                    }
+                }
+            }
+            ˇ
+            fn main() {}
+        "});
+        cx.update_editor(|editor, cx| editor.copy_breadcrumbs(&CopyBreadcrumbs, cx).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("dir/file.rs".to_string())
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_defers_selection_broadcast(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn teˇst() {
+                do_work();
+            }
+        "});
+        cx.run_until_parked();
+
+        let buffer = cx.multibuffer(|multibuffer, _| multibuffer.as_singleton().unwrap());
+        let selection_updates = Rc::new(Cell::new(0));
+        let _subscription = cx.update(|cx| {
+            let selection_updates = selection_updates.clone();
+            cx.subscribe(&buffer, move |_, event: &language::Event, _| {
+                if let language::Event::Operation(language::Operation::UpdateSelections {
+                    ..
+                }) = event
+                {
+                    selection_updates.set(selection_updates.get() + 1);
+                }
+            })
+        });
+
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let open_menu = |cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| {
+                deploy_context_menu(editor, Default::default(), point, cx)
+            });
+            cx.run_until_parked();
+            cx.editor(|editor, _| {
+                editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .clone()
+            })
+        };
+
+        // Opening and then cancelling the menu only moves the cursor locally.
+        let context_menu = open_menu(&mut cx);
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        cx.update(|cx| context_menu.update(cx, |menu, cx| menu.cancel(&Default::default(), cx)));
+        cx.run_until_parked();
+        assert_eq!(selection_updates.get(), 0);
+
+        // Choosing an item shares the cursor's new position.
+        let context_menu = open_menu(&mut cx);
+        assert_eq!(selection_updates.get(), 0);
+        cx.update(|cx| {
+            context_menu.update(cx, |menu, cx| {
+                menu.select_first(&Default::default(), cx);
+                menu.confirm(&Default::default(), cx);
+            })
+        });
+        cx.run_until_parked();
+        assert!(selection_updates.get() > 0);
+    }
+
+    #[gpui::test]
+    async fn test_code_actions_menu(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn teˇst() {
+                do_work();
+            }
+        "});
+        let point = cx.display_point(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let viewport_size = cx.update(|cx| cx.viewport_size());
+        let position = gpui::point(
+            viewport_size.width - gpui::px(10.),
+            viewport_size.height - gpui::px(10.),
+        );
+        cx.update_editor(|editor, cx| deploy_code_actions_menu(editor, position, point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+                do_wˇork();
+            }
+        "});
+        let context_menu = cx.editor(|editor, _app| {
+            editor
+                .mouse_context_menu
+                .as_ref()
+                .unwrap()
+                .context_menu
+                .clone()
+        });
+        cx.update(|cx| {
+            assert_eq!(
+                context_menu.read(cx).item_labels(),
+                [Some(SharedString::from("Code Actions"))]
+            );
+        });
+
+        let item = cx.debug_bounds("MENU_ITEM-Code Actions").unwrap();
+        assert!(item.top() >= gpui::Pixels::ZERO);
+        assert!(item.left() >= gpui::Pixels::ZERO);
+        assert!(item.bottom() <= position.y);
+        assert!(item.right() <= position.x);
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_open_terminal_here(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        let opened_terminals = Rc::new(RefCell::new(Vec::new()));
+        cx.update_workspace({
+            let opened_terminals = opened_terminals.clone();
+            move |workspace, _| {
+                workspace.register_action(move |_, action: &workspace::OpenTerminal, _| {
+                    opened_terminals
+                        .borrow_mut()
+                        .push(action.working_directory.clone())
+                });
+            }
+        });
+
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Open Terminal Here").is_some());
+
+        cx.update_editor(|editor, cx| editor.open_in_terminal(&OpenInTerminal, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            opened_terminals.borrow().as_slice(),
+            [std::path::PathBuf::from("/root/dir")]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_run_test(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let language = Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                path_suffixes: vec!["rs".to_string()],
+                run_test_command: Some("cargo test -- $ZED_TEST_NAME".into()),
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        )
+        .with_runnable_query(indoc! {r##"
+            (
+                (attribute_item) @_attribute
+                .
+                (function_item
+                    name: (_) @name) @run
+                (#eq? @_attribute "#[test]")
+            )"##})
+        .unwrap();
+        let mut cx = EditorLspTestContext::new(language, Default::default(), cx).await;
+        let spawned_commands = Rc::new(RefCell::new(Vec::new()));
+        cx.update_workspace({
+            let spawned_commands = spawned_commands.clone();
+            move |workspace, _| {
+                workspace.register_action(move |_, action: &workspace::SpawnInTerminal, _| {
+                    spawned_commands.borrow_mut().push(action.clone())
+                });
+            }
+        });
+
+        // There is no test to run outside of a test function
+        cx.set_state(indoc! {"
+            fn heˇlper() {}
+
+            #[test]
+            fn it_works() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn heˇlper() {}
+
+            #[test]
+            fn it_works() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Run Test").is_none());
+        cx.update_editor(|editor, _| editor.mouse_context_menu.take());
+
+        let point = cx.display_point(indoc! {"
+            fn helper() {}
+
+            #[test]
+            fn it_woˇrks() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Run Test").is_some());
+
+        cx.update_editor(|editor, cx| runnables::run_test_at_cursor(editor, &RunTestAtCursor, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            spawned_commands.borrow().as_slice(),
+            [workspace::SpawnInTerminal {
+                command: "cargo test -- it_works".into(),
+                working_directory: Some(std::path::PathBuf::from("/root/dir")),
+            }]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_open_terminal_here_without_file(
+        cx: &mut gpui::TestAppContext,
+    ) {
+        init_test(cx, |_| {});
+
+        let cx = &mut EditorTestContext::new(cx).await;
+        cx.set_state("fn teˇst() {}");
+        let point = cx.display_point("fn teˇst() {}");
+        cx.update_editor(|editor, cx| {
+            assert_eq!(editor.local_file_directory(cx), None);
+            deploy_context_menu(editor, Default::default(), point, cx)
+        });
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Open Terminal Here").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Copy Path").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Copy Relative Path").is_none());
+        assert!(cx.debug_bounds("MENU_ITEM-Reveal in Finder").is_none());
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_copy_paths(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            fn teˇst() {}
+        "});
+        let point = cx.display_point(indoc! {"
+            fn teˇst() {}
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("MENU_ITEM-Copy Path").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Copy Relative Path").is_some());
+        assert!(cx.debug_bounds("MENU_ITEM-Reveal in Finder").is_some());
+
+        cx.update_editor(|editor, cx| editor.copy_path(&CopyPath, cx));
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("/root/dir/file.rs".to_string())
+        );
+        cx.update_editor(|editor, cx| editor.copy_relative_path(&CopyRelativePath, cx));
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("dir/file.rs".to_string())
+        );
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_select_all_occurrences(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(lsp::ServerCapabilities::default(), cx).await;
+        cx.set_state(indoc! {"
+            let «fooˇ» = barfoo(foo);
+        "});
+        let point = cx.display_point(indoc! {"
+            let fˇoo = barfoo(foo);
+        "});
+        cx.update_editor(|editor, cx| deploy_context_menu(editor, Default::default(), point, cx));
+        cx.update(|cx| cx.refresh());
+        cx.run_until_parked();
+        assert!(cx
+            .debug_bounds("MENU_ITEM-Select All Occurrences of Selection")
+            .is_some());
+
+        cx.update_editor(|editor, cx| {
+            editor.mouse_context_menu.take();
+            editor.select_all_matches(&SelectAllMatches, cx).unwrap();
+        });
+        cx.assert_editor_state(indoc! {"
+            let «fooˇ» = bar«fooˇ»(«fooˇ»);
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_mouse_context_menu_generate_doc_comment(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let language = Language::new(
+            LanguageConfig {
+                name: "Rust".into(),
+                path_suffixes: vec!["rs".to_string()],
+                line_comments: vec!["// ".into(), "/// ".into()],
+                ..Default::default()
+            },
+            Some(tree_sitter_rust::language()),
+        );
+        let mut cx = EditorLspTestContext::new(
+            language,
+            lsp::ServerCapabilities {
+                document_symbol_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        #[allow(deprecated)]
+        let add = lsp::DocumentSymbol {
+            name: "add".to_string(),
+            detail: Some("fn(a: i32, mut b: i32) -> i32".to_string()),
+            kind: lsp::SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            range: lsp::Range::new(lsp::Position::new(2, 0), lsp::Position::new(4, 1)),
+            selection_range: lsp::Range::new(lsp::Position::new(2, 3), lsp::Position::new(2, 6)),
+            children: None,
+        };
+        let _symbol_requests =
+            cx.handle_request::<lsp::request::DocumentSymbolRequest, _, _>(move |_, _, _| {
+                let add = add.clone();
+                async move { Ok(Some(lsp::DocumentSymbolResponse::Nested(vec![add]))) }
+            });
+
+        fn menu_labels(cx: &mut EditorLspTestContext) -> Vec<Option<SharedString>> {
+            cx.executor()
+                .advance_clock(crate::CODE_ACTIONS_DEBOUNCE_TIMEOUT);
+            cx.run_until_parked();
+            cx.update_editor(|editor, cx| {
+                let point = editor.selections.newest_display(cx).head();
+                deploy_context_menu(editor, Default::default(), point, cx);
+                let menu = editor.mouse_context_menu.take().unwrap().context_menu;
+                let labels = menu.read(cx).item_labels();
+                labels
+            })
+        }
+
+        cx.set_state(indoc! {"
+            struct Calculator;
+
+            fn add(a: i32, mut b: i32) -> i32 {
+                aˇ + b
+            }
+        "});
+        assert!(menu_labels(&mut cx).contains(&Some("Generate Doc Comment".into())));
+
+        cx.update_editor(|editor, cx| {
+            doc_comment::generate_doc_comment(editor, &GenerateDocComment, cx)
+        });
+        cx.assert_editor_state(indoc! {"
+            struct Calculator;
+
+            /// Describe `add`.
+            ///
+            /// # Arguments
+            ///
+            /// * `a`
+            /// * `b`
+            fn add(a: i32, mut b: i32) -> i32 {
+                aˇ + b
+            }
+        "});
+
+        // Outside of a function there is nothing to document.
+        cx.set_state(indoc! {"
+            struct Calcˇulator;
+
+            fn add(a: i32, mut b: i32) -> i32 {
+                a + b
+            }
+        "});
+        assert!(!menu_labels(&mut cx).contains(&Some("Generate Doc Comment".into())));
+    }
+
+    #[gpui::test]
+    async fn test_gutter_context_menu(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorTestContext::new(cx).await;
+        cx.set_state(indoc! {"
+            fn test() {
+                do_work();ˇ
+            }
+        "});
+
+        fn deploy(buffer_row: u32, cx: &mut EditorTestContext) -> View<ContextMenu> {
+            cx.update_editor(|editor, cx| {
+                deploy_gutter_context_menu(editor, &DeployGutterContextMenu { buffer_row }, cx);
+                editor
+                    .mouse_context_menu
+                    .as_ref()
+                    .unwrap()
+                    .context_menu
+                    .clone()
+            })
+        }
+
+        let context_menu = deploy(0, &mut cx);
+        cx.update(|cx| {
+            assert_eq!(
+                context_menu.read(cx).item_labels(),
+                [
+                    Some("Copy Line Number".into()),
+                    Some("Toggle Bookmark".into()),
+                    Some("Select Line".into()),
+                    None,
+                    Some("Fold".into()),
+                ]
+            );
+        });
+
+        // The cursor stays where it was until an item is chosen, and the selections
+        // are only broadcast once it is.
+        cx.set_diff_base(Some(indoc! {"
+            fn test() {
+                do_other_work();
+            }
+        "}));
+        cx.run_until_parked();
+        let context_menu = deploy(1, &mut cx);
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+                do_work();ˇ
+            }
+        "});
+        cx.update_editor(|editor, _| assert!(editor.defer_selection_broadcast));
+        cx.update(|cx| {
+            assert_eq!(
+                context_menu.read(cx).item_labels(),
+                [
+                    Some("Copy Line Number".into()),
+                    Some("Toggle Bookmark".into()),
+                    Some("Select Line".into()),
+                    Some("Toggle Git Hunk Diff".into()),
+                ]
+            );
+            context_menu.update(cx, |menu, cx| {
+                menu.select_first(&Default::default(), cx);
+                menu.confirm(&Default::default(), cx);
+            });
+        });
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("2".to_string())
+        );
+
+        let context_menu = deploy(1, &mut cx);
+        cx.update(|cx| {
+            context_menu.update(cx, |menu, cx| {
+                menu.select_first(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                menu.select_next(&Default::default(), cx);
+                menu.confirm(&Default::default(), cx);
+            });
+        });
+        cx.assert_editor_state(indoc! {"
+            fn test() {
+            «    do_work();
+            ˇ»}
+        "});
+        cx.update_editor(|editor, _| assert!(!editor.defer_selection_broadcast));
+    }
 }
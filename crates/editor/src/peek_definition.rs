@@ -0,0 +1,418 @@
+use crate::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    scroll::Autoscroll,
+    Editor, OpenPeekedDefinitionInSplit, PeekDefinition, PeekNextDefinition,
+    PeekPreviousDefinition,
+};
+use gpui::{
+    div, AppContext, IntoElement, Model, ParentElement, Render, Styled, View, ViewContext,
+    VisualContext, WeakView,
+};
+use language::{OffsetRangeExt, ToPoint};
+use project::{Location, Project};
+use std::sync::Arc;
+use ui::{prelude::*, IconButton, Tooltip};
+use util::ResultExt;
+
+/// How many lines of the editor the peek takes up, including its header.
+const PEEK_HEIGHT: u8 = 14;
+
+/// A definition shown in an editor embedded below the line it was peeked from, so that
+/// it can be read without navigating away.
+pub struct PeekState {
+    view: View<PeekView>,
+    block_id: BlockId,
+}
+
+pub struct PeekView {
+    locations: Vec<Location>,
+    index: usize,
+    editor: View<Editor>,
+    parent: WeakView<Editor>,
+}
+
+pub fn peek_definition(editor: &mut Editor, _: &PeekDefinition, cx: &mut ViewContext<Editor>) {
+    let Some(project) = editor.project.clone() else {
+        return;
+    };
+    let head = editor.selections.newest_anchor().head();
+    let Some((buffer, buffer_head)) = editor.buffer.read(cx).text_anchor_for_position(head, cx)
+    else {
+        return;
+    };
+
+    let definitions = project.update(cx, |project, cx| {
+        project.definition(&buffer, buffer_head, cx)
+    });
+    cx.spawn(|editor, mut cx| async move {
+        let locations = definitions
+            .await?
+            .into_iter()
+            .map(|link| link.target)
+            .collect::<Vec<_>>();
+        editor.update(&mut cx, |editor, cx| show_peek(editor, head, locations, cx))
+    })
+    .detach_and_log_err(cx);
+}
+
+fn show_peek(
+    editor: &mut Editor,
+    position: crate::Anchor,
+    locations: Vec<Location>,
+    cx: &mut ViewContext<Editor>,
+) {
+    close_peek(editor, cx);
+    if locations.is_empty() {
+        return;
+    }
+    let Some(project) = editor.project.clone() else {
+        return;
+    };
+
+    let parent = cx.view().downgrade();
+    let view = cx.new_view(|cx| {
+        let editor = PeekView::build_editor(&locations[0], project.clone(), cx);
+        PeekView {
+            locations,
+            index: 0,
+            editor,
+            parent,
+        }
+    });
+    let block_id = editor.insert_blocks(
+        [BlockProperties {
+            style: BlockStyle::Sticky,
+            position,
+            height: PEEK_HEIGHT,
+            render: Arc::new({
+                let view = view.clone();
+                move |cx: &mut BlockContext| {
+                    div()
+                        .h(cx.line_height * PEEK_HEIGHT as f32)
+                        .w_full()
+                        .pl(cx.gutter_width)
+                        .child(view.clone())
+                        .into_any_element()
+                }
+            }),
+            disposition: BlockDisposition::Below,
+        }],
+        Some(Autoscroll::fit()),
+        cx,
+    )[0];
+    editor.peek = Some(PeekState { view, block_id });
+    cx.notify();
+}
+
+/// Closes the peek, returning whether one was open.
+pub fn close_peek(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> bool {
+    let Some(peek) = editor.peek.take() else {
+        return false;
+    };
+    if peek
+        .view
+        .read(cx)
+        .editor
+        .focus_handle(cx)
+        .contains_focused(cx)
+    {
+        editor.focus(cx);
+    }
+    editor.remove_blocks([peek.block_id].into_iter().collect(), None, cx);
+    cx.notify();
+    true
+}
+
+pub fn peek_next_definition(
+    editor: &mut Editor,
+    _: &PeekNextDefinition,
+    cx: &mut ViewContext<Editor>,
+) {
+    if let Some(peek) = &editor.peek {
+        peek.view.update(cx, |peek, cx| peek.select_next(cx));
+    }
+}
+
+pub fn peek_previous_definition(
+    editor: &mut Editor,
+    _: &PeekPreviousDefinition,
+    cx: &mut ViewContext<Editor>,
+) {
+    if let Some(peek) = &editor.peek {
+        peek.view.update(cx, |peek, cx| peek.select_prev(cx));
+    }
+}
+
+pub fn open_peeked_definition_in_split(
+    editor: &mut Editor,
+    _: &OpenPeekedDefinitionInSplit,
+    cx: &mut ViewContext<Editor>,
+) {
+    if let Some(location) = editor
+        .peek
+        .as_ref()
+        .map(|peek| peek.view.read(cx).location().clone())
+    {
+        open_in_split(editor, location, cx);
+    }
+}
+
+fn open_in_split(editor: &mut Editor, location: Location, cx: &mut ViewContext<Editor>) {
+    let Some(workspace) = editor.workspace() else {
+        return;
+    };
+    close_peek(editor, cx);
+
+    let range = location.range.to_offset(location.buffer.read(cx));
+    cx.window_context().defer(move |cx| {
+        let target_editor: View<Editor> = workspace.update(cx, |workspace, cx| {
+            workspace.split_project_item(location.buffer.clone(), cx)
+        });
+        target_editor.update(cx, |target_editor, cx| {
+            target_editor
+                .change_selections(Some(Autoscroll::center()), cx, |s| s.select_ranges([range]));
+        });
+    });
+}
+
+impl PeekView {
+    fn build_editor(
+        location: &Location,
+        project: Model<Project>,
+        cx: &mut ViewContext<Self>,
+    ) -> View<Editor> {
+        let range = location.range.to_offset(location.buffer.read(cx));
+        cx.new_view(|cx| {
+            let mut editor = Editor::for_buffer(location.buffer.clone(), Some(project), cx);
+            editor.change_selections(Some(Autoscroll::center()), cx, |s| s.select_ranges([range]));
+            editor
+        })
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.locations[self.index]
+    }
+
+    pub fn editor(&self) -> &View<Editor> {
+        &self.editor
+    }
+
+    fn select(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let Some(project) = self.editor.read(cx).project.clone() else {
+            return;
+        };
+        self.index = index;
+        self.editor = Self::build_editor(&self.locations[index], project, cx);
+        cx.notify();
+    }
+
+    fn select_next(&mut self, cx: &mut ViewContext<Self>) {
+        self.select((self.index + 1) % self.locations.len(), cx);
+    }
+
+    fn select_prev(&mut self, cx: &mut ViewContext<Self>) {
+        let count = self.locations.len();
+        self.select((self.index + count - 1) % count, cx);
+    }
+
+    fn title(&self, cx: &AppContext) -> String {
+        let location = self.location();
+        let buffer = location.buffer.read(cx);
+        let path = buffer
+            .file()
+            .map(|file| file.path().to_string_lossy().to_string())
+            .unwrap_or_else(|| "untitled".to_string());
+        let row = location.range.start.to_point(buffer).row;
+        format!("{path}:{}", row + 1)
+    }
+}
+
+impl Render for PeekView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let count = self.locations.len();
+        v_flex()
+            .size_full()
+            .border_y_1()
+            .border_color(cx.theme().colors().border)
+            .child(
+                h_flex()
+                    .px_2()
+                    .gap_1()
+                    .bg(cx.theme().colors().editor_subheader_background)
+                    .child(Label::new(self.title(cx)).size(LabelSize::Small))
+                    .when(count > 1, |header| {
+                        header
+                            .child(
+                                Label::new(format!("{} of {count}", self.index + 1))
+                                    .size(LabelSize::Small)
+                                    .color(Color::Muted),
+                            )
+                            .child(
+                                IconButton::new("peek-previous", IconName::ChevronLeft)
+                                    .tooltip(|cx| Tooltip::text("Previous Definition", cx))
+                                    .on_click(cx.listener(|this, _, cx| this.select_prev(cx))),
+                            )
+                            .child(
+                                IconButton::new("peek-next", IconName::ChevronRight)
+                                    .tooltip(|cx| Tooltip::text("Next Definition", cx))
+                                    .on_click(cx.listener(|this, _, cx| this.select_next(cx))),
+                            )
+                    })
+                    .child(div().flex_1())
+                    .child(
+                        IconButton::new("peek-open-in-split", IconName::Split)
+                            .tooltip(|cx| Tooltip::text("Open in Split", cx))
+                            .on_click(cx.listener(|this, _, cx| {
+                                let location = this.location().clone();
+                                this.parent
+                                    .update(cx, |editor, cx| open_in_split(editor, location, cx))
+                                    .log_err();
+                            })),
+                    )
+                    .child(
+                        IconButton::new("peek-close", IconName::Close)
+                            .tooltip(|cx| Tooltip::text("Close", cx))
+                            .on_click(cx.listener(|this, _, cx| {
+                                this.parent
+                                    .update(cx, |editor, cx| close_peek(editor, cx))
+                                    .log_err();
+                            })),
+                    ),
+            )
+            .child(div().flex_1().child(self.editor.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext, Cancel,
+    };
+    use futures::StreamExt;
+    use indoc::indoc;
+    use language::Point;
+    use lsp::request::GotoDefinition;
+
+    #[gpui::test]
+    async fn test_peek_definition(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn one() {}
+            fn two() {}
+            fn test() { ˇone(); }
+        "});
+        let first_range = cx.lsp_range(indoc! {"
+            fn «one»() {}
+            fn two() {}
+            fn test() { one(); }
+        "});
+        let second_range = cx.lsp_range(indoc! {"
+            fn one() {}
+            fn «two»() {}
+            fn test() { one(); }
+        "});
+        let mut requests = cx.handle_request::<GotoDefinition, _, _>(move |url, _, _| async move {
+            Ok(Some(lsp::GotoDefinitionResponse::Array(vec![
+                lsp::Location::new(url.clone(), first_range),
+                lsp::Location::new(url, second_range),
+            ])))
+        });
+        cx.update_editor(|editor, cx| peek_definition(editor, &PeekDefinition, cx));
+        requests.next().await;
+        cx.run_until_parked();
+
+        let peeked_text = |cx: &mut EditorLspTestContext| {
+            cx.editor(|editor, cx| {
+                let peek = editor.peek.as_ref().unwrap().view.read(cx);
+                let peek_editor = peek.editor().read(cx);
+                let selection = peek_editor.selections.newest::<Point>(cx);
+                peek_editor
+                    .buffer()
+                    .read(cx)
+                    .snapshot(cx)
+                    .text_for_range(selection.range())
+                    .collect::<String>()
+            })
+        };
+
+        // The peek shows the first definition, without moving the cursor
+        assert_eq!(peeked_text(&mut cx), "one");
+        cx.assert_editor_state(indoc! {"
+            fn one() {}
+            fn two() {}
+            fn test() { ˇone(); }
+        "});
+
+        // The other definitions can be stepped through, wrapping around at either end
+        cx.update_editor(|editor, cx| peek_next_definition(editor, &PeekNextDefinition, cx));
+        assert_eq!(peeked_text(&mut cx), "two");
+        cx.update_editor(|editor, cx| peek_next_definition(editor, &PeekNextDefinition, cx));
+        assert_eq!(peeked_text(&mut cx), "one");
+        cx.update_editor(|editor, cx| {
+            peek_previous_definition(editor, &PeekPreviousDefinition, cx)
+        });
+        assert_eq!(peeked_text(&mut cx), "two");
+
+        cx.update_editor(|editor, cx| editor.cancel(&Cancel, cx));
+        cx.editor(|editor, _| assert!(editor.peek.is_none()));
+        cx.assert_editor_state(indoc! {"
+            fn one() {}
+            fn two() {}
+            fn test() { ˇone(); }
+        "});
+    }
+
+    #[gpui::test]
+    async fn test_open_peeked_definition_in_split(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+
+        let mut cx = EditorLspTestContext::new_rust(
+            lsp::ServerCapabilities {
+                definition_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+        cx.set_state(indoc! {"
+            fn one() {}
+            fn test() { ˇone(); }
+        "});
+        let target_range = cx.lsp_range(indoc! {"
+            fn «one»() {}
+            fn test() { one(); }
+        "});
+        let mut requests = cx.handle_request::<GotoDefinition, _, _>(move |url, _, _| async move {
+            Ok(Some(lsp::GotoDefinitionResponse::Scalar(
+                lsp::Location::new(url, target_range),
+            )))
+        });
+        cx.update_editor(|editor, cx| peek_definition(editor, &PeekDefinition, cx));
+        requests.next().await;
+        cx.run_until_parked();
+        cx.editor(|editor, _| assert!(editor.peek.is_some()));
+
+        cx.update_editor(|editor, cx| {
+            open_peeked_definition_in_split(editor, &OpenPeekedDefinitionInSplit, cx)
+        });
+        cx.run_until_parked();
+        cx.editor(|editor, _| assert!(editor.peek.is_none()));
+        let split_selection = cx.update_workspace(|workspace, cx| {
+            assert_eq!(workspace.panes().len(), 2);
+            let split_editor = workspace.active_item_as::<Editor>(cx).unwrap();
+            let selection = split_editor.read(cx).selections.newest::<Point>(cx);
+            selection.range()
+        });
+        assert_eq!(split_selection, Point::new(0, 3)..Point::new(0, 6));
+    }
+}
@@ -1,7 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use anyhow::Result;
+
+use db::sqlez::debounced_writer::DebouncedWriter;
 use db::sqlez_macros::sql;
 use db::{define_connection, query};
+use futures::Future;
 
 use workspace::{ItemId, WorkspaceDb, WorkspaceId};
 
@@ -15,6 +22,43 @@ define_connection!(
     //   scroll_vertical_offset: f32,
     //   scroll_horizontal_offset: f32,
     // )
+    //
+    // file_editor_states(
+    //   workspace_id: usize,
+    //   path: PathBuf,
+    //   scroll_top_row: usize,
+    //   scroll_horizontal_offset: f32,
+    //   scroll_vertical_offset: f32,
+    // )
+    //
+    // file_editor_selections(
+    //   workspace_id: usize,
+    //   path: PathBuf,
+    //   start_offset: usize,
+    //   end_offset: usize,
+    //   reversed: bool,
+    // )
+    //
+    // file_editor_folds(
+    //   workspace_id: usize,
+    //   path: PathBuf,
+    //   start_offset: usize,
+    //   end_offset: usize,
+    // )
+    //
+    // editor_unsaved_contents(
+    //   item_id: usize,
+    //   workspace_id: usize,
+    //   path: Option<PathBuf>,
+    //   contents: String,
+    // )
+    //
+    // clipboard_history(
+    //   id: usize,
+    //   text: String,
+    //   selections: String,
+    //   language: Option<String>,
+    // )
     pub static ref DB: EditorDb<WorkspaceDb> =
         &[sql! (
             CREATE TABLE editors(
@@ -31,9 +75,70 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN scroll_top_row INTEGER NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_horizontal_offset REAL NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_vertical_offset REAL NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            CREATE TABLE file_editor_states(
+                workspace_id INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                scroll_top_row INTEGER NOT NULL,
+                scroll_horizontal_offset REAL NOT NULL,
+                scroll_vertical_offset REAL NOT NULL,
+                PRIMARY KEY(workspace_id, path),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+            CREATE TABLE file_editor_selections(
+                workspace_id INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                reversed INTEGER NOT NULL,
+                FOREIGN KEY(workspace_id, path) REFERENCES file_editor_states(workspace_id, path)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+            CREATE TABLE file_editor_folds(
+                workspace_id INTEGER NOT NULL,
+                path BLOB NOT NULL,
+                start_offset INTEGER NOT NULL,
+                end_offset INTEGER NOT NULL,
+                FOREIGN KEY(workspace_id, path) REFERENCES file_editor_states(workspace_id, path)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+        ),
+        sql! (
+            CREATE TABLE editor_unsaved_contents(
+                item_id INTEGER NOT NULL,
+                workspace_id INTEGER NOT NULL,
+                path BLOB,
+                contents TEXT NOT NULL,
+                PRIMARY KEY(item_id, workspace_id),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+                ON UPDATE CASCADE
+            ) STRICT;
+        ),
+        sql! (
+            CREATE TABLE clipboard_history(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                text TEXT NOT NULL,
+                selections TEXT NOT NULL,
+                language TEXT
+            ) STRICT;
         )];
 );
 
+/// How often the state that changes often, such as the state of the editor's file, is
+/// written.
+const QUEUED_WRITES_INTERVAL: Duration = Duration::from_millis(500);
+
+lazy_static::lazy_static! {
+    static ref QUEUED_WRITES: DebouncedWriter<(WorkspaceDb, EditorDb)> =
+        DebouncedWriter::new(DB.0.clone(), QUEUED_WRITES_INTERVAL);
+}
+
 impl EditorDb {
     query! {
         pub fn get_path(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<PathBuf>> {
@@ -80,4 +185,195 @@ impl EditorDb {
             WHERE item_id = ?1 AND workspace_id = ?2
         }
     }
+
+    /// Writes the queued state right away, such as when the app quits.
+    pub fn flush_queued_writes(&self) -> impl Future<Output = Result<()>> {
+        QUEUED_WRITES.flush()
+    }
+
+    // Returns the scroll top row, and offset, of the last editor of the file at `path`
+    query! {
+        pub fn get_file_scroll_position(workspace_id: WorkspaceId, path: &Path) -> Result<Option<(u32, f32, f32)>> {
+            SELECT scroll_top_row, scroll_horizontal_offset, scroll_vertical_offset
+            FROM file_editor_states
+            WHERE workspace_id = ? AND path = ?
+        }
+    }
+
+    // Returns the buffer offsets of each selection, and whether it's reversed
+    query! {
+        pub fn get_file_selections(workspace_id: WorkspaceId, path: &Path) -> Result<Vec<(usize, usize, bool)>> {
+            SELECT start_offset, end_offset, reversed
+            FROM file_editor_selections
+            WHERE workspace_id = ? AND path = ?
+            ORDER BY start_offset
+        }
+    }
+
+    // Returns the buffer offsets of each fold
+    query! {
+        pub fn get_file_folds(workspace_id: WorkspaceId, path: &Path) -> Result<Vec<(usize, usize)>> {
+            SELECT start_offset, end_offset
+            FROM file_editor_folds
+            WHERE workspace_id = ? AND path = ?
+            ORDER BY start_offset
+        }
+    }
+
+    /// Queues the state of the last editor of the file at `path` to be saved, replacing the
+    /// one queued or saved before, so that it can be restored when the file is opened again.
+    pub fn queue_file_state(
+        &self,
+        workspace_id: WorkspaceId,
+        path: PathBuf,
+        scroll_position: (u32, f32, f32),
+        selections: Vec<(usize, usize, bool)>,
+        folds: Vec<(usize, usize)>,
+    ) {
+        let key = format!("{workspace_id}:{path:?}");
+        QUEUED_WRITES.write("file_editor_states", key, move |conn| {
+            let (top_row, horizontal_offset, vertical_offset) = scroll_position;
+            conn.exec_bound(sql!(
+                INSERT INTO file_editor_states
+                    (workspace_id, path, scroll_top_row, scroll_horizontal_offset, scroll_vertical_offset)
+                VALUES
+                    (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT DO UPDATE SET
+                    scroll_top_row = ?3,
+                    scroll_horizontal_offset = ?4,
+                    scroll_vertical_offset = ?5
+            ))?((workspace_id, path.as_path(), top_row, horizontal_offset, vertical_offset))?;
+
+            conn.exec_bound(sql!(
+                DELETE FROM file_editor_selections WHERE workspace_id = ? AND path = ?
+            ))?((workspace_id, path.as_path()))?;
+            let mut insert_selection = conn.exec_bound(sql!(
+                INSERT INTO file_editor_selections
+                    (workspace_id, path, start_offset, end_offset, reversed)
+                VALUES
+                    (?, ?, ?, ?, ?)
+            ))?;
+            for (start, end, reversed) in selections {
+                insert_selection((workspace_id, path.as_path(), start, end, reversed))?;
+            }
+
+            conn.exec_bound(sql!(
+                DELETE FROM file_editor_folds WHERE workspace_id = ? AND path = ?
+            ))?((workspace_id, path.as_path()))?;
+            let mut insert_fold = conn.exec_bound(sql!(
+                INSERT INTO file_editor_folds
+                    (workspace_id, path, start_offset, end_offset)
+                VALUES
+                    (?, ?, ?, ?)
+            ))?;
+            for (start, end) in folds {
+                insert_fold((workspace_id, path.as_path(), start, end))?;
+            }
+            Ok(())
+        });
+    }
+
+    // Returns the path of the editor's file, if it has one, and its unsaved contents
+    query! {
+        pub fn get_unsaved_contents(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<(Option<PathBuf>, String)>> {
+            SELECT path, contents FROM editor_unsaved_contents
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
+
+    query! {
+        pub async fn save_unsaved_contents(item_id: ItemId, workspace_id: WorkspaceId, path: Option<PathBuf>, contents: String) -> Result<()> {
+            INSERT INTO editor_unsaved_contents
+                (item_id, workspace_id, path, contents)
+            VALUES
+                (?1, ?2, ?3, ?4)
+            ON CONFLICT DO UPDATE SET
+                path = ?3,
+                contents = ?4
+        }
+    }
+
+    query! {
+        pub async fn delete_unsaved_contents(item_id: ItemId, workspace_id: WorkspaceId) -> Result<()> {
+            DELETE FROM editor_unsaved_contents
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
+
+    // Returns the text, the selections serialized as JSON and the language of the entries
+    // of the clipboard history, the most recent first
+    query! {
+        pub fn get_clipboard_history() -> Result<Vec<(String, String, Option<String>)>> {
+            SELECT text, selections, language FROM clipboard_history
+            ORDER BY id DESC
+        }
+    }
+
+    /// Adds the entry to the clipboard history as the most recent one, forgetting the least
+    /// recent entries beyond the given limit.
+    pub async fn save_clipboard_entry(
+        &self,
+        text: String,
+        selections: String,
+        language: Option<String>,
+        limit: usize,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_clipboard_entry", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM clipboard_history WHERE text = ?
+                ))?(text.as_str())?;
+                conn.exec_bound(sql!(
+                    INSERT INTO clipboard_history (text, selections, language)
+                    VALUES (?, ?, ?)
+                ))?((text.as_str(), selections.as_str(), language.as_deref()))?;
+                conn.exec_bound(sql!(
+                    DELETE FROM clipboard_history WHERE id NOT IN (
+                        SELECT id FROM clipboard_history ORDER BY id DESC LIMIT ?
+                    )
+                ))?(limit)?;
+                Ok(())
+            })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[gpui::test]
+    async fn test_queued_file_states_replace_each_other() {
+        let workspace_id = workspace::WORKSPACE_DB.next_id().await.unwrap();
+        let path = PathBuf::from("/a.txt");
+
+        DB.queue_file_state(
+            workspace_id,
+            path.clone(),
+            (1, 0., 0.),
+            vec![],
+            vec![(0, 5)],
+        );
+        DB.queue_file_state(
+            workspace_id,
+            path.clone(),
+            (2, 0., 0.),
+            vec![(3, 4, true)],
+            vec![(0, 5), (10, 20)],
+        );
+        DB.flush_queued_writes().await.unwrap();
+        assert_eq!(
+            DB.get_file_scroll_position(workspace_id, &path).unwrap(),
+            Some((2, 0., 0.))
+        );
+        assert_eq!(
+            DB.get_file_selections(workspace_id, &path).unwrap(),
+            [(3, 4, true)]
+        );
+        assert_eq!(
+            DB.get_file_folds(workspace_id, &path).unwrap(),
+            [(0, 5), (10, 20)]
+        );
+    }
 }
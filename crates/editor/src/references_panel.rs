@@ -0,0 +1,569 @@
+use crate::{scroll::Autoscroll, Editor};
+use gpui::{
+    actions, px, uniform_list, AnyElement, AppContext, EventEmitter, FocusHandle, FocusableView,
+    Model, Subscription, UniformListScrollHandle, View, WeakView,
+};
+use language::{Anchor, Buffer, BufferSnapshot, OffsetRangeExt, Point, ToOffset, ToPoint};
+use project::Location;
+use std::ops::Range;
+use ui::prelude::*;
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    Workspace,
+};
+
+actions!(references_panel, [ToggleFocus]);
+
+/// How many lines are shown above and below each reference.
+const CONTEXT_LINES: u32 = 1;
+
+/// How many rows of the list each reference takes up. References at the start or end of
+/// their file are padded with empty rows, so that only the visible rows need to be rendered.
+const ROWS_PER_REFERENCE: usize = 2 * CONTEXT_LINES as usize + 1;
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(|workspace, _: &ToggleFocus, cx| {
+            workspace.toggle_panel_focus::<ReferencesPanel>(cx);
+        });
+    })
+    .detach();
+}
+
+/// Lists the results of the last Find All References, grouped by file. The references are
+/// anchored into their buffers, so they follow any edits made after the search.
+pub struct ReferencesPanel {
+    title: SharedString,
+    files: Vec<ReferencedFile>,
+    selected_ix: Option<usize>,
+    workspace: WeakView<Workspace>,
+    position: DockPosition,
+    width: Option<Pixels>,
+    height: Option<Pixels>,
+    focus_handle: FocusHandle,
+    scroll_handle: UniformListScrollHandle,
+    _buffer_subscriptions: Vec<Subscription>,
+}
+
+struct ReferencedFile {
+    buffer: Model<Buffer>,
+    path: SharedString,
+    ranges: Vec<Range<Anchor>>,
+}
+
+/// What is shown in a row of the list, which is either a file's header or one of the lines
+/// shown for a reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ListRow {
+    File {
+        file_ix: usize,
+    },
+    Line {
+        file_ix: usize,
+        range_ix: usize,
+        reference_ix: usize,
+        /// Which of the reference's rows this is, with the reference's own line at
+        /// `CONTEXT_LINES`.
+        line_ix: usize,
+    },
+}
+
+impl ReferencesPanel {
+    pub fn new(workspace: WeakView<Workspace>, cx: &mut ViewContext<Self>) -> Self {
+        Self {
+            title: SharedString::default(),
+            files: Vec::new(),
+            selected_ix: None,
+            workspace,
+            position: DockPosition::Bottom,
+            width: None,
+            height: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: UniformListScrollHandle::new(),
+            _buffer_subscriptions: Vec::new(),
+        }
+    }
+
+    /// Replaces the listed references, grouping them by the buffer they're in.
+    pub fn set_locations(
+        &mut self,
+        title: String,
+        mut locations: Vec<Location>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        locations.sort_by_key(|location| {
+            let buffer = location.buffer.read(cx);
+            (
+                buffer.file().map(|file| file.path().clone()),
+                buffer.remote_id(),
+                location.range.start.to_offset(buffer),
+            )
+        });
+
+        let mut files = Vec::<ReferencedFile>::new();
+        for location in locations {
+            match files.last_mut() {
+                Some(file) if file.buffer == location.buffer => file.ranges.push(location.range),
+                _ => {
+                    let path = location
+                        .buffer
+                        .read(cx)
+                        .file()
+                        .map(|file| file.path().to_string_lossy().to_string())
+                        .unwrap_or_else(|| "untitled".to_string());
+                    files.push(ReferencedFile {
+                        buffer: location.buffer,
+                        path: path.into(),
+                        ranges: vec![location.range],
+                    });
+                }
+            }
+        }
+
+        self._buffer_subscriptions = files
+            .iter()
+            .map(|file| cx.observe(&file.buffer, |_, _, cx| cx.notify()))
+            .collect();
+        self.title = title.into();
+        self.files = files;
+        self.selected_ix = (self.reference_count() > 0).then_some(0);
+        cx.notify();
+    }
+
+    fn reference_count(&self) -> usize {
+        self.files.iter().map(|file| file.ranges.len()).sum()
+    }
+
+    fn reference(&self, mut ix: usize) -> Option<(&ReferencedFile, &Range<Anchor>)> {
+        for file in &self.files {
+            if let Some(range) = file.ranges.get(ix) {
+                return Some((file, range));
+            }
+            ix -= file.ranges.len();
+        }
+        None
+    }
+
+    fn row_count(&self) -> usize {
+        self.files
+            .iter()
+            .map(|file| 1 + file.ranges.len() * ROWS_PER_REFERENCE)
+            .sum()
+    }
+
+    fn list_row(&self, mut row: usize) -> Option<ListRow> {
+        let mut reference_ix = 0;
+        for (file_ix, file) in self.files.iter().enumerate() {
+            if row == 0 {
+                return Some(ListRow::File { file_ix });
+            }
+            row -= 1;
+            let range_ix = row / ROWS_PER_REFERENCE;
+            if range_ix < file.ranges.len() {
+                return Some(ListRow::Line {
+                    file_ix,
+                    range_ix,
+                    reference_ix: reference_ix + range_ix,
+                    line_ix: row % ROWS_PER_REFERENCE,
+                });
+            }
+            row -= file.ranges.len() * ROWS_PER_REFERENCE;
+            reference_ix += file.ranges.len();
+        }
+        None
+    }
+
+    /// The index of the row showing the given reference's own line, counting the files'
+    /// header rows and the lines around each reference.
+    fn row_for_reference(&self, mut ix: usize) -> usize {
+        let mut row = 0;
+        for file in &self.files {
+            row += 1;
+            if ix < file.ranges.len() {
+                return row + ix * ROWS_PER_REFERENCE + CONTEXT_LINES as usize;
+            }
+            ix -= file.ranges.len();
+            row += file.ranges.len() * ROWS_PER_REFERENCE;
+        }
+        row
+    }
+
+    fn select_reference(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.selected_ix = Some(ix);
+        self.scroll_handle
+            .scroll_to_item(self.row_for_reference(ix));
+        cx.notify();
+    }
+
+    fn select_next(&mut self, _: &menu::SelectNext, cx: &mut ViewContext<Self>) {
+        let count = self.reference_count();
+        if count > 0 {
+            let ix = self.selected_ix.map_or(0, |ix| (ix + 1) % count);
+            self.select_reference(ix, cx);
+        }
+    }
+
+    fn select_prev(&mut self, _: &menu::SelectPrev, cx: &mut ViewContext<Self>) {
+        let count = self.reference_count();
+        if count > 0 {
+            let ix = self
+                .selected_ix
+                .map_or(count - 1, |ix| (ix + count - 1) % count);
+            self.select_reference(ix, cx);
+        }
+    }
+
+    fn select_first(&mut self, _: &menu::SelectFirst, cx: &mut ViewContext<Self>) {
+        if self.reference_count() > 0 {
+            self.select_reference(0, cx);
+        }
+    }
+
+    fn select_last(&mut self, _: &menu::SelectLast, cx: &mut ViewContext<Self>) {
+        let count = self.reference_count();
+        if count > 0 {
+            self.select_reference(count - 1, cx);
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        if let Some(ix) = self.selected_ix {
+            self.open_reference(ix, cx);
+        }
+    }
+
+    fn open_reference(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let Some((file, range)) = self.reference(ix) else {
+            return;
+        };
+        let buffer = file.buffer.clone();
+        let range = range.to_offset(buffer.read(cx));
+        self.workspace
+            .update(cx, |workspace, cx| {
+                let editor: View<Editor> = workspace.open_project_item(buffer, cx);
+                editor.update(cx, |editor, cx| {
+                    let range = editor.range_for_match(&range);
+                    editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                        s.select_ranges([range])
+                    });
+                });
+            })
+            .ok();
+    }
+
+    /// Renders the rows in `range`, reading the text of only the lines that are shown.
+    fn render_rows(&self, range: Range<usize>, cx: &mut ViewContext<Self>) -> Vec<AnyElement> {
+        let mut snapshot: Option<(usize, BufferSnapshot)> = None;
+        range
+            .filter_map(|row| {
+                Some(match self.list_row(row)? {
+                    ListRow::File { file_ix } => self.render_file(file_ix).into_any_element(),
+                    ListRow::Line {
+                        file_ix,
+                        range_ix,
+                        reference_ix,
+                        line_ix,
+                    } => {
+                        if snapshot.as_ref().map_or(true, |(ix, _)| *ix != file_ix) {
+                            let buffer = self.files[file_ix].buffer.read(cx);
+                            snapshot = Some((file_ix, buffer.snapshot()));
+                        }
+                        let (_, snapshot) = snapshot.as_ref()?;
+                        let range = &self.files[file_ix].ranges[range_ix];
+                        self.render_reference_line(row, reference_ix, line_ix, range, snapshot, cx)
+                            .into_any_element()
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn render_file(&self, file_ix: usize) -> impl IntoElement {
+        let file = &self.files[file_ix];
+        h_flex()
+            .px_2()
+            .gap_2()
+            .child(Label::new(file.path.clone()))
+            .child(
+                Label::new(file.ranges.len().to_string())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+    }
+
+    fn render_reference_line(
+        &self,
+        row: usize,
+        ix: usize,
+        line_ix: usize,
+        range: &Range<Anchor>,
+        snapshot: &BufferSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let reference_row = range.start.to_point(snapshot).row;
+        let line_row = (reference_row + line_ix as u32)
+            .checked_sub(CONTEXT_LINES)
+            .filter(|line_row| *line_row <= snapshot.max_point().row);
+        let text = line_row.map(|line_row| {
+            snapshot
+                .text_for_range(
+                    Point::new(line_row, 0)..Point::new(line_row, snapshot.line_len(line_row)),
+                )
+                .collect::<String>()
+        });
+        let selected = self.selected_ix == Some(ix);
+        let colors = cx.theme().colors();
+        let (selected_bg, hover_bg) = (colors.element_selected, colors.element_hover);
+
+        h_flex()
+            .id(("reference", row))
+            .px_2()
+            .when(selected, |this| this.bg(selected_bg))
+            .hover(|style| style.bg(hover_bg))
+            .on_click(cx.listener(move |this, _, cx| {
+                this.select_reference(ix, cx);
+                this.open_reference(ix, cx);
+            }))
+            .child(
+                Label::new(match (line_row, text) {
+                    (Some(line_row), Some(text)) => {
+                        format!("{:>5}  {}", line_row + 1, text.trim_end())
+                    }
+                    _ => String::new(),
+                })
+                .size(LabelSize::Small)
+                .color(if line_ix == CONTEXT_LINES as usize {
+                    Color::Default
+                } else {
+                    Color::Muted
+                }),
+            )
+    }
+}
+
+impl EventEmitter<PanelEvent> for ReferencesPanel {}
+
+impl FocusableView for ReferencesPanel {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Panel for ReferencesPanel {
+    fn persistent_name() -> &'static str {
+        "ReferencesPanel"
+    }
+
+    fn position(&self, _: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, _: DockPosition) -> bool {
+        true
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _: &WindowContext) -> Pixels {
+        match self.position {
+            DockPosition::Left | DockPosition::Right => self.width.unwrap_or(px(320.)),
+            DockPosition::Bottom => self.height.unwrap_or(px(240.)),
+        }
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        match self.position {
+            DockPosition::Left | DockPosition::Right => self.width = size,
+            DockPosition::Bottom => self.height = size,
+        }
+        cx.notify();
+    }
+
+    fn icon(&self, _: &WindowContext) -> Option<IconName> {
+        Some(IconName::MagnifyingGlass)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("References")
+    }
+
+    fn icon_label(&self, _: &WindowContext) -> Option<String> {
+        let count = self.reference_count();
+        (count > 0).then(|| count.to_string())
+    }
+
+    fn toggle_action(&self) -> Box<dyn gpui::Action> {
+        Box::new(ToggleFocus)
+    }
+}
+
+impl Render for ReferencesPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("ReferencesPanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::select_first))
+            .on_action(cx.listener(Self::select_last))
+            .on_action(cx.listener(Self::confirm))
+            .size_full()
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .child(Label::new(self.title.clone()).color(Color::Muted)),
+            )
+            .child(
+                uniform_list(
+                    cx.view().clone(),
+                    "references",
+                    self.row_count(),
+                    |this, range, cx| this.render_rows(range, cx),
+                )
+                .flex_1()
+                .track_scroll(self.scroll_handle.clone()),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor_tests::init_test;
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::{FakeFs, Project};
+    use serde_json::json;
+
+    #[gpui::test]
+    async fn test_references_panel(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                "a.rs": "fn one() {}\n",
+                "b.rs": "one();\none();\n",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        let buffer_a = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/a.rs", cx)
+            })
+            .await
+            .unwrap();
+        let buffer_b = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/b.rs", cx)
+            })
+            .await
+            .unwrap();
+        let location = |buffer: &Model<Buffer>, start: Point, cx: &mut VisualTestContext| {
+            let range = buffer.read_with(cx, |buffer, _| {
+                buffer.anchor_before(start)..buffer.anchor_after(start + Point::new(0, 3))
+            });
+            Location {
+                buffer: buffer.clone(),
+                range,
+            }
+        };
+        let locations = vec![
+            location(&buffer_b, Point::new(1, 0), cx),
+            location(&buffer_a, Point::new(0, 3), cx),
+            location(&buffer_b, Point::new(0, 0), cx),
+        ];
+
+        let panel = workspace.update(cx, |workspace, cx| {
+            let panel = cx.new_view(|cx| ReferencesPanel::new(workspace.weak_handle(), cx));
+            panel.update(cx, |panel, cx| {
+                panel.set_locations("References to `one`".into(), locations, cx)
+            });
+            panel
+        });
+
+        let reference_rows = |panel: &View<ReferencesPanel>, cx: &mut VisualTestContext| {
+            panel.update(cx, |panel, cx| {
+                panel
+                    .files
+                    .iter()
+                    .map(|file| {
+                        let snapshot = file.buffer.read(cx).snapshot();
+                        let rows = file
+                            .ranges
+                            .iter()
+                            .map(|range| range.start.to_point(&snapshot).row)
+                            .collect::<Vec<_>>();
+                        (file.path.to_string(), rows)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(
+            reference_rows(&panel, cx),
+            [
+                ("a.rs".to_string(), vec![0]),
+                ("b.rs".to_string(), vec![0, 1])
+            ]
+        );
+
+        // Editing a buffer moves its references along with their text
+        buffer_b.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+        assert_eq!(
+            reference_rows(&panel, cx),
+            [
+                ("a.rs".to_string(), vec![0]),
+                ("b.rs".to_string(), vec![1, 2])
+            ]
+        );
+
+        // Each reference takes up the same number of rows, so only the visible ones are rendered
+        panel.update(cx, |panel, _| {
+            assert_eq!(panel.row_count(), 11);
+            assert_eq!(panel.list_row(0), Some(ListRow::File { file_ix: 0 }));
+            assert_eq!(panel.list_row(4), Some(ListRow::File { file_ix: 1 }));
+            assert_eq!(
+                panel.list_row(panel.row_for_reference(2)),
+                Some(ListRow::Line {
+                    file_ix: 1,
+                    range_ix: 1,
+                    reference_ix: 2,
+                    line_ix: CONTEXT_LINES as usize,
+                })
+            );
+            assert_eq!(panel.list_row(11), None);
+        });
+
+        panel.update(cx, |panel, cx| {
+            assert_eq!(panel.selected_ix, Some(0));
+            panel.select_prev(&menu::SelectPrev, cx);
+            assert_eq!(panel.selected_ix, Some(2));
+            panel.select_next(&menu::SelectNext, cx);
+            assert_eq!(panel.selected_ix, Some(0));
+            panel.select_next(&menu::SelectNext, cx);
+            panel.select_next(&menu::SelectNext, cx);
+            panel.confirm(&menu::Confirm, cx);
+        });
+
+        let editor = workspace.update(cx, |workspace, cx| {
+            workspace
+                .active_item(cx)
+                .and_then(|item| item.downcast::<Editor>())
+                .unwrap()
+        });
+        editor.update(cx, |editor, cx| {
+            assert_eq!(editor.text(cx), "\none();\none();\n");
+            assert_eq!(
+                editor.selections.ranges::<Point>(cx),
+                [Point::new(2, 0)..Point::new(2, 3)]
+            );
+        });
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{Cancel, ConfirmRename, Editor};
+use gpui::{AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Model, WeakView};
+use language::{Anchor, Buffer, Point, ToPoint};
+use project::ProjectTransaction;
+use std::ops::Range;
+use ui::{prelude::*, Checkbox, Selection};
+use workspace::{ModalView, Workspace};
+
+/// A rename's edits, grouped by the buffer they apply to.
+pub type RenameEdits = Vec<(Model<Buffer>, Vec<(Range<Anchor>, String)>)>;
+
+/// Lists every occurrence that a rename touches across files before it's applied, so that
+/// some of them can be left out.
+pub struct RenamePreview {
+    title: String,
+    files: Vec<RenamedFile>,
+    editor: WeakView<Editor>,
+    workspace: WeakView<Workspace>,
+    focus_handle: FocusHandle,
+}
+
+struct RenamedFile {
+    buffer: Model<Buffer>,
+    path: SharedString,
+    occurrences: Vec<RenamedOccurrence>,
+}
+
+struct RenamedOccurrence {
+    range: Range<Anchor>,
+    new_text: String,
+    /// The line holding the occurrence, prefixed with its number.
+    line: SharedString,
+    included: bool,
+}
+
+impl RenamePreview {
+    pub fn new(
+        title: String,
+        edits: RenameEdits,
+        editor: WeakView<Editor>,
+        workspace: WeakView<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let files = edits
+            .into_iter()
+            .map(|(buffer, edits)| {
+                let snapshot = buffer.read(cx).snapshot();
+                let path = snapshot
+                    .file()
+                    .map(|file| file.path().to_string_lossy().to_string())
+                    .unwrap_or_else(|| "untitled".to_string());
+                let occurrences = edits
+                    .into_iter()
+                    .map(|(range, new_text)| {
+                        let row = range.start.to_point(&snapshot).row;
+                        let line = snapshot
+                            .text_for_range(
+                                Point::new(row, 0)..Point::new(row, snapshot.line_len(row)),
+                            )
+                            .collect::<String>();
+                        RenamedOccurrence {
+                            range,
+                            new_text,
+                            line: format!("{}: {}", row + 1, line.trim()).into(),
+                            included: true,
+                        }
+                    })
+                    .collect();
+                RenamedFile {
+                    buffer,
+                    path: path.into(),
+                    occurrences,
+                }
+            })
+            .collect();
+
+        Self {
+            title,
+            files,
+            editor,
+            workspace,
+            focus_handle: cx.focus_handle(),
+        }
+    }
+
+    fn included_count(&self) -> usize {
+        self.files
+            .iter()
+            .flat_map(|file| &file.occurrences)
+            .filter(|occurrence| occurrence.included)
+            .count()
+    }
+
+    /// Includes all of a file's occurrences, or leaves them all out if they're all included.
+    fn toggle_file(&mut self, file_ix: usize, cx: &mut ViewContext<Self>) {
+        let file = &mut self.files[file_ix];
+        let included = !file
+            .occurrences
+            .iter()
+            .all(|occurrence| occurrence.included);
+        for occurrence in &mut file.occurrences {
+            occurrence.included = included;
+        }
+        cx.notify();
+    }
+
+    fn toggle_occurrence(&mut self, file_ix: usize, ix: usize, cx: &mut ViewContext<Self>) {
+        let occurrence = &mut self.files[file_ix].occurrences[ix];
+        occurrence.included = !occurrence.included;
+        cx.notify();
+    }
+
+    fn confirm(&mut self, _: &ConfirmRename, cx: &mut ViewContext<Self>) {
+        let edits = self
+            .files
+            .iter()
+            .map(|file| {
+                let edits = file
+                    .occurrences
+                    .iter()
+                    .filter(|occurrence| occurrence.included)
+                    .map(|occurrence| (occurrence.range.clone(), occurrence.new_text.clone()))
+                    .collect();
+                (file.buffer.clone(), edits)
+            })
+            .collect();
+        let transaction = apply_rename_edits(edits, cx);
+
+        let editor = self.editor.clone();
+        let workspace = self.workspace.clone();
+        let title = self.title.clone();
+        cx.spawn(|_, mut cx| async move {
+            Editor::open_project_transaction(&editor, workspace, transaction, title, cx.clone())
+                .await?;
+            editor.update(&mut cx, |editor, cx| editor.refresh_document_highlights(cx))
+        })
+        .detach_and_log_err(cx);
+        cx.emit(DismissEvent);
+    }
+
+    fn cancel(&mut self, _: &Cancel, cx: &mut ViewContext<Self>) {
+        cx.emit(DismissEvent);
+    }
+}
+
+/// Applies a rename's edits as one transaction per buffer, so that undoing the rename in one
+/// file leaves the others renamed.
+pub fn apply_rename_edits(edits: RenameEdits, cx: &mut AppContext) -> ProjectTransaction {
+    let mut project_transaction = ProjectTransaction::default();
+    for (buffer, edits) in edits {
+        if edits.is_empty() {
+            continue;
+        }
+        let transaction = buffer.update(cx, |buffer, cx| {
+            buffer.finalize_last_transaction();
+            buffer.start_transaction();
+            buffer.edit(edits, None, cx);
+            buffer.end_transaction(cx)?;
+            buffer.finalize_last_transaction().cloned()
+        });
+        if let Some(transaction) = transaction {
+            project_transaction.0.insert(buffer, transaction);
+        }
+    }
+    project_transaction
+}
+
+impl ModalView for RenamePreview {}
+
+impl EventEmitter<DismissEvent> for RenamePreview {}
+
+impl FocusableView for RenamePreview {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for RenamePreview {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let included_count = self.included_count();
+        v_flex()
+            .key_context("RenamePreview")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .elevation_2(cx)
+            .w(rems(34.))
+            .child(h_flex().px_2().py_1().child(Label::new(self.title.clone())))
+            .child(
+                v_flex()
+                    .id("rename-preview-files")
+                    .max_h(rems(24.))
+                    .overflow_y_scroll()
+                    .px_2()
+                    .children(self.files.iter().enumerate().map(|(file_ix, file)| {
+                        let selection = if file.occurrences.iter().all(|o| o.included) {
+                            Selection::Selected
+                        } else if file.occurrences.iter().any(|o| o.included) {
+                            Selection::Indeterminate
+                        } else {
+                            Selection::Unselected
+                        };
+                        v_flex()
+                            .child(
+                                h_flex()
+                                    .gap_1()
+                                    .child(
+                                        Checkbox::new(("rename-preview-file", file_ix), selection)
+                                            .on_click(cx.listener(move |this, _, cx| {
+                                                this.toggle_file(file_ix, cx)
+                                            })),
+                                    )
+                                    .child(Label::new(file.path.clone())),
+                            )
+                            .children(file.occurrences.iter().enumerate().map(
+                                |(ix, occurrence)| {
+                                    let selection = if occurrence.included {
+                                        Selection::Selected
+                                    } else {
+                                        Selection::Unselected
+                                    };
+                                    let id = ElementId::NamedInteger(
+                                        format!("rename-preview-occurrence-{file_ix}").into(),
+                                        ix,
+                                    );
+                                    h_flex()
+                                        .pl_4()
+                                        .gap_1()
+                                        .child(Checkbox::new(id, selection).on_click(cx.listener(
+                                            move |this, _, cx| {
+                                                this.toggle_occurrence(file_ix, ix, cx)
+                                            },
+                                        )))
+                                        .child(
+                                            Label::new(occurrence.line.clone())
+                                                .size(LabelSize::Small)
+                                                .color(Color::Muted),
+                                        )
+                                },
+                            ))
+                    })),
+            )
+            .child(
+                h_flex()
+                    .justify_end()
+                    .gap_1()
+                    .px_2()
+                    .py_1()
+                    .child(
+                        Button::new("rename-preview-cancel", "Cancel")
+                            .on_click(cx.listener(|this, _, cx| this.cancel(&Cancel, cx))),
+                    )
+                    .child(
+                        Button::new("rename-preview-apply", format!("Rename {included_count}"))
+                            .disabled(included_count == 0)
+                            .on_click(cx.listener(|this, _, cx| this.confirm(&ConfirmRename, cx))),
+                    ),
+            )
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Editor, EditorMode, RunTestAtCursor};
+use gpui::{Action, AppContext, ViewContext};
+use workspace::SpawnInTerminal;
+
+/// Runs the test under the newest cursor in a new terminal.
+pub fn run_test_at_cursor(editor: &mut Editor, _: &RunTestAtCursor, cx: &mut ViewContext<Editor>) {
+    if let Some(spawn) = test_at_cursor(editor, cx) {
+        cx.dispatch_action(spawn.boxed_clone());
+    }
+}
+
+/// Whether the newest cursor is within a test that the language knows how to run.
+pub(crate) fn has_test_at_cursor(editor: &Editor, cx: &AppContext) -> bool {
+    test_at_cursor(editor, cx).is_some()
+}
+
+/// Returns the language's command for running the test under the newest cursor, run
+/// from the directory of the test's file.
+fn test_at_cursor(editor: &Editor, cx: &AppContext) -> Option<SpawnInTerminal> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    if !editor.project.as_ref()?.read(cx).is_local() {
+        return None;
+    }
+    let head = editor.selections.newest_anchor().head();
+    let (buffer, position) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+    let buffer = buffer.read(cx);
+    let path = buffer.file()?.as_local()?.abs_path(cx);
+    let directory = path.parent()?.to_path_buf();
+
+    let snapshot = buffer.snapshot();
+    let runnable = snapshot.runnable_containing(position)?;
+    let language = snapshot.language_at(runnable.range.start)?;
+    let command = language
+        .run_test_command()?
+        .replace("$ZED_TEST_NAME", &shell_quote(&runnable.name))
+        .replace("$ZED_FILE", &shell_quote(&path.to_string_lossy()))
+        .replace("$ZED_DIRNAME", &shell_quote(&directory.to_string_lossy()));
+    Some(SpawnInTerminal {
+        command,
+        working_directory: Some(directory),
+    })
+}
+
+/// Quotes the text if the shell would otherwise split it or expand parts of it.
+fn shell_quote(text: &str) -> String {
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '='))
+    {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
@@ -4,6 +4,7 @@ pub(crate) mod scroll_amount;
 
 use crate::{
     display_map::{DisplaySnapshot, ToDisplayPoint},
+    file_state::queue_file_state_save,
     hover_popover::hide_hover,
     persistence::DB,
     Anchor, DisplayPoint, Editor, EditorEvent, EditorMode, InlayHintRefreshReason,
@@ -134,6 +135,7 @@ pub struct ScrollManager {
     show_scrollbars: bool,
     hide_scrollbar_task: Option<Task<()>>,
     dragging_scrollbar: bool,
+    dragging_minimap: bool,
     visible_line_count: Option<f32>,
 }
 
@@ -147,6 +149,7 @@ impl ScrollManager {
             show_scrollbars: true,
             hide_scrollbar_task: None,
             dragging_scrollbar: false,
+            dragging_minimap: false,
             last_autoscroll: None,
             visible_line_count: None,
         }
@@ -288,6 +291,17 @@ impl ScrollManager {
         }
     }
 
+    pub fn is_dragging_minimap(&self) -> bool {
+        self.dragging_minimap
+    }
+
+    pub fn set_is_dragging_minimap(&mut self, dragging: bool, cx: &mut ViewContext<Editor>) {
+        if dragging != self.dragging_minimap {
+            self.dragging_minimap = dragging;
+            cx.notify();
+        }
+    }
+
     pub fn clamp_scroll_left(&mut self, max: f32) -> bool {
         if max < self.anchor.offset.x {
             self.anchor.offset.x = max;
@@ -375,6 +389,7 @@ impl Editor {
             cx,
         );
 
+        queue_file_state_save(self, cx);
         self.refresh_inlay_hints(InlayHintRefreshReason::NewLinesShown, cx);
     }
 
@@ -392,6 +407,7 @@ impl Editor {
             .row;
         self.scroll_manager
             .set_anchor(scroll_anchor, top_row, true, false, workspace_id, cx);
+        queue_file_state_save(self, cx);
     }
 
     pub(crate) fn set_scroll_anchor_remote(
@@ -0,0 +1,166 @@
+use crate::{scroll::Autoscroll, Editor};
+use collections::HashMap;
+use gpui::ViewContext;
+use language::{Selection, SelectionGoal};
+use multi_buffer::ToOffset as _;
+use std::{mem, ops::Range};
+use util::ResultExt;
+
+/// Selects the ranges that `Editor::select_larger_syntax_node` expanded the selections to,
+/// keyed by selection id. Selections that no syntax node contains, such as in languages
+/// without a grammar, first expand to the next range that the language server suggests.
+pub(crate) fn select_larger_ranges(
+    editor: &mut Editor,
+    old_selections: Vec<Selection<usize>>,
+    mut new_ranges: HashMap<usize, Range<usize>>,
+    unexpanded: Vec<Selection<usize>>,
+    cx: &mut ViewContext<Editor>,
+) {
+    editor.selection_ranges_task = None;
+    let Some(project) = editor.project.clone().filter(|_| !unexpanded.is_empty()) else {
+        select_expanded_ranges(editor, &old_selections, new_ranges, cx);
+        return;
+    };
+
+    let buffer = editor.buffer.read(cx).snapshot(cx);
+    let requests = unexpanded
+        .into_iter()
+        .filter_map(|selection| {
+            let head = buffer.anchor_before(selection.head());
+            let (buffer, position) = editor.buffer.read(cx).text_anchor_for_position(head, cx)?;
+            let ranges = project.update(cx, |project, cx| {
+                project.selection_ranges(&buffer, position, cx)
+            });
+            Some((selection.id, selection.range(), head.excerpt_id, ranges))
+        })
+        .collect::<Vec<_>>();
+    editor.selection_ranges_task = Some(cx.spawn(|editor, mut cx| async move {
+        let mut responses = Vec::new();
+        for (id, old_range, excerpt_id, ranges) in requests {
+            if let Some(ranges) = ranges.await.log_err() {
+                responses.push((id, old_range, excerpt_id, ranges));
+            }
+        }
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                // Leave the selections alone if they changed in the meantime
+                if editor.selections.all::<usize>(cx) != old_selections {
+                    return;
+                }
+                let snapshot = editor.buffer.read(cx).snapshot(cx);
+                for (id, old_range, excerpt_id, ranges) in responses {
+                    let larger_range = ranges
+                        .into_iter()
+                        .map(|range| {
+                            snapshot
+                                .anchor_in_excerpt(excerpt_id, range.start)
+                                .to_offset(&snapshot)
+                                ..snapshot
+                                    .anchor_in_excerpt(excerpt_id, range.end)
+                                    .to_offset(&snapshot)
+                        })
+                        .find(|range| {
+                            range.start <= old_range.start
+                                && range.end >= old_range.end
+                                && range.len() > old_range.len()
+                        });
+                    if let Some(larger_range) = larger_range {
+                        new_ranges.insert(id, larger_range);
+                    }
+                }
+                select_expanded_ranges(editor, &old_selections, new_ranges, cx);
+            })
+            .ok();
+    }));
+}
+
+fn select_expanded_ranges(
+    editor: &mut Editor,
+    old_selections: &[Selection<usize>],
+    new_ranges: HashMap<usize, Range<usize>>,
+    cx: &mut ViewContext<Editor>,
+) {
+    if new_ranges.is_empty() {
+        return;
+    }
+
+    let new_selections = old_selections
+        .iter()
+        .map(|selection| match new_ranges.get(&selection.id) {
+            Some(range) => Selection {
+                id: selection.id,
+                start: range.start,
+                end: range.end,
+                goal: SelectionGoal::None,
+                reversed: selection.reversed,
+            },
+            None => selection.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    // Ranges from the language server are remembered like syntax nodes, so that
+    // `select_smaller_syntax_node` retraces them too
+    let mut stack = mem::take(&mut editor.select_larger_syntax_node_stack);
+    stack.push(old_selections.into());
+    editor.change_selections(Some(Autoscroll::fit()), cx, |s| {
+        s.select(new_selections);
+    });
+    editor.select_larger_syntax_node_stack = stack;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        editor_tests::init_test, test::editor_lsp_test_context::EditorLspTestContext,
+        SelectLargerSyntaxNode, SelectSmallerSyntaxNode,
+    };
+    use futures::StreamExt as _;
+    use language::{Language, LanguageConfig};
+
+    #[gpui::test]
+    async fn test_select_larger_syntax_node_with_language_server(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        // Without a grammar, only the language server can expand selections
+        let language = Language::new(
+            LanguageConfig {
+                name: "Plain".into(),
+                path_suffixes: vec!["txt".to_string()],
+                ..Default::default()
+            },
+            None,
+        );
+        let mut cx = EditorLspTestContext::new(
+            language,
+            lsp::ServerCapabilities {
+                selection_range_provider: Some(lsp::SelectionRangeProviderCapability::Simple(true)),
+                ..Default::default()
+            },
+            cx,
+        )
+        .await;
+
+        cx.set_state("ˇone two\n");
+        let mut requests =
+            cx.handle_request::<lsp::request::SelectionRangeRequest, _, _>(|_, _, _| async move {
+                Ok(Some(vec![lsp::SelectionRange {
+                    range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 3)),
+                    parent: Some(Box::new(lsp::SelectionRange {
+                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(1, 0)),
+                        parent: None,
+                    })),
+                }]))
+            });
+        cx.update_editor(|editor, cx| {
+            editor.select_larger_syntax_node(&SelectLargerSyntaxNode, cx)
+        });
+        requests.next().await;
+        cx.run_until_parked();
+        cx.assert_editor_state("«oneˇ» two\n");
+
+        cx.update_editor(|editor, cx| {
+            editor.select_smaller_syntax_node(&SelectSmallerSyntaxNode, cx)
+        });
+        cx.assert_editor_state("ˇone two\n");
+    }
+}
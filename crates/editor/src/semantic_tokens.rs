@@ -0,0 +1,183 @@
+use crate::{Editor, EditorMode};
+use gpui::{FontStyle, HighlightStyle, Task, UnderlineStyle, ViewContext};
+use language::{language_settings::language_settings, OffsetRangeExt, ToOffset};
+use project::SemanticToken;
+use std::time::Duration;
+use theme::{ActiveTheme, SyntaxTheme};
+use util::ResultExt;
+
+/// How long to wait after an edit before requesting new semantic tokens.
+const SEMANTIC_TOKENS_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How much deprecated code is faded out, unless the theme styles it.
+const DEPRECATED_FADE: f32 = 0.3;
+
+#[derive(Default)]
+pub(crate) struct SemanticTokensState {
+    refresh_task: Option<Task<()>>,
+}
+
+/// Requests the buffer's semantic tokens, restyling the buffer once they arrive.
+pub(crate) fn refresh_semantic_tokens(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if editor.mode != EditorMode::Full {
+        return None;
+    }
+    let project = editor.project.clone()?;
+    let buffer = editor.buffer.read(cx).as_singleton()?;
+    let enabled = {
+        let buffer = buffer.read(cx);
+        language_settings(buffer.language(), buffer.file(), cx).semantic_tokens
+    };
+    if !enabled {
+        editor.semantic_tokens.refresh_task = None;
+        if editor
+            .display_map
+            .update(cx, |map, _| map.clear_semantic_highlights())
+        {
+            cx.notify();
+        }
+        return None;
+    }
+
+    editor.semantic_tokens.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        if debounce {
+            cx.background_executor()
+                .timer(SEMANTIC_TOKENS_DEBOUNCE)
+                .await;
+        }
+
+        let Some(tokens) = project
+            .update(&mut cx, |project, cx| {
+                let range = {
+                    let buffer = buffer.read(cx);
+                    buffer.anchor_before(0)..buffer.anchor_after(buffer.len())
+                };
+                project.semantic_tokens(&buffer, range, cx)
+            })
+            .ok()
+        else {
+            return;
+        };
+        let Some(tokens) = tokens.await.log_err() else {
+            return;
+        };
+
+        editor
+            .update(&mut cx, |editor, cx| {
+                show_semantic_tokens(editor, tokens, cx)
+            })
+            .log_err();
+    }));
+    None
+}
+
+fn show_semantic_tokens(
+    editor: &mut Editor,
+    mut tokens: Vec<SemanticToken>,
+    cx: &mut ViewContext<Editor>,
+) {
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let Some((&excerpt_id, _, buffer_snapshot)) = snapshot.as_singleton() else {
+        return;
+    };
+    tokens.sort_by_key(|token| token.range.start.to_offset(buffer_snapshot));
+
+    let theme = cx.theme().syntax().clone();
+    let mut highlights = Vec::with_capacity(tokens.len());
+    let mut last_end = 0;
+    for token in tokens {
+        // Overlapping tokens can't be told apart when highlighting, so keep the first one
+        let range = token.range.to_offset(buffer_snapshot);
+        if range.is_empty() || range.start < last_end {
+            continue;
+        }
+        let Some(style) = semantic_token_style(&token, &theme) else {
+            continue;
+        };
+        last_end = range.end;
+        highlights.push((
+            snapshot.anchor_in_excerpt(excerpt_id, token.range.start)
+                ..snapshot.anchor_in_excerpt(excerpt_id, token.range.end),
+            style,
+        ));
+    }
+
+    editor
+        .display_map
+        .update(cx, |map, _| map.set_semantic_highlights(highlights));
+    cx.notify();
+}
+
+/// Styles a token with the theme's `semantic.<type>` style, or with the syntax style
+/// closest to its type, and then with the style of each of its modifiers.
+fn semantic_token_style(token: &SemanticToken, theme: &SyntaxTheme) -> Option<HighlightStyle> {
+    let mut style = theme_style(theme, &format!("semantic.{}", token.token_type))
+        .or_else(|| theme_style(theme, syntax_capture_for_token_type(&token.token_type)?));
+    for modifier in &token.modifiers {
+        let modifier_style = theme_style(
+            theme,
+            &format!("semantic.{}.{}", token.token_type, modifier),
+        )
+        .or_else(|| theme_style(theme, &format!("semantic.{modifier}")))
+        .or_else(|| default_modifier_style(modifier));
+        if let Some(modifier_style) = modifier_style {
+            style
+                .get_or_insert_with(HighlightStyle::default)
+                .highlight(modifier_style);
+        }
+    }
+    style
+}
+
+fn theme_style(theme: &SyntaxTheme, name: &str) -> Option<HighlightStyle> {
+    theme
+        .highlights
+        .iter()
+        .find_map(|(key, style)| (key == name).then_some(*style))
+}
+
+/// The capture of the syntax highlighting queries that highlights the same kind of
+/// code as the token type.
+fn syntax_capture_for_token_type(token_type: &str) -> Option<&'static str> {
+    Some(match token_type {
+        "type" | "class" | "enum" | "interface" | "struct" | "typeParameter" => "type",
+        "parameter" | "variable" => "variable",
+        "function" | "method" | "macro" => "function",
+        "property" => "property",
+        "enumMember" => "variant",
+        "decorator" => "attribute",
+        "keyword" | "modifier" => "keyword",
+        "comment" => "comment",
+        "string" | "regexp" => "string",
+        "number" => "number",
+        "operator" => "operator",
+        _ => return None,
+    })
+}
+
+/// Styles the modifiers that are worth telling apart in any theme.
+fn default_modifier_style(modifier: &str) -> Option<HighlightStyle> {
+    match modifier {
+        "deprecated" => Some(HighlightStyle {
+            fade_out: Some(DEPRECATED_FADE),
+            ..Default::default()
+        }),
+        "mutable" => Some(HighlightStyle {
+            underline: Some(UnderlineStyle {
+                color: None,
+                thickness: 1.0.into(),
+                wavy: false,
+            }),
+            ..Default::default()
+        }),
+        "async" => Some(HighlightStyle {
+            font_style: Some(FontStyle::Italic),
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
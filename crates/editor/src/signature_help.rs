@@ -0,0 +1,256 @@
+use crate::{
+    DisplayPoint, Editor, EditorMode, EditorStyle, ShowSignatureHelp, SignatureHelpNext,
+    SignatureHelpPrevious,
+};
+use gpui::{
+    div, AnyElement, FontWeight, HighlightStyle, InteractiveElement, IntoElement, ParentElement,
+    Pixels, Size, StatefulInteractiveElement, Styled, StyledText, Task, ViewContext,
+};
+use project::{SignatureHelp, SignatureInformation};
+use ui::prelude::*;
+use util::ResultExt;
+
+/// The signature of the call whose argument list the cursor is in.
+#[derive(Default)]
+pub(crate) struct SignatureHelpState {
+    popover: Option<SignatureHelpPopover>,
+    pending_request: Option<Task<()>>,
+}
+
+struct SignatureHelpPopover {
+    help: SignatureHelp,
+    active_signature: usize,
+    /// Whether the user picked the active signature, which is then kept as
+    /// the signature help updates.
+    signature_chosen: bool,
+}
+
+impl SignatureHelpState {
+    pub fn visible(&self) -> bool {
+        self.popover.is_some()
+    }
+
+    pub fn active_signature(&self) -> Option<&SignatureInformation> {
+        let popover = self.popover.as_ref()?;
+        popover.help.signatures.get(popover.active_signature)
+    }
+
+    /// Renders the active signature, with its active parameter highlighted, to be
+    /// shown above the newest cursor.
+    pub fn render(
+        &self,
+        cursor: DisplayPoint,
+        style: &EditorStyle,
+        max_size: Size<Pixels>,
+        cx: &mut ViewContext<Editor>,
+    ) -> Option<(DisplayPoint, AnyElement)> {
+        let popover = self.popover.as_ref()?;
+        let signature = self.active_signature()?;
+        let parameter_highlight = signature
+            .active_parameter
+            .and_then(|ix| signature.parameters.get(ix))
+            .map(|range| {
+                (
+                    range.clone(),
+                    HighlightStyle {
+                        color: Some(cx.theme().colors().text_accent),
+                        font_weight: Some(FontWeight::BOLD),
+                        ..Default::default()
+                    },
+                )
+            });
+        let signature_count = popover.help.signatures.len();
+        let documentation = signature
+            .documentation
+            .clone()
+            .filter(|documentation| !documentation.trim().is_empty());
+
+        let element = v_flex()
+            .id("signature_help")
+            .elevation_2(cx)
+            .p_2()
+            .gap_1()
+            .overflow_y_scroll()
+            .max_w(max_size.width)
+            .max_h(max_size.height)
+            // Prevent a mouse move on the popover from being propagated to the editor,
+            // because that would dismiss the hover popover.
+            .on_mouse_move(|_, cx| cx.stop_propagation())
+            .child(
+                h_flex()
+                    .gap_2()
+                    .when(signature_count > 1, |this| {
+                        this.child(
+                            Label::new(format!(
+                                "{}/{}",
+                                popover.active_signature + 1,
+                                signature_count
+                            ))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                        )
+                    })
+                    .child(
+                        div().child(
+                            StyledText::new(signature.label.clone())
+                                .with_highlights(&style.text, parameter_highlight),
+                        ),
+                    ),
+            )
+            .when_some(documentation, |this, documentation| {
+                this.child(
+                    Label::new(documentation)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+            })
+            .into_any_element();
+        Some((cursor, element))
+    }
+}
+
+pub fn show_signature_help(
+    editor: &mut Editor,
+    _: &ShowSignatureHelp,
+    cx: &mut ViewContext<Editor>,
+) {
+    let is_retrigger = editor.signature_help.visible();
+    request_signature_help(editor, None, is_retrigger, cx);
+}
+
+pub fn signature_help_next(
+    editor: &mut Editor,
+    _: &SignatureHelpNext,
+    cx: &mut ViewContext<Editor>,
+) {
+    cycle_signatures(editor, true, cx);
+}
+
+pub fn signature_help_previous(
+    editor: &mut Editor,
+    _: &SignatureHelpPrevious,
+    cx: &mut ViewContext<Editor>,
+) {
+    cycle_signatures(editor, false, cx);
+}
+
+fn cycle_signatures(editor: &mut Editor, forward: bool, cx: &mut ViewContext<Editor>) {
+    let Some(popover) = editor.signature_help.popover.as_mut() else {
+        cx.propagate();
+        return;
+    };
+    let count = popover.help.signatures.len();
+    popover.active_signature = if forward {
+        (popover.active_signature + 1) % count
+    } else {
+        (popover.active_signature + count - 1) % count
+    };
+    popover.signature_chosen = true;
+    cx.notify();
+}
+
+/// Shows signature help when one of the language server's trigger characters is
+/// typed, and updates the signature help already shown on any other input.
+pub(crate) fn trigger_signature_help_on_input(
+    editor: &mut Editor,
+    text: &str,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(project) = editor.project.clone() else {
+        return;
+    };
+    let head = editor.selections.newest_anchor().head();
+    let Some((buffer, _)) = editor.buffer.read(cx).text_anchor_for_position(head, cx) else {
+        return;
+    };
+
+    let is_retrigger = editor.signature_help.visible();
+    if project
+        .read(cx)
+        .is_signature_help_trigger(&buffer, text, is_retrigger, cx)
+    {
+        request_signature_help(editor, Some(text.to_string()), is_retrigger, cx);
+    } else if is_retrigger {
+        request_signature_help(editor, None, true, cx);
+    }
+}
+
+/// Updates the signature help shown for the new cursor position. The language
+/// server returns nothing once the cursor leaves the argument list, which hides it.
+pub(crate) fn refresh_signature_help(editor: &mut Editor, cx: &mut ViewContext<Editor>) {
+    if editor.signature_help.visible() {
+        request_signature_help(editor, None, true, cx);
+    }
+}
+
+pub(crate) fn hide_signature_help(editor: &mut Editor, cx: &mut ViewContext<Editor>) -> bool {
+    editor.signature_help.pending_request = None;
+    if editor.signature_help.popover.take().is_some() {
+        cx.notify();
+        true
+    } else {
+        false
+    }
+}
+
+fn request_signature_help(
+    editor: &mut Editor,
+    trigger_character: Option<String>,
+    is_retrigger: bool,
+    cx: &mut ViewContext<Editor>,
+) {
+    if editor.mode != EditorMode::Full {
+        return;
+    }
+    let Some(project) = editor.project.clone() else {
+        return;
+    };
+    let head = editor.selections.newest_anchor().head();
+    let Some((buffer, buffer_head)) = editor.buffer.read(cx).text_anchor_for_position(head, cx)
+    else {
+        return;
+    };
+
+    let help = project.update(cx, |project, cx| {
+        project.signature_help(&buffer, buffer_head, trigger_character, is_retrigger, cx)
+    });
+    editor.signature_help.pending_request = Some(cx.spawn(|editor, mut cx| async move {
+        let help = help.await.log_err().flatten();
+        editor
+            .update(&mut cx, |editor, cx| {
+                update_signature_help(editor, help, cx)
+            })
+            .ok();
+    }));
+}
+
+fn update_signature_help(
+    editor: &mut Editor,
+    help: Option<SignatureHelp>,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(help) = help else {
+        if editor.signature_help.popover.take().is_some() {
+            cx.notify();
+        }
+        return;
+    };
+
+    let chosen_signature = editor
+        .signature_help
+        .popover
+        .as_ref()
+        .filter(|popover| popover.signature_chosen)
+        .and_then(|popover| {
+            let label = &popover.help.signatures[popover.active_signature].label;
+            help.signatures
+                .iter()
+                .position(|signature| &signature.label == label)
+        });
+    editor.signature_help.popover = Some(SignatureHelpPopover {
+        active_signature: chosen_signature.unwrap_or(help.active_signature),
+        signature_chosen: chosen_signature.is_some(),
+        help,
+    });
+    cx.notify();
+}
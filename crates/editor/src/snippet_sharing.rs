@@ -0,0 +1,303 @@
+use crate::{Editor, EditorSettings, ShareSelectionAsSnippet};
+use anyhow::{anyhow, Context as _};
+use gpui::{AppContext, ClipboardItem, PromptLevel, ViewContext};
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use smol::io::AsyncReadExt;
+use std::path::Path;
+use util::http::AsyncBody;
+
+#[derive(Serialize)]
+struct SnippetUpload {
+    content: String,
+    language: Option<String>,
+    file_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SnippetUploadResponse {
+    url: String,
+}
+
+pub(crate) fn snippet_sharing_enabled(cx: &AppContext) -> bool {
+    EditorSettings::get_global(cx)
+        .snippet_sharing_endpoint
+        .is_some()
+}
+
+/// Uploads the newest selection to the configured snippet endpoint, and copies the
+/// returned link to the clipboard.
+pub fn share_selection_as_snippet(
+    editor: &mut Editor,
+    _: &ShareSelectionAsSnippet,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(endpoint) = EditorSettings::get_global(cx)
+        .snippet_sharing_endpoint
+        .clone()
+    else {
+        return;
+    };
+    let Some(project) = editor.project.as_ref() else {
+        return;
+    };
+    let http_client = project.read(cx).client().http_client();
+
+    let selection = editor.selections.newest::<usize>(cx);
+    if selection.is_empty() {
+        return;
+    }
+
+    let buffer = editor.buffer.read(cx).snapshot(cx);
+    let content = buffer.text_for_range(selection.range()).collect::<String>();
+    let language = buffer
+        .language_at(selection.start)
+        .map(|language| language.name().to_string());
+    let path = editor
+        .buffer
+        .read(cx)
+        .as_singleton()
+        .and_then(|buffer| Some(buffer.read(cx).file()?.path().clone()));
+    let file_name = path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|file_name| file_name.to_string_lossy().into_owned());
+    let is_sensitive = path.as_deref().map_or(false, is_sensitive_path);
+
+    let upload = SnippetUpload {
+        content,
+        language,
+        file_name,
+    };
+
+    cx.spawn(|_, mut cx| async move {
+        if is_sensitive {
+            let answer = cx.update(|cx| {
+                cx.prompt(
+                    PromptLevel::Warning,
+                    "This file may contain secrets. Share the selection anyway?",
+                    None,
+                    &["Share", "Cancel"],
+                )
+            })?;
+            if answer.await.ok() != Some(0) {
+                return Ok(());
+            }
+        }
+
+        let request_body = AsyncBody::from(serde_json::to_string(&upload)?);
+        let mut response = http_client.post_json(&endpoint, request_body).await?;
+        let mut body = Vec::new();
+        response
+            .body_mut()
+            .read_to_end(&mut body)
+            .await
+            .context("error reading snippet upload response")?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "snippet upload failed with status {}: {}",
+                response.status(),
+                String::from_utf8_lossy(&body)
+            ));
+        }
+
+        let response: SnippetUploadResponse =
+            serde_json::from_slice(&body).context("error deserializing snippet upload response")?;
+        cx.update(|cx| cx.write_to_clipboard(ClipboardItem::new(response.url)))
+    })
+    .detach_and_log_err(cx);
+}
+
+fn is_sensitive_path(path: &Path) -> bool {
+    let Some(file_name) = path.file_name().and_then(|file_name| file_name.to_str()) else {
+        return false;
+    };
+    let file_name = file_name.to_lowercase();
+
+    file_name == ".env"
+        || file_name.starts_with(".env.")
+        || file_name.contains("secret")
+        || file_name.contains("credential")
+        || [".pem", ".key", ".p12", ".pfx"]
+            .iter()
+            .any(|extension| file_name.ends_with(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_tests::init_test, mouse_context_menu::deploy_context_menu,
+        test::build_editor_with_project, DisplayPoint, MultiBuffer,
+    };
+    use client::{Client, UserStore};
+    use gpui::{Context, TestAppContext, VisualTestContext};
+    use language::LanguageRegistry;
+    use node_runtime::FakeNodeRuntime;
+    use parking_lot::Mutex;
+    use project::{FakeFs, Project};
+    use serde_json::json;
+    use settings::SettingsStore;
+    use std::sync::Arc;
+    use util::http::{FakeHttpClient, Response};
+
+    #[gpui::test]
+    async fn test_share_selection_as_snippet(cx: &mut TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|settings, cx| {
+                settings.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.snippet_sharing_endpoint =
+                        Some("https://snippets.example.com/upload".to_string());
+                });
+            })
+        });
+
+        let uploads = Arc::new(Mutex::new(Vec::new()));
+        let http_client = FakeHttpClient::create({
+            let uploads = uploads.clone();
+            move |mut request| {
+                let uploads = uploads.clone();
+                async move {
+                    let mut body = String::new();
+                    request.body_mut().read_to_string(&mut body).await.unwrap();
+                    uploads.lock().push((request.uri().to_string(), body));
+                    Ok(Response::builder()
+                        .status(200)
+                        .body(r#"{"url":"https://snippets.example.com/s/1"}"#.into())
+                        .unwrap())
+                }
+            }
+        });
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                "main.rs": "fn main() {}\n",
+                ".env": "TOKEN=hunter2\n",
+            }),
+        )
+        .await;
+        let mut languages = LanguageRegistry::test();
+        languages.set_executor(cx.executor());
+        let client = cx.update(|cx| Client::new(http_client, cx));
+        let user_store = cx.new_model(|cx| UserStore::new(client.clone(), cx));
+        let project = cx.update(|cx| {
+            Project::local(
+                client,
+                FakeNodeRuntime::new(),
+                user_store,
+                Arc::new(languages),
+                fs,
+                cx,
+            )
+        });
+        let (tree, _) = project
+            .update(cx, |project, cx| {
+                project.find_or_create_local_worktree("/root", true, cx)
+            })
+            .await
+            .unwrap();
+        tree.update(cx, |tree, _| tree.as_local().unwrap().scan_complete())
+            .await;
+
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/main.rs", cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.add_window(|cx| {
+            build_editor_with_project(
+                project.clone(),
+                MultiBuffer::build_from_buffer(buffer, cx),
+                cx,
+            )
+        });
+
+        _ = editor.update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([3..7]));
+            let point = DisplayPoint::new(0, 5);
+            deploy_context_menu(editor, Default::default(), point, cx);
+        });
+        let mut visual_cx = VisualTestContext::from_window(*editor, cx);
+        visual_cx.update(|cx| cx.refresh());
+        visual_cx.run_until_parked();
+        assert!(visual_cx
+            .debug_bounds("MENU_ITEM-Share as Snippet")
+            .is_some());
+
+        _ = editor.update(cx, |editor, cx| {
+            share_selection_as_snippet(editor, &ShareSelectionAsSnippet, cx);
+        });
+        cx.run_until_parked();
+
+        let uploaded = uploads.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(uploaded.len(), 1);
+        assert_eq!(uploaded[0].0, "https://snippets.example.com/upload");
+        let body: serde_json::Value = serde_json::from_str(&uploaded[0].1).unwrap();
+        assert_eq!(body["content"], "main");
+        assert_eq!(body["file_name"], "main.rs");
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("https://snippets.example.com/s/1".to_string())
+        );
+
+        // Sharing from a sensitive file requires confirmation.
+        cx.write_to_clipboard(ClipboardItem::new(String::new()));
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/.env", cx)
+            })
+            .await
+            .unwrap();
+        let editor = cx.add_window(|cx| {
+            build_editor_with_project(project, MultiBuffer::build_from_buffer(buffer, cx), cx)
+        });
+        _ = editor.update(cx, |editor, cx| {
+            editor.change_selections(None, cx, |s| s.select_ranges([0..5]));
+            share_selection_as_snippet(editor, &ShareSelectionAsSnippet, cx);
+        });
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+        assert!(uploads.lock().is_empty());
+
+        cx.simulate_prompt_answer(1);
+        cx.run_until_parked();
+        assert!(uploads.lock().is_empty());
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some(String::new())
+        );
+
+        // Once confirmed, the selection is uploaded as usual.
+        _ = editor.update(cx, |editor, cx| {
+            share_selection_as_snippet(editor, &ShareSelectionAsSnippet, cx);
+        });
+        cx.run_until_parked();
+        assert!(cx.has_pending_prompt());
+        assert!(uploads.lock().is_empty());
+
+        cx.simulate_prompt_answer(0);
+        cx.run_until_parked();
+        let uploaded = uploads.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(uploaded.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&uploaded[0].1).unwrap();
+        assert_eq!(body["content"], "TOKEN");
+        assert_eq!(body["file_name"], ".env");
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("https://snippets.example.com/s/1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_sensitive_path() {
+        assert!(is_sensitive_path(Path::new("project/.env")));
+        assert!(is_sensitive_path(Path::new(".env.production")));
+        assert!(is_sensitive_path(Path::new("certs/server.pem")));
+        assert!(is_sensitive_path(Path::new("config/secrets.toml")));
+        assert!(!is_sensitive_path(Path::new("src/main.rs")));
+    }
+}
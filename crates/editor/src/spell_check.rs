@@ -0,0 +1,356 @@
+use crate::{AddWordToDictionary, Anchor, Editor, EditorMode, EditorSettings, ReplaceMisspelling};
+use anyhow::{anyhow, Context as _, Result};
+use futures::{future::Shared, AsyncWriteExt as _, FutureExt as _};
+use gpui::{px, AppContext, HighlightStyle, Task, UnderlineStyle, ViewContext};
+use language::ToOffset as _;
+use multi_buffer::{MultiBufferSnapshot, ToOffset};
+use settings::Settings;
+use spellcheck::Dictionary;
+use std::{ops::Range, path::PathBuf, sync::Arc, time::Duration};
+use theme::ActiveTheme;
+use util::{paths, ResultExt};
+
+/// How long to wait after an edit before checking the editor's text again.
+const SPELL_CHECK_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The most suggestions shown for a misspelled word.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// The directories that Hunspell dictionaries are looked for in, besides the user's.
+const SYSTEM_DICTIONARY_DIRS: &[&str] = &[
+    "/Library/Spelling",
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+];
+
+enum MisspellingHighlights {}
+
+/// The misspelled words in the editor's comments, strings and prose.
+#[derive(Default)]
+pub(crate) struct SpellCheckState {
+    misspellings: Vec<Range<Anchor>>,
+    refresh_task: Option<Task<()>>,
+}
+
+/// The dictionary that all editors check words against, along with the `language` and
+/// `dictionary` settings that it was loaded for.
+struct GlobalDictionary {
+    source: (String, Option<String>),
+    dictionary: Shared<Task<Option<Arc<Dictionary>>>>,
+}
+
+pub fn replace_misspelling(
+    editor: &mut Editor,
+    action: &ReplaceMisspelling,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(range) = misspelling_at_cursor(editor, cx) else {
+        return;
+    };
+    editor.transact(cx, |editor, cx| {
+        editor.buffer.update(cx, |buffer, cx| {
+            buffer.edit([(range, action.replacement.as_str())], None, cx)
+        });
+    });
+}
+
+pub fn add_word_to_dictionary(
+    editor: &mut Editor,
+    _: &AddWordToDictionary,
+    cx: &mut ViewContext<Editor>,
+) {
+    let Some(range) = misspelling_at_cursor(editor, cx) else {
+        return;
+    };
+    let Some(dictionary) = loaded_dictionary(cx) else {
+        return;
+    };
+    let word = editor
+        .buffer
+        .read(cx)
+        .snapshot(cx)
+        .text_for_range(range)
+        .collect::<String>();
+    dictionary.add_word(&word);
+    refresh_spell_check(editor, false, cx);
+
+    cx.background_executor()
+        .spawn(async move {
+            let path = &*paths::SPELLING_DICTIONARY;
+            if let Some(dir) = path.parent() {
+                smol::fs::create_dir_all(dir).await?;
+            }
+            let mut file = smol::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .context("failed to open the user's dictionary")?;
+            file.write_all(format!("{word}\n").as_bytes()).await?;
+            file.flush().await?;
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+}
+
+/// The misspelled word under the newest cursor, along with the words suggested in its
+/// place.
+pub(crate) fn suggestions_at_cursor(
+    editor: &Editor,
+    cx: &AppContext,
+) -> Option<(String, Vec<String>)> {
+    let range = misspelling_at_cursor(editor, cx)?;
+    let word = editor
+        .buffer
+        .read(cx)
+        .snapshot(cx)
+        .text_for_range(range)
+        .collect::<String>();
+    let suggestions = loaded_dictionary(cx)?.suggest(&word, MAX_SUGGESTIONS);
+    Some((word, suggestions))
+}
+
+/// Checks the editor's text again, unless spell checking is turned off.
+pub(crate) fn refresh_spell_check(
+    editor: &mut Editor,
+    debounce: bool,
+    cx: &mut ViewContext<Editor>,
+) -> Option<()> {
+    if editor.mode != EditorMode::Full || !EditorSettings::get_global(cx).spell_check.enabled {
+        editor.spell_check.refresh_task = None;
+        if !std::mem::take(&mut editor.spell_check.misspellings).is_empty() {
+            editor.clear_highlights::<MisspellingHighlights>(cx);
+        }
+        return None;
+    }
+
+    let dictionary = dictionary(cx);
+    editor.spell_check.refresh_task = Some(cx.spawn(|editor, mut cx| async move {
+        if debounce {
+            cx.background_executor().timer(SPELL_CHECK_DEBOUNCE).await;
+        }
+        let Some(dictionary) = dictionary.await else {
+            return;
+        };
+        let Ok(snapshot) = editor.update(&mut cx, |editor, cx| editor.buffer.read(cx).snapshot(cx))
+        else {
+            return;
+        };
+
+        let misspellings = cx
+            .background_executor()
+            .spawn(async move { misspellings(&snapshot, &dictionary) })
+            .await;
+        editor
+            .update(&mut cx, |editor, cx| {
+                let style = HighlightStyle {
+                    underline: Some(UnderlineStyle {
+                        thickness: px(1.),
+                        color: Some(cx.theme().status().info),
+                        wavy: true,
+                    }),
+                    ..Default::default()
+                };
+                editor.highlight_text::<MisspellingHighlights>(misspellings.clone(), style, cx);
+                editor.spell_check.misspellings = misspellings;
+            })
+            .ok();
+    }));
+    None
+}
+
+fn misspelling_at_cursor(editor: &Editor, cx: &AppContext) -> Option<Range<Anchor>> {
+    let snapshot = editor.buffer.read(cx).snapshot(cx);
+    let head = editor.selections.newest::<usize>(cx).head();
+    editor
+        .spell_check
+        .misspellings
+        .iter()
+        .find(|range| {
+            range.start.to_offset(&snapshot) <= head && head <= range.end.to_offset(&snapshot)
+        })
+        .cloned()
+}
+
+fn misspellings(snapshot: &MultiBufferSnapshot, dictionary: &Dictionary) -> Vec<Range<Anchor>> {
+    let mut misspellings = Vec::new();
+    for (excerpt_id, buffer, excerpt_range) in snapshot.excerpts() {
+        let range = excerpt_range.context.start.to_offset(buffer)
+            ..excerpt_range.context.end.to_offset(buffer);
+        for checked_range in buffer.spell_checked_ranges(range) {
+            let text = buffer
+                .text_for_range(checked_range.clone())
+                .collect::<String>();
+            for misspelling in dictionary.misspellings(&text) {
+                let start = buffer.anchor_after(checked_range.start + misspelling.start);
+                let end = buffer.anchor_before(checked_range.start + misspelling.end);
+                misspellings.push(
+                    snapshot.anchor_in_excerpt(excerpt_id, start)
+                        ..snapshot.anchor_in_excerpt(excerpt_id, end),
+                );
+            }
+        }
+    }
+    misspellings
+}
+
+/// The dictionary for the current settings, which is loaded in the background the first
+/// time it is needed.
+fn dictionary(cx: &mut AppContext) -> Shared<Task<Option<Arc<Dictionary>>>> {
+    let settings = &EditorSettings::get_global(cx).spell_check;
+    let source = (settings.language.clone(), settings.dictionary.clone());
+    if let Some(global) = cx.try_global::<GlobalDictionary>() {
+        if global.source == source {
+            return global.dictionary.clone();
+        }
+    }
+
+    let dictionary = cx
+        .background_executor()
+        .spawn({
+            let (language, path) = source.clone();
+            async move {
+                load_dictionary(&language, path.as_deref())
+                    .await
+                    .log_err()
+                    .map(Arc::new)
+            }
+        })
+        .shared();
+    cx.set_global(GlobalDictionary {
+        source,
+        dictionary: dictionary.clone(),
+    });
+    dictionary
+}
+
+fn loaded_dictionary(cx: &AppContext) -> Option<Arc<Dictionary>> {
+    cx.try_global::<GlobalDictionary>()?
+        .dictionary
+        .peek()?
+        .clone()
+}
+
+async fn load_dictionary(language: &str, path: Option<&str>) -> Result<Dictionary> {
+    let candidates = match path {
+        Some(path) => vec![match path.strip_prefix("~/") {
+            Some(path) => paths::HOME.join(path),
+            None => PathBuf::from(path),
+        }],
+        None => std::iter::once(paths::HOME.join("Library/Spelling"))
+            .chain(SYSTEM_DICTIONARY_DIRS.iter().map(PathBuf::from))
+            .map(|dir| dir.join(language))
+            .collect(),
+    };
+
+    let mut dictionary = None;
+    for candidate in &candidates {
+        let with_extension = |extension: &str| {
+            let mut path = candidate.clone().into_os_string();
+            path.push(extension);
+            PathBuf::from(path)
+        };
+        let (Ok(aff), Ok(dic)) = (
+            smol::fs::read(with_extension(".aff")).await,
+            smol::fs::read(with_extension(".dic")).await,
+        ) else {
+            continue;
+        };
+        dictionary = Some(
+            Dictionary::from_hunspell(
+                &String::from_utf8_lossy(&aff),
+                &String::from_utf8_lossy(&dic),
+            )
+            .with_context(|| format!("failed to parse the dictionary at {candidate:?}"))?,
+        );
+        break;
+    }
+    let dictionary = dictionary.ok_or_else(|| {
+        anyhow!("no Hunspell dictionary found for spell checking at {candidates:?}")
+    })?;
+
+    if let Ok(words) = smol::fs::read_to_string(&*paths::SPELLING_DICTIONARY).await {
+        dictionary.add_words(&words);
+    }
+    Ok(dictionary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        editor_settings::SpellCheckContent, editor_tests::init_test,
+        test::editor_lsp_test_context::EditorLspTestContext,
+    };
+    use futures::FutureExt as _;
+    use indoc::indoc;
+    use settings::SettingsStore;
+
+    #[gpui::test]
+    async fn test_spell_check(cx: &mut gpui::TestAppContext) {
+        init_test(cx, |_| {});
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings::<EditorSettings>(cx, |settings| {
+                    settings.spell_check = Some(SpellCheckContent {
+                        enabled: Some(true),
+                        language: None,
+                        dictionary: None,
+                    });
+                });
+            });
+            let dictionary = Dictionary::from_word_list("the\nquick\nfox\n");
+            cx.set_global(GlobalDictionary {
+                source: ("en_US".into(), None),
+                dictionary: Task::ready(Some(Arc::new(dictionary))).shared(),
+            });
+        });
+
+        let mut cx = EditorLspTestContext::new_rust(Default::default(), cx).await;
+        cx.set_state(indoc! {r#"
+            // ˇTeh quick fox
+            fn jumsp() -> &'static str { "the qiuck fox" }
+        "#});
+        cx.executor().advance_clock(SPELL_CHECK_DEBOUNCE);
+        cx.run_until_parked();
+
+        // Only comments and strings are checked
+        let misspellings = |cx: &mut EditorLspTestContext| {
+            cx.update_editor(|editor, cx| {
+                let snapshot = editor.buffer.read(cx).snapshot(cx);
+                editor
+                    .spell_check
+                    .misspellings
+                    .iter()
+                    .map(|range| range.start.to_offset(&snapshot)..range.end.to_offset(&snapshot))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let expected = cx.ranges(indoc! {r#"
+            // «Teh» quick fox
+            fn jumsp() -> &'static str { "the «qiuck» fox" }
+        "#});
+        assert_eq!(misspellings(&mut cx), expected);
+
+        let suggestions = cx.update_editor(|editor, cx| suggestions_at_cursor(editor, cx));
+        assert_eq!(suggestions, Some(("Teh".into(), vec!["The".into()])));
+
+        cx.update_editor(|editor, cx| {
+            replace_misspelling(
+                editor,
+                &ReplaceMisspelling {
+                    replacement: "The".into(),
+                },
+                cx,
+            )
+        });
+        cx.executor().advance_clock(SPELL_CHECK_DEBOUNCE);
+        cx.run_until_parked();
+        let expected = cx.ranges(indoc! {r#"
+            // The quick fox
+            fn jumsp() -> &'static str { "the «qiuck» fox" }
+        "#});
+        assert_eq!(misspellings(&mut cx), expected);
+    }
+}
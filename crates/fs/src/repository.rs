@@ -28,6 +28,9 @@ pub trait GitRepository: Send {
     fn load_index_text(&self, relative_file_path: &Path) -> Option<String>;
     fn branch_name(&self) -> Option<String>;
 
+    /// Get the URL of the remote with the given name, such as `origin`.
+    fn remote_url(&self, name: &str) -> Option<String>;
+
     /// Get the statuses of all of the files in the index that start with the given
     /// path and have changes with respect to the HEAD commit. This is fast because
     /// the index stores hashes of trees, so that unchanged directories can be skipped.
@@ -94,6 +97,11 @@ impl GitRepository for LibGitRepository {
         Some(branch.to_string())
     }
 
+    fn remote_url(&self, name: &str) -> Option<String> {
+        let remote = self.find_remote(name).ok()?;
+        remote.url().map(|url| url.to_string())
+    }
+
     fn staged_statuses(&self, path_prefix: &Path) -> TreeMap<RepoPath, GitFileStatus> {
         let mut map = TreeMap::default();
 
@@ -239,6 +247,7 @@ pub struct FakeGitRepositoryState {
     pub index_contents: HashMap<PathBuf, String>,
     pub worktree_statuses: HashMap<RepoPath, GitFileStatus>,
     pub branch_name: Option<String>,
+    pub remote_urls: HashMap<String, String>,
 }
 
 impl FakeGitRepository {
@@ -260,6 +269,11 @@ impl GitRepository for FakeGitRepository {
         state.branch_name.clone()
     }
 
+    fn remote_url(&self, name: &str) -> Option<String> {
+        let state = self.state.lock();
+        state.remote_urls.get(name).cloned()
+    }
+
     fn staged_statuses(&self, path_prefix: &Path) -> TreeMap<RepoPath, GitFileStatus> {
         let mut map = TreeMap::default();
         let state = self.state.lock();
@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Context, Result};
+use collections::HashMap;
+use futures::AsyncWriteExt;
+use git2::Oid;
+use std::{ops::Range, path::Path, process::Stdio};
+use text::Rope;
+
+/// The commit that last changed each line of a file, as reported by `git blame`.
+#[derive(Clone, Debug, Default)]
+pub struct Blame {
+    /// The blamed ranges of lines, ordered by row.
+    pub entries: Vec<BlameEntry>,
+    /// The URL of the repository's `origin` remote, which permalinks point into.
+    pub remote_url: Option<String>,
+}
+
+/// A range of lines that were last changed by the same commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlameEntry {
+    pub sha: Oid,
+    /// The zero-based rows of the blamed lines in the blamed content.
+    pub range: Range<u32>,
+    /// The zero-based row of the first line in the file as of the commit.
+    pub original_row: u32,
+    pub author: Option<String>,
+    pub author_mail: Option<String>,
+    /// The time the commit was authored, in seconds since the Unix epoch.
+    pub author_time: Option<i64>,
+    pub summary: Option<String>,
+    /// The path of the file as of the commit.
+    pub filename: String,
+}
+
+impl BlameEntry {
+    /// Whether the lines have changes that aren't committed yet, which git blames on a
+    /// commit whose hash is all zeros.
+    pub fn is_uncommitted(&self) -> bool {
+        self.sha.is_zero()
+    }
+}
+
+impl Blame {
+    /// Runs `git blame` on `content`, which is the possibly unsaved content of the file at
+    /// `path` within the repository at `working_directory`.
+    pub async fn for_path(
+        git_binary: &Path,
+        working_directory: &Path,
+        path: &Path,
+        content: &Rope,
+        remote_url: Option<String>,
+    ) -> Result<Self> {
+        let output = run_git_blame(git_binary, working_directory, path, content).await?;
+        let mut entries = parse_git_blame(&output)?;
+        entries.sort_unstable_by_key(|entry| entry.range.start);
+        Ok(Self {
+            entries,
+            remote_url,
+        })
+    }
+}
+
+async fn run_git_blame(
+    git_binary: &Path,
+    working_directory: &Path,
+    path: &Path,
+    content: &Rope,
+) -> Result<String> {
+    let mut child = smol::process::Command::new(git_binary)
+        .current_dir(working_directory)
+        .arg("blame")
+        .arg("--incremental")
+        .arg("--contents")
+        .arg("-")
+        .arg("--")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to start git blame")?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("failed to open git blame's stdin"))?;
+    for chunk in content.chunks() {
+        stdin.write_all(chunk.as_bytes()).await?;
+    }
+    stdin.flush().await?;
+    drop(stdin);
+
+    let output = child
+        .output()
+        .await
+        .context("failed to read git blame's output")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git blame failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Parses the output of `git blame --incremental`. Each entry starts with a line holding
+/// the commit's hash, the entry's original and final line numbers and its number of lines,
+/// followed by the commit's details and ends with the file name. The details of a commit
+/// are only listed the first time the commit is blamed.
+fn parse_git_blame(output: &str) -> Result<Vec<BlameEntry>> {
+    let mut entries = Vec::new();
+    let mut commit_details = HashMap::<Oid, BlameEntry>::default();
+    let mut current: Option<BlameEntry> = None;
+
+    for line in output.lines() {
+        let Some(entry) = current.as_mut() else {
+            let (sha, original_line, final_line, line_count) =
+                parse_blame_header(line).ok_or_else(|| anyhow!("invalid blame header {line:?}"))?;
+            let row = final_line.saturating_sub(1);
+
+            let range = row..row + line_count;
+            let original_row = original_line.saturating_sub(1);
+            current = Some(match commit_details.get(&sha) {
+                Some(entry) => BlameEntry {
+                    range,
+                    original_row,
+                    ..entry.clone()
+                },
+                None => BlameEntry {
+                    sha,
+                    range,
+                    original_row,
+                    author: None,
+                    author_mail: None,
+                    author_time: None,
+                    summary: None,
+                    filename: String::new(),
+                },
+            });
+            continue;
+        };
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => entry.author = Some(value.to_string()),
+            "author-mail" => {
+                entry.author_mail = Some(
+                    value
+                        .trim_start_matches('<')
+                        .trim_end_matches('>')
+                        .to_string(),
+                )
+            }
+            "author-time" => entry.author_time = value.parse().ok(),
+            "summary" => entry.summary = Some(value.to_string()),
+            "filename" => {
+                let mut entry = current.take().unwrap();
+                entry.filename = value.to_string();
+                commit_details
+                    .entry(entry.sha)
+                    .or_insert_with(|| entry.clone());
+                entries.push(entry);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_blame_header(line: &str) -> Option<(Oid, u32, u32, u32)> {
+    let mut fields = line.split(' ');
+    let sha = Oid::from_str(fields.next()?).ok()?;
+    let original_line = fields.next()?.parse().ok()?;
+    let final_line = fields.next()?.parse().ok()?;
+    let line_count = fields.next()?.parse().ok()?;
+    Some((sha, original_line, final_line, line_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unindent::Unindent as _;
+
+    #[test]
+    fn test_parse_git_blame() {
+        let output = "
+            1b1a6a3d6ed5f0cbcf9e3b0f1b0d4e8cf3c1b9a2 1 1 2
+            author Jane Doe
+            author-mail <jane@example.com>
+            author-time 1700000000
+            author-tz +0100
+            committer Jane Doe
+            committer-mail <jane@example.com>
+            committer-time 1700000000
+            committer-tz +0100
+            summary Add the first lines
+            boundary
+            filename src/main.rs
+            0000000000000000000000000000000000000000 3 3 1
+            author Not Committed Yet
+            author-mail <not.committed.yet>
+            author-time 1710000000
+            author-tz +0000
+            committer Not Committed Yet
+            committer-mail <not.committed.yet>
+            committer-time 1710000000
+            committer-tz +0000
+            summary Version of src/main.rs from -
+            previous 1b1a6a3d6ed5f0cbcf9e3b0f1b0d4e8cf3c1b9a2 src/main.rs
+            filename src/main.rs
+            1b1a6a3d6ed5f0cbcf9e3b0f1b0d4e8cf3c1b9a2 4 4 1
+            filename src/old_main.rs
+        "
+        .unindent();
+
+        let sha = Oid::from_str("1b1a6a3d6ed5f0cbcf9e3b0f1b0d4e8cf3c1b9a2").unwrap();
+        let entries = parse_git_blame(&output).unwrap();
+        assert_eq!(
+            entries,
+            &[
+                BlameEntry {
+                    sha,
+                    range: 0..2,
+                    original_row: 0,
+                    author: Some("Jane Doe".into()),
+                    author_mail: Some("jane@example.com".into()),
+                    author_time: Some(1700000000),
+                    summary: Some("Add the first lines".into()),
+                    filename: "src/main.rs".into(),
+                },
+                BlameEntry {
+                    sha: Oid::zero(),
+                    range: 2..3,
+                    original_row: 2,
+                    author: Some("Not Committed Yet".into()),
+                    author_mail: Some("not.committed.yet".into()),
+                    author_time: Some(1710000000),
+                    summary: Some("Version of src/main.rs from -".into()),
+                    filename: "src/main.rs".into(),
+                },
+                // The details of a commit are only listed for its first entry
+                BlameEntry {
+                    sha,
+                    range: 3..4,
+                    original_row: 3,
+                    author: Some("Jane Doe".into()),
+                    author_mail: Some("jane@example.com".into()),
+                    author_time: Some(1700000000),
+                    summary: Some("Add the first lines".into()),
+                    filename: "src/old_main.rs".into(),
+                },
+            ]
+        );
+        assert!(!entries[0].is_uncommitted());
+        assert!(entries[1].is_uncommitted());
+    }
+}
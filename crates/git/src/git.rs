@@ -3,7 +3,9 @@ use std::ffi::OsStr;
 pub use git2 as libgit;
 pub use lazy_static::lazy_static;
 
+pub mod blame;
 pub mod diff;
+pub mod permalink;
 
 lazy_static! {
     pub static ref DOT_GIT: &'static OsStr = OsStr::new(".git");
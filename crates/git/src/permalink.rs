@@ -0,0 +1,156 @@
+/// A site hosting git repositories, which links to their files and commits can be
+/// built for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitHostingProvider {
+    Github,
+    Gitlab,
+    Bitbucket,
+}
+
+impl GitHostingProvider {
+    fn base_url(&self) -> &'static str {
+        match self {
+            Self::Github => "https://github.com",
+            Self::Gitlab => "https://gitlab.com",
+            Self::Bitbucket => "https://bitbucket.org",
+        }
+    }
+}
+
+/// The repository that a remote refers to on a hosting provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedGitRemote<'a> {
+    pub provider: GitHostingProvider,
+    pub owner: &'a str,
+    pub repo: &'a str,
+}
+
+impl<'a> ParsedGitRemote<'a> {
+    /// Parses remote URLs such as `git@github.com:owner/repo.git` and
+    /// `https://github.com/owner/repo.git`.
+    pub fn parse(url: &'a str) -> Option<Self> {
+        let (provider, path) = [
+            GitHostingProvider::Github,
+            GitHostingProvider::Gitlab,
+            GitHostingProvider::Bitbucket,
+        ]
+        .into_iter()
+        .find_map(|provider| {
+            let host = provider.base_url().trim_start_matches("https://");
+            let path = url
+                .strip_prefix(&format!("git@{host}:"))
+                .or_else(|| url.strip_prefix(&format!("ssh://git@{host}/")))
+                .or_else(|| url.strip_prefix(&format!("https://{host}/")))
+                .or_else(|| {
+                    // Bitbucket's HTTPS URLs include the name of the user
+                    let (_, rest) = url.strip_prefix("https://")?.split_once('@')?;
+                    rest.strip_prefix(host)?.strip_prefix('/')
+                })?;
+            Some((provider, path))
+        })?;
+
+        let path = path.trim_end_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let (owner, repo) = path.rsplit_once('/')?;
+        if owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        Some(Self {
+            provider,
+            owner,
+            repo,
+        })
+    }
+
+    /// Builds a link to a line of the file at `path` as of the commit with the given hash.
+    pub fn permalink(&self, sha: &str, path: &str, row: u32) -> String {
+        let line = row + 1;
+        let base = self.repository_url();
+        match self.provider {
+            GitHostingProvider::Github => format!("{base}/blob/{sha}/{path}#L{line}"),
+            GitHostingProvider::Gitlab => format!("{base}/-/blob/{sha}/{path}#L{line}"),
+            GitHostingProvider::Bitbucket => format!("{base}/src/{sha}/{path}#lines-{line}"),
+        }
+    }
+
+    /// Builds a link to the commit with the given hash.
+    pub fn commit_url(&self, sha: &str) -> String {
+        let base = self.repository_url();
+        match self.provider {
+            GitHostingProvider::Github => format!("{base}/commit/{sha}"),
+            GitHostingProvider::Gitlab => format!("{base}/-/commit/{sha}"),
+            GitHostingProvider::Bitbucket => format!("{base}/commits/{sha}"),
+        }
+    }
+
+    fn repository_url(&self) -> String {
+        format!("{}/{}/{}", self.provider.base_url(), self.owner, self.repo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_remote() {
+        for url in [
+            "git@github.com:zed-industries/zed.git",
+            "ssh://git@github.com/zed-industries/zed.git",
+            "https://github.com/zed-industries/zed.git",
+            "https://github.com/zed-industries/zed",
+        ] {
+            assert_eq!(
+                ParsedGitRemote::parse(url),
+                Some(ParsedGitRemote {
+                    provider: GitHostingProvider::Github,
+                    owner: "zed-industries",
+                    repo: "zed",
+                }),
+                "{url}"
+            );
+        }
+
+        assert_eq!(
+            ParsedGitRemote::parse("git@gitlab.com:group/subgroup/project.git"),
+            Some(ParsedGitRemote {
+                provider: GitHostingProvider::Gitlab,
+                owner: "group/subgroup",
+                repo: "project",
+            })
+        );
+        assert_eq!(
+            ParsedGitRemote::parse("https://jane@bitbucket.org/team/repo.git"),
+            Some(ParsedGitRemote {
+                provider: GitHostingProvider::Bitbucket,
+                owner: "team",
+                repo: "repo",
+            })
+        );
+        assert_eq!(ParsedGitRemote::parse("https://example.com/a/b.git"), None);
+        assert_eq!(ParsedGitRemote::parse("git@github.com:zed.git"), None);
+    }
+
+    #[test]
+    fn test_permalinks() {
+        let github = ParsedGitRemote::parse("git@github.com:zed-industries/zed.git").unwrap();
+        assert_eq!(
+            github.permalink("abc123", "crates/editor/src/editor.rs", 9),
+            "https://github.com/zed-industries/zed/blob/abc123/crates/editor/src/editor.rs#L10"
+        );
+        assert_eq!(
+            github.commit_url("abc123"),
+            "https://github.com/zed-industries/zed/commit/abc123"
+        );
+
+        let gitlab = ParsedGitRemote::parse("https://gitlab.com/group/project.git").unwrap();
+        assert_eq!(
+            gitlab.permalink("abc123", "src/main.rs", 0),
+            "https://gitlab.com/group/project/-/blob/abc123/src/main.rs#L1"
+        );
+        assert_eq!(
+            gitlab.commit_url("abc123"),
+            "https://gitlab.com/group/project/-/commit/abc123"
+        );
+    }
+}
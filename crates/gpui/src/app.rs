@@ -522,6 +522,23 @@ impl AppContext {
         self.platform.read_from_clipboard()
     }
 
+    /// Writes data to the primary selection, which is pasted by middle-clicking on Linux.
+    /// This does nothing on platforms without a primary selection.
+    pub fn write_to_primary(&self, item: ClipboardItem) {
+        self.platform.write_to_primary(item)
+    }
+
+    /// Reads data from the primary selection, on platforms which have one.
+    pub fn read_from_primary(&self) -> Option<ClipboardItem> {
+        self.platform.read_from_primary()
+    }
+
+    /// Returns whether the platform has a primary selection, so that callers can skip
+    /// preparing items for it where it doesn't.
+    pub fn has_primary_selection(&self) -> bool {
+        self.platform.has_primary_selection()
+    }
+
     /// Writes credentials to the platform keychain.
     pub fn write_credentials(
         &self,
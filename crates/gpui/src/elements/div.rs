@@ -2257,6 +2257,17 @@ impl ScrollHandle {
         *self.0.borrow().offset.borrow()
     }
 
+    /// Set the scroll offset. It's clamped to the scrollable area the next time
+    /// the element is painted.
+    pub fn set_offset(&self, offset: Point<Pixels>) {
+        *self.0.borrow().offset.borrow_mut() = offset;
+    }
+
+    /// Get the bounds of the scrolled element.
+    pub fn bounds(&self) -> Bounds<Pixels> {
+        self.0.borrow().bounds
+    }
+
     /// Get the top child that's scrolled into view.
     pub fn top_item(&self) -> usize {
         let state = self.0.borrow();
@@ -107,6 +107,9 @@ pub(crate) trait Platform: 'static {
 
     fn write_to_clipboard(&self, item: ClipboardItem);
     fn read_from_clipboard(&self) -> Option<ClipboardItem>;
+    fn write_to_primary(&self, item: ClipboardItem);
+    fn read_from_primary(&self) -> Option<ClipboardItem>;
+    fn has_primary_selection(&self) -> bool;
 
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>>;
     fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>>;
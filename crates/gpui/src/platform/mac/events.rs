@@ -60,6 +60,7 @@ pub fn key_to_native(key: &str) -> Cow<str> {
         "f10" => NSF10FunctionKey,
         "f11" => NSF11FunctionKey,
         "f12" => NSF12FunctionKey,
+        "menu" => NSMenuFunctionKey,
         _ => return Cow::Borrowed(key),
     };
     Cow::Owned(String::from_utf16(&[code]).unwrap())
@@ -288,6 +289,7 @@ unsafe fn parse_keystroke(native_event: id) -> Keystroke {
         Some(NSF10FunctionKey) => "f10".to_string(),
         Some(NSF11FunctionKey) => "f11".to_string(),
         Some(NSF12FunctionKey) => "f12".to_string(),
+        Some(NSMenuFunctionKey) => "menu".to_string(),
         _ => {
             let mut chars_ignoring_modifiers_and_shift =
                 chars_for_modified_key(native_event.keyCode(), false, false);
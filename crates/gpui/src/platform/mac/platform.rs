@@ -866,6 +866,17 @@ impl Platform for MacPlatform {
         }
     }
 
+    // macOS has no primary selection
+    fn write_to_primary(&self, _item: ClipboardItem) {}
+
+    fn read_from_primary(&self) -> Option<ClipboardItem> {
+        None
+    }
+
+    fn has_primary_selection(&self) -> bool {
+        false
+    }
+
     fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>> {
         let url = url.to_string();
         let username = username.to_string();
@@ -277,6 +277,16 @@ pub struct CodeAction {
     pub lsp_action: lsp::CodeAction,
 }
 
+/// A piece of code that can be run on its own, such as a test function, as found
+/// by its language's `runnables` query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Runnable {
+    /// The range of the whole runnable item.
+    pub range: Range<Anchor>,
+    /// The name that the runnable is run by.
+    pub name: String,
+}
+
 /// An operation used to synchronize this buffer with its other replicas.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Operation {
@@ -2584,6 +2594,150 @@ impl BufferSnapshot {
         (start..end, word_kind)
     }
 
+    /// Returns the ranges of the identifiers within `range` that have the same name as the
+    /// identifier at `position`, including that identifier, ordered by position.
+    pub fn identifier_occurrences<T: ToOffset>(
+        &self,
+        position: T,
+        range: Range<T>,
+    ) -> Vec<Range<usize>> {
+        let offset = position.to_offset(self);
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+        let is_identifier = |node: &tree_sitter::Node| node.kind().ends_with("identifier");
+
+        let mut occurrences = Vec::new();
+        for layer in self.syntax.layers_for_range(offset..offset, &self.text) {
+            // The cursor may also be right after the identifier
+            let Some(identifier) = [Some(offset), offset.checked_sub(1)]
+                .into_iter()
+                .flatten()
+                .filter_map(|offset| layer.node().descendant_for_byte_range(offset, offset))
+                .find(|node| is_identifier(node) && node.child_count() == 0)
+            else {
+                continue;
+            };
+            let name = self
+                .text_for_range(identifier.byte_range())
+                .collect::<String>();
+
+            let mut cursor = layer.node().walk();
+            loop {
+                let node = cursor.node();
+                let intersects_range =
+                    node.start_byte() < range.end && range.start < node.end_byte();
+                if intersects_range && cursor.goto_first_child() {
+                    continue;
+                }
+                if intersects_range
+                    && is_identifier(&node)
+                    && node.byte_range().len() == name.len()
+                    && self.text_for_range(node.byte_range()).collect::<String>() == name
+                {
+                    occurrences.push(node.byte_range());
+                }
+                while !cursor.goto_next_sibling() {
+                    if !cursor.goto_parent() {
+                        break;
+                    }
+                }
+                if cursor.node().id() == layer.node().id() {
+                    break;
+                }
+            }
+        }
+
+        occurrences.sort_by_key(|range| range.start);
+        occurrences.dedup();
+        occurrences
+    }
+
+    /// Returns the ranges within `range` that are spell checked, ordered by position. These
+    /// are the comments and strings, along with everything but code in prose languages such
+    /// as Markdown and in plain text.
+    pub fn spell_checked_ranges<T: ToOffset>(&self, range: Range<T>) -> Vec<Range<usize>> {
+        let range = range.start.to_offset(self)..range.end.to_offset(self);
+        let mut layers = self
+            .syntax
+            .layers_for_range(range.clone(), &self.text)
+            .peekable();
+        if layers.peek().is_none() {
+            return if self.language.as_ref().map_or(true, |language| {
+                language.config.prose || language.grammar().is_none()
+            }) {
+                vec![range]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let mut ranges = Vec::new();
+        for layer in layers {
+            let is_prose = layer.language.config.prose;
+            let mut included = Vec::new();
+            let mut excluded = Vec::new();
+            if is_prose {
+                included.push(layer.node().byte_range());
+            }
+
+            let mut cursor = layer.node().walk();
+            loop {
+                let node = cursor.node();
+                let kind = node.kind();
+                let intersects_range =
+                    node.start_byte() < range.end && range.start < node.end_byte();
+                let is_checked = !is_prose && (kind.contains("comment") || kind.contains("string"));
+                let is_code = is_prose
+                    && (kind.contains("code")
+                        || kind.contains("html")
+                        || kind.contains("autolink")
+                        || kind.ends_with("destination"));
+                if is_checked && intersects_range {
+                    included.push(node.byte_range());
+                } else if is_code && intersects_range {
+                    excluded.push(node.byte_range());
+                } else if intersects_range && cursor.goto_first_child() {
+                    continue;
+                }
+
+                while !cursor.goto_next_sibling() {
+                    if !cursor.goto_parent() {
+                        break;
+                    }
+                }
+                if cursor.node().id() == layer.node().id() {
+                    break;
+                }
+            }
+
+            for included in included {
+                let mut start = included.start.max(range.start);
+                let end = included.end.min(range.end);
+                for excluded in &excluded {
+                    if excluded.end <= start || excluded.start >= end {
+                        continue;
+                    }
+                    if start < excluded.start {
+                        ranges.push(start..excluded.start);
+                    }
+                    start = start.max(excluded.end);
+                }
+                if start < end {
+                    ranges.push(start..end);
+                }
+            }
+        }
+
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
     /// Returns the range for the closes syntax node enclosing the given range.
     pub fn range_for_syntax_ancestor<T: ToOffset>(&self, range: Range<T>) -> Option<Range<usize>> {
         let range = range.start.to_offset(self)..range.end.to_offset(self);
@@ -2875,6 +3029,54 @@ impl BufferSnapshot {
         })
     }
 
+    /// Returns the innermost runnable that contains the given position.
+    pub fn runnable_containing<T: ToOffset>(&self, position: T) -> Option<Runnable> {
+        let offset = position.to_offset(self);
+        let mut matches = self.syntax.matches(
+            offset.saturating_sub(1)..self.len().min(offset + 1),
+            &self.text,
+            |grammar| grammar.runnable_config.as_ref().map(|c| &c.query),
+        );
+        let configs = matches
+            .grammars()
+            .iter()
+            .map(|grammar| grammar.runnable_config.as_ref().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut innermost: Option<(Range<usize>, Range<usize>)> = None;
+        while let Some(mat) = matches.peek() {
+            let mut run_range = None;
+            let mut name_range = None;
+            let config = &configs[mat.grammar_index];
+            for capture in mat.captures {
+                if capture.index == config.run_capture_ix {
+                    run_range = Some(capture.node.byte_range());
+                } else if capture.index == config.name_capture_ix {
+                    name_range = Some(capture.node.byte_range());
+                }
+            }
+            matches.advance();
+
+            let Some((run_range, name_range)) = run_range.zip(name_range) else {
+                continue;
+            };
+            if run_range.start <= offset
+                && offset <= run_range.end
+                && innermost
+                    .as_ref()
+                    .map_or(true, |(innermost, _)| run_range.len() < innermost.len())
+            {
+                innermost = Some((run_range, name_range));
+            }
+        }
+
+        let (range, name_range) = innermost?;
+        Some(Runnable {
+            range: self.anchor_before(range.start)..self.anchor_after(range.end),
+            name: self.text_for_range(name_range).collect(),
+        })
+    }
+
     /// Returns selections for remote peers intersecting the given range.
     #[allow(clippy::type_complexity)]
     pub fn remote_selections_in_range(
@@ -731,6 +731,57 @@ async fn test_symbols_containing(cx: &mut gpui::TestAppContext) {
     }
 }
 
+#[gpui::test]
+async fn test_runnable_containing(cx: &mut gpui::TestAppContext) {
+    let language = rust_lang()
+        .with_runnable_query(
+            r#"
+            (
+                (attribute_item) @_attribute
+                .
+                (attribute_item)*
+                .
+                (function_item
+                    name: (_) @name) @run
+                (#match? @_attribute "^#\\[(\\w+::)*test(\\(.*\\))?\\]$")
+            )
+            "#,
+        )
+        .unwrap();
+
+    let text = r#"
+        fn helper() {}
+
+        #[cfg(test)]
+        mod tests {
+            #[test]
+            fn one() {
+                helper();
+            }
+
+            #[gpui::test(iterations = 10)]
+            #[should_panic]
+            fn two() {}
+        }
+    "#
+    .unindent();
+
+    let buffer = cx.new_model(|cx| {
+        Buffer::new(0, cx.entity_id().as_u64(), text).with_language(Arc::new(language), cx)
+    });
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+
+    let runnable_name = |position| {
+        snapshot
+            .runnable_containing(position)
+            .map(|runnable| runnable.name)
+    };
+    assert_eq!(runnable_name(Point::new(0, 5)), None);
+    assert_eq!(runnable_name(Point::new(6, 10)), Some("one".to_string()));
+    assert_eq!(runnable_name(Point::new(11, 10)), Some("two".to_string()));
+    assert_eq!(runnable_name(Point::new(3, 4)), None);
+}
+
 #[gpui::test]
 fn test_enclosing_bracket_ranges(cx: &mut AppContext) {
     let mut assert = |selection_text, range_markers| {
@@ -918,6 +969,70 @@ fn test_range_for_syntax_ancestor(cx: &mut AppContext) {
     }
 }
 
+#[gpui::test]
+fn test_identifier_occurrences(cx: &mut AppContext) {
+    cx.new_model(|cx| {
+        let text = "fn a(b: u32) -> u32 { let c = b + 1; b * c }";
+        let buffer =
+            Buffer::new(0, cx.entity_id().as_u64(), text).with_language(Arc::new(rust_lang()), cx);
+        let snapshot = buffer.snapshot();
+        let b = text.find("b:").unwrap();
+        let occurrences = |position: usize, range: Range<usize>| {
+            snapshot
+                .identifier_occurrences(position, range)
+                .into_iter()
+                .map(|range| (range.start, &text[range]))
+                .collect::<Vec<_>>()
+        };
+
+        let expected = text.match_indices('b').collect::<Vec<_>>();
+        assert_eq!(expected.len(), 3);
+        assert_eq!(occurrences(b, 0..text.len()), expected);
+        // Right after the identifier
+        assert_eq!(occurrences(b + 1, 0..text.len()), expected);
+        // Only the occurrences within the range
+        assert_eq!(occurrences(b, b + 1..text.len()), expected[1..]);
+        // Not on an identifier
+        assert_eq!(occurrences(text.find("+").unwrap() + 1, 0..text.len()), &[]);
+
+        buffer
+    });
+}
+
+#[gpui::test]
+fn test_spell_checked_ranges(cx: &mut AppContext) {
+    cx.new_model(|cx| {
+        let text = "// A coment\nfn a() -> &'static str { \"teh\" }\n";
+        let buffer =
+            Buffer::new(0, cx.entity_id().as_u64(), text).with_language(Arc::new(rust_lang()), cx);
+        let snapshot = buffer.snapshot();
+        assert_eq!(
+            snapshot
+                .spell_checked_ranges(0..text.len())
+                .into_iter()
+                .map(|range| text[range].trim_end())
+                .collect::<Vec<_>>(),
+            ["// A coment", "\"teh\""]
+        );
+        assert_eq!(
+            snapshot
+                .spell_checked_ranges(5..text.len())
+                .into_iter()
+                .map(|range| text[range].trim_end())
+                .collect::<Vec<_>>(),
+            ["coment", "\"teh\""]
+        );
+        buffer
+    });
+
+    // Plain text is checked everywhere
+    cx.new_model(|cx| {
+        let buffer = Buffer::new(0, cx.entity_id().as_u64(), "Teh text\n");
+        assert_eq!(buffer.snapshot().spell_checked_ranges(0..9), [0..9]);
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_autoindent_with_soft_tabs(cx: &mut AppContext) {
     init_settings(cx, |_| {});
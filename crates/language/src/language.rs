@@ -438,6 +438,15 @@ pub struct LanguageConfig {
     /// The name of a Prettier parser that should be used for this language.
     #[serde(default)]
     pub prettier_parser_name: Option<String>,
+    /// The command that runs a single test, such as one found by the `runnables` query.
+    /// `$ZED_TEST_NAME` is replaced with the test's name, `$ZED_FILE` with the path of
+    /// its file and `$ZED_DIRNAME` with the path of the file's directory.
+    #[serde(default)]
+    pub run_test_command: Option<String>,
+    /// Whether the language's documents are prose, such as Markdown's, which are spell
+    /// checked everywhere but in code rather than only in comments and strings.
+    #[serde(default)]
+    pub prose: bool,
 }
 
 /// Tree-sitter language queries for a given language.
@@ -450,6 +459,7 @@ pub struct LanguageQueries {
     pub embedding: Option<Cow<'static, str>>,
     pub injections: Option<Cow<'static, str>>,
     pub overrides: Option<Cow<'static, str>>,
+    pub runnables: Option<Cow<'static, str>>,
 }
 
 /// Represents a language for the given range. Some languages (e.g. HTML)
@@ -517,6 +527,8 @@ impl Default for LanguageConfig {
             word_characters: Default::default(),
             prettier_parser_name: None,
             collapsed_placeholder: Default::default(),
+            run_test_command: None,
+            prose: false,
         }
     }
 }
@@ -625,6 +637,7 @@ pub struct Grammar {
     pub embedding_config: Option<EmbeddingConfig>,
     pub(crate) injection_config: Option<InjectionConfig>,
     pub(crate) override_config: Option<OverrideConfig>,
+    pub(crate) runnable_config: Option<RunnableConfig>,
     pub(crate) highlight_map: Mutex<HighlightMap>,
 }
 
@@ -672,6 +685,12 @@ struct InjectionPatternConfig {
     combined: bool,
 }
 
+struct RunnableConfig {
+    query: Query,
+    run_capture_ix: u32,
+    name_capture_ix: u32,
+}
+
 struct BracketConfig {
     query: Query,
     open_capture_ix: u32,
@@ -1287,6 +1306,7 @@ impl Language {
                     indents_config: None,
                     injection_config: None,
                     override_config: None,
+                    runnable_config: None,
                     error_query: Query::new(&ts_language, "(ERROR) @error").unwrap(),
                     ts_language,
                     highlight_map: Default::default(),
@@ -1343,6 +1363,11 @@ impl Language {
                 .with_override_query(query.as_ref())
                 .context("Error loading override query")?;
         }
+        if let Some(query) = queries.runnables {
+            self = self
+                .with_runnable_query(query.as_ref())
+                .context("Error loading runnables query")?;
+        }
         Ok(self)
     }
 
@@ -1411,6 +1436,25 @@ impl Language {
         Ok(self)
     }
 
+    pub fn with_runnable_query(mut self, source: &str) -> Result<Self> {
+        let grammar = self.grammar_mut();
+        let query = Query::new(&grammar.ts_language, source)?;
+        let mut run_capture_ix = None;
+        let mut name_capture_ix = None;
+        get_capture_indices(
+            &query,
+            &mut [("run", &mut run_capture_ix), ("name", &mut name_capture_ix)],
+        );
+        if let Some((run_capture_ix, name_capture_ix)) = run_capture_ix.zip(name_capture_ix) {
+            grammar.runnable_config = Some(RunnableConfig {
+                query,
+                run_capture_ix,
+                name_capture_ix,
+            });
+        }
+        Ok(self)
+    }
+
     pub fn with_brackets_query(mut self, source: &str) -> Result<Self> {
         let grammar = self.grammar_mut();
         let query = Query::new(&grammar.ts_language, source)?;
@@ -1705,6 +1749,10 @@ impl Language {
     pub fn prettier_parser_name(&self) -> Option<&str> {
         self.config.prettier_parser_name.as_deref()
     }
+
+    pub fn run_test_command(&self) -> Option<&str> {
+        self.config.run_test_command.as_deref()
+    }
 }
 
 impl LanguageScope {
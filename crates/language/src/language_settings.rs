@@ -91,6 +91,12 @@ pub struct LanguageSettings {
     pub extend_comment_on_newline: bool,
     /// Inlay hint related settings.
     pub inlay_hints: InlayHintSettings,
+    /// Whether to show the code lenses provided by language servers above the code they
+    /// refer to.
+    pub code_lens: bool,
+    /// Whether to highlight code with the semantic tokens provided by language servers,
+    /// on top of its syntax highlighting.
+    pub semantic_tokens: bool,
     /// Whether to automatically close brackets.
     pub use_autoclose: bool,
 }
@@ -210,6 +216,18 @@ pub struct LanguageSettingsContent {
     /// Inlay hint related settings.
     #[serde(default)]
     pub inlay_hints: Option<InlayHintSettings>,
+    /// Whether to show the code lenses provided by language servers above the code they
+    /// refer to.
+    ///
+    /// Default: true
+    #[serde(default)]
+    pub code_lens: Option<bool>,
+    /// Whether to highlight code with the semantic tokens provided by language servers,
+    /// on top of its syntax highlighting.
+    ///
+    /// Default: false
+    #[serde(default)]
+    pub semantic_tokens: Option<bool>,
     /// Whether to automatically type closing characters for you. For example,
     /// when you type (, Zed will automatically add a closing ) at the correct position.
     ///
@@ -320,12 +338,32 @@ pub struct InlayHintSettings {
     /// Default: true
     #[serde(default = "default_true")]
     pub show_other_hints: bool,
+    /// How long to wait after the buffer is edited before querying for new hints,
+    /// in milliseconds. Set to 0 to query right away.
+    ///
+    /// Default: 700
+    #[serde(default = "default_edit_debounce_ms")]
+    pub edit_debounce_ms: u64,
+    /// How long to wait after the editor is scrolled before querying for the newly
+    /// visible hints, in milliseconds. Set to 0 to query right away.
+    ///
+    /// Default: 50
+    #[serde(default = "default_scroll_debounce_ms")]
+    pub scroll_debounce_ms: u64,
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_edit_debounce_ms() -> u64 {
+    700
+}
+
+fn default_scroll_debounce_ms() -> u64 {
+    50
+}
+
 impl InlayHintSettings {
     /// Returns the kinds of inlay hints that are enabled based on the settings.
     pub fn enabled_inlay_hint_kinds(&self) -> HashSet<Option<InlayHintKind>> {
@@ -580,6 +618,8 @@ fn merge_settings(settings: &mut LanguageSettings, src: &LanguageSettingsContent
         src.extend_comment_on_newline,
     );
     merge(&mut settings.inlay_hints, src.inlay_hints);
+    merge(&mut settings.code_lens, src.code_lens);
+    merge(&mut settings.semantic_tokens, src.semantic_tokens);
     fn merge<T>(target: &mut T, value: Option<T>) {
         if let Some(value) = value {
             *target = value;
@@ -91,6 +91,8 @@ pub enum Link {
     Path {
         /// The path to the item.
         path: PathBuf,
+        /// The zero-based row the link points to within the item, if any.
+        row: Option<u32>,
     },
 }
 
@@ -100,9 +102,21 @@ impl Link {
             return Some(Link::Web { url: text });
         }
 
+        if text.starts_with("file://") {
+            let url = lsp::Url::parse(&text).ok()?;
+            let path = url.to_file_path().ok()?;
+            // Language servers point at a line with a fragment like `#L12` or `#L12,5`
+            let row = url
+                .fragment()
+                .and_then(|fragment| fragment.strip_prefix('L')?.split([',', ':']).next())
+                .and_then(|line| line.parse::<u32>().ok())
+                .map(|line| line.saturating_sub(1));
+            return Some(Link::Path { path, row });
+        }
+
         let path = PathBuf::from(text);
         if path.is_absolute() {
-            return Some(Link::Path { path });
+            return Some(Link::Path { path, row: None });
         }
 
         None
@@ -153,6 +167,7 @@ pub async fn parse_markdown_block(
     let mut italic_depth = 0;
     let mut link_url = None;
     let mut current_language = None;
+    let mut code_block_start = None;
     let mut list_stack = Vec::new();
 
     for event in Parser::new_ext(markdown, Options::all()) {
@@ -202,10 +217,16 @@ pub async fn parse_markdown_block(
             }
 
             Event::Code(t) => {
-                text.push_str(t.as_ref());
+                let link = link_url.clone().and_then(|u| Link::identify(u));
+                match &language {
+                    // Inline code in documentation is usually in the documented language
+                    Some(language) if link.is_none() => {
+                        highlight_code(text, highlights, t.as_ref(), language)
+                    }
+                    _ => text.push_str(t.as_ref()),
+                }
                 region_ranges.push(prev_len..text.len());
 
-                let link = link_url.clone().and_then(|u| Link::identify(u));
                 if link.is_some() {
                     highlights.push((
                         prev_len..text.len(),
@@ -228,6 +249,7 @@ pub async fn parse_markdown_block(
 
                 Tag::CodeBlock(kind) => {
                     new_paragraph(text, &mut list_stack);
+                    code_block_start = Some(text.len());
                     current_language = if let CodeBlockKind::Fenced(language) = kind {
                         language_registry
                             .language_for_name(language.as_ref())
@@ -273,7 +295,18 @@ pub async fn parse_markdown_block(
 
             Event::End(tag) => match tag {
                 Tag::Heading(_, _, _) => bold_depth -= 1,
-                Tag::CodeBlock(_) => current_language = None,
+                Tag::CodeBlock(_) => {
+                    current_language = None;
+                    if let Some(start) = code_block_start.take() {
+                        // Don't extend the block's background past its last line
+                        let end = text.trim_end_matches('\n').len().max(start);
+                        region_ranges.push(start..end);
+                        regions.push(ParsedRegion {
+                            code: true,
+                            link: None,
+                        });
+                    }
+                }
                 Tag::Emphasis => italic_depth -= 1,
                 Tag::Strong => bold_depth -= 1,
                 Tag::Link(_, _, _) => link_url = None,
@@ -486,6 +486,10 @@ impl LanguageServer {
                         link_support: Some(true),
                         dynamic_registration: None,
                     }),
+                    declaration: Some(GotoCapability {
+                        link_support: Some(true),
+                        dynamic_registration: None,
+                    }),
                     code_action: Some(CodeActionClientCapabilities {
                         code_action_literal_support: Some(CodeActionLiteralSupport {
                             code_action_kind: CodeActionKindLiteralSupport {
@@ -528,6 +532,85 @@ impl LanguageServer {
                         content_format: Some(vec![MarkupKind::Markdown]),
                         dynamic_registration: None,
                     }),
+                    call_hierarchy: Some(CallHierarchyClientCapabilities {
+                        dynamic_registration: None,
+                    }),
+                    code_lens: Some(CodeLensClientCapabilities {
+                        dynamic_registration: None,
+                    }),
+                    linked_editing_range: Some(LinkedEditingRangeClientCapabilities {
+                        dynamic_registration: None,
+                    }),
+                    selection_range: Some(SelectionRangeClientCapabilities {
+                        dynamic_registration: None,
+                    }),
+                    folding_range: Some(FoldingRangeClientCapabilities {
+                        line_folding_only: Some(true),
+                        ..Default::default()
+                    }),
+                    signature_help: Some(SignatureHelpClientCapabilities {
+                        signature_information: Some(SignatureInformationSettings {
+                            documentation_format: Some(vec![
+                                MarkupKind::Markdown,
+                                MarkupKind::PlainText,
+                            ]),
+                            parameter_information: Some(ParameterInformationSettings {
+                                label_offset_support: Some(true),
+                            }),
+                            active_parameter_support: Some(true),
+                        }),
+                        context_support: Some(true),
+                        dynamic_registration: None,
+                    }),
+                    semantic_tokens: Some(SemanticTokensClientCapabilities {
+                        requests: SemanticTokensClientCapabilitiesRequests {
+                            range: Some(true),
+                            full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                        },
+                        token_types: vec![
+                            SemanticTokenType::NAMESPACE,
+                            SemanticTokenType::TYPE,
+                            SemanticTokenType::CLASS,
+                            SemanticTokenType::ENUM,
+                            SemanticTokenType::INTERFACE,
+                            SemanticTokenType::STRUCT,
+                            SemanticTokenType::TYPE_PARAMETER,
+                            SemanticTokenType::PARAMETER,
+                            SemanticTokenType::VARIABLE,
+                            SemanticTokenType::PROPERTY,
+                            SemanticTokenType::ENUM_MEMBER,
+                            SemanticTokenType::EVENT,
+                            SemanticTokenType::FUNCTION,
+                            SemanticTokenType::METHOD,
+                            SemanticTokenType::MACRO,
+                            SemanticTokenType::KEYWORD,
+                            SemanticTokenType::MODIFIER,
+                            SemanticTokenType::COMMENT,
+                            SemanticTokenType::STRING,
+                            SemanticTokenType::NUMBER,
+                            SemanticTokenType::REGEXP,
+                            SemanticTokenType::OPERATOR,
+                            SemanticTokenType::DECORATOR,
+                        ],
+                        token_modifiers: vec![
+                            SemanticTokenModifier::DECLARATION,
+                            SemanticTokenModifier::DEFINITION,
+                            SemanticTokenModifier::READONLY,
+                            SemanticTokenModifier::STATIC,
+                            SemanticTokenModifier::DEPRECATED,
+                            SemanticTokenModifier::ABSTRACT,
+                            SemanticTokenModifier::ASYNC,
+                            SemanticTokenModifier::MODIFICATION,
+                            SemanticTokenModifier::DOCUMENTATION,
+                            SemanticTokenModifier::DEFAULT_LIBRARY,
+                            SemanticTokenModifier::new("mutable"),
+                        ],
+                        formats: vec![TokenFormat::RELATIVE],
+                        overlapping_token_support: Some(false),
+                        multiline_token_support: Some(false),
+                        augments_syntax_tokens: Some(true),
+                        ..Default::default()
+                    }),
                     inlay_hint: Some(InlayHintClientCapabilities {
                         resolve_support: Some(InlayHintResolveClientCapabilities {
                             properties: vec![
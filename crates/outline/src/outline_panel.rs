@@ -0,0 +1,488 @@
+use anyhow::{anyhow, Result};
+use db::kvp::KEY_VALUE_STORE;
+use editor::{scroll::Autoscroll, Editor, EditorEvent};
+use gpui::{
+    actions, px, Action, AppContext, AsyncWindowContext, EventEmitter, FocusHandle, FocusableView,
+    Model, ScrollHandle, Subscription, Task, View, WeakView,
+};
+use language::{Anchor, Buffer, BufferSnapshot, Point, ToPoint};
+use project::{DocumentSymbol, Project};
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, time::Duration};
+use ui::{prelude::*, ListItem};
+use util::{ResultExt, TryFutureExt};
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    Workspace,
+};
+
+const OUTLINE_PANEL_KEY: &str = "OutlinePanel";
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+actions!(outline_panel, [ToggleFocus]);
+
+pub fn init(cx: &mut AppContext) {
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(|workspace, _: &ToggleFocus, cx| {
+            workspace.toggle_panel_focus::<OutlinePanel>(cx);
+        });
+    })
+    .detach();
+}
+
+/// Lists the symbols of the active editor's buffer as a tree, taken from its language server
+/// or, failing that, from its syntax tree.
+pub struct OutlinePanel {
+    project: Model<Project>,
+    active_editor: Option<ActiveEditor>,
+    /// The symbols, each followed by the symbols nested in it.
+    symbols: Vec<OutlineSymbol>,
+    /// The indices of the symbols that match the filter, or contain one that does.
+    visible_symbols: Vec<usize>,
+    selected_symbol: Option<usize>,
+    filter_editor: View<Editor>,
+    position: DockPosition,
+    width: Option<Pixels>,
+    focus_handle: FocusHandle,
+    scroll_handle: ScrollHandle,
+    pending_serialization: Task<Option<()>>,
+    update_symbols_task: Task<Option<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+struct ActiveEditor {
+    editor: WeakView<Editor>,
+    buffer: Model<Buffer>,
+    _subscription: Subscription,
+}
+
+struct OutlineSymbol {
+    label: SharedString,
+    detail: Option<SharedString>,
+    depth: usize,
+    /// The symbol's whole extent, used to find the symbol containing the cursor.
+    range: Range<Anchor>,
+    /// Where to place the cursor when navigating to the symbol.
+    target: Anchor,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedOutlinePanel {
+    width: Option<Pixels>,
+}
+
+impl OutlinePanel {
+    pub async fn load(
+        workspace: WeakView<Workspace>,
+        mut cx: AsyncWindowContext,
+    ) -> Result<View<Self>> {
+        let serialized_panel = cx
+            .background_executor()
+            .spawn(async move { KEY_VALUE_STORE.read_kvp(OUTLINE_PANEL_KEY) })
+            .await
+            .map_err(|e| anyhow!("Failed to load outline panel: {}", e))
+            .log_err()
+            .flatten()
+            .map(|panel| serde_json::from_str::<SerializedOutlinePanel>(&panel))
+            .transpose()
+            .log_err()
+            .flatten();
+
+        workspace.update(&mut cx, |workspace, cx| {
+            let workspace_view = cx.view().clone();
+            cx.new_view(|cx| {
+                let mut panel = Self::new(workspace, workspace_view, cx);
+                if let Some(serialized_panel) = serialized_panel {
+                    panel.width = serialized_panel.width;
+                }
+                panel
+            })
+        })
+    }
+
+    fn new(
+        workspace: &Workspace,
+        workspace_view: View<Workspace>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let filter_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Filter symbols…", cx);
+            editor
+        });
+        let subscriptions = vec![
+            cx.subscribe(&filter_editor, |this, _, event, cx| {
+                if let EditorEvent::BufferEdited = event {
+                    this.update_visible_symbols(cx);
+                }
+            }),
+            cx.subscribe(&workspace_view, |this, workspace, event, cx| {
+                if let workspace::Event::ActiveItemChanged = event {
+                    this.update_active_editor(workspace, cx);
+                }
+            }),
+        ];
+
+        let mut this = Self {
+            project: workspace.project().clone(),
+            active_editor: None,
+            symbols: Vec::new(),
+            visible_symbols: Vec::new(),
+            selected_symbol: None,
+            filter_editor,
+            position: DockPosition::Right,
+            width: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: ScrollHandle::new(),
+            pending_serialization: Task::ready(None),
+            update_symbols_task: Task::ready(None),
+            _subscriptions: subscriptions,
+        };
+        this.set_active_editor(
+            workspace
+                .active_item(cx)
+                .and_then(|item| item.act_as::<Editor>(cx)),
+            cx,
+        );
+        this
+    }
+
+    fn serialize(&mut self, cx: &mut ViewContext<Self>) {
+        let width = self.width;
+        self.pending_serialization = cx.background_executor().spawn(
+            async move {
+                KEY_VALUE_STORE
+                    .write_kvp(
+                        OUTLINE_PANEL_KEY.into(),
+                        serde_json::to_string(&SerializedOutlinePanel { width })?,
+                    )
+                    .await?;
+                anyhow::Ok(())
+            }
+            .log_err(),
+        );
+    }
+
+    fn update_active_editor(&mut self, workspace: View<Workspace>, cx: &mut ViewContext<Self>) {
+        let editor = workspace
+            .read(cx)
+            .active_item(cx)
+            .and_then(|item| item.act_as::<Editor>(cx));
+        self.set_active_editor(editor, cx);
+    }
+
+    fn set_active_editor(&mut self, editor: Option<View<Editor>>, cx: &mut ViewContext<Self>) {
+        let editor_changed = match (&editor, &self.active_editor) {
+            (Some(editor), Some(active_editor)) => editor.downgrade() != active_editor.editor,
+            (None, None) => false,
+            _ => true,
+        };
+        if !editor_changed {
+            return;
+        }
+
+        // Only editors for a single buffer have an outline
+        self.active_editor = editor.and_then(|editor| {
+            let buffer = editor.read(cx).buffer().read(cx).as_singleton()?;
+            Some(ActiveEditor {
+                editor: editor.downgrade(),
+                buffer,
+                _subscription: cx.subscribe(&editor, |this, _, event, cx| match event {
+                    EditorEvent::BufferEdited | EditorEvent::Reparsed => {
+                        this.update_symbols(true, cx)
+                    }
+                    EditorEvent::SelectionsChanged { .. } => this.reveal_cursor(cx),
+                    _ => {}
+                }),
+            })
+        });
+        self.symbols.clear();
+        self.visible_symbols.clear();
+        self.selected_symbol = None;
+        self.update_symbols(false, cx);
+        cx.notify();
+    }
+
+    fn update_symbols(&mut self, debounce: bool, cx: &mut ViewContext<Self>) {
+        let Some(buffer) = self
+            .active_editor
+            .as_ref()
+            .map(|active_editor| active_editor.buffer.clone())
+        else {
+            self.update_symbols_task = Task::ready(None);
+            return;
+        };
+        let project = self.project.clone();
+        self.update_symbols_task = cx.spawn(|this, mut cx| {
+            async move {
+                if debounce {
+                    cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+                }
+                let document_symbols = project
+                    .update(&mut cx, |project, cx| project.document_symbols(&buffer, cx))?
+                    .await
+                    .log_err()
+                    .unwrap_or_default();
+                this.update(&mut cx, |this, cx| {
+                    let snapshot = buffer.read(cx).snapshot();
+                    this.symbols = if document_symbols.is_empty() {
+                        syntax_symbols(&snapshot)
+                    } else {
+                        let mut symbols = Vec::new();
+                        flatten_document_symbols(document_symbols, 0, &mut symbols);
+                        symbols
+                    };
+                    this.update_visible_symbols(cx);
+                    this.reveal_cursor(cx);
+                })
+            }
+            .log_err()
+        });
+    }
+
+    fn update_visible_symbols(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.filter_editor.read(cx).text(cx).to_lowercase();
+        let mut visible = vec![query.is_empty(); self.symbols.len()];
+        if !query.is_empty() {
+            for ix in 0..self.symbols.len() {
+                if !self.symbols[ix].label.to_lowercase().contains(&query) {
+                    continue;
+                }
+                // Keep the symbols containing a match, so that it's shown in context
+                visible[ix] = true;
+                let mut depth = self.symbols[ix].depth;
+                for ancestor_ix in (0..ix).rev() {
+                    if depth == 0 {
+                        break;
+                    }
+                    if self.symbols[ancestor_ix].depth < depth {
+                        depth = self.symbols[ancestor_ix].depth;
+                        visible[ancestor_ix] = true;
+                    }
+                }
+            }
+        }
+        self.visible_symbols = (0..self.symbols.len()).filter(|ix| visible[*ix]).collect();
+        cx.notify();
+    }
+
+    /// Selects the innermost symbol containing the active editor's cursor.
+    fn reveal_cursor(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(active_editor) = self.active_editor.as_ref() else {
+            return;
+        };
+        let Some(editor) = active_editor.editor.upgrade() else {
+            return;
+        };
+        let cursor = editor.read(cx).selections.newest::<Point>(cx).head();
+        let snapshot = active_editor.buffer.read(cx).snapshot();
+        let symbol_ix = self.symbols.iter().rposition(|symbol| {
+            symbol.range.start.to_point(&snapshot) <= cursor
+                && cursor <= symbol.range.end.to_point(&snapshot)
+        });
+        if let Some(symbol_ix) = symbol_ix {
+            self.select(symbol_ix, cx);
+        }
+    }
+
+    fn select(&mut self, symbol_ix: usize, cx: &mut ViewContext<Self>) {
+        self.selected_symbol = Some(symbol_ix);
+        if let Some(ix) = self.visible_symbols.iter().position(|ix| *ix == symbol_ix) {
+            self.scroll_handle.scroll_to_item(ix);
+        }
+        cx.notify();
+    }
+
+    fn selected_visible_ix(&self) -> Option<usize> {
+        let selected_symbol = self.selected_symbol?;
+        self.visible_symbols
+            .iter()
+            .position(|ix| *ix == selected_symbol)
+    }
+
+    fn select_next(&mut self, _: &menu::SelectNext, cx: &mut ViewContext<Self>) {
+        let count = self.visible_symbols.len();
+        if count > 0 {
+            let ix = self.selected_visible_ix().map_or(0, |ix| (ix + 1) % count);
+            self.select(self.visible_symbols[ix], cx);
+        }
+    }
+
+    fn select_prev(&mut self, _: &menu::SelectPrev, cx: &mut ViewContext<Self>) {
+        let count = self.visible_symbols.len();
+        if count > 0 {
+            let ix = self
+                .selected_visible_ix()
+                .map_or(count - 1, |ix| (ix + count - 1) % count);
+            self.select(self.visible_symbols[ix], cx);
+        }
+    }
+
+    fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        if self.selected_visible_ix().is_some() {
+            if let Some(symbol_ix) = self.selected_symbol {
+                self.open_symbol(symbol_ix, true, cx);
+            }
+        }
+    }
+
+    fn open_symbol(&mut self, symbol_ix: usize, focus_editor: bool, cx: &mut ViewContext<Self>) {
+        let Some(active_editor) = self.active_editor.as_ref() else {
+            return;
+        };
+        let Some(editor) = active_editor.editor.upgrade() else {
+            return;
+        };
+        let target = self.symbols[symbol_ix]
+            .target
+            .to_point(&active_editor.buffer.read(cx).snapshot());
+        editor.update(cx, |editor, cx| {
+            editor.change_selections(Some(Autoscroll::center()), cx, |s| {
+                s.select_ranges([target..target])
+            });
+            if focus_editor {
+                editor.focus(cx);
+            }
+        });
+    }
+
+    fn render_symbol(
+        &self,
+        symbol_ix: usize,
+        symbol: &OutlineSymbol,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        ListItem::new(("outline-symbol", symbol_ix))
+            .indent_level(symbol.depth)
+            .indent_step_size(px(12.))
+            .selected(self.selected_symbol == Some(symbol_ix))
+            .on_click(cx.listener(move |this, _, cx| {
+                this.selected_symbol = Some(symbol_ix);
+                this.open_symbol(symbol_ix, false, cx);
+                cx.notify();
+            }))
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(Label::new(symbol.label.clone()))
+                    .children(symbol.detail.clone().map(|detail| {
+                        Label::new(detail)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                    })),
+            )
+    }
+}
+
+fn flatten_document_symbols(
+    document_symbols: Vec<DocumentSymbol>,
+    depth: usize,
+    symbols: &mut Vec<OutlineSymbol>,
+) {
+    for symbol in document_symbols {
+        symbols.push(OutlineSymbol {
+            label: symbol.name.into(),
+            detail: symbol.detail.map(Into::into),
+            depth,
+            range: symbol.range,
+            target: symbol.selection_range.start,
+        });
+        flatten_document_symbols(symbol.children, depth + 1, symbols);
+    }
+}
+
+fn syntax_symbols(snapshot: &BufferSnapshot) -> Vec<OutlineSymbol> {
+    let Some(outline) = snapshot.outline(None) else {
+        return Vec::new();
+    };
+    outline
+        .items
+        .into_iter()
+        .map(|item| OutlineSymbol {
+            label: item.text.into(),
+            detail: None,
+            depth: item.depth,
+            target: item.range.start,
+            range: item.range,
+        })
+        .collect()
+}
+
+impl EventEmitter<PanelEvent> for OutlinePanel {}
+
+impl FocusableView for OutlinePanel {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Panel for OutlinePanel {
+    fn persistent_name() -> &'static str {
+        "OutlinePanel"
+    }
+
+    fn position(&self, _: &WindowContext) -> DockPosition {
+        self.position
+    }
+
+    fn position_is_valid(&self, position: DockPosition) -> bool {
+        matches!(position, DockPosition::Left | DockPosition::Right)
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        self.position = position;
+        cx.notify();
+    }
+
+    fn size(&self, _: &WindowContext) -> Pixels {
+        self.width.unwrap_or(px(240.))
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.width = size;
+        self.serialize(cx);
+        cx.notify();
+    }
+
+    fn icon(&self, _: &WindowContext) -> Option<IconName> {
+        Some(IconName::Hash)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("Outline Panel")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleFocus)
+    }
+}
+
+impl Render for OutlinePanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let symbols = self
+            .visible_symbols
+            .iter()
+            .map(|ix| {
+                self.render_symbol(*ix, &self.symbols[*ix], cx)
+                    .into_any_element()
+            })
+            .collect::<Vec<_>>();
+
+        v_flex()
+            .key_context("OutlinePanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::confirm))
+            .size_full()
+            .child(h_flex().px_2().py_1().child(self.filter_editor.clone()))
+            .child(
+                v_flex()
+                    .id("outline-symbols")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .children(symbols),
+            )
+    }
+}
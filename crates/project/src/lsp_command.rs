@@ -1,7 +1,9 @@
 use crate::{
-    DocumentHighlight, Hover, HoverBlock, HoverBlockKind, InlayHint, InlayHintLabel,
-    InlayHintLabelPart, InlayHintLabelPartTooltip, InlayHintTooltip, Location, LocationLink,
-    MarkupContent, Project, ProjectTransaction, ResolveState,
+    CallHierarchyCall, CallHierarchyItem, CodeLens, DocumentHighlight, DocumentSymbol,
+    FunctionSymbol, Hover, HoverBlock, HoverBlockKind, InlayHint, InlayHintLabel,
+    InlayHintLabelPart, InlayHintLabelPartTooltip, InlayHintTooltip, LinkedEditingRanges, Location,
+    LocationLink, MarkupContent, Project, ProjectTransaction, ResolveState, SignatureHelp,
+    SignatureInformation,
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -13,15 +15,17 @@ use language::{
     point_from_lsp, point_to_lsp, prepare_completion_documentation,
     proto::{deserialize_anchor, deserialize_version, serialize_anchor, serialize_version},
     range_from_lsp, range_to_lsp, Anchor, Bias, Buffer, BufferSnapshot, CachedLspAdapter, CharKind,
-    CodeAction, Completion, OffsetRangeExt, PointUtf16, ToOffset, ToPointUtf16, Transaction,
+    CodeAction, Completion, OffsetRangeExt, Point, PointUtf16, ToOffset, ToPointUtf16, Transaction,
     Unclipped,
 };
 use lsp::{
     CompletionListItemDefaultsEditRange, DocumentHighlightKind, LanguageServer, LanguageServerId,
     OneOf, ServerCapabilities,
 };
-use std::{cmp::Reverse, ops::Range, path::Path, sync::Arc};
+use regex::Regex;
+use std::{cmp::Reverse, mem, ops::Range, path::Path, sync::Arc};
 use text::LineEnding;
+use util::ResultExt;
 
 pub fn lsp_formatting_options(tab_size: u32) -> lsp::FormattingOptions {
     lsp::FormattingOptions {
@@ -101,14 +105,46 @@ pub(crate) struct GetDefinition {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetDeclaration {
+    pub position: PointUtf16,
+}
+
 pub(crate) struct GetTypeDefinition {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetImplementation {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetEnclosingSymbols {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetFunctionSymbol {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetDocumentSymbols;
+
+pub(crate) struct GetCodeLenses;
+
 pub(crate) struct GetReferences {
     pub position: PointUtf16,
 }
 
+pub(crate) struct PrepareCallHierarchy {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetIncomingCalls {
+    pub item: lsp::CallHierarchyItem,
+}
+
+pub(crate) struct GetOutgoingCalls {
+    pub item: lsp::CallHierarchyItem,
+}
+
 pub(crate) struct GetDocumentHighlights {
     pub position: PointUtf16,
 }
@@ -117,6 +153,22 @@ pub(crate) struct GetHover {
     pub position: PointUtf16,
 }
 
+pub(crate) struct GetSignatureHelp {
+    pub position: PointUtf16,
+    pub trigger_character: Option<String>,
+    pub is_retrigger: bool,
+}
+
+pub(crate) struct GetLinkedEditingRanges {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetSelectionRanges {
+    pub position: PointUtf16,
+}
+
+pub(crate) struct GetFoldingRanges;
+
 pub(crate) struct GetCompletions {
     pub position: PointUtf16,
 }
@@ -592,213 +644,29 @@ impl LspCommand for GetTypeDefinition {
     }
 }
 
-fn language_server_for_buffer(
-    project: &Model<Project>,
-    buffer: &Model<Buffer>,
-    server_id: LanguageServerId,
-    cx: &mut AsyncAppContext,
-) -> Result<(Arc<CachedLspAdapter>, Arc<LanguageServer>)> {
-    project
-        .update(cx, |project, cx| {
-            project
-                .language_server_for_buffer(buffer.read(cx), server_id, cx)
-                .map(|(adapter, server)| (adapter.clone(), server.clone()))
-        })?
-        .ok_or_else(|| anyhow!("no language server found for buffer"))
-}
-
-async fn location_links_from_proto(
-    proto_links: Vec<proto::LocationLink>,
-    project: Model<Project>,
-    mut cx: AsyncAppContext,
-) -> Result<Vec<LocationLink>> {
-    let mut links = Vec::new();
-
-    for link in proto_links {
-        let origin = match link.origin {
-            Some(origin) => {
-                let buffer = project
-                    .update(&mut cx, |this, cx| {
-                        this.wait_for_remote_buffer(origin.buffer_id, cx)
-                    })?
-                    .await?;
-                let start = origin
-                    .start
-                    .and_then(deserialize_anchor)
-                    .ok_or_else(|| anyhow!("missing origin start"))?;
-                let end = origin
-                    .end
-                    .and_then(deserialize_anchor)
-                    .ok_or_else(|| anyhow!("missing origin end"))?;
-                buffer
-                    .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
-                    .await?;
-                Some(Location {
-                    buffer,
-                    range: start..end,
-                })
-            }
-            None => None,
-        };
-
-        let target = link.target.ok_or_else(|| anyhow!("missing target"))?;
-        let buffer = project
-            .update(&mut cx, |this, cx| {
-                this.wait_for_remote_buffer(target.buffer_id, cx)
-            })?
-            .await?;
-        let start = target
-            .start
-            .and_then(deserialize_anchor)
-            .ok_or_else(|| anyhow!("missing target start"))?;
-        let end = target
-            .end
-            .and_then(deserialize_anchor)
-            .ok_or_else(|| anyhow!("missing target end"))?;
-        buffer
-            .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
-            .await?;
-        let target = Location {
-            buffer,
-            range: start..end,
-        };
-
-        links.push(LocationLink { origin, target })
-    }
-
-    Ok(links)
-}
-
-async fn location_links_from_lsp(
-    message: Option<lsp::GotoDefinitionResponse>,
-    project: Model<Project>,
-    buffer: Model<Buffer>,
-    server_id: LanguageServerId,
-    mut cx: AsyncAppContext,
-) -> Result<Vec<LocationLink>> {
-    let message = match message {
-        Some(message) => message,
-        None => return Ok(Vec::new()),
-    };
-
-    let mut unresolved_links = Vec::new();
-    match message {
-        lsp::GotoDefinitionResponse::Scalar(loc) => {
-            unresolved_links.push((None, loc.uri, loc.range));
-        }
-
-        lsp::GotoDefinitionResponse::Array(locs) => {
-            unresolved_links.extend(locs.into_iter().map(|l| (None, l.uri, l.range)));
-        }
+#[async_trait(?Send)]
+impl LspCommand for GetDeclaration {
+    type Response = Vec<LocationLink>;
+    type LspRequest = lsp::request::GotoDeclaration;
+    type ProtoRequest = proto::GetDeclaration;
 
-        lsp::GotoDefinitionResponse::Link(links) => {
-            unresolved_links.extend(links.into_iter().map(|l| {
-                (
-                    l.origin_selection_range,
-                    l.target_uri,
-                    l.target_selection_range,
-                )
-            }));
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.declaration_provider {
+            None => false,
+            Some(lsp::DeclarationCapability::Simple(false)) => false,
+            _ => true,
         }
     }
 
-    let (lsp_adapter, language_server) =
-        language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
-    let mut definitions = Vec::new();
-    for (origin_range, target_uri, target_range) in unresolved_links {
-        let target_buffer_handle = project
-            .update(&mut cx, |this, cx| {
-                this.open_local_buffer_via_lsp(
-                    target_uri,
-                    language_server.server_id(),
-                    lsp_adapter.name.clone(),
-                    cx,
-                )
-            })?
-            .await?;
-
-        cx.update(|cx| {
-            let origin_location = origin_range.map(|origin_range| {
-                let origin_buffer = buffer.read(cx);
-                let origin_start =
-                    origin_buffer.clip_point_utf16(point_from_lsp(origin_range.start), Bias::Left);
-                let origin_end =
-                    origin_buffer.clip_point_utf16(point_from_lsp(origin_range.end), Bias::Left);
-                Location {
-                    buffer: buffer.clone(),
-                    range: origin_buffer.anchor_after(origin_start)
-                        ..origin_buffer.anchor_before(origin_end),
-                }
-            });
-
-            let target_buffer = target_buffer_handle.read(cx);
-            let target_start =
-                target_buffer.clip_point_utf16(point_from_lsp(target_range.start), Bias::Left);
-            let target_end =
-                target_buffer.clip_point_utf16(point_from_lsp(target_range.end), Bias::Left);
-            let target_location = Location {
-                buffer: target_buffer_handle,
-                range: target_buffer.anchor_after(target_start)
-                    ..target_buffer.anchor_before(target_end),
-            };
-
-            definitions.push(LocationLink {
-                origin: origin_location,
-                target: target_location,
-            })
-        })?;
-    }
-    Ok(definitions)
-}
-
-fn location_links_to_proto(
-    links: Vec<LocationLink>,
-    project: &mut Project,
-    peer_id: PeerId,
-    cx: &mut AppContext,
-) -> Vec<proto::LocationLink> {
-    links
-        .into_iter()
-        .map(|definition| {
-            let origin = definition.origin.map(|origin| {
-                let buffer_id = project.create_buffer_for_peer(&origin.buffer, peer_id, cx);
-                proto::Location {
-                    start: Some(serialize_anchor(&origin.range.start)),
-                    end: Some(serialize_anchor(&origin.range.end)),
-                    buffer_id,
-                }
-            });
-
-            let buffer_id = project.create_buffer_for_peer(&definition.target.buffer, peer_id, cx);
-            let target = proto::Location {
-                start: Some(serialize_anchor(&definition.target.range.start)),
-                end: Some(serialize_anchor(&definition.target.range.end)),
-                buffer_id,
-            };
-
-            proto::LocationLink {
-                origin,
-                target: Some(target),
-            }
-        })
-        .collect()
-}
-
-#[async_trait(?Send)]
-impl LspCommand for GetReferences {
-    type Response = Vec<Location>;
-    type LspRequest = lsp::request::References;
-    type ProtoRequest = proto::GetReferences;
-
     fn to_lsp(
         &self,
         path: &Path,
         _: &Buffer,
         _: &Arc<LanguageServer>,
         _: &AppContext,
-    ) -> lsp::ReferenceParams {
-        lsp::ReferenceParams {
-            text_document_position: lsp::TextDocumentPositionParams {
+    ) -> lsp::request::GotoDeclarationParams {
+        lsp::request::GotoDeclarationParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
                 text_document: lsp::TextDocumentIdentifier {
                     uri: lsp::Url::from_file_path(path).unwrap(),
                 },
@@ -806,69 +674,2218 @@ impl LspCommand for GetReferences {
             },
             work_done_progress_params: Default::default(),
             partial_result_params: Default::default(),
-            context: lsp::ReferenceContext {
-                include_declaration: true,
-            },
         }
     }
 
     async fn response_from_lsp(
         self,
-        locations: Option<Vec<lsp::Location>>,
+        message: Option<lsp::request::GotoDeclarationResponse>,
         project: Model<Project>,
         buffer: Model<Buffer>,
         server_id: LanguageServerId,
-        mut cx: AsyncAppContext,
-    ) -> Result<Vec<Location>> {
-        let mut references = Vec::new();
-        let (lsp_adapter, language_server) =
-            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_lsp(message, project, buffer, server_id, cx).await
+    }
 
-        if let Some(locations) = locations {
-            for lsp_location in locations {
-                let target_buffer_handle = project
-                    .update(&mut cx, |this, cx| {
-                        this.open_local_buffer_via_lsp(
-                            lsp_location.uri,
-                            language_server.server_id(),
-                            lsp_adapter.name.clone(),
-                            cx,
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDeclaration {
+        proto::GetDeclaration {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetDeclaration,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<LocationLink>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetDeclarationResponse {
+        let links = location_links_to_proto(response, project, peer_id, cx);
+        proto::GetDeclarationResponse { links }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetDeclarationResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_proto(message.links, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetDeclaration) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetImplementation {
+    type Response = Vec<LocationLink>;
+    type LspRequest = lsp::request::GotoImplementation;
+    type ProtoRequest = proto::GetImplementation;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.implementation_provider {
+            None => false,
+            Some(lsp::ImplementationProviderCapability::Simple(false)) => false,
+            _ => true,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::request::GotoImplementationParams {
+        lsp::request::GotoImplementationParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::request::GotoImplementationResponse>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_lsp(message, project, buffer, server_id, cx).await
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetImplementation {
+        proto::GetImplementation {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetImplementation,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<LocationLink>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetImplementationResponse {
+        let links = location_links_to_proto(response, project, peer_id, cx);
+        proto::GetImplementationResponse { links }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetImplementationResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        cx: AsyncAppContext,
+    ) -> Result<Vec<LocationLink>> {
+        location_links_from_proto(message.links, project, cx).await
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetImplementation) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetEnclosingSymbols {
+    type Response = Vec<String>;
+    type LspRequest = lsp::request::DocumentSymbolRequest;
+    type ProtoRequest = proto::GetEnclosingSymbols;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.document_symbol_provider {
+            None => false,
+            Some(OneOf::Left(supported)) => *supported,
+            Some(OneOf::Right(_)) => true,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::DocumentSymbolParams {
+        lsp::DocumentSymbolParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::DocumentSymbolResponse>,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: LanguageServerId,
+        _: AsyncAppContext,
+    ) -> Result<Vec<String>> {
+        let position = point_to_lsp(self.position);
+        let contains = |range: &lsp::Range| range.start <= position && position <= range.end;
+
+        let mut names = Vec::new();
+        match message {
+            None => {}
+            Some(lsp::DocumentSymbolResponse::Nested(mut symbols)) => {
+                while let Some(symbol) = symbols.into_iter().find(|symbol| contains(&symbol.range))
+                {
+                    names.push(symbol.name);
+                    symbols = symbol.children.unwrap_or_default();
+                }
+            }
+            Some(lsp::DocumentSymbolResponse::Flat(mut symbols)) => {
+                // Flat symbols don't describe their nesting, so order them from the
+                // outermost range inwards instead.
+                symbols.retain(|symbol| contains(&symbol.location.range));
+                symbols.sort_by_key(|symbol| {
+                    (
+                        symbol.location.range.start,
+                        Reverse(symbol.location.range.end),
+                    )
+                });
+                names.extend(symbols.into_iter().map(|symbol| symbol.name));
+            }
+        }
+        Ok(names)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetEnclosingSymbols {
+        proto::GetEnclosingSymbols {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetEnclosingSymbols,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<String>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetEnclosingSymbolsResponse {
+        proto::GetEnclosingSymbolsResponse { names: response }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetEnclosingSymbolsResponse,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: AsyncAppContext,
+    ) -> Result<Vec<String>> {
+        Ok(message.names)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetEnclosingSymbols) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetFunctionSymbol {
+    type Response = Option<FunctionSymbol>;
+    type LspRequest = lsp::request::DocumentSymbolRequest;
+    type ProtoRequest = proto::GetFunctionSymbol;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.document_symbol_provider {
+            None => false,
+            Some(OneOf::Left(supported)) => *supported,
+            Some(OneOf::Right(_)) => true,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::DocumentSymbolParams {
+        lsp::DocumentSymbolParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::DocumentSymbolResponse>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Option<FunctionSymbol>> {
+        let position = point_to_lsp(self.position);
+        let contains = |range: &lsp::Range| range.start <= position && position <= range.end;
+        let is_function = |kind: lsp::SymbolKind| {
+            matches!(
+                kind,
+                lsp::SymbolKind::FUNCTION | lsp::SymbolKind::METHOD | lsp::SymbolKind::CONSTRUCTOR
+            )
+        };
+
+        // The innermost function containing the position, along with the signature the
+        // server reported for it, if any.
+        let mut function = None;
+        match message {
+            None => {}
+            Some(lsp::DocumentSymbolResponse::Nested(mut symbols)) => {
+                while let Some(symbol) = symbols.into_iter().find(|symbol| contains(&symbol.range))
+                {
+                    if is_function(symbol.kind) {
+                        function = Some((symbol.name, symbol.range, symbol.detail));
+                    }
+                    symbols = symbol.children.unwrap_or_default();
+                }
+            }
+            Some(lsp::DocumentSymbolResponse::Flat(symbols)) => {
+                function = symbols
+                    .into_iter()
+                    .filter(|symbol| is_function(symbol.kind) && contains(&symbol.location.range))
+                    .max_by_key(|symbol| {
+                        (
+                            symbol.location.range.start,
+                            Reverse(symbol.location.range.end),
                         )
-                    })?
-                    .await?;
+                    })
+                    .map(|symbol| (symbol.name, symbol.location.range, None));
+            }
+        }
+        let Some((name, range, detail)) = function else {
+            return Ok(None);
+        };
+
+        buffer.update(&mut cx, |buffer, _| {
+            let start = buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+            let end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+            // Servers don't always include the signature in the symbol's detail, so fall
+            // back to reading it from the declaration itself.
+            let signature = detail
+                .filter(|detail| detail.contains('('))
+                .unwrap_or_else(|| {
+                    let text = buffer.text_for_range(start..end).collect::<String>();
+                    match text.find(&name) {
+                        Some(ix) => text[ix + name.len()..].to_string(),
+                        None => text,
+                    }
+                });
+            Some(FunctionSymbol {
+                parameters: parameter_names(&signature),
+                name,
+                range: buffer.anchor_after(start)..buffer.anchor_before(end),
+            })
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetFunctionSymbol {
+        proto::GetFunctionSymbol {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetFunctionSymbol,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Option<FunctionSymbol>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetFunctionSymbolResponse {
+        proto::GetFunctionSymbolResponse {
+            symbol: response.map(|symbol| proto::FunctionSymbol {
+                name: symbol.name,
+                start: Some(serialize_anchor(&symbol.range.start)),
+                end: Some(serialize_anchor(&symbol.range.end)),
+                parameters: symbol.parameters,
+            }),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetFunctionSymbolResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Option<FunctionSymbol>> {
+        let Some(symbol) = message.symbol else {
+            return Ok(None);
+        };
+        let start = symbol
+            .start
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("missing symbol start"))?;
+        let end = symbol
+            .end
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("missing symbol end"))?;
+        buffer
+            .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+            .await?;
+        Ok(Some(FunctionSymbol {
+            name: symbol.name,
+            range: start..end,
+            parameters: symbol.parameters,
+        }))
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetFunctionSymbol) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetDocumentSymbols {
+    type Response = Vec<DocumentSymbol>;
+    type LspRequest = lsp::request::DocumentSymbolRequest;
+    type ProtoRequest = proto::GetDocumentSymbols;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.document_symbol_provider {
+            None => false,
+            Some(OneOf::Left(supported)) => *supported,
+            Some(OneOf::Right(_)) => true,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::DocumentSymbolParams {
+        lsp::DocumentSymbolParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::DocumentSymbolResponse>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentSymbol>> {
+        buffer.update(&mut cx, |buffer, _| match message {
+            None => Vec::new(),
+            Some(lsp::DocumentSymbolResponse::Nested(symbols)) => symbols
+                .into_iter()
+                .map(|symbol| document_symbol_from_lsp(symbol, buffer))
+                .collect(),
+            Some(lsp::DocumentSymbolResponse::Flat(mut symbols)) => {
+                // Flat symbols don't describe their nesting, so nest each symbol in the
+                // innermost one whose range contains it.
+                symbols.sort_by_key(|symbol| {
+                    (
+                        symbol.location.range.start,
+                        Reverse(symbol.location.range.end),
+                    )
+                });
+                let mut roots = Vec::new();
+                let mut stack: Vec<(lsp::Range, DocumentSymbol)> = Vec::new();
+                for symbol in symbols {
+                    let range = symbol.location.range;
+                    while stack
+                        .last()
+                        .map_or(false, |(parent_range, _)| parent_range.end < range.end)
+                    {
+                        pop_document_symbol(&mut stack, &mut roots);
+                    }
+                    let anchor_range = anchor_range_from_lsp(range, buffer);
+                    stack.push((
+                        range,
+                        DocumentSymbol {
+                            name: symbol.name,
+                            kind: symbol.kind,
+                            detail: None,
+                            range: anchor_range.clone(),
+                            selection_range: anchor_range,
+                            children: Vec::new(),
+                        },
+                    ));
+                }
+                while !stack.is_empty() {
+                    pop_document_symbol(&mut stack, &mut roots);
+                }
+                roots
+            }
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDocumentSymbols {
+        proto::GetDocumentSymbols {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetDocumentSymbols,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self)
+    }
+
+    fn response_to_proto(
+        response: Vec<DocumentSymbol>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetDocumentSymbolsResponse {
+        proto::GetDocumentSymbolsResponse {
+            symbols: response.into_iter().map(document_symbol_to_proto).collect(),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetDocumentSymbolsResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentSymbol>> {
+        let mut anchors = Vec::new();
+        let symbols = message
+            .symbols
+            .into_iter()
+            .map(|symbol| document_symbol_from_proto(symbol, &mut anchors))
+            .collect::<Result<Vec<_>>>()?;
+        buffer
+            .update(&mut cx, |buffer, _| buffer.wait_for_anchors(anchors))?
+            .await?;
+        Ok(symbols)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetDocumentSymbols) -> u64 {
+        message.buffer_id
+    }
+}
+
+/// Moves the innermost symbol being nested into its parent's children, or into the roots if
+/// it has no parent.
+fn pop_document_symbol(
+    stack: &mut Vec<(lsp::Range, DocumentSymbol)>,
+    roots: &mut Vec<DocumentSymbol>,
+) {
+    if let Some((_, symbol)) = stack.pop() {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(symbol),
+            None => roots.push(symbol),
+        }
+    }
+}
+
+fn anchor_range_from_lsp(range: lsp::Range, buffer: &Buffer) -> Range<Anchor> {
+    let start = buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+    let end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+    buffer.anchor_after(start)..buffer.anchor_before(end)
+}
+
+fn document_symbol_from_lsp(symbol: lsp::DocumentSymbol, buffer: &Buffer) -> DocumentSymbol {
+    DocumentSymbol {
+        name: symbol.name,
+        kind: symbol.kind,
+        detail: symbol.detail,
+        range: anchor_range_from_lsp(symbol.range, buffer),
+        selection_range: anchor_range_from_lsp(symbol.selection_range, buffer),
+        children: symbol
+            .children
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| document_symbol_from_lsp(child, buffer))
+            .collect(),
+    }
+}
+
+fn document_symbol_to_proto(symbol: DocumentSymbol) -> proto::DocumentSymbol {
+    proto::DocumentSymbol {
+        name: symbol.name,
+        kind: unsafe { mem::transmute(symbol.kind) },
+        detail: symbol.detail,
+        start: Some(serialize_anchor(&symbol.range.start)),
+        end: Some(serialize_anchor(&symbol.range.end)),
+        selection_start: Some(serialize_anchor(&symbol.selection_range.start)),
+        selection_end: Some(serialize_anchor(&symbol.selection_range.end)),
+        children: symbol
+            .children
+            .into_iter()
+            .map(document_symbol_to_proto)
+            .collect(),
+    }
+}
+
+/// Deserializes a symbol and its children, collecting the anchors they refer to so that they
+/// can be waited for.
+fn document_symbol_from_proto(
+    symbol: proto::DocumentSymbol,
+    anchors: &mut Vec<Anchor>,
+) -> Result<DocumentSymbol> {
+    let mut deserialize = |anchor: Option<proto::Anchor>| {
+        let anchor = anchor
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid document symbol range"))?;
+        anchors.push(anchor);
+        anyhow::Ok(anchor)
+    };
+    let range = deserialize(symbol.start)?..deserialize(symbol.end)?;
+    let selection_range = deserialize(symbol.selection_start)?..deserialize(symbol.selection_end)?;
+    Ok(DocumentSymbol {
+        name: symbol.name,
+        kind: unsafe { mem::transmute(symbol.kind) },
+        detail: symbol.detail,
+        range,
+        selection_range,
+        children: symbol
+            .children
+            .into_iter()
+            .map(|child| document_symbol_from_proto(child, anchors))
+            .collect::<Result<_>>()?,
+    })
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetCodeLenses {
+    type Response = Vec<CodeLens>;
+    type LspRequest = lsp::request::CodeLensRequest;
+    type ProtoRequest = proto::GetCodeLenses;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.code_lens_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CodeLensParams {
+        lsp::CodeLensParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lenses: Option<Vec<lsp::CodeLens>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        let (_, language_server) =
+            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+        let resolve = language_server
+            .capabilities()
+            .code_lens_provider
+            .as_ref()
+            .and_then(|options| options.resolve_provider)
+            .unwrap_or(false);
+
+        // Servers may leave out the commands until each lens is resolved, since they can be
+        // expensive to compute (e.g. reference counts).
+        let lenses = future::join_all(lenses.unwrap_or_default().into_iter().map(|lens| {
+            let language_server = language_server.clone();
+            async move {
+                if lens.command.is_some() || !resolve {
+                    return Some(lens);
+                }
+                language_server
+                    .request::<lsp::request::CodeLensResolve>(lens)
+                    .await
+                    .log_err()
+            }
+        }))
+        .await;
+
+        buffer.update(&mut cx, |buffer, _| {
+            lenses
+                .into_iter()
+                .flatten()
+                .filter(|lens| lens.command.is_some())
+                .map(|lens| CodeLens {
+                    server_id,
+                    range: anchor_range_from_lsp(lens.range, buffer),
+                    lsp_lens: lens,
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetCodeLenses {
+        proto::GetCodeLenses {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetCodeLenses,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self)
+    }
+
+    fn response_to_proto(
+        response: Vec<CodeLens>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetCodeLensesResponse {
+        proto::GetCodeLensesResponse {
+            lenses: response.iter().map(code_lens_to_proto).collect(),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetCodeLensesResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CodeLens>> {
+        let lenses = message
+            .lenses
+            .into_iter()
+            .map(code_lens_from_proto)
+            .collect::<Result<Vec<_>>>()?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_anchors(
+                    lenses
+                        .iter()
+                        .flat_map(|lens| [lens.range.start, lens.range.end]),
+                )
+            })?
+            .await?;
+        Ok(lenses)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetCodeLenses) -> u64 {
+        message.buffer_id
+    }
+}
+
+pub(crate) fn code_lens_to_proto(lens: &CodeLens) -> proto::CodeLens {
+    proto::CodeLens {
+        server_id: lens.server_id.0 as u64,
+        start: Some(serialize_anchor(&lens.range.start)),
+        end: Some(serialize_anchor(&lens.range.end)),
+        lsp_lens: serde_json::to_vec(&lens.lsp_lens).unwrap(),
+    }
+}
+
+pub(crate) fn code_lens_from_proto(lens: proto::CodeLens) -> Result<CodeLens> {
+    let start = lens
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid code lens start"))?;
+    let end = lens
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid code lens end"))?;
+    Ok(CodeLens {
+        server_id: LanguageServerId(lens.server_id as usize),
+        range: start..end,
+        lsp_lens: serde_json::from_slice(&lens.lsp_lens)?,
+    })
+}
+
+/// Returns the names of the parameters in the first parameter list of `signature`,
+/// leaving out receivers like `self`. Names are taken from before any type annotation
+/// (`name: Type`), or otherwise from the end of the parameter (`Type name`).
+fn parameter_names(signature: &str) -> Vec<String> {
+    let Some(start) = signature.find('(') else {
+        return Vec::new();
+    };
+
+    let mut parameters = Vec::new();
+    let mut depth = 0;
+    let mut parameter_start = start + 1;
+    let mut previous = '(';
+    for (ix, character) in signature.char_indices().skip_while(|(ix, _)| *ix <= start) {
+        match character {
+            // The arrow of a return type isn't a closing bracket
+            '>' if previous == '-' => {}
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' if depth > 0 => depth -= 1,
+            ',' | ')' if depth == 0 => {
+                parameters.push(&signature[parameter_start..ix]);
+                parameter_start = ix + 1;
+                if character == ')' {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        previous = character;
+    }
+
+    parameters
+        .into_iter()
+        .filter_map(|parameter| {
+            let parameter = parameter.split('=').next()?.trim();
+            let name = match parameter.split_once(':') {
+                Some((name, _)) => name.trim(),
+                None => parameter.split_whitespace().last()?,
+            };
+            let name = name
+                .trim_start_matches("mut ")
+                .trim_start_matches(|c: char| c == '&' || c == '*' || c == '.');
+            let is_receiver = matches!(name, "self" | "mut self" | "this");
+            (!name.is_empty() && !is_receiver).then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn language_server_for_buffer(
+    project: &Model<Project>,
+    buffer: &Model<Buffer>,
+    server_id: LanguageServerId,
+    cx: &mut AsyncAppContext,
+) -> Result<(Arc<CachedLspAdapter>, Arc<LanguageServer>)> {
+    project
+        .update(cx, |project, cx| {
+            project
+                .language_server_for_buffer(buffer.read(cx), server_id, cx)
+                .map(|(adapter, server)| (adapter.clone(), server.clone()))
+        })?
+        .ok_or_else(|| anyhow!("no language server found for buffer"))
+}
+
+async fn location_links_from_proto(
+    proto_links: Vec<proto::LocationLink>,
+    project: Model<Project>,
+    mut cx: AsyncAppContext,
+) -> Result<Vec<LocationLink>> {
+    let mut links = Vec::new();
+
+    for link in proto_links {
+        let origin = match link.origin {
+            Some(origin) => {
+                let buffer = project
+                    .update(&mut cx, |this, cx| {
+                        this.wait_for_remote_buffer(origin.buffer_id, cx)
+                    })?
+                    .await?;
+                let start = origin
+                    .start
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing origin start"))?;
+                let end = origin
+                    .end
+                    .and_then(deserialize_anchor)
+                    .ok_or_else(|| anyhow!("missing origin end"))?;
+                buffer
+                    .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+                    .await?;
+                Some(Location {
+                    buffer,
+                    range: start..end,
+                })
+            }
+            None => None,
+        };
+
+        let target = link.target.ok_or_else(|| anyhow!("missing target"))?;
+        let buffer = project
+            .update(&mut cx, |this, cx| {
+                this.wait_for_remote_buffer(target.buffer_id, cx)
+            })?
+            .await?;
+        let start = target
+            .start
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("missing target start"))?;
+        let end = target
+            .end
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("missing target end"))?;
+        buffer
+            .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+            .await?;
+        let target = Location {
+            buffer,
+            range: start..end,
+        };
+
+        links.push(LocationLink { origin, target })
+    }
+
+    Ok(links)
+}
+
+async fn location_links_from_lsp(
+    message: Option<lsp::GotoDefinitionResponse>,
+    project: Model<Project>,
+    buffer: Model<Buffer>,
+    server_id: LanguageServerId,
+    mut cx: AsyncAppContext,
+) -> Result<Vec<LocationLink>> {
+    let message = match message {
+        Some(message) => message,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut unresolved_links = Vec::new();
+    match message {
+        lsp::GotoDefinitionResponse::Scalar(loc) => {
+            unresolved_links.push((None, loc.uri, loc.range));
+        }
+
+        lsp::GotoDefinitionResponse::Array(locs) => {
+            unresolved_links.extend(locs.into_iter().map(|l| (None, l.uri, l.range)));
+        }
+
+        lsp::GotoDefinitionResponse::Link(links) => {
+            unresolved_links.extend(links.into_iter().map(|l| {
+                (
+                    l.origin_selection_range,
+                    l.target_uri,
+                    l.target_selection_range,
+                )
+            }));
+        }
+    }
+
+    let (lsp_adapter, language_server) =
+        language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+    let mut definitions = Vec::new();
+    for (origin_range, target_uri, target_range) in unresolved_links {
+        let target_buffer_handle = project
+            .update(&mut cx, |this, cx| {
+                this.open_local_buffer_via_lsp(
+                    target_uri,
+                    language_server.server_id(),
+                    lsp_adapter.name.clone(),
+                    cx,
+                )
+            })?
+            .await?;
+
+        cx.update(|cx| {
+            let origin_location = origin_range.map(|origin_range| {
+                let origin_buffer = buffer.read(cx);
+                let origin_start =
+                    origin_buffer.clip_point_utf16(point_from_lsp(origin_range.start), Bias::Left);
+                let origin_end =
+                    origin_buffer.clip_point_utf16(point_from_lsp(origin_range.end), Bias::Left);
+                Location {
+                    buffer: buffer.clone(),
+                    range: origin_buffer.anchor_after(origin_start)
+                        ..origin_buffer.anchor_before(origin_end),
+                }
+            });
+
+            let target_buffer = target_buffer_handle.read(cx);
+            let target_start =
+                target_buffer.clip_point_utf16(point_from_lsp(target_range.start), Bias::Left);
+            let target_end =
+                target_buffer.clip_point_utf16(point_from_lsp(target_range.end), Bias::Left);
+            let target_location = Location {
+                buffer: target_buffer_handle,
+                range: target_buffer.anchor_after(target_start)
+                    ..target_buffer.anchor_before(target_end),
+            };
+
+            definitions.push(LocationLink {
+                origin: origin_location,
+                target: target_location,
+            })
+        })?;
+    }
+    Ok(definitions)
+}
+
+fn location_links_to_proto(
+    links: Vec<LocationLink>,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> Vec<proto::LocationLink> {
+    links
+        .into_iter()
+        .map(|definition| {
+            let origin = definition.origin.map(|origin| {
+                let buffer_id = project.create_buffer_for_peer(&origin.buffer, peer_id, cx);
+                proto::Location {
+                    start: Some(serialize_anchor(&origin.range.start)),
+                    end: Some(serialize_anchor(&origin.range.end)),
+                    buffer_id,
+                }
+            });
+
+            let buffer_id = project.create_buffer_for_peer(&definition.target.buffer, peer_id, cx);
+            let target = proto::Location {
+                start: Some(serialize_anchor(&definition.target.range.start)),
+                end: Some(serialize_anchor(&definition.target.range.end)),
+                buffer_id,
+            };
+
+            proto::LocationLink {
+                origin,
+                target: Some(target),
+            }
+        })
+        .collect()
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetReferences {
+    type Response = Vec<Location>;
+    type LspRequest = lsp::request::References;
+    type ProtoRequest = proto::GetReferences;
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::ReferenceParams {
+        lsp::ReferenceParams {
+            text_document_position: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: lsp::ReferenceContext {
+                include_declaration: true,
+            },
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        locations: Option<Vec<lsp::Location>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Location>> {
+        let mut references = Vec::new();
+        let (lsp_adapter, language_server) =
+            language_server_for_buffer(&project, &buffer, server_id, &mut cx)?;
+
+        if let Some(locations) = locations {
+            for lsp_location in locations {
+                let target_buffer_handle = project
+                    .update(&mut cx, |this, cx| {
+                        this.open_local_buffer_via_lsp(
+                            lsp_location.uri,
+                            language_server.server_id(),
+                            lsp_adapter.name.clone(),
+                            cx,
+                        )
+                    })?
+                    .await?;
+
+                target_buffer_handle
+                    .clone()
+                    .update(&mut cx, |target_buffer, _| {
+                        let target_start = target_buffer
+                            .clip_point_utf16(point_from_lsp(lsp_location.range.start), Bias::Left);
+                        let target_end = target_buffer
+                            .clip_point_utf16(point_from_lsp(lsp_location.range.end), Bias::Left);
+                        references.push(Location {
+                            buffer: target_buffer_handle,
+                            range: target_buffer.anchor_after(target_start)
+                                ..target_buffer.anchor_before(target_end),
+                        });
+                    })?;
+            }
+        }
+
+        Ok(references)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetReferences {
+        proto::GetReferences {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetReferences,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<Location>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetReferencesResponse {
+        let locations = response
+            .into_iter()
+            .map(|definition| {
+                let buffer_id = project.create_buffer_for_peer(&definition.buffer, peer_id, cx);
+                proto::Location {
+                    start: Some(serialize_anchor(&definition.range.start)),
+                    end: Some(serialize_anchor(&definition.range.end)),
+                    buffer_id,
+                }
+            })
+            .collect();
+        proto::GetReferencesResponse { locations }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetReferencesResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<Location>> {
+        let mut locations = Vec::new();
+        for location in message.locations {
+            let target_buffer = project
+                .update(&mut cx, |this, cx| {
+                    this.wait_for_remote_buffer(location.buffer_id, cx)
+                })?
+                .await?;
+            let start = location
+                .start
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target start"))?;
+            let end = location
+                .end
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target end"))?;
+            target_buffer
+                .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+                .await?;
+            locations.push(Location {
+                buffer: target_buffer,
+                range: start..end,
+            })
+        }
+        Ok(locations)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetReferences) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for PrepareCallHierarchy {
+    type Response = Vec<CallHierarchyItem>;
+    type LspRequest = lsp::request::CallHierarchyPrepare;
+    type ProtoRequest = proto::PrepareCallHierarchy;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_call_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CallHierarchyPrepareParams {
+        lsp::CallHierarchyPrepareParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        items: Option<Vec<lsp::CallHierarchyItem>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyItem>> {
+        let mut result = Vec::new();
+        for item in items.unwrap_or_default() {
+            result.push(
+                call_hierarchy_item_from_lsp(item, &project, &buffer, server_id, &mut cx).await?,
+            );
+        }
+        Ok(result)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::PrepareCallHierarchy {
+        proto::PrepareCallHierarchy {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::PrepareCallHierarchy,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<CallHierarchyItem>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::PrepareCallHierarchyResponse {
+        proto::PrepareCallHierarchyResponse {
+            items: response
+                .into_iter()
+                .map(|item| call_hierarchy_item_to_proto(item, project, peer_id, cx))
+                .collect(),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::PrepareCallHierarchyResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyItem>> {
+        let mut items = Vec::new();
+        for item in message.items {
+            items.push(call_hierarchy_item_from_proto(item, &project, &mut cx).await?);
+        }
+        Ok(items)
+    }
+
+    fn buffer_id_from_proto(message: &proto::PrepareCallHierarchy) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetIncomingCalls {
+    type Response = Vec<CallHierarchyCall>;
+    type LspRequest = lsp::request::CallHierarchyIncomingCalls;
+    type ProtoRequest = proto::GetIncomingCalls;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_call_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        _: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CallHierarchyIncomingCallsParams {
+        lsp::CallHierarchyIncomingCallsParams {
+            item: self.item.clone(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        calls: Option<Vec<lsp::CallHierarchyIncomingCall>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let mut result = Vec::new();
+        for call in calls.unwrap_or_default() {
+            let item =
+                call_hierarchy_item_from_lsp(call.from, &project, &buffer, server_id, &mut cx)
+                    .await?;
+            // The call sites are within the caller
+            let call_sites = locations_from_lsp(&item.location.buffer, call.from_ranges, &mut cx)?;
+            result.push(CallHierarchyCall { item, call_sites });
+        }
+        Ok(result)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetIncomingCalls {
+        proto::GetIncomingCalls {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            lsp_item: serde_json::to_vec(&self.item).unwrap(),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetIncomingCalls,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: AsyncAppContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            item: serde_json::from_slice(&message.lsp_item)?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<CallHierarchyCall>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetIncomingCallsResponse {
+        proto::GetIncomingCallsResponse {
+            calls: response
+                .into_iter()
+                .map(|call| call_hierarchy_call_to_proto(call, project, peer_id, cx))
+                .collect(),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetIncomingCallsResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let mut calls = Vec::new();
+        for call in message.calls {
+            calls.push(call_hierarchy_call_from_proto(call, &project, &mut cx).await?);
+        }
+        Ok(calls)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetIncomingCalls) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetOutgoingCalls {
+    type Response = Vec<CallHierarchyCall>;
+    type LspRequest = lsp::request::CallHierarchyOutgoingCalls;
+    type ProtoRequest = proto::GetOutgoingCalls;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        supports_call_hierarchy(capabilities)
+    }
+
+    fn to_lsp(
+        &self,
+        _: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::CallHierarchyOutgoingCallsParams {
+        lsp::CallHierarchyOutgoingCallsParams {
+            item: self.item.clone(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        calls: Option<Vec<lsp::CallHierarchyOutgoingCall>>,
+        project: Model<Project>,
+        buffer: Model<Buffer>,
+        server_id: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let mut result = Vec::new();
+        for call in calls.unwrap_or_default() {
+            let item = call_hierarchy_item_from_lsp(call.to, &project, &buffer, server_id, &mut cx)
+                .await?;
+            // The call sites are within the caller, whose buffer the request was made for
+            let call_sites = locations_from_lsp(&buffer, call.from_ranges, &mut cx)?;
+            result.push(CallHierarchyCall { item, call_sites });
+        }
+        Ok(result)
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetOutgoingCalls {
+        proto::GetOutgoingCalls {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            lsp_item: serde_json::to_vec(&self.item).unwrap(),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetOutgoingCalls,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: AsyncAppContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            item: serde_json::from_slice(&message.lsp_item)?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<CallHierarchyCall>,
+        project: &mut Project,
+        peer_id: PeerId,
+        _: &clock::Global,
+        cx: &mut AppContext,
+    ) -> proto::GetOutgoingCallsResponse {
+        proto::GetOutgoingCallsResponse {
+            calls: response
+                .into_iter()
+                .map(|call| call_hierarchy_call_to_proto(call, project, peer_id, cx))
+                .collect(),
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetOutgoingCallsResponse,
+        project: Model<Project>,
+        _: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<CallHierarchyCall>> {
+        let mut calls = Vec::new();
+        for call in message.calls {
+            calls.push(call_hierarchy_call_from_proto(call, &project, &mut cx).await?);
+        }
+        Ok(calls)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetOutgoingCalls) -> u64 {
+        message.buffer_id
+    }
+}
+
+fn supports_call_hierarchy(capabilities: &ServerCapabilities) -> bool {
+    !matches!(
+        capabilities.call_hierarchy_provider,
+        None | Some(lsp::CallHierarchyServerCapability::Simple(false))
+    )
+}
+
+async fn call_hierarchy_item_from_lsp(
+    lsp_item: lsp::CallHierarchyItem,
+    project: &Model<Project>,
+    buffer: &Model<Buffer>,
+    server_id: LanguageServerId,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyItem> {
+    let (lsp_adapter, language_server) =
+        language_server_for_buffer(project, buffer, server_id, cx)?;
+    let item_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_via_lsp(
+                lsp_item.uri.clone(),
+                language_server.server_id(),
+                lsp_adapter.name.clone(),
+                cx,
+            )
+        })?
+        .await?;
+    let location = locations_from_lsp(&item_buffer, [lsp_item.selection_range], cx)?
+        .pop()
+        .ok_or_else(|| anyhow!("missing call hierarchy item location"))?;
+    Ok(call_hierarchy_item(lsp_item, location))
+}
+
+fn call_hierarchy_item(lsp_item: lsp::CallHierarchyItem, location: Location) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: lsp_item.name.clone(),
+        kind: lsp_item.kind,
+        detail: lsp_item.detail.clone(),
+        location,
+        lsp_item,
+    }
+}
+
+fn locations_from_lsp(
+    buffer: &Model<Buffer>,
+    ranges: impl IntoIterator<Item = lsp::Range>,
+    cx: &mut AsyncAppContext,
+) -> Result<Vec<Location>> {
+    buffer.update(cx, |snapshot, _| {
+        ranges
+            .into_iter()
+            .map(|range| {
+                let start = snapshot.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+                let end = snapshot.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+                Location {
+                    buffer: buffer.clone(),
+                    range: snapshot.anchor_after(start)..snapshot.anchor_before(end),
+                }
+            })
+            .collect()
+    })
+}
+
+fn location_to_proto(
+    location: &Location,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> proto::Location {
+    proto::Location {
+        buffer_id: project.create_buffer_for_peer(&location.buffer, peer_id, cx),
+        start: Some(serialize_anchor(&location.range.start)),
+        end: Some(serialize_anchor(&location.range.end)),
+    }
+}
+
+async fn location_from_proto(
+    location: proto::Location,
+    project: &Model<Project>,
+    cx: &mut AsyncAppContext,
+) -> Result<Location> {
+    let buffer = project
+        .update(cx, |this, cx| {
+            this.wait_for_remote_buffer(location.buffer_id, cx)
+        })?
+        .await?;
+    let start = location
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location start"))?;
+    let end = location
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("missing location end"))?;
+    buffer
+        .update(cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+        .await?;
+    Ok(Location {
+        buffer,
+        range: start..end,
+    })
+}
+
+fn call_hierarchy_item_to_proto(
+    item: CallHierarchyItem,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> proto::CallHierarchyItem {
+    proto::CallHierarchyItem {
+        location: Some(location_to_proto(&item.location, project, peer_id, cx)),
+        lsp_item: serde_json::to_vec(&item.lsp_item).unwrap(),
+    }
+}
+
+async fn call_hierarchy_item_from_proto(
+    item: proto::CallHierarchyItem,
+    project: &Model<Project>,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyItem> {
+    let lsp_item = serde_json::from_slice(&item.lsp_item)?;
+    let location = item
+        .location
+        .ok_or_else(|| anyhow!("missing call hierarchy item location"))?;
+    let location = location_from_proto(location, project, cx).await?;
+    Ok(call_hierarchy_item(lsp_item, location))
+}
+
+fn call_hierarchy_call_to_proto(
+    call: CallHierarchyCall,
+    project: &mut Project,
+    peer_id: PeerId,
+    cx: &mut AppContext,
+) -> proto::CallHierarchyCall {
+    proto::CallHierarchyCall {
+        call_sites: call
+            .call_sites
+            .iter()
+            .map(|location| location_to_proto(location, project, peer_id, cx))
+            .collect(),
+        item: Some(call_hierarchy_item_to_proto(
+            call.item, project, peer_id, cx,
+        )),
+    }
+}
+
+async fn call_hierarchy_call_from_proto(
+    call: proto::CallHierarchyCall,
+    project: &Model<Project>,
+    cx: &mut AsyncAppContext,
+) -> Result<CallHierarchyCall> {
+    let item = call
+        .item
+        .ok_or_else(|| anyhow!("missing call hierarchy item"))?;
+    let item = call_hierarchy_item_from_proto(item, project, cx).await?;
+    let mut call_sites = Vec::new();
+    for location in call.call_sites {
+        call_sites.push(location_from_proto(location, project, cx).await?);
+    }
+    Ok(CallHierarchyCall { item, call_sites })
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetDocumentHighlights {
+    type Response = Vec<DocumentHighlight>;
+    type LspRequest = lsp::request::DocumentHighlightRequest;
+    type ProtoRequest = proto::GetDocumentHighlights;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.document_highlight_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::DocumentHighlightParams {
+        lsp::DocumentHighlightParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        lsp_highlights: Option<Vec<lsp::DocumentHighlight>>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentHighlight>> {
+        buffer.update(&mut cx, |buffer, _| {
+            let mut lsp_highlights = lsp_highlights.unwrap_or_default();
+            lsp_highlights.sort_unstable_by_key(|h| (h.range.start, Reverse(h.range.end)));
+            lsp_highlights
+                .into_iter()
+                .map(|lsp_highlight| {
+                    let start = buffer
+                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.start), Bias::Left);
+                    let end = buffer
+                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.end), Bias::Left);
+                    DocumentHighlight {
+                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
+                        kind: lsp_highlight
+                            .kind
+                            .unwrap_or(lsp::DocumentHighlightKind::READ),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDocumentHighlights {
+        proto::GetDocumentHighlights {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetDocumentHighlights,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Vec<DocumentHighlight>,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetDocumentHighlightsResponse {
+        let highlights = response
+            .into_iter()
+            .map(|highlight| proto::DocumentHighlight {
+                start: Some(serialize_anchor(&highlight.range.start)),
+                end: Some(serialize_anchor(&highlight.range.end)),
+                kind: match highlight.kind {
+                    DocumentHighlightKind::TEXT => proto::document_highlight::Kind::Text.into(),
+                    DocumentHighlightKind::WRITE => proto::document_highlight::Kind::Write.into(),
+                    DocumentHighlightKind::READ => proto::document_highlight::Kind::Read.into(),
+                    _ => proto::document_highlight::Kind::Text.into(),
+                },
+            })
+            .collect();
+        proto::GetDocumentHighlightsResponse { highlights }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetDocumentHighlightsResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Vec<DocumentHighlight>> {
+        let mut highlights = Vec::new();
+        for highlight in message.highlights {
+            let start = highlight
+                .start
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target start"))?;
+            let end = highlight
+                .end
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing target end"))?;
+            buffer
+                .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
+                .await?;
+            let kind = match proto::document_highlight::Kind::from_i32(highlight.kind) {
+                Some(proto::document_highlight::Kind::Text) => DocumentHighlightKind::TEXT,
+                Some(proto::document_highlight::Kind::Read) => DocumentHighlightKind::READ,
+                Some(proto::document_highlight::Kind::Write) => DocumentHighlightKind::WRITE,
+                None => DocumentHighlightKind::TEXT,
+            };
+            highlights.push(DocumentHighlight {
+                range: start..end,
+                kind,
+            });
+        }
+        Ok(highlights)
+    }
+
+    fn buffer_id_from_proto(message: &proto::GetDocumentHighlights) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetHover {
+    type Response = Option<Hover>;
+    type LspRequest = lsp::request::HoverRequest;
+    type ProtoRequest = proto::GetHover;
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::HoverParams {
+        lsp::HoverParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::Hover>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        let Some(hover) = message else {
+            return Ok(None);
+        };
+
+        let (language, range) = buffer.update(&mut cx, |buffer, _| {
+            (
+                buffer.language().cloned(),
+                hover.range.map(|range| {
+                    let token_start =
+                        buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+                    let token_end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+                    buffer.anchor_after(token_start)..buffer.anchor_before(token_end)
+                }),
+            )
+        })?;
+
+        fn hover_blocks_from_marked_string(marked_string: lsp::MarkedString) -> Option<HoverBlock> {
+            let block = match marked_string {
+                lsp::MarkedString::String(content) => HoverBlock {
+                    text: content,
+                    kind: HoverBlockKind::Markdown,
+                },
+                lsp::MarkedString::LanguageString(lsp::LanguageString { language, value }) => {
+                    HoverBlock {
+                        text: value,
+                        kind: HoverBlockKind::Code { language },
+                    }
+                }
+            };
+            if block.text.is_empty() {
+                None
+            } else {
+                Some(block)
+            }
+        }
+
+        let contents = match hover.contents {
+            lsp::HoverContents::Scalar(marked_string) => {
+                hover_blocks_from_marked_string(marked_string)
+                    .into_iter()
+                    .collect()
+            }
+            lsp::HoverContents::Array(marked_strings) => marked_strings
+                .into_iter()
+                .filter_map(hover_blocks_from_marked_string)
+                .collect(),
+            lsp::HoverContents::Markup(markup_content) => vec![HoverBlock {
+                text: markup_content.value,
+                kind: if markup_content.kind == lsp::MarkupKind::Markdown {
+                    HoverBlockKind::Markdown
+                } else {
+                    HoverBlockKind::PlainText
+                },
+            }],
+        };
+
+        Ok(Some(Hover {
+            contents,
+            range,
+            language,
+        }))
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> Self::ProtoRequest {
+        proto::GetHover {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version),
+        }
+    }
+
+    async fn from_proto(
+        message: Self::ProtoRequest,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: Self::Response,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetHoverResponse {
+        if let Some(response) = response {
+            let (start, end) = if let Some(range) = response.range {
+                (
+                    Some(language::proto::serialize_anchor(&range.start)),
+                    Some(language::proto::serialize_anchor(&range.end)),
+                )
+            } else {
+                (None, None)
+            };
+
+            let contents = response
+                .contents
+                .into_iter()
+                .map(|block| proto::HoverBlock {
+                    text: block.text,
+                    is_markdown: block.kind == HoverBlockKind::Markdown,
+                    language: if let HoverBlockKind::Code { language } = block.kind {
+                        Some(language)
+                    } else {
+                        None
+                    },
+                })
+                .collect();
+
+            proto::GetHoverResponse {
+                start,
+                end,
+                contents,
+            }
+        } else {
+            proto::GetHoverResponse {
+                start: None,
+                end: None,
+                contents: Vec::new(),
+            }
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetHoverResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        let contents: Vec<_> = message
+            .contents
+            .into_iter()
+            .map(|block| HoverBlock {
+                text: block.text,
+                kind: if let Some(language) = block.language {
+                    HoverBlockKind::Code { language }
+                } else if block.is_markdown {
+                    HoverBlockKind::Markdown
+                } else {
+                    HoverBlockKind::PlainText
+                },
+            })
+            .collect();
+        if contents.is_empty() {
+            return Ok(None);
+        }
 
-                target_buffer_handle
-                    .clone()
-                    .update(&mut cx, |target_buffer, _| {
-                        let target_start = target_buffer
-                            .clip_point_utf16(point_from_lsp(lsp_location.range.start), Bias::Left);
-                        let target_end = target_buffer
-                            .clip_point_utf16(point_from_lsp(lsp_location.range.end), Bias::Left);
-                        references.push(Location {
-                            buffer: target_buffer_handle,
-                            range: target_buffer.anchor_after(target_start)
-                                ..target_buffer.anchor_before(target_end),
-                        });
-                    })?;
-            }
+        let language = buffer.update(&mut cx, |buffer, _| buffer.language().cloned())?;
+        let range = if let (Some(start), Some(end)) = (message.start, message.end) {
+            language::proto::deserialize_anchor(start)
+                .and_then(|start| language::proto::deserialize_anchor(end).map(|end| start..end))
+        } else {
+            None
+        };
+
+        Ok(Some(Hover {
+            contents,
+            range,
+            language,
+        }))
+    }
+
+    fn buffer_id_from_proto(message: &Self::ProtoRequest) -> u64 {
+        message.buffer_id
+    }
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetSignatureHelp {
+    type Response = Option<SignatureHelp>;
+    type LspRequest = lsp::request::SignatureHelpRequest;
+    type ProtoRequest = proto::GetSignatureHelp;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        capabilities.signature_help_provider.is_some()
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::SignatureHelpParams {
+        lsp::SignatureHelpParams {
+            context: Some(lsp::SignatureHelpContext {
+                trigger_kind: if self.trigger_character.is_some() {
+                    lsp::SignatureHelpTriggerKind::TRIGGER_CHARACTER
+                } else if self.is_retrigger {
+                    lsp::SignatureHelpTriggerKind::CONTENT_CHANGE
+                } else {
+                    lsp::SignatureHelpTriggerKind::INVOKED
+                },
+                trigger_character: self.trigger_character.clone(),
+                is_retrigger: self.is_retrigger,
+                active_signature_help: None,
+            }),
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
         }
+    }
 
-        Ok(references)
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::SignatureHelp>,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: LanguageServerId,
+        _: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        let Some(help) = message.filter(|help| !help.signatures.is_empty()) else {
+            return Ok(None);
+        };
+
+        let signatures = help
+            .signatures
+            .into_iter()
+            .map(|signature| {
+                // Simple parameter labels are substrings of the signature label, so look
+                // for each one after the previous parameter to skip the function name.
+                let mut search_start = 0;
+                let parameters = signature
+                    .parameters
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|parameter| {
+                        let range = match &parameter.label {
+                            lsp::ParameterLabel::Simple(label) => {
+                                let start =
+                                    search_start + signature.label[search_start..].find(label)?;
+                                start..start + label.len()
+                            }
+                            lsp::ParameterLabel::LabelOffsets([start, end]) => {
+                                utf16_offset_to_byte(&signature.label, *start as usize)?
+                                    ..utf16_offset_to_byte(&signature.label, *end as usize)?
+                            }
+                        };
+                        search_start = range.end;
+                        Some(range)
+                    })
+                    .collect();
+                SignatureInformation {
+                    documentation: signature.documentation.map(
+                        |documentation| match documentation {
+                            lsp::Documentation::String(text) => text,
+                            lsp::Documentation::MarkupContent(content) => content.value,
+                        },
+                    ),
+                    parameters,
+                    active_parameter: signature
+                        .active_parameter
+                        .or(help.active_parameter)
+                        .map(|ix| ix as usize),
+                    label: signature.label,
+                }
+            })
+            .collect::<Vec<_>>();
+        let active_signature =
+            (help.active_signature.unwrap_or(0) as usize).min(signatures.len().saturating_sub(1));
+
+        Ok(Some(SignatureHelp {
+            signatures,
+            active_signature,
+        }))
     }
 
-    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetReferences {
-        proto::GetReferences {
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> Self::ProtoRequest {
+        proto::GetSignatureHelp {
             project_id,
             buffer_id: buffer.remote_id(),
             position: Some(language::proto::serialize_anchor(
                 &buffer.anchor_before(self.position),
             )),
-            version: serialize_version(&buffer.version()),
+            trigger_character: self.trigger_character.clone(),
+            is_retrigger: self.is_retrigger,
+            version: serialize_version(&buffer.version),
         }
     }
 
     async fn from_proto(
-        message: proto::GetReferences,
+        message: Self::ProtoRequest,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
@@ -884,76 +2901,267 @@ impl LspCommand for GetReferences {
             .await?;
         Ok(Self {
             position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+            trigger_character: message.trigger_character,
+            is_retrigger: message.is_retrigger,
         })
     }
 
     fn response_to_proto(
-        response: Vec<Location>,
-        project: &mut Project,
-        peer_id: PeerId,
+        response: Self::Response,
+        _: &mut Project,
+        _: PeerId,
         _: &clock::Global,
-        cx: &mut AppContext,
-    ) -> proto::GetReferencesResponse {
-        let locations = response
+        _: &mut AppContext,
+    ) -> proto::GetSignatureHelpResponse {
+        let Some(response) = response else {
+            return proto::GetSignatureHelpResponse {
+                signatures: Vec::new(),
+                active_signature: 0,
+            };
+        };
+
+        proto::GetSignatureHelpResponse {
+            signatures: response
+                .signatures
+                .into_iter()
+                .map(|signature| proto::SignatureInformation {
+                    label: signature.label,
+                    documentation: signature.documentation,
+                    parameters: signature
+                        .parameters
+                        .into_iter()
+                        .map(|range| proto::SignatureParameter {
+                            start: range.start as u32,
+                            end: range.end as u32,
+                        })
+                        .collect(),
+                    active_parameter: signature.active_parameter.map(|ix| ix as u32),
+                })
+                .collect(),
+            active_signature: response.active_signature as u32,
+        }
+    }
+
+    async fn response_from_proto(
+        self,
+        message: proto::GetSignatureHelpResponse,
+        _: Model<Project>,
+        _: Model<Buffer>,
+        _: AsyncAppContext,
+    ) -> Result<Self::Response> {
+        if message.signatures.is_empty() {
+            return Ok(None);
+        }
+
+        let signatures = message
+            .signatures
             .into_iter()
-            .map(|definition| {
-                let buffer_id = project.create_buffer_for_peer(&definition.buffer, peer_id, cx);
-                proto::Location {
-                    start: Some(serialize_anchor(&definition.range.start)),
-                    end: Some(serialize_anchor(&definition.range.end)),
-                    buffer_id,
+            .map(|signature| {
+                let parameters = signature
+                    .parameters
+                    .into_iter()
+                    .map(|parameter| parameter.start as usize..parameter.end as usize)
+                    .filter(|range| {
+                        signature.label.is_char_boundary(range.start)
+                            && signature.label.is_char_boundary(range.end)
+                    })
+                    .collect();
+                SignatureInformation {
+                    label: signature.label,
+                    documentation: signature.documentation,
+                    parameters,
+                    active_parameter: signature.active_parameter.map(|ix| ix as usize),
                 }
             })
-            .collect();
-        proto::GetReferencesResponse { locations }
+            .collect::<Vec<_>>();
+        let active_signature =
+            (message.active_signature as usize).min(signatures.len().saturating_sub(1));
+
+        Ok(Some(SignatureHelp {
+            signatures,
+            active_signature,
+        }))
+    }
+
+    fn buffer_id_from_proto(message: &Self::ProtoRequest) -> u64 {
+        message.buffer_id
+    }
+}
+
+fn utf16_offset_to_byte(text: &str, utf16_offset: usize) -> Option<usize> {
+    let mut utf16_len = 0;
+    for (ix, ch) in text.char_indices() {
+        if utf16_len >= utf16_offset {
+            return (utf16_len == utf16_offset).then_some(ix);
+        }
+        utf16_len += ch.len_utf16();
+    }
+    (utf16_len == utf16_offset).then_some(text.len())
+}
+
+#[async_trait(?Send)]
+impl LspCommand for GetLinkedEditingRanges {
+    type Response = LinkedEditingRanges;
+    type LspRequest = lsp::request::LinkedEditingRange;
+    type ProtoRequest = proto::GetLinkedEditingRanges;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.linked_editing_range_provider {
+            Some(lsp::LinkedEditingRangeServerCapabilities::Simple(enabled)) => *enabled,
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    fn to_lsp(
+        &self,
+        path: &Path,
+        _: &Buffer,
+        _: &Arc<LanguageServer>,
+        _: &AppContext,
+    ) -> lsp::LinkedEditingRangeParams {
+        lsp::LinkedEditingRangeParams {
+            text_document_position_params: lsp::TextDocumentPositionParams {
+                text_document: lsp::TextDocumentIdentifier {
+                    uri: lsp::Url::from_file_path(path).unwrap(),
+                },
+                position: point_to_lsp(self.position),
+            },
+            work_done_progress_params: Default::default(),
+        }
+    }
+
+    async fn response_from_lsp(
+        self,
+        message: Option<lsp::LinkedEditingRanges>,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        _: LanguageServerId,
+        mut cx: AsyncAppContext,
+    ) -> Result<LinkedEditingRanges> {
+        let Some(message) = message else {
+            return Ok(Default::default());
+        };
+        // Ranges grow with text typed at either end of them
+        let ranges = buffer.update(&mut cx, |buffer, _| {
+            message
+                .ranges
+                .into_iter()
+                .map(|range| {
+                    let start = buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+                    let end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+                    buffer.anchor_before(start)..buffer.anchor_after(end)
+                })
+                .collect()
+        })?;
+        Ok(LinkedEditingRanges {
+            ranges,
+            word_pattern: message
+                .word_pattern
+                .and_then(|pattern| Regex::new(&pattern).log_err()),
+        })
+    }
+
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetLinkedEditingRanges {
+        proto::GetLinkedEditingRanges {
+            project_id,
+            buffer_id: buffer.remote_id(),
+            position: Some(language::proto::serialize_anchor(
+                &buffer.anchor_before(self.position),
+            )),
+            version: serialize_version(&buffer.version()),
+        }
+    }
+
+    async fn from_proto(
+        message: proto::GetLinkedEditingRanges,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
+        mut cx: AsyncAppContext,
+    ) -> Result<Self> {
+        let position = message
+            .position
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid position"))?;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&message.version))
+            })?
+            .await?;
+        Ok(Self {
+            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
+        })
+    }
+
+    fn response_to_proto(
+        response: LinkedEditingRanges,
+        _: &mut Project,
+        _: PeerId,
+        _: &clock::Global,
+        _: &mut AppContext,
+    ) -> proto::GetLinkedEditingRangesResponse {
+        proto::GetLinkedEditingRangesResponse {
+            ranges: response
+                .ranges
+                .into_iter()
+                .map(|range| proto::LinkedEditingRange {
+                    start: Some(serialize_anchor(&range.start)),
+                    end: Some(serialize_anchor(&range.end)),
+                })
+                .collect(),
+            word_pattern: response
+                .word_pattern
+                .map(|pattern| pattern.as_str().to_string()),
+        }
     }
 
     async fn response_from_proto(
         self,
-        message: proto::GetReferencesResponse,
-        project: Model<Project>,
-        _: Model<Buffer>,
+        message: proto::GetLinkedEditingRangesResponse,
+        _: Model<Project>,
+        buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
-    ) -> Result<Vec<Location>> {
-        let mut locations = Vec::new();
-        for location in message.locations {
-            let target_buffer = project
-                .update(&mut cx, |this, cx| {
-                    this.wait_for_remote_buffer(location.buffer_id, cx)
-                })?
-                .await?;
-            let start = location
+    ) -> Result<LinkedEditingRanges> {
+        let mut ranges = Vec::new();
+        for range in message.ranges {
+            let start = range
                 .start
                 .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target start"))?;
-            let end = location
+                .ok_or_else(|| anyhow!("missing range start"))?;
+            let end = range
                 .end
                 .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target end"))?;
-            target_buffer
+                .ok_or_else(|| anyhow!("missing range end"))?;
+            buffer
                 .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
                 .await?;
-            locations.push(Location {
-                buffer: target_buffer,
-                range: start..end,
-            })
+            ranges.push(start..end);
         }
-        Ok(locations)
+        Ok(LinkedEditingRanges {
+            ranges,
+            word_pattern: message
+                .word_pattern
+                .and_then(|pattern| Regex::new(&pattern).log_err()),
+        })
     }
 
-    fn buffer_id_from_proto(message: &proto::GetReferences) -> u64 {
+    fn buffer_id_from_proto(message: &proto::GetLinkedEditingRanges) -> u64 {
         message.buffer_id
     }
 }
 
-#[async_trait(?Send)]
-impl LspCommand for GetDocumentHighlights {
-    type Response = Vec<DocumentHighlight>;
-    type LspRequest = lsp::request::DocumentHighlightRequest;
-    type ProtoRequest = proto::GetDocumentHighlights;
+#[async_trait(?Send)]
+impl LspCommand for GetSelectionRanges {
+    type Response = Vec<Range<Anchor>>;
+    type LspRequest = lsp::request::SelectionRangeRequest;
+    type ProtoRequest = proto::GetSelectionRanges;
 
     fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
-        capabilities.document_highlight_provider.is_some()
+        match &capabilities.selection_range_provider {
+            Some(lsp::SelectionRangeProviderCapability::Simple(enabled)) => *enabled,
+            Some(_) => true,
+            None => false,
+        }
     }
 
     fn to_lsp(
@@ -962,14 +3170,12 @@ impl LspCommand for GetDocumentHighlights {
         _: &Buffer,
         _: &Arc<LanguageServer>,
         _: &AppContext,
-    ) -> lsp::DocumentHighlightParams {
-        lsp::DocumentHighlightParams {
-            text_document_position_params: lsp::TextDocumentPositionParams {
-                text_document: lsp::TextDocumentIdentifier {
-                    uri: lsp::Url::from_file_path(path).unwrap(),
-                },
-                position: point_to_lsp(self.position),
+    ) -> lsp::SelectionRangeParams {
+        lsp::SelectionRangeParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
             },
+            positions: vec![point_to_lsp(self.position)],
             work_done_progress_params: Default::default(),
             partial_result_params: Default::default(),
         }
@@ -977,35 +3183,35 @@ impl LspCommand for GetDocumentHighlights {
 
     async fn response_from_lsp(
         self,
-        lsp_highlights: Option<Vec<lsp::DocumentHighlight>>,
+        message: Option<Vec<lsp::SelectionRange>>,
         _: Model<Project>,
         buffer: Model<Buffer>,
         _: LanguageServerId,
         mut cx: AsyncAppContext,
-    ) -> Result<Vec<DocumentHighlight>> {
+    ) -> Result<Vec<Range<Anchor>>> {
+        // The response has a hierarchy of ranges for each of the requested positions,
+        // from the innermost range to the outermost one.
+        let Some(mut selection_range) = message.into_iter().flatten().next() else {
+            return Ok(Vec::new());
+        };
         buffer.update(&mut cx, |buffer, _| {
-            let mut lsp_highlights = lsp_highlights.unwrap_or_default();
-            lsp_highlights.sort_unstable_by_key(|h| (h.range.start, Reverse(h.range.end)));
-            lsp_highlights
-                .into_iter()
-                .map(|lsp_highlight| {
-                    let start = buffer
-                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.start), Bias::Left);
-                    let end = buffer
-                        .clip_point_utf16(point_from_lsp(lsp_highlight.range.end), Bias::Left);
-                    DocumentHighlight {
-                        range: buffer.anchor_after(start)..buffer.anchor_before(end),
-                        kind: lsp_highlight
-                            .kind
-                            .unwrap_or(lsp::DocumentHighlightKind::READ),
-                    }
-                })
-                .collect()
+            let mut ranges = Vec::new();
+            loop {
+                let range = selection_range.range;
+                let start = buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
+                let end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
+                ranges.push(buffer.anchor_after(start)..buffer.anchor_before(end));
+                match selection_range.parent {
+                    Some(parent) => selection_range = *parent,
+                    None => break,
+                }
+            }
+            ranges
         })
     }
 
-    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetDocumentHighlights {
-        proto::GetDocumentHighlights {
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetSelectionRanges {
+        proto::GetSelectionRanges {
             project_id,
             buffer_id: buffer.remote_id(),
             position: Some(language::proto::serialize_anchor(
@@ -1016,7 +3222,7 @@ impl LspCommand for GetDocumentHighlights {
     }
 
     async fn from_proto(
-        message: proto::GetDocumentHighlights,
+        message: proto::GetSelectionRanges,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
@@ -1036,72 +3242,66 @@ impl LspCommand for GetDocumentHighlights {
     }
 
     fn response_to_proto(
-        response: Vec<DocumentHighlight>,
+        response: Vec<Range<Anchor>>,
         _: &mut Project,
         _: PeerId,
         _: &clock::Global,
         _: &mut AppContext,
-    ) -> proto::GetDocumentHighlightsResponse {
-        let highlights = response
-            .into_iter()
-            .map(|highlight| proto::DocumentHighlight {
-                start: Some(serialize_anchor(&highlight.range.start)),
-                end: Some(serialize_anchor(&highlight.range.end)),
-                kind: match highlight.kind {
-                    DocumentHighlightKind::TEXT => proto::document_highlight::Kind::Text.into(),
-                    DocumentHighlightKind::WRITE => proto::document_highlight::Kind::Write.into(),
-                    DocumentHighlightKind::READ => proto::document_highlight::Kind::Read.into(),
-                    _ => proto::document_highlight::Kind::Text.into(),
-                },
-            })
-            .collect();
-        proto::GetDocumentHighlightsResponse { highlights }
+    ) -> proto::GetSelectionRangesResponse {
+        proto::GetSelectionRangesResponse {
+            ranges: response
+                .into_iter()
+                .map(|range| proto::SelectionRange {
+                    start: Some(serialize_anchor(&range.start)),
+                    end: Some(serialize_anchor(&range.end)),
+                })
+                .collect(),
+        }
     }
 
     async fn response_from_proto(
         self,
-        message: proto::GetDocumentHighlightsResponse,
+        message: proto::GetSelectionRangesResponse,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
-    ) -> Result<Vec<DocumentHighlight>> {
-        let mut highlights = Vec::new();
-        for highlight in message.highlights {
-            let start = highlight
+    ) -> Result<Vec<Range<Anchor>>> {
+        let mut ranges = Vec::new();
+        for range in message.ranges {
+            let start = range
                 .start
                 .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target start"))?;
-            let end = highlight
+                .ok_or_else(|| anyhow!("missing range start"))?;
+            let end = range
                 .end
                 .and_then(deserialize_anchor)
-                .ok_or_else(|| anyhow!("missing target end"))?;
+                .ok_or_else(|| anyhow!("missing range end"))?;
             buffer
                 .update(&mut cx, |buffer, _| buffer.wait_for_anchors([start, end]))?
                 .await?;
-            let kind = match proto::document_highlight::Kind::from_i32(highlight.kind) {
-                Some(proto::document_highlight::Kind::Text) => DocumentHighlightKind::TEXT,
-                Some(proto::document_highlight::Kind::Read) => DocumentHighlightKind::READ,
-                Some(proto::document_highlight::Kind::Write) => DocumentHighlightKind::WRITE,
-                None => DocumentHighlightKind::TEXT,
-            };
-            highlights.push(DocumentHighlight {
-                range: start..end,
-                kind,
-            });
+            ranges.push(start..end);
         }
-        Ok(highlights)
+        Ok(ranges)
     }
 
-    fn buffer_id_from_proto(message: &proto::GetDocumentHighlights) -> u64 {
+    fn buffer_id_from_proto(message: &proto::GetSelectionRanges) -> u64 {
         message.buffer_id
     }
 }
 
 #[async_trait(?Send)]
-impl LspCommand for GetHover {
-    type Response = Option<Hover>;
-    type LspRequest = lsp::request::HoverRequest;
-    type ProtoRequest = proto::GetHover;
+impl LspCommand for GetFoldingRanges {
+    type Response = Vec<Range<Anchor>>;
+    type LspRequest = lsp::request::FoldingRangeRequest;
+    type ProtoRequest = proto::GetFoldingRanges;
+
+    fn check_capabilities(&self, capabilities: &ServerCapabilities) -> bool {
+        match &capabilities.folding_range_provider {
+            Some(lsp::FoldingRangeProviderCapability::Simple(enabled)) => *enabled,
+            Some(_) => true,
+            None => false,
+        }
+    }
 
     fn to_lsp(
         &self,
@@ -1109,206 +3309,109 @@ impl LspCommand for GetHover {
         _: &Buffer,
         _: &Arc<LanguageServer>,
         _: &AppContext,
-    ) -> lsp::HoverParams {
-        lsp::HoverParams {
-            text_document_position_params: lsp::TextDocumentPositionParams {
-                text_document: lsp::TextDocumentIdentifier {
-                    uri: lsp::Url::from_file_path(path).unwrap(),
-                },
-                position: point_to_lsp(self.position),
+    ) -> lsp::FoldingRangeParams {
+        lsp::FoldingRangeParams {
+            text_document: lsp::TextDocumentIdentifier {
+                uri: lsp::Url::from_file_path(path).unwrap(),
             },
             work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
         }
     }
 
     async fn response_from_lsp(
         self,
-        message: Option<lsp::Hover>,
+        message: Option<Vec<lsp::FoldingRange>>,
         _: Model<Project>,
         buffer: Model<Buffer>,
         _: LanguageServerId,
         mut cx: AsyncAppContext,
-    ) -> Result<Self::Response> {
-        let Some(hover) = message else {
-            return Ok(None);
-        };
-
-        let (language, range) = buffer.update(&mut cx, |buffer, _| {
-            (
-                buffer.language().cloned(),
-                hover.range.map(|range| {
-                    let token_start =
-                        buffer.clip_point_utf16(point_from_lsp(range.start), Bias::Left);
-                    let token_end = buffer.clip_point_utf16(point_from_lsp(range.end), Bias::Left);
-                    buffer.anchor_after(token_start)..buffer.anchor_before(token_end)
-                }),
-            )
-        })?;
-
-        fn hover_blocks_from_marked_string(marked_string: lsp::MarkedString) -> Option<HoverBlock> {
-            let block = match marked_string {
-                lsp::MarkedString::String(content) => HoverBlock {
-                    text: content,
-                    kind: HoverBlockKind::Markdown,
-                },
-                lsp::MarkedString::LanguageString(lsp::LanguageString { language, value }) => {
-                    HoverBlock {
-                        text: value,
-                        kind: HoverBlockKind::Code { language },
-                    }
-                }
-            };
-            if block.text.is_empty() {
-                None
-            } else {
-                Some(block)
-            }
-        }
-
-        let contents = match hover.contents {
-            lsp::HoverContents::Scalar(marked_string) => {
-                hover_blocks_from_marked_string(marked_string)
-                    .into_iter()
-                    .collect()
-            }
-            lsp::HoverContents::Array(marked_strings) => marked_strings
+    ) -> Result<Vec<Range<Anchor>>> {
+        // Only whole lines are folded, from the end of the start line to the end of the end
+        // line, as the client capabilities tell servers
+        buffer.update(&mut cx, |buffer, _| {
+            let max_row = buffer.max_point().row;
+            message
                 .into_iter()
-                .filter_map(hover_blocks_from_marked_string)
-                .collect(),
-            lsp::HoverContents::Markup(markup_content) => vec![HoverBlock {
-                text: markup_content.value,
-                kind: if markup_content.kind == lsp::MarkupKind::Markdown {
-                    HoverBlockKind::Markdown
-                } else {
-                    HoverBlockKind::PlainText
-                },
-            }],
-        };
-
-        Ok(Some(Hover {
-            contents,
-            range,
-            language,
-        }))
+                .flatten()
+                .filter(|range| range.start_line < range.end_line && range.end_line <= max_row)
+                .map(|range| {
+                    let start = Point::new(range.start_line, buffer.line_len(range.start_line));
+                    let end = Point::new(range.end_line, buffer.line_len(range.end_line));
+                    buffer.anchor_after(start)..buffer.anchor_before(end)
+                })
+                .collect()
+        })
     }
 
-    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> Self::ProtoRequest {
-        proto::GetHover {
+    fn to_proto(&self, project_id: u64, buffer: &Buffer) -> proto::GetFoldingRanges {
+        proto::GetFoldingRanges {
             project_id,
             buffer_id: buffer.remote_id(),
-            position: Some(language::proto::serialize_anchor(
-                &buffer.anchor_before(self.position),
-            )),
-            version: serialize_version(&buffer.version),
+            version: serialize_version(&buffer.version()),
         }
     }
 
     async fn from_proto(
-        message: Self::ProtoRequest,
+        message: proto::GetFoldingRanges,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
     ) -> Result<Self> {
-        let position = message
-            .position
-            .and_then(deserialize_anchor)
-            .ok_or_else(|| anyhow!("invalid position"))?;
         buffer
             .update(&mut cx, |buffer, _| {
                 buffer.wait_for_version(deserialize_version(&message.version))
             })?
             .await?;
-        Ok(Self {
-            position: buffer.update(&mut cx, |buffer, _| position.to_point_utf16(buffer))?,
-        })
+        Ok(Self)
     }
 
     fn response_to_proto(
-        response: Self::Response,
+        response: Vec<Range<Anchor>>,
         _: &mut Project,
         _: PeerId,
         _: &clock::Global,
         _: &mut AppContext,
-    ) -> proto::GetHoverResponse {
-        if let Some(response) = response {
-            let (start, end) = if let Some(range) = response.range {
-                (
-                    Some(language::proto::serialize_anchor(&range.start)),
-                    Some(language::proto::serialize_anchor(&range.end)),
-                )
-            } else {
-                (None, None)
-            };
-
-            let contents = response
-                .contents
+    ) -> proto::GetFoldingRangesResponse {
+        proto::GetFoldingRangesResponse {
+            ranges: response
                 .into_iter()
-                .map(|block| proto::HoverBlock {
-                    text: block.text,
-                    is_markdown: block.kind == HoverBlockKind::Markdown,
-                    language: if let HoverBlockKind::Code { language } = block.kind {
-                        Some(language)
-                    } else {
-                        None
-                    },
+                .map(|range| proto::FoldingRange {
+                    start: Some(serialize_anchor(&range.start)),
+                    end: Some(serialize_anchor(&range.end)),
                 })
-                .collect();
-
-            proto::GetHoverResponse {
-                start,
-                end,
-                contents,
-            }
-        } else {
-            proto::GetHoverResponse {
-                start: None,
-                end: None,
-                contents: Vec::new(),
-            }
+                .collect(),
         }
     }
 
     async fn response_from_proto(
         self,
-        message: proto::GetHoverResponse,
+        message: proto::GetFoldingRangesResponse,
         _: Model<Project>,
         buffer: Model<Buffer>,
         mut cx: AsyncAppContext,
-    ) -> Result<Self::Response> {
-        let contents: Vec<_> = message
-            .contents
-            .into_iter()
-            .map(|block| HoverBlock {
-                text: block.text,
-                kind: if let Some(language) = block.language {
-                    HoverBlockKind::Code { language }
-                } else if block.is_markdown {
-                    HoverBlockKind::Markdown
-                } else {
-                    HoverBlockKind::PlainText
-                },
-            })
-            .collect();
-        if contents.is_empty() {
-            return Ok(None);
+    ) -> Result<Vec<Range<Anchor>>> {
+        let mut ranges = Vec::new();
+        for range in message.ranges {
+            let start = range
+                .start
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing range start"))?;
+            let end = range
+                .end
+                .and_then(deserialize_anchor)
+                .ok_or_else(|| anyhow!("missing range end"))?;
+            ranges.push(start..end);
         }
-
-        let language = buffer.update(&mut cx, |buffer, _| buffer.language().cloned())?;
-        let range = if let (Some(start), Some(end)) = (message.start, message.end) {
-            language::proto::deserialize_anchor(start)
-                .and_then(|start| language::proto::deserialize_anchor(end).map(|end| start..end))
-        } else {
-            None
-        };
-
-        Ok(Some(Hover {
-            contents,
-            range,
-            language,
-        }))
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_anchors(ranges.iter().flat_map(|range| [range.start, range.end]))
+            })?
+            .await?;
+        Ok(ranges)
     }
 
-    fn buffer_id_from_proto(message: &Self::ProtoRequest) -> u64 {
+    fn buffer_id_from_proto(message: &proto::GetFoldingRanges) -> u64 {
         message.buffer_id
     }
 }
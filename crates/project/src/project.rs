@@ -4,6 +4,7 @@ pub mod lsp_ext_command;
 mod prettier_support;
 pub mod project_settings;
 pub mod search;
+mod semantic_tokens;
 pub mod terminals;
 pub mod worktree;
 
@@ -57,7 +58,9 @@ use postage::watch;
 use prettier_support::{DefaultPrettier, PrettierInstance};
 use project_settings::{LspSettings, ProjectSettings};
 use rand::prelude::*;
+use regex::Regex;
 use search::SearchQuery;
+use semantic_tokens::CachedSemanticTokens;
 use serde::Serialize;
 use settings::{Settings, SettingsStore};
 use sha2::{Digest, Sha256};
@@ -90,6 +93,7 @@ use util::{
 pub use fs::*;
 #[cfg(any(test, feature = "test-support"))]
 pub use prettier::FORMAT_SUFFIX as TEST_PRETTIER_FORMAT_SUFFIX;
+pub use semantic_tokens::SemanticToken;
 pub use worktree::*;
 
 const MAX_SERVER_REINSTALL_ATTEMPT_COUNT: u64 = 4;
@@ -138,6 +142,7 @@ pub struct Project {
     /// Used for re-issuing buffer requests when peers temporarily disconnect
     incomplete_remote_buffers: HashMap<u64, Option<Model<Buffer>>>,
     buffer_snapshots: HashMap<u64, HashMap<LanguageServerId, Vec<LspBufferSnapshot>>>, // buffer_id -> server_id -> vec of snapshots
+    semantic_tokens_cache: HashMap<u64, CachedSemanticTokens>,
     buffers_being_formatted: HashSet<u64>,
     buffers_needing_diff: HashSet<WeakModel<Buffer>>,
     git_diff_debouncer: DelayedDebounced,
@@ -407,6 +412,57 @@ pub struct DocumentHighlight {
     pub kind: DocumentHighlightKind,
 }
 
+/// A function, method or constructor from a language server's document symbols, with the
+/// names of its parameters.
+#[derive(Clone, Debug)]
+pub struct FunctionSymbol {
+    pub name: String,
+    pub range: Range<language::Anchor>,
+    pub parameters: Vec<String>,
+}
+
+/// A symbol from a language server's document symbols, along with the symbols nested in it.
+#[derive(Clone, Debug)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: lsp::SymbolKind,
+    pub detail: Option<String>,
+    /// The symbol's whole extent, including its body.
+    pub range: Range<language::Anchor>,
+    /// The symbol's name.
+    pub selection_range: Range<language::Anchor>,
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// A command that a language server shows above a range of a buffer, such as a count of the
+/// references to a symbol.
+#[derive(Clone, Debug)]
+pub struct CodeLens {
+    pub server_id: LanguageServerId,
+    pub range: Range<language::Anchor>,
+    pub lsp_lens: lsp::CodeLens,
+}
+
+/// A function or method in a language server's call hierarchy.
+#[derive(Clone, Debug)]
+pub struct CallHierarchyItem {
+    pub name: String,
+    pub kind: lsp::SymbolKind,
+    pub detail: Option<String>,
+    /// The item's name, where it's declared.
+    pub location: Location,
+    pub(crate) lsp_item: lsp::CallHierarchyItem,
+}
+
+/// A call to or from an item in a call hierarchy.
+#[derive(Clone, Debug)]
+pub struct CallHierarchyCall {
+    /// The caller for incoming calls, or the callee for outgoing calls.
+    pub item: CallHierarchyItem,
+    /// Where the callee is called, within the caller.
+    pub call_sites: Vec<Location>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Symbol {
     pub language_server_name: LanguageServerName,
@@ -445,6 +501,30 @@ impl Hover {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureHelp {
+    pub signatures: Vec<SignatureInformation>,
+    pub active_signature: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureInformation {
+    pub label: String,
+    pub documentation: Option<String>,
+    /// The byte ranges of the signature's parameters within its label.
+    pub parameters: Vec<Range<usize>>,
+    pub active_parameter: Option<usize>,
+}
+
+/// Ranges of a buffer that have the same content and are edited together, such as
+/// the names of an HTML element's opening and closing tags.
+#[derive(Clone, Debug, Default)]
+pub struct LinkedEditingRanges {
+    pub ranges: Vec<Range<Anchor>>,
+    /// The content that the ranges may be edited to while staying linked.
+    pub word_pattern: Option<Regex>,
+}
+
 #[derive(Default)]
 pub struct ProjectTransaction(pub HashMap<Model<Buffer>, language::Transaction>);
 
@@ -592,6 +672,7 @@ impl Project {
         client.add_model_request_handler(Self::handle_apply_additional_edits_for_completion);
         client.add_model_request_handler(Self::handle_resolve_completion_documentation);
         client.add_model_request_handler(Self::handle_apply_code_action);
+        client.add_model_request_handler(Self::handle_execute_code_lens);
         client.add_model_request_handler(Self::handle_on_type_formatting);
         client.add_model_request_handler(Self::handle_inlay_hints);
         client.add_model_request_handler(Self::handle_resolve_inlay_hint);
@@ -600,12 +681,26 @@ impl Project {
         client.add_model_request_handler(Self::handle_synchronize_buffers);
         client.add_model_request_handler(Self::handle_format_buffers);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCodeActions>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetCodeLenses>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetCompletions>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetHover>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSignatureHelp>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetLinkedEditingRanges>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetSelectionRanges>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetFoldingRanges>);
+        client.add_model_request_handler(Self::handle_get_semantic_tokens);
         client.add_model_request_handler(Self::handle_lsp_command::<GetDefinition>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetDeclaration>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetTypeDefinition>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetImplementation>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetEnclosingSymbols>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetFunctionSymbol>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetDocumentSymbols>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetDocumentHighlights>);
         client.add_model_request_handler(Self::handle_lsp_command::<GetReferences>);
+        client.add_model_request_handler(Self::handle_lsp_command::<PrepareCallHierarchy>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetIncomingCalls>);
+        client.add_model_request_handler(Self::handle_lsp_command::<GetOutgoingCalls>);
         client.add_model_request_handler(Self::handle_lsp_command::<PrepareRename>);
         client.add_model_request_handler(Self::handle_lsp_command::<PerformRename>);
         client.add_model_request_handler(Self::handle_search_project);
@@ -645,6 +740,7 @@ impl Project {
                 local_buffer_ids_by_path: Default::default(),
                 local_buffer_ids_by_entry_id: Default::default(),
                 buffer_snapshots: Default::default(),
+                semantic_tokens_cache: Default::default(),
                 join_project_response_message_id: 0,
                 client_state: ProjectClientState::Local,
                 opened_buffer: watch::channel(),
@@ -776,6 +872,7 @@ impl Project {
                 buffers_needing_diff: Default::default(),
                 git_diff_debouncer: DelayedDebounced::new(),
                 buffer_snapshots: Default::default(),
+                semantic_tokens_cache: Default::default(),
                 nonce: StdRng::from_entropy().gen(),
                 terminals: Terminals {
                     local_handles: Vec::new(),
@@ -2190,6 +2287,7 @@ impl Project {
             }
 
             self.buffer_snapshots.remove(&buffer.remote_id());
+            self.semantic_tokens_cache.remove(&buffer.remote_id());
             let file_url = lsp::Url::from_file_path(old_path).unwrap();
             for (_, language_server) in self.language_servers_for_buffer(buffer, cx) {
                 language_server
@@ -4343,6 +4441,94 @@ impl Project {
         }
     }
 
+    /// Formats the given ranges of each buffer with its language server's range formatting,
+    /// regardless of the `formatter` setting. Only local projects are supported for now.
+    pub fn format_ranges(
+        &mut self,
+        ranges: HashMap<Model<Buffer>, Vec<Range<Anchor>>>,
+        push_to_history: bool,
+        cx: &mut ModelContext<Project>,
+    ) -> Task<Result<ProjectTransaction>> {
+        if !self.is_local() {
+            return Task::ready(Err(anyhow!(
+                "formatting selections isn't supported in remote projects"
+            )));
+        }
+
+        let buffers_with_servers = ranges
+            .into_iter()
+            .filter_map(|(buffer_handle, ranges)| {
+                let buffer = buffer_handle.read(cx);
+                let abs_path = File::from_dyn(buffer.file())?.as_local()?.abs_path(cx);
+                let (_, server) =
+                    self.language_servers_for_buffer(buffer, cx)
+                        .find(|(_, server)| {
+                            !matches!(
+                                server.capabilities().document_range_formatting_provider,
+                                None | Some(OneOf::Left(false))
+                            )
+                        })?;
+                let tab_size = language_settings(buffer.language(), buffer.file(), cx).tab_size;
+                Some((buffer_handle, ranges, abs_path, server.clone(), tab_size))
+            })
+            .collect::<Vec<_>>();
+
+        cx.spawn(move |project, mut cx| async move {
+            let mut project_transaction = ProjectTransaction::default();
+            for (buffer, ranges, abs_path, language_server, tab_size) in buffers_with_servers {
+                let uri = lsp::Url::from_file_path(&abs_path)
+                    .map_err(|_| anyhow!("failed to convert abs path to uri"))?;
+                let mut edits = Vec::new();
+                for range in ranges {
+                    let range = buffer.update(&mut cx, |buffer, _| {
+                        range_to_lsp(range.to_point_utf16(&buffer.snapshot()))
+                    })?;
+                    let lsp_edits = language_server
+                        .request::<lsp::request::RangeFormatting>(
+                            lsp::DocumentRangeFormattingParams {
+                                text_document: lsp::TextDocumentIdentifier::new(uri.clone()),
+                                range,
+                                options: lsp_command::lsp_formatting_options(tab_size.get()),
+                                work_done_progress_params: Default::default(),
+                            },
+                        )
+                        .await
+                        .context("failed to format via language server")?;
+                    if let Some(lsp_edits) = lsp_edits {
+                        edits.extend(
+                            project
+                                .update(&mut cx, |project, cx| {
+                                    project.edits_from_lsp(
+                                        &buffer,
+                                        lsp_edits,
+                                        language_server.server_id(),
+                                        None,
+                                        cx,
+                                    )
+                                })?
+                                .await?,
+                        );
+                    }
+                }
+
+                buffer.update(&mut cx, |buffer, cx| {
+                    buffer.finalize_last_transaction();
+                    buffer.start_transaction();
+                    buffer.edit(edits, None, cx);
+                    if buffer.end_transaction(cx).is_some() {
+                        if let Some(transaction) = buffer.finalize_last_transaction().cloned() {
+                            if !push_to_history {
+                                buffer.forget_transaction(transaction.id);
+                            }
+                            project_transaction.0.insert(cx.handle(), transaction);
+                        }
+                    }
+                })?;
+            }
+            Ok(project_transaction)
+        })
+    }
+
     async fn format_via_lsp(
         this: &WeakModel<Self>,
         buffer: &Model<Buffer>,
@@ -4467,6 +4653,21 @@ impl Project {
         )
     }
 
+    pub fn declaration<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<LocationLink>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetDeclaration { position },
+            cx,
+        )
+    }
+
     pub fn type_definition<T: ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -4482,6 +4683,116 @@ impl Project {
         )
     }
 
+    pub fn implementation<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<LocationLink>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetImplementation { position },
+            cx,
+        )
+    }
+
+    /// Returns the names of the document symbols enclosing `position`, outermost first.
+    pub fn enclosing_symbols<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<String>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetEnclosingSymbols { position },
+            cx,
+        )
+    }
+
+    /// Returns the innermost function-like document symbol containing `position`.
+    pub fn function_symbol<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Option<FunctionSymbol>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetFunctionSymbol { position },
+            cx,
+        )
+    }
+
+    /// Returns the symbols declared in the buffer, as a tree.
+    pub fn document_symbols(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<DocumentSymbol>>> {
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetDocumentSymbols,
+            cx,
+        )
+    }
+
+    /// Returns the call hierarchy items at `position`, whose callers and callees can then be
+    /// requested with [`Project::incoming_calls`] and [`Project::outgoing_calls`].
+    pub fn prepare_call_hierarchy<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyItem>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            PrepareCallHierarchy { position },
+            cx,
+        )
+    }
+
+    /// Returns the calls to the given item.
+    pub fn incoming_calls(
+        &self,
+        item: &CallHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyCall>>> {
+        self.request_lsp(
+            item.location.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetIncomingCalls {
+                item: item.lsp_item.clone(),
+            },
+            cx,
+        )
+    }
+
+    /// Returns the calls made by the given item.
+    pub fn outgoing_calls(
+        &self,
+        item: &CallHierarchyItem,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CallHierarchyCall>>> {
+        self.request_lsp(
+            item.location.buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetOutgoingCalls {
+                item: item.lsp_item.clone(),
+            },
+            cx,
+        )
+    }
+
     pub fn references<T: ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -4746,6 +5057,111 @@ impl Project {
         )
     }
 
+    /// Requests the signature help at the given position. `trigger_character` is
+    /// the character whose input caused the request, and `is_retrigger` is set
+    /// when signature help is already showing.
+    pub fn signature_help<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        trigger_character: Option<String>,
+        is_retrigger: bool,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Option<SignatureHelp>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSignatureHelp {
+                position,
+                trigger_character,
+                is_retrigger,
+            },
+            cx,
+        )
+    }
+
+    /// Returns whether typing `text` in the buffer should request signature help,
+    /// either as a new request or, if `is_retrigger`, as an update of the one shown.
+    pub fn is_signature_help_trigger(
+        &self,
+        buffer: &Model<Buffer>,
+        text: &str,
+        is_retrigger: bool,
+        cx: &AppContext,
+    ) -> bool {
+        let buffer = buffer.read(cx);
+        if self.is_local() {
+            let Some((_, server)) = self.primary_language_server_for_buffer(buffer, cx) else {
+                return false;
+            };
+            let Some(options) = server.capabilities().signature_help_provider.as_ref() else {
+                return false;
+            };
+            let retrigger_characters = options
+                .retrigger_characters
+                .as_ref()
+                .filter(|_| is_retrigger);
+            options
+                .trigger_characters
+                .iter()
+                .chain(retrigger_characters)
+                .flatten()
+                .any(|trigger| trigger == text)
+        } else {
+            // Remote projects don't know the host's server capabilities, so fall
+            // back to the characters that start and separate argument lists.
+            buffer.language().is_some() && matches!(text, "(" | ",")
+        }
+    }
+
+    pub fn linked_editing_ranges<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<LinkedEditingRanges>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetLinkedEditingRanges { position },
+            cx,
+        )
+    }
+
+    /// Returns the ranges that expanding a selection at the given position selects, from
+    /// the innermost range to the outermost one.
+    pub fn selection_ranges<T: ToPointUtf16>(
+        &self,
+        buffer: &Model<Buffer>,
+        position: T,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<Range<Anchor>>>> {
+        let position = position.to_point_utf16(buffer.read(cx));
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetSelectionRanges { position },
+            cx,
+        )
+    }
+
+    /// Returns the ranges of whole lines that the language server suggests folding, each
+    /// from the end of its first line to the end of its last line.
+    pub fn folding_ranges(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<Range<Anchor>>>> {
+        self.request_lsp(
+            buffer.clone(),
+            LanguageServerToQuery::Primary,
+            GetFoldingRanges,
+            cx,
+        )
+    }
+
     pub fn completions<T: ToOffset + ToPointUtf16>(
         &self,
         buffer: &Model<Buffer>,
@@ -5149,29 +5565,13 @@ impl Project {
                 }
 
                 if let Some(command) = action.lsp_action.command {
-                    this.update(&mut cx, |this, _| {
-                        this.last_workspace_edits_by_language_server
-                            .remove(&lang_server.server_id());
-                    })?;
-
-                    let result = lang_server
-                        .request::<lsp::request::ExecuteCommand>(lsp::ExecuteCommandParams {
-                            command: command.command,
-                            arguments: command.arguments.unwrap_or_default(),
-                            ..Default::default()
-                        })
-                        .await;
-
-                    if let Err(err) = result {
-                        // TODO: LSP ERROR
-                        return Err(err);
-                    }
-
-                    return Ok(this.update(&mut cx, |this, _| {
-                        this.last_workspace_edits_by_language_server
-                            .remove(&lang_server.server_id())
-                            .unwrap_or_default()
-                    })?);
+                    return Self::execute_language_server_command(
+                        this,
+                        lang_server,
+                        command,
+                        &mut cx,
+                    )
+                    .await;
                 }
 
                 Ok(ProjectTransaction::default())
@@ -5199,6 +5599,90 @@ impl Project {
         }
     }
 
+    /// Runs a language server command, returning the edits that the server asked to apply
+    /// while running it.
+    async fn execute_language_server_command(
+        this: WeakModel<Self>,
+        language_server: Arc<LanguageServer>,
+        command: lsp::Command,
+        cx: &mut AsyncAppContext,
+    ) -> Result<ProjectTransaction> {
+        this.update(cx, |this, _| {
+            this.last_workspace_edits_by_language_server
+                .remove(&language_server.server_id());
+        })?;
+
+        language_server
+            .request::<lsp::request::ExecuteCommand>(lsp::ExecuteCommandParams {
+                command: command.command,
+                arguments: command.arguments.unwrap_or_default(),
+                ..Default::default()
+            })
+            .await?;
+
+        this.update(cx, |this, _| {
+            this.last_workspace_edits_by_language_server
+                .remove(&language_server.server_id())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn code_lenses(
+        &self,
+        buffer_handle: &Model<Buffer>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<CodeLens>>> {
+        self.request_lsp(
+            buffer_handle.clone(),
+            LanguageServerToQuery::Primary,
+            GetCodeLenses,
+            cx,
+        )
+    }
+
+    /// Runs a code lens's command on the language server that provided it.
+    pub fn execute_code_lens(
+        &self,
+        buffer_handle: Model<Buffer>,
+        lens: CodeLens,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        if self.is_local() {
+            let Some(command) = lens.lsp_lens.command.clone() else {
+                return Task::ready(Ok(Default::default()));
+            };
+            let Some((_, lang_server)) =
+                self.language_server_for_buffer(buffer_handle.read(cx), lens.server_id, cx)
+            else {
+                return Task::ready(Ok(Default::default()));
+            };
+            let lang_server = lang_server.clone();
+            cx.spawn(move |this, mut cx| async move {
+                Self::execute_language_server_command(this, lang_server, command, &mut cx).await
+            })
+        } else if let Some(project_id) = self.remote_id() {
+            let client = self.client.clone();
+            let request = proto::ExecuteCodeLens {
+                project_id,
+                buffer_id: buffer_handle.read(cx).remote_id(),
+                lens: Some(code_lens_to_proto(&lens)),
+            };
+            cx.spawn(move |this, mut cx| async move {
+                let response = client
+                    .request(request)
+                    .await?
+                    .transaction
+                    .ok_or_else(|| anyhow!("missing transaction"))?;
+                this.update(&mut cx, |this, cx| {
+                    this.deserialize_project_transaction(response, true, cx)
+                })?
+                .await
+            })
+        } else {
+            Task::ready(Err(anyhow!("project does not have a remote id")))
+        }
+    }
+
     fn apply_on_type_formatting(
         &self,
         buffer: Model<Buffer>,
@@ -5493,6 +5977,112 @@ impl Project {
         )
     }
 
+    /// Requests the edits which renaming the symbol at `position` would make, grouped by buffer,
+    /// without applying them so that they can be previewed. Returns `None` when the rename can't
+    /// be previewed, because the project is remote or the rename would also create, rename or
+    /// delete files, in which case it should be performed with `perform_rename` instead.
+    pub fn rename_edits<T: ToPointUtf16>(
+        &mut self,
+        buffer: Model<Buffer>,
+        position: T,
+        new_name: String,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Option<Vec<(Model<Buffer>, Vec<(Range<Anchor>, String)>)>>>> {
+        if !self.is_local() {
+            return Task::ready(Ok(None));
+        }
+        let buffer = buffer.read(cx);
+        let position = position.to_point_utf16(buffer);
+        let Some(abs_path) = File::from_dyn(buffer.file())
+            .and_then(|file| file.as_local())
+            .map(|file| file.abs_path(cx))
+        else {
+            return Task::ready(Ok(None));
+        };
+        let Some((adapter, server)) = self
+            .primary_language_server_for_buffer(buffer, cx)
+            .map(|(adapter, server)| (adapter.clone(), server.clone()))
+        else {
+            return Task::ready(Ok(None));
+        };
+
+        cx.spawn(move |this, mut cx| async move {
+            let uri = lsp::Url::from_file_path(&abs_path)
+                .map_err(|_| anyhow!("failed to convert abs path to uri"))?;
+            let workspace_edit = server
+                .request::<lsp::request::Rename>(lsp::RenameParams {
+                    text_document_position: lsp::TextDocumentPositionParams::new(
+                        lsp::TextDocumentIdentifier::new(uri),
+                        point_to_lsp(position),
+                    ),
+                    new_name,
+                    work_done_progress_params: Default::default(),
+                })
+                .await?;
+            let Some(workspace_edit) = workspace_edit else {
+                return Ok(Some(Vec::new()));
+            };
+
+            let document_edits = match (workspace_edit.document_changes, workspace_edit.changes) {
+                (Some(lsp::DocumentChanges::Edits(edits)), _) => edits,
+                (Some(lsp::DocumentChanges::Operations(operations)), _) => {
+                    let mut edits = Vec::new();
+                    for operation in operations {
+                        match operation {
+                            lsp::DocumentChangeOperation::Edit(edit) => edits.push(edit),
+                            lsp::DocumentChangeOperation::Op(_) => return Ok(None),
+                        }
+                    }
+                    edits
+                }
+                (None, Some(changes)) => changes
+                    .into_iter()
+                    .map(|(uri, edits)| lsp::TextDocumentEdit {
+                        text_document: lsp::OptionalVersionedTextDocumentIdentifier {
+                            uri,
+                            version: None,
+                        },
+                        edits: edits.into_iter().map(OneOf::Left).collect(),
+                    })
+                    .collect(),
+                (None, None) => Vec::new(),
+            };
+
+            let mut edits_by_buffer = Vec::new();
+            for document_edit in document_edits {
+                let buffer = this
+                    .update(&mut cx, |this, cx| {
+                        this.open_local_buffer_via_lsp(
+                            document_edit.text_document.uri,
+                            server.server_id(),
+                            adapter.name.clone(),
+                            cx,
+                        )
+                    })?
+                    .await?;
+                let edits = this
+                    .update(&mut cx, |this, cx| {
+                        let edits = document_edit.edits.into_iter().map(|edit| match edit {
+                            OneOf::Left(edit) => edit,
+                            OneOf::Right(edit) => edit.text_edit,
+                        });
+                        this.edits_from_lsp(
+                            &buffer,
+                            edits,
+                            server.server_id(),
+                            document_edit.text_document.version,
+                            cx,
+                        )
+                    })?
+                    .await?;
+                if !edits.is_empty() {
+                    edits_by_buffer.push((buffer, edits));
+                }
+            }
+            Ok(Some(edits_by_buffer))
+        })
+    }
+
     pub fn on_type_format<T: ToPointUtf16>(
         &self,
         buffer: Model<Buffer>,
@@ -6924,6 +7514,46 @@ impl Project {
         })
     }
 
+    /// Runs `git blame` on the buffer's content, which may have unsaved changes. Only
+    /// buffers of local files in a git repository can be blamed.
+    pub fn blame_buffer(
+        &self,
+        buffer: &Model<Buffer>,
+        cx: &AppContext,
+    ) -> Task<Result<git::blame::Blame>> {
+        let buffer = buffer.read(cx);
+        let Some(file) = File::from_dyn(buffer.file()) else {
+            return Task::ready(Err(anyhow!("buffer has no file")));
+        };
+        let Some(worktree) = file.worktree.read(cx).as_local() else {
+            return Task::ready(Err(anyhow!(
+                "blaming buffers of remote projects is not supported"
+            )));
+        };
+        let snapshot = worktree.snapshot();
+        let Some((work_directory, repo)) = snapshot.local_repo_for_path(&file.path) else {
+            return Task::ready(Err(anyhow!("{:?} is not in a git repository", file.path)));
+        };
+        let Ok(relative_path) = file.path.strip_prefix(&work_directory) else {
+            return Task::ready(Err(anyhow!("{:?} is not in a git repository", file.path)));
+        };
+
+        let working_directory = worktree.abs_path().join(&work_directory);
+        let relative_path = relative_path.to_path_buf();
+        let remote_url = repo.repo_ptr.lock().remote_url("origin");
+        let content = buffer.as_rope().clone();
+        cx.background_executor().spawn(async move {
+            git::blame::Blame::for_path(
+                Path::new("git"),
+                &working_directory,
+                &relative_path,
+                &content,
+                remote_url,
+            )
+            .await
+        })
+    }
+
     // RPC message handlers
 
     async fn handle_unshare_project(
@@ -7792,6 +8422,37 @@ impl Project {
         })
     }
 
+    async fn handle_execute_code_lens(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::ExecuteCodeLens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::ExecuteCodeLensResponse> {
+        let sender_id = envelope.original_sender_id()?;
+        let lens = code_lens_from_proto(
+            envelope
+                .payload
+                .lens
+                .ok_or_else(|| anyhow!("invalid code lens"))?,
+        )?;
+        let execute_code_lens = this.update(&mut cx, |this, cx| {
+            let buffer = this
+                .opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))?;
+            Ok::<_, anyhow::Error>(this.execute_code_lens(buffer, lens, cx))
+        })??;
+
+        let project_transaction = execute_code_lens.await?;
+        let project_transaction = this.update(&mut cx, |this, cx| {
+            this.serialize_project_transaction_for_peer(project_transaction, sender_id, cx)
+        })?;
+        Ok(proto::ExecuteCodeLensResponse {
+            transaction: Some(project_transaction),
+        })
+    }
+
     async fn handle_on_type_formatting(
         this: Model<Self>,
         envelope: TypedEnvelope<proto::OnTypeFormatting>,
@@ -43,6 +43,24 @@ pub struct GitSettings {
     /// Default: tracked_files
     pub git_gutter: Option<GitGutterSetting>,
     pub gutter_debounce: Option<u64>,
+    /// Whether or not to show the commit that last changed the
+    /// line of the cursor at the end of the line.
+    ///
+    /// Default: off
+    pub inline_blame: Option<InlineBlameSettings>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct InlineBlameSettings {
+    /// Whether or not to show the inline blame.
+    ///
+    /// Default: true
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema)]
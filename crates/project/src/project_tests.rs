@@ -2343,6 +2343,214 @@ async fn test_definition(cx: &mut gpui::TestAppContext) {
     }
 }
 
+#[gpui::test]
+async fn test_call_hierarchy(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                call_hierarchy_provider: Some(lsp::CallHierarchyServerCapability::Simple(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.rs": "fn a() {}",
+            "b.rs": "fn b() { crate::a() }",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    fn lsp_item(name: &str, path: &str, start: u32, end: u32) -> lsp::CallHierarchyItem {
+        let range = lsp::Range::new(lsp::Position::new(0, start), lsp::Position::new(0, end));
+        lsp::CallHierarchyItem {
+            name: name.to_string(),
+            kind: lsp::SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: lsp::Url::from_file_path(path).unwrap(),
+            range,
+            selection_range: range,
+            data: None,
+        }
+    }
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::CallHierarchyPrepare, _, _>(
+        |params, _| async move {
+            assert_eq!(
+                params.text_document_position_params.position,
+                lsp::Position::new(0, 3)
+            );
+            Ok(Some(vec![lsp_item("a", "/dir/a.rs", 3, 4)]))
+        },
+    );
+    fake_server.handle_request::<lsp::request::CallHierarchyIncomingCalls, _, _>(
+        |params, _| async move {
+            assert_eq!(params.item.name, "a");
+            Ok(Some(vec![lsp::CallHierarchyIncomingCall {
+                from: lsp_item("b", "/dir/b.rs", 3, 4),
+                from_ranges: vec![lsp::Range::new(
+                    lsp::Position::new(0, 9),
+                    lsp::Position::new(0, 17),
+                )],
+            }]))
+        },
+    );
+
+    let mut items = project
+        .update(cx, |project, cx| {
+            project.prepare_call_hierarchy(&buffer, 3, cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    let item = items.pop().unwrap();
+    assert_eq!(item.name, "a");
+
+    let calls = project
+        .update(cx, |project, cx| project.incoming_calls(&item, cx))
+        .await
+        .unwrap();
+    assert_eq!(calls.len(), 1);
+    cx.update(|cx| {
+        let call = &calls[0];
+        assert_eq!(call.item.name, "b");
+        let caller_buffer = call.item.location.buffer.read(cx);
+        assert_eq!(caller_buffer.text(), "fn b() { crate::a() }");
+        assert_eq!(call.item.location.range.to_offset(caller_buffer), 3..4);
+        assert_eq!(
+            call.call_sites
+                .iter()
+                .map(|site| site.range.to_offset(caller_buffer))
+                .collect::<Vec<_>>(),
+            [9..17]
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_document_symbols(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let mut language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_servers = language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                document_symbol_provider: Some(lsp::OneOf::Left(true)),
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.rs": "mod a {\n    fn b() {}\n    fn c() {}\n}\nfn d() {}\n",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| project.languages.add(Arc::new(language)));
+
+    let buffer = project
+        .update(cx, |project, cx| project.open_local_buffer("/dir/a.rs", cx))
+        .await
+        .unwrap();
+
+    #[allow(deprecated)]
+    fn symbol(
+        name: &str,
+        kind: lsp::SymbolKind,
+        start: (u32, u32),
+        end: (u32, u32),
+    ) -> lsp::SymbolInformation {
+        lsp::SymbolInformation {
+            name: name.to_string(),
+            kind,
+            tags: None,
+            deprecated: None,
+            location: lsp::Location::new(
+                lsp::Url::from_file_path("/dir/a.rs").unwrap(),
+                lsp::Range::new(
+                    lsp::Position::new(start.0, start.1),
+                    lsp::Position::new(end.0, end.1),
+                ),
+            ),
+            container_name: None,
+        }
+    }
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.handle_request::<lsp::request::DocumentSymbolRequest, _, _>(|_, _| async move {
+        // Flat symbols, listed out of order
+        Ok(Some(lsp::DocumentSymbolResponse::Flat(vec![
+            symbol("d", lsp::SymbolKind::FUNCTION, (4, 0), (4, 9)),
+            symbol("c", lsp::SymbolKind::FUNCTION, (2, 4), (2, 13)),
+            symbol("a", lsp::SymbolKind::MODULE, (0, 0), (3, 1)),
+            symbol("b", lsp::SymbolKind::FUNCTION, (1, 4), (1, 13)),
+        ])))
+    });
+
+    let symbols = project
+        .update(cx, |project, cx| project.document_symbols(&buffer, cx))
+        .await
+        .unwrap();
+
+    fn names(symbols: &[DocumentSymbol]) -> Vec<(String, Vec<String>)> {
+        symbols
+            .iter()
+            .map(|symbol| {
+                let children = symbol.children.iter().map(|child| child.name.clone());
+                (symbol.name.clone(), children.collect())
+            })
+            .collect()
+    }
+    assert_eq!(
+        names(&symbols),
+        [
+            ("a".to_string(), vec!["b".to_string(), "c".to_string()]),
+            ("d".to_string(), vec![]),
+        ]
+    );
+    cx.update(|cx| {
+        let buffer = buffer.read(cx);
+        assert_eq!(symbols[0].children[1].range.to_offset(buffer), 26..35);
+    });
+}
+
 #[gpui::test]
 async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -0,0 +1,340 @@
+use std::{cmp::Reverse, ops::Range, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use client::{proto, Client, TypedEnvelope};
+use gpui::{AsyncAppContext, Model, ModelContext, Task};
+use language::{
+    point_to_lsp,
+    proto::{deserialize_anchor, deserialize_version, serialize_anchor, serialize_version},
+    Anchor, Bias, Buffer, BufferSnapshot, PointUtf16, ToPointUtf16, Unclipped,
+};
+use lsp::{LanguageServerId, SemanticTokensFullOptions, SemanticTokensServerCapabilities};
+
+use crate::{File, Project};
+
+/// A range of a buffer that a language server classified, such as a call of a
+/// deprecated function, to highlight it beyond what its syntax tree tells apart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticToken {
+    pub range: Range<Anchor>,
+    pub token_type: String,
+    pub modifiers: Vec<String>,
+}
+
+/// The last tokens a language server sent for a buffer, which its next
+/// delta response is applied to.
+pub(crate) struct CachedSemanticTokens {
+    server_id: LanguageServerId,
+    result_id: String,
+    data: Vec<lsp::SemanticToken>,
+}
+
+impl Project {
+    /// Requests the semantic tokens of the buffer from its primary language server.
+    /// Servers that can't provide the tokens of a whole buffer are asked for the
+    /// ones in `range`.
+    pub fn semantic_tokens(
+        &mut self,
+        buffer: &Model<Buffer>,
+        range: Range<Anchor>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<SemanticToken>>> {
+        if self.is_local() {
+            self.local_semantic_tokens(buffer, range, cx)
+        } else if let Some(project_id) = self.remote_id() {
+            let buffer = buffer.clone();
+            let request = {
+                let buffer = buffer.read(cx);
+                proto::GetSemanticTokens {
+                    project_id,
+                    buffer_id: buffer.remote_id(),
+                    start: Some(serialize_anchor(&range.start)),
+                    end: Some(serialize_anchor(&range.end)),
+                    version: serialize_version(&buffer.version()),
+                }
+            };
+            let client = self.client.clone();
+            cx.spawn(move |_, mut cx| async move {
+                let response = client.request(request).await?;
+                let tokens = response
+                    .tokens
+                    .into_iter()
+                    .map(semantic_token_from_proto)
+                    .collect::<Result<Vec<_>>>()?;
+                buffer
+                    .update(&mut cx, |buffer, _| {
+                        buffer.wait_for_anchors(
+                            tokens
+                                .iter()
+                                .flat_map(|token| [token.range.start, token.range.end]),
+                        )
+                    })?
+                    .await?;
+                Ok(tokens)
+            })
+        } else {
+            Task::ready(Ok(Vec::new()))
+        }
+    }
+
+    fn local_semantic_tokens(
+        &mut self,
+        buffer_handle: &Model<Buffer>,
+        range: Range<Anchor>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<SemanticToken>>> {
+        let buffer = buffer_handle.read(cx);
+        let Some(file) = File::from_dyn(buffer.file()).and_then(File::as_local) else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        let Some((_, server)) = self.primary_language_server_for_buffer(buffer, cx) else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        let options = match server.capabilities().semantic_tokens_provider.clone() {
+            Some(SemanticTokensServerCapabilities::SemanticTokensOptions(options)) => options,
+            Some(SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(options)) => {
+                options.semantic_tokens_options
+            }
+            None => return Task::ready(Ok(Vec::new())),
+        };
+
+        let server = server.clone();
+        let server_id = server.server_id();
+        let buffer_id = buffer.remote_id();
+        let snapshot = buffer.snapshot();
+        let text_document = lsp::TextDocumentIdentifier {
+            uri: lsp::Url::from_file_path(file.abs_path(cx)).unwrap(),
+        };
+        let (supports_full, supports_delta) = match options.full {
+            Some(SemanticTokensFullOptions::Bool(full)) => (full, false),
+            Some(SemanticTokensFullOptions::Delta { delta }) => (true, delta == Some(true)),
+            None => (false, false),
+        };
+        let previous_result_id = self
+            .semantic_tokens_cache
+            .get(&buffer_id)
+            .filter(|cached| supports_delta && cached.server_id == server_id)
+            .map(|cached| cached.result_id.clone());
+        let range = range.start.to_point_utf16(&snapshot)..range.end.to_point_utf16(&snapshot);
+        let legend = options.legend;
+
+        cx.spawn(move |this, mut cx| async move {
+            let data = if let Some(previous_result_id) = previous_result_id {
+                let response = server
+                    .request::<lsp::request::SemanticTokensFullDeltaRequest>(
+                        lsp::SemanticTokensDeltaParams {
+                            text_document,
+                            previous_result_id: previous_result_id.clone(),
+                            work_done_progress_params: Default::default(),
+                            partial_result_params: Default::default(),
+                        },
+                    )
+                    .await?;
+                match response {
+                    Some(lsp::SemanticTokensFullDeltaResult::Tokens(tokens)) => {
+                        cache_semantic_tokens(&this, buffer_id, server_id, &tokens, &mut cx)?;
+                        tokens.data
+                    }
+                    Some(lsp::SemanticTokensFullDeltaResult::TokensDelta(delta)) => {
+                        this.update(&mut cx, |this, _| {
+                            let cached = this
+                                .semantic_tokens_cache
+                                .get_mut(&buffer_id)
+                                .filter(|cached| cached.result_id == previous_result_id)
+                                .ok_or_else(|| anyhow!("semantic tokens changed during request"))?;
+                            apply_semantic_token_edits(&mut cached.data, delta.edits);
+                            let data = cached.data.clone();
+                            match delta.result_id {
+                                Some(result_id) => cached.result_id = result_id,
+                                None => drop(this.semantic_tokens_cache.remove(&buffer_id)),
+                            }
+                            anyhow::Ok(data)
+                        })??
+                    }
+                    Some(lsp::SemanticTokensFullDeltaResult::PartialTokensDelta { .. }) | None => {
+                        Vec::new()
+                    }
+                }
+            } else if supports_full {
+                let response = server
+                    .request::<lsp::request::SemanticTokensFullRequest>(lsp::SemanticTokensParams {
+                        text_document,
+                        work_done_progress_params: Default::default(),
+                        partial_result_params: Default::default(),
+                    })
+                    .await?;
+                match response {
+                    Some(lsp::SemanticTokensResult::Tokens(tokens)) => {
+                        cache_semantic_tokens(&this, buffer_id, server_id, &tokens, &mut cx)?;
+                        tokens.data
+                    }
+                    Some(lsp::SemanticTokensResult::Partial(partial)) => partial.data,
+                    None => Vec::new(),
+                }
+            } else if options.range == Some(true) {
+                let response = server
+                    .request::<lsp::request::SemanticTokensRangeRequest>(
+                        lsp::SemanticTokensRangeParams {
+                            text_document,
+                            range: lsp::Range::new(
+                                point_to_lsp(range.start),
+                                point_to_lsp(range.end),
+                            ),
+                            work_done_progress_params: Default::default(),
+                            partial_result_params: Default::default(),
+                        },
+                    )
+                    .await?;
+                match response {
+                    Some(lsp::SemanticTokensRangeResult::Tokens(tokens)) => tokens.data,
+                    Some(lsp::SemanticTokensRangeResult::Partial(partial)) => partial.data,
+                    None => Vec::new(),
+                }
+            } else {
+                Vec::new()
+            };
+
+            Ok(semantic_tokens_from_lsp(&data, &legend, &snapshot))
+        })
+    }
+
+    pub(crate) async fn handle_get_semantic_tokens(
+        this: Model<Self>,
+        envelope: TypedEnvelope<proto::GetSemanticTokens>,
+        _: Arc<Client>,
+        mut cx: AsyncAppContext,
+    ) -> Result<proto::GetSemanticTokensResponse> {
+        let start = envelope
+            .payload
+            .start
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid start"))?;
+        let end = envelope
+            .payload
+            .end
+            .and_then(deserialize_anchor)
+            .ok_or_else(|| anyhow!("invalid end"))?;
+        let buffer = this.update(&mut cx, |this, _| {
+            this.opened_buffers
+                .get(&envelope.payload.buffer_id)
+                .and_then(|buffer| buffer.upgrade())
+                .ok_or_else(|| anyhow!("unknown buffer id {}", envelope.payload.buffer_id))
+        })??;
+        buffer
+            .update(&mut cx, |buffer, _| {
+                buffer.wait_for_version(deserialize_version(&envelope.payload.version))
+            })?
+            .await?;
+
+        let tokens = this
+            .update(&mut cx, |this, cx| {
+                this.semantic_tokens(&buffer, start..end, cx)
+            })?
+            .await?;
+        Ok(proto::GetSemanticTokensResponse {
+            tokens: tokens
+                .into_iter()
+                .map(|token| proto::SemanticToken {
+                    start: Some(serialize_anchor(&token.range.start)),
+                    end: Some(serialize_anchor(&token.range.end)),
+                    token_type: token.token_type,
+                    modifiers: token.modifiers,
+                })
+                .collect(),
+        })
+    }
+}
+
+fn cache_semantic_tokens(
+    this: &gpui::WeakModel<Project>,
+    buffer_id: u64,
+    server_id: LanguageServerId,
+    tokens: &lsp::SemanticTokens,
+    cx: &mut AsyncAppContext,
+) -> Result<()> {
+    this.update(cx, |this, _| match &tokens.result_id {
+        Some(result_id) => {
+            this.semantic_tokens_cache.insert(
+                buffer_id,
+                CachedSemanticTokens {
+                    server_id,
+                    result_id: result_id.clone(),
+                    data: tokens.data.clone(),
+                },
+            );
+        }
+        None => drop(this.semantic_tokens_cache.remove(&buffer_id)),
+    })
+}
+
+/// Applies the edits of a delta response to the tokens it's relative to. Edits
+/// index into the tokens' encoding as a flat list of integers, five per token, and
+/// all refer to the tokens from before any of them is applied.
+fn apply_semantic_token_edits(
+    data: &mut Vec<lsp::SemanticToken>,
+    mut edits: Vec<lsp::SemanticTokensEdit>,
+) {
+    edits.sort_by_key(|edit| Reverse(edit.start));
+    for edit in edits {
+        let start = (edit.start as usize / 5).min(data.len());
+        let end = (start + edit.delete_count as usize / 5).min(data.len());
+        data.splice(start..end, edit.data.unwrap_or_default());
+    }
+}
+
+/// Decodes tokens, whose positions are each relative to the previous token's.
+fn semantic_tokens_from_lsp(
+    data: &[lsp::SemanticToken],
+    legend: &lsp::SemanticTokensLegend,
+    snapshot: &BufferSnapshot,
+) -> Vec<SemanticToken> {
+    let mut row = 0;
+    let mut column = 0;
+    data.iter()
+        .filter_map(|token| {
+            if token.delta_line > 0 {
+                row += token.delta_line;
+                column = token.delta_start;
+            } else {
+                column += token.delta_start;
+            }
+
+            let token_type = legend.token_types.get(token.token_type as usize)?;
+            let modifiers = legend
+                .token_modifiers
+                .iter()
+                .take(u32::BITS as usize)
+                .enumerate()
+                .filter(|(ix, _)| token.token_modifiers_bitset & (1 << ix) != 0)
+                .map(|(_, modifier)| modifier.as_str().to_string())
+                .collect();
+            let start =
+                snapshot.clip_point_utf16(Unclipped(PointUtf16::new(row, column)), Bias::Left);
+            let end = snapshot.clip_point_utf16(
+                Unclipped(PointUtf16::new(row, column + token.length)),
+                Bias::Left,
+            );
+            Some(SemanticToken {
+                range: snapshot.anchor_after(start)..snapshot.anchor_before(end),
+                token_type: token_type.as_str().to_string(),
+                modifiers,
+            })
+        })
+        .collect()
+}
+
+fn semantic_token_from_proto(token: proto::SemanticToken) -> Result<SemanticToken> {
+    let start = token
+        .start
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid semantic token start"))?;
+    let end = token
+        .end
+        .and_then(deserialize_anchor)
+        .ok_or_else(|| anyhow!("invalid semantic token end"))?;
+    Ok(SemanticToken {
+        range: start..end,
+        token_type: token.token_type,
+        modifiers: token.modifiers,
+    })
+}
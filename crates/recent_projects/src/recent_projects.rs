@@ -36,7 +36,7 @@ impl RecentProjects {
         // out workspace locations once the future runs to completion.
         cx.spawn(|this, mut cx| async move {
             let workspaces = WORKSPACE_DB
-                .recent_workspaces_on_disk()
+                .frecent_workspaces_on_disk()
                 .await
                 .unwrap_or_default()
                 .into_iter()
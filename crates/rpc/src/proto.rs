@@ -160,6 +160,8 @@ messages!(
     (DeleteNotification, Foreground),
     (DeleteProjectEntry, Foreground),
     (Error, Foreground),
+    (ExecuteCodeLens, Background),
+    (ExecuteCodeLensResponse, Background),
     (ExpandProjectEntry, Foreground),
     (ExpandProjectEntryResponse, Foreground),
     (Follow, Foreground),
@@ -174,22 +176,48 @@ messages!(
     (GetChannelMessagesResponse, Background),
     (GetCodeActions, Background),
     (GetCodeActionsResponse, Background),
+    (GetCodeLenses, Background),
+    (GetCodeLensesResponse, Background),
     (GetCompletions, Background),
     (GetCompletionsResponse, Background),
+    (GetDeclaration, Background),
+    (GetDeclarationResponse, Background),
     (GetDefinition, Background),
     (GetDefinitionResponse, Background),
     (GetDocumentHighlights, Background),
     (GetDocumentHighlightsResponse, Background),
+    (GetDocumentSymbols, Background),
+    (GetDocumentSymbolsResponse, Background),
+    (GetFoldingRanges, Background),
+    (GetFoldingRangesResponse, Background),
+    (GetEnclosingSymbols, Background),
+    (GetEnclosingSymbolsResponse, Background),
+    (GetFunctionSymbol, Background),
+    (GetFunctionSymbolResponse, Background),
     (GetHover, Background),
     (GetHoverResponse, Background),
+    (GetImplementation, Background),
+    (GetImplementationResponse, Background),
+    (GetIncomingCalls, Background),
+    (GetIncomingCallsResponse, Background),
+    (GetLinkedEditingRanges, Background),
+    (GetLinkedEditingRangesResponse, Background),
     (GetNotifications, Foreground),
     (GetNotificationsResponse, Foreground),
+    (GetOutgoingCalls, Background),
+    (GetOutgoingCallsResponse, Background),
     (GetPrivateUserInfo, Foreground),
     (GetPrivateUserInfoResponse, Foreground),
     (GetProjectSymbols, Background),
     (GetProjectSymbolsResponse, Background),
     (GetReferences, Background),
     (GetReferencesResponse, Background),
+    (GetSelectionRanges, Background),
+    (GetSelectionRangesResponse, Background),
+    (GetSemanticTokens, Background),
+    (GetSemanticTokensResponse, Background),
+    (GetSignatureHelp, Background),
+    (GetSignatureHelpResponse, Background),
     (GetTypeDefinition, Background),
     (GetTypeDefinitionResponse, Background),
     (GetUsers, Foreground),
@@ -223,6 +251,8 @@ messages!(
     (PerformRename, Background),
     (PerformRenameResponse, Background),
     (Ping, Foreground),
+    (PrepareCallHierarchy, Background),
+    (PrepareCallHierarchyResponse, Background),
     (PrepareRename, Background),
     (PrepareRenameResponse, Background),
     (ProjectEntryResponse, Foreground),
@@ -301,6 +331,7 @@ request_messages!(
     (DeclineCall, Ack),
     (DeleteChannel, Ack),
     (DeleteProjectEntry, ProjectEntryResponse),
+    (ExecuteCodeLens, ExecuteCodeLensResponse),
     (ExpandProjectEntry, ExpandProjectEntryResponse),
     (Follow, FollowResponse),
     (FormatBuffers, FormatBuffersResponse),
@@ -309,14 +340,27 @@ request_messages!(
     (GetChannelMessages, GetChannelMessagesResponse),
     (GetChannelMessagesById, GetChannelMessagesResponse),
     (GetCodeActions, GetCodeActionsResponse),
+    (GetCodeLenses, GetCodeLensesResponse),
     (GetCompletions, GetCompletionsResponse),
+    (GetDeclaration, GetDeclarationResponse),
     (GetDefinition, GetDefinitionResponse),
     (GetDocumentHighlights, GetDocumentHighlightsResponse),
+    (GetDocumentSymbols, GetDocumentSymbolsResponse),
+    (GetFoldingRanges, GetFoldingRangesResponse),
+    (GetEnclosingSymbols, GetEnclosingSymbolsResponse),
+    (GetFunctionSymbol, GetFunctionSymbolResponse),
     (GetHover, GetHoverResponse),
+    (GetImplementation, GetImplementationResponse),
+    (GetIncomingCalls, GetIncomingCallsResponse),
+    (GetLinkedEditingRanges, GetLinkedEditingRangesResponse),
     (GetNotifications, GetNotificationsResponse),
+    (GetOutgoingCalls, GetOutgoingCallsResponse),
     (GetPrivateUserInfo, GetPrivateUserInfoResponse),
     (GetProjectSymbols, GetProjectSymbolsResponse),
     (GetReferences, GetReferencesResponse),
+    (GetSelectionRanges, GetSelectionRangesResponse),
+    (GetSemanticTokens, GetSemanticTokensResponse),
+    (GetSignatureHelp, GetSignatureHelpResponse),
     (GetTypeDefinition, GetTypeDefinitionResponse),
     (GetUsers, UsersResponse),
     (IncomingCall, Ack),
@@ -337,6 +381,7 @@ request_messages!(
     (OpenBufferForSymbol, OpenBufferForSymbolResponse),
     (PerformRename, PerformRenameResponse),
     (Ping, Ack),
+    (PrepareCallHierarchy, PrepareCallHierarchyResponse),
     (PrepareRename, PrepareRenameResponse),
     (RefreshInlayHints, Ack),
     (RejoinChannelBuffers, RejoinChannelBuffersResponse),
@@ -382,15 +427,29 @@ entity_messages!(
     CreateBufferForPeer,
     CreateProjectEntry,
     DeleteProjectEntry,
+    ExecuteCodeLens,
     ExpandProjectEntry,
     FormatBuffers,
     GetCodeActions,
+    GetCodeLenses,
     GetCompletions,
+    GetDeclaration,
     GetDefinition,
     GetDocumentHighlights,
+    GetDocumentSymbols,
+    GetEnclosingSymbols,
+    GetFoldingRanges,
+    GetFunctionSymbol,
     GetHover,
+    GetImplementation,
+    GetIncomingCalls,
+    GetLinkedEditingRanges,
+    GetOutgoingCalls,
     GetProjectSymbols,
     GetReferences,
+    GetSelectionRanges,
+    GetSemanticTokens,
+    GetSignatureHelp,
     GetTypeDefinition,
     InlayHints,
     JoinProject,
@@ -400,6 +459,7 @@ entity_messages!(
     OpenBufferByPath,
     OpenBufferForSymbol,
     PerformRename,
+    PrepareCallHierarchy,
     PrepareRename,
     RefreshInlayHints,
     ReloadBuffers,
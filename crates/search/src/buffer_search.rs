@@ -1,7 +1,7 @@
 mod registrar;
 
 use crate::{
-    history::SearchHistory,
+    history::{SearchHistory, SearchHistoryKind},
     mode::{next_mode, SearchMode},
     search_bar::render_nav_button,
     ActivateRegexMode, ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery,
@@ -28,7 +28,7 @@ use util::ResultExt;
 use workspace::{
     item::ItemHandle,
     searchable::{Direction, SearchEvent, SearchableItemHandle, WeakSearchableItemHandle},
-    ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView, Workspace,
+    ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView, Workspace, WorkspaceId,
 };
 
 pub use registrar::DivRegistrar;
@@ -508,6 +508,13 @@ impl BufferSearchBar {
         }
     }
 
+    /// Keeps the bar's search history across restarts, sharing it with the other buffer
+    /// searches of the workspace.
+    pub fn with_persisted_history(mut self, workspace_id: WorkspaceId) -> Self {
+        self.search_history = SearchHistory::persisted(workspace_id, SearchHistoryKind::Buffer);
+        self
+    }
+
     pub fn is_dismissed(&self) -> bool {
         self.dismissed
     }
@@ -875,6 +882,7 @@ impl BufferSearchBar {
 
                             this.update_match_index(cx);
                             this.search_history.add(query_text);
+                            this.search_history.store(cx);
                             if !this.dismissed {
                                 let matches = this
                                     .searchable_items_with_matches
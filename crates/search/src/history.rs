@@ -1,13 +1,68 @@
+use crate::persistence::SEARCH_DB;
+use gpui::AppContext;
 use smallvec::SmallVec;
+use util::ResultExt;
+use workspace::WorkspaceId;
+
 const SEARCH_HISTORY_LIMIT: usize = 20;
 
+/// The kinds of search whose histories are stored separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchHistoryKind {
+    Buffer,
+    Project,
+}
+
+impl SearchHistoryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Buffer => "buffer",
+            Self::Project => "project",
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct SearchHistory {
     history: SmallVec<[String; SEARCH_HISTORY_LIMIT]>,
     selected: Option<usize>,
+    /// The workspace and the kind of search that the history is stored for, so that it
+    /// outlives the session.
+    persisted_for: Option<(WorkspaceId, SearchHistoryKind)>,
 }
 
 impl SearchHistory {
+    /// Loads the history that was stored for the workspace's searches of the given kind,
+    /// which is then stored again whenever it changes.
+    pub fn persisted(workspace_id: WorkspaceId, kind: SearchHistoryKind) -> Self {
+        let mut history = SEARCH_DB
+            .get_search_history(workspace_id, kind.as_str())
+            .log_err()
+            .unwrap_or_default();
+        history.drain(..history.len().saturating_sub(SEARCH_HISTORY_LIMIT));
+        Self {
+            history: history.into(),
+            selected: None,
+            persisted_for: Some((workspace_id, kind)),
+        }
+    }
+
+    /// Stores the history for the workspace it was loaded for, if any.
+    pub fn store(&self, cx: &AppContext) {
+        let Some((workspace_id, kind)) = self.persisted_for else {
+            return;
+        };
+        let queries = self.history.to_vec();
+        cx.background_executor()
+            .spawn(async move {
+                SEARCH_DB
+                    .save_search_history(workspace_id, kind.as_str(), queries)
+                    .await
+                    .log_err()
+            })
+            .detach();
+    }
+
     pub fn add(&mut self, search_string: String) {
         if let Some(i) = self.selected {
             if search_string == self.history[i] {
@@ -181,4 +236,28 @@ mod tests {
         search_history.reset_selection();
         assert_eq!(search_history.current(), None);
     }
+
+    #[gpui::test]
+    async fn test_persisted_history() {
+        let workspace_id = workspace::WORKSPACE_DB.next_id().await.unwrap();
+        let queries = (0..SEARCH_HISTORY_LIMIT + 2)
+            .map(|i| format!("item{i}"))
+            .collect::<Vec<_>>();
+        SEARCH_DB
+            .save_search_history(workspace_id, SearchHistoryKind::Buffer.as_str(), queries)
+            .await
+            .unwrap();
+
+        // Only the most recent queries are kept
+        let mut search_history = SearchHistory::persisted(workspace_id, SearchHistoryKind::Buffer);
+        assert_eq!(search_history.history.len(), SEARCH_HISTORY_LIMIT);
+        assert_eq!(search_history.current(), None);
+        let last_query = format!("item{}", SEARCH_HISTORY_LIMIT + 1);
+        assert_eq!(search_history.previous(), Some(last_query.as_str()));
+        assert_eq!(search_history.history[0], "item2");
+
+        // Each kind of search has its own history
+        let mut search_history = SearchHistory::persisted(workspace_id, SearchHistoryKind::Project);
+        assert_eq!(search_history.previous(), None);
+    }
 }
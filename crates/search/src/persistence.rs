@@ -0,0 +1,55 @@
+use anyhow::Result;
+use db::{define_connection, query, sqlez_macros::sql};
+use workspace::{WorkspaceDb, WorkspaceId};
+
+define_connection! {
+    pub static ref SEARCH_DB: SearchDb<WorkspaceDb> =
+        &[sql!(
+            CREATE TABLE search_history (
+                workspace_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                query TEXT NOT NULL,
+                PRIMARY KEY(workspace_id, kind, position),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(workspace_id)
+                ON DELETE CASCADE
+            ) STRICT;
+        )];
+}
+
+impl SearchDb {
+    query! {
+        pub fn get_search_history(workspace_id: WorkspaceId, kind: &str) -> Result<Vec<String>> {
+            SELECT query
+            FROM search_history
+            WHERE workspace_id = ? AND kind = ?
+            ORDER BY position
+        }
+    }
+
+    /// Replaces the workspace's history of the given kind of search with the given
+    /// queries, the oldest one first.
+    pub async fn save_search_history(
+        &self,
+        workspace_id: WorkspaceId,
+        kind: &'static str,
+        queries: Vec<String>,
+    ) -> Result<()> {
+        self.write(move |conn| {
+            conn.with_savepoint("save_search_history", || {
+                conn.exec_bound(sql!(
+                    DELETE FROM search_history WHERE workspace_id = ? AND kind = ?
+                ))?((workspace_id, kind))?;
+                let mut insert_query = conn.exec_bound(sql!(
+                    INSERT INTO search_history (workspace_id, kind, position, query)
+                    VALUES (?, ?, ?, ?)
+                ))?;
+                for (position, query) in queries.iter().enumerate() {
+                    insert_query((workspace_id, kind, position, query.as_str()))?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
-    history::SearchHistory, mode::SearchMode, ActivateRegexMode, ActivateSemanticMode,
-    ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery, ReplaceAll, ReplaceNext,
-    SearchOptions, SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive, ToggleIncludeIgnored,
-    ToggleReplace, ToggleWholeWord,
+    history::{SearchHistory, SearchHistoryKind},
+    mode::SearchMode,
+    ActivateRegexMode, ActivateSemanticMode, ActivateTextMode, CycleMode, NextHistoryQuery,
+    PreviousHistoryQuery, ReplaceAll, ReplaceNext, SearchOptions, SelectNextMatch, SelectPrevMatch,
+    ToggleCaseSensitive, ToggleIncludeIgnored, ToggleReplace, ToggleWholeWord,
 };
 use anyhow::{Context as _, Result};
 use collections::HashMap;
@@ -201,6 +202,13 @@ impl ProjectSearch {
         }
     }
 
+    /// Keeps the search history across restarts, sharing it with the other project
+    /// searches of the workspace.
+    fn with_persisted_history(mut self, workspace_id: WorkspaceId) -> Self {
+        self.search_history = SearchHistory::persisted(workspace_id, SearchHistoryKind::Project);
+        self
+    }
+
     fn clone(&self, cx: &mut ModelContext<Self>) -> Model<Self> {
         cx.new_model(|cx| Self {
             project: self.project.clone(),
@@ -222,6 +230,7 @@ impl ProjectSearch {
             .update(cx, |project, cx| project.search(query.clone(), cx));
         self.search_id += 1;
         self.search_history.add(query.as_str().to_string());
+        self.search_history.store(cx);
         self.active_query = Some(query);
         self.match_ranges.clear();
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
@@ -278,6 +287,7 @@ impl ProjectSearch {
         self.search_id += 1;
         self.match_ranges.clear();
         self.search_history.add(inputs.as_str().to_string());
+        self.search_history.store(cx);
         self.no_results = None;
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
             let results = search?.await.log_err()?;
@@ -992,7 +1002,10 @@ impl ProjectSearchView {
             return;
         };
 
-        let model = cx.new_model(|cx| ProjectSearch::new(workspace.project().clone(), cx));
+        let model = cx.new_model(|cx| {
+            ProjectSearch::new(workspace.project().clone(), cx)
+                .with_persisted_history(workspace.database_id())
+        });
         let search = cx.new_view(|cx| ProjectSearchView::new(model, cx, None));
         workspace.add_item(Box::new(search.clone()), cx);
         search.update(cx, |search, cx| {
@@ -1039,7 +1052,8 @@ impl ProjectSearchView {
             });
             if let Some(new_query) = new_query {
                 let model = cx.new_model(|cx| {
-                    let mut model = ProjectSearch::new(workspace.project().clone(), cx);
+                    let mut model = ProjectSearch::new(workspace.project().clone(), cx)
+                        .with_persisted_history(workspace.database_id());
                     model.search(new_query, cx);
                     model
                 });
@@ -1090,7 +1104,10 @@ impl ProjectSearchView {
                 None
             };
 
-            let model = cx.new_model(|cx| ProjectSearch::new(workspace.project().clone(), cx));
+            let model = cx.new_model(|cx| {
+                ProjectSearch::new(workspace.project().clone(), cx)
+                    .with_persisted_history(workspace.database_id())
+            });
             let view = cx.new_view(|cx| ProjectSearchView::new(model, cx, settings));
 
             workspace.add_item(Box::new(view.clone()), cx);
@@ -14,12 +14,13 @@ impl Snippet {
     pub fn parse(source: &str) -> Result<Self> {
         let mut text = String::with_capacity(source.len());
         let mut tabstops = BTreeMap::new();
-        parse_snippet(source, false, &mut text, &mut tabstops)
+        let mut variables = Vec::new();
+        parse_snippet(source, false, &mut text, &mut tabstops, &mut variables)
             .context("failed to parse snippet")?;
 
         let len = text.len() as isize;
         let final_tabstop = tabstops.remove(&0);
-        let mut tabstops = tabstops.into_values().collect::<Vec<_>>();
+        let mut tabstops = tabstops.into_values().chain(variables).collect::<Vec<_>>();
 
         if let Some(final_tabstop) = final_tabstop {
             tabstops.push(final_tabstop);
@@ -39,12 +40,13 @@ fn parse_snippet<'a>(
     nested: bool,
     text: &mut String,
     tabstops: &mut BTreeMap<usize, TabStop>,
+    variables: &mut Vec<TabStop>,
 ) -> Result<&'a str> {
     loop {
         match source.chars().next() {
             None => return Ok(""),
             Some('$') => {
-                source = parse_tabstop(&source[1..], text, tabstops)?;
+                source = parse_tabstop(&source[1..], text, tabstops, variables)?;
             }
             Some('\\') => {
                 source = &source[1..];
@@ -72,37 +74,110 @@ fn parse_snippet<'a>(
 }
 
 fn parse_tabstop<'a>(
-    mut source: &'a str,
+    source: &'a str,
     text: &mut String,
     tabstops: &mut BTreeMap<usize, TabStop>,
+    variables: &mut Vec<TabStop>,
 ) -> Result<&'a str> {
-    let tabstop_start = text.len();
-    let tabstop_index;
-    if source.starts_with('{') {
-        let (index, rest) = parse_int(&source[1..])?;
-        tabstop_index = index;
+    let braced = source.starts_with('{');
+    let mut source = if braced { &source[1..] } else { source };
+    if source.starts_with(|c: char| c.is_ascii_digit()) {
+        let tabstop_start = text.len();
+        let (tabstop_index, rest) = parse_int(source)?;
         source = rest;
+        if braced {
+            if let Some(placeholder) = source.strip_prefix(':') {
+                source = parse_snippet(placeholder, true, text, tabstops, variables)?;
+            } else if let Some(choices) = source.strip_prefix('|') {
+                source = parse_choice(choices, text)?;
+            }
+            source = parse_closing_brace(source)?;
+        }
 
-        if source.starts_with(':') {
-            source = parse_snippet(&source[1..], true, text, tabstops)?;
+        // A tabstop without a placeholder mirrors the placeholder of an earlier
+        // occurrence of the same tabstop.
+        if text.len() == tabstop_start {
+            if let Some(range) = tabstops.get(&tabstop_index).and_then(|t| t.first()) {
+                let mirrored = text[range.start as usize..range.end as usize].to_string();
+                text.push_str(&mirrored);
+            }
         }
 
-        if source.starts_with('}') {
-            source = &source[1..];
-        } else {
-            return Err(anyhow!("expected a closing brace"));
+        tabstops
+            .entry(tabstop_index)
+            .or_default()
+            .push(tabstop_start as isize..text.len() as isize);
+        Ok(source)
+    } else if source.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let name_len = source
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(source.len());
+        let (name, rest) = source.split_at(name_len);
+        source = rest;
+        if braced {
+            if let Some(default) = source.strip_prefix(':') {
+                source = parse_snippet(default, true, text, tabstops, variables)?;
+                return parse_closing_brace(source);
+            }
+            source = parse_closing_brace(source)?;
         }
+
+        // Variables aren't resolved, so those without a default are inserted as a
+        // placeholder with their name, to be visited after the numbered tabstops.
+        let variable_start = text.len();
+        text.push_str(name);
+        variables.push(
+            [variable_start as isize..text.len() as isize]
+                .into_iter()
+                .collect(),
+        );
+        Ok(source)
     } else {
-        let (index, rest) = parse_int(source)?;
-        tabstop_index = index;
-        source = rest;
+        // Neither a tabstop nor a variable follows, so the dollar sign is literal
+        text.push('$');
+        if braced {
+            text.push('{');
+        }
+        Ok(source)
     }
+}
 
-    tabstops
-        .entry(tabstop_index)
-        .or_default()
-        .push(tabstop_start as isize..text.len() as isize);
-    Ok(source)
+/// Parses the options of a choice, such as `one,two|` in `${1|one,two|}`, inserting
+/// the first option.
+fn parse_choice<'a>(mut source: &'a str, text: &mut String) -> Result<&'a str> {
+    let mut is_first_choice = true;
+    loop {
+        match source.chars().next() {
+            None => return Err(anyhow!("expected the end of a choice")),
+            Some('|') => return Ok(&source[1..]),
+            Some(',') => {
+                is_first_choice = false;
+                source = &source[1..];
+            }
+            Some(c) => {
+                source = &source[c.len_utf8()..];
+                let c = match c {
+                    '\\' => match source.chars().next() {
+                        Some(escaped) => {
+                            source = &source[escaped.len_utf8()..];
+                            escaped
+                        }
+                        None => c,
+                    },
+                    _ => c,
+                };
+                if is_first_choice {
+                    text.push(c);
+                }
+            }
+        }
+    }
+}
+
+fn parse_closing_brace(source: &str) -> Result<&str> {
+    source
+        .strip_prefix('}')
+        .ok_or_else(|| anyhow!("expected a closing brace"))
 }
 
 fn parse_int(source: &str) -> Result<(usize, &str)> {
@@ -199,6 +274,35 @@ mod tests {
         assert_eq!(tabstops(&snippet), &[vec![3..3]]);
     }
 
+    #[test]
+    fn test_snippet_with_mirrored_tabstops() {
+        // Mirrors show the text of the tabstop's placeholder
+        let snippet = Snippet::parse("let ${1:name} = 1; $1 += 1;$0").unwrap();
+        assert_eq!(snippet.text, "let name = 1; name += 1;");
+        assert_eq!(tabstops(&snippet), &[vec![4..8, 14..18], vec![24..24]]);
+    }
+
+    #[test]
+    fn test_snippet_with_choices() {
+        let snippet = Snippet::parse("${1|one,two\\,three|} $2").unwrap();
+        assert_eq!(snippet.text, "one ");
+        assert_eq!(tabstops(&snippet), &[vec![0..3], vec![4..4]]);
+    }
+
+    #[test]
+    fn test_snippet_with_variables() {
+        // Variables with a default insert the default, and the others become
+        // placeholders after the numbered tabstops.
+        let snippet = Snippet::parse("${TM_FILENAME:main.rs}:$1 $TM_LINE_NUMBER$0").unwrap();
+        assert_eq!(snippet.text, "main.rs: TM_LINE_NUMBER");
+        assert_eq!(tabstops(&snippet), &[vec![8..8], vec![9..23], vec![23..23]]);
+
+        // A dollar sign that doesn't start a tabstop or a variable is literal
+        let snippet = Snippet::parse("cost: $ 5").unwrap();
+        assert_eq!(snippet.text, "cost: $ 5");
+        assert_eq!(tabstops(&snippet), &[vec![9..9]]);
+    }
+
     fn tabstops(snippet: &Snippet) -> Vec<Vec<Range<isize>>> {
         snippet.tabstops.iter().map(|t| t.to_vec()).collect()
     }
@@ -0,0 +1,321 @@
+use anyhow::{anyhow, Context, Result};
+use collections::{HashMap, HashSet};
+
+/// A flag, which `.dic` files attach to words to allow affixes and options for them.
+type Flag = u64;
+
+/// How flags are written, as set by the `FLAG` option of an `.aff` file.
+#[derive(Clone, Copy, Default)]
+enum FlagFormat {
+    /// Each character is a flag.
+    #[default]
+    Char,
+    /// Each pair of characters is a flag.
+    Long,
+    /// Flags are comma-separated numbers.
+    Num,
+}
+
+impl FlagFormat {
+    fn parse(&self, flags: &str) -> Vec<Flag> {
+        match self {
+            Self::Char => flags.chars().map(|flag| flag as Flag).collect(),
+            Self::Long => {
+                let chars = flags.chars().collect::<Vec<_>>();
+                chars
+                    .chunks(2)
+                    .map(|pair| pair.iter().fold(0, |flag, &ch| (flag << 32) | ch as Flag))
+                    .collect()
+            }
+            Self::Num => flags
+                .split(',')
+                .filter_map(|flag| flag.trim().parse().ok())
+                .collect(),
+        }
+    }
+
+    fn parse_one(&self, flag: Option<&str>) -> Option<Flag> {
+        self.parse(flag?).first().copied()
+    }
+}
+
+/// A part of an affix's condition, which the characters at the start of a word (for
+/// prefixes) or at its end (for suffixes) have to match for the affix to apply.
+#[derive(Debug)]
+enum ConditionPart {
+    Any,
+    Char(char),
+    Set { chars: Vec<char>, negated: bool },
+}
+
+impl ConditionPart {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Char(expected) => ch == *expected,
+            Self::Set { chars, negated } => chars.contains(&ch) != *negated,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Affix {
+    strip: String,
+    add: String,
+    condition: Vec<ConditionPart>,
+}
+
+impl Affix {
+    fn apply_prefix(&self, word: &str) -> Option<String> {
+        let rest = word.strip_prefix(self.strip.as_str())?;
+        if self.condition.len() > word.chars().count()
+            || !self
+                .condition
+                .iter()
+                .zip(word.chars())
+                .all(|(part, ch)| part.matches(ch))
+        {
+            return None;
+        }
+        Some(format!("{}{}", self.add, rest))
+    }
+
+    fn apply_suffix(&self, word: &str) -> Option<String> {
+        let rest = word.strip_suffix(self.strip.as_str())?;
+        if self.condition.len() > word.chars().count()
+            || !self
+                .condition
+                .iter()
+                .rev()
+                .zip(word.chars().rev())
+                .all(|(part, ch)| part.matches(ch))
+        {
+            return None;
+        }
+        Some(format!("{}{}", rest, self.add))
+    }
+}
+
+struct AffixGroup {
+    /// Whether the group's affixes can be combined with affixes of the other kind.
+    cross_product: bool,
+    affixes: Vec<Affix>,
+}
+
+/// The affixes and options of a Hunspell dictionary, as described by its `.aff` file.
+#[derive(Default)]
+pub(crate) struct AffixFile {
+    flag_format: FlagFormat,
+    prefixes: HashMap<Flag, AffixGroup>,
+    suffixes: HashMap<Flag, AffixGroup>,
+    need_affix: Option<Flag>,
+    forbidden_word: Option<Flag>,
+    only_in_compound: Option<Flag>,
+    /// The characters tried when looking for suggestions, most common first.
+    pub try_chars: String,
+}
+
+impl AffixFile {
+    pub fn parse(aff: &str) -> Result<Self> {
+        let mut file = Self::default();
+        let mut lines = aff.lines().enumerate();
+        while let Some((ix, line)) = lines.next() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("FLAG") => {
+                    file.flag_format = match fields.next() {
+                        Some("long") => FlagFormat::Long,
+                        Some("num") => FlagFormat::Num,
+                        _ => FlagFormat::Char,
+                    }
+                }
+                Some("TRY") => file.try_chars = fields.next().unwrap_or_default().to_string(),
+                Some("NEEDAFFIX") => file.need_affix = file.flag_format.parse_one(fields.next()),
+                Some("FORBIDDENWORD") => {
+                    file.forbidden_word = file.flag_format.parse_one(fields.next())
+                }
+                Some("ONLYINCOMPOUND") => {
+                    file.only_in_compound = file.flag_format.parse_one(fields.next())
+                }
+                Some(kind @ ("PFX" | "SFX")) => {
+                    let header = || anyhow!("invalid affix header on line {}", ix + 1);
+                    let flag = file
+                        .flag_format
+                        .parse_one(fields.next())
+                        .ok_or_else(header)?;
+                    let cross_product = fields.next() == Some("Y");
+                    let count = fields
+                        .next()
+                        .and_then(|count| count.parse::<usize>().ok())
+                        .ok_or_else(header)?;
+
+                    let mut affixes = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        let (ix, line) = lines
+                            .next()
+                            .with_context(|| format!("missing affixes for {kind} {flag}"))?;
+                        let fields = line.split_whitespace().collect::<Vec<_>>();
+                        let [_, _, strip, add, rest @ ..] = fields.as_slice() else {
+                            return Err(anyhow!("invalid affix on line {}", ix + 1));
+                        };
+                        // Continuation flags, which allow further affixes, aren't supported
+                        let add = add.split('/').next().unwrap_or_default();
+                        affixes.push(Affix {
+                            strip: empty_if_zero(strip).to_string(),
+                            add: empty_if_zero(add).to_string(),
+                            condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                        });
+                    }
+
+                    let groups = if kind == "PFX" {
+                        &mut file.prefixes
+                    } else {
+                        &mut file.suffixes
+                    };
+                    groups.insert(
+                        flag,
+                        AffixGroup {
+                            cross_product,
+                            affixes,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(file)
+    }
+
+    /// Adds the words of a `.dic` file to `words`, along with the forms that their
+    /// affixes allow.
+    pub fn expand_words(&self, dic: &str, words: &mut HashSet<String>) {
+        // The first line holds the approximate number of words
+        for line in dic.lines().skip(1) {
+            // Morphological fields may follow the word after whitespace
+            let Some(entry) = line.split_whitespace().next() else {
+                continue;
+            };
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => (word, self.flag_format.parse(flags)),
+                None => (entry, Vec::new()),
+            };
+            let has_flag = |flag: Option<Flag>| flag.map_or(false, |flag| flags.contains(&flag));
+            if has_flag(self.forbidden_word) || has_flag(self.only_in_compound) {
+                continue;
+            }
+            if !has_flag(self.need_affix) {
+                words.insert(word.to_string());
+            }
+
+            let mut suffixed = Vec::new();
+            for group in flags.iter().filter_map(|flag| self.suffixes.get(flag)) {
+                for affix in &group.affixes {
+                    if let Some(form) = affix.apply_suffix(word) {
+                        if group.cross_product {
+                            suffixed.push(form.clone());
+                        }
+                        words.insert(form);
+                    }
+                }
+            }
+            for group in flags.iter().filter_map(|flag| self.prefixes.get(flag)) {
+                for affix in &group.affixes {
+                    words.extend(affix.apply_prefix(word));
+                    if group.cross_product {
+                        words.extend(suffixed.iter().filter_map(|form| affix.apply_prefix(form)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn empty_if_zero(field: &str) -> &str {
+    if field == "0" {
+        ""
+    } else {
+        field
+    }
+}
+
+fn parse_condition(condition: &str) -> Vec<ConditionPart> {
+    if condition == "." {
+        return Vec::new();
+    }
+
+    let mut parts = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(ch) = chars.next() {
+        parts.push(match ch {
+            '.' => ConditionPart::Any,
+            '[' => {
+                let mut set = chars.by_ref().take_while(|ch| *ch != ']').peekable();
+                let negated = set.next_if_eq(&'^').is_some();
+                ConditionPart::Set {
+                    chars: set.collect(),
+                    negated,
+                }
+            }
+            ch => ConditionPart::Char(ch),
+        });
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unindent::Unindent as _;
+
+    #[test]
+    fn test_expand_words() {
+        let aff = "
+            SET UTF-8
+            TRY esianrtolcdugmphbyfvkwz
+            NEEDAFFIX X
+
+            PFX U Y 1
+            PFX U 0 un .
+
+            SFX S Y 3
+            SFX S y ies [^aeiou]y
+            SFX S 0 s [aeiou]y
+            SFX S 0 s [^y]
+
+            SFX D N 2
+            SFX D 0 d e
+            SFX D 0 ed [^e]
+        "
+        .unindent();
+        let dic = "
+            4
+            carry/SU
+            play/SD
+            bake/D
+            foo/Xs
+        "
+        .unindent();
+
+        let affixes = AffixFile::parse(&aff).unwrap();
+        assert_eq!(affixes.try_chars, "esianrtolcdugmphbyfvkwz");
+
+        let mut words = HashSet::default();
+        affixes.expand_words(&dic, &mut words);
+        let mut words = words.iter().map(String::as_str).collect::<Vec<_>>();
+        words.sort_unstable();
+        assert_eq!(
+            words,
+            [
+                "bake",
+                "baked",
+                "carries",
+                "carry",
+                "play",
+                "played",
+                "plays",
+                "uncarries",
+                "uncarry"
+            ]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+mod hunspell;
+
+use anyhow::Result;
+use collections::HashSet;
+use hunspell::AffixFile;
+use parking_lot::RwLock;
+use std::ops::Range;
+
+/// The letters tried when looking for suggestions in dictionaries that don't list their own.
+const DEFAULT_ALPHABET: &str = "etaoinshrdlcumwfgypbvkjxqz";
+
+/// Words this short aren't checked, as they're mostly abbreviations and units.
+const MIN_CHECKED_WORD_LEN: usize = 3;
+
+/// Only words up to this length are searched for suggestions that are two edits away,
+/// as the number of candidates grows quadratically with it.
+const MAX_TWO_EDITS_WORD_LEN: usize = 12;
+
+/// A set of correctly spelled words, along with the words the user added to it.
+pub struct Dictionary {
+    words: HashSet<String>,
+    added_words: RwLock<HashSet<String>>,
+    /// The letters tried when looking for suggestions, most common first.
+    alphabet: Vec<char>,
+}
+
+impl Dictionary {
+    /// Loads a Hunspell dictionary from the contents of its `.aff` and `.dic` files.
+    pub fn from_hunspell(aff: &str, dic: &str) -> Result<Self> {
+        let affixes = AffixFile::parse(aff)?;
+        let mut words = HashSet::default();
+        affixes.expand_words(dic, &mut words);
+        let mut alphabet = Vec::new();
+        for ch in affixes.try_chars.chars().flat_map(char::to_lowercase) {
+            if ch.is_alphabetic() && !alphabet.contains(&ch) {
+                alphabet.push(ch);
+            }
+        }
+        if alphabet.is_empty() {
+            alphabet = DEFAULT_ALPHABET.chars().collect();
+        }
+        Ok(Self {
+            words,
+            added_words: Default::default(),
+            alphabet,
+        })
+    }
+
+    /// Creates a dictionary of the words in `list`, one per line.
+    pub fn from_word_list(list: &str) -> Self {
+        Self {
+            words: word_list(list).map(str::to_string).collect(),
+            added_words: Default::default(),
+            alphabet: DEFAULT_ALPHABET.chars().collect(),
+        }
+    }
+
+    /// Adds a word that is spelled correctly, though the dictionary doesn't contain it.
+    pub fn add_word(&self, word: &str) {
+        self.added_words.write().insert(word.to_string());
+    }
+
+    /// Adds the words in `list`, one per line, such as the ones of the user's dictionary.
+    pub fn add_words(&self, list: &str) {
+        self.added_words
+            .write()
+            .extend(word_list(list).map(str::to_string));
+    }
+
+    /// Whether the word is spelled correctly. Capitalized words are also correct when
+    /// their lowercase form is.
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.replace('’', "'");
+        if self.contains(&word) {
+            return true;
+        }
+        is_capitalized(&word) && self.contains(&word.to_lowercase())
+    }
+
+    /// Returns up to `limit` words that are close to the given misspelled one, the closest
+    /// ones first.
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let lowercase = word.to_lowercase();
+        let mut suggestions = Vec::new();
+        let edits = edits(&lowercase, &self.alphabet);
+        self.add_suggestions(&edits, &mut suggestions, limit);
+        if suggestions.is_empty() && lowercase.chars().count() <= MAX_TWO_EDITS_WORD_LEN {
+            for edit in &edits {
+                self.add_suggestions(&self::edits(edit, &self.alphabet), &mut suggestions, limit);
+            }
+        }
+
+        if is_capitalized(word) {
+            for suggestion in &mut suggestions {
+                *suggestion = capitalize(suggestion);
+            }
+        }
+        suggestions
+    }
+
+    /// Returns the byte ranges of the misspelled words in `text`.
+    pub fn misspellings(&self, text: &str) -> Vec<Range<usize>> {
+        checked_words(text)
+            .into_iter()
+            .filter(|range| !self.check(&text[range.clone()]))
+            .collect()
+    }
+
+    fn add_suggestions(&self, candidates: &[String], suggestions: &mut Vec<String>, limit: usize) {
+        for candidate in candidates {
+            if suggestions.len() >= limit {
+                break;
+            }
+            if !suggestions.contains(candidate) && self.check(candidate) {
+                suggestions.push(candidate.clone());
+            }
+        }
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.added_words.read().contains(word)
+    }
+}
+
+/// Returns the byte ranges of the words in `text` that are spell checked. Words that
+/// look like code, such as `camelCase` or `snake_case` identifiers, paths and calls, are
+/// skipped, along with words quoted in backticks, acronyms and words with digits.
+pub fn checked_words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut in_backticks = false;
+    let mut previous = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        if !is_word_char(ch) {
+            if ch == '`' {
+                in_backticks = !in_backticks;
+            }
+            previous = Some(ch);
+            continue;
+        }
+
+        let mut end = start + ch.len_utf8();
+        while let Some((ix, ch)) = chars.next_if(|(_, ch)| is_word_char(*ch) || is_apostrophe(*ch))
+        {
+            end = ix + ch.len_utf8();
+        }
+        let word = text[start..end].trim_end_matches(is_apostrophe);
+        let end = start + word.len();
+
+        let mut following = text[end..].chars();
+        let next = following.next();
+        let looks_like_code = matches!(previous, Some('.' | '/' | '\\' | '@' | '#' | '$' | '&'))
+            || next == Some('(')
+            || (matches!(next, Some('.' | '/' | '\\' | ':' | '@'))
+                && following
+                    .next()
+                    .map_or(false, |ch| ch.is_alphanumeric() || ch == ':' || ch == '/'));
+        let is_checked_case = word.chars().skip(1).all(|ch| !ch.is_uppercase());
+        if !in_backticks
+            && !looks_like_code
+            && is_checked_case
+            && word.chars().filter(|ch| !is_apostrophe(*ch)).count() >= MIN_CHECKED_WORD_LEN
+            && word
+                .chars()
+                .all(|ch| ch.is_alphabetic() || is_apostrophe(ch))
+        {
+            words.push(start..end);
+        }
+        previous = text[..end].chars().next_back();
+    }
+    words
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn is_apostrophe(ch: char) -> bool {
+    ch == '\'' || ch == '’'
+}
+
+fn is_capitalized(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().map_or(false, char::is_uppercase) && chars.all(|ch| !ch.is_uppercase())
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn word_list(list: &str) -> impl Iterator<Item = &str> {
+    list.lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+}
+
+/// Returns the words that are one edit away from the given one, in the order of how
+/// common the edits are: swapped letters, replaced letters, removed letters and then
+/// inserted letters.
+fn edits(word: &str, alphabet: &[char]) -> Vec<String> {
+    let chars = word.chars().collect::<Vec<_>>();
+    let mut edits = Vec::new();
+    for ix in 1..chars.len() {
+        let mut edit = chars.clone();
+        edit.swap(ix - 1, ix);
+        edits.push(edit.into_iter().collect());
+    }
+    for ix in 0..chars.len() {
+        for &ch in alphabet {
+            if ch != chars[ix] {
+                let mut edit = chars.clone();
+                edit[ix] = ch;
+                edits.push(edit.into_iter().collect());
+            }
+        }
+    }
+    for ix in 0..chars.len() {
+        let mut edit = chars.clone();
+        edit.remove(ix);
+        edits.push(edit.into_iter().collect());
+    }
+    for ix in 0..=chars.len() {
+        for &ch in alphabet {
+            let mut edit = chars.clone();
+            edit.insert(ix, ch);
+            edits.push(edit.into_iter().collect());
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_words() {
+        let text = "Returns the `file_path` of x.rs, or None if it's an HTTP URL like \
+            https://zed.dev/docs. See foo() and std::io; we're done in 30s, ok";
+        let words = checked_words(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            words,
+            ["Returns", "the", "None", "it's", "like", "See", "and", "we're", "done"]
+        );
+    }
+
+    #[test]
+    fn test_check_and_suggest() {
+        let dictionary = Dictionary::from_word_list("the\nthere\nthem\nParis\n# a comment\n");
+        assert!(dictionary.check("the"));
+        assert!(dictionary.check("The"));
+        assert!(dictionary.check("Paris"));
+        assert!(!dictionary.check("paris"));
+        assert!(!dictionary.check("teh"));
+
+        assert_eq!(dictionary.suggest("teh", 5), ["the"]);
+        // Capitalized like the misspelled word
+        assert_eq!(dictionary.suggest("Thre", 5), ["The", "There"]);
+        // Two edits away, when there are no words one edit away
+        assert_eq!(dictionary.suggest("thenn", 5), ["them", "the"]);
+
+        let text = "teh Zed editr is there";
+        let misspellings = dictionary.misspellings(text);
+        assert_eq!(
+            misspellings
+                .iter()
+                .map(|range| &text[range.clone()])
+                .collect::<Vec<_>>(),
+            ["teh", "Zed", "editr"]
+        );
+
+        dictionary.add_words("Zed\neditr\n");
+        assert_eq!(dictionary.misspellings(text), [0..3]);
+        dictionary.add_word("teh");
+        assert!(dictionary.misspellings(text).is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=LIBSQLITE3_FLAGS");
+
+    // The snapshot API is only compiled into sqlite when it's enabled, so linking fails
+    // with a confusing error about missing symbols without it
+    if env::var_os("CARGO_FEATURE_SNAPSHOT").is_some() {
+        let flags = env::var("LIBSQLITE3_FLAGS").unwrap_or_default();
+        if !flags
+            .split_whitespace()
+            .any(|flag| flag == "-DSQLITE_ENABLE_SNAPSHOT")
+        {
+            panic!(
+                "the `snapshot` feature of sqlez needs sqlite to be built with the snapshot API, \
+                 by setting LIBSQLITE3_FLAGS=\"-DSQLITE_ENABLE_SNAPSHOT\""
+            );
+        }
+    }
+}
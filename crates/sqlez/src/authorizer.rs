@@ -0,0 +1,335 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// An action which a statement will take, passed to the authorizer while the statement is
+/// being prepared. Names are those written in the statement, so they may differ in case
+/// from the names in the schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthAction<'a> {
+    CreateIndex {
+        index: &'a str,
+        table: &'a str,
+        temporary: bool,
+    },
+    CreateTable {
+        table: &'a str,
+        temporary: bool,
+    },
+    CreateTrigger {
+        trigger: &'a str,
+        table: &'a str,
+        temporary: bool,
+    },
+    CreateView {
+        view: &'a str,
+        temporary: bool,
+    },
+    CreateVirtualTable {
+        table: &'a str,
+        module: &'a str,
+    },
+    DropIndex {
+        index: &'a str,
+        table: &'a str,
+        temporary: bool,
+    },
+    DropTable {
+        table: &'a str,
+        temporary: bool,
+    },
+    DropTrigger {
+        trigger: &'a str,
+        table: &'a str,
+        temporary: bool,
+    },
+    DropView {
+        view: &'a str,
+        temporary: bool,
+    },
+    DropVirtualTable {
+        table: &'a str,
+        module: &'a str,
+    },
+    AlterTable {
+        schema: &'a str,
+        table: &'a str,
+    },
+    /// Reading a column. Ignoring this reads the column as NULL instead.
+    Read {
+        table: &'a str,
+        column: &'a str,
+    },
+    Insert {
+        table: &'a str,
+    },
+    /// Updating a column. Ignoring this leaves the column unchanged.
+    Update {
+        table: &'a str,
+        column: &'a str,
+    },
+    /// Deleting rows. Ignoring this deletes the rows one at a time rather than truncating
+    /// the table.
+    Delete {
+        table: &'a str,
+    },
+    Select,
+    Function {
+        name: &'a str,
+    },
+    Pragma {
+        name: &'a str,
+        value: Option<&'a str>,
+    },
+    Transaction {
+        operation: &'a str,
+    },
+    Savepoint {
+        operation: &'a str,
+        name: &'a str,
+    },
+    Attach {
+        filename: &'a str,
+    },
+    Detach {
+        schema: &'a str,
+    },
+    Reindex {
+        index: &'a str,
+    },
+    Analyze {
+        table: &'a str,
+    },
+    Recursive,
+    /// An action added in a newer version of sqlite.
+    Other {
+        code: i32,
+    },
+}
+
+/// What the authorizer decides for an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthResult {
+    Allow,
+    /// Fails to prepare the statement with an authorization error.
+    Deny,
+    /// Prepares the statement, but skips the action in a way which depends on the action.
+    /// For actions which can't be skipped this is the same as `Deny`.
+    Ignore,
+}
+
+/// Decides whether each action of a statement being prepared is allowed. Must not use the
+/// connection which called it.
+pub type Authorizer = Box<dyn FnMut(AuthAction) -> AuthResult + Send>;
+
+impl<'a> AuthAction<'a> {
+    unsafe fn from_raw(code: c_int, first: *const c_char, second: *const c_char) -> AuthAction<'a> {
+        let first = text(first);
+        let second = text(second);
+        let (first_text, second_text) = (first.unwrap_or(""), second.unwrap_or(""));
+
+        match code {
+            SQLITE_CREATE_INDEX | SQLITE_CREATE_TEMP_INDEX => AuthAction::CreateIndex {
+                index: first_text,
+                table: second_text,
+                temporary: code == SQLITE_CREATE_TEMP_INDEX,
+            },
+            SQLITE_CREATE_TABLE | SQLITE_CREATE_TEMP_TABLE => AuthAction::CreateTable {
+                table: first_text,
+                temporary: code == SQLITE_CREATE_TEMP_TABLE,
+            },
+            SQLITE_CREATE_TRIGGER | SQLITE_CREATE_TEMP_TRIGGER => AuthAction::CreateTrigger {
+                trigger: first_text,
+                table: second_text,
+                temporary: code == SQLITE_CREATE_TEMP_TRIGGER,
+            },
+            SQLITE_CREATE_VIEW | SQLITE_CREATE_TEMP_VIEW => AuthAction::CreateView {
+                view: first_text,
+                temporary: code == SQLITE_CREATE_TEMP_VIEW,
+            },
+            SQLITE_CREATE_VTABLE => AuthAction::CreateVirtualTable {
+                table: first_text,
+                module: second_text,
+            },
+            SQLITE_DROP_INDEX | SQLITE_DROP_TEMP_INDEX => AuthAction::DropIndex {
+                index: first_text,
+                table: second_text,
+                temporary: code == SQLITE_DROP_TEMP_INDEX,
+            },
+            SQLITE_DROP_TABLE | SQLITE_DROP_TEMP_TABLE => AuthAction::DropTable {
+                table: first_text,
+                temporary: code == SQLITE_DROP_TEMP_TABLE,
+            },
+            SQLITE_DROP_TRIGGER | SQLITE_DROP_TEMP_TRIGGER => AuthAction::DropTrigger {
+                trigger: first_text,
+                table: second_text,
+                temporary: code == SQLITE_DROP_TEMP_TRIGGER,
+            },
+            SQLITE_DROP_VIEW | SQLITE_DROP_TEMP_VIEW => AuthAction::DropView {
+                view: first_text,
+                temporary: code == SQLITE_DROP_TEMP_VIEW,
+            },
+            SQLITE_DROP_VTABLE => AuthAction::DropVirtualTable {
+                table: first_text,
+                module: second_text,
+            },
+            SQLITE_ALTER_TABLE => AuthAction::AlterTable {
+                schema: first_text,
+                table: second_text,
+            },
+            SQLITE_READ => AuthAction::Read {
+                table: first_text,
+                column: second_text,
+            },
+            SQLITE_INSERT => AuthAction::Insert { table: first_text },
+            SQLITE_UPDATE => AuthAction::Update {
+                table: first_text,
+                column: second_text,
+            },
+            SQLITE_DELETE => AuthAction::Delete { table: first_text },
+            SQLITE_SELECT => AuthAction::Select,
+            SQLITE_FUNCTION => AuthAction::Function { name: second_text },
+            SQLITE_PRAGMA => AuthAction::Pragma {
+                name: first_text,
+                value: second,
+            },
+            SQLITE_TRANSACTION => AuthAction::Transaction {
+                operation: first_text,
+            },
+            SQLITE_SAVEPOINT => AuthAction::Savepoint {
+                operation: first_text,
+                name: second_text,
+            },
+            SQLITE_ATTACH => AuthAction::Attach {
+                filename: first_text,
+            },
+            SQLITE_DETACH => AuthAction::Detach { schema: first_text },
+            SQLITE_REINDEX => AuthAction::Reindex { index: first_text },
+            SQLITE_ANALYZE => AuthAction::Analyze { table: first_text },
+            SQLITE_RECURSIVE => AuthAction::Recursive,
+            code => AuthAction::Other { code },
+        }
+    }
+}
+
+unsafe fn text<'a>(pointer: *const c_char) -> Option<&'a str> {
+    if pointer.is_null() {
+        None
+    } else {
+        CStr::from_ptr(pointer).to_str().ok()
+    }
+}
+
+impl Connection {
+    /// Sets a callback which decides whether each action of a statement is allowed as the
+    /// statement is prepared, such as to run untrusted SQL with restricted capabilities.
+    /// This replaces any existing authorizer. Cached statements were authorized when they
+    /// were prepared, so the statement cache is cleared.
+    pub fn set_authorizer(
+        &self,
+        authorizer: impl FnMut(AuthAction) -> AuthResult + Send + 'static,
+    ) {
+        unsafe extern "C" fn call_authorizer(
+            authorizer: *mut c_void,
+            code: c_int,
+            first: *const c_char,
+            second: *const c_char,
+            _schema: *const c_char,
+            _trigger_or_view: *const c_char,
+        ) -> c_int {
+            let authorizer = &mut *(authorizer as *mut Authorizer);
+            let action = AuthAction::from_raw(code, first, second);
+            // A panicking authorizer denies the action, as it's unclear whether it is safe
+            match panic::catch_unwind(AssertUnwindSafe(|| authorizer(action))) {
+                Ok(AuthResult::Allow) => SQLITE_OK,
+                Ok(AuthResult::Ignore) => SQLITE_IGNORE,
+                Ok(AuthResult::Deny) | Err(_) => SQLITE_DENY,
+            }
+        }
+
+        self.clear_statement_cache();
+        let mut authorizer: Box<Authorizer> = Box::new(Box::new(authorizer));
+        unsafe {
+            sqlite3_set_authorizer(
+                self.sqlite3,
+                Some(call_authorizer),
+                &mut *authorizer as *mut Authorizer as *mut c_void,
+            )
+        };
+        *self.authorizer.borrow_mut() = Some(authorizer);
+    }
+
+    /// Removes the callback set by `set_authorizer`.
+    pub fn clear_authorizer(&self) {
+        self.clear_statement_cache();
+        unsafe { sqlite3_set_authorizer(self.sqlite3, None, ptr::null_mut()) };
+        *self.authorizer.borrow_mut() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+
+    use crate::{
+        authorizer::{AuthAction, AuthResult},
+        connection::Connection,
+    };
+
+    #[test]
+    fn test_authorizer() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_authorizer"));
+        connection.exec("CREATE TABLE test (name TEXT, secret TEXT)")?()?;
+        connection.exec("INSERT INTO test VALUES ('one', 'hidden')")?()?;
+
+        let pragmas = Arc::new(Mutex::new(Vec::new()));
+        connection.set_authorizer({
+            let pragmas = pragmas.clone();
+            move |action| match action {
+                AuthAction::Read {
+                    column: "secret", ..
+                } => AuthResult::Ignore,
+                AuthAction::Read { .. } | AuthAction::Select => AuthResult::Allow,
+                AuthAction::Pragma { name, value } => {
+                    pragmas
+                        .lock()
+                        .unwrap()
+                        .push((name.to_string(), value.map(str::to_string)));
+                    AuthResult::Deny
+                }
+                _ => AuthResult::Deny,
+            }
+        });
+
+        // Ignored columns read as NULL
+        assert_eq!(
+            connection.select_row::<(String, Option<String>)>("SELECT name, secret FROM test")?()?,
+            Some(("one".to_string(), None))
+        );
+        assert!(connection
+            .exec("INSERT INTO test VALUES ('two', NULL)")
+            .is_err());
+        assert!(connection.exec("ATTACH ':memory:' AS other").is_err());
+        assert!(connection.exec("PRAGMA user_version = 3").is_err());
+        assert_eq!(
+            *pragmas.lock().unwrap(),
+            [("user_version".to_string(), Some("3".to_string()))]
+        );
+
+        connection.clear_authorizer();
+        connection.exec("INSERT INTO test VALUES ('two', NULL)")?()?;
+        assert_eq!(
+            connection.select_row::<String>("SELECT secret FROM test WHERE name = 'one'")?()?,
+            Some("hidden".to_string())
+        );
+        Ok(())
+    }
+}
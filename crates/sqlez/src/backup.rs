@@ -0,0 +1,190 @@
+use std::{ffi::CString, thread, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// How far a backup has progressed, in database pages.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BackupProgress {
+    /// Pages which are still to be copied.
+    pub remaining: i32,
+    /// Pages in the source database.
+    pub page_count: i32,
+}
+
+/// How a call to `Backup::run` ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupOutcome {
+    Finished,
+    Cancelled,
+}
+
+/// An in-progress copy of one connection's main database into another's, which copies a
+/// limited number of pages at a time. The source database can be used between steps, so a
+/// large backup doesn't hold its lock for the whole copy. Writes to the source made through
+/// other connections restart the copy. Dropping the backup before it finishes leaves the
+/// destination as it was.
+pub struct Backup<'a> {
+    raw_backup: *mut sqlite3_backup,
+    destination: &'a Connection,
+    _source: &'a Connection,
+    progress: BackupProgress,
+}
+
+impl<'a> Backup<'a> {
+    pub fn new(source: &'a Connection, destination: &'a Connection) -> Result<Self> {
+        let main = CString::new("main")?;
+        let raw_backup = unsafe {
+            sqlite3_backup_init(
+                destination.sqlite3,
+                main.as_ptr(),
+                source.sqlite3,
+                main.as_ptr(),
+            )
+        };
+        if raw_backup.is_null() {
+            return Err(destination.error_for_code(destination.last_error_code()))
+                .context("Failed to start backup");
+        }
+
+        Ok(Self {
+            raw_backup,
+            destination,
+            _source: source,
+            progress: BackupProgress::default(),
+        })
+    }
+
+    /// Copies up to `pages` pages, or all of the remaining pages when `pages` is negative.
+    /// Returns true once the backup is complete. If the source or destination is locked by
+    /// another connection, this copies nothing and can be called again later.
+    pub fn step(&mut self, pages: i32) -> Result<bool> {
+        let code = unsafe { sqlite3_backup_step(self.raw_backup, pages) };
+        self.progress = unsafe {
+            BackupProgress {
+                remaining: sqlite3_backup_remaining(self.raw_backup),
+                page_count: sqlite3_backup_pagecount(self.raw_backup),
+            }
+        };
+
+        match code {
+            SQLITE_DONE => Ok(true),
+            SQLITE_OK | SQLITE_BUSY | SQLITE_LOCKED => Ok(false),
+            code => Err(self.destination.error_for_code(code)).context("Failed to step backup"),
+        }
+    }
+
+    /// The progress as of the last call to `step`.
+    pub fn progress(&self) -> BackupProgress {
+        self.progress
+    }
+
+    /// Steps through the backup `pages_per_step` pages at a time, sleeping for `pause` in
+    /// between so that other work can use the databases. `on_progress` is called after
+    /// each step, and cancels the backup by returning false.
+    pub fn run(
+        &mut self,
+        pages_per_step: i32,
+        pause: Duration,
+        mut on_progress: impl FnMut(BackupProgress) -> bool,
+    ) -> Result<BackupOutcome> {
+        if pages_per_step == 0 {
+            return Err(anyhow!(
+                "A backup can't make progress copying 0 pages per step"
+            ));
+        }
+
+        loop {
+            let done = self.step(pages_per_step)?;
+            if !on_progress(self.progress) {
+                return Ok(BackupOutcome::Cancelled);
+            }
+            if done {
+                return Ok(BackupOutcome::Finished);
+            }
+            thread::sleep(pause);
+        }
+    }
+}
+
+impl<'a> Drop for Backup<'a> {
+    fn drop(&mut self) {
+        unsafe { sqlite3_backup_finish(self.raw_backup) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Result;
+
+    use crate::{
+        backup::{Backup, BackupOutcome, BackupProgress},
+        connection::Connection,
+    };
+
+    fn source_database(name: &str) -> Result<Connection> {
+        let connection = Connection::open_memory(Some(name));
+        connection.exec("PRAGMA page_size = 1024")?()?;
+        connection.exec("CREATE TABLE test (value TEXT)")?()?;
+        for _ in 0..64 {
+            connection.exec_bound::<String>("INSERT INTO test VALUES (?)")?("a".repeat(900))?;
+        }
+        Ok(connection)
+    }
+
+    fn row_count(connection: &Connection) -> Result<Option<i64>> {
+        connection.select_row::<i64>("SELECT count(*) FROM test")?()
+    }
+
+    #[test]
+    fn test_incremental_backup() -> Result<()> {
+        let source = source_database("incremental_backup")?;
+        let destination = Connection::open_memory(Some("incremental_backup_destination"));
+
+        let mut progress = Vec::new();
+        let outcome =
+            Backup::new(&source, &destination)?.run(8, Duration::ZERO, |step_progress| {
+                progress.push(step_progress);
+                true
+            })?;
+
+        assert_eq!(outcome, BackupOutcome::Finished);
+        assert!(progress.len() > 1);
+        assert_eq!(
+            progress.last(),
+            Some(&BackupProgress {
+                remaining: 0,
+                page_count: progress[0].page_count,
+            })
+        );
+        assert!(progress
+            .windows(2)
+            .all(|steps| steps[1].remaining < steps[0].remaining));
+        assert_eq!(row_count(&destination)?, Some(64));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_backup() -> Result<()> {
+        let source = source_database("cancelled_backup")?;
+        let destination = Connection::open_memory(Some("cancelled_backup_destination"));
+
+        let mut steps = 0;
+        let outcome = Backup::new(&source, &destination)?.run(1, Duration::ZERO, |_| {
+            steps += 1;
+            steps < 3
+        })?;
+
+        assert_eq!(outcome, BackupOutcome::Cancelled);
+        assert_eq!(steps, 3);
+        assert!(row_count(&destination).is_err());
+
+        source.backup_main(&destination)?;
+        assert_eq!(row_count(&destination)?, Some(64));
+        Ok(())
+    }
+}
@@ -3,9 +3,10 @@ use std::{
     os::unix::prelude::OsStrExt,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 
 use crate::statement::{SqlType, Statement};
 
@@ -60,10 +61,22 @@ impl<const C: usize> Bind for &[u8; C] {
     }
 }
 
+impl<const C: usize> StaticColumnCount for [u8; C] {}
+impl<const C: usize> Bind for [u8; C] {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        self.as_slice().bind(statement, start_index)
+    }
+}
+
 impl<const C: usize> Column for [u8; C] {
     fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
         let bytes_slice = statement.column_blob(start_index)?;
-        let array = bytes_slice.try_into()?;
+        let array = bytes_slice.try_into().with_context(|| {
+            format!(
+                "Failed to read [u8; {C}] at index {start_index} from a blob of {} bytes",
+                bytes_slice.len()
+            )
+        })?;
         Ok((array, start_index + 1))
     }
 }
@@ -251,6 +264,19 @@ impl Column for String {
     }
 }
 
+/// Reads a text column, replacing invalid UTF-8 instead of failing like `String` does.
+/// Only for text that is displayed rather than relied upon.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LossyString(pub String);
+
+impl StaticColumnCount for LossyString {}
+impl Column for LossyString {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let result = statement.column_text_lossy(start_index)?;
+        Ok((LossyString(result.into_owned()), start_index + 1))
+    }
+}
+
 impl<T: StaticColumnCount> StaticColumnCount for Option<T> {
     fn column_count() -> usize {
         T::column_count()
@@ -262,7 +288,7 @@ impl<T: Bind + StaticColumnCount> Bind for Option<T> {
             this.bind(statement, start_index)
         } else {
             for _ in 0..T::column_count() {
-                statement.bind_null(start_index)?;
+                statement.bind_null_at(start_index)?;
                 start_index += 1;
             }
             Ok(start_index)
@@ -270,6 +296,33 @@ impl<T: Bind + StaticColumnCount> Bind for Option<T> {
     }
 }
 
+/// Binds an explicit SQL NULL, for parameters which have no corresponding Rust value.
+pub struct Null;
+
+impl StaticColumnCount for Null {}
+impl Bind for Null {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        statement
+            .bind_null_at(start_index)
+            .with_context(|| format!("Failed to bind NULL at index {start_index}"))?;
+        Ok(start_index + 1)
+    }
+}
+
+/// Binds a blob of the given number of zero bytes, reserving space for a large value which
+/// is then written incrementally through `Connection::open_blob`.
+pub struct ZeroBlob(pub usize);
+
+impl StaticColumnCount for ZeroBlob {}
+impl Bind for ZeroBlob {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        statement
+            .bind_zeroblob(start_index, self.0)
+            .with_context(|| format!("Failed to bind zeroblob at index {start_index}"))?;
+        Ok(start_index + 1)
+    }
+}
+
 impl<T: Column + StaticColumnCount> Column for Option<T> {
     fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
         if let SqlType::Null = statement.column_type(start_index)? {
@@ -347,6 +400,106 @@ impl Column for uuid::Uuid {
     }
 }
 
+/// Stores a uuid as its hyphenated text form, for columns which are read by people or by
+/// other tools. Plain `uuid::Uuid` values are stored as 16 byte blobs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct UuidText(pub uuid::Uuid);
+
+impl StaticColumnCount for UuidText {}
+impl Bind for UuidText {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let mut buffer = uuid::Uuid::encode_buffer();
+        let text: &str = self.0.hyphenated().encode_lower(&mut buffer);
+        text.bind(statement, start_index)
+            .with_context(|| format!("Failed to bind uuid at index {start_index}"))
+    }
+}
+
+impl Column for UuidText {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let text = statement.column_text(start_index)?;
+        let uuid = uuid::Uuid::parse_str(text)
+            .with_context(|| format!("Failed to parse uuid {text:?} in column {start_index}"))?;
+        Ok((UuidText(uuid), start_index + 1))
+    }
+}
+
+// Times are stored as integer nanoseconds since the unix epoch, which covers the years
+// 1678 to 2262
+impl StaticColumnCount for SystemTime {}
+impl Bind for SystemTime {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let nanos = match self.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => i64::try_from(since_epoch.as_nanos()),
+            Err(before_epoch) => {
+                i64::try_from(before_epoch.duration().as_nanos()).map(|nanos| -nanos)
+            }
+        }
+        .map_err(|_| anyhow!("Time at index {start_index} is too far from the unix epoch"))?;
+        nanos
+            .bind(statement, start_index)
+            .with_context(|| format!("Failed to bind SystemTime at index {start_index}"))
+    }
+}
+
+impl Column for SystemTime {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let nanos = statement.column_int64(start_index)?;
+        let offset = Duration::from_nanos(nanos.unsigned_abs());
+        let time = if nanos >= 0 {
+            UNIX_EPOCH + offset
+        } else {
+            UNIX_EPOCH - offset
+        };
+        Ok((time, start_index + 1))
+    }
+}
+
+// Durations are stored as integer nanoseconds
+impl StaticColumnCount for Duration {}
+impl Bind for Duration {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let nanos = i64::try_from(self.as_nanos())
+            .map_err(|_| anyhow!("Duration at index {start_index} is too long to store"))?;
+        nanos
+            .bind(statement, start_index)
+            .with_context(|| format!("Failed to bind Duration at index {start_index}"))
+    }
+}
+
+impl Column for Duration {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let nanos = statement.column_int64(start_index)?;
+        let nanos = u64::try_from(nanos)
+            .map_err(|_| anyhow!("Negative duration in column {start_index}"))?;
+        Ok((Duration::from_nanos(nanos), start_index + 1))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl StaticColumnCount for chrono::DateTime<chrono::Utc> {}
+#[cfg(feature = "chrono")]
+impl Bind for chrono::DateTime<chrono::Utc> {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let nanos = self
+            .timestamp_nanos_opt()
+            .ok_or_else(|| anyhow!("Time at index {start_index} is too far from the unix epoch"))?;
+        nanos
+            .bind(statement, start_index)
+            .with_context(|| format!("Failed to bind DateTime at index {start_index}"))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Column for chrono::DateTime<chrono::Utc> {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        use chrono::TimeZone as _;
+
+        let nanos = statement.column_int64(start_index)?;
+        Ok((chrono::Utc.timestamp_nanos(nanos), start_index + 1))
+    }
+}
+
 impl StaticColumnCount for () {
     fn column_count() -> usize {
         0
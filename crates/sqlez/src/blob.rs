@@ -0,0 +1,234 @@
+use std::{
+    ffi::{c_int, c_void, CString},
+    io::{self, Read, Seek, SeekFrom, Write},
+    ptr,
+};
+
+use anyhow::{Context, Result};
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// A handle onto a single blob value, which reads and writes it in chunks rather than
+/// copying the whole value in and out of a statement. Blobs can't change size through the
+/// handle, so space for a value is reserved by first storing a `ZeroBlob` of the right
+/// length. The handle is closed when dropped.
+pub struct Blob<'a> {
+    connection: &'a Connection,
+    raw_blob: *mut sqlite3_blob,
+    position: usize,
+    len: usize,
+}
+
+impl Connection {
+    /// Opens the blob in `column` of the row with the given `rowid`, within the table of the
+    /// `database` schema (such as "main"). Writing to the blob requires `read_only` to be false.
+    pub fn open_blob(
+        &self,
+        database: &str,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        read_only: bool,
+    ) -> Result<Blob<'_>> {
+        let database = CString::new(database)?;
+        let table_name = CString::new(table)?;
+        let column_name = CString::new(column)?;
+
+        let mut raw_blob = ptr::null_mut();
+        let code = unsafe {
+            sqlite3_blob_open(
+                self.sqlite3,
+                database.as_ptr(),
+                table_name.as_ptr(),
+                column_name.as_ptr(),
+                rowid,
+                c_int::from(!read_only),
+                &mut raw_blob,
+            )
+        };
+        if code != SQLITE_OK {
+            // Even failed opens can allocate a handle
+            unsafe { sqlite3_blob_close(raw_blob) };
+            return Err(self.error_for_code(code)).with_context(|| {
+                format!("Failed to open blob in {table}.{column} of row {rowid}")
+            });
+        }
+
+        let len = unsafe { sqlite3_blob_bytes(raw_blob) } as usize;
+        Ok(Blob {
+            connection: self,
+            raw_blob,
+            position: 0,
+            len,
+        })
+    }
+}
+
+impl<'a> Blob<'a> {
+    /// The size of the blob in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Moves the handle onto the same column of another row, which is cheaper than opening
+    /// a new handle. The position is reset to the start of the new blob.
+    pub fn reopen(&mut self, rowid: i64) -> Result<()> {
+        let code = unsafe { sqlite3_blob_reopen(self.raw_blob, rowid) };
+        if code != SQLITE_OK {
+            return Err(self.connection.error_for_code(code))
+                .with_context(|| format!("Failed to reopen blob at row {rowid}"));
+        }
+        self.position = 0;
+        self.len = unsafe { sqlite3_blob_bytes(self.raw_blob) } as usize;
+        Ok(())
+    }
+
+    fn io_error(&self, code: c_int) -> io::Error {
+        io::Error::other(self.connection.error_for_code(code))
+    }
+}
+
+impl<'a> Read for Blob<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = buf.len().min(self.len.saturating_sub(self.position));
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let code = unsafe {
+            sqlite3_blob_read(
+                self.raw_blob,
+                buf.as_mut_ptr() as *mut c_void,
+                count as c_int,
+                self.position as c_int,
+            )
+        };
+        if code != SQLITE_OK {
+            return Err(self.io_error(code));
+        }
+        self.position += count;
+        Ok(count)
+    }
+}
+
+impl<'a> Write for Blob<'a> {
+    /// Writes as much of `buf` as fits before the end of the blob. Once the end is reached
+    /// this writes nothing, so `write_all` fails rather than growing the blob.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(self.len.saturating_sub(self.position));
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let code = unsafe {
+            sqlite3_blob_write(
+                self.raw_blob,
+                buf.as_ptr() as *const c_void,
+                count as c_int,
+                self.position as c_int,
+            )
+        };
+        if code != SQLITE_OK {
+            return Err(self.io_error(code));
+        }
+        self.position += count;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Seek for Blob<'a> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset as i64),
+            SeekFrom::End(offset) => (self.len as i64).checked_add(offset),
+            SeekFrom::Current(offset) => (self.position as i64).checked_add(offset),
+        };
+        match position {
+            Some(position) if position >= 0 && position <= self.len as i64 => {
+                self.position = position as usize;
+                Ok(position as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cannot seek outside of the blob",
+            )),
+        }
+    }
+}
+
+impl<'a> Drop for Blob<'a> {
+    fn drop(&mut self) {
+        unsafe { sqlite3_blob_close(self.raw_blob) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+
+    use anyhow::Result;
+
+    use crate::{bindable::ZeroBlob, connection::Connection};
+
+    #[test]
+    fn test_incremental_blob_io() -> Result<()> {
+        let connection = Connection::open_memory(Some("incremental_blob_io"));
+        connection.exec("CREATE TABLE files (id INTEGER PRIMARY KEY, contents BLOB)")?()?;
+        connection.exec_bound::<(i64, ZeroBlob)>("INSERT INTO files VALUES (?, ?)")?((
+            1,
+            ZeroBlob(10_000),
+        ))?;
+        connection.exec("INSERT INTO files VALUES (2, x'0102030405')")?()?;
+
+        let contents = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        {
+            let mut blob = connection.open_blob("main", "files", "contents", 1, false)?;
+            assert_eq!(blob.len(), 10_000);
+            for chunk in contents.chunks(4096) {
+                blob.write_all(chunk)?;
+            }
+            // The blob can't grow past the space reserved for it
+            assert_eq!(
+                blob.write_all(&[0]).unwrap_err().kind(),
+                io::ErrorKind::WriteZero
+            );
+        }
+        assert_eq!(
+            connection.select_row::<Vec<u8>>("SELECT contents FROM files WHERE id = 1")?()?,
+            Some(contents.clone())
+        );
+
+        let mut blob = connection.open_blob("main", "files", "contents", 1, true)?;
+        let mut read = Vec::new();
+        blob.read_to_end(&mut read)?;
+        assert_eq!(read, contents);
+
+        let mut chunk = [0; 4];
+        blob.seek(SeekFrom::End(-4))?;
+        blob.read_exact(&mut chunk)?;
+        assert_eq!(chunk, contents[9_996..]);
+        assert!(blob.seek(SeekFrom::Current(1)).is_err());
+        blob.rewind()?;
+        assert!(blob.write(&[1]).is_err());
+
+        blob.reopen(2)?;
+        assert_eq!(blob.len(), 5);
+        blob.seek(SeekFrom::Start(2))?;
+        blob.read_exact(&mut chunk[..3])?;
+        assert_eq!(chunk[..3], [3, 4, 5]);
+
+        assert!(connection
+            .open_blob("main", "files", "contents", 3, true)
+            .is_err());
+        Ok(())
+    }
+}
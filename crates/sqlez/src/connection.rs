@@ -1,53 +1,297 @@
 use std::{
     cell::RefCell,
-    ffi::{CStr, CString},
+    ffi::{c_int, c_void, CStr, CString},
     marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
     path::Path,
     ptr,
+    time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use libsqlite3_sys::*;
 
+use crate::{
+    authorizer::Authorizer,
+    backup::Backup,
+    bindable::Column,
+    encryption::KeyProvider,
+    error::Error,
+    hooks::{CommitHook, ProgressHandler, RollbackHook, UpdateHook},
+    interrupt::InterruptHandle,
+    pragma::JournalMode,
+    statement::Statement,
+    statement_cache::{StatementCache, DEFAULT_STATEMENT_CACHE_CAPACITY},
+    trace::Tracer,
+};
+
+/// How much work `Connection::wal_checkpoint` does, matching the modes of
+/// `sqlite3_wal_checkpoint_v2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointMode {
+    Passive,
+    Full,
+    Restart,
+    Truncate,
+}
+
+/// The result of a WAL checkpoint. When another connection prevented the checkpoint
+/// from completing, `busy` is set and the frame counts describe the partial progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckpointOutcome {
+    pub busy: bool,
+    pub log_frames: i32,
+    pub checkpointed_frames: i32,
+}
+
+/// What `Connection::maintenance` managed to do within its time budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// The result of checkpointing the write-ahead log, or `None` if the database
+    /// isn't in WAL mode.
+    pub checkpoint: Option<CheckpointOutcome>,
+    pub optimized: bool,
+}
+
+/// Called with the SQL of each statement just before it starts running.
+pub type StatementLogger = Box<dyn Fn(&str) + Send>;
+
+/// Called with the number of times it has already been called for the same lock, when
+/// a statement is blocked by another connection. Returns whether to try again.
+pub type BusyHandler = Box<dyn FnMut(i32) -> bool + Send>;
+
 pub struct Connection {
     pub(crate) sqlite3: *mut sqlite3,
     persistent: bool,
     pub(crate) write: RefCell<bool>,
+    pub(crate) statement_cache: RefCell<StatementCache>,
+    pub(crate) statement_logger: RefCell<Option<StatementLogger>>,
+    // Boxed twice so that sqlite can be given a thin pointer to the handler
+    busy_handler: RefCell<Option<Box<BusyHandler>>>,
+    pub(crate) update_hook: RefCell<Option<Box<UpdateHook>>>,
+    pub(crate) commit_hook: RefCell<Option<Box<CommitHook>>>,
+    pub(crate) rollback_hook: RefCell<Option<Box<RollbackHook>>>,
+    pub(crate) progress_handler: RefCell<Option<Box<ProgressHandler>>>,
+    pub(crate) tracer: RefCell<Option<Box<Tracer>>>,
+    pub(crate) authorizer: RefCell<Option<Box<Authorizer>>>,
+    pub(crate) interrupt_handle: InterruptHandle,
     _sqlite: PhantomData<sqlite3>,
 }
 unsafe impl Send for Connection {}
 
+/// Configures how a connection is opened, for cases which `Connection::open_path` and
+/// `Connection::open_memory` don't cover, such as opening a database read-only. The
+/// defaults match `open_path`: read-write, creating the database if it's missing, with URI
+/// filenames and without sqlite's own mutex.
+pub struct ConnectionBuilder {
+    uri: String,
+    read_only: bool,
+    create: bool,
+    full_mutex: bool,
+    uri_filenames: bool,
+    shared_cache: bool,
+    in_memory: bool,
+    vfs: Option<String>,
+    key_provider: Option<KeyProvider>,
+}
+
+impl ConnectionBuilder {
+    /// Opens the database read-only. Statements which write are rejected when prepared.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Whether to create the database if it doesn't exist. Defaults to true, and must be
+    /// turned off for read-only connections.
+    pub fn with_create(mut self, create: bool) -> Self {
+        self.create = create;
+        self
+    }
+
+    /// Uses sqlite's serialized threading mode for this connection, rather than relying on
+    /// the connection only being used from one thread at a time.
+    pub fn with_full_mutex(mut self, full_mutex: bool) -> Self {
+        self.full_mutex = full_mutex;
+        self
+    }
+
+    /// Whether the filename is interpreted as a `file:` URI with query parameters such as
+    /// `mode` and `cache`. Defaults to true.
+    pub fn with_uri_filenames(mut self, uri_filenames: bool) -> Self {
+        self.uri_filenames = uri_filenames;
+        self
+    }
+
+    /// Shares the page cache with other connections to the same database in this process.
+    pub fn with_shared_cache(mut self, shared_cache: bool) -> Self {
+        self.shared_cache = shared_cache;
+        self
+    }
+
+    /// Opens an in-memory database, using the filename only to identify a shared cache.
+    pub fn with_in_memory(mut self, in_memory: bool) -> Self {
+        self.in_memory = in_memory;
+        self
+    }
+
+    /// Opens the database through the named VFS rather than the default one.
+    pub fn with_vfs(mut self, vfs: impl Into<String>) -> Self {
+        self.vfs = Some(vfs.into());
+        self
+    }
+
+    /// Opens the database encrypted, with the key from `key_provider`. Building fails when
+    /// sqlez was built without the `sqlcipher` feature, rather than storing data unencrypted.
+    pub fn with_key_provider(mut self, key_provider: KeyProvider) -> Self {
+        self.key_provider = Some(key_provider);
+        self
+    }
+
+    pub fn build(self) -> Result<Connection> {
+        if self.read_only && self.create {
+            bail!("A read-only connection can't also create its database");
+        }
+
+        let mut flags = if self.read_only {
+            SQLITE_OPEN_READONLY
+        } else {
+            SQLITE_OPEN_READWRITE
+        };
+        if self.create {
+            flags |= SQLITE_OPEN_CREATE;
+        }
+        flags |= if self.full_mutex {
+            SQLITE_OPEN_FULLMUTEX
+        } else {
+            SQLITE_OPEN_NOMUTEX
+        };
+        if self.uri_filenames {
+            flags |= SQLITE_OPEN_URI;
+        }
+        if self.shared_cache {
+            flags |= SQLITE_OPEN_SHAREDCACHE;
+        }
+        if self.in_memory {
+            flags |= SQLITE_OPEN_MEMORY;
+        }
+
+        #[cfg(not(feature = "sqlcipher"))]
+        if self.key_provider.is_some() {
+            bail!("Encrypted databases need sqlez to be built with the sqlcipher feature");
+        }
+
+        let connection =
+            Connection::open_with_flags(&self.uri, !self.in_memory, flags, self.vfs.as_deref())?;
+        #[cfg(feature = "sqlcipher")]
+        if let Some(key_provider) = &self.key_provider {
+            connection.set_key(&key_provider()?)?;
+        }
+        Ok(connection)
+    }
+}
+
 impl Connection {
     pub(crate) fn open(uri: &str, persistent: bool) -> Result<Self> {
+        let flags =
+            SQLITE_OPEN_CREATE | SQLITE_OPEN_NOMUTEX | SQLITE_OPEN_READWRITE | SQLITE_OPEN_URI;
+        Self::open_with_flags(uri, persistent, flags, None)
+    }
+
+    pub(crate) fn open_with_flags(
+        uri: &str,
+        persistent: bool,
+        flags: c_int,
+        vfs: Option<&str>,
+    ) -> Result<Self> {
+        let vfs = vfs.map(CString::new).transpose()?;
         let mut connection = Self {
             sqlite3: ptr::null_mut(),
             persistent,
-            write: RefCell::new(true),
+            write: RefCell::new(flags & SQLITE_OPEN_READONLY == 0),
+            statement_cache: RefCell::new(StatementCache::new(DEFAULT_STATEMENT_CACHE_CAPACITY)),
+            statement_logger: RefCell::new(None),
+            busy_handler: RefCell::new(None),
+            update_hook: RefCell::new(None),
+            commit_hook: RefCell::new(None),
+            rollback_hook: RefCell::new(None),
+            progress_handler: RefCell::new(None),
+            tracer: RefCell::new(None),
+            authorizer: RefCell::new(None),
+            interrupt_handle: InterruptHandle::new(ptr::null_mut()),
             _sqlite: PhantomData,
         };
 
-        let flags = SQLITE_OPEN_CREATE | SQLITE_OPEN_NOMUTEX | SQLITE_OPEN_READWRITE;
         unsafe {
             sqlite3_open_v2(
                 CString::new(uri)?.as_ptr(),
                 &mut connection.sqlite3,
                 flags,
-                ptr::null(),
+                vfs.as_ref().map_or(ptr::null(), |vfs| vfs.as_ptr()),
             );
 
             // Turn on extended error codes
             sqlite3_extended_result_codes(connection.sqlite3, 1);
+            connection.interrupt_handle = InterruptHandle::new(connection.sqlite3);
 
             connection.last_error()?;
         }
+        // The bundled sqlite allows loading extensions unless it is turned off
+        connection.enable_load_extension(false)?;
 
         Ok(connection)
     }
 
-    /// Attempts to open the database at uri. If it fails, a shared memory db will be opened
-    /// instead.
-    pub fn open_file(uri: &str) -> Self {
-        Self::open(uri, true).unwrap_or_else(|_| Self::open_memory(Some(uri)))
+    /// Starts configuring a connection to `uri`, see `ConnectionBuilder`.
+    pub fn builder(uri: impl Into<String>) -> ConnectionBuilder {
+        ConnectionBuilder {
+            uri: uri.into(),
+            read_only: false,
+            create: true,
+            full_mutex: false,
+            uri_filenames: true,
+            shared_cache: false,
+            in_memory: false,
+            vfs: None,
+            key_provider: None,
+        }
+    }
+
+    /// Attempts to open the database at uri, encrypted with the key from `key_provider` if
+    /// one is given. If it fails, a shared memory db will be opened instead. Corrupt
+    /// databases are recovered as by `open_file_with_recovery`, and what happened is logged.
+    /// Encrypted databases aren't recovered, as being given the wrong key looks the same as
+    /// corruption.
+    pub fn open_file(uri: &str, key_provider: Option<&KeyProvider>) -> Self {
+        if let Some(key_provider) = key_provider {
+            return Self::open_encrypted(uri, key_provider).unwrap_or_else(|error| {
+                log::error!("Encrypted database {uri} could not be opened: {error:?}");
+                Self::open_memory(Some(uri))
+            });
+        }
+
+        let (connection, report) = Self::open_file_with_recovery(uri);
+        if let Some(report) = report {
+            log::error!("Database {uri} could not be opened as it was: {report:?}");
+        }
+        connection
+    }
+
+    /// Opens the database at `path`, escaping it into a `file:` URI so that paths
+    /// containing characters like spaces, `?` or `#` are opened correctly.
+    pub fn open_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open(&path_to_uri(path.as_ref()), true)
+    }
+
+    /// Opens the database at `path` like `open_path`, and switches it to write-ahead
+    /// logging so that readers and a writer on other connections don't block each other.
+    /// The journal mode is persistent, so later connections to the file also use WAL.
+    pub fn open_path_wal(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = Self::open_path(path)?;
+        if connection.pragma().set_journal_mode(JournalMode::Wal)? != JournalMode::Wal {
+            bail!("Failed to enable write-ahead logging");
+        }
+        Ok(connection)
     }
 
     pub fn open_memory(uri: Option<&str>) -> Self {
@@ -60,6 +304,16 @@ impl Connection {
         Self::open(&in_memory_path, false).expect("Could not create fallback in memory db")
     }
 
+    /// Opens a private in-memory db, rather than the shared cache db used by `open_memory`,
+    /// with its page cache limited to `cache_size`. As with `PRAGMA cache_size`, a positive
+    /// size is a number of pages and a negative one a number of kibibytes. This keeps cache
+    /// behavior deterministic for tests and benchmarks.
+    pub fn open_memory_with_cache(name: &str, cache_size: i32) -> Result<Self> {
+        let connection = Self::open(&format!("file:{}?mode=memory", name), false)?;
+        connection.exec(&format!("PRAGMA cache_size = {}", cache_size))?()?;
+        Ok(connection)
+    }
+
     pub fn persistent(&self) -> bool {
         self.persistent
     }
@@ -68,25 +322,278 @@ impl Connection {
         *self.write.borrow()
     }
 
-    pub fn backup_main(&self, destination: &Connection) -> Result<()> {
+    /// Sets a logger that is passed each statement's SQL before the statement runs, so
+    /// that a statement which fails or hangs can be identified from the log. Unlike a
+    /// trace callback, this is called before the statement executes. Passing `None`
+    /// removes the logger.
+    pub fn set_statement_logger(&self, logger: Option<StatementLogger>) {
+        *self.statement_logger.borrow_mut() = logger;
+    }
+
+    pub(crate) fn log_statement(&self, sql: &str) {
+        if let Some(logger) = self.statement_logger.borrow().as_ref() {
+            logger(sql);
+        }
+    }
+
+    /// Makes statements which are blocked by another connection's lock keep retrying for up
+    /// to `timeout` before failing with `Error::Busy`. This replaces any busy handler.
+    pub fn set_busy_timeout(&self, timeout: Duration) {
+        let milliseconds = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+        unsafe { sqlite3_busy_timeout(self.sqlite3, milliseconds) };
+        *self.busy_handler.borrow_mut() = None;
+    }
+
+    /// Sets a handler which decides whether statements blocked by another connection's
+    /// lock should retry, so that callers can back off as they see fit. It is called
+    /// with the number of previous attempts, and returning false fails the statement
+    /// with `Error::Busy`. This replaces any busy timeout.
+    pub fn set_busy_handler(&self, handler: impl FnMut(i32) -> bool + Send + 'static) {
+        extern "C" fn call_busy_handler(handler: *mut c_void, count: c_int) -> c_int {
+            let handler = unsafe { &mut *(handler as *mut BusyHandler) };
+            // Panics can't unwind through sqlite, so treat them as giving up
+            panic::catch_unwind(AssertUnwindSafe(|| handler(count))).unwrap_or(false) as c_int
+        }
+
+        let mut handler: Box<BusyHandler> = Box::new(Box::new(handler));
         unsafe {
-            let backup = sqlite3_backup_init(
-                destination.sqlite3,
-                CString::new("main")?.as_ptr(),
+            sqlite3_busy_handler(
                 self.sqlite3,
-                CString::new("main")?.as_ptr(),
-            );
-            sqlite3_backup_step(backup, -1);
-            sqlite3_backup_finish(backup);
-            destination.last_error()
+                Some(call_busy_handler),
+                &mut *handler as *mut BusyHandler as *mut c_void,
+            )
+        };
+        *self.busy_handler.borrow_mut() = Some(handler);
+    }
+
+    /// Copies the main database into `destination` in one step. Use `Backup` to copy a
+    /// large database in increments instead.
+    pub fn backup_main(&self, destination: &Connection) -> Result<()> {
+        let mut backup = Backup::new(self, destination)?;
+        if !backup.step(-1)? {
+            bail!("Backup couldn't complete because a database is locked");
         }
+        Ok(())
     }
 
     pub fn backup_main_to(&self, destination: impl AsRef<Path>) -> Result<()> {
-        let destination = Self::open_file(destination.as_ref().to_string_lossy().as_ref());
+        let destination = Self::open_file(destination.as_ref().to_string_lossy().as_ref(), None);
         self.backup_main(&destination)
     }
 
+    /// Attaches the database at `path` as `alias`, creating it if it doesn't exist, so that
+    /// its tables can be used through this connection as `alias.table`. This lets rows be
+    /// copied between databases with a single `INSERT INTO ... SELECT`.
+    pub fn attach(&self, path: impl AsRef<Path>, alias: &str) -> Result<()> {
+        let path = path.as_ref();
+        self.validate_new_schema(alias)?;
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.is_dir() {
+                bail!(
+                    "Cannot attach {:?}, as its directory doesn't exist",
+                    path.display()
+                );
+            }
+        }
+
+        self.exec_bound::<(&str, &str)>("ATTACH DATABASE ? AS ?")?((&path_to_uri(path), alias))
+    }
+
+    /// Attaches the database at `uri` as `schema` in read-only mode, so that it can be
+    /// queried but not modified through this connection. `uri` is either a `file:` URI,
+    /// whose `mode` parameter is replaced, or a path.
+    pub fn attach_readonly(&self, uri: &str, schema: &str) -> Result<()> {
+        self.validate_new_schema(schema)?;
+        let uri = if uri.starts_with("file:") {
+            uri.to_string()
+        } else {
+            path_to_uri(Path::new(uri))
+        };
+        let (uri, fragment) = match uri.split_once('#') {
+            Some((uri, fragment)) => (uri, Some(fragment)),
+            None => (uri.as_str(), None),
+        };
+        let (location, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let mut parameters = query
+            .split('&')
+            .filter(|parameter| !parameter.is_empty() && !parameter.starts_with("mode="))
+            .collect::<Vec<_>>();
+        parameters.push("mode=ro");
+        let mut uri = format!("{}?{}", location, parameters.join("&"));
+        if let Some(fragment) = fragment {
+            uri.push('#');
+            uri.push_str(fragment);
+        }
+
+        self.exec_bound::<(&str, &str)>("ATTACH DATABASE ? AS ?")?((&uri, schema))
+    }
+
+    pub fn detach(&self, schema: &str) -> Result<()> {
+        if is_builtin_schema(schema) {
+            bail!("Cannot detach the {schema} database");
+        }
+        if !self
+            .attached_schemas()?
+            .iter()
+            .any(|attached| attached == schema)
+        {
+            bail!("No database is attached as {schema}");
+        }
+        // Cached statements may refer to the schema, and detaching isn't allowed while they
+        // hold onto it
+        self.clear_statement_cache();
+        self.exec_bound::<&str>("DETACH DATABASE ?")?(schema)
+    }
+
+    /// Returns the names of the databases attached to this connection, not including the
+    /// main and temp databases.
+    pub fn attached_schemas(&self) -> Result<Vec<String>> {
+        let schemas = self.select::<String>("SELECT name FROM pragma_database_list")?()?;
+        Ok(schemas
+            .into_iter()
+            .filter(|name| !is_builtin_schema(name))
+            .collect())
+    }
+
+    fn validate_new_schema(&self, schema: &str) -> Result<()> {
+        let mut chars = schema.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            bail!("{schema:?} is not a valid schema name");
+        }
+        if is_builtin_schema(schema) {
+            bail!("Cannot attach a database as {schema}");
+        }
+        if self
+            .attached_schemas()?
+            .iter()
+            .any(|attached| attached.eq_ignore_ascii_case(schema))
+        {
+            bail!("A database is already attached as {schema}");
+        }
+        Ok(())
+    }
+
+    /// Reads the current value of the pragma `name`, such as `cache_size`.
+    pub fn pragma_get<C: Column>(&self, name: &str) -> Result<Option<C>> {
+        if !name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_')
+        {
+            bail!("Invalid pragma name: {:?}", name);
+        }
+        self.select_row::<C>(&format!("PRAGMA {}", name))?()
+    }
+
+    /// Estimates the number of rows in `table` from the statistics gathered by `ANALYZE`,
+    /// without scanning the table. Returns `None` if no statistics exist for it.
+    pub fn estimated_row_count(&self, table: &str) -> Result<Option<i64>> {
+        let has_statistics = self.select_row::<bool>(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_stat1')",
+        )?()?
+        .unwrap_or(false);
+        if !has_statistics {
+            return Ok(None);
+        }
+
+        // The first integer of each entry is the approximate number of rows in the table
+        let stat = self.select_row_bound::<&str, String>(
+            "SELECT stat FROM sqlite_stat1 WHERE tbl = ? ORDER BY idx IS NOT NULL LIMIT 1",
+        )?(table)?;
+        Ok(stat.and_then(|stat| stat.split_whitespace().next()?.parse().ok()))
+    }
+
+    /// Runs an insert, returning the rowid of the inserted row, or `None` if it violated
+    /// a constraint such as `UNIQUE`, so that callers can treat the row as already existing.
+    pub fn insert_or_ignore(&self, query: &str) -> Result<Option<i64>> {
+        let mut statement = Statement::prepare(self, query)?;
+        match statement.exec() {
+            Ok(_) => Ok(Some(unsafe { sqlite3_last_insert_rowid(self.sqlite3) })),
+            Err(error)
+                if error
+                    .downcast_ref::<Error>()
+                    .is_some_and(Error::is_constraint) =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns the number of rows inserted, updated or deleted by the most recently completed
+    /// statement on this connection. When `exec` runs several statements, this only reflects
+    /// the last one, so run the statement whose changes you need on its own, or use the count
+    /// returned by `Statement::exec`.
+    pub fn changes(&self) -> i64 {
+        unsafe { sqlite3_changes(self.sqlite3) as i64 }
+    }
+
+    /// Returns the number of rows inserted, updated or deleted since the connection was
+    /// opened, including changes made by triggers.
+    pub fn total_changes(&self) -> i64 {
+        unsafe { sqlite3_total_changes(self.sqlite3) as i64 }
+    }
+
+    /// Checkpoints the write-ahead log of every attached database. Being blocked by a
+    /// reader or writer isn't treated as an error, so that callers can retry instead.
+    pub fn wal_checkpoint(&self, mode: CheckpointMode) -> Result<CheckpointOutcome> {
+        let mode = match mode {
+            CheckpointMode::Passive => SQLITE_CHECKPOINT_PASSIVE,
+            CheckpointMode::Full => SQLITE_CHECKPOINT_FULL,
+            CheckpointMode::Restart => SQLITE_CHECKPOINT_RESTART,
+            CheckpointMode::Truncate => SQLITE_CHECKPOINT_TRUNCATE,
+        };
+        let mut log_frames = 0;
+        let mut checkpointed_frames = 0;
+        let code = unsafe {
+            sqlite3_wal_checkpoint_v2(
+                self.sqlite3,
+                ptr::null(),
+                mode,
+                &mut log_frames,
+                &mut checkpointed_frames,
+            )
+        };
+
+        let busy = match code & 0xff {
+            SQLITE_OK => false,
+            SQLITE_BUSY => true,
+            _ => {
+                self.last_error()?;
+                return Err(anyhow!("WAL checkpoint failed with code {}", code));
+            }
+        };
+        Ok(CheckpointOutcome {
+            busy,
+            log_frames,
+            checkpointed_frames,
+        })
+    }
+
+    /// Performs background upkeep for long lived connections, such as when the editor is
+    /// idle: a passive WAL checkpoint, followed by `PRAGMA optimize` if `budget` allows.
+    /// Neither step waits on other connections, so this never blocks for long.
+    pub fn maintenance(&self, budget: Duration) -> Result<MaintenanceReport> {
+        let start = Instant::now();
+
+        let checkpoint = self.wal_checkpoint(CheckpointMode::Passive)?;
+        // The frame counts are -1 when the database isn't using a write-ahead log
+        let checkpoint = (checkpoint.log_frames >= 0).then_some(checkpoint);
+
+        let optimized = start.elapsed() < budget;
+        if optimized {
+            self.optimize()?;
+        }
+
+        Ok(MaintenanceReport {
+            checkpoint,
+            optimized,
+        })
+    }
+
     pub fn sql_has_syntax_error(&self, sql: &str) -> Option<(String, usize)> {
         let sql = CString::new(sql).unwrap();
         let mut remaining_sql = sql.as_c_str();
@@ -113,6 +620,16 @@ impl Connection {
                     // create a new in-memory DB to test.
 
                     let temp_connection = Connection::open_memory(None);
+                    // Tables in attached schemas need that schema to exist here too
+                    if let Some((schema, _)) = table_to_alter.split_once('.') {
+                        if !is_builtin_schema(schema) {
+                            temp_connection
+                                .exec(&format!("ATTACH DATABASE ':memory:' AS {schema}"))
+                                .and_then(|mut attach| attach())
+                                .ok();
+                        }
+                    }
+
                     //This should always succeed, if it doesn't then you really should know about it
                     temp_connection
                         .exec(&format!(
@@ -169,30 +686,33 @@ impl Connection {
         None
     }
 
+    pub(crate) fn last_error_code(&self) -> i32 {
+        unsafe { sqlite3_errcode(self.sqlite3) }
+    }
+
     pub(crate) fn last_error(&self) -> Result<()> {
-        unsafe {
-            let code = sqlite3_errcode(self.sqlite3);
-            const NON_ERROR_CODES: &[i32] = &[SQLITE_OK, SQLITE_ROW];
-            if NON_ERROR_CODES.contains(&code) {
-                return Ok(());
-            }
+        let code = self.last_error_code();
+        const NON_ERROR_CODES: &[i32] = &[SQLITE_OK, SQLITE_ROW];
+        if NON_ERROR_CODES.contains(&code) {
+            return Ok(());
+        }
+        Err(self.error_for_code(code).into())
+    }
 
+    /// Builds the error for `code`, with the message sqlite recorded for the last failed call.
+    pub(crate) fn error_for_code(&self, code: i32) -> Error {
+        let message = unsafe {
             let message = sqlite3_errmsg(self.sqlite3);
-            let message = if message.is_null() {
+            if message.is_null() {
                 None
             } else {
                 Some(
                     String::from_utf8_lossy(CStr::from_ptr(message as *const _).to_bytes())
                         .into_owned(),
                 )
-            };
-
-            Err(anyhow!(
-                "Sqlite call failed with code {} and message: {:?}",
-                code as isize,
-                message
-            ))
-        }
+            }
+        };
+        Error::from_code(code, message)
     }
 
     pub(crate) fn with_write<T>(&self, callback: impl FnOnce(&Connection) -> T) -> T {
@@ -222,18 +742,49 @@ fn parse_alter_table(remaining_sql_str: &str) -> Option<String> {
     None
 }
 
+fn is_builtin_schema(schema: &str) -> bool {
+    schema.eq_ignore_ascii_case("main") || schema.eq_ignore_ascii_case("temp")
+}
+
+pub(crate) fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut uri = String::with_capacity(path.len() + 5);
+    uri.push_str("file:");
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
 impl Drop for Connection {
     fn drop(&mut self) {
+        // Cached statements must be finalized for the connection to close
+        self.statement_cache.get_mut().clear();
+        self.interrupt_handle.close();
         unsafe { sqlite3_close(self.sqlite3) };
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::{
+        path::Path,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
     use anyhow::Result;
     use indoc::indoc;
 
-    use crate::connection::Connection;
+    use crate::{
+        connection::{path_to_uri, CheckpointMode, Connection},
+        error::Error,
+    };
 
     #[test]
     fn string_round_trips() -> Result<()> {
@@ -261,6 +812,419 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_open_path() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez open path #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db?with spaces.db");
+
+        {
+            let connection = Connection::open_path(&path)?;
+            connection.exec("CREATE TABLE test (value TEXT)")?()?;
+            connection.exec_bound("INSERT INTO test (value) VALUES (?)")?("round trip")?;
+        }
+        assert!(path.exists());
+
+        let connection = Connection::open_path(&path)?;
+        assert_eq!(
+            connection.select_row::<String>("SELECT value FROM test")?()?,
+            Some("round trip".to_string())
+        );
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_memory_with_cache() -> Result<()> {
+        let connection = Connection::open_memory_with_cache("test_open_memory_with_cache", 16)?;
+        assert_eq!(connection.pragma_get::<i32>("cache_size")?, Some(16));
+
+        // Each connection gets its own db, unlike with `open_memory`
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        let other = Connection::open_memory_with_cache("test_open_memory_with_cache", 32)?;
+        assert_eq!(other.pragma_get::<i32>("cache_size")?, Some(32));
+        assert!(other.exec("SELECT * FROM test").is_err());
+
+        // Negative sizes are kibibytes, and are passed through as they are
+        let kibibytes = Connection::open_memory_with_cache("test_open_memory_with_cache", -64)?;
+        assert_eq!(kibibytes.pragma_get::<i32>("cache_size")?, Some(-64));
+
+        assert!(connection
+            .pragma_get::<i32>("cache_size; DROP TABLE test")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_or_ignore() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_insert_or_ignore"));
+        connection.exec("CREATE TABLE test (id INTEGER PRIMARY KEY, value TEXT NOT NULL)")?()?;
+
+        assert_eq!(
+            connection.insert_or_ignore("INSERT INTO test (id, value) VALUES (7, 'first')")?,
+            Some(7)
+        );
+        assert_eq!(
+            connection.insert_or_ignore("INSERT INTO test (id, value) VALUES (7, 'second')")?,
+            None
+        );
+        assert_eq!(
+            connection.select_row::<String>("SELECT value FROM test WHERE id = 7")?()?,
+            Some("first".to_string())
+        );
+
+        // Errors other than constraint violations are still reported
+        assert!(connection
+            .insert_or_ignore("INSERT INTO missing (id) VALUES (1)")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_changes() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_changes"));
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        connection.exec("INSERT INTO test (value) VALUES (1), (2), (3)")?()?;
+        assert_eq!(connection.changes(), 3);
+
+        connection.exec("DELETE FROM test WHERE value < 3")?()?;
+        assert_eq!(connection.changes(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_logger() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_statement_logger"));
+        connection.exec("CREATE TABLE test (id INTEGER PRIMARY KEY)")?()?;
+        connection.exec("INSERT INTO test (id) VALUES (1)")?()?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        connection.set_statement_logger(Some(Box::new({
+            let events = events.clone();
+            move |sql| events.lock().unwrap().push(format!("run: {}", sql))
+        })));
+
+        let result = connection.exec("INSERT INTO test (id) VALUES (1)")?();
+        if result.is_err() {
+            events.lock().unwrap().push("failed".to_string());
+        }
+        assert_eq!(
+            events.lock().unwrap().as_slice(),
+            &["run: INSERT INTO test (id) VALUES (1)", "failed"]
+        );
+
+        connection.set_statement_logger(None);
+        connection.exec("DELETE FROM test")?()?;
+        assert_eq!(events.lock().unwrap().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_connection_builder() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez connection builder #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db").to_string_lossy().to_string();
+
+        assert!(Connection::builder(&path)
+            .with_create(false)
+            .build()
+            .is_err());
+        let connection = Connection::builder(&path).with_full_mutex(true).build()?;
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        connection.exec("INSERT INTO test (value) VALUES (1)")?()?;
+
+        assert!(Connection::builder(&path)
+            .with_read_only(true)
+            .build()
+            .is_err());
+        let read_only = Connection::builder(&path)
+            .with_read_only(true)
+            .with_create(false)
+            .build()?;
+        assert!(!read_only.can_write());
+        assert_eq!(
+            read_only.select::<i64>("SELECT value FROM test")?()?,
+            vec![1]
+        );
+        assert!(read_only
+            .exec("INSERT INTO test (value) VALUES (2)")
+            .is_err());
+
+        let in_memory = Connection::builder("test_connection_builder")
+            .with_in_memory(true)
+            .build()?;
+        assert!(!in_memory.persistent());
+        assert!(Connection::builder(&path)
+            .with_vfs("no such vfs")
+            .build()
+            .is_err());
+
+        drop((connection, read_only));
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_path_wal() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez open path wal #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let connection = Connection::open_path_wal(&path)?;
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        connection.exec("INSERT INTO test (value) VALUES (1)")?()?;
+        assert_eq!(
+            Connection::open_path(&path)?.select_row::<String>("PRAGMA journal_mode")?()?,
+            Some("wal".to_string())
+        );
+
+        let outcome = connection.wal_checkpoint(CheckpointMode::Truncate)?;
+        assert!(!outcome.busy);
+        assert_eq!(outcome.checkpointed_frames, outcome.log_frames);
+        assert_eq!(
+            connection.select_row::<i32>("SELECT COUNT(*) FROM test")?()?,
+            Some(1)
+        );
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_busy_timeout_and_handler() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez busy handler #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let connection = Connection::open_path(&path)?;
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        let blocker = Connection::open_path(&path)?;
+        blocker.exec("BEGIN IMMEDIATE")?()?;
+
+        let attempts = Arc::new(Mutex::new(Vec::new()));
+        connection.set_busy_handler({
+            let attempts = attempts.clone();
+            move |count| {
+                attempts.lock().unwrap().push(count);
+                count < 3
+            }
+        });
+        let error = connection.exec("INSERT INTO test (value) VALUES (1)")?().unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Busy));
+        assert_eq!(*attempts.lock().unwrap(), vec![0, 1, 2, 3]);
+
+        // The timeout waits for the other connection to release its lock
+        connection.set_busy_timeout(Duration::from_secs(5));
+        let blocker = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            blocker.exec("COMMIT").and_then(|mut commit| commit())
+        });
+        connection.exec("INSERT INTO test (value) VALUES (1)")?()?;
+        blocker.join().unwrap()?;
+        assert_eq!(attempts.lock().unwrap().len(), 4);
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_checkpoint_reports_busy() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez wal checkpoint #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let writer = Connection::open_path(&path)?;
+        writer.exec("PRAGMA journal_mode=WAL")?()?;
+        writer.exec("CREATE TABLE test (value INTEGER)")?()?;
+        writer.exec("INSERT INTO test (value) VALUES (1)")?()?;
+
+        // Hold a read transaction open on the current snapshot, so that frames
+        // written after it can't all be copied back into the database
+        let reader = Connection::open_path(&path)?;
+        reader.exec("BEGIN")?()?;
+        assert_eq!(
+            reader.select_row::<i32>("SELECT COUNT(*) FROM test")?()?,
+            Some(1)
+        );
+        writer.exec("INSERT INTO test (value) VALUES (2)")?()?;
+
+        let outcome = writer.wal_checkpoint(CheckpointMode::Full)?;
+        assert!(outcome.busy);
+        assert!(outcome.log_frames > 0);
+        assert!(outcome.checkpointed_frames < outcome.log_frames);
+
+        reader.exec("COMMIT")?()?;
+        let outcome = writer.wal_checkpoint(CheckpointMode::Full)?;
+        assert!(!outcome.busy);
+        assert_eq!(outcome.checkpointed_frames, outcome.log_frames);
+
+        drop((reader, writer));
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_maintenance() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez maintenance #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let connection = Connection::open_path(&path)?;
+        connection.exec("PRAGMA journal_mode=WAL")?()?;
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+        for value in 0..10 {
+            connection.exec_bound::<i32>("INSERT INTO test (value) VALUES (?)")?(value)?;
+        }
+
+        let report = connection.maintenance(Duration::from_secs(60))?;
+        let checkpoint = report.checkpoint.unwrap();
+        assert!(!checkpoint.busy);
+        assert!(checkpoint.checkpointed_frames > 0);
+        assert_eq!(checkpoint.checkpointed_frames, checkpoint.log_frames);
+        assert!(report.optimized);
+
+        // Without any budget left after checkpointing, optimizing is skipped
+        let report = connection.maintenance(Duration::ZERO)?;
+        assert!(report.checkpoint.is_some());
+        assert!(!report.optimized);
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_attach_readonly() -> Result<()> {
+        // Characters with a meaning in URIs are escaped from paths
+        let path =
+            std::env::temp_dir().join(format!("sqlez attach readonly #%{}.db", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        {
+            let external = Connection::open_file(&path, None);
+            external.exec("CREATE TABLE test (value INTEGER)")?()?;
+            external.exec("INSERT INTO test (value) VALUES (1)")?()?;
+        }
+
+        let connection = Connection::open_memory(Some("test_attach_readonly"));
+        connection.attach_readonly(&path, "external")?;
+        assert_eq!(
+            connection.select::<i64>("SELECT value FROM external.test")?()?,
+            vec![1]
+        );
+        assert!(connection
+            .exec("INSERT INTO external.test (value) VALUES (2)")
+            .and_then(|mut insert| insert())
+            .is_err());
+
+        connection.detach("external")?;
+        assert!(connection
+            .select::<i64>("SELECT value FROM external.test")
+            .is_err());
+
+        // A mode given in the URI is replaced rather than conflicting with read-only mode
+        let uri = format!("{}?cache=private&mode=rwc", path_to_uri(Path::new(&path)));
+        connection.attach_readonly(&uri, "external")?;
+        assert_eq!(
+            connection.select::<i64>("SELECT value FROM external.test")?()?,
+            vec![1]
+        );
+        assert!(connection
+            .exec("INSERT INTO external.test (value) VALUES (2)")
+            .and_then(|mut insert| insert())
+            .is_err());
+        connection.detach("external")?;
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_attach() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("sqlez attach #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+
+        let connection = Connection::open_path(directory.join("stable.db"))?;
+        connection.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")?()?;
+        connection.exec("INSERT INTO items VALUES (1, 'one'), (2, 'two')")?()?;
+
+        connection.attach(directory.join("preview.db"), "preview")?;
+        assert_eq!(connection.attached_schemas()?, vec!["preview".to_string()]);
+        connection.exec("CREATE TABLE preview.items (id INTEGER PRIMARY KEY, name TEXT)")?()?;
+        connection.exec("INSERT INTO preview.items SELECT * FROM main.items")?()?;
+        assert_eq!(
+            connection.sql_has_syntax_error("ALTER TABLE preview.items ADD COLUMN size INTEGER"),
+            None
+        );
+
+        assert!(connection
+            .attach(directory.join("other.db"), "PREVIEW")
+            .is_err());
+        assert!(connection
+            .attach(directory.join("other.db"), "main")
+            .is_err());
+        assert!(connection
+            .attach(directory.join("other.db"), "bad name")
+            .is_err());
+        assert!(connection
+            .attach(directory.join("missing").join("other.db"), "other")
+            .is_err());
+
+        // Cached statements against the schema don't prevent detaching it
+        connection
+            .prepare_cached("SELECT name FROM preview.items")?
+            .rows::<String>()?;
+        connection.detach("preview")?;
+        assert!(connection.detach("preview").is_err());
+        assert!(connection.attached_schemas()?.is_empty());
+
+        let preview = Connection::open_path(directory.join("preview.db"))?;
+        assert_eq!(
+            preview.select::<String>("SELECT name FROM items ORDER BY id")?()?,
+            vec!["one".to_string(), "two".to_string()]
+        );
+
+        drop((connection, preview));
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimated_row_count() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_estimated_row_count"));
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                id INTEGER PRIMARY KEY,
+                value TEXT
+            );"})?()?;
+        connection.exec("CREATE INDEX test_value ON test(value)")?()?;
+
+        // Nothing has been analyzed yet
+        assert_eq!(connection.estimated_row_count("test")?, None);
+
+        let mut insert =
+            connection.exec_bound::<(i64, String)>("INSERT INTO test VALUES (?, ?)")?;
+        for id in 0..500 {
+            insert((id, format!("value {}", id % 10)))?;
+        }
+        connection.exec("ANALYZE")?()?;
+
+        let estimate = connection.estimated_row_count("test")?.unwrap();
+        assert!((450..=550).contains(&estimate), "estimate was {estimate}");
+        assert_eq!(connection.estimated_row_count("missing")?, None);
+
+        Ok(())
+    }
+
     #[test]
     fn tuple_round_trips() {
         let connection = Connection::open_memory(Some("tuple_round_trips"));
@@ -0,0 +1,214 @@
+use std::{
+    sync::{Arc, Weak},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures::Future;
+use parking_lot::Mutex;
+
+use crate::{
+    connection::Connection, domain::Migrator, thread_safe_connection::ThreadSafeConnection,
+};
+
+type PendingWrite = Box<dyn 'static + Send + FnOnce(&Connection) -> Result<()>>;
+
+/// Coalesces frequent writes to the same row, such as persisting a scroll position on every
+/// change. Each write is keyed by the table and key it writes, and only the latest write for
+/// each key is kept until the pending writes are flushed, which happens on an interval and on
+/// `flush`. Batches are written in the order they are flushed, so the last write for a key
+/// always wins. Pending writes are flushed when the writer is dropped.
+pub struct DebouncedWriter<M: Migrator + 'static = ()> {
+    state: Arc<State<M>>,
+}
+
+struct State<M: Migrator + 'static> {
+    connection: ThreadSafeConnection<M>,
+    // Kept in the order of each key's latest write
+    pending: Mutex<Vec<((&'static str, String), PendingWrite)>>,
+}
+
+impl<M: Migrator> DebouncedWriter<M> {
+    /// Creates a writer which flushes its pending writes to `connection` every `interval`,
+    /// on a background thread which exits at the first interval after the writer is dropped.
+    pub fn new(connection: ThreadSafeConnection<M>, interval: Duration) -> Self {
+        let state = Arc::new(State {
+            connection,
+            pending: Default::default(),
+        });
+
+        let weak_state = Arc::downgrade(&state);
+        thread::spawn(move || flush_periodically(weak_state, interval));
+        Self { state }
+    }
+
+    /// Replaces any pending write for `key` in `table` with `callback`, which runs with the
+    /// write capable connection when the writer is next flushed.
+    pub fn write(
+        &self,
+        table: &'static str,
+        key: impl Into<String>,
+        callback: impl 'static + Send + FnOnce(&Connection) -> Result<()>,
+    ) {
+        let key = (table, key.into());
+        let mut pending = self.state.pending.lock();
+        pending.retain(|(pending_key, _)| *pending_key != key);
+        pending.push((key, Box::new(callback)));
+    }
+
+    /// Queues the pending writes, returning a future which resolves once they and any
+    /// batches flushed before them have been written. Resolves to the first error from the
+    /// batch, though the other writes in it are still made. A write which fails is rolled
+    /// back, so that none of its changes are kept.
+    pub fn flush(&self) -> impl Future<Output = Result<()>> {
+        self.state.flush()
+    }
+}
+
+impl<M: Migrator> State<M> {
+    fn flush(&self) -> impl Future<Output = Result<()>> {
+        // The lock is held while the batch is queued, so batches are queued in the order
+        // they were taken
+        let mut pending = self.pending.lock();
+        let batch = std::mem::take(&mut *pending);
+        self.connection.write(move |connection| {
+            let mut first_error = None;
+            connection.with_savepoint("debounced_writer", || {
+                for ((table, key), write) in batch {
+                    let result = connection.with_savepoint("debounced_write", || write(connection));
+                    if let Err(error) = result {
+                        log::error!("Debounced write to {table} for {key:?} failed: {error:?}");
+                        first_error.get_or_insert(error);
+                    }
+                }
+                Ok(())
+            })?;
+            first_error.map_or(Ok(()), Err)
+        })
+    }
+}
+
+impl<M: Migrator> Drop for State<M> {
+    fn drop(&mut self) {
+        if !self.pending.get_mut().is_empty() {
+            drop(self.flush());
+        }
+    }
+}
+
+fn flush_periodically<M: Migrator>(state: Weak<State<M>>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let Some(state) = state.upgrade() else {
+            break;
+        };
+        if !state.pending.lock().is_empty() {
+            drop(state.flush());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use anyhow::{anyhow, Result};
+
+    use crate::{
+        connection::Connection, debounced_writer::DebouncedWriter, domain::Domain,
+        thread_safe_connection::ThreadSafeConnection,
+    };
+
+    enum TestDomain {}
+    impl Domain for TestDomain {
+        fn name() -> &'static str {
+            "test"
+        }
+        fn migrations() -> &'static [&'static str] {
+            &["CREATE TABLE positions(key TEXT PRIMARY KEY, value INTEGER) STRICT;"]
+        }
+    }
+
+    fn set_position(key: &'static str, value: i64) -> impl FnOnce(&Connection) -> Result<()> {
+        move |connection| {
+            connection.exec_bound::<(&str, i64)>(
+                "INSERT OR REPLACE INTO positions(key, value) VALUES (?, ?)",
+            )?((key, value))
+        }
+    }
+
+    fn positions(connection: &Connection) -> Result<Vec<(String, i64)>> {
+        connection.select::<(String, i64)>("SELECT key, value FROM positions ORDER BY key")?()
+    }
+
+    fn open(name: &str) -> ThreadSafeConnection<TestDomain> {
+        smol::block_on(ThreadSafeConnection::<TestDomain>::builder(name, false).build()).unwrap()
+    }
+
+    #[test]
+    fn test_writes_are_coalesced() -> Result<()> {
+        let connection = open("test_writes_are_coalesced");
+        let writer = DebouncedWriter::new(connection.clone(), Duration::from_secs(60 * 60));
+
+        let written = Arc::new(AtomicUsize::new(0));
+        for value in 0..10 {
+            let written = written.clone();
+            writer.write("positions", "first", move |connection| {
+                written.fetch_add(1, Ordering::SeqCst);
+                set_position("first", value)(connection)
+            });
+        }
+        writer.write("positions", "second", set_position("second", 1));
+        assert!(positions(&connection)?.is_empty());
+
+        smol::block_on(writer.flush())?;
+        assert_eq!(written.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            positions(&connection)?,
+            [("first".to_string(), 9), ("second".to_string(), 1)]
+        );
+
+        // A failing write is reported and rolled back without losing the rest of the batch
+        writer.write("positions", "first", |connection| {
+            set_position("first", 10)(connection)?;
+            Err(anyhow!("failed"))
+        });
+        writer.write("positions", "second", set_position("second", 2));
+        assert!(smol::block_on(writer.flush()).is_err());
+        assert_eq!(
+            positions(&connection)?,
+            [("first".to_string(), 9), ("second".to_string(), 2)]
+        );
+
+        // Dropping the writer flushes what is still pending
+        writer.write("positions", "second", set_position("second", 3));
+        drop(writer);
+        smol::block_on(connection.flush());
+        assert_eq!(positions(&connection)?[1], ("second".to_string(), 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_writes_are_flushed_on_an_interval() -> Result<()> {
+        let connection = open("test_writes_are_flushed_on_an_interval");
+        let writer = DebouncedWriter::new(connection.clone(), Duration::from_millis(10));
+
+        writer.write("positions", "first", set_position("first", 1));
+        for _ in 0..500 {
+            if !positions(&connection)?.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(positions(&connection)?, [("first".to_string(), 1)]);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+
+/// Supplies the key for an encrypted database each time a connection to it is opened, such
+/// as by reading it from the system keychain, so that the key doesn't have to be kept in
+/// memory between opens.
+pub type KeyProvider = Arc<dyn Fn() -> Result<Vec<u8>> + Send + Sync>;
+
+#[cfg(feature = "sqlcipher")]
+mod sqlcipher {
+    use std::ffi::{c_int, c_void};
+
+    use anyhow::{Context, Result};
+    use libsqlite3_sys::*;
+
+    use crate::connection::Connection;
+
+    // Declared here rather than taken from the bindings, which only include the codec API
+    // for some versions of sqlite
+    extern "C" {
+        fn sqlite3_key(db: *mut sqlite3, key: *const c_void, len: c_int) -> c_int;
+        fn sqlite3_rekey(db: *mut sqlite3, key: *const c_void, len: c_int) -> c_int;
+    }
+
+    impl Connection {
+        /// Sets the key used to encrypt the database, which must be called before anything
+        /// else reads or writes it. New databases are encrypted with the key. Fails if the
+        /// key doesn't match the one the database was encrypted with.
+        pub fn set_key(&self, key: &[u8]) -> Result<()> {
+            let code =
+                unsafe { sqlite3_key(self.sqlite3, key.as_ptr() as *const c_void, key.len() as _) };
+            if code != SQLITE_OK {
+                return Err(self.error_for_code(code)).context("Failed to set database key");
+            }
+            // sqlite only decrypts the database once it reads a page, which fails with the
+            // wrong key
+            self.select_row::<i64>("SELECT count(*) FROM sqlite_schema")?()
+                .context("Failed to decrypt database")?;
+            Ok(())
+        }
+
+        /// Re-encrypts the database with `key`, which is then needed to open it. An empty
+        /// key decrypts the database.
+        pub fn rekey(&self, key: &[u8]) -> Result<()> {
+            let code = unsafe {
+                sqlite3_rekey(self.sqlite3, key.as_ptr() as *const c_void, key.len() as _)
+            };
+            if code != SQLITE_OK {
+                return Err(self.error_for_code(code)).context("Failed to change database key");
+            }
+            Ok(())
+        }
+    }
+}
+
+impl Connection {
+    /// Opens the encrypted database at `uri` with the key from `key_provider`. Fails when
+    /// sqlez was built without the `sqlcipher` feature, rather than storing data unencrypted.
+    pub fn open_encrypted(uri: &str, key_provider: &KeyProvider) -> Result<Self> {
+        Self::builder(uri)
+            .with_key_provider(key_provider.clone())
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+
+    use crate::{connection::Connection, encryption::KeyProvider};
+
+    #[test]
+    #[cfg(feature = "sqlcipher")]
+    fn test_encrypted_database() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez encryption #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+        let uri = path.to_str().unwrap();
+        let key: KeyProvider = Arc::new(|| Ok(b"first key".to_vec()));
+
+        {
+            let connection = Connection::open_encrypted(uri, &key)?;
+            connection.exec("CREATE TABLE test (secret TEXT)")?()?;
+            connection.exec("INSERT INTO test VALUES ('hidden')")?()?;
+        }
+        assert!(!std::fs::read(&path)?
+            .windows(b"hidden".len())
+            .any(|window| window == b"hidden"));
+
+        assert!(Connection::open_path(&path)?.quick_check().is_err());
+        let wrong_key: KeyProvider = Arc::new(|| Ok(b"wrong key".to_vec()));
+        assert!(Connection::open_encrypted(uri, &wrong_key).is_err());
+
+        Connection::open_encrypted(uri, &key)?.rekey(b"second key")?;
+        assert!(Connection::open_encrypted(uri, &key).is_err());
+        let second_key: KeyProvider = Arc::new(|| Ok(b"second key".to_vec()));
+        let connection = Connection::open_encrypted(uri, &second_key)?;
+        assert_eq!(
+            connection.select_row::<String>("SELECT secret FROM test")?()?,
+            Some("hidden".to_string())
+        );
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "sqlcipher"))]
+    fn test_encryption_needs_the_sqlcipher_feature() -> Result<()> {
+        let key: KeyProvider = Arc::new(|| Ok(b"key".to_vec()));
+        assert!(Connection::open_encrypted("sqlez-unencrypted.db", &key).is_err());
+
+        // Data that was meant to be encrypted is never written to disk unencrypted
+        let connection = Connection::open_file("sqlez-unencrypted.db", Some(&key));
+        assert!(!connection.persistent());
+        assert!(!std::path::Path::new("sqlez-unencrypted.db").exists());
+        Ok(())
+    }
+}
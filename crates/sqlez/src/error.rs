@@ -0,0 +1,161 @@
+use std::fmt;
+
+use libsqlite3_sys::*;
+
+/// The errors returned by sqlite, sorted by the result codes that callers commonly need to
+/// handle. Functions in this crate return `anyhow::Error`s, which these can be recovered from
+/// with `downcast_ref::<Error>()`, even when context has been added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Another connection holds a conflicting lock on the database.
+    Busy,
+    /// A conflicting lock is held within the same shared cache or connection.
+    Locked,
+    /// The statement was stopped by `InterruptHandle::interrupt`.
+    Interrupted,
+    /// The database can't be written to.
+    ReadOnly,
+    /// The database file is malformed, or isn't a database.
+    Corrupt,
+    /// The disk the database is on is full.
+    Full,
+    /// The library was used incorrectly, such as stepping a finalized statement.
+    Misuse,
+    ConstraintUnique(String),
+    ConstraintPrimaryKey(String),
+    ConstraintForeignKey(String),
+    ConstraintNotNull(String),
+    /// Any other constraint, such as a `CHECK`.
+    Constraint(String),
+    Sqlite {
+        code: i32,
+        message: Option<String>,
+    },
+}
+
+impl Error {
+    /// Builds the error for an extended result code and its message.
+    pub fn from_code(code: i32, message: Option<String>) -> Self {
+        let message_text = || message.clone().unwrap_or_default();
+        match code & 0xff {
+            SQLITE_BUSY => Error::Busy,
+            SQLITE_LOCKED => Error::Locked,
+            SQLITE_INTERRUPT => Error::Interrupted,
+            SQLITE_READONLY => Error::ReadOnly,
+            SQLITE_CORRUPT | SQLITE_NOTADB => Error::Corrupt,
+            SQLITE_FULL => Error::Full,
+            SQLITE_MISUSE => Error::Misuse,
+            SQLITE_CONSTRAINT => match code {
+                SQLITE_CONSTRAINT_UNIQUE => Error::ConstraintUnique(message_text()),
+                SQLITE_CONSTRAINT_PRIMARYKEY => Error::ConstraintPrimaryKey(message_text()),
+                SQLITE_CONSTRAINT_FOREIGNKEY => Error::ConstraintForeignKey(message_text()),
+                SQLITE_CONSTRAINT_NOTNULL => Error::ConstraintNotNull(message_text()),
+                _ => Error::Constraint(message_text()),
+            },
+            _ => Error::Sqlite { code, message },
+        }
+    }
+
+    pub fn is_constraint(&self) -> bool {
+        matches!(
+            self,
+            Error::ConstraintUnique(_)
+                | Error::ConstraintPrimaryKey(_)
+                | Error::ConstraintForeignKey(_)
+                | Error::ConstraintNotNull(_)
+                | Error::Constraint(_)
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Busy => write!(f, "Sqlite database is locked by another connection"),
+            Error::Locked => write!(f, "Sqlite database table is locked"),
+            Error::Interrupted => write!(f, "Sqlite statement was interrupted"),
+            Error::ReadOnly => write!(f, "Sqlite database is read only"),
+            Error::Corrupt => write!(f, "Sqlite database is corrupt"),
+            Error::Full => write!(f, "Sqlite database is full"),
+            Error::Misuse => write!(f, "Sqlite library was misused"),
+            Error::ConstraintUnique(message)
+            | Error::ConstraintPrimaryKey(message)
+            | Error::ConstraintForeignKey(message)
+            | Error::ConstraintNotNull(message)
+            | Error::Constraint(message) => write!(f, "Sqlite constraint failed: {}", message),
+            Error::Sqlite { code, message } => write!(
+                f,
+                "Sqlite call failed with code {} and message: {:?}",
+                code, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Context added to the error from `Connection::eager_exec`, identifying which of the
+/// statements it was given failed. Recovered with `downcast_ref::<FailedStatement>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedStatement {
+    /// The zero based position of the statement among the statements which were run.
+    pub index: usize,
+    pub sql: String,
+}
+
+impl fmt::Display for FailedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Statement {} failed:\n{}", self.index, self.sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use indoc::indoc;
+
+    use crate::{connection::Connection, error::Error};
+
+    fn sqlite_error(result: Result<()>) -> Error {
+        result
+            .unwrap_err()
+            .downcast_ref::<Error>()
+            .expect("expected a sqlite error")
+            .clone()
+    }
+
+    #[test]
+    fn test_constraint_errors() -> Result<()> {
+        let connection = Connection::open_memory(Some("constraint_errors"));
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                id INTEGER PRIMARY KEY,
+                name TEXT UNIQUE,
+                value INTEGER NOT NULL
+            )"})?()?;
+        connection.exec("INSERT INTO test (id, name, value) VALUES (1, 'one', 1)")?()?;
+
+        assert!(matches!(
+            sqlite_error(connection.exec("INSERT INTO test (id, name, value) VALUES (2, 'one', 2)")?()),
+            Error::ConstraintUnique(message) if message.contains("test.name")
+        ));
+        assert!(matches!(
+            sqlite_error(connection
+                .exec("INSERT INTO test (id, name, value) VALUES (1, 'two', 2)")?(
+            )),
+            Error::ConstraintPrimaryKey(_)
+        ));
+        assert!(matches!(
+            sqlite_error(connection
+                .exec("INSERT INTO test (id, name) VALUES (3, 'three')")?(
+            )),
+            Error::ConstraintNotNull(_)
+        ));
+        assert!(matches!(
+            sqlite_error(connection.exec("SELECT * FROM missing").map(|_| ())),
+            Error::Sqlite { .. }
+        ));
+
+        Ok(())
+    }
+}
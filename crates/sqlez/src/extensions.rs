@@ -0,0 +1,104 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    os::unix::prelude::OsStrExt,
+    path::Path,
+    ptr,
+};
+
+use anyhow::{anyhow, Context, Result};
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+impl Connection {
+    /// Allows or disallows loading extensions with `load_extension`, which connections
+    /// disallow when they are opened. The SQL `load_extension()` function stays disabled either way,
+    /// so SQL run on the connection can't load extensions itself.
+    pub fn enable_load_extension(&self, enabled: bool) -> Result<()> {
+        let code = unsafe {
+            sqlite3_db_config(
+                self.sqlite3,
+                SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION,
+                c_int::from(enabled),
+                ptr::null_mut::<c_int>(),
+            )
+        };
+        if code != SQLITE_OK {
+            return Err(self.error_for_code(code)).context("Failed to configure extension loading");
+        }
+        Ok(())
+    }
+
+    /// Loads the sqlite extension in the shared library at `path`, calling `entry_point`, or
+    /// the entry point sqlite derives from the file name when this is `None`. Extensions
+    /// stay loaded until the connection is closed. Fails unless loading was allowed with
+    /// `enable_load_extension`.
+    ///
+    /// # Safety
+    ///
+    /// The library's initializers and entry point run in this process with no sandboxing,
+    /// so it must be a trusted sqlite extension built for this platform. Anything it
+    /// registers, such as functions and virtual tables, must not outlive the library, which
+    /// is unloaded when the connection is closed.
+    pub unsafe fn load_extension(&self, path: &Path, entry_point: Option<&str>) -> Result<()> {
+        let file = CString::new(path.as_os_str().as_bytes())?;
+        let entry_point = entry_point.map(CString::new).transpose()?;
+
+        let mut message: *mut c_char = ptr::null_mut();
+        let code = sqlite3_load_extension(
+            self.sqlite3,
+            file.as_ptr(),
+            entry_point
+                .as_ref()
+                .map_or(ptr::null(), |entry| entry.as_ptr()),
+            &mut message,
+        );
+        if code == SQLITE_OK {
+            return Ok(());
+        }
+
+        let error = if message.is_null() {
+            anyhow!(self.error_for_code(code))
+        } else {
+            let text = CStr::from_ptr(message).to_string_lossy().into_owned();
+            sqlite3_free(message as *mut c_void);
+            anyhow!(text)
+        };
+        Err(error).with_context(|| format!("Failed to load extension {path:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_load_extension_is_disabled_by_default() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_load_extension_is_disabled"));
+        let missing = Path::new("/nonexistent/libsqlez_test_extension");
+
+        let error = unsafe { connection.load_extension(missing, None) }.unwrap_err();
+        assert!(format!("{error:#}").contains("not authorized"));
+
+        // Once enabled, loading gets as far as looking for the library
+        connection.enable_load_extension(true)?;
+        let error =
+            unsafe { connection.load_extension(missing, Some("sqlite3_test_init")) }.unwrap_err();
+        assert!(!format!("{error:#}").contains("not authorized"));
+
+        // SQL can't load extensions, even while the API is enabled
+        assert!(connection
+            .select_row::<i64>("SELECT load_extension('/nonexistent/libsqlez_test_extension')")
+            .and_then(|mut select| select())
+            .is_err());
+
+        connection.enable_load_extension(false)?;
+        let error = unsafe { connection.load_extension(missing, None) }.unwrap_err();
+        assert!(format!("{error:#}").contains("not authorized"));
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{bail, Result};
+use libsqlite3_sys::sqlite3_last_insert_rowid;
+
+use crate::{connection::Connection, statement::Statement};
+
+/// Describes an FTS5 full-text search table, whose rows are documents with one or more
+/// text columns. Only indexed columns are searched, but all columns can be read back.
+#[derive(Clone, Debug)]
+pub struct FtsTable {
+    name: String,
+    columns: Vec<FtsColumn>,
+    tokenizer: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct FtsColumn {
+    name: String,
+    indexed: bool,
+}
+
+/// A document matching a search, from `FtsTable::search`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FtsMatch {
+    pub rowid: i64,
+    /// The bm25 score of the match. Lower is more relevant.
+    pub rank: f64,
+}
+
+/// The text which `FtsTable::highlight` and `FtsTable::snippet` insert around matching
+/// terms, and that `snippet` uses to mark where text was cut.
+#[derive(Clone, Copy, Debug)]
+pub struct FtsMarkers<'a> {
+    pub open: &'a str,
+    pub close: &'a str,
+    pub ellipsis: &'a str,
+}
+
+impl FtsTable {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            columns: Vec::new(),
+            tokenizer: None,
+        }
+    }
+
+    /// Adds a column which is searched by `MATCH` queries.
+    pub fn with_column(mut self, name: impl Into<String>) -> Self {
+        self.columns.push(FtsColumn {
+            name: name.into(),
+            indexed: true,
+        });
+        self
+    }
+
+    /// Adds a column which is stored with each document but isn't searched.
+    pub fn with_unindexed_column(mut self, name: impl Into<String>) -> Self {
+        self.columns.push(FtsColumn {
+            name: name.into(),
+            indexed: false,
+        });
+        self
+    }
+
+    /// Sets the tokenizer, such as `porter unicode61` or `trigram`. Defaults to `unicode61`.
+    pub fn with_tokenizer(mut self, tokenizer: impl Into<String>) -> Self {
+        self.tokenizer = Some(tokenizer.into());
+        self
+    }
+
+    /// Creates the virtual table if it doesn't exist yet.
+    pub fn create(&self, connection: &Connection) -> Result<()> {
+        if self.columns.is_empty() {
+            bail!("FTS table {} has no columns", self.name);
+        }
+
+        let mut definitions = self
+            .columns
+            .iter()
+            .map(|column| {
+                let name = quote_identifier(&column.name);
+                if column.indexed {
+                    name
+                } else {
+                    format!("{} UNINDEXED", name)
+                }
+            })
+            .collect::<Vec<_>>();
+        if let Some(tokenizer) = &self.tokenizer {
+            definitions.push(format!("tokenize = {}", quote_string(tokenizer)));
+        }
+
+        connection.exec(&format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING fts5({})",
+            quote_identifier(&self.name),
+            definitions.join(", ")
+        ))?()
+    }
+
+    /// Adds a document with a value for each column, in the order they were added to the
+    /// table description. Returns the document's rowid.
+    pub fn insert(&self, connection: &Connection, values: &[&str]) -> Result<i64> {
+        if values.len() != self.columns.len() {
+            bail!(
+                "FTS table {} has {} columns, but {} values were given",
+                self.name,
+                self.columns.len(),
+                values.len()
+            );
+        }
+
+        let placeholders = vec!["?"; values.len()].join(", ");
+        let mut statement = Statement::prepare(
+            connection,
+            format!(
+                "INSERT INTO {} VALUES ({})",
+                quote_identifier(&self.name),
+                placeholders
+            ),
+        )?;
+        for (ix, value) in values.iter().enumerate() {
+            statement.bind_text(ix as i32 + 1, value)?;
+        }
+        statement.exec()?;
+        Ok(unsafe { sqlite3_last_insert_rowid(connection.sqlite3) })
+    }
+
+    /// Removes the document with `rowid`, if there is one.
+    pub fn delete(&self, connection: &Connection, rowid: i64) -> Result<()> {
+        connection.exec_bound::<i64>(&format!(
+            "DELETE FROM {} WHERE rowid = ?",
+            quote_identifier(&self.name)
+        ))?(rowid)
+    }
+
+    /// Returns the documents matching an FTS5 `query`, most relevant first.
+    pub fn search(
+        &self,
+        connection: &Connection,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<FtsMatch>> {
+        let table = quote_identifier(&self.name);
+        let rows = connection.select_bound::<(&str, i64), (i64, f64)>(&format!(
+            "SELECT rowid, rank FROM {table} WHERE {table} MATCH ? ORDER BY rank LIMIT ?"
+        ))?((query, limit as i64))?;
+        Ok(rows
+            .into_iter()
+            .map(|(rowid, rank)| FtsMatch { rowid, rank })
+            .collect())
+    }
+
+    /// Returns the text of `column` in the document with `rowid`, with the terms matching
+    /// `query` wrapped in the open and close markers. Returns `None` if the document doesn't
+    /// match.
+    pub fn highlight(
+        &self,
+        connection: &Connection,
+        query: &str,
+        rowid: i64,
+        column: usize,
+        markers: FtsMarkers,
+    ) -> Result<Option<String>> {
+        let table = quote_identifier(&self.name);
+        connection.select_row_bound::<(i64, &str, &str, &str, i64), String>(&format!(
+            "SELECT highlight({table}, ?, ?, ?) FROM {table} WHERE {table} MATCH ? AND rowid = ?"
+        ))?((column as i64, markers.open, markers.close, query, rowid))
+    }
+
+    /// Like `highlight`, but only returns the fragment of `column` which best matches the
+    /// query, of at most `max_tokens` tokens.
+    pub fn snippet(
+        &self,
+        connection: &Connection,
+        query: &str,
+        rowid: i64,
+        column: usize,
+        markers: FtsMarkers,
+        max_tokens: usize,
+    ) -> Result<Option<String>> {
+        let table = quote_identifier(&self.name);
+        connection.select_row_bound::<(i64, &str, &str, &str, i64, &str, i64), String>(
+            &format!(
+                "SELECT snippet({table}, ?, ?, ?, ?, ?) FROM {table} WHERE {table} MATCH ? AND rowid = ?"
+            ),
+        )?((
+            column as i64,
+            markers.open,
+            markers.close,
+            markers.ellipsis,
+            max_tokens as i64,
+            query,
+            rowid,
+        ))
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn quote_string(string: &str) -> String {
+    format!("'{}'", string.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        connection::Connection,
+        fts::{FtsMarkers, FtsTable},
+    };
+
+    const MARKERS: FtsMarkers = FtsMarkers {
+        open: "[",
+        close: "]",
+        ellipsis: "...",
+    };
+
+    #[test]
+    fn test_fts_table() -> Result<()> {
+        let connection = Connection::open_memory(Some("fts_table"));
+        let table = FtsTable::new("command_history")
+            .with_column("command")
+            .with_column("description")
+            .with_unindexed_column("workspace")
+            .with_tokenizer("porter unicode61");
+        table.create(&connection)?;
+        // Creating the table again is a no-op
+        table.create(&connection)?;
+
+        let open_file = table.insert(
+            &connection,
+            &["open file", "Opens a file in the editor", "zed"],
+        )?;
+        let save_files = table.insert(
+            &connection,
+            &["save all", "Saves all of the open files", "zed"],
+        )?;
+        let toggle = table.insert(&connection, &["toggle dock", "Toggles the dock", "zed"])?;
+        assert!(table.insert(&connection, &["too few"]).is_err());
+
+        // The porter tokenizer stems "files" to "file"
+        let matches = table.search(&connection, "file", 10)?;
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].rowid, open_file);
+        assert_eq!(matches[1].rowid, save_files);
+        assert!(matches[0].rank <= matches[1].rank);
+        assert!(table.search(&connection, "zed", 10)?.is_empty());
+
+        assert_eq!(
+            table.highlight(&connection, "dock", toggle, 1, MARKERS)?,
+            Some("Toggles the [dock]".to_string())
+        );
+        assert_eq!(
+            table.snippet(&connection, "editor", open_file, 1, MARKERS, 3)?,
+            Some("...in the [editor]".to_string())
+        );
+        assert_eq!(
+            table.highlight(&connection, "dock", open_file, 1, MARKERS)?,
+            None
+        );
+
+        table.delete(&connection, toggle)?;
+        assert!(table.search(&connection, "dock", 10)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    ffi::{c_int, c_void, CString},
+    panic::{self, AssertUnwindSafe},
+    slice,
+};
+
+use anyhow::Result;
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// A dynamically typed sqlite value, as passed to and returned from user defined functions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    // Text arguments which aren't valid UTF-8 are decoded lossily, as functions have no way
+    // to report them other than failing the whole query
+    pub(crate) unsafe fn from_raw(value: *mut sqlite3_value) -> Self {
+        match sqlite3_value_type(value) {
+            SQLITE_INTEGER => Value::Integer(sqlite3_value_int64(value)),
+            SQLITE_FLOAT => Value::Real(sqlite3_value_double(value)),
+            SQLITE_TEXT => {
+                let pointer = sqlite3_value_text(value);
+                let len = sqlite3_value_bytes(value) as usize;
+                if pointer.is_null() {
+                    Value::Text(String::new())
+                } else {
+                    let bytes = slice::from_raw_parts(pointer, len);
+                    Value::Text(String::from_utf8_lossy(bytes).into_owned())
+                }
+            }
+            SQLITE_BLOB => {
+                let pointer = sqlite3_value_blob(value) as *const u8;
+                let len = sqlite3_value_bytes(value) as usize;
+                if pointer.is_null() {
+                    Value::Blob(Vec::new())
+                } else {
+                    Value::Blob(slice::from_raw_parts(pointer, len).to_vec())
+                }
+            }
+            _ => Value::Null,
+        }
+    }
+
+    pub(crate) unsafe fn set_result(&self, context: *mut sqlite3_context) {
+        match self {
+            Value::Null => sqlite3_result_null(context),
+            Value::Integer(integer) => sqlite3_result_int64(context, *integer),
+            Value::Real(real) => sqlite3_result_double(context, *real),
+            Value::Text(text) => sqlite3_result_text(
+                context,
+                text.as_ptr() as *const _,
+                text.len() as c_int,
+                SQLITE_TRANSIENT(),
+            ),
+            Value::Blob(blob) => sqlite3_result_blob(
+                context,
+                blob.as_ptr() as *const _,
+                blob.len() as c_int,
+                SQLITE_TRANSIENT(),
+            ),
+        }
+    }
+}
+
+// Reports a failed or panicking callback as the result of the function call, as neither
+// errors nor panics can be propagated through sqlite
+pub(crate) unsafe fn set_result(
+    context: *mut sqlite3_context,
+    result: std::thread::Result<Result<Value>>,
+) {
+    let message = match result {
+        Ok(Ok(value)) => return value.set_result(context),
+        Ok(Err(error)) => format!("{:#}", error),
+        Err(_) => "User defined function panicked".to_string(),
+    };
+    sqlite3_result_error(
+        context,
+        message.as_ptr() as *const _,
+        message.len() as c_int,
+    );
+}
+
+pub(crate) unsafe fn arguments(argc: c_int, argv: *mut *mut sqlite3_value) -> Vec<Value> {
+    (0..argc as usize)
+        .map(|ix| Value::from_raw(*argv.add(ix)))
+        .collect()
+}
+
+pub(crate) unsafe extern "C" fn drop_boxed<T>(data: *mut c_void) {
+    drop(Box::from_raw(data as *mut T));
+}
+
+pub(crate) fn function_flags(deterministic: bool) -> c_int {
+    if deterministic {
+        SQLITE_UTF8 | SQLITE_DETERMINISTIC
+    } else {
+        SQLITE_UTF8
+    }
+}
+
+/// A custom aggregate function, which folds the rows of each group into a `State`.
+pub trait Aggregate: Send + 'static {
+    type State: Default;
+
+    /// Adds a row's arguments to the group's state.
+    fn step(&self, state: &mut Self::State, arguments: &[Value]) -> Result<()>;
+
+    /// Returns the result for a group. Groups without any rows get a default state.
+    fn finalize(&self, state: Self::State) -> Result<Value>;
+}
+
+/// An aggregate which can also be used as a window function, by removing rows from its
+/// state as they leave the window.
+pub trait WindowAggregate: Aggregate {
+    /// Returns the result for the rows currently in the window.
+    fn value(&self, state: &Self::State) -> Result<Value>;
+
+    /// Removes a row's arguments from the state, undoing its `step`.
+    fn inverse(&self, state: &mut Self::State, arguments: &[Value]) -> Result<()>;
+}
+
+// Each group's state is boxed, and the pointer is kept in sqlite's zeroed per-group memory
+unsafe fn aggregate_state<A: Aggregate>(context: *mut sqlite3_context) -> Option<*mut A::State> {
+    let slot = sqlite3_aggregate_context(context, std::mem::size_of::<*mut A::State>() as c_int)
+        as *mut *mut A::State;
+    if slot.is_null() {
+        return None;
+    }
+    if (*slot).is_null() {
+        *slot = Box::into_raw(Box::<A::State>::default());
+    }
+    Some(*slot)
+}
+
+unsafe fn set_error(context: *mut sqlite3_context, result: std::thread::Result<Result<()>>) {
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => set_result(context, Ok(Err(error))),
+        Err(panic) => set_result(context, Err(panic)),
+    }
+}
+
+unsafe extern "C" fn call_step<A: Aggregate>(
+    context: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let aggregate = &*(sqlite3_user_data(context) as *const A);
+    let Some(state) = aggregate_state::<A>(context) else {
+        return sqlite3_result_error_nomem(context);
+    };
+    let arguments = arguments(argc, argv);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| aggregate.step(&mut *state, &arguments)));
+    set_error(context, result);
+}
+
+unsafe extern "C" fn call_finalize<A: Aggregate>(context: *mut sqlite3_context) {
+    let aggregate = &*(sqlite3_user_data(context) as *const A);
+    // Passing a size of zero doesn't allocate, so groups without rows have no state yet
+    let slot = sqlite3_aggregate_context(context, 0) as *mut *mut A::State;
+    let state = if slot.is_null() || (*slot).is_null() {
+        A::State::default()
+    } else {
+        let state = *Box::from_raw(*slot);
+        *slot = std::ptr::null_mut();
+        state
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| aggregate.finalize(state)));
+    set_result(context, result);
+}
+
+unsafe extern "C" fn call_value<A: WindowAggregate>(context: *mut sqlite3_context) {
+    let aggregate = &*(sqlite3_user_data(context) as *const A);
+    let Some(state) = aggregate_state::<A>(context) else {
+        return sqlite3_result_error_nomem(context);
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| aggregate.value(&*state)));
+    set_result(context, result);
+}
+
+unsafe extern "C" fn call_inverse<A: WindowAggregate>(
+    context: *mut sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let aggregate = &*(sqlite3_user_data(context) as *const A);
+    let Some(state) = aggregate_state::<A>(context) else {
+        return sqlite3_result_error_nomem(context);
+    };
+    let arguments = arguments(argc, argv);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        aggregate.inverse(&mut *state, &arguments)
+    }));
+    set_error(context, result);
+}
+
+impl Connection {
+    /// Registers `function` as a SQL function called `name`, taking `n_args` arguments, or
+    /// any number of arguments if `n_args` is -1. Deterministic functions always return the
+    /// same result for the same arguments, which lets sqlite use them in indexes. Errors
+    /// returned by the function fail the statement calling it. The function lives until it
+    /// is replaced or the connection is closed.
+    pub fn create_scalar_function<F>(
+        &self,
+        name: &str,
+        n_args: i32,
+        deterministic: bool,
+        function: F,
+    ) -> Result<()>
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + 'static,
+    {
+        unsafe extern "C" fn call_function<F>(
+            context: *mut sqlite3_context,
+            argc: c_int,
+            argv: *mut *mut sqlite3_value,
+        ) where
+            F: Fn(&[Value]) -> Result<Value>,
+        {
+            let function = &*(sqlite3_user_data(context) as *const F);
+            let arguments = arguments(argc, argv);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| function(&arguments)));
+            set_result(context, result);
+        }
+
+        let name = CString::new(name)?;
+        // sqlite drops the function through `drop_boxed`, even if registering it fails
+        let code = unsafe {
+            sqlite3_create_function_v2(
+                self.sqlite3,
+                name.as_ptr(),
+                n_args,
+                function_flags(deterministic),
+                Box::into_raw(Box::new(function)) as *mut c_void,
+                Some(call_function::<F>),
+                None,
+                None,
+                Some(drop_boxed::<F>),
+            )
+        };
+        self.check_registration(code)
+    }
+
+    /// Registers `aggregate` as an aggregate SQL function called `name`, taking `n_args`
+    /// arguments, or any number if `n_args` is -1.
+    pub fn create_aggregate_function<A: Aggregate>(
+        &self,
+        name: &str,
+        n_args: i32,
+        deterministic: bool,
+        aggregate: A,
+    ) -> Result<()> {
+        let name = CString::new(name)?;
+        let code = unsafe {
+            sqlite3_create_function_v2(
+                self.sqlite3,
+                name.as_ptr(),
+                n_args,
+                function_flags(deterministic),
+                Box::into_raw(Box::new(aggregate)) as *mut c_void,
+                None,
+                Some(call_step::<A>),
+                Some(call_finalize::<A>),
+                Some(drop_boxed::<A>),
+            )
+        };
+        self.check_registration(code)
+    }
+
+    /// Registers `aggregate` as a SQL function called `name` which can be used both as an
+    /// aggregate and as a window function with an `OVER` clause.
+    pub fn create_window_function<A: WindowAggregate>(
+        &self,
+        name: &str,
+        n_args: i32,
+        deterministic: bool,
+        aggregate: A,
+    ) -> Result<()> {
+        let name = CString::new(name)?;
+        let code = unsafe {
+            sqlite3_create_window_function(
+                self.sqlite3,
+                name.as_ptr(),
+                n_args,
+                function_flags(deterministic),
+                Box::into_raw(Box::new(aggregate)) as *mut c_void,
+                Some(call_step::<A>),
+                Some(call_finalize::<A>),
+                Some(call_value::<A>),
+                Some(call_inverse::<A>),
+                Some(drop_boxed::<A>),
+            )
+        };
+        self.check_registration(code)
+    }
+
+    /// Registers `compare` as a collation sequence called `name`, for use in `COLLATE`
+    /// clauses and column definitions. Text which isn't valid UTF-8 is compared lossily. The
+    /// collation lives until it is replaced or the connection is closed.
+    pub fn create_collation<F>(&self, name: &str, compare: F) -> Result<()>
+    where
+        F: Fn(&str, &str) -> Ordering + Send + 'static,
+    {
+        unsafe extern "C" fn call_compare<F>(
+            compare: *mut c_void,
+            left_len: c_int,
+            left: *const c_void,
+            right_len: c_int,
+            right: *const c_void,
+        ) -> c_int
+        where
+            F: Fn(&str, &str) -> Ordering,
+        {
+            let compare = &*(compare as *const F);
+            let text = |pointer: *const c_void, len: c_int| {
+                if pointer.is_null() {
+                    return Cow::Borrowed("");
+                }
+                String::from_utf8_lossy(slice::from_raw_parts(pointer as *const u8, len as usize))
+            };
+            let (left, right) = (text(left, left_len), text(right, right_len));
+            // A panic can't unwind through sqlite, so treat the strings as equal
+            match panic::catch_unwind(AssertUnwindSafe(|| compare(&left, &right))) {
+                Ok(Ordering::Less) => -1,
+                Ok(Ordering::Greater) => 1,
+                Ok(Ordering::Equal) | Err(_) => 0,
+            }
+        }
+
+        let name = CString::new(name)?;
+        // Unlike functions, sqlite doesn't drop the collation if registering it fails
+        let compare = Box::into_raw(Box::new(compare));
+        let code = unsafe {
+            sqlite3_create_collation_v2(
+                self.sqlite3,
+                name.as_ptr(),
+                SQLITE_UTF8,
+                compare as *mut c_void,
+                Some(call_compare::<F>),
+                Some(drop_boxed::<F>),
+            )
+        };
+        if code != SQLITE_OK {
+            drop(unsafe { Box::from_raw(compare) });
+        }
+        self.check_registration(code)
+    }
+
+    pub(crate) fn check_registration(&self, code: c_int) -> Result<()> {
+        if code == SQLITE_OK {
+            Ok(())
+        } else {
+            Err(self.error_for_code(code).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+
+    use crate::{
+        connection::Connection,
+        functions::{Aggregate, Value, WindowAggregate},
+    };
+
+    struct Joined;
+
+    impl Aggregate for Joined {
+        type State = Vec<String>;
+
+        fn step(&self, state: &mut Vec<String>, arguments: &[Value]) -> Result<()> {
+            match arguments {
+                [Value::Text(text)] => state.push(text.clone()),
+                _ => anyhow::bail!("joined takes a string"),
+            }
+            Ok(())
+        }
+
+        fn finalize(&self, state: Vec<String>) -> Result<Value> {
+            Ok(Value::Text(state.join("+")))
+        }
+    }
+
+    struct Total;
+
+    impl Aggregate for Total {
+        type State = i64;
+
+        fn step(&self, state: &mut i64, arguments: &[Value]) -> Result<()> {
+            if let [Value::Integer(value)] = arguments {
+                *state += value;
+            }
+            Ok(())
+        }
+
+        fn finalize(&self, state: i64) -> Result<Value> {
+            Ok(Value::Integer(state))
+        }
+    }
+
+    impl WindowAggregate for Total {
+        fn value(&self, state: &i64) -> Result<Value> {
+            Ok(Value::Integer(*state))
+        }
+
+        fn inverse(&self, state: &mut i64, arguments: &[Value]) -> Result<()> {
+            if let [Value::Integer(value)] = arguments {
+                *state -= value;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_scalar_functions() -> Result<()> {
+        let connection = Connection::open_memory(Some("scalar_functions"));
+        connection.create_scalar_function("fuzzy_score", 2, true, |arguments| match arguments {
+            [Value::Text(path), Value::Text(query)] => {
+                Ok(Value::Integer(path.matches(query.as_str()).count() as i64))
+            }
+            _ => anyhow::bail!("fuzzy_score takes two strings"),
+        })?;
+        connection.create_scalar_function("describe", -1, true, |arguments| {
+            Ok(Value::Text(format!("{:?}", arguments)))
+        })?;
+
+        assert_eq!(
+            connection.select_row::<i64>("SELECT fuzzy_score('src/src/main.rs', 'src')")?()?,
+            Some(2)
+        );
+        assert_eq!(
+            connection.select_row::<String>("SELECT describe(NULL, 1, 2.5, 'a', x'01')")?()?,
+            Some(r#"[Null, Integer(1), Real(2.5), Text("a"), Blob([1])]"#.to_string())
+        );
+
+        let error = connection.select_row::<i64>("SELECT fuzzy_score(1, 2)")?().unwrap_err();
+        assert!(format!("{:#}", error).contains("fuzzy_score takes two strings"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_functions_are_dropped_with_connection() -> Result<()> {
+        let connection = Connection::open_memory(Some("functions_are_dropped_with_connection"));
+        let captured = Arc::new(());
+        connection.create_scalar_function("captured", 0, false, {
+            let captured = captured.clone();
+            move |_| Ok(Value::Integer(Arc::strong_count(&captured) as i64))
+        })?;
+        assert_eq!(
+            connection.select_row::<i64>("SELECT captured()")?()?,
+            Some(2)
+        );
+
+        drop(connection);
+        assert_eq!(Arc::strong_count(&captured), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_and_window_functions() -> Result<()> {
+        let connection = Connection::open_memory(Some("aggregate_and_window_functions"));
+        connection.create_aggregate_function("joined", 1, true, Joined)?;
+        connection.create_window_function("total", 1, true, Total)?;
+        connection.exec("CREATE TABLE test (name TEXT, value INTEGER)")?()?;
+        connection.exec("INSERT INTO test VALUES ('a', 1), ('b', 2), ('c', 3), ('d', 4)")?()?;
+
+        assert_eq!(
+            connection.select_row::<String>("SELECT joined(name) FROM test")?()?,
+            Some("a+b+c+d".to_string())
+        );
+        assert_eq!(
+            connection.select_row::<String>("SELECT joined(name) FROM test WHERE value > 10")?()?,
+            Some(String::new())
+        );
+        assert_eq!(
+            connection.select::<(bool, i64)>(
+                "SELECT value % 2 = 0, total(value) FROM test GROUP BY value % 2 ORDER BY 1"
+            )?()?,
+            vec![(false, 4), (true, 6)]
+        );
+        assert_eq!(
+            connection.select::<i64>(
+                "SELECT total(value) OVER (ORDER BY value ROWS BETWEEN 1 PRECEDING AND CURRENT ROW)
+                FROM test ORDER BY value"
+            )?()?,
+            vec![1, 3, 5, 7]
+        );
+
+        let error =
+            connection.select_row::<String>("SELECT joined(value) FROM test")?().unwrap_err();
+        assert!(format!("{:#}", error).contains("joined takes a string"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_collations() -> Result<()> {
+        let connection = Connection::open_memory(Some("collations"));
+        connection.create_collation("path", |left, right| {
+            left.to_lowercase().cmp(&right.to_lowercase())
+        })?;
+        connection.exec("CREATE TABLE paths (path TEXT COLLATE path)")?()?;
+        connection.exec("INSERT INTO paths VALUES ('b.rs'), ('A.rs'), ('a.md'), ('B.md')")?()?;
+
+        assert_eq!(
+            connection.select::<String>("SELECT path FROM paths ORDER BY path")?()?,
+            vec!["a.md", "A.rs", "B.md", "b.rs"]
+        );
+        assert_eq!(
+            connection.select_row::<i64>("SELECT COUNT(*) FROM paths WHERE path = 'a.RS'")?()?,
+            Some(1)
+        );
+        Ok(())
+    }
+}
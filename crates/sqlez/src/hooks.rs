@@ -0,0 +1,315 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// The kind of change reported to an update hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Called with the operation, database name, table name and rowid of each row which is
+/// inserted, updated or deleted. Must not use the connection which called it.
+pub type UpdateHook = Box<dyn FnMut(Operation, &str, &str, i64) + Send>;
+
+/// Called when a transaction is about to commit. Returns whether to let the commit go
+/// ahead, or to roll the transaction back instead. Must not use the connection which
+/// called it.
+pub type CommitHook = Box<dyn FnMut() -> bool + Send>;
+
+/// Called when a transaction is rolled back, including when a commit hook vetoes a commit.
+/// Must not use the connection which called it.
+pub type RollbackHook = Box<dyn FnMut() + Send>;
+
+/// Called periodically while a statement runs. Returning `Break` stops the statement, which
+/// then fails with `Error::Interrupted`. Must not use the connection which called it.
+pub type ProgressHandler = Box<dyn FnMut() -> ControlFlow<()> + Send>;
+
+impl Connection {
+    /// Sets a hook which is called for each changed row of a rowid table, such as to
+    /// invalidate caches of the table's contents. This replaces any existing hook. Changes
+    /// made by truncating a table with `DELETE FROM` without a `WHERE` clause, and changes
+    /// to `WITHOUT ROWID` tables, aren't reported.
+    pub fn set_update_hook(&self, hook: impl FnMut(Operation, &str, &str, i64) + Send + 'static) {
+        unsafe extern "C" fn call_update_hook(
+            hook: *mut c_void,
+            operation: c_int,
+            database: *const c_char,
+            table: *const c_char,
+            rowid: sqlite3_int64,
+        ) {
+            let hook = &mut *(hook as *mut UpdateHook);
+            let operation = match operation {
+                SQLITE_INSERT => Operation::Insert,
+                SQLITE_DELETE => Operation::Delete,
+                _ => Operation::Update,
+            };
+            let database = CStr::from_ptr(database).to_string_lossy();
+            let table = CStr::from_ptr(table).to_string_lossy();
+            // Panics can't unwind through sqlite
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                hook(operation, &database, &table, rowid)
+            }))
+            .ok();
+        }
+
+        let mut hook: Box<UpdateHook> = Box::new(Box::new(hook));
+        unsafe {
+            sqlite3_update_hook(
+                self.sqlite3,
+                Some(call_update_hook),
+                &mut *hook as *mut UpdateHook as *mut c_void,
+            )
+        };
+        *self.update_hook.borrow_mut() = Some(hook);
+    }
+
+    /// Removes the hook set by `set_update_hook`.
+    pub fn clear_update_hook(&self) {
+        unsafe { sqlite3_update_hook(self.sqlite3, None, ptr::null_mut()) };
+        *self.update_hook.borrow_mut() = None;
+    }
+
+    /// Sets a hook which is called before each transaction commits, including the implicit
+    /// transactions of statements run outside of one, and can veto the commit. The commit
+    /// may still fail after the hook returns true, such as when another connection holds a
+    /// lock, in which case the transaction stays open. This replaces any existing hook.
+    pub fn set_commit_hook(&self, hook: impl FnMut() -> bool + Send + 'static) {
+        unsafe extern "C" fn call_commit_hook(hook: *mut c_void) -> c_int {
+            let hook = &mut *(hook as *mut CommitHook);
+            // A panicking hook vetoes the commit, as it's unclear whether it should go ahead
+            let commit = panic::catch_unwind(AssertUnwindSafe(hook)).unwrap_or(false);
+            !commit as c_int
+        }
+
+        let mut hook: Box<CommitHook> = Box::new(Box::new(hook));
+        unsafe {
+            sqlite3_commit_hook(
+                self.sqlite3,
+                Some(call_commit_hook),
+                &mut *hook as *mut CommitHook as *mut c_void,
+            )
+        };
+        *self.commit_hook.borrow_mut() = Some(hook);
+    }
+
+    /// Removes the hook set by `set_commit_hook`.
+    pub fn clear_commit_hook(&self) {
+        unsafe { sqlite3_commit_hook(self.sqlite3, None, ptr::null_mut()) };
+        *self.commit_hook.borrow_mut() = None;
+    }
+
+    /// Sets a hook which is called after each transaction is rolled back, such as to
+    /// discard in-memory state which depended on its changes. This replaces any existing
+    /// hook.
+    pub fn set_rollback_hook(&self, hook: impl FnMut() + Send + 'static) {
+        unsafe extern "C" fn call_rollback_hook(hook: *mut c_void) {
+            let hook = &mut *(hook as *mut RollbackHook);
+            panic::catch_unwind(AssertUnwindSafe(hook)).ok();
+        }
+
+        let mut hook: Box<RollbackHook> = Box::new(Box::new(hook));
+        unsafe {
+            sqlite3_rollback_hook(
+                self.sqlite3,
+                Some(call_rollback_hook),
+                &mut *hook as *mut RollbackHook as *mut c_void,
+            )
+        };
+        *self.rollback_hook.borrow_mut() = Some(hook);
+    }
+
+    /// Removes the hook set by `set_rollback_hook`.
+    pub fn clear_rollback_hook(&self) {
+        unsafe { sqlite3_rollback_hook(self.sqlite3, None, ptr::null_mut()) };
+        *self.rollback_hook.borrow_mut() = None;
+    }
+
+    /// Sets a handler which is called roughly every `n_ops` virtual machine instructions
+    /// while a statement runs, such as to check a cancellation flag during an expensive
+    /// query. This replaces any existing handler.
+    pub fn set_progress_handler(
+        &self,
+        n_ops: i32,
+        handler: impl FnMut() -> ControlFlow<()> + Send + 'static,
+    ) {
+        unsafe extern "C" fn call_progress_handler(handler: *mut c_void) -> c_int {
+            let handler = &mut *(handler as *mut ProgressHandler);
+            // A panicking handler stops the statement, rather than letting it run on
+            match panic::catch_unwind(AssertUnwindSafe(handler)) {
+                Ok(ControlFlow::Continue(())) => 0,
+                Ok(ControlFlow::Break(())) | Err(_) => 1,
+            }
+        }
+
+        let mut handler: Box<ProgressHandler> = Box::new(Box::new(handler));
+        unsafe {
+            sqlite3_progress_handler(
+                self.sqlite3,
+                n_ops.max(1),
+                Some(call_progress_handler),
+                &mut *handler as *mut ProgressHandler as *mut c_void,
+            )
+        };
+        *self.progress_handler.borrow_mut() = Some(handler);
+    }
+
+    /// Removes the handler set by `set_progress_handler`.
+    pub fn clear_progress_handler(&self) {
+        unsafe { sqlite3_progress_handler(self.sqlite3, 0, None, ptr::null_mut()) };
+        *self.progress_handler.borrow_mut() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ops::ControlFlow,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use anyhow::Result;
+
+    use crate::{connection::Connection, error::Error, hooks::Operation};
+
+    #[test]
+    fn test_update_hook() -> Result<()> {
+        let connection = Connection::open_memory(Some("update_hook"));
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        connection.set_update_hook({
+            let changes = changes.clone();
+            move |operation, database, table, rowid| {
+                changes.lock().unwrap().push((
+                    operation,
+                    database.to_string(),
+                    table.to_string(),
+                    rowid,
+                ))
+            }
+        });
+
+        connection.exec("INSERT INTO test (value) VALUES (1)")?()?;
+        connection.exec("UPDATE test SET value = 2")?()?;
+        connection.exec("DELETE FROM test WHERE value = 2")?()?;
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                (Operation::Insert, "main".to_string(), "test".to_string(), 1),
+                (Operation::Update, "main".to_string(), "test".to_string(), 1),
+                (Operation::Delete, "main".to_string(), "test".to_string(), 1),
+            ]
+        );
+
+        connection.clear_update_hook();
+        connection.exec("INSERT INTO test (value) VALUES (3)")?()?;
+        assert_eq!(changes.lock().unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_and_rollback_hooks() -> Result<()> {
+        let connection = Connection::open_memory(Some("commit_and_rollback_hooks"));
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let allow_commit = Arc::new(Mutex::new(true));
+        connection.set_commit_hook({
+            let events = events.clone();
+            let allow_commit = allow_commit.clone();
+            move || {
+                events.lock().unwrap().push("commit");
+                *allow_commit.lock().unwrap()
+            }
+        });
+        connection.set_rollback_hook({
+            let events = events.clone();
+            move || events.lock().unwrap().push("rollback")
+        });
+
+        connection.with_transaction(|transaction| {
+            transaction.exec("INSERT INTO test (value) VALUES (1)")?()
+        })?;
+        assert!(connection
+            .with_transaction(|transaction| -> Result<()> {
+                transaction.exec("INSERT INTO test (value) VALUES (2)")?()?;
+                anyhow::bail!("Failed transaction")
+            })
+            .is_err());
+        assert_eq!(*events.lock().unwrap(), vec!["commit", "rollback"]);
+
+        // A vetoed commit is rolled back
+        *allow_commit.lock().unwrap() = false;
+        assert!(connection.exec("INSERT INTO test (value) VALUES (3)")?().is_err());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["commit", "rollback", "commit", "rollback"]
+        );
+        assert_eq!(
+            connection.select::<i32>("SELECT value FROM test")?()?,
+            vec![1]
+        );
+
+        connection.clear_commit_hook();
+        connection.clear_rollback_hook();
+        connection.exec("INSERT INTO test (value) VALUES (4)")?()?;
+        assert_eq!(events.lock().unwrap().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_progress_handler() -> Result<()> {
+        let connection = Connection::open_memory(Some("progress_handler"));
+        let expensive_query = "
+            WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers LIMIT 100000)
+            SELECT sum(n) FROM numbers";
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        connection.set_progress_handler(1000, {
+            let calls = calls.clone();
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(
+            connection.select_row::<i64>(expensive_query)?()?,
+            Some(5_000_050_000)
+        );
+        assert!(calls.load(Ordering::SeqCst) > 10);
+
+        // Breaking stops the statement part way through
+        connection.set_progress_handler(1000, {
+            let calls = calls.clone();
+            move || {
+                if calls.fetch_add(1, Ordering::SeqCst) % 5 == 4 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }
+        });
+        calls.store(0, Ordering::SeqCst);
+        let error = connection.select_row::<i64>(expensive_query)?().unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Interrupted));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        connection.clear_progress_handler();
+        assert!(connection.select_row::<i64>(expensive_query)?().is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::{ptr, sync::Arc};
+
+use libsqlite3_sys::*;
+use parking_lot::Mutex;
+
+use crate::connection::Connection;
+
+/// Interrupts the statements running on a connection, from any thread. The handle
+/// does nothing once its connection has been closed.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    sqlite3: Arc<Mutex<RawConnection>>,
+}
+
+// Cleared when the connection is dropped, so that it is never used after being closed
+struct RawConnection(*mut sqlite3);
+
+// sqlite3_interrupt is safe to call from any thread while the connection is open
+unsafe impl Send for RawConnection {}
+
+impl InterruptHandle {
+    pub(crate) fn new(sqlite3: *mut sqlite3) -> Self {
+        Self {
+            sqlite3: Arc::new(Mutex::new(RawConnection(sqlite3))),
+        }
+    }
+
+    /// Stops the statements currently running on the connection. They fail with an
+    /// `Error::Interrupted`, which can be found with `anyhow::Error::downcast_ref`.
+    pub fn interrupt(&self) {
+        let sqlite3 = self.sqlite3.lock();
+        if !sqlite3.0.is_null() {
+            unsafe { sqlite3_interrupt(sqlite3.0) };
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.sqlite3.lock().0 = ptr::null_mut();
+    }
+}
+
+impl Connection {
+    /// Returns a handle for interrupting this connection's running statements from
+    /// another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt_handle.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{connection::Connection, error::Error, statement::Statement};
+
+    #[test]
+    fn test_interrupt_running_query() {
+        let connection = Connection::open_memory(Some("test_interrupt_running_query"));
+        let handle = connection.interrupt_handle();
+
+        let query = thread::spawn(move || {
+            connection
+                .select_row::<i64>(
+                    "WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers)
+                    SELECT count(*) FROM numbers",
+                )
+                .unwrap()()
+        });
+
+        // Interrupting before the query starts has no effect, so keep trying until it stops
+        let start = Instant::now();
+        while !query.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            handle.interrupt();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let error = query.join().unwrap().unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Interrupted));
+
+        // The handle outlives the connection without doing anything
+        handle.interrupt();
+    }
+
+    #[test]
+    fn test_interrupted_connection_stays_usable() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let connection = Connection::open_memory(Some("test_interrupted_connection_stays_usable"));
+        let handle = connection.interrupt_handle();
+        assert_send_sync(&handle);
+
+        let mut statement = Statement::prepare(
+            &connection,
+            "WITH RECURSIVE numbers(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM numbers)
+            SELECT n FROM numbers",
+        )
+        .unwrap();
+        let mut rows = statement.iter::<i64>();
+        assert_eq!(rows.next().unwrap().unwrap(), 1);
+        handle.interrupt();
+        let error = rows.next().unwrap().unwrap_err();
+        assert_eq!(error.downcast_ref::<Error>(), Some(&Error::Interrupted));
+        drop(rows);
+        drop(statement);
+
+        assert_eq!(
+            connection.select_row::<i64>("SELECT 1").unwrap()().unwrap(),
+            Some(1)
+        );
+    }
+}
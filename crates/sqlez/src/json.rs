@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    bindable::{Bind, Column, StaticColumnCount},
+    statement::Statement,
+};
+
+/// Stores a value as JSON text, so that structured values can be bound and read without
+/// serializing them at each call site. The stored JSON can be queried with sqlite's JSON
+/// functions, see `json_path`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T> StaticColumnCount for Json<T> {}
+
+impl<T: Serialize> Bind for Json<T> {
+    fn bind(&self, statement: &Statement, start_index: i32) -> Result<i32> {
+        let json = serde_json::to_string(&self.0)
+            .with_context(|| format!("Failed to serialize JSON at index {start_index}"))?;
+        statement.bind_text(start_index, &json)?;
+        Ok(start_index + 1)
+    }
+}
+
+impl<T: DeserializeOwned> Column for Json<T> {
+    fn column(statement: &mut Statement, start_index: i32) -> Result<(Self, i32)> {
+        let json = statement.column_text(start_index)?;
+        let value = serde_json::from_str(json)
+            .with_context(|| format!("Failed to deserialize JSON at index {start_index}"))?;
+        Ok((Json(value), start_index + 1))
+    }
+}
+
+/// Returns SQL which selects the value at `path` (such as `$.panes[0].size`) within the JSON
+/// stored in `column`. The selected value is JSON text, so it can be read as a `Json<T>`
+/// even when it is a string, unlike the SQL values returned by `json_extract`.
+pub fn json_path(column: &str, path: &str) -> String {
+    format!("({} -> '{}')", column, path.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        connection::Connection,
+        json::{json_path, Json},
+    };
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Layout {
+        axis: String,
+        sizes: Vec<f32>,
+        flags: HashMap<String, bool>,
+    }
+
+    #[test]
+    fn test_json_round_trips() -> Result<()> {
+        let connection = Connection::open_memory(Some("json_round_trips"));
+        connection.exec("CREATE TABLE layouts (id INTEGER PRIMARY KEY, layout TEXT)")?()?;
+
+        let layout = Layout {
+            axis: "horizontal".to_string(),
+            sizes: vec![0.25, 0.75],
+            flags: HashMap::from_iter([("zoomed".to_string(), true)]),
+        };
+        connection.exec_bound::<(i64, Json<&Layout>)>("INSERT INTO layouts VALUES (?, ?)")?((
+            1,
+            Json(&layout),
+        ))?;
+
+        assert_eq!(
+            connection.select_row::<Json<Layout>>("SELECT layout FROM layouts")?()?,
+            Some(Json(layout))
+        );
+        assert_eq!(
+            connection.select_row::<Json<String>>(&format!(
+                "SELECT {} FROM layouts",
+                json_path("layout", "$.axis")
+            ))?()?,
+            Some(Json("horizontal".to_string()))
+        );
+        assert_eq!(
+            connection.select_row::<i64>(&format!(
+                "SELECT id FROM layouts WHERE {} = 'true'",
+                json_path("layout", "$.flags.zoomed")
+            ))?()?,
+            Some(1)
+        );
+
+        connection.exec("INSERT INTO layouts VALUES (2, 'not json')")?()?;
+        assert!(connection
+            .select_row::<Json<Layout>>("SELECT layout FROM layouts WHERE id = 2")?(
+        )
+        .is_err());
+        Ok(())
+    }
+}
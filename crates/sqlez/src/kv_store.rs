@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use futures::{
+    future::{self, BoxFuture},
+    Future, FutureExt,
+};
+use indoc::indoc;
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    connection::Connection,
+    debounced_writer::DebouncedWriter,
+    domain::{Domain, Migrator},
+    thread_safe_connection::ThreadSafeConnection,
+};
+
+const TABLE: &str = "typed_kv_store";
+
+/// The migrations for the table which every `KeyValueStore` keeps its values in. Include this
+/// in the connection's migrator to use stores on it.
+pub enum TypedKeyValueStore {}
+
+impl Domain for TypedKeyValueStore {
+    fn name() -> &'static str {
+        "TypedKeyValueStore"
+    }
+
+    fn migrations() -> &'static [&'static str] {
+        &[indoc! {"
+            CREATE TABLE typed_kv_store(
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                expires_at INTEGER,
+                PRIMARY KEY(namespace, key)
+            ) STRICT;
+        "}]
+    }
+}
+
+/// Persists values of type `T` as JSON, by key, within a namespace, so that features don't
+/// need their own tables for simple state. Values may be set to expire, after which they
+/// are no longer returned.
+///
+/// With `with_write_behind`, writes are batched and only the latest write for each key is
+/// made, which suits values that change often. Reads through the store see its pending
+/// writes, but other stores and connections don't until they are flushed.
+pub struct KeyValueStore<T, M: Migrator + 'static = TypedKeyValueStore> {
+    namespace: Arc<str>,
+    connection: ThreadSafeConnection<M>,
+    write_behind: Option<WriteBehind<M>>,
+    _value: PhantomData<fn() -> T>,
+}
+
+struct WriteBehind<M: Migrator + 'static> {
+    writer: DebouncedWriter<M>,
+    // Values which are queued but not yet written, by key. `None` marks a pending delete.
+    pending: Arc<Mutex<HashMap<String, PendingValue>>>,
+    next_generation: AtomicU64,
+}
+
+#[derive(Clone)]
+struct PendingValue {
+    entry: Option<Entry>,
+    // Identifies the write, so that a finished write doesn't remove a newer one
+    generation: u64,
+}
+
+#[derive(Clone)]
+struct Entry {
+    json: String,
+    expires_at: Option<SystemTime>,
+}
+
+impl Entry {
+    fn is_live(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at > SystemTime::now(),
+            None => true,
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned, M: Migrator> KeyValueStore<T, M> {
+    pub fn new(connection: ThreadSafeConnection<M>, namespace: &str) -> Self {
+        Self {
+            namespace: Arc::from(namespace),
+            connection,
+            write_behind: None,
+            _value: PhantomData,
+        }
+    }
+
+    /// Batches writes, flushing them every `interval` and when the store is dropped.
+    pub fn with_write_behind(mut self, interval: Duration) -> Self {
+        self.write_behind = Some(WriteBehind {
+            writer: DebouncedWriter::new(self.connection.clone(), interval),
+            pending: Default::default(),
+            next_generation: AtomicU64::new(0),
+        });
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<T>> {
+        if let Some(pending) = self.pending(key) {
+            return pending
+                .filter(Entry::is_live)
+                .map(|entry| self.deserialize(key, &entry.json))
+                .transpose();
+        }
+
+        let json = self
+            .connection
+            .select_row_bound::<(&str, &str, SystemTime), String>(indoc! {"
+                SELECT value FROM typed_kv_store
+                WHERE namespace = ? AND key = ? AND (expires_at IS NULL OR expires_at > ?)
+            "})?((&*self.namespace, key, SystemTime::now()))?;
+        json.map(|json| self.deserialize(key, &json)).transpose()
+    }
+
+    /// Returns every live value in the namespace, ordered by key.
+    pub fn list(&self) -> Result<Vec<(String, T)>> {
+        let mut entries = self
+            .connection
+            .select_bound::<(&str, SystemTime), (String, String)>(indoc! {"
+                SELECT key, value FROM typed_kv_store
+                WHERE namespace = ? AND (expires_at IS NULL OR expires_at > ?)
+            "})?((&*self.namespace, SystemTime::now()))?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        if let Some(write_behind) = &self.write_behind {
+            for (key, pending) in write_behind.pending.lock().iter() {
+                match pending.entry.as_ref().filter(|entry| entry.is_live()) {
+                    Some(entry) => entries.insert(key.clone(), entry.json.clone()),
+                    None => entries.remove(key),
+                };
+            }
+        }
+
+        let mut entries = entries
+            .into_iter()
+            .map(|(key, json)| {
+                let value = self.deserialize(&key, &json)?;
+                Ok((key, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(entries)
+    }
+
+    /// Stores `value` under `key`, replacing any existing value. The returned future can be
+    /// dropped, as the write is queued either way.
+    pub fn set(&self, key: &str, value: &T) -> impl Future<Output = Result<()>> {
+        self.write_entry(key, value, None)
+    }
+
+    /// Stores `value` under `key` like `set`, but stops returning it once `ttl` has passed.
+    /// A `ttl` too long for its expiry time to be stored, such as `Duration::MAX`, never
+    /// expires.
+    pub fn set_with_ttl(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<()>> {
+        // Times are stored as i64 nanoseconds since the unix epoch
+        let expires_at = SystemTime::now().checked_add(ttl).filter(|expires_at| {
+            expires_at
+                .duration_since(UNIX_EPOCH)
+                .is_ok_and(|since_epoch| i64::try_from(since_epoch.as_nanos()).is_ok())
+        });
+        self.write_entry(key, value, expires_at)
+    }
+
+    pub fn delete(&self, key: &str) -> impl Future<Output = Result<()>> {
+        self.write(key, None)
+    }
+
+    /// Deletes the expired values of every namespace, which are otherwise kept until they
+    /// are overwritten.
+    pub fn delete_expired(&self) -> impl Future<Output = Result<()>> {
+        self.connection.write(|connection| {
+            connection
+                .exec_bound::<SystemTime>("DELETE FROM typed_kv_store WHERE expires_at <= ?")?(
+                SystemTime::now(),
+            )
+        })
+    }
+
+    /// Waits until the writes made through this store have been written.
+    pub fn flush(&self) -> impl Future<Output = Result<()>> {
+        match &self.write_behind {
+            Some(write_behind) => write_behind.writer.flush().boxed(),
+            None => self.connection.flush().map(Ok).boxed(),
+        }
+    }
+
+    fn write_entry(
+        &self,
+        key: &str,
+        value: &T,
+        expires_at: Option<SystemTime>,
+    ) -> BoxFuture<'static, Result<()>> {
+        match serde_json::to_string(value) {
+            Ok(json) => self.write(key, Some(Entry { json, expires_at })),
+            Err(error) => future::ready(Err(error.into())).boxed(),
+        }
+    }
+
+    fn write(&self, key: &str, entry: Option<Entry>) -> BoxFuture<'static, Result<()>> {
+        let namespace = self.namespace.clone();
+        let key = key.to_string();
+        let Some(write_behind) = &self.write_behind else {
+            return self
+                .connection
+                .write(move |connection| write(connection, &namespace, &key, entry))
+                .boxed();
+        };
+
+        let generation = write_behind.next_generation.fetch_add(1, Ordering::SeqCst);
+        write_behind.pending.lock().insert(
+            key.clone(),
+            PendingValue {
+                entry: entry.clone(),
+                generation,
+            },
+        );
+        let pending = write_behind.pending.clone();
+        write_behind
+            .writer
+            .write(TABLE, format!("{namespace}\0{key}"), move |connection| {
+                let result = write(connection, &namespace, &key, entry);
+                let mut pending = pending.lock();
+                if pending.get(&key).map(|pending| pending.generation) == Some(generation) {
+                    pending.remove(&key);
+                }
+                result
+            });
+        future::ready(Ok(())).boxed()
+    }
+
+    fn pending(&self, key: &str) -> Option<Option<Entry>> {
+        let write_behind = self.write_behind.as_ref()?;
+        let pending = write_behind.pending.lock();
+        pending.get(key).map(|pending| pending.entry.clone())
+    }
+
+    fn deserialize(&self, key: &str, json: &str) -> Result<T> {
+        serde_json::from_str(json)
+            .with_context(|| format!("Failed to deserialize {}/{key}", self.namespace))
+    }
+}
+
+fn write(connection: &Connection, namespace: &str, key: &str, entry: Option<Entry>) -> Result<()> {
+    match entry {
+        Some(Entry { json, expires_at }) => {
+            connection.exec_bound::<(&str, &str, &str, Option<SystemTime>)>(indoc! {"
+                INSERT OR REPLACE INTO typed_kv_store(namespace, key, value, expires_at)
+                VALUES (?, ?, ?, ?)
+            "})?((namespace, key, json.as_str(), expires_at))
+        }
+        None => connection.exec_bound::<(&str, &str)>(
+            "DELETE FROM typed_kv_store WHERE namespace = ? AND key = ?",
+        )?((namespace, key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use anyhow::Result;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        kv_store::{KeyValueStore, TypedKeyValueStore},
+        thread_safe_connection::ThreadSafeConnection,
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct PanelSize {
+        width: f32,
+        height: f32,
+    }
+
+    fn open(name: &str) -> ThreadSafeConnection<TypedKeyValueStore> {
+        smol::block_on(ThreadSafeConnection::<TypedKeyValueStore>::builder(name, false).build())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_key_value_store() -> Result<()> {
+        let connection = open("test_key_value_store");
+        let sizes = KeyValueStore::<PanelSize, _>::new(connection.clone(), "panel_sizes");
+        let dismissed = KeyValueStore::<bool, _>::new(connection, "dismissed_notifications");
+
+        assert_eq!(sizes.get("terminal")?, None);
+        smol::block_on(sizes.set(
+            "terminal",
+            &PanelSize {
+                width: 10.,
+                height: 20.,
+            },
+        ))?;
+        smol::block_on(sizes.set(
+            "project",
+            &PanelSize {
+                width: 30.,
+                height: 40.,
+            },
+        ))?;
+        smol::block_on(dismissed.set("terminal", &true))?;
+
+        assert_eq!(
+            sizes.get("terminal")?,
+            Some(PanelSize {
+                width: 10.,
+                height: 20.
+            })
+        );
+        // Namespaces keep the same keys apart
+        assert_eq!(dismissed.get("terminal")?, Some(true));
+        assert_eq!(
+            sizes
+                .list()?
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>(),
+            ["project", "terminal"]
+        );
+
+        smol::block_on(sizes.delete("terminal"))?;
+        assert_eq!(sizes.get("terminal")?, None);
+        assert_eq!(dismissed.get("terminal")?, Some(true));
+
+        // A value of the wrong type is an error rather than missing
+        let wrong_type =
+            KeyValueStore::<String, _>::new(open("test_key_value_store"), "panel_sizes");
+        assert!(wrong_type.get("project").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_value_store_ttl() -> Result<()> {
+        let store = KeyValueStore::<i64, _>::new(open("test_key_value_store_ttl"), "ttl");
+        smol::block_on(store.set_with_ttl("short", &1, Duration::from_millis(50)))?;
+        smol::block_on(store.set_with_ttl("long", &2, Duration::from_secs(60 * 60)))?;
+        assert_eq!(store.get("short")?, Some(1));
+
+        // Expiry times which can't be represented never expire
+        smol::block_on(store.set_with_ttl("max", &3, Duration::MAX))?;
+        smol::block_on(store.set_with_ttl(
+            "millennia",
+            &4,
+            Duration::from_secs(1000 * 365 * 24 * 60 * 60),
+        ))?;
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(store.get("short")?, None);
+        assert_eq!(
+            store.list()?,
+            [
+                ("long".to_string(), 2),
+                ("max".to_string(), 3),
+                ("millennia".to_string(), 4)
+            ]
+        );
+        smol::block_on(store.delete_expired())?;
+        assert_eq!(
+            store
+                .connection
+                .select_row::<i64>("SELECT count(*) FROM typed_kv_store")?()?,
+            Some(3)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_value_store_write_behind() -> Result<()> {
+        let connection = open("test_key_value_store_write_behind");
+        let store = KeyValueStore::<i64, _>::new(connection.clone(), "scroll")
+            .with_write_behind(Duration::from_secs(60 * 60));
+        let other = KeyValueStore::<i64, _>::new(connection, "scroll");
+
+        for position in 0..10 {
+            drop(store.set("editor", &position));
+        }
+        drop(store.set("removed", &1));
+        drop(store.delete("removed"));
+
+        // Pending writes are only seen through the store which made them
+        assert_eq!(store.get("editor")?, Some(9));
+        assert_eq!(store.get("removed")?, None);
+        assert_eq!(store.list()?, [("editor".to_string(), 9)]);
+        assert_eq!(other.get("editor")?, None);
+
+        smol::block_on(store.flush())?;
+        assert_eq!(other.get("editor")?, Some(9));
+        assert_eq!(other.list()?, [("editor".to_string(), 9)]);
+
+        drop(store.set("editor", &10));
+        drop(store);
+        smol::block_on(other.flush())?;
+        assert_eq!(other.get("editor")?, Some(10));
+        Ok(())
+    }
+}
@@ -1,11 +1,38 @@
+pub mod authorizer;
+pub mod backup;
 pub mod bindable;
+pub mod blob;
 pub mod connection;
+pub mod debounced_writer;
 pub mod domain;
+pub mod encryption;
+pub mod error;
+pub mod extensions;
+pub mod fts;
+pub mod functions;
+pub mod hooks;
+pub mod interrupt;
+pub mod json;
+pub mod kv_store;
+pub mod maintenance;
 pub mod migrations;
+pub mod pool;
+pub mod pragma;
+pub mod query_plan;
+pub mod recovery;
 pub mod savepoint;
+pub mod serde_rows;
+pub mod serialize;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
 pub mod statement;
+pub mod statement_cache;
 pub mod thread_safe_connection;
+pub mod trace;
+pub mod transaction;
 pub mod typed_statements;
+pub mod vtab;
 mod util;
 
 pub use anyhow;
+pub use error::Error;
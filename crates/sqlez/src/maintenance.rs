@@ -0,0 +1,121 @@
+use anyhow::{anyhow, Result};
+
+use crate::connection::Connection;
+
+/// How much of the main database file is in use, in pages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DatabaseSize {
+    pub page_size: u64,
+    pub page_count: u64,
+    /// Pages which hold no data, and are reused before the file grows. Vacuuming returns
+    /// them to the file system.
+    pub freelist_count: u64,
+}
+
+impl DatabaseSize {
+    pub fn bytes(&self) -> u64 {
+        self.page_size * self.page_count
+    }
+
+    pub fn free_bytes(&self) -> u64 {
+        self.page_size * self.freelist_count
+    }
+}
+
+impl Connection {
+    /// Rebuilds the main database into a minimal file, returning free pages to the file
+    /// system. This rewrites the whole database, so it takes as long as copying it, and
+    /// fails inside a transaction.
+    pub fn vacuum(&self) -> Result<()> {
+        self.exec("VACUUM")?()
+    }
+
+    /// Returns up to `pages` free pages to the file system, without rewriting the rest of
+    /// the database. This only works once `PRAGMA auto_vacuum = INCREMENTAL` is set, and
+    /// otherwise does nothing.
+    pub fn incremental_vacuum(&self, pages: u32) -> Result<()> {
+        self.exec(&format!("PRAGMA incremental_vacuum({pages})"))?()
+    }
+
+    /// Gathers statistics about the tables and indexes, which the query planner uses to
+    /// pick indexes.
+    pub fn analyze(&self) -> Result<()> {
+        self.exec("ANALYZE")?()
+    }
+
+    /// Runs `PRAGMA optimize`, which analyzes the tables whose statistics look out of date.
+    /// The work each analysis may do on large tables is bounded, so this is cheap enough to
+    /// run regularly.
+    pub fn optimize(&self) -> Result<()> {
+        self.exec("PRAGMA analysis_limit = 400")?()?;
+        self.exec("PRAGMA optimize")?()
+    }
+
+    pub fn database_size(&self) -> Result<DatabaseSize> {
+        let get = |pragma: &str| {
+            self.pragma_get::<u64>(pragma)?
+                .ok_or_else(|| anyhow!("Pragma {pragma} returned no value"))
+        };
+        Ok(DatabaseSize {
+            page_size: get("page_size")?,
+            page_count: get("page_count")?,
+            freelist_count: get("freelist_count")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_maintenance() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez maintenance #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let connection = Connection::open_path(directory.join("db.db"))?;
+        connection.exec("PRAGMA auto_vacuum = INCREMENTAL")?()?;
+        connection.exec("CREATE TABLE test (value TEXT)")?()?;
+        connection.exec("CREATE INDEX test_value ON test (value)")?()?;
+        for index in 0..100 {
+            connection.exec_bound::<String>("INSERT INTO test VALUES (?)")?(format!(
+                "{index:0>1000}"
+            ))?;
+        }
+
+        let full = connection.database_size()?;
+        assert_eq!(full.freelist_count, 0);
+        assert_eq!(full.bytes(), full.page_size * full.page_count);
+
+        connection.exec("DELETE FROM test")?()?;
+        let emptied = connection.database_size()?;
+        assert_eq!(emptied.page_count, full.page_count);
+        assert!(emptied.free_bytes() > 0);
+
+        connection.incremental_vacuum(10)?;
+        let partly_vacuumed = connection.database_size()?;
+        assert_eq!(partly_vacuumed.page_count, emptied.page_count - 10);
+        assert_eq!(partly_vacuumed.freelist_count, emptied.freelist_count - 10);
+
+        connection.vacuum()?;
+        let vacuumed = connection.database_size()?;
+        assert_eq!(vacuumed.freelist_count, 0);
+        assert!(vacuumed.page_count < partly_vacuumed.page_count);
+
+        connection.exec("INSERT INTO test VALUES ('one'), ('two')")?()?;
+        connection.analyze()?;
+        assert_eq!(connection.estimated_row_count("test")?, Some(2));
+        connection.optimize()?;
+
+        // Vacuuming can't happen while a transaction is open
+        connection.exec("BEGIN")?()?;
+        assert!(connection.vacuum().is_err());
+        connection.exec("ROLLBACK")?()?;
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -1,32 +1,108 @@
 // Migrations are constructed by domain, and stored in a table in the connection db with domain name,
-// effected tables, actual query text, and order.
+// effected tables, actual query text, its checksum, and order.
 // If a migration is run and any of the query texts don't match, the app panics on startup (maybe fallback
 // to creating a new db?)
 // Otherwise any missing migrations are run on the connection
 
-use std::ffi::CString;
+use std::{
+    ffi::{CStr, CString},
+    ptr,
+};
 
 use anyhow::{anyhow, Context, Result};
 use indoc::{formatdoc, indoc};
-use libsqlite3_sys::sqlite3_exec;
+use libsqlite3_sys::*;
 
-use crate::connection::Connection;
+use crate::{connection::Connection, error::FailedStatement};
 
 impl Connection {
-    fn eager_exec(&self, sql: &str) -> anyhow::Result<()> {
+    /// Runs each of the statements in `sql` in turn, preparing each one only once the
+    /// statements before it have run, so that later statements can depend on the schema
+    /// changes of earlier ones. If a statement fails, the statements after it aren't run and
+    /// the error carries a `FailedStatement` saying which one it was.
+    pub fn eager_exec(&self, sql: &str) -> Result<()> {
+        self.eager_exec_checked(sql, false)
+    }
+
+    /// Runs `sql` like `eager_exec`. When `in_migration` is set, each statement is checked
+    /// before it runs, and statements which can't run within the migration's transaction
+    /// fail rather than ending or escaping it.
+    fn eager_exec_checked(&self, sql: &str, in_migration: bool) -> Result<()> {
         let sql_str = CString::new(sql).context("Error creating cstr")?;
-        unsafe {
-            sqlite3_exec(
-                self.sqlite3,
-                sql_str.as_c_str().as_ptr(),
-                None,
-                std::ptr::null_mut(),
-                std::ptr::null_mut(),
-            );
-        }
-        self.last_error()
-            .with_context(|| format!("Prepare call failed for query:\n{}", sql))?;
+        let mut remaining_sql = sql_str.as_c_str();
+        let mut index = 0;
+
+        while !remaining_sql.to_bytes().is_empty() {
+            let mut raw_statement = ptr::null_mut::<sqlite3_stmt>();
+            let mut remaining_sql_ptr = ptr::null();
+            let code = unsafe {
+                sqlite3_prepare_v2(
+                    self.sqlite3,
+                    remaining_sql.as_ptr(),
+                    -1,
+                    &mut raw_statement,
+                    &mut remaining_sql_ptr,
+                )
+            };
+
+            let statement_sql = if code == SQLITE_OK && !remaining_sql_ptr.is_null() {
+                let len = unsafe { remaining_sql_ptr.offset_from(remaining_sql.as_ptr()) };
+                String::from_utf8_lossy(&remaining_sql.to_bytes()[..len as usize])
+                    .trim()
+                    .to_string()
+            } else {
+                remaining_sql.to_string_lossy().trim().to_string()
+            };
+            let failure = |code| {
+                let error = anyhow!(self.error_for_code(code));
+                Err(error.context(FailedStatement {
+                    index,
+                    sql: statement_sql.clone(),
+                }))
+            };
+
+            if code != SQLITE_OK {
+                unsafe { sqlite3_finalize(raw_statement) };
+                return failure(code);
+            }
+            // Whitespace and comments after the last statement prepare to nothing
+            if !raw_statement.is_null() {
+                if let Some(statement) =
+                    non_transactional_statement(&statement_sql).filter(|_| in_migration)
+                {
+                    unsafe { sqlite3_finalize(raw_statement) };
+                    return Err(anyhow!(
+                        "{} statements can't run inside the migration's transaction",
+                        statement
+                    )
+                    .context(FailedStatement {
+                        index,
+                        sql: statement_sql,
+                    }));
+                }
+                self.log_statement(&statement_sql);
+                let code = unsafe {
+                    let mut code = sqlite3_step(raw_statement);
+                    while code == SQLITE_ROW {
+                        code = sqlite3_step(raw_statement);
+                    }
+                    code
+                };
+                let result = if code == SQLITE_DONE {
+                    Ok(())
+                } else {
+                    failure(code)
+                };
+                unsafe { sqlite3_finalize(raw_statement) };
+                result?;
+                index += 1;
+            }
 
+            if remaining_sql_ptr.is_null() {
+                break;
+            }
+            remaining_sql = unsafe { CStr::from_ptr(remaining_sql_ptr) };
+        }
         Ok(())
     }
 
@@ -34,45 +110,192 @@ impl Connection {
     /// Note: Unlike everything else in SQLez, migrations are run eagerly, without first
     /// preparing the SQL statements. This makes it possible to do multi-statement schema
     /// updates in a single string without running into prepare errors.
+    ///
+    /// A checksum of each step is recorded alongside it, and if the text of a step which has
+    /// already run has since changed, this fails rather than leaving the schema out of step
+    /// with the migrations.
     pub fn migrate(&self, domain: &'static str, migrations: &[&'static str]) -> Result<()> {
+        let steps = migrations
+            .iter()
+            .map(|migration| (*migration, None))
+            .collect::<Vec<_>>();
+        self.run_migrations(domain, &steps, false)
+    }
+
+    /// Like `migrate`, but each step is paired with a down migration which undoes it. The
+    /// down migrations are stored with the steps, so that `rollback_migrations_to` can unwind
+    /// steps which were written by a newer build, that the current build knows nothing about.
+    pub fn migrate_reversible(
+        &self,
+        domain: &'static str,
+        migrations: &[(&'static str, &'static str)],
+    ) -> Result<()> {
+        let steps = migrations
+            .iter()
+            .map(|(up, down)| (*up, Some(*down)))
+            .collect::<Vec<_>>();
+        self.run_migrations(domain, &steps, false)
+    }
+
+    /// Unwinds the domain's completed steps, newest first, until only the first `version`
+    /// steps remain, by running their stored down migrations. Fails without changing
+    /// anything if one of the steps has no down migration.
+    pub fn rollback_migrations_to(&self, domain: &'static str, version: usize) -> Result<()> {
+        self.with_savepoint("rolling_back_migrations", || {
+            self.create_migrations_table()?;
+            let steps_to_undo = self.select_bound::<(&str, usize), (usize, Option<String>)>(
+                indoc! {"
+                    SELECT step, down FROM migrations
+                    WHERE domain = ? AND step >= ?
+                    ORDER BY step DESC
+                    "},
+            )?((domain, version))?;
+
+            let mut remove_completed_migration = self.exec_bound::<(&str, usize)>(
+                "DELETE FROM migrations WHERE domain = ? AND step = ?",
+            )?;
+            for (step, down) in steps_to_undo {
+                let Some(down) = down else {
+                    return Err(anyhow!(
+                        "Migration for {} at step {} has no down migration, so it can't be rolled back",
+                        domain,
+                        step
+                    ));
+                };
+                self.eager_exec(&down)
+                    .with_context(|| format!("Failed to roll back {} step {}", domain, step))?;
+                remove_completed_migration((domain, step))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Like `migrate`, but re-runs any completed steps whose text has changed, instead of
+    /// failing. The changed steps must be safe to run against the schema left by their old
+    /// versions.
+    pub fn migrate_forcing_changes(
+        &self,
+        domain: &'static str,
+        migrations: &[&'static str],
+    ) -> Result<()> {
+        let steps = migrations
+            .iter()
+            .map(|migration| (*migration, None))
+            .collect::<Vec<_>>();
+        self.run_migrations(domain, &steps, true)
+    }
+
+    fn create_migrations_table(&self) -> Result<()> {
+        self.exec(indoc! {"
+            CREATE TABLE IF NOT EXISTS migrations (
+                domain TEXT,
+                step INTEGER,
+                migration TEXT,
+                checksum TEXT,
+                down TEXT
+            )"})?()?;
+        // Tables created by earlier versions are missing the newer columns
+        for column in ["checksum", "down"] {
+            if self.select_row_bound::<&str, i64>(
+                "SELECT 1 FROM pragma_table_info('migrations') WHERE name = ?",
+            )?(column)?
+            .is_none()
+            {
+                self.exec(&format!("ALTER TABLE migrations ADD COLUMN {column} TEXT"))?()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_migrations(
+        &self,
+        domain: &'static str,
+        steps: &[(&'static str, Option<&'static str>)],
+        force: bool,
+    ) -> Result<()> {
         self.with_savepoint("migrating", || {
             // Setup the migrations table unconditionally
-            self.exec(indoc! {"
-                CREATE TABLE IF NOT EXISTS migrations (
-                    domain TEXT,
-                    step INTEGER,
-                    migration TEXT
-                )"})?()?;
+            self.create_migrations_table()?;
 
-            let completed_migrations =
-                self.select_bound::<&str, (String, usize, String)>(indoc! {"
-                    SELECT domain, step, migration FROM migrations
+            let completed_migrations = self
+                .select_bound::<&str, (usize, String, Option<String>, Option<String>)>(indoc! {"
+                    SELECT step, migration, checksum, down FROM migrations
                     WHERE domain = ?
                     ORDER BY step
                     "})?(domain)?;
 
-            let mut store_completed_migration = self
-                .exec_bound("INSERT INTO migrations (domain, step, migration) VALUES (?, ?, ?)")?;
+            let mut store_completed_migration = self.exec_bound(indoc! {"
+                INSERT INTO migrations (domain, step, migration, checksum, down)
+                VALUES (?, ?, ?, ?, ?)"})?;
+            let mut update_completed_migration = self.exec_bound(indoc! {"
+                UPDATE migrations SET migration = ?3, checksum = ?4, down = ?5
+                WHERE domain = ?1 AND step = ?2"})?;
+
+            for (index, (migration, down)) in steps.iter().enumerate() {
+                let checksum = migration_checksum(migration);
+
+                let mut rerun = false;
+                if let Some((_, completed_migration, completed_checksum, completed_down)) =
+                    completed_migrations.get(index)
+                {
+                    // Steps recorded before checksums were stored are checked by their text
+                    let stored_checksum = completed_checksum
+                        .clone()
+                        .unwrap_or_else(|| migration_checksum(completed_migration));
+                    if stored_checksum == checksum {
+                        // Migration already run. Record its checksum and down migration if
+                        // they're missing
+                        if completed_checksum.is_none()
+                            || (down.is_some() && completed_down.as_deref() != *down)
+                        {
+                            update_completed_migration((
+                                domain,
+                                index,
+                                *migration,
+                                checksum.as_str(),
+                                *down,
+                            ))?;
+                        }
+                        continue;
+                    }
 
-            for (index, migration) in migrations.iter().enumerate() {
-                if let Some((_, _, completed_migration)) = completed_migrations.get(index) {
-                    if completed_migration != migration {
+                    if !force {
                         return Err(anyhow!(formatdoc! {"
-                            Migration changed for {} at step {}
+                            Migration changed for {} at step {} (stored checksum {}, proposed checksum {})
 
                             Stored migration:
                             {}
 
                             Proposed migration:
-                            {}", domain, index, completed_migration, migration}));
-                    } else {
-                        // Migration already run. Continue
-                        continue;
+                            {}", domain, index, stored_checksum, checksum, completed_migration, migration}));
                     }
+                    rerun = true;
                 }
 
-                self.eager_exec(migration)?;
-                store_completed_migration((domain, index, *migration))?;
+                // Each step and its record are applied atomically, so a failing statement
+                // doesn't leave the step partially applied
+                self.with_savepoint("migration_step", || {
+                    self.eager_exec_checked(migration, true).with_context(|| {
+                        format!("Migration for {} at step {} failed", domain, index)
+                    })?;
+                    if rerun {
+                        update_completed_migration((
+                            domain,
+                            index,
+                            *migration,
+                            checksum.as_str(),
+                            *down,
+                        ))
+                    } else {
+                        store_completed_migration((
+                            domain,
+                            index,
+                            *migration,
+                            checksum.as_str(),
+                            *down,
+                        ))
+                    }
+                })?;
             }
 
             Ok(())
@@ -80,11 +303,65 @@ impl Connection {
     }
 }
 
+/// A 64 bit FNV-1a hash of the migration's text, as hex. This has to stay stable across
+/// releases, as it's compared against checksums stored by earlier versions.
+fn migration_checksum(migration: &str) -> String {
+    let hash = migration
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    format!("{:016x}", hash)
+}
+
+/// The kind of `statement`, which is a single statement as split off by sqlite, if it can't
+/// run within a migration's transaction. Statements which end, escape or nest within the
+/// transaction would break the step's savepoint, and some pragmas can't be changed inside a
+/// transaction, or are silently ignored there.
+fn non_transactional_statement(statement: &str) -> Option<String> {
+    let (keyword, rest) = take_word(skip_whitespace_and_comments(statement));
+    match keyword.as_str() {
+        "VACUUM" | "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" | "ATTACH"
+        | "DETACH" => Some(keyword),
+        "PRAGMA" => {
+            let (mut pragma, rest) = take_word(skip_whitespace_and_comments(rest));
+            // The pragma may be prefixed by its schema's name
+            if let Some(rest) = skip_whitespace_and_comments(rest).strip_prefix('.') {
+                pragma = take_word(skip_whitespace_and_comments(rest)).0;
+            }
+            matches!(pragma.as_str(), "JOURNAL_MODE" | "FOREIGN_KEYS")
+                .then(|| format!("PRAGMA {}", pragma.to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+fn skip_whitespace_and_comments(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.split_once('\n').map_or("", |(_, rest)| rest);
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.split_once("*/").map_or("", |(_, rest)| rest);
+        } else {
+            return sql;
+        }
+    }
+}
+
+/// Splits the keyword or identifier at the start of `sql` off, uppercased.
+fn take_word(sql: &str) -> (String, &str) {
+    let end = sql
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(sql.len());
+    (sql[..end].to_ascii_uppercase(), &sql[end..])
+}
+
 #[cfg(test)]
 mod test {
     use indoc::indoc;
 
-    use crate::connection::Connection;
+    use crate::{connection::Connection, error::FailedStatement, migrations::migration_checksum};
 
     #[test]
     fn test_migrations_are_added_to_table() {
@@ -315,4 +592,265 @@ mod test {
 
         assert_eq!(res, "test text");
     }
+    #[test]
+    fn test_failed_migration_step_is_rolled_back() {
+        let connection = Connection::open_memory(Some("test_failed_migration_step_is_rolled_back"));
+        let first_step = "CREATE TABLE test1 (a TEXT)";
+        connection.migrate("test", &[first_step]).unwrap();
+
+        let result = connection.migrate(
+            "test",
+            &[
+                first_step,
+                indoc! {"
+                    CREATE TABLE test2 (a TEXT);
+                    INSERT INTO missing_table VALUES (1);"},
+            ],
+        );
+        assert!(result.is_err());
+
+        let tables = connection
+            .select::<String>("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap()()
+        .unwrap();
+        assert_eq!(tables, ["migrations", "test1"]);
+        assert_eq!(
+            connection
+                .select::<String>("SELECT migration FROM migrations")
+                .unwrap()()
+            .unwrap(),
+            [first_step]
+        );
+    }
+
+    #[test]
+    fn test_non_transactional_migration_is_rejected() {
+        let connection =
+            Connection::open_memory(Some("test_non_transactional_migration_is_rejected"));
+
+        let error = connection
+            .migrate("test", &["CREATE TABLE test1 (a TEXT); vacuum;"])
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("VACUUM"));
+        // The statements before the rejected one are rolled back with the step
+        assert!(connection.exec("SELECT * FROM test1").is_err());
+
+        for (migration, statement) in [
+            ("-- Compacts the db\nvacuum", "VACUUM"),
+            ("CREATE TABLE test1 (a TEXT); /* Done */ END", "END"),
+            ("SAVEPOINT nested; RELEASE nested", "SAVEPOINT"),
+            ("ROLLBACK", "ROLLBACK"),
+            ("PRAGMA main . journal_mode = WAL", "PRAGMA journal_mode"),
+            ("pragma foreign_keys=off", "PRAGMA foreign_keys"),
+        ] {
+            let error = connection.migrate("test", &[migration]).unwrap_err();
+            assert!(format!("{error:#}").contains(statement), "{error:#}");
+        }
+
+        // Semicolons and keywords in strings, comments and triggers are left alone
+        connection
+            .migrate(
+                "test",
+                &[indoc! {"
+                    CREATE TABLE test1 (a TEXT); -- then vacuum; commit
+                    INSERT INTO test1 VALUES ('a; vacuum; begin');
+                    CREATE TRIGGER test1_trigger AFTER INSERT ON test1 BEGIN
+                        DELETE FROM test1 WHERE a = 'b; commit';
+                    END;
+                    PRAGMA user_version = 1;"}],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_migration_checksums() {
+        let connection = Connection::open_memory(Some("test_migration_checksums"));
+
+        // Tables from before checksums were recorded get the column added and backfilled
+        connection
+            .exec(indoc! {"
+                CREATE TABLE migrations (
+                    domain TEXT,
+                    step INTEGER,
+                    migration TEXT
+                );
+                CREATE TABLE test (a TEXT);"})
+            .unwrap()()
+        .unwrap();
+        connection
+            .exec("INSERT INTO migrations VALUES ('test', 0, 'CREATE TABLE test (a TEXT)')")
+            .unwrap()()
+        .unwrap();
+
+        connection
+            .migrate(
+                "test",
+                &["CREATE TABLE test (a TEXT)", "ALTER TABLE test ADD b TEXT"],
+            )
+            .unwrap();
+        assert_eq!(
+            connection
+                .select::<String>("SELECT checksum FROM migrations ORDER BY step")
+                .unwrap()()
+            .unwrap(),
+            [
+                migration_checksum("CREATE TABLE test (a TEXT)"),
+                migration_checksum("ALTER TABLE test ADD b TEXT"),
+            ]
+        );
+
+        let error = connection
+            .migrate(
+                "test",
+                &["CREATE TABLE test (a TEXT)", "ALTER TABLE test ADD c TEXT"],
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains(&format!(
+            "stored checksum {}",
+            migration_checksum("ALTER TABLE test ADD b TEXT")
+        )));
+    }
+
+    #[test]
+    fn test_forced_migrations_rerun_changed_steps() {
+        let connection =
+            Connection::open_memory(Some("test_forced_migrations_rerun_changed_steps"));
+        connection
+            .migrate(
+                "test",
+                &[
+                    "CREATE TABLE test (a TEXT)",
+                    "CREATE TABLE test_index (a TEXT)",
+                ],
+            )
+            .unwrap();
+
+        let changed_step = indoc! {"
+            DROP TABLE IF EXISTS test_index;
+            CREATE INDEX test_index ON test (a);"};
+        connection
+            .migrate_forcing_changes("test", &["CREATE TABLE test (a TEXT)", changed_step])
+            .unwrap();
+        assert_eq!(
+            connection
+                .select::<String>("SELECT name FROM sqlite_master WHERE name = 'test_index'")
+                .unwrap()()
+            .unwrap(),
+            ["test_index"]
+        );
+        assert_eq!(
+            connection
+                .select::<(String, String)>(
+                    "SELECT migration, checksum FROM migrations ORDER BY step"
+                )
+                .unwrap()()
+            .unwrap()[1],
+            (changed_step.to_string(), migration_checksum(changed_step))
+        );
+
+        // The updated step now counts as completed
+        connection
+            .migrate("test", &["CREATE TABLE test (a TEXT)", changed_step])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rolling_back_reversible_migrations() {
+        let connection = Connection::open_memory(Some("test_rolling_back_reversible_migrations"));
+        let steps = [
+            ("CREATE TABLE test (a TEXT)", "DROP TABLE test"),
+            (
+                "ALTER TABLE test ADD b TEXT",
+                "ALTER TABLE test DROP COLUMN b",
+            ),
+            ("CREATE TABLE test2 (c TEXT)", "DROP TABLE test2"),
+        ];
+        connection.migrate_reversible("test", &steps).unwrap();
+        connection
+            .exec("INSERT INTO test (a, b) VALUES ('a', 'b')")
+            .unwrap()()
+        .unwrap();
+
+        // An older build which only knows about the first step can unwind the others
+        connection.rollback_migrations_to("test", 1).unwrap();
+        let tables = || {
+            connection
+                .select::<String>(
+                    "SELECT name FROM sqlite_master WHERE name LIKE 'test%' ORDER BY name",
+                )
+                .unwrap()()
+            .unwrap()
+        };
+        assert_eq!(tables(), ["test"]);
+        assert_eq!(
+            connection.select::<String>("SELECT * FROM test").unwrap()().unwrap(),
+            ["a"]
+        );
+        connection.migrate("test", &[steps[0].0]).unwrap();
+
+        // Rolled back steps run again when migrating forwards
+        connection.migrate_reversible("test", &steps).unwrap();
+        assert_eq!(tables(), ["test", "test2"]);
+
+        // Steps without a down migration can't be rolled back, and nothing is undone
+        connection
+            .migrate("other", &["CREATE TABLE other (a TEXT)"])
+            .unwrap();
+        assert!(connection.rollback_migrations_to("other", 0).is_err());
+        assert_eq!(
+            connection
+                .select::<String>("SELECT name FROM sqlite_master WHERE name = 'other'")
+                .unwrap()()
+            .unwrap(),
+            ["other"]
+        );
+    }
+
+    #[test]
+    fn test_eager_exec_reports_the_failing_statement() {
+        let connection = Connection::open_memory(Some("eager_exec_reports_the_failing_statement"));
+
+        // Statements are prepared one at a time, so later ones can use earlier schema changes
+        connection
+            .eager_exec(indoc! {"
+                CREATE TABLE test (value INTEGER UNIQUE);
+                -- A comment between statements
+                INSERT INTO test (value) VALUES (1);
+                SELECT value FROM test;
+            "})
+            .unwrap();
+
+        let error = connection
+            .eager_exec(indoc! {"
+                INSERT INTO test (value) VALUES (2);
+                INSERT INTO test (value) VALUES (1);
+                INSERT INTO test (value) VALUES (3);
+            "})
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FailedStatement>(),
+            Some(&FailedStatement {
+                index: 1,
+                sql: "INSERT INTO test (value) VALUES (1);".to_string(),
+            })
+        );
+        assert!(error
+            .downcast_ref::<crate::Error>()
+            .unwrap()
+            .is_constraint());
+        assert_eq!(
+            connection
+                .select::<i64>("SELECT value FROM test ORDER BY value")
+                .unwrap()()
+            .unwrap(),
+            [1, 2]
+        );
+
+        let error = connection
+            .eager_exec("DELETE FROM test; DELETE FROM missing; DELETE FROM test")
+            .unwrap_err();
+        let failed = error.downcast_ref::<FailedStatement>().unwrap();
+        assert_eq!(failed.index, 1);
+        assert!(failed.sql.starts_with("DELETE FROM missing;"));
+    }
 }
@@ -0,0 +1,181 @@
+use std::{ops::Deref, path::Path, time::Duration};
+
+use anyhow::{bail, Result};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+
+use crate::{connection::Connection, domain::Migrator};
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A database file shared by one write connection and several read connections. The file
+/// uses write-ahead logging, so reads on the read connections aren't blocked by a write in
+/// progress. Unlike `ThreadSafeConnection`, connections are checked out of the pool through
+/// guards, rather than being kept per thread.
+pub struct Pool {
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    reader_returned: Condvar,
+}
+
+/// A read-only connection checked out of a `Pool`, which returns it to the pool when
+/// dropped. Statements which write are rejected when they are prepared.
+pub struct ReadGuard<'a> {
+    pool: &'a Pool,
+    connection: Option<Connection>,
+}
+
+/// The pool's write connection, held exclusively until the guard is dropped.
+pub struct WriteGuard<'a> {
+    connection: MutexGuard<'a, Connection>,
+}
+
+impl Pool {
+    /// Opens the database at `path` in WAL mode, runs `M`'s migrations on the write
+    /// connection, and then opens `reader_count` read connections.
+    pub fn open<M: Migrator>(path: impl AsRef<Path>, reader_count: usize) -> Result<Self> {
+        if reader_count == 0 {
+            bail!("A connection pool needs at least one reader");
+        }
+        let path = path.as_ref();
+
+        let writer = Connection::open_path_wal(path)?;
+        writer.set_busy_timeout(BUSY_TIMEOUT);
+        M::migrate(&writer)?;
+
+        let readers = (0..reader_count)
+            .map(|_| {
+                let reader = Connection::open_path(path)?;
+                reader.set_busy_timeout(BUSY_TIMEOUT);
+                *reader.write.borrow_mut() = false;
+                Ok(reader)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            writer: Mutex::new(writer),
+            readers: Mutex::new(readers),
+            reader_returned: Condvar::new(),
+        })
+    }
+
+    /// Checks out a read connection, waiting for one to be returned if they are all in use.
+    pub fn read(&self) -> ReadGuard<'_> {
+        let mut readers = self.readers.lock();
+        loop {
+            if let Some(connection) = readers.pop() {
+                return ReadGuard {
+                    pool: self,
+                    connection: Some(connection),
+                };
+            }
+            self.reader_returned.wait(&mut readers);
+        }
+    }
+
+    /// Checks out a read connection if one is available, without waiting.
+    pub fn try_read(&self) -> Option<ReadGuard<'_>> {
+        let connection = self.readers.lock().pop()?;
+        Some(ReadGuard {
+            pool: self,
+            connection: Some(connection),
+        })
+    }
+
+    /// Takes the write connection, waiting for any other writer to finish with it.
+    pub fn write(&self) -> WriteGuard<'_> {
+        WriteGuard {
+            connection: self.writer.lock(),
+        }
+    }
+}
+
+impl<'a> Deref for ReadGuard<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for ReadGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.readers.lock().push(connection);
+            self.pool.reader_returned.notify_one();
+        }
+    }
+}
+
+impl<'a> Deref for WriteGuard<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use anyhow::Result;
+
+    use crate::{domain::Domain, pool::Pool};
+
+    struct TestDomain;
+
+    impl Domain for TestDomain {
+        fn name() -> &'static str {
+            "test"
+        }
+
+        fn migrations() -> &'static [&'static str] {
+            &["CREATE TABLE test (value INTEGER)"]
+        }
+    }
+
+    #[test]
+    fn test_pool() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("sqlez pool #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let pool = Arc::new(Pool::open::<TestDomain>(directory.join("db.db"), 2)?);
+
+        pool.write().exec("INSERT INTO test (value) VALUES (1)")?()?;
+
+        // Reads see committed data while a write transaction is open
+        let writer = pool.write();
+        let transaction = writer.transaction()?;
+        transaction.exec("INSERT INTO test (value) VALUES (2)")?()?;
+        assert_eq!(
+            pool.read().select::<i64>("SELECT value FROM test")?()?,
+            vec![1]
+        );
+        transaction.commit()?;
+        drop(writer);
+        assert_eq!(
+            pool.read()
+                .select::<i64>("SELECT value FROM test ORDER BY value")?()?,
+            vec![1, 2]
+        );
+
+        let reader = pool.read();
+        assert!(reader.exec("INSERT INTO test (value) VALUES (3)").is_err());
+
+        // Once every reader is checked out, readers wait for one to be returned
+        let other_reader = pool.read();
+        assert!(pool.try_read().is_none());
+        let waiting = thread::spawn({
+            let pool = pool.clone();
+            move || pool.read().select_row::<i64>("SELECT count(*) FROM test")?()
+        });
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiting.is_finished());
+        drop(reader);
+        assert_eq!(waiting.join().unwrap()?, Some(2));
+        drop(other_reader);
+
+        drop(pool);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, Result};
+
+use crate::{bindable::Column, connection::Connection};
+
+/// How the rollback journal is kept, see `Pragmas::journal_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Wal,
+    Off,
+}
+
+impl JournalMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+            JournalMode::Memory => "MEMORY",
+            JournalMode::Wal => "WAL",
+            JournalMode::Off => "OFF",
+        }
+    }
+
+    fn parse(mode: &str) -> Result<Self> {
+        [
+            JournalMode::Delete,
+            JournalMode::Truncate,
+            JournalMode::Persist,
+            JournalMode::Memory,
+            JournalMode::Wal,
+            JournalMode::Off,
+        ]
+        .into_iter()
+        .find(|journal_mode| journal_mode.as_str().eq_ignore_ascii_case(mode))
+        .ok_or_else(|| anyhow!("Unknown journal mode {mode:?}"))
+    }
+}
+
+/// How often sqlite waits for writes to reach the disk, see `Pragmas::synchronous`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    fn as_i64(&self) -> i64 {
+        match self {
+            Synchronous::Off => 0,
+            Synchronous::Normal => 1,
+            Synchronous::Full => 2,
+            Synchronous::Extra => 3,
+        }
+    }
+}
+
+/// The size of the page cache. sqlite stores this as a page count when it is positive and
+/// as a negated number of kibibytes otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheSize {
+    Pages(u32),
+    Kibibytes(u32),
+}
+
+/// Typed access to the pragmas which are commonly changed, from `Connection::pragma`.
+/// Other pragmas can still be read with `Connection::pragma_get`.
+pub struct Pragmas<'a> {
+    connection: &'a Connection,
+}
+
+impl Connection {
+    pub fn pragma(&self) -> Pragmas<'_> {
+        Pragmas { connection: self }
+    }
+}
+
+impl<'a> Pragmas<'a> {
+    /// Whether foreign key constraints are enforced. This can't be changed inside a
+    /// transaction, where setting it is silently ignored.
+    pub fn foreign_keys(&self) -> Result<bool> {
+        self.get("foreign_keys")
+    }
+
+    pub fn set_foreign_keys(&self, enabled: bool) -> Result<()> {
+        self.set("foreign_keys", if enabled { "ON" } else { "OFF" })
+    }
+
+    pub fn journal_mode(&self) -> Result<JournalMode> {
+        JournalMode::parse(&self.get::<String>("journal_mode")?)
+    }
+
+    /// Changes the journal mode, returning the mode in use afterwards. This may not be the
+    /// requested mode, as for example in-memory databases can't use WAL.
+    pub fn set_journal_mode(&self, mode: JournalMode) -> Result<JournalMode> {
+        let mode = self
+            .connection
+            .select_row::<String>(&format!("PRAGMA journal_mode = {}", mode.as_str()))?(
+        )?
+        .ok_or_else(|| anyhow!("Setting the journal mode returned no mode"))?;
+        JournalMode::parse(&mode)
+    }
+
+    pub fn synchronous(&self) -> Result<Synchronous> {
+        match self.get::<i64>("synchronous")? {
+            0 => Ok(Synchronous::Off),
+            1 => Ok(Synchronous::Normal),
+            2 => Ok(Synchronous::Full),
+            3 => Ok(Synchronous::Extra),
+            level => Err(anyhow!("Unknown synchronous level {level}")),
+        }
+    }
+
+    pub fn set_synchronous(&self, synchronous: Synchronous) -> Result<()> {
+        self.set("synchronous", synchronous.as_i64())
+    }
+
+    /// An integer stored in the database header which sqlite doesn't use itself.
+    pub fn user_version(&self) -> Result<i32> {
+        self.get("user_version")
+    }
+
+    pub fn set_user_version(&self, version: i32) -> Result<()> {
+        self.set("user_version", version)
+    }
+
+    pub fn cache_size(&self) -> Result<CacheSize> {
+        let size = self.get::<i64>("cache_size")?;
+        let amount = u32::try_from(size.unsigned_abs())?;
+        if size >= 0 {
+            Ok(CacheSize::Pages(amount))
+        } else {
+            Ok(CacheSize::Kibibytes(amount))
+        }
+    }
+
+    pub fn set_cache_size(&self, size: CacheSize) -> Result<()> {
+        match size {
+            CacheSize::Pages(pages) => self.set("cache_size", pages),
+            CacheSize::Kibibytes(kibibytes) => self.set("cache_size", -i64::from(kibibytes)),
+        }
+    }
+
+    /// The number of bytes of the database file which are memory mapped, or 0 when memory
+    /// mapping is off.
+    pub fn mmap_size(&self) -> Result<u64> {
+        self.get("mmap_size")
+    }
+
+    /// Changes the memory mapped size, returning the size in use afterwards. This is capped
+    /// at the limit sqlite was compiled with.
+    pub fn set_mmap_size(&self, bytes: u64) -> Result<u64> {
+        self.set("mmap_size", i64::try_from(bytes)?)?;
+        self.mmap_size()
+    }
+
+    fn get<T: Column>(&self, name: &str) -> Result<T> {
+        self.connection
+            .pragma_get::<T>(name)?
+            .ok_or_else(|| anyhow!("Pragma {name} returned no value"))
+    }
+
+    fn set(&self, name: &str, value: impl Display) -> Result<()> {
+        self.connection
+            .exec(&format!("PRAGMA {} = {}", name, value))?()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        connection::Connection,
+        pragma::{CacheSize, JournalMode, Synchronous},
+    };
+
+    #[test]
+    fn test_typed_pragmas() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("sqlez pragma #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let connection = Connection::open_path(directory.join("db.db"))?;
+        let pragma = connection.pragma();
+
+        pragma.set_foreign_keys(true)?;
+        assert!(pragma.foreign_keys()?);
+        pragma.set_foreign_keys(false)?;
+        assert!(!pragma.foreign_keys()?);
+
+        assert_eq!(pragma.journal_mode()?, JournalMode::Delete);
+        assert_eq!(pragma.set_journal_mode(JournalMode::Wal)?, JournalMode::Wal);
+        assert_eq!(pragma.journal_mode()?, JournalMode::Wal);
+
+        pragma.set_synchronous(Synchronous::Normal)?;
+        assert_eq!(pragma.synchronous()?, Synchronous::Normal);
+
+        assert_eq!(pragma.user_version()?, 0);
+        pragma.set_user_version(7)?;
+        assert_eq!(pragma.user_version()?, 7);
+
+        pragma.set_cache_size(CacheSize::Pages(100))?;
+        assert_eq!(pragma.cache_size()?, CacheSize::Pages(100));
+        pragma.set_cache_size(CacheSize::Kibibytes(4096))?;
+        assert_eq!(pragma.cache_size()?, CacheSize::Kibibytes(4096));
+
+        pragma.set_mmap_size(0)?;
+        assert_eq!(pragma.mmap_size()?, 0);
+
+        // In-memory databases can't change to WAL, so report the mode they stay in
+        let memory = Connection::open_memory(None);
+        assert_eq!(
+            memory.pragma().set_journal_mode(JournalMode::Wal)?,
+            JournalMode::Memory
+        );
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Context, Result};
+
+use crate::statement::Statement;
+
+/// How a step of a query plan reads a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanOperation {
+    /// Reads every row of the table, possibly through a covering index.
+    Scan,
+    /// Looks up rows through an index or the rowid.
+    Search,
+    /// Any other step, such as building a temporary b-tree for an `ORDER BY`.
+    Other,
+}
+
+/// One step of the plan chosen for a query, from `EXPLAIN QUERY PLAN`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanNode {
+    pub operation: PlanOperation,
+    /// The table which is scanned or searched.
+    pub table: Option<String>,
+    /// The index used by the step, if any.
+    pub index: Option<String>,
+    /// sqlite's description of the step. Its format isn't stable across sqlite versions.
+    pub detail: String,
+    pub children: Vec<PlanNode>,
+}
+
+/// The plan sqlite chose for a statement, as a tree of steps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub nodes: Vec<PlanNode>,
+}
+
+impl<'a> Statement<'a> {
+    /// Returns the plan sqlite will use to run this statement, which can be used to check
+    /// that a query reads through an index rather than scanning a whole table. For
+    /// statements prepared from several queries, the plans of each are concatenated.
+    pub fn query_plan(&self) -> Result<QueryPlan> {
+        let mut plan = QueryPlan::default();
+        for sql in self.statement_sql() {
+            let rows = Statement::prepare(self.connection(), format!("EXPLAIN QUERY PLAN {sql}"))
+                .and_then(|mut explain| explain.rows::<(i64, i64, i64, String)>())
+                .with_context(|| format!("Failed to explain query:\n{sql}"))?;
+            let rows = rows
+                .into_iter()
+                .map(|(id, parent, _, detail)| (id, parent, detail))
+                .collect::<Vec<_>>();
+            plan.nodes.extend(plan_nodes(&rows, 0));
+        }
+        Ok(plan)
+    }
+}
+
+impl QueryPlan {
+    /// Iterates over every step of the plan, parents before their children.
+    pub fn iter(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack = self.nodes.iter().rev().collect::<Vec<_>>();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Whether any step reads every row of a table without an index.
+    pub fn has_full_scan(&self) -> bool {
+        self.iter()
+            .any(|node| node.operation == PlanOperation::Scan && node.index.is_none())
+    }
+
+    /// Whether any step reads through the index called `index`.
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.iter().any(|node| node.index.as_deref() == Some(index))
+    }
+}
+
+fn plan_nodes(rows: &[(i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+    rows.iter()
+        .filter(|(_, row_parent, _)| *row_parent == parent)
+        .map(|(id, _, detail)| {
+            let mut node = parse_detail(detail);
+            node.children = plan_nodes(rows, *id);
+            node
+        })
+        .collect()
+}
+
+fn parse_detail(detail: &str) -> PlanNode {
+    let mut words = detail.split_whitespace();
+    let operation = match words.next() {
+        Some("SCAN") => PlanOperation::Scan,
+        Some("SEARCH") => PlanOperation::Search,
+        _ => PlanOperation::Other,
+    };
+
+    let (mut table, mut index) = (None, None);
+    if operation != PlanOperation::Other {
+        // Older versions of sqlite describe steps as `SCAN TABLE name`
+        let mut name = words.next();
+        if name == Some("TABLE") {
+            name = words.next();
+        }
+        table = name.map(str::to_string);
+
+        let words = words.collect::<Vec<_>>();
+        if let Some(position) = words.iter().position(|word| *word == "INDEX") {
+            // Automatic indexes don't have a name
+            index = words
+                .get(position + 1)
+                .filter(|name| !name.starts_with('('))
+                .map(|name| name.to_string());
+        } else if words.windows(2).any(|pair| pair == ["PRIMARY", "KEY"]) {
+            index = Some("PRIMARY KEY".to_string());
+        }
+    }
+
+    PlanNode {
+        operation,
+        table,
+        index,
+        detail: detail.to_string(),
+        children: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        connection::Connection,
+        query_plan::{parse_detail, PlanOperation},
+        statement::Statement,
+    };
+
+    #[test]
+    fn test_query_plan() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_query_plan"));
+        connection.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, size INTEGER)")?()?;
+        connection.exec("CREATE INDEX items_name ON items (name)")?()?;
+
+        let plan =
+            Statement::prepare(&connection, "SELECT id FROM items WHERE name = ?")?.query_plan()?;
+        assert!(plan.uses_index("items_name"));
+        assert!(!plan.has_full_scan());
+        assert_eq!(plan.nodes[0].operation, PlanOperation::Search);
+        assert_eq!(plan.nodes[0].table.as_deref(), Some("items"));
+
+        let plan =
+            Statement::prepare(&connection, "SELECT id FROM items WHERE size > 1")?.query_plan()?;
+        assert!(plan.has_full_scan());
+
+        let plan = Statement::prepare(
+            &connection,
+            "SELECT id FROM items WHERE id = ?; SELECT name FROM items ORDER BY size",
+        )?
+        .query_plan()?;
+        assert!(plan.uses_index("PRIMARY KEY"));
+        assert!(plan.iter().any(|node| node.detail.contains("TEMP B-TREE")));
+
+        // Subqueries are children of the step which runs them
+        let plan = Statement::prepare(
+            &connection,
+            "SELECT id FROM items WHERE size = (SELECT max(size) FROM items)",
+        )?
+        .query_plan()?;
+        assert!(plan.nodes.iter().any(|node| !node.children.is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_plan_detail() {
+        let node = parse_detail("SEARCH TABLE items USING COVERING INDEX items_name (name=?)");
+        assert_eq!(node.operation, PlanOperation::Search);
+        assert_eq!(node.table.as_deref(), Some("items"));
+        assert_eq!(node.index.as_deref(), Some("items_name"));
+
+        let node = parse_detail("SEARCH items USING AUTOMATIC COVERING INDEX (name=?)");
+        assert_eq!(node.index, None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    connection::{path_to_uri, Connection},
+    error::Error,
+    statement::{SqlType, Statement},
+};
+
+/// What happened when `Connection::open_file_with_recovery` found that a database couldn't
+/// be used as it was, so that the caller can tell the user.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// The problems which made the database unusable, such as those found by the
+    /// integrity check.
+    pub problems: Vec<String>,
+    /// Where the corrupt database was moved, if it was moved aside.
+    pub corrupt_path: Option<PathBuf>,
+    /// The rows which were salvaged into the fresh database.
+    pub recovered_rows: usize,
+    /// The tables and schema entries which couldn't be salvaged, or were only partly
+    /// salvaged, with the reason.
+    pub failures: Vec<String>,
+    /// Set when the database couldn't be opened or replaced, so an in-memory database is
+    /// used instead and nothing will be persisted.
+    pub in_memory: bool,
+}
+
+impl Connection {
+    /// Checks the main database for corruption with `PRAGMA quick_check`, returning the
+    /// problems found, which is empty for a healthy database. Fails if sqlite can't read the
+    /// database at all, such as when the file isn't a database.
+    pub fn quick_check(&self) -> Result<Vec<String>> {
+        let results = self.select::<String>("PRAGMA quick_check")?()?;
+        if results.len() == 1 && results[0] == "ok" {
+            Ok(Vec::new())
+        } else {
+            Ok(results)
+        }
+    }
+
+    /// Opens the database at `uri` like `open_file`, but checks it for corruption first.
+    /// A corrupt database is moved aside, and as much of it as can be read is salvaged into
+    /// a fresh database at the same path. Falls back to a shared memory db when the database
+    /// can't be opened or replaced. Returns a report when anything other than a plain open
+    /// happened.
+    pub fn open_file_with_recovery(uri: &str) -> (Self, Option<RecoveryReport>) {
+        let problems = match Self::open(uri, true) {
+            Ok(connection) => match connection.quick_check() {
+                Ok(problems) if problems.is_empty() => return (connection, None),
+                Ok(problems) => problems,
+                Err(error) if is_corrupt(&error) => vec![format!("{error:#}")],
+                // Failing to check, such as when another process holds a lock, doesn't mean
+                // the data is bad
+                Err(_) => return (connection, None),
+            },
+            Err(error) if is_corrupt(&error) => vec![format!("{error:#}")],
+            Err(error) => {
+                let report = RecoveryReport {
+                    problems: vec![format!("{error:#}")],
+                    in_memory: true,
+                    ..Default::default()
+                };
+                return (Self::open_memory(Some(uri)), Some(report));
+            }
+        };
+
+        let mut report = RecoveryReport {
+            problems,
+            ..Default::default()
+        };
+        // Only plain paths can be moved aside, as URIs may carry parameters which change
+        // which file is opened
+        if uri.starts_with("file:") || uri == ":memory:" {
+            report.in_memory = true;
+            return (Self::open_memory(Some(uri)), Some(report));
+        }
+
+        let path = Path::new(uri);
+        let connection = move_aside(path).and_then(|corrupt_path| {
+            report.corrupt_path = Some(corrupt_path.clone());
+            let fresh = Self::open(uri, true)?;
+            salvage(&corrupt_path, &fresh, &mut report);
+            Ok(fresh)
+        });
+        match connection {
+            Ok(connection) => (connection, Some(report)),
+            Err(error) => {
+                report.failures.push(format!("{error:#}"));
+                report.in_memory = true;
+                (Self::open_memory(Some(uri)), Some(report))
+            }
+        }
+    }
+}
+
+fn is_corrupt(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Error>() == Some(&Error::Corrupt)
+}
+
+// Renames the database and its journal files so that a fresh database can be created in
+// its place. The write-ahead log has to move with it, or sqlite would replay it into the
+// fresh database.
+fn move_aside(path: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    // Databases found corrupt within the same second get distinct names
+    let corrupt_path = (0..)
+        .map(|attempt| {
+            let mut corrupt_path = path.as_os_str().to_owned();
+            match attempt {
+                0 => corrupt_path.push(format!(".corrupt-{timestamp}")),
+                _ => corrupt_path.push(format!(".corrupt-{timestamp}-{attempt}")),
+            }
+            PathBuf::from(corrupt_path)
+        })
+        .find(|corrupt_path| !corrupt_path.exists())
+        .unwrap();
+
+    fs::rename(path, &corrupt_path)
+        .with_context(|| format!("Failed to move corrupt database {path:?} aside"))?;
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut journal = path.as_os_str().to_owned();
+        journal.push(suffix);
+        let mut moved_journal = corrupt_path.as_os_str().to_owned();
+        moved_journal.push(suffix);
+        if Path::new(&journal).exists() {
+            fs::rename(&journal, &moved_journal)
+                .with_context(|| format!("Failed to move {journal:?} aside"))?;
+        }
+    }
+    Ok(corrupt_path)
+}
+
+// Copies as much of the schema and data of the corrupt database as can be read into
+// `fresh`, recording what couldn't be copied. Tables are filled before indexes and
+// triggers are created, so that triggers don't run on the salvaged rows.
+fn salvage(corrupt_path: &Path, fresh: &Connection, report: &mut RecoveryReport) {
+    let corrupt = match Connection::builder(path_to_uri(corrupt_path))
+        .with_read_only(true)
+        .with_create(false)
+        .build()
+    {
+        Ok(corrupt) => corrupt,
+        Err(error) => {
+            report.failures.push(format!("{error:#}"));
+            return;
+        }
+    };
+    let schema = match corrupt.select::<(String, String, i64, Option<String>)>(
+        "SELECT type, name, rootpage, sql FROM sqlite_schema ORDER BY rowid",
+    ) {
+        Ok(mut select) => select(),
+        Err(error) => Err(error),
+    };
+    let schema = match schema {
+        Ok(schema) => schema,
+        Err(error) => {
+            report
+                .failures
+                .push(format!("Failed to read schema: {error:#}"));
+            return;
+        }
+    };
+
+    let result = fresh.with_savepoint("salvage", || {
+        for (kind, name, rootpage, sql) in &schema {
+            let Some(sql) = sql.as_deref().filter(|_| kind == "table") else {
+                continue;
+            };
+            // Internal tables are created by sqlite as they are needed
+            if name.starts_with("sqlite_") {
+                continue;
+            }
+            // Virtual tables create their shadow tables, so those already exist
+            if let Err(error) = fresh.exec(sql).and_then(|mut exec| exec()) {
+                let exists = fresh.select_row_bound::<&str, bool>(
+                    "SELECT count(*) > 0 FROM sqlite_schema WHERE type = 'table' AND name = ?",
+                )?(name)?;
+                if exists != Some(true) {
+                    report
+                        .failures
+                        .push(format!("Failed to create table {name}: {error:#}"));
+                    continue;
+                }
+            }
+            // The rows of virtual tables are stored in their shadow tables
+            if *rootpage != 0 {
+                copy_rows(&corrupt, fresh, name, report);
+            }
+        }
+
+        for (kind, name, _, sql) in &schema {
+            let Some(sql) = sql.as_deref().filter(|_| kind != "table") else {
+                continue;
+            };
+            if let Err(error) = fresh.exec(sql).and_then(|mut exec| exec()) {
+                report
+                    .failures
+                    .push(format!("Failed to create {kind} {name}: {error:#}"));
+            }
+        }
+        Ok(())
+    });
+    if let Err(error) = result {
+        report.failures.push(format!("{error:#}"));
+    }
+}
+
+// Copies rows until the first one which can't be read, keeping those copied before it
+fn copy_rows(corrupt: &Connection, fresh: &Connection, table: &str, report: &mut RecoveryReport) {
+    let quoted = format!("\"{}\"", table.replace('"', "\"\""));
+    let result = (|| -> Result<()> {
+        let mut select = Statement::prepare(corrupt, format!("SELECT * FROM {quoted}"))?;
+        let placeholders = vec!["?"; select.column_count() as usize].join(", ");
+        let mut insert = Statement::prepare(
+            fresh,
+            format!("INSERT INTO {quoted} VALUES ({placeholders})"),
+        )?;
+
+        select.for_each_row(|row| {
+            for index in 0..row.column_count() {
+                let parameter = index + 1;
+                match row.column_type(index)? {
+                    SqlType::Integer => insert.bind_int64(parameter, row.column_int64(index)?)?,
+                    SqlType::Float => insert.bind_double(parameter, row.column_double(index)?)?,
+                    SqlType::Text => insert.bind_text(parameter, &row.column_text_lossy(index)?)?,
+                    SqlType::Blob => insert.bind_blob(parameter, row.column_blob(index)?)?,
+                    SqlType::Null => {
+                        insert.bind_null(parameter)?;
+                    }
+                }
+            }
+            insert.exec()?;
+            report.recovered_rows += 1;
+            Ok(())
+        })
+    })();
+    if let Err(error) = result {
+        report
+            .failures
+            .push(format!("Failed to copy all rows of {table}: {error:#}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Seek, SeekFrom, Write},
+    };
+
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_open_file_with_recovery() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez recovery #{}", std::process::id()));
+        fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+        let uri = path.to_str().unwrap();
+
+        {
+            let connection = Connection::open_path(&path)?;
+            connection.exec("PRAGMA page_size = 4096")?()?;
+            connection.exec("CREATE TABLE small (value INTEGER)")?()?;
+            connection.exec("CREATE TABLE large (id INTEGER PRIMARY KEY, text TEXT)")?()?;
+            connection.exec("CREATE INDEX small_value ON small (value)")?()?;
+            connection.exec("INSERT INTO small VALUES (1), (2), (3)")?()?;
+            for id in 0..100 {
+                connection.exec_bound::<(i64, String)>("INSERT INTO large VALUES (?, ?)")?((
+                    id,
+                    format!("{id:0>500}"),
+                ))?;
+            }
+        }
+
+        // A healthy database is opened as it is
+        let (connection, report) = Connection::open_file_with_recovery(uri);
+        assert_eq!(report, None);
+        let page_count = connection.select_row::<u64>("PRAGMA page_count")?()?.unwrap();
+        drop(connection);
+
+        // Overwrite the last page, which holds the last rows of the large table
+        let mut file = fs::OpenOptions::new().write(true).open(&path)?;
+        file.seek(SeekFrom::Start((page_count - 1) * 4096))?;
+        file.write_all(&[0xff; 4096])?;
+        drop(file);
+
+        let (connection, report) = Connection::open_file_with_recovery(uri);
+        let report = report.expect("corruption should be reported");
+        assert!(!report.problems.is_empty());
+        assert!(!report.in_memory);
+        assert!(report.corrupt_path.as_ref().unwrap().exists());
+        assert!(connection.quick_check()?.is_empty());
+        assert_eq!(
+            connection.select::<i64>("SELECT value FROM small")?()?,
+            vec![1, 2, 3]
+        );
+        let salvaged = connection.select_row::<usize>("SELECT count(*) FROM large")?()?.unwrap();
+        assert!(salvaged > 0 && salvaged < 100);
+        assert_eq!(report.recovered_rows, 3 + salvaged);
+        assert_eq!(
+            connection
+                .select_row::<String>("SELECT name FROM sqlite_schema WHERE type = 'index'")?(
+            )?,
+            Some("small_value".to_string())
+        );
+        drop(connection);
+
+        // Files which aren't databases are replaced with an empty database
+        fs::write(&path, [0xff; 4096])?;
+        let (connection, report) = Connection::open_file_with_recovery(uri);
+        let report = report.expect("corruption should be reported");
+        assert_eq!(report.recovered_rows, 0);
+        assert!(!report.failures.is_empty());
+        assert!(!report.in_memory);
+        connection.exec("CREATE TABLE small (value INTEGER)")?()?;
+
+        drop(connection);
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -1,9 +1,80 @@
+use std::ops::Deref;
+
 use anyhow::Result;
-use indoc::formatdoc;
 
 use crate::connection::Connection;
 
+/// An open savepoint, which is rolled back and released when dropped unless `release` is
+/// called. Savepoints nest, so rolling one back only undoes the changes made since it was
+/// opened, leaving any outer transaction or savepoint open. Derefs to the connection so
+/// that statements can be run through it.
+pub struct Savepoint<'a> {
+    connection: &'a Connection,
+    name: String,
+    finished: bool,
+}
+
+impl<'a> Savepoint<'a> {
+    /// Keeps the changes made within the savepoint, merging them into the enclosing
+    /// transaction or committing them if there is none.
+    pub fn release(mut self) -> Result<()> {
+        self.connection.release(&self.name)?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Undoes the changes made within the savepoint and closes it. Dropping the savepoint
+    /// does the same, but this reports any error.
+    pub fn rollback(mut self) -> Result<()> {
+        self.finished = true;
+        self.connection.rollback_to(&self.name)?;
+        self.connection.release(&self.name)
+    }
+}
+
+impl<'a> Deref for Savepoint<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+    }
+}
+
+impl<'a> Drop for Savepoint<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.connection
+                .rollback_to(&self.name)
+                .and_then(|_| self.connection.release(&self.name))
+                .ok();
+        }
+    }
+}
+
 impl Connection {
+    /// Opens `SAVEPOINT name`, returning a guard which rolls it back unless it is released.
+    pub fn savepoint(&self, name: impl AsRef<str>) -> Result<Savepoint<'_>> {
+        let name = name.as_ref();
+        self.exec(&format!("SAVEPOINT {name}"))?()?;
+        Ok(Savepoint {
+            connection: self,
+            name: name.to_string(),
+            finished: false,
+        })
+    }
+
+    /// Releases the most recent savepoint called `name`, along with any savepoints
+    /// opened after it.
+    pub fn release(&self, name: impl AsRef<str>) -> Result<()> {
+        self.exec(&format!("RELEASE {}", name.as_ref()))?()
+    }
+
+    /// Undoes the changes made since the most recent savepoint called `name` was opened.
+    /// The savepoint stays open, so it must still be released.
+    pub fn rollback_to(&self, name: impl AsRef<str>) -> Result<()> {
+        self.exec(&format!("ROLLBACK TO {}", name.as_ref()))?()
+    }
+
     // Run a set of commands within the context of a `SAVEPOINT name`. If the callback
     // returns Err(_), the savepoint will be rolled back. Otherwise, the save
     // point is released.
@@ -11,18 +82,11 @@ impl Connection {
     where
         F: FnOnce() -> Result<R>,
     {
-        let name = name.as_ref();
-        self.exec(&format!("SAVEPOINT {name}"))?()?;
+        let savepoint = self.savepoint(name)?;
         let result = f();
         match result {
-            Ok(_) => {
-                self.exec(&format!("RELEASE {name}"))?()?;
-            }
-            Err(_) => {
-                self.exec(&formatdoc! {"
-                    ROLLBACK TO {name};
-                    RELEASE {name}"})?()?;
-            }
+            Ok(_) => savepoint.release()?,
+            Err(_) => savepoint.rollback()?,
         }
         result
     }
@@ -34,18 +98,11 @@ impl Connection {
     where
         F: FnOnce() -> Result<Option<R>>,
     {
-        let name = name.as_ref();
-        self.exec(&format!("SAVEPOINT {name}"))?()?;
+        let savepoint = self.savepoint(name)?;
         let result = f();
         match result {
-            Ok(Some(_)) => {
-                self.exec(&format!("RELEASE {name}"))?()?;
-            }
-            Ok(None) | Err(_) => {
-                self.exec(&formatdoc! {"
-                    ROLLBACK TO {name};
-                    RELEASE {name}"})?()?;
-            }
+            Ok(Some(_)) => savepoint.release()?,
+            Ok(None) | Err(_) => savepoint.rollback()?,
         }
         result
     }
@@ -145,4 +202,68 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_savepoint_guards() -> Result<()> {
+        let connection = Connection::open_memory(Some("savepoint_guards"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        let transaction = connection.transaction()?;
+        transaction.exec("INSERT INTO test(col) VALUES (1)")?()?;
+
+        let outer = transaction.savepoint("outer")?;
+        outer.exec("INSERT INTO test(col) VALUES (2)")?()?;
+        {
+            // A failed inner step only undoes its own savepoint
+            let inner = outer.savepoint("inner")?;
+            inner.exec("INSERT INTO test(col) VALUES (3)")?()?;
+        }
+        let inner = outer.savepoint("inner")?;
+        inner.exec("INSERT INTO test(col) VALUES (4)")?()?;
+        inner.release()?;
+        outer.release()?;
+
+        let rolled_back = transaction.savepoint("rolled_back")?;
+        rolled_back.exec("INSERT INTO test(col) VALUES (5)")?()?;
+        rolled_back.rollback()?;
+
+        assert!(connection.in_transaction());
+        transaction.commit()?;
+
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test ORDER BY col")?()?,
+            vec![1, 2, 4]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback_to_keeps_savepoint_open() -> Result<()> {
+        let connection = Connection::open_memory(Some("rollback_to_keeps_savepoint_open"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        connection.exec("SAVEPOINT manual")?()?;
+        connection.exec("INSERT INTO test(col) VALUES (1)")?()?;
+        connection.rollback_to("manual")?;
+        assert!(connection.in_transaction());
+        connection.exec("INSERT INTO test(col) VALUES (2)")?()?;
+        connection.release("manual")?;
+        assert!(!connection.in_transaction());
+
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test")?()?,
+            vec![2]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,278 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::de::{
+    self,
+    value::{SeqDeserializer, StrDeserializer},
+    DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+};
+
+use crate::statement::{SqlType, Statement};
+
+impl<'a> Statement<'a> {
+    /// Reads every row into a `T`, matching the result columns to `T`'s fields by name rather
+    /// than by position. Columns without a matching field are ignored, and `Option` fields
+    /// without a matching column are `None`.
+    pub fn rows_serde<T: DeserializeOwned>(&mut self) -> Result<Vec<T>> {
+        self.map(|this| deserialize_row(this))
+    }
+
+    /// Reads the single row returned by the statement like `rows_serde`. Errors if the
+    /// statement returns no rows or more than one row.
+    pub fn row_serde<T: DeserializeOwned>(&mut self) -> Result<T> {
+        self.single(|this| deserialize_row(this))
+    }
+
+    /// Reads the single row returned by the statement like `rows_serde`, or `None` if it
+    /// returned no rows.
+    pub fn maybe_row_serde<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        self.maybe(|this| deserialize_row(this))
+    }
+}
+
+fn deserialize_row<T: DeserializeOwned>(statement: &mut Statement) -> Result<T> {
+    T::deserialize(RowDeserializer { statement })
+        .map_err(|error| anyhow::Error::msg(error.0))
+        .context("Failed to deserialize row")
+}
+
+#[derive(Debug)]
+struct DeserializeError(String);
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        DeserializeError(message.to_string())
+    }
+}
+
+impl From<anyhow::Error> for DeserializeError {
+    fn from(error: anyhow::Error) -> Self {
+        DeserializeError(format!("{error:#}"))
+    }
+}
+
+// Presents the current row as a map from column names to values
+struct RowDeserializer<'s, 'a> {
+    statement: &'s mut Statement<'a>,
+}
+
+impl<'de, 's, 'a> de::Deserializer<'de> for RowDeserializer<'s, 'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        let column_count = self.statement.column_count();
+        visitor.visit_map(Columns {
+            statement: self.statement,
+            index: 0,
+            column_count,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct Columns<'s, 'a> {
+    statement: &'s mut Statement<'a>,
+    index: i32,
+    column_count: i32,
+}
+
+impl<'de, 's, 'a> MapAccess<'de> for Columns<'s, 'a> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeserializeError> {
+        if self.index >= self.column_count {
+            return Ok(None);
+        }
+        let name: StrDeserializer<DeserializeError> =
+            self.statement.column_name(self.index)?.into_deserializer();
+        seed.deserialize(name).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DeserializeError> {
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(ValueDeserializer {
+            statement: self.statement,
+            index,
+        })
+        .map_err(|error| {
+            let name = self.statement.column_name(index).unwrap_or("?");
+            DeserializeError(format!("Column {name}: {error}"))
+        })
+    }
+}
+
+// Deserializes a single column, going by the sqlite type of its value
+struct ValueDeserializer<'s, 'a> {
+    statement: &'s mut Statement<'a>,
+    index: i32,
+}
+
+impl<'de, 's, 'a> de::Deserializer<'de> for ValueDeserializer<'s, 'a> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.statement.column_type(self.index)? {
+            SqlType::Integer => visitor.visit_i64(self.statement.column_int64(self.index)?),
+            SqlType::Float => visitor.visit_f64(self.statement.column_double(self.index)?),
+            SqlType::Text => visitor.visit_str(self.statement.column_text(self.index)?),
+            SqlType::Blob => visitor.visit_bytes(self.statement.column_blob(self.index)?),
+            SqlType::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.statement.column_type(self.index)? {
+            SqlType::Integer => visitor.visit_bool(self.statement.column_int64(self.index)? != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.statement.column_type(self.index)? {
+            SqlType::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    // Types like `Vec<u8>` read bytes as a sequence rather than with `visit_bytes`
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.statement.column_type(self.index)? {
+            SqlType::Blob => {
+                let bytes = self.statement.column_blob(self.index)?.iter().copied();
+                visitor.visit_seq(SeqDeserializer::new(bytes))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Enums are stored as the names of their unit variants
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        let variant: StrDeserializer<DeserializeError> =
+            self.statement.column_text(self.index)?.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use indoc::indoc;
+    use serde::Deserialize;
+
+    use crate::{connection::Connection, statement::Statement};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Axis {
+        Horizontal,
+        Vertical,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pane {
+        id: u32,
+        axis: Axis,
+        active: bool,
+        size: Option<f32>,
+        title: String,
+        thumbnail: Option<Vec<u8>>,
+    }
+
+    #[test]
+    fn test_rows_serde() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_rows_serde"));
+        connection.exec(
+            "CREATE TABLE panes (id INTEGER, title TEXT, axis TEXT, active INTEGER, size REAL)",
+        )?()?;
+        connection.exec(indoc! {"
+            INSERT INTO panes VALUES
+                (1, 'one', 'vertical', 1, 0.5),
+                (2, 'two', 'horizontal', 0, NULL)"})?()?;
+
+        // Columns are matched by name, in any order, and extra columns are ignored
+        let mut statement = Statement::prepare(
+            &connection,
+            "SELECT size, 'unused' AS extra, title, axis, active, id FROM panes ORDER BY id",
+        )?;
+        assert_eq!(
+            statement.rows_serde::<Pane>()?,
+            vec![
+                Pane {
+                    id: 1,
+                    axis: Axis::Vertical,
+                    active: true,
+                    size: Some(0.5),
+                    title: "one".to_string(),
+                    thumbnail: None,
+                },
+                Pane {
+                    id: 2,
+                    axis: Axis::Horizontal,
+                    active: false,
+                    size: None,
+                    title: "two".to_string(),
+                    thumbnail: None,
+                },
+            ]
+        );
+
+        let mut statement = Statement::prepare(
+            &connection,
+            "SELECT *, x'0102' AS thumbnail FROM panes WHERE id = ?",
+        )?;
+        statement.bind(&1, 1)?;
+        assert_eq!(statement.row_serde::<Pane>()?.thumbnail, Some(vec![1, 2]));
+        statement.bind(&3, 1)?;
+        assert_eq!(statement.maybe_row_serde::<Pane>()?, None);
+        assert!(statement.row_serde::<Pane>().is_err());
+
+        // Missing columns and mismatched types name the column at fault
+        let error = Statement::prepare(&connection, "SELECT id, axis FROM panes")?
+            .rows_serde::<Pane>()
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("missing field `active`"));
+        let error = Statement::prepare(&connection, "SELECT title AS id FROM panes")?
+            .rows_serde::<Pane>()
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("Column id"));
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    ffi::{c_void, CString},
+    ptr, slice,
+};
+
+use anyhow::{anyhow, Context, Result};
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+impl Connection {
+    /// Copies the contents of the `schema` database (such as "main") into bytes in the
+    /// sqlite file format, which can be written to a file or loaded back with `deserialize`.
+    pub fn serialize(&self, schema: &str) -> Result<Vec<u8>> {
+        let schema_name = CString::new(schema)?;
+        let mut size = 0;
+        let data = unsafe { sqlite3_serialize(self.sqlite3, schema_name.as_ptr(), &mut size, 0) };
+        if data.is_null() {
+            // Empty databases have no pages to copy
+            if size == 0 && self.has_schema(schema)? {
+                return Ok(Vec::new());
+            }
+            return Err(anyhow!("Failed to serialize the {schema} database"));
+        }
+
+        // The copy belongs to us, and has to be freed by sqlite's allocator
+        let bytes = unsafe { slice::from_raw_parts(data, size as usize).to_vec() };
+        unsafe { sqlite3_free(data as *mut c_void) };
+        Ok(bytes)
+    }
+
+    /// Replaces the contents of the `schema` database with a copy of `bytes`, which are in
+    /// the sqlite file format, such as from `serialize`. The database then lives in memory,
+    /// so changes to it aren't written back to any file it was previously opened from.
+    pub fn deserialize(&self, schema: &str, bytes: &[u8]) -> Result<()> {
+        let schema_name = CString::new(schema)?;
+        // Statements which are prepared against the schema keep it from being replaced
+        self.clear_statement_cache();
+
+        let len = bytes.len();
+        let data = unsafe { sqlite3_malloc64(len.max(1) as sqlite3_uint64) } as *mut u8;
+        if data.is_null() {
+            return Err(anyhow!(
+                "Failed to allocate {len} bytes to deserialize into"
+            ));
+        }
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), data, len) };
+
+        // With FREEONCLOSE, sqlite takes ownership of the buffer, freeing it on failure too
+        let code = unsafe {
+            sqlite3_deserialize(
+                self.sqlite3,
+                schema_name.as_ptr(),
+                data,
+                len as sqlite3_int64,
+                len as sqlite3_int64,
+                (SQLITE_DESERIALIZE_FREEONCLOSE | SQLITE_DESERIALIZE_RESIZEABLE) as u32,
+            )
+        };
+        if code != SQLITE_OK {
+            return Err(self.error_for_code(code))
+                .with_context(|| format!("Failed to deserialize the {schema} database"));
+        }
+        Ok(())
+    }
+
+    fn has_schema(&self, schema: &str) -> Result<bool> {
+        let mut find_schema = self
+            .select_row_bound::<&str, i64>("SELECT 1 FROM pragma_database_list WHERE name = ?")?;
+        Ok(find_schema(schema)?.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_serialize_round_trip() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_serialize_round_trip"));
+        connection.exec("CREATE TABLE test (value TEXT)")?()?;
+        connection.exec("INSERT INTO test (value) VALUES ('one'), ('two')")?()?;
+
+        let bytes = connection.serialize("main")?;
+        assert!(bytes.starts_with(b"SQLite format 3\0"));
+        assert!(connection.serialize("missing").is_err());
+
+        let copy = Connection::open_memory(None);
+        assert_eq!(copy.serialize("main")?, Vec::<u8>::new());
+        copy.deserialize("main", &bytes)?;
+        assert_eq!(
+            copy.select::<String>("SELECT value FROM test")?()?,
+            vec!["one".to_string(), "two".to_string()]
+        );
+
+        // The deserialized database can grow, and doesn't affect the original
+        copy.exec("INSERT INTO test (value) SELECT value FROM test")?()?;
+        assert_eq!(
+            copy.select_row::<i64>("SELECT count(*) FROM test")?()?,
+            Some(4)
+        );
+        assert_eq!(
+            connection.select_row::<i64>("SELECT count(*) FROM test")?()?,
+            Some(2)
+        );
+
+        assert!(copy.deserialize("missing", &bytes).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::{ffi::CString, ops::Deref, ptr};
+
+use anyhow::{Context, Result};
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// Identifies a version of a WAL database, so that other connections to the same file can
+/// read that version with `Connection::open_snapshot`. A snapshot stays readable until a
+/// checkpoint copies the WAL frames it depends on back into the database file.
+pub struct Snapshot {
+    raw_snapshot: *mut sqlite3_snapshot,
+}
+
+// The snapshot is an opaque record of a WAL position, which isn't tied to a connection
+unsafe impl Send for Snapshot {}
+unsafe impl Sync for Snapshot {}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        unsafe { sqlite3_snapshot_free(self.raw_snapshot) };
+    }
+}
+
+/// A read transaction which is pinned to a single version of the main database, so that
+/// the statements run through it see consistent results while other connections write. The
+/// transaction ends when the guard is dropped. Derefs to the connection.
+pub struct ReadSnapshot<'a> {
+    connection: &'a Connection,
+}
+
+impl<'a> ReadSnapshot<'a> {
+    /// Records the version being read, so that other connections can read it too.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let main = CString::new("main")?;
+        let mut raw_snapshot = ptr::null_mut();
+        let code = unsafe {
+            sqlite3_snapshot_get(self.connection.sqlite3, main.as_ptr(), &mut raw_snapshot)
+        };
+        if code != SQLITE_OK {
+            return Err(self.connection.error_for_code(code)).context("Failed to get snapshot");
+        }
+        Ok(Snapshot { raw_snapshot })
+    }
+}
+
+impl<'a> Deref for ReadSnapshot<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+    }
+}
+
+impl<'a> Drop for ReadSnapshot<'a> {
+    fn drop(&mut self) {
+        self.connection.rollback_if_open().ok();
+    }
+}
+
+impl Connection {
+    /// Starts a read transaction on the latest version of the main database, which must be
+    /// in WAL mode. Errors if a transaction is already open.
+    pub fn snapshot(&self) -> Result<ReadSnapshot<'_>> {
+        self.begin()?;
+        let snapshot = ReadSnapshot { connection: self };
+        // BEGIN is deferred, so the read only starts once something is read
+        self.exec("SELECT 1 FROM sqlite_schema LIMIT 1")?()?;
+        Ok(snapshot)
+    }
+
+    /// Starts a read transaction on the version of the main database recorded by
+    /// `snapshot`, which may be older than the latest version. Fails if the snapshot is no
+    /// longer readable, or if a transaction is already open.
+    pub fn open_snapshot(&self, snapshot: &Snapshot) -> Result<ReadSnapshot<'_>> {
+        // The WAL index has to be open before a snapshot can be read from it
+        self.exec("SELECT 1 FROM sqlite_schema LIMIT 1")?()?;
+        self.begin()?;
+        let read_snapshot = ReadSnapshot { connection: self };
+
+        let main = CString::new("main")?;
+        let code =
+            unsafe { sqlite3_snapshot_open(self.sqlite3, main.as_ptr(), snapshot.raw_snapshot) };
+        if code != SQLITE_OK {
+            return Err(self.error_for_code(code)).context("Failed to open snapshot");
+        }
+        Ok(read_snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_snapshots() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez snapshot #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let writer = Connection::open_path_wal(&path)?;
+        writer.exec("CREATE TABLE test (value INTEGER)")?()?;
+        writer.exec("INSERT INTO test (value) VALUES (1)")?()?;
+        let reader = Connection::open_path(&path)?;
+        let other_reader = Connection::open_path(&path)?;
+        let count =
+            |connection: &Connection| connection.select_row::<i64>("SELECT count(*) FROM test")?();
+
+        let snapshot = {
+            let pinned = reader.snapshot()?;
+            writer.exec("INSERT INTO test (value) VALUES (2)")?()?;
+            // Every statement sees the version from when the read started
+            assert_eq!(count(&pinned)?, Some(1));
+            assert_eq!(pinned.select::<i64>("SELECT value FROM test")?()?, vec![1]);
+            assert!(reader.snapshot().is_err());
+            pinned.snapshot()?
+        };
+        assert_eq!(count(&reader)?, Some(2));
+
+        // Other connections can go back to the recorded version
+        writer.exec("INSERT INTO test (value) VALUES (3)")?()?;
+        {
+            let pinned = other_reader.open_snapshot(&snapshot)?;
+            assert_eq!(count(&pinned)?, Some(1));
+        }
+        assert_eq!(count(&other_reader)?, Some(3));
+
+        // Once checkpointed, the old version can no longer be read
+        drop((reader, other_reader));
+        writer.exec("PRAGMA wal_checkpoint(TRUNCATE)")?()?;
+        writer.exec("INSERT INTO test (value) VALUES (4)")?()?;
+        let reader = Connection::open_path(&path)?;
+        assert!(reader.open_snapshot(&snapshot).is_err());
+        assert!(!reader.in_transaction());
+
+        drop((writer, reader));
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
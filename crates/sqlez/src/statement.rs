@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::ffi::{c_int, CStr, CString};
 use std::marker::PhantomData;
 use std::{ptr, slice, str};
@@ -12,6 +14,16 @@ pub struct Statement<'a> {
     raw_statements: Vec<*mut sqlite3_stmt>,
     current_statement: usize,
     connection: &'a Connection,
+    // Set for statements from `Connection::prepare_cached`, which are returned to the
+    // connection's statement cache instead of being finalized when dropped
+    cache_key: Option<String>,
+    // Rows modified by the statements which have finished in the current run, and the
+    // connection's total changes from when the running statement started
+    changes: usize,
+    total_changes_at_start: i64,
+    // While binding a named parameter, the only statement which values are bound in, as
+    // the other statements may use the same index for different parameters
+    bind_target: Cell<Option<usize>>,
     phantom: PhantomData<sqlite3_stmt>,
 }
 
@@ -36,6 +48,10 @@ impl<'a> Statement<'a> {
             raw_statements: Default::default(),
             current_statement: 0,
             connection,
+            cache_key: None,
+            changes: 0,
+            total_changes_at_start: 0,
+            bind_target: Cell::new(None),
             phantom: PhantomData,
         };
         unsafe {
@@ -58,12 +74,22 @@ impl<'a> Statement<'a> {
                     &mut remaining_sql_ptr,
                 );
 
+                // The error offset is relative to the start of this statement
+                let statement_offset = remaining_sql.as_ptr().offset_from(sql.as_ptr());
                 remaining_sql = CStr::from_ptr(remaining_sql_ptr);
                 statement.raw_statements.push(raw_statement);
 
-                connection.last_error().with_context(|| {
-                    format!("Prepare call failed for query:\n{}", query.as_ref())
-                })?;
+                if let Err(error) = connection.last_error() {
+                    let offset = match sqlite3_error_offset(connection.sqlite3) {
+                        -1 => String::new(),
+                        offset => format!(" at offset {}", statement_offset + offset as isize),
+                    };
+                    return Err(error.context(format!(
+                        "Prepare call failed{} for query:\n{}",
+                        offset,
+                        query.as_ref()
+                    )));
+                }
 
                 if !connection.can_write() && sqlite3_stmt_readonly(raw_statement) == 0 {
                     let sql = CStr::from_ptr(sqlite3_sql(raw_statement));
@@ -78,17 +104,85 @@ impl<'a> Statement<'a> {
         Ok(statement)
     }
 
+    pub(crate) fn from_cached(
+        connection: &'a Connection,
+        query: &str,
+        raw_statements: Vec<*mut sqlite3_stmt>,
+    ) -> Result<Self> {
+        let statement = Self {
+            raw_statements,
+            current_statement: 0,
+            connection,
+            cache_key: Some(query.to_string()),
+            changes: 0,
+            total_changes_at_start: 0,
+            bind_target: Cell::new(None),
+            phantom: PhantomData,
+        };
+
+        if !connection.can_write() {
+            for raw_statement in &statement.raw_statements {
+                unsafe {
+                    if sqlite3_stmt_readonly(*raw_statement) == 0 {
+                        bail!(
+                            "Write statement prepared with connection that is not write capable. SQL:\n{} ",
+                            query
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(statement)
+    }
+
+    pub(crate) fn cached(mut self, query: &str) -> Self {
+        self.cache_key = Some(query.to_string());
+        self
+    }
+
     fn current_statement(&self) -> *mut sqlite3_stmt {
         *self.raw_statements.get(self.current_statement).unwrap()
     }
 
-    pub fn reset(&mut self) {
+    /// Returns the statement to its initial state and clears its bindings, so that it can
+    /// be bound and run again, even after a partial read.
+    pub fn reset(&mut self) -> Result<&mut Self> {
+        self.rewind();
+        unsafe {
+            for raw_statement in self.raw_statements.iter() {
+                sqlite3_clear_bindings(*raw_statement);
+            }
+        }
+        Ok(self)
+    }
+
+    // Returns to the first step after each run, keeping the bindings so that the statement
+    // can be run again with the same values.
+    fn rewind(&mut self) {
         unsafe {
             for raw_statement in self.raw_statements.iter() {
                 sqlite3_reset(*raw_statement);
             }
         }
         self.current_statement = 0;
+        self.changes = 0;
+    }
+
+    pub(crate) fn connection(&self) -> &'a Connection {
+        self.connection
+    }
+
+    /// The SQL text of each of the statements this was prepared from.
+    pub(crate) fn statement_sql(&self) -> Vec<String> {
+        self.raw_statements
+            .iter()
+            .map(|raw_statement| unsafe {
+                CStr::from_ptr(sqlite3_sql(*raw_statement))
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
     }
 
     pub fn parameter_count(&self) -> i32 {
@@ -102,9 +196,20 @@ impl<'a> Statement<'a> {
     }
 
     fn bind_index_with(&self, index: i32, bind: impl Fn(&*mut sqlite3_stmt)) -> Result<()> {
+        if index < 1 {
+            return Err(anyhow!("Parameter index {index} is out of range"));
+        }
+
         let mut any_succeed = false;
         unsafe {
-            for raw_statement in self.raw_statements.iter() {
+            for (statement_ix, raw_statement) in self.raw_statements.iter().enumerate() {
+                if self
+                    .bind_target
+                    .get()
+                    .is_some_and(|target| target != statement_ix)
+                {
+                    continue;
+                }
                 if index <= sqlite3_bind_parameter_count(*raw_statement) {
                     bind(raw_statement);
                     self.connection
@@ -133,6 +238,17 @@ impl<'a> Statement<'a> {
         })
     }
 
+    /// Binds a blob of `len` zeroes without allocating it, to be filled in afterwards with
+    /// `Connection::open_blob`.
+    pub fn bind_zeroblob(&self, index: i32, len: usize) -> Result<()> {
+        let index = index as c_int;
+        let len = sqlite3_uint64::try_from(len)?;
+
+        self.bind_index_with(index, |raw_statement| unsafe {
+            sqlite3_bind_zeroblob64(*raw_statement, index, len);
+        })
+    }
+
     pub fn column_blob(&mut self, index: i32) -> Result<&[u8]> {
         let index = index as c_int;
         let pointer = unsafe { sqlite3_column_blob(self.current_statement(), index) };
@@ -200,7 +316,13 @@ impl<'a> Statement<'a> {
         Ok(result)
     }
 
-    pub fn bind_null(&self, index: i32) -> Result<()> {
+    /// Binds NULL at `index`, returning the statement so further bindings can be chained.
+    pub fn bind_null(&mut self, index: i32) -> Result<&mut Self> {
+        self.bind_null_at(index)?;
+        Ok(self)
+    }
+
+    pub(crate) fn bind_null_at(&self, index: i32) -> Result<()> {
         let index = index as c_int;
         self.bind_index_with(index, |raw_statement| unsafe {
             sqlite3_bind_null(*raw_statement, index);
@@ -218,6 +340,18 @@ impl<'a> Statement<'a> {
     }
 
     pub fn column_text(&mut self, index: i32) -> Result<&str> {
+        let bytes = self.column_text_bytes(index)?;
+        str::from_utf8(bytes).with_context(|| format!("Text in column {index} is not valid UTF-8"))
+    }
+
+    /// Reads text like `column_text`, but replaces invalid UTF-8 sequences with
+    /// `U+FFFD REPLACEMENT CHARACTER` rather than returning an error.
+    pub fn column_text_lossy(&mut self, index: i32) -> Result<Cow<'_, str>> {
+        let bytes = self.column_text_bytes(index)?;
+        Ok(String::from_utf8_lossy(bytes))
+    }
+
+    fn column_text_bytes(&mut self, index: i32) -> Result<&[u8]> {
         let index = index as c_int;
         let pointer = unsafe { sqlite3_column_text(self.current_statement(), index) };
 
@@ -225,15 +359,14 @@ impl<'a> Statement<'a> {
             .last_error()
             .with_context(|| format!("Failed to read text from column {index}"))?;
         if pointer.is_null() {
-            return Ok("");
+            return Ok(&[]);
         }
         let len = unsafe { sqlite3_column_bytes(self.current_statement(), index) as usize };
         self.connection
             .last_error()
             .with_context(|| format!("Failed to read text length at {index}"))?;
 
-        let slice = unsafe { slice::from_raw_parts(pointer, len) };
-        Ok(str::from_utf8(slice)?)
+        Ok(unsafe { slice::from_raw_parts(pointer, len) })
     }
 
     pub fn bind<T: Bind>(&self, value: &T, index: i32) -> Result<i32> {
@@ -258,16 +391,94 @@ impl<'a> Statement<'a> {
         }
     }
 
+    /// The number of columns in each row returned by the current statement.
+    pub fn column_count(&self) -> i32 {
+        unsafe { sqlite3_column_count(self.current_statement()) }
+    }
+
+    /// The name of a result column, which is its alias if the query gives it one.
+    pub fn column_name(&self, index: i32) -> Result<&str> {
+        let pointer = unsafe { sqlite3_column_name(self.current_statement(), index) };
+        if pointer.is_null() {
+            return Err(anyhow!("Column {index} is out of range"));
+        }
+        unsafe { CStr::from_ptr(pointer) }
+            .to_str()
+            .with_context(|| format!("Name of column {index} is not valid UTF-8"))
+    }
+
     pub fn with_bindings(&mut self, bindings: &impl Bind) -> Result<&mut Self> {
         self.bind(bindings, 1)?;
         Ok(self)
     }
 
+    /// Binds `value` to the parameter called `name`, which may be written with or without
+    /// its `:`, `@` or `$` prefix. Errors if the statement has no such parameter.
+    pub fn bind_named<T: Bind + ?Sized>(&self, name: &str, value: &T) -> Result<()> {
+        // Each statement numbers its own parameters, so the value is bound in only the
+        // statements which use the parameter, at the index it has in each of them
+        for (statement_ix, index) in self.parameter_indices(name)? {
+            self.bind_target.set(Some(statement_ix));
+            let result = value.bind(self, index);
+            self.bind_target.set(None);
+            result.with_context(|| format!("Failed to bind parameter {name}"))?;
+        }
+        Ok(())
+    }
+
+    /// Binds each value to its named parameter, like `bind_named`.
+    pub fn with_named_bindings(&mut self, bindings: &[(&str, &dyn Bind)]) -> Result<&mut Self> {
+        for (name, value) in bindings {
+            self.bind_named(name, *value)?;
+        }
+        Ok(self)
+    }
+
+    /// The index of the parameter called `name` in each statement which uses it.
+    fn parameter_indices(&self, name: &str) -> Result<Vec<(usize, i32)>> {
+        let names = if name.starts_with([':', '@', '$']) {
+            vec![name.to_string()]
+        } else {
+            [':', '@', '$']
+                .iter()
+                .map(|prefix| format!("{prefix}{name}"))
+                .collect()
+        };
+
+        let mut indices = Vec::new();
+        for name in names {
+            let name = CString::new(name)?;
+            for (statement_ix, raw_statement) in self.raw_statements.iter().enumerate() {
+                let index = unsafe { sqlite3_bind_parameter_index(*raw_statement, name.as_ptr()) };
+                if index != 0 {
+                    indices.push((statement_ix, index));
+                }
+            }
+        }
+        if indices.is_empty() {
+            bail!("No parameter named {name}");
+        }
+        Ok(indices)
+    }
+
     fn step(&mut self) -> Result<StepResult> {
         unsafe {
-            match sqlite3_step(self.current_statement()) {
+            let raw_statement = self.current_statement();
+            // Log each statement when it starts running rather than on every row
+            if sqlite3_stmt_busy(raw_statement) == 0 {
+                let sql = CStr::from_ptr(sqlite3_sql(raw_statement));
+                self.connection.log_statement(&sql.to_string_lossy());
+                self.total_changes_at_start = self.connection.total_changes();
+            }
+
+            match sqlite3_step(raw_statement) {
                 SQLITE_ROW => Ok(StepResult::Row),
                 SQLITE_DONE => {
+                    // sqlite only updates the change count for inserts, updates and
+                    // deletes, so other statements would report the count of an earlier one
+                    if self.connection.total_changes() != self.total_changes_at_start {
+                        self.changes += self.connection.changes() as usize;
+                    }
                     if self.current_statement >= self.raw_statements.len() - 1 {
                         Ok(StepResult::Done)
                     } else {
@@ -275,22 +486,29 @@ impl<'a> Statement<'a> {
                         self.step()
                     }
                 }
-                SQLITE_MISUSE => Err(anyhow!("Statement step returned SQLITE_MISUSE")),
-                _other_error => {
-                    self.connection.last_error()?;
-                    unreachable!("Step returned error code and last error failed to catch it");
+                code => {
+                    let error = self.connection.error_for_code(code);
+                    let sql = CStr::from_ptr(sqlite3_sql(raw_statement)).to_string_lossy();
+                    let offset = match sqlite3_error_offset(self.connection.sqlite3) {
+                        -1 => String::new(),
+                        offset => format!(" at offset {}", offset),
+                    };
+                    Err(anyhow::Error::from(error)
+                        .context(format!("Failed to run statement{}:\n{}", offset, sql)))
                 }
             }
         }
     }
 
-    pub fn exec(&mut self) -> Result<()> {
-        fn logic(this: &mut Statement) -> Result<()> {
+    /// Runs the statement to completion, returning the number of rows inserted, updated or
+    /// deleted by it. Changes made by triggers and foreign key actions aren't counted.
+    pub fn exec(&mut self) -> Result<usize> {
+        fn logic(this: &mut Statement) -> Result<usize> {
             while this.step()? == StepResult::Row {}
-            Ok(())
+            Ok(this.changes)
         }
         let result = logic(self);
-        self.reset();
+        self.rewind();
         result
     }
 
@@ -307,7 +525,7 @@ impl<'a> Statement<'a> {
         }
 
         let result = logic(self, callback);
-        self.reset();
+        self.rewind();
         result
     }
 
@@ -315,6 +533,37 @@ impl<'a> Statement<'a> {
         self.map(|s| s.column::<R>())
     }
 
+    /// Reads the statement's rows one at a time, stepping the statement as the iterator is
+    /// advanced rather than collecting every row up front.
+    pub fn iter<R: Column>(&mut self) -> Rows<'_, 'a, R> {
+        Rows {
+            statement: self,
+            done: false,
+            _row: PhantomData,
+        }
+    }
+
+    /// Calls `callback` with the statement positioned on each row in turn, stopping at the
+    /// first error.
+    pub fn for_each_row(
+        &mut self,
+        mut callback: impl FnMut(&mut Statement) -> Result<()>,
+    ) -> Result<()> {
+        fn logic(
+            this: &mut Statement,
+            callback: &mut impl FnMut(&mut Statement) -> Result<()>,
+        ) -> Result<()> {
+            while this.step()? == StepResult::Row {
+                callback(this)?;
+            }
+            Ok(())
+        }
+
+        let result = logic(self, &mut callback);
+        self.rewind();
+        result
+    }
+
     pub fn single<R>(&mut self, callback: impl FnOnce(&mut Statement) -> Result<R>) -> Result<R> {
         fn logic<R>(
             this: &mut Statement,
@@ -334,7 +583,7 @@ impl<'a> Statement<'a> {
             Ok(result)
         }
         let result = logic(self, callback);
-        self.reset();
+        self.rewind();
         result
     }
 
@@ -367,17 +616,63 @@ impl<'a> Statement<'a> {
             Ok(result)
         }
         let result = logic(self, callback);
-        self.reset();
+        self.rewind();
         result
     }
 
+    /// Reads the single row returned by the statement, or `None` if it returned no rows.
+    /// Errors if the statement fails or returns more than one row.
     pub fn maybe_row<R: Column>(&mut self) -> Result<Option<R>> {
         self.maybe(|this| this.column::<R>())
     }
 }
 
+/// The rows of a statement, from `Statement::iter`. Once the iterator is exhausted, fails
+/// or is dropped, the statement is reset so that it can be run again.
+pub struct Rows<'s, 'a, R> {
+    statement: &'s mut Statement<'a>,
+    done: bool,
+    _row: PhantomData<R>,
+}
+
+impl<'s, 'a, R: Column> Iterator for Rows<'s, 'a, R> {
+    type Item = Result<R>;
+
+    fn next(&mut self) -> Option<Result<R>> {
+        if self.done {
+            return None;
+        }
+
+        let row = match self.statement.step() {
+            Ok(StepResult::Row) => self.statement.column::<R>(),
+            Ok(StepResult::Done) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => Err(error),
+        };
+        self.done = row.is_err();
+        Some(row)
+    }
+}
+
+impl<'s, 'a, R> Drop for Rows<'s, 'a, R> {
+    fn drop(&mut self) {
+        self.statement.rewind();
+    }
+}
+
 impl<'a> Drop for Statement<'a> {
     fn drop(&mut self) {
+        if let Some(cache_key) = self.cache_key.take() {
+            self.reset().ok();
+            self.connection
+                .statement_cache
+                .borrow_mut()
+                .insert(cache_key, std::mem::take(&mut self.raw_statements));
+            return;
+        }
+
         unsafe {
             for raw_statement in self.raw_statements.iter() {
                 sqlite3_finalize(*raw_statement);
@@ -386,13 +681,87 @@ impl<'a> Drop for Statement<'a> {
     }
 }
 
+/// Counts the parameters which a statement prepared from `sql` would take, numbering them
+/// the way sqlite does, but without preparing it, so that queries against tables which don't
+/// exist yet can be checked. Like `Statement::parameter_count`, this is the largest count of
+/// the statements in `sql`.
+pub fn sql_parameter_count(sql: &str) -> usize {
+    let is_identifier = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80;
+    let bytes = sql.as_bytes();
+    let skip_to = |from: usize, end: &[u8]| {
+        bytes[from..]
+            .windows(end.len())
+            .position(|window| window == end)
+            .map_or(bytes.len(), |position| from + position + end.len())
+    };
+
+    let mut max_count = 0;
+    let mut count = 0;
+    let mut names = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index = match byte {
+            b'\'' | b'"' | b'`' => skip_to(index + 1, &[byte]),
+            b'[' => skip_to(index + 1, b"]"),
+            b'-' if bytes.get(index + 1) == Some(&b'-') => skip_to(index + 2, b"\n"),
+            b'/' if bytes.get(index + 1) == Some(&b'*') => skip_to(index + 2, b"*/"),
+            b';' => {
+                max_count = max_count.max(count);
+                count = 0;
+                names.clear();
+                index + 1
+            }
+            b'?' => {
+                let digits = bytes[index + 1..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_digit())
+                    .count();
+                match sql[index + 1..index + 1 + digits].parse::<usize>() {
+                    Ok(number) => count = count.max(number),
+                    Err(_) => count += 1,
+                }
+                index + 1 + digits
+            }
+            b':' | b'@' | b'$' => {
+                let length = bytes[index + 1..]
+                    .iter()
+                    .take_while(|byte| is_identifier(**byte))
+                    .count();
+                // The prefix is part of the name, so `:a` and `$a` are different parameters
+                let name = &sql[index..index + 1 + length];
+                if length > 0 && !names.contains(&name) {
+                    names.push(name);
+                    count += 1;
+                }
+                index + 1 + length
+            }
+            // Skipped as a whole, as identifiers may contain `$`
+            byte if is_identifier(byte) => {
+                index
+                    + bytes[index..]
+                        .iter()
+                        .take_while(|byte| is_identifier(**byte) || **byte == b'$')
+                        .count()
+            }
+            _ => index + 1,
+        };
+    }
+    max_count.max(count)
+}
+
 #[cfg(test)]
 mod test {
+    use std::time::{Duration, SystemTime};
+
+    use anyhow::Result;
     use indoc::indoc;
 
     use crate::{
+        bindable::{LossyString, Null, UuidText},
         connection::Connection,
-        statement::{Statement, StepResult},
+        error::Error,
+        statement::{sql_parameter_count, Statement, StepResult},
     };
 
     #[test]
@@ -458,6 +827,42 @@ mod test {
         assert_eq!(read.step().unwrap(), StepResult::Done);
     }
 
+    #[test]
+    fn fixed_size_blob_round_trips() {
+        let connection = Connection::open_memory(Some("fixed_size_blob_round_trips"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE blobs (
+                    id INTEGER PRIMARY KEY,
+                    data BLOB
+                )"})
+            .unwrap()()
+        .unwrap();
+
+        let uuid: [u8; 16] = std::array::from_fn(|ix| ix as u8);
+        connection
+            .exec_bound::<(i32, [u8; 16])>("INSERT INTO blobs (id, data) VALUES (?, ?)")
+            .unwrap()((1, uuid))
+        .unwrap();
+        assert_eq!(
+            connection
+                .select_row::<[u8; 16]>("SELECT data FROM blobs WHERE id = 1")
+                .unwrap()()
+            .unwrap(),
+            Some(uuid)
+        );
+
+        // Blobs of the wrong length are an error rather than being truncated or padded
+        connection
+            .exec_bound::<(i32, &[u8])>("INSERT INTO blobs (id, data) VALUES (?, ?)")
+            .unwrap()((2, &uuid[..15]))
+        .unwrap();
+        assert!(connection
+            .select_row::<[u8; 16]>("SELECT data FROM blobs WHERE id = 2")
+            .unwrap()()
+        .is_err());
+    }
+
     #[test]
     pub fn maybe_returns_options() {
         let connection = Connection::open_memory(Some("maybe_returns_options"));
@@ -487,4 +892,481 @@ mod test {
             Some(text_to_insert.to_string())
         );
     }
+    #[test]
+    fn maybe_row_by_rowid() {
+        let connection = Connection::open_memory(Some("maybe_row_by_rowid"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE texts (
+                    text TEXT
+                )"})
+            .unwrap()()
+        .unwrap();
+        connection
+            .exec_bound("INSERT INTO texts VALUES (?)")
+            .unwrap()("first")
+        .unwrap();
+
+        let mut statement =
+            Statement::prepare(&connection, "SELECT text FROM texts WHERE rowid = ?").unwrap();
+        assert_eq!(
+            statement
+                .with_bindings(&2)
+                .unwrap()
+                .maybe_row::<String>()
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            statement
+                .with_bindings(&1)
+                .unwrap()
+                .maybe_row::<String>()
+                .unwrap(),
+            Some("first".to_string())
+        );
+    }
+
+    #[test]
+    fn uuids_round_trip() {
+        let connection = Connection::open_memory(Some("uuids_round_trip"));
+        connection
+            .exec("CREATE TABLE test (id BLOB, name TEXT)")
+            .unwrap()()
+        .unwrap();
+
+        let id = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        connection
+            .exec_bound::<(uuid::Uuid, UuidText)>("INSERT INTO test VALUES (?, ?)")
+            .unwrap()((id, UuidText(id)))
+        .unwrap();
+        assert_eq!(
+            connection
+                .select_row::<(u64, String)>("SELECT length(id), name FROM test")
+                .unwrap()()
+            .unwrap(),
+            Some((16, "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string()))
+        );
+        assert_eq!(
+            connection
+                .select_row::<(uuid::Uuid, UuidText)>("SELECT id, name FROM test")
+                .unwrap()()
+            .unwrap(),
+            Some((id, UuidText(id)))
+        );
+
+        // Uuids stored in the wrong form are errors rather than garbage values
+        assert!(connection
+            .select_row::<uuid::Uuid>("SELECT name FROM test")
+            .unwrap()()
+        .is_err());
+        assert!(connection
+            .select_row::<UuidText>("SELECT 'not a uuid'")
+            .unwrap()()
+        .is_err());
+    }
+
+    #[test]
+    fn times_round_trip() {
+        let connection = Connection::open_memory(Some("times_round_trip"));
+        connection
+            .exec("CREATE TABLE test (opened INTEGER, duration INTEGER)")
+            .unwrap()()
+        .unwrap();
+
+        let times = [
+            SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+            SystemTime::UNIX_EPOCH - Duration::new(86_400, 1),
+        ];
+        for (ix, time) in times.iter().enumerate() {
+            connection
+                .exec_bound::<(SystemTime, Duration)>("INSERT INTO test VALUES (?, ?)")
+                .unwrap()((*time, Duration::from_millis(ix as u64 * 1500)))
+            .unwrap();
+        }
+        assert_eq!(
+            connection
+                .select::<(SystemTime, Duration)>("SELECT opened, duration FROM test")
+                .unwrap()()
+            .unwrap(),
+            vec![
+                (times[0], Duration::ZERO),
+                (times[1], Duration::from_millis(1500))
+            ]
+        );
+
+        // Values are stored as plain nanoseconds, so they can be compared in SQL
+        assert_eq!(
+            connection
+                .select_row::<i64>("SELECT opened FROM test WHERE duration = 0")
+                .unwrap()()
+            .unwrap(),
+            Some(1_700_000_000_123_456_789)
+        );
+        assert!(connection
+            .exec_bound::<Duration>("INSERT INTO test (duration) VALUES (?)")
+            .unwrap()(Duration::MAX)
+        .is_err());
+        connection
+            .exec("INSERT INTO test (duration) VALUES (-1)")
+            .unwrap()()
+        .unwrap();
+        assert!(connection
+            .select::<Duration>("SELECT duration FROM test")
+            .unwrap()()
+        .is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_times_round_trip() {
+        use chrono::{DateTime, TimeZone, Utc};
+
+        let connection = Connection::open_memory(Some("chrono_times_round_trip"));
+        let time = Utc.timestamp_nanos(1_700_000_000_123_456_789);
+        assert_eq!(
+            connection
+                .select_row_bound::<DateTime<Utc>, DateTime<Utc>>("SELECT ?")
+                .unwrap()(time)
+            .unwrap(),
+            Some(time)
+        );
+    }
+
+    #[test]
+    fn binding_explicit_nulls() {
+        let connection = Connection::open_memory(Some("binding_explicit_nulls"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE test (
+                    text TEXT,
+                    sentinel INTEGER,
+                    data BLOB
+                )"})
+            .unwrap()()
+        .unwrap();
+
+        let mut insert =
+            Statement::prepare(&connection, "INSERT INTO test VALUES (?, ?, ?)").unwrap();
+        insert
+            .with_bindings(&("text", Null, &[1u8, 2, 3][..]))
+            .unwrap()
+            .exec()
+            .unwrap();
+        insert.bind_text(1, "chained").unwrap();
+        insert
+            .bind_null(2)
+            .unwrap()
+            .bind_null(3)
+            .unwrap()
+            .exec()
+            .unwrap();
+        assert!(insert.bind_null(0).is_err());
+        assert!(insert.bind_null(4).is_err());
+
+        assert_eq!(
+            connection
+                .select_row::<usize>("SELECT COUNT(*) FROM test WHERE sentinel IS NULL")
+                .unwrap()()
+            .unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            connection
+                .select::<(String, Option<i32>, Option<Vec<u8>>)>(
+                    "SELECT * FROM test ORDER BY rowid"
+                )
+                .unwrap()()
+            .unwrap(),
+            vec![
+                ("text".to_string(), None, Some(vec![1, 2, 3])),
+                ("chained".to_string(), None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn reset_clears_bindings() {
+        let connection = Connection::open_memory(Some("reset_clears_bindings"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE test (
+                    col INTEGER
+                )"})
+            .unwrap()()
+        .unwrap();
+        connection
+            .exec("INSERT INTO test(col) VALUES (1), (2), (3), (4)")
+            .unwrap()()
+        .unwrap();
+
+        let mut statement = Statement::prepare(
+            &connection,
+            "SELECT col FROM test WHERE col >= ?1 AND col <= ?2",
+        )
+        .unwrap();
+        statement.with_bindings(&(1, 3)).unwrap();
+        assert_eq!(statement.step().unwrap(), StepResult::Row);
+        assert_eq!(statement.column::<i32>().unwrap(), 1);
+
+        // Only the first parameter is rebound, so the second must have been cleared to NULL
+        statement.reset().unwrap().bind(&2, 1).unwrap();
+        assert_eq!(statement.rows::<i32>().unwrap(), Vec::<i32>::new());
+
+        statement.reset().unwrap().with_bindings(&(2, 4)).unwrap();
+        assert_eq!(statement.rows::<i32>().unwrap(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn invalid_utf8_text_is_an_error() {
+        let connection = Connection::open_memory(Some("invalid_utf8_text_is_an_error"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE texts (
+                    text TEXT
+                )"})
+            .unwrap()()
+        .unwrap();
+        connection
+            .exec_bound::<&[u8]>("INSERT INTO texts VALUES (CAST(? AS TEXT))")
+            .unwrap()(&[b'a', 0xff, b'b'])
+        .unwrap();
+
+        assert!(connection
+            .select_row::<String>("SELECT text FROM texts")
+            .unwrap()()
+        .is_err());
+        assert_eq!(
+            connection
+                .select_row::<LossyString>("SELECT text FROM texts")
+                .unwrap()()
+            .unwrap(),
+            Some(LossyString("a\u{fffd}b".to_string()))
+        );
+    }
+
+    #[test]
+    fn binding_named_parameters() {
+        let connection = Connection::open_memory(Some("binding_named_parameters"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE test (
+                    id INTEGER,
+                    name TEXT,
+                    value REAL
+                )"})
+            .unwrap()()
+        .unwrap();
+
+        let mut insert = Statement::prepare(
+            &connection,
+            "INSERT INTO test (id, name, value) VALUES (:id, @name, $value)",
+        )
+        .unwrap();
+        insert
+            .with_named_bindings(&[("name", &"first"), ("$value", &2.5), (":id", &1)])
+            .unwrap()
+            .exec()
+            .unwrap();
+        assert!(insert.bind_named("missing", &1).is_err());
+        assert!(insert.bind_named(":name", &1).is_err());
+
+        let mut select =
+            Statement::prepare(&connection, "SELECT * FROM test WHERE id = :id").unwrap();
+        select.bind_named("id", &1).unwrap();
+        assert_eq!(
+            select.maybe_row::<(i32, String, f64)>().unwrap(),
+            Some((1, "first".to_string(), 2.5))
+        );
+    }
+
+    #[test]
+    fn binding_named_parameters_across_statements() {
+        let connection =
+            Connection::open_memory(Some("binding_named_parameters_across_statements"));
+        connection
+            .exec("CREATE TABLE test (id INTEGER, name TEXT)")
+            .unwrap()()
+        .unwrap();
+
+        // Both parameters are at index 1 of their statement, so binding one by name must
+        // leave the other alone
+        let mut statement = Statement::prepare(
+            &connection,
+            "INSERT INTO test (id, name) VALUES (1, :name); SELECT name FROM test WHERE id = :id",
+        )
+        .unwrap();
+        statement
+            .with_named_bindings(&[("id", &1), ("name", &"first")])
+            .unwrap();
+        assert_eq!(
+            statement.maybe_row::<String>().unwrap(),
+            Some("first".to_string())
+        );
+
+        // A parameter used by several statements is bound at its index in each of them
+        let mut statement = Statement::prepare(
+            &connection,
+            "INSERT INTO test (id, name) VALUES (2, :name); SELECT id FROM test WHERE id > :id AND name = :name",
+        )
+        .unwrap();
+        statement
+            .with_named_bindings(&[("name", &"second"), ("id", &1)])
+            .unwrap();
+        assert_eq!(statement.maybe_row::<i32>().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn optional_values_round_trip() {
+        let connection = Connection::open_memory(Some("optional_values_round_trip"));
+        connection
+            .exec(indoc! {"
+                CREATE TABLE test (
+                    id INTEGER,
+                    name TEXT,
+                    value INTEGER
+                )"})
+            .unwrap()()
+        .unwrap();
+
+        let mut insert = connection
+            .exec_bound::<(i32, Option<String>, Option<i32>)>("INSERT INTO test VALUES (?, ?, ?)")
+            .unwrap();
+        insert((1, Some("named".to_string()), None)).unwrap();
+        insert((2, None, Some(3))).unwrap();
+
+        assert_eq!(
+            connection
+                .select::<(i32, Option<String>, Option<i32>)>("SELECT * FROM test ORDER BY id")
+                .unwrap()()
+            .unwrap(),
+            vec![(1, Some("named".to_string()), None), (2, None, Some(3))]
+        );
+        assert_eq!(
+            connection
+                .select_row::<Option<(String, i32)>>("SELECT name, value FROM test WHERE id = 2")
+                .unwrap()()
+            .unwrap(),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn iterating_rows_steps_lazily() {
+        let connection = Connection::open_memory(Some("iterating_rows_steps_lazily"));
+        connection.exec("CREATE TABLE test (col INTEGER)").unwrap()().unwrap();
+        connection
+            .exec("INSERT INTO test(col) VALUES (1), (2), (3)")
+            .unwrap()()
+        .unwrap();
+
+        let mut statement =
+            Statement::prepare(&connection, "SELECT col FROM test ORDER BY col").unwrap();
+        let mut rows = statement.iter::<i32>();
+        assert_eq!(rows.next().unwrap().unwrap(), 1);
+        drop(rows);
+
+        // The statement starts over after a partial read
+        assert_eq!(
+            statement.iter::<i32>().collect::<Result<Vec<_>>>().unwrap(),
+            vec![1, 2, 3]
+        );
+
+        let mut sum = 0;
+        statement
+            .for_each_row(|row| {
+                sum += row.column::<i32>()?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(sum, 6);
+
+        let mut visited = 0;
+        assert!(statement
+            .for_each_row(|_| {
+                visited += 1;
+                anyhow::bail!("stop")
+            })
+            .is_err());
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn errors_describe_the_failing_sql() {
+        let connection = Connection::open_memory(Some("errors_describe_the_failing_sql"));
+        connection
+            .exec("CREATE TABLE test (id INTEGER PRIMARY KEY, value INTEGER NOT NULL)")
+            .unwrap()()
+        .unwrap();
+
+        let query = "SELECT 1; SELECT * FROM test WHERE missing_column = 1";
+        let error = Statement::prepare(&connection, query).err().unwrap();
+        let message = format!("{:#}", error);
+        assert!(message.contains("no such column: missing_column"));
+        assert!(message.contains(&format!(
+            "at offset {}",
+            query.find("missing_column").unwrap()
+        )));
+        assert!(message.contains(query));
+
+        let error = connection.exec("INSERT INTO test (id) VALUES (1)").unwrap()().unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("NOT NULL constraint failed: test.value"));
+        assert!(message.contains("INSERT INTO test (id) VALUES (1)"));
+        assert!(error.downcast_ref::<Error>().is_some());
+    }
+
+    #[test]
+    fn exec_returns_changed_rows() -> Result<()> {
+        let connection = Connection::open_memory(Some("exec_returns_changed_rows"));
+        connection.exec("CREATE TABLE test (value INTEGER); CREATE TABLE log (value INTEGER)")?()?;
+        connection.exec(indoc! {"
+            CREATE TRIGGER logged AFTER UPDATE ON test BEGIN
+                INSERT INTO log VALUES (new.value);
+            END"})?()?;
+
+        let mut insert = Statement::prepare(&connection, "INSERT INTO test VALUES (1), (2)")?;
+        assert_eq!(insert.exec()?, 2);
+        let total_changes = connection.total_changes();
+
+        let mut update = Statement::prepare(&connection, "UPDATE test SET value = value + 1")?;
+        assert_eq!(update.exec()?, 2);
+        let mut update = Statement::prepare(&connection, "UPDATE test SET value = 0 WHERE 0")?;
+        assert_eq!(update.exec()?, 0);
+        // Rows changed by the trigger count towards the total
+        assert_eq!(connection.total_changes(), total_changes + 4);
+
+        // Statements which don't change rows don't repeat the count of earlier ones
+        let mut statements = Statement::prepare(
+            &connection,
+            "DELETE FROM test WHERE value = 2; CREATE TABLE other (value); SELECT 1",
+        )?;
+        assert_eq!(statements.exec()?, 1);
+        let mut select = Statement::prepare(&connection, "SELECT * FROM test")?;
+        assert_eq!(select.exec()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn sql_parameter_count_matches_prepared_statements() -> Result<()> {
+        let connection = Connection::open_memory(Some("sql_parameter_count"));
+        connection.exec("CREATE TABLE test (a, b, \"c?\", d$)")?()?;
+
+        for sql in [
+            "SELECT * FROM test",
+            "SELECT * FROM test WHERE a = ? AND b = ?",
+            "SELECT * FROM test WHERE a = ?3 AND b = ?",
+            "SELECT * FROM test WHERE a = :a AND b = @b AND d$ = $a AND a = :a",
+            "SELECT * FROM test WHERE a = '?' AND \"c?\" = ? -- ?\n AND b = /* ? */ ?",
+            "INSERT INTO test (a) VALUES (?); UPDATE test SET a = ?, b = ?",
+        ] {
+            let statement = Statement::prepare(&connection, sql)?;
+            assert_eq!(
+                sql_parameter_count(sql),
+                statement.parameter_count() as usize,
+                "{sql}"
+            );
+        }
+        Ok(())
+    }
 }
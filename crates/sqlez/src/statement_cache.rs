@@ -0,0 +1,266 @@
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::Result;
+use libsqlite3_sys::*;
+
+use crate::{connection::Connection, statement::Statement};
+
+pub(crate) const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 32;
+
+/// A least recently used cache of prepared statements, keyed by their SQL text. Statements
+/// are checked out of the cache while in use and returned to it when they are dropped.
+pub(crate) struct StatementCache {
+    capacity: usize,
+    // Most recently used statements are at the front
+    entries: VecDeque<(String, Vec<*mut sqlite3_stmt>)>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// How effective `Connection::prepare_cached` has been, for tuning the cache's capacity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Calls which reused a cached statement.
+    pub hits: u64,
+    /// Calls which had to prepare their statement.
+    pub misses: u64,
+    /// Statements which were finalized to keep the cache within its capacity.
+    pub evictions: u64,
+    /// The number of distinct statements currently in the cache.
+    pub size: usize,
+}
+
+impl StatementCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn take(&mut self, query: &str) -> Option<Vec<*mut sqlite3_stmt>> {
+        let Some(ix) = self.entries.iter().position(|(sql, _)| sql == query) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        self.entries
+            .remove(ix)
+            .map(|(_, raw_statements)| raw_statements)
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            size: self.entries.len(),
+        }
+    }
+
+    /// Returns statements to the cache, finalizing the least recently used entries if
+    /// that takes the cache over its capacity.
+    pub(crate) fn insert(&mut self, query: String, raw_statements: Vec<*mut sqlite3_stmt>) {
+        if let Some(ix) = self.entries.iter().position(|(sql, _)| *sql == query) {
+            // Another copy of this statement was returned first, so this one is redundant
+            if let Some((_, redundant)) = self.entries.remove(ix) {
+                finalize(&redundant);
+            }
+        }
+        self.entries.push_front((query, raw_statements));
+        self.evict_to(self.capacity);
+    }
+
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict_to(capacity);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for (_, raw_statements) in self.entries.drain(..) {
+            finalize(&raw_statements);
+        }
+    }
+
+    fn evict_to(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            if let Some((_, raw_statements)) = self.entries.pop_back() {
+                finalize(&raw_statements);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Drop for StatementCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn finalize(raw_statements: &[*mut sqlite3_stmt]) {
+    unsafe {
+        for raw_statement in raw_statements {
+            sqlite3_finalize(*raw_statement);
+        }
+    }
+}
+
+impl Connection {
+    /// Prepares `query`, reusing a previously compiled statement for the same SQL when
+    /// one is cached. The statement is returned to the cache when it is dropped.
+    pub fn prepare_cached(&self, query: &str) -> Result<Statement<'_>> {
+        let cached = self.statement_cache.borrow_mut().take(query);
+        match cached {
+            Some(raw_statements) => Statement::from_cached(self, query, raw_statements),
+            None => Statement::prepare(self, query).map(|statement| statement.cached(query)),
+        }
+    }
+
+    /// Sets how many distinct statements `prepare_cached` keeps around, finalizing the
+    /// least recently used ones if there are already more than that.
+    pub fn set_statement_cache_capacity(&self, capacity: usize) {
+        self.statement_cache.borrow_mut().set_capacity(capacity);
+    }
+
+    /// Returns the statement cache's hit, miss and eviction counts since the connection was
+    /// opened, along with its current size.
+    pub fn statement_cache_stats(&self) -> CacheStats {
+        self.statement_cache.borrow().stats()
+    }
+
+    /// Finalizes all of the statements held by the statement cache.
+    pub fn clear_statement_cache(&self) {
+        self.statement_cache.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use libsqlite3_sys::sqlite3_next_stmt;
+
+    use crate::{connection::Connection, statement_cache::CacheStats};
+
+    fn open_statement_count(connection: &Connection) -> usize {
+        let mut count = 0;
+        unsafe {
+            let mut raw_statement = sqlite3_next_stmt(connection.sqlite3, ptr::null_mut());
+            while !raw_statement.is_null() {
+                count += 1;
+                raw_statement = sqlite3_next_stmt(connection.sqlite3, raw_statement);
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_cached_statements_are_reused() {
+        let connection = Connection::open_memory(Some("test_cached_statements_are_reused"));
+        connection
+            .exec("CREATE TABLE test (value INTEGER)")
+            .unwrap()()
+        .unwrap();
+
+        for value in 0..3 {
+            let mut insert = connection
+                .prepare_cached("INSERT INTO test (value) VALUES (?)")
+                .unwrap();
+            insert.bind(&value, 1).unwrap();
+            insert.exec().unwrap();
+        }
+        assert_eq!(open_statement_count(&connection), 1);
+
+        assert_eq!(
+            connection.select::<i32>("SELECT value FROM test").unwrap()().unwrap(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_bound_statements_are_cached() {
+        let connection = Connection::open_memory(Some("test_bound_statements_are_cached"));
+        connection
+            .exec("CREATE TABLE test (value INTEGER)")
+            .unwrap()()
+        .unwrap();
+
+        for value in 0..3 {
+            connection
+                .exec_bound::<i32>("INSERT INTO test (value) VALUES (?)")
+                .unwrap()(value)
+            .unwrap();
+            connection
+                .select_row_bound::<i32, i32>("SELECT value FROM test WHERE value = ?")
+                .unwrap()(value)
+            .unwrap();
+        }
+        assert_eq!(open_statement_count(&connection), 2);
+        assert_eq!(connection.statement_cache_stats().hits, 4);
+    }
+
+    #[test]
+    fn test_statement_cache_stats() {
+        let connection = Connection::open_memory(Some("test_statement_cache_stats"));
+        connection.set_statement_cache_capacity(1);
+
+        for query in ["SELECT 1", "SELECT 1", "SELECT 2"] {
+            connection
+                .prepare_cached(query)
+                .unwrap()
+                .maybe_row::<i32>()
+                .unwrap();
+        }
+        assert_eq!(
+            connection.statement_cache_stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 1,
+                size: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_evicted_statements_are_finalized() {
+        let connection = Connection::open_memory(Some("test_evicted_statements_are_finalized"));
+        connection.set_statement_cache_capacity(2);
+
+        for value in 0..5 {
+            connection
+                .prepare_cached(&format!("SELECT {}", value))
+                .unwrap()
+                .maybe_row::<i32>()
+                .unwrap();
+        }
+        assert_eq!(open_statement_count(&connection), 2);
+
+        // Statements which are checked out of the cache are not evicted
+        let statement = connection.prepare_cached("SELECT 3").unwrap();
+        connection.set_statement_cache_capacity(0);
+        assert_eq!(open_statement_count(&connection), 1);
+        drop(statement);
+        assert_eq!(open_statement_count(&connection), 0);
+
+        connection.set_statement_cache_capacity(4);
+        for value in 0..3 {
+            connection
+                .prepare_cached(&format!("SELECT {}", value))
+                .unwrap()
+                .maybe_row::<i32>()
+                .unwrap();
+        }
+        assert_eq!(open_statement_count(&connection), 3);
+        connection.clear_statement_cache();
+        assert_eq!(open_statement_count(&connection), 0);
+    }
+}
@@ -5,7 +5,9 @@ use parking_lot::{Mutex, RwLock};
 use std::{collections::HashMap, marker::PhantomData, ops::Deref, sync::Arc, thread};
 use thread_local::ThreadLocal;
 
-use crate::{connection::Connection, domain::Migrator, util::UnboundedSyncSender};
+use crate::{
+    connection::Connection, domain::Migrator, encryption::KeyProvider, util::UnboundedSyncSender,
+};
 
 const MIGRATION_RETRIES: usize = 10;
 
@@ -28,6 +30,7 @@ pub struct ThreadSafeConnection<M: Migrator + 'static = ()> {
     uri: Arc<str>,
     persistent: bool,
     connection_initialize_query: Option<&'static str>,
+    key_provider: Option<KeyProvider>,
     connections: Arc<ThreadLocal<Connection>>,
     _migrator: PhantomData<*mut M>,
 }
@@ -57,6 +60,13 @@ impl<M: Migrator> ThreadSafeConnectionBuilder<M> {
         self
     }
 
+    /// Opens the database file encrypted, with the key from `key_provider`, see
+    /// `Connection::open_encrypted`.
+    pub fn with_key_provider(mut self, key_provider: KeyProvider) -> Self {
+        self.connection.key_provider = Some(key_provider);
+        self
+    }
+
     /// Specifies how the thread safe connection should serialize writes. If provided
     /// the connection will call the write_queue_constructor for each database file in
     /// this process. The constructor is responsible for setting up a background thread or
@@ -130,6 +140,7 @@ impl<M: Migrator> ThreadSafeConnection<M> {
                 uri: Arc::from(uri),
                 persistent,
                 connection_initialize_query: None,
+                key_provider: None,
                 connections: Default::default(),
                 _migrator: PhantomData,
             },
@@ -138,8 +149,8 @@ impl<M: Migrator> ThreadSafeConnection<M> {
 
     /// Opens a new db connection with the initialized file path. This is internal and only
     /// called from the deref function.
-    fn open_file(uri: &str) -> Connection {
-        Connection::open_file(uri)
+    fn open_file(uri: &str, key_provider: Option<&KeyProvider>) -> Connection {
+        Connection::open_file(uri, key_provider)
     }
 
     /// Opens a shared memory connection using the file path as the identifier. This is internal
@@ -148,6 +159,9 @@ impl<M: Migrator> ThreadSafeConnection<M> {
         Connection::open_memory(Some(uri))
     }
 
+    /// Queues `callback` to run with the write capable connection on the database's write
+    /// queue, which by default is a background thread. The write runs whether or not the
+    /// returned future is awaited, and the future resolves to the callback's result.
     pub fn write<T: 'static + Send + Sync>(
         &self,
         callback: impl 'static + Send + FnOnce(&Connection) -> T,
@@ -171,13 +185,20 @@ impl<M: Migrator> ThreadSafeConnection<M> {
         receiver.map(|response| response.expect("Write queue unexpectedly closed"))
     }
 
+    /// Waits for every write queued before this call to finish, such as before quitting so
+    /// that queued writes aren't lost. Writes are run in the order they were queued.
+    pub fn flush(&self) -> impl Future<Output = ()> {
+        self.write(|_| ())
+    }
+
     pub(crate) fn create_connection(
         persistent: bool,
         uri: &str,
         connection_initialize_query: Option<&'static str>,
+        key_provider: Option<&KeyProvider>,
     ) -> Connection {
         let mut connection = if persistent {
-            Self::open_file(uri)
+            Self::open_file(uri, key_provider)
         } else {
             Self::open_shared_memory(uri)
         };
@@ -210,6 +231,7 @@ impl ThreadSafeConnection<()> {
             uri: Arc::from(uri),
             persistent,
             connection_initialize_query,
+            key_provider: None,
             connections: Default::default(),
             _migrator: PhantomData,
         };
@@ -225,6 +247,7 @@ impl<M: Migrator> Clone for ThreadSafeConnection<M> {
             uri: self.uri.clone(),
             persistent: self.persistent,
             connection_initialize_query: self.connection_initialize_query,
+            key_provider: self.key_provider.clone(),
             connections: self.connections.clone(),
             _migrator: PhantomData,
         }
@@ -236,7 +259,12 @@ impl<M: Migrator> Deref for ThreadSafeConnection<M> {
 
     fn deref(&self) -> &Self::Target {
         self.connections.get_or(|| {
-            Self::create_connection(self.persistent, &self.uri, self.connection_initialize_query)
+            Self::create_connection(
+                self.persistent,
+                &self.uri,
+                self.connection_initialize_query,
+                self.key_provider.as_ref(),
+            )
         })
     }
 }
@@ -355,4 +383,41 @@ mod test {
 
         smol::block_on(builder.build()).unwrap();
     }
+
+    #[test]
+    fn flush_waits_for_queued_writes() {
+        enum TestDomain {}
+        impl Domain for TestDomain {
+            fn name() -> &'static str {
+                "test"
+            }
+            fn migrations() -> &'static [&'static str] {
+                &["CREATE TABLE test(value INTEGER) STRICT;"]
+            }
+        }
+
+        let connection = smol::block_on(
+            ThreadSafeConnection::<TestDomain>::builder("flush_waits_for_queued_writes", false)
+                .build(),
+        )
+        .unwrap();
+
+        // Writes are queued without waiting for them, and run on the background thread
+        let caller = thread::current().id();
+        for value in 0..10 {
+            drop(connection.write(move |connection| {
+                assert_ne!(thread::current().id(), caller);
+                connection
+                    .exec_bound::<i64>("INSERT INTO test(value) VALUES (?)")
+                    .unwrap()(value)
+                .unwrap();
+            }));
+        }
+
+        smol::block_on(connection.flush());
+        assert_eq!(
+            connection.select::<i64>("SELECT value FROM test").unwrap()().unwrap(),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
 }
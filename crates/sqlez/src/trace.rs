@@ -0,0 +1,183 @@
+use std::{
+    ffi::{c_char, c_int, c_uint, c_void, CStr},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    time::Duration,
+};
+
+use libsqlite3_sys::*;
+
+use crate::connection::Connection;
+
+/// Called with the SQL of each statement as it starts running. Statements run by triggers
+/// are reported with their SQL as a `--` comment.
+pub type TraceCallback = Box<dyn Fn(&str) + Send>;
+
+/// Called with the SQL of each statement once it finishes running, along with how long it
+/// took to run.
+pub type ProfileCallback = Box<dyn Fn(&str, Duration) + Send>;
+
+// sqlite3_trace_v2 takes a single callback for all kinds of events, so the trace and
+// profile callbacks are registered together
+#[derive(Default)]
+pub(crate) struct Tracer {
+    trace: Option<TraceCallback>,
+    profile: Option<ProfileCallback>,
+}
+
+impl Connection {
+    /// Sets a callback which is passed the SQL of each statement as it starts running,
+    /// replacing any existing trace callback. Unlike a statement logger, this also sees
+    /// statements run by triggers.
+    pub fn trace(&self, callback: impl Fn(&str) + Send + 'static) {
+        self.update_tracer(|tracer| tracer.trace = Some(Box::new(callback)));
+    }
+
+    /// Removes the callback set by `trace`.
+    pub fn clear_trace(&self) {
+        self.update_tracer(|tracer| tracer.trace = None);
+    }
+
+    /// Sets a callback which is passed the SQL of each statement along with how long it
+    /// took to run, replacing any existing profile callback.
+    pub fn profile(&self, callback: impl Fn(&str, Duration) + Send + 'static) {
+        self.update_tracer(|tracer| tracer.profile = Some(Box::new(callback)));
+    }
+
+    /// Removes the callback set by `profile`, or by `log_slow_statements`.
+    pub fn clear_profile(&self) {
+        self.update_tracer(|tracer| tracer.profile = None);
+    }
+
+    /// Logs a warning with the SQL of each statement which takes longer than `threshold` to
+    /// run. This replaces any existing profile callback.
+    pub fn log_slow_statements(&self, threshold: Duration) {
+        self.profile(move |sql, duration| {
+            if duration > threshold {
+                log::warn!("Slow statement took {:?}:\n{}", duration, sql);
+            }
+        });
+    }
+
+    fn update_tracer(&self, update: impl FnOnce(&mut Tracer)) {
+        unsafe extern "C" fn call_tracer(
+            event: c_uint,
+            tracer: *mut c_void,
+            statement: *mut c_void,
+            detail: *mut c_void,
+        ) -> c_int {
+            let tracer = &*(tracer as *const Tracer);
+            // Panics can't unwind through sqlite
+            panic::catch_unwind(AssertUnwindSafe(|| match event as c_int {
+                SQLITE_TRACE_STMT => {
+                    if let Some(trace) = &tracer.trace {
+                        let sql = CStr::from_ptr(detail as *const c_char).to_string_lossy();
+                        trace(&sql);
+                    }
+                }
+                SQLITE_TRACE_PROFILE => {
+                    if let Some(profile) = &tracer.profile {
+                        let sql = CStr::from_ptr(sqlite3_sql(statement as *mut sqlite3_stmt))
+                            .to_string_lossy();
+                        let nanoseconds = *(detail as *const i64);
+                        profile(&sql, Duration::from_nanos(nanoseconds as u64));
+                    }
+                }
+                _ => {}
+            }))
+            .ok();
+            0
+        }
+
+        let mut tracer = self.tracer.borrow_mut();
+        update(tracer.get_or_insert_with(Default::default));
+
+        let tracer_ref = tracer.as_deref().unwrap();
+        let mut mask = 0;
+        if tracer_ref.trace.is_some() {
+            mask |= SQLITE_TRACE_STMT;
+        }
+        if tracer_ref.profile.is_some() {
+            mask |= SQLITE_TRACE_PROFILE;
+        }
+
+        unsafe {
+            if mask == 0 {
+                sqlite3_trace_v2(self.sqlite3, 0, None, ptr::null_mut());
+            } else {
+                sqlite3_trace_v2(
+                    self.sqlite3,
+                    mask as c_uint,
+                    Some(call_tracer),
+                    tracer_ref as *const Tracer as *mut c_void,
+                );
+            }
+        }
+        if mask == 0 {
+            *tracer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_trace_and_profile() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_trace_and_profile"));
+        connection.exec("CREATE TABLE test (value INTEGER)")?()?;
+
+        let traced = Arc::new(Mutex::new(Vec::new()));
+        let profiled = Arc::new(Mutex::new(Vec::new()));
+        connection.trace({
+            let traced = traced.clone();
+            move |sql| traced.lock().unwrap().push(sql.to_string())
+        });
+        connection.profile({
+            let profiled = profiled.clone();
+            move |sql, duration| profiled.lock().unwrap().push((sql.to_string(), duration))
+        });
+
+        connection.exec_bound::<i32>("INSERT INTO test (value) VALUES (?)")?(1)?;
+        connection.select::<i32>("SELECT value FROM test")?()?;
+        assert_eq!(
+            *traced.lock().unwrap(),
+            [
+                "INSERT INTO test (value) VALUES (?)",
+                "SELECT value FROM test"
+            ]
+        );
+        assert_eq!(
+            profiled
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(sql, _)| sql.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "INSERT INTO test (value) VALUES (?)",
+                "SELECT value FROM test"
+            ]
+        );
+
+        // Clearing one callback leaves the other in place
+        connection.clear_trace();
+        connection.select::<i32>("SELECT value FROM test")?()?;
+        assert_eq!(traced.lock().unwrap().len(), 2);
+        assert_eq!(profiled.lock().unwrap().len(), 3);
+
+        connection.log_slow_statements(Duration::from_secs(60));
+        connection.clear_profile();
+        connection.select::<i32>("SELECT value FROM test")?()?;
+        assert_eq!(profiled.lock().unwrap().len(), 3);
+        Ok(())
+    }
+}
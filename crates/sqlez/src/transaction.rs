@@ -0,0 +1,343 @@
+use std::{ops::Deref, thread, time::Duration};
+
+use anyhow::{bail, Result};
+use libsqlite3_sys::sqlite3_get_autocommit;
+
+use crate::{connection::Connection, error::Error};
+
+const NESTED_TRANSACTION_SAVEPOINT: &str = "nested_transaction";
+const BUSY_RETRIES: u32 = 8;
+const BUSY_RETRY_DELAY: Duration = Duration::from_millis(5);
+
+/// An open transaction, which is rolled back when dropped unless `commit` is called. Derefs
+/// to the connection so that statements can be run through it.
+pub struct Transaction<'a> {
+    connection: &'a Connection,
+    // Transactions opened within another transaction are savepoints, so that rolling them
+    // back only undoes their own changes
+    nested: bool,
+    finished: bool,
+}
+
+impl<'a> Transaction<'a> {
+    /// Commits the transaction, or releases its savepoint when it is nested. If this fails,
+    /// the transaction is rolled back.
+    pub fn commit(mut self) -> Result<()> {
+        if self.nested {
+            self.connection.release(NESTED_TRANSACTION_SAVEPOINT)?;
+        } else {
+            self.connection.commit()?;
+        }
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Rolls back the transaction's changes. Dropping the transaction does the same, but
+    /// this reports any error.
+    pub fn rollback(mut self) -> Result<()> {
+        self.finished = true;
+        self.rollback_changes()
+    }
+
+    fn rollback_changes(&self) -> Result<()> {
+        if self.nested {
+            self.connection.rollback_to(NESTED_TRANSACTION_SAVEPOINT)?;
+            self.connection.release(NESTED_TRANSACTION_SAVEPOINT)
+        } else {
+            self.connection.rollback_if_open()
+        }
+    }
+}
+
+impl<'a> Deref for Transaction<'a> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.rollback_changes().ok();
+        }
+    }
+}
+
+impl Connection {
+    /// Returns true if a transaction is currently open on this connection, either
+    /// through an explicit `BEGIN` or an outermost `SAVEPOINT`.
+    pub fn in_transaction(&self) -> bool {
+        unsafe { sqlite3_get_autocommit(self.sqlite3) == 0 }
+    }
+
+    /// Opens a transaction with `BEGIN`. Errors if a transaction is already open, use
+    /// `transaction` or a savepoint to nest work instead.
+    pub fn begin(&self) -> Result<()> {
+        if self.in_transaction() {
+            bail!("Cannot begin a transaction while another transaction is open");
+        }
+        self.exec("BEGIN")?()
+    }
+
+    /// Commits the open transaction. Errors if there is no open transaction.
+    pub fn commit(&self) -> Result<()> {
+        if !self.in_transaction() {
+            bail!("Cannot commit when no transaction is open");
+        }
+        self.exec("COMMIT")?()
+    }
+
+    /// Rolls back the open transaction. Errors if there is no open transaction.
+    pub fn rollback(&self) -> Result<()> {
+        if !self.in_transaction() {
+            bail!("Cannot roll back when no transaction is open");
+        }
+        self.exec("ROLLBACK")?()
+    }
+
+    /// Opens a transaction which must be committed with `Transaction::commit`, and is
+    /// otherwise rolled back when dropped. When a transaction is already open, a savepoint
+    /// is used instead, so that nested transactions compose.
+    pub fn transaction(&self) -> Result<Transaction<'_>> {
+        let nested = self.in_transaction();
+        if nested {
+            self.exec(&format!("SAVEPOINT {NESTED_TRANSACTION_SAVEPOINT}"))?()?;
+        } else {
+            self.begin()?;
+        }
+        Ok(Transaction {
+            connection: self,
+            nested,
+            finished: false,
+        })
+    }
+
+    // Run a set of commands within the context of a transaction. If the callback
+    // returns Err(_), the transaction will be rolled back. Otherwise it is committed.
+    // If another connection holds the database lock, the transaction is rolled back
+    // and the callback retried, so it may be called more than once.
+    pub fn with_transaction<R, F>(&self, mut f: F) -> Result<R>
+    where
+        F: FnMut(&Transaction) -> Result<R>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = self.transaction().and_then(|transaction| {
+                let result = f(&transaction)?;
+                transaction.commit()?;
+                Ok(result)
+            });
+
+            // Nested transactions can't resolve contention, as the outer transaction
+            // keeps holding onto its locks
+            match result {
+                Err(error)
+                    if error.downcast_ref::<Error>() == Some(&Error::Busy)
+                        && !self.in_transaction()
+                        && attempt < BUSY_RETRIES =>
+                {
+                    attempt += 1;
+                    thread::sleep(BUSY_RETRY_DELAY * attempt);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Some errors (such as SQLITE_FULL) cause sqlite to roll back the transaction on its
+    // own, in which case issuing another ROLLBACK would fail.
+    pub(crate) fn rollback_if_open(&self) -> Result<()> {
+        if self.in_transaction() {
+            self.rollback()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::connection::Connection;
+    use anyhow::Result;
+    use indoc::indoc;
+
+    #[test]
+    fn test_transaction_commits_and_rolls_back() -> Result<()> {
+        let connection = Connection::open_memory(Some("transaction_commits_and_rolls_back"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        assert!(!connection.in_transaction());
+        connection.with_transaction(|_| {
+            assert!(connection.in_transaction());
+            connection.exec("INSERT INTO test(col) VALUES (1)")?()
+        })?;
+        assert!(!connection.in_transaction());
+
+        assert!(connection
+            .with_transaction(|_| -> Result<()> {
+                connection.exec("INSERT INTO test(col) VALUES (2)")?()?;
+                anyhow::bail!("Failed transaction :(")
+            })
+            .is_err());
+        assert!(!connection.in_transaction());
+
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test")?()?,
+            vec![1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_transactions() -> Result<()> {
+        let connection = Connection::open_memory(Some("nested_transactions"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        connection.with_transaction(|_| {
+            connection.exec("INSERT INTO test(col) VALUES (1)")?()?;
+
+            assert!(connection
+                .with_transaction(|_| -> Result<()> {
+                    connection.exec("INSERT INTO test(col) VALUES (2)")?()?;
+                    assert_eq!(
+                        connection.select::<usize>("SELECT col FROM test ORDER BY col")?()?,
+                        vec![1, 2]
+                    );
+                    anyhow::bail!("Failed inner transaction :(")
+                })
+                .is_err());
+
+            // The outer transaction is still open and only the inner changes were undone
+            assert!(connection.in_transaction());
+            assert_eq!(
+                connection.select::<usize>("SELECT col FROM test ORDER BY col")?()?,
+                vec![1]
+            );
+
+            connection.with_transaction(|_| connection.exec("INSERT INTO test(col) VALUES (3)")?())
+        })?;
+
+        assert!(!connection.in_transaction());
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test ORDER BY col")?()?,
+            vec![1, 3]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_transactions() -> Result<()> {
+        let connection = Connection::open_memory(Some("explicit_transactions"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        connection.begin()?;
+        assert!(connection.begin().is_err());
+        connection.exec("INSERT INTO test(col) VALUES (1)")?()?;
+        connection.commit()?;
+
+        connection.begin()?;
+        connection.exec("INSERT INTO test(col) VALUES (2)")?()?;
+        connection.rollback()?;
+
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test")?()?,
+            vec![1]
+        );
+
+        assert!(connection.commit().is_err());
+        assert!(connection.rollback().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_guard() -> Result<()> {
+        let connection = Connection::open_memory(Some("transaction_guard"));
+
+        connection.exec(indoc! {"
+            CREATE TABLE test (
+                col INTEGER
+            );"})?()?;
+
+        let transaction = connection.transaction()?;
+        transaction.exec("INSERT INTO test(col) VALUES (1)")?()?;
+        {
+            // Dropping a nested transaction only undoes its own changes
+            let nested = transaction.transaction()?;
+            nested.exec("INSERT INTO test(col) VALUES (2)")?()?;
+        }
+        assert!(connection.in_transaction());
+        transaction.commit()?;
+        assert!(!connection.in_transaction());
+
+        {
+            let transaction = connection.transaction()?;
+            transaction.exec("INSERT INTO test(col) VALUES (3)")?()?;
+        }
+        assert!(!connection.in_transaction());
+
+        let transaction = connection.transaction()?;
+        transaction.exec("INSERT INTO test(col) VALUES (4)")?()?;
+        transaction.rollback()?;
+
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test")?()?,
+            vec![1]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_transaction_retries_when_busy() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("sqlez transaction busy #{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("db.db");
+
+        let connection = Connection::open_path(&path)?;
+        connection.exec("CREATE TABLE test (col INTEGER)")?()?;
+
+        // Another connection holds the write lock for a while
+        let blocker = Connection::open_path(&path)?;
+        blocker.exec("BEGIN IMMEDIATE")?()?;
+        let blocker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            blocker.exec("COMMIT").and_then(|mut commit| commit())
+        });
+
+        let mut attempts = 0;
+        connection.with_transaction(|transaction| {
+            attempts += 1;
+            transaction.exec("INSERT INTO test(col) VALUES (1)")?()
+        })?;
+        blocker.join().unwrap()?;
+
+        assert!(attempts > 1);
+        assert_eq!(
+            connection.select::<usize>("SELECT col FROM test")?()?,
+            vec![1]
+        );
+
+        drop(connection);
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
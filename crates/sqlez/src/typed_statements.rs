@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 
 use crate::{
     bindable::{Bind, Column},
@@ -6,6 +6,9 @@ use crate::{
     statement::Statement,
 };
 
+/// How many rows `insert_batch` inserts in each transaction.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
 impl Connection {
     /// Prepare a statement which has no bindings and returns nothing.
     ///
@@ -14,12 +17,13 @@ impl Connection {
     /// Use a true migration instead.
     pub fn exec<'a>(&'a self, query: &str) -> Result<impl 'a + FnMut() -> Result<()>> {
         let mut statement = Statement::prepare(self, query)?;
-        Ok(move || statement.exec())
+        Ok(move || statement.exec().map(|_| ()))
     }
 
     /// Prepare a statement which takes a binding, but returns nothing.
     /// The bindings for a given invocation should be passed to the returned
-    /// closure
+    /// closure. Bound statements are reused through `prepare_cached`, as they
+    /// tend to be run repeatedly.
     ///
     /// Note: If there are multiple statements that depend upon each other
     /// (such as those which make schema changes), preparation will fail.
@@ -28,8 +32,8 @@ impl Connection {
         &'a self,
         query: &str,
     ) -> Result<impl 'a + FnMut(B) -> Result<()>> {
-        let mut statement = Statement::prepare(self, query)?;
-        Ok(move |bindings| statement.with_bindings(&bindings)?.exec())
+        let mut statement = self.prepare_cached(query)?;
+        Ok(move |bindings| statement.with_bindings(&bindings)?.exec().map(|_| ()))
     }
 
     /// Prepare a statement which has no bindings and returns a `Vec<C>`.
@@ -54,7 +58,7 @@ impl Connection {
         &'a self,
         query: &str,
     ) -> Result<impl 'a + FnMut(B) -> Result<Vec<C>>> {
-        let mut statement = Statement::prepare(self, query)?;
+        let mut statement = self.prepare_cached(query)?;
         Ok(move |bindings| statement.with_bindings(&bindings)?.rows::<C>())
     }
 
@@ -84,7 +88,7 @@ impl Connection {
         &'a self,
         query: &str,
     ) -> Result<impl 'a + FnMut(B) -> Result<Option<C>>> {
-        let mut statement = Statement::prepare(self, query)?;
+        let mut statement = self.prepare_cached(query)?;
         Ok(move |bindings| {
             statement
                 .with_bindings(&bindings)
@@ -93,4 +97,105 @@ impl Connection {
                 .context("Maybe row failed")
         })
     }
+
+    /// Runs `query` once for each of `rows`, reusing one prepared statement, and returns
+    /// how many rows were inserted. The inserts are grouped into transactions of
+    /// `DEFAULT_BATCH_SIZE` rows, as running each insert in its own implicit transaction is
+    /// much slower.
+    ///
+    /// If an insert fails, the rows inserted so far in its transaction are rolled back, but
+    /// earlier transactions stay committed. Run this within a transaction to make the whole
+    /// batch atomic.
+    pub fn insert_batch<B: Bind>(
+        &self,
+        query: &str,
+        rows: impl IntoIterator<Item = B>,
+    ) -> Result<usize> {
+        self.insert_batch_with_chunk_size(query, rows, DEFAULT_BATCH_SIZE)
+    }
+
+    /// Like `insert_batch`, but commits after every `chunk_size` rows.
+    pub fn insert_batch_with_chunk_size<B: Bind>(
+        &self,
+        query: &str,
+        rows: impl IntoIterator<Item = B>,
+        chunk_size: usize,
+    ) -> Result<usize> {
+        if chunk_size == 0 {
+            bail!("Batches must insert at least one row per transaction");
+        }
+
+        let mut statement = self.prepare_cached(query)?;
+        let mut rows = rows.into_iter().peekable();
+        let mut inserted = 0;
+        while rows.peek().is_some() {
+            let transaction = self.transaction()?;
+            for row in rows.by_ref().take(chunk_size) {
+                statement
+                    .with_bindings(&row)?
+                    .exec()
+                    .with_context(|| format!("Failed to insert row {} of batch", inserted))?;
+                inserted += 1;
+            }
+            transaction.commit()?;
+        }
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::SeqCst},
+        Arc,
+    };
+
+    use anyhow::Result;
+
+    use crate::connection::Connection;
+
+    #[test]
+    fn test_insert_batch() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_insert_batch"));
+        connection.exec("CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT)")?()?;
+
+        let commits = Arc::new(AtomicUsize::new(0));
+        connection.set_commit_hook({
+            let commits = commits.clone();
+            move || {
+                commits.fetch_add(1, SeqCst);
+                true
+            }
+        });
+        let inserted = connection.insert_batch_with_chunk_size(
+            "INSERT INTO test (id, name) VALUES (?, ?)",
+            (0..10).map(|id| (id, format!("row {}", id))),
+            4,
+        )?;
+        connection.clear_commit_hook();
+        assert_eq!(inserted, 10);
+        assert_eq!(commits.load(SeqCst), 3);
+        assert_eq!(
+            connection.select_row::<i64>("SELECT count(*) FROM test")?()?,
+            Some(10)
+        );
+
+        // A failing row only rolls back the rows of its own chunk
+        let result = connection.insert_batch_with_chunk_size(
+            "INSERT INTO test (id, name) VALUES (?, ?)",
+            [(10, "a"), (11, "b"), (12, "c"), (0, "duplicate")],
+            2,
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            connection.select::<i64>("SELECT id FROM test WHERE id >= 10")?()?,
+            vec![10, 11]
+        );
+        assert!(!connection.in_transaction());
+
+        assert!(connection
+            .insert_batch_with_chunk_size("INSERT INTO test (id) VALUES (?)", [20], 0)
+            .is_err());
+        Ok(())
+    }
 }
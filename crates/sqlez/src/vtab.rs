@@ -0,0 +1,372 @@
+use std::{
+    ffi::{c_char, c_int, c_void, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use anyhow::{anyhow, Result};
+use libsqlite3_sys::*;
+
+use crate::{
+    connection::Connection,
+    functions::{self, Value},
+};
+
+/// A read-only virtual table implemented in Rust, whose rows are produced by a cursor when
+/// the table is queried rather than being stored in the database. This suits exposing live
+/// state to SQL, such as for debug tooling.
+pub trait VTab: Send + 'static {
+    type Cursor: VTabCursor;
+
+    /// The column definitions of the table, as they would be written in a `CREATE TABLE`
+    /// statement, such as `path TEXT, size INTEGER`.
+    fn columns(&self) -> String;
+
+    /// Opens a cursor for a new scan over the table's rows.
+    fn open(&self) -> Result<Self::Cursor>;
+}
+
+/// A scan over the rows of a `VTab`. sqlite applies any `WHERE` clause to the rows itself,
+/// so each scan is over every row.
+pub trait VTabCursor: 'static {
+    /// Moves to the first row, starting the scan over.
+    fn filter(&mut self) -> Result<()>;
+
+    /// Moves to the next row.
+    fn next(&mut self) -> Result<()>;
+
+    /// Whether the cursor has moved past the last row.
+    fn eof(&self) -> bool;
+
+    /// The value in the zero based column `index` of the current row.
+    fn column(&self, index: usize) -> Result<Value>;
+
+    /// A unique integer identifying the current row.
+    fn rowid(&self) -> Result<i64>;
+}
+
+// Registered with sqlite as the module's client data, so the module definition lives
+// exactly as long as the registration
+struct Module<T> {
+    module: sqlite3_module,
+    table: T,
+}
+
+// sqlite casts between these and the base structs, so the base must come first
+#[repr(C)]
+struct Table<T> {
+    base: sqlite3_vtab,
+    table: *const T,
+}
+
+#[repr(C)]
+struct Cursor<C> {
+    base: sqlite3_vtab_cursor,
+    cursor: C,
+}
+
+// Copies a message into memory from sqlite's allocator, which sqlite frees once reported
+unsafe fn sqlite_message(message: &str) -> *mut c_char {
+    let copy = sqlite3_malloc64(message.len() as sqlite3_uint64 + 1) as *mut u8;
+    if !copy.is_null() {
+        ptr::copy_nonoverlapping(message.as_ptr(), copy, message.len());
+        *copy.add(message.len()) = 0;
+    }
+    copy as *mut c_char
+}
+
+// Replaces the table's error message, which sqlite reports when a callback fails
+unsafe fn set_table_error(vtab: *mut sqlite3_vtab, message: String) {
+    sqlite3_free((*vtab).zErrMsg as *mut c_void);
+    (*vtab).zErrMsg = sqlite_message(&message.replace('\0', ""));
+}
+
+unsafe fn report<R>(vtab: *mut sqlite3_vtab, result: std::thread::Result<Result<R>>) -> Option<R> {
+    let message = match result {
+        Ok(Ok(value)) => return Some(value),
+        Ok(Err(error)) => format!("{:#}", error),
+        Err(_) => "Virtual table panicked".to_string(),
+    };
+    set_table_error(vtab, message);
+    None
+}
+
+unsafe extern "C" fn connect<T: VTab>(
+    db: *mut sqlite3,
+    module: *mut c_void,
+    _argc: c_int,
+    _argv: *const *const c_char,
+    vtab: *mut *mut sqlite3_vtab,
+    error: *mut *mut c_char,
+) -> c_int {
+    let module = &*(module as *const Module<T>);
+    let declaration = panic::catch_unwind(AssertUnwindSafe(|| {
+        CString::new(format!("CREATE TABLE x({})", module.table.columns()))
+    }));
+    let Ok(Ok(declaration)) = declaration else {
+        *error = sqlite_message("Invalid virtual table columns");
+        return SQLITE_ERROR;
+    };
+
+    let code = sqlite3_declare_vtab(db, declaration.as_ptr());
+    if code != SQLITE_OK {
+        return code;
+    }
+    let table = Box::new(Table {
+        base: sqlite3_vtab {
+            pModule: ptr::null(),
+            nRef: 0,
+            zErrMsg: ptr::null_mut(),
+        },
+        table: &module.table as *const T,
+    });
+    *vtab = Box::into_raw(table) as *mut sqlite3_vtab;
+    SQLITE_OK
+}
+
+unsafe extern "C" fn best_index(_vtab: *mut sqlite3_vtab, info: *mut sqlite3_index_info) -> c_int {
+    // Every scan reads the whole table, and sqlite checks the constraints on each row
+    (*info).estimatedCost = 1_000_000.0;
+    SQLITE_OK
+}
+
+unsafe extern "C" fn disconnect<T: VTab>(vtab: *mut sqlite3_vtab) -> c_int {
+    let table = Box::from_raw(vtab as *mut Table<T>);
+    sqlite3_free(table.base.zErrMsg as *mut c_void);
+    SQLITE_OK
+}
+
+unsafe extern "C" fn open<T: VTab>(
+    vtab: *mut sqlite3_vtab,
+    cursor: *mut *mut sqlite3_vtab_cursor,
+) -> c_int {
+    let table = &*(*(vtab as *mut Table<T>)).table;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| table.open()));
+    let Some(opened) = report(vtab, result) else {
+        return SQLITE_ERROR;
+    };
+    let opened = Box::new(Cursor {
+        base: sqlite3_vtab_cursor { pVtab: vtab },
+        cursor: opened,
+    });
+    *cursor = Box::into_raw(opened) as *mut sqlite3_vtab_cursor;
+    SQLITE_OK
+}
+
+unsafe extern "C" fn close<T: VTab>(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    drop(Box::from_raw(cursor as *mut Cursor<T::Cursor>));
+    SQLITE_OK
+}
+
+// Runs a callback on the cursor, reporting its failure through the cursor's table
+unsafe fn with_cursor<T: VTab, R>(
+    cursor: *mut sqlite3_vtab_cursor,
+    callback: impl FnOnce(&mut T::Cursor) -> Result<R>,
+) -> Option<R> {
+    let vtab = (*cursor).pVtab;
+    let cursor = &mut (*(cursor as *mut Cursor<T::Cursor>)).cursor;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| callback(cursor)));
+    report(vtab, result)
+}
+
+unsafe extern "C" fn filter<T: VTab>(
+    cursor: *mut sqlite3_vtab_cursor,
+    _index_number: c_int,
+    _index_string: *const c_char,
+    _argc: c_int,
+    _argv: *mut *mut sqlite3_value,
+) -> c_int {
+    match with_cursor::<T, _>(cursor, |cursor| cursor.filter()) {
+        Some(()) => SQLITE_OK,
+        None => SQLITE_ERROR,
+    }
+}
+
+unsafe extern "C" fn next<T: VTab>(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    match with_cursor::<T, _>(cursor, |cursor| cursor.next()) {
+        Some(()) => SQLITE_OK,
+        None => SQLITE_ERROR,
+    }
+}
+
+unsafe extern "C" fn eof<T: VTab>(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    // A panic ends the scan, as eof can't report errors
+    with_cursor::<T, _>(cursor, |cursor| Ok(cursor.eof())).unwrap_or(true) as c_int
+}
+
+unsafe extern "C" fn column<T: VTab>(
+    cursor: *mut sqlite3_vtab_cursor,
+    context: *mut sqlite3_context,
+    index: c_int,
+) -> c_int {
+    let cursor = &(*(cursor as *mut Cursor<T::Cursor>)).cursor;
+    // Errors set as the column's result fail the statement, like in user defined functions
+    let result = panic::catch_unwind(AssertUnwindSafe(|| cursor.column(index as usize)));
+    functions::set_result(context, result);
+    SQLITE_OK
+}
+
+unsafe extern "C" fn rowid<T: VTab>(
+    cursor: *mut sqlite3_vtab_cursor,
+    rowid: *mut sqlite3_int64,
+) -> c_int {
+    match with_cursor::<T, _>(cursor, |cursor| cursor.rowid()) {
+        Some(id) => {
+            *rowid = id;
+            SQLITE_OK
+        }
+        None => SQLITE_ERROR,
+    }
+}
+
+impl Connection {
+    /// Registers `table` as a read-only virtual table called `name`, which can be queried
+    /// like any other table without being created first. The table isn't stored in the
+    /// database, and lives until it is replaced or the connection is closed.
+    pub fn create_module<T: VTab>(&self, name: &str, table: T) -> Result<()> {
+        let name = CString::new(name)?;
+        let module = Box::new(Module {
+            module: sqlite3_module {
+                iVersion: 1,
+                // Without xCreate, the table can only be used through its module name
+                xCreate: None,
+                xConnect: Some(connect::<T>),
+                xBestIndex: Some(best_index),
+                xDisconnect: Some(disconnect::<T>),
+                xDestroy: Some(disconnect::<T>),
+                xOpen: Some(open::<T>),
+                xClose: Some(close::<T>),
+                xFilter: Some(filter::<T>),
+                xNext: Some(next::<T>),
+                xEof: Some(eof::<T>),
+                xColumn: Some(column::<T>),
+                xRowid: Some(rowid::<T>),
+                xUpdate: None,
+                xBegin: None,
+                xSync: None,
+                xCommit: None,
+                xRollback: None,
+                xFindFunction: None,
+                xRename: None,
+                xSavepoint: None,
+                xRelease: None,
+                xRollbackTo: None,
+                xShadowName: None,
+            },
+            table,
+        });
+        let module = Box::into_raw(module);
+
+        // sqlite drops the module through `drop_boxed`, even if registering it fails
+        let code = unsafe {
+            sqlite3_create_module_v2(
+                self.sqlite3,
+                name.as_ptr(),
+                &(*module).module,
+                module as *mut c_void,
+                Some(functions::drop_boxed::<Module<T>>),
+            )
+        };
+        self.check_registration(code)
+            .map_err(|error| anyhow!("Failed to register virtual table: {error:#}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::{anyhow, Result};
+
+    use crate::{
+        connection::Connection,
+        functions::Value,
+        vtab::{VTab, VTabCursor},
+    };
+
+    struct Entries(Arc<Mutex<Vec<(String, i64)>>>);
+
+    struct EntriesCursor {
+        rows: Vec<(String, i64)>,
+        position: usize,
+    }
+
+    impl VTab for Entries {
+        type Cursor = EntriesCursor;
+
+        fn columns(&self) -> String {
+            "path TEXT, size INTEGER".to_string()
+        }
+
+        fn open(&self) -> Result<EntriesCursor> {
+            Ok(EntriesCursor {
+                rows: self.0.lock().unwrap().clone(),
+                position: 0,
+            })
+        }
+    }
+
+    impl VTabCursor for EntriesCursor {
+        fn filter(&mut self) -> Result<()> {
+            self.position = 0;
+            Ok(())
+        }
+
+        fn next(&mut self) -> Result<()> {
+            self.position += 1;
+            Ok(())
+        }
+
+        fn eof(&self) -> bool {
+            self.position >= self.rows.len()
+        }
+
+        fn column(&self, index: usize) -> Result<Value> {
+            let (path, size) = &self.rows[self.position];
+            match index {
+                0 => Ok(Value::Text(path.clone())),
+                1 if *size < 0 => Err(anyhow!("Invalid size for {path}")),
+                1 => Ok(Value::Integer(*size)),
+                _ => Ok(Value::Null),
+            }
+        }
+
+        fn rowid(&self) -> Result<i64> {
+            Ok(self.position as i64)
+        }
+    }
+
+    #[test]
+    fn test_virtual_table() -> Result<()> {
+        let connection = Connection::open_memory(Some("test_virtual_table"));
+        let entries = Arc::new(Mutex::new(vec![
+            ("a.rs".to_string(), 10),
+            ("b.rs".to_string(), 20),
+        ]));
+        connection.create_module("entries", Entries(entries.clone()))?;
+
+        assert_eq!(
+            connection.select::<(String, i64)>("SELECT path, size FROM entries")?()?,
+            vec![("a.rs".to_string(), 10), ("b.rs".to_string(), 20)]
+        );
+        assert_eq!(
+            connection.select::<String>("SELECT path FROM entries WHERE size > 15")?()?,
+            vec!["b.rs".to_string()]
+        );
+
+        // Each query scans the current state rather than a copy
+        entries.lock().unwrap().push(("c.rs".to_string(), 30));
+        assert_eq!(
+            connection.select_row::<i64>("SELECT sum(size) FROM entries")?()?,
+            Some(60)
+        );
+        assert!(connection
+            .exec("INSERT INTO entries VALUES ('d.rs', 1)")
+            .is_err());
+
+        entries.lock().unwrap().push(("bad.rs".to_string(), -1));
+        let error = connection.select::<i64>("SELECT size FROM entries")?().unwrap_err();
+        assert!(format!("{error:#}").contains("Invalid size for bad.rs"));
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
-use sqlez::thread_safe_connection::{locking_queue, ThreadSafeConnection};
-use syn::Error;
+use quote::{format_ident, quote};
+use sqlez::{
+    statement::sql_parameter_count,
+    thread_safe_connection::{locking_queue, ThreadSafeConnection},
+};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Index};
 
 lazy_static::lazy_static! {
     static ref SQLITE: ThreadSafeConnection =  {
@@ -8,17 +12,237 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Checks the syntax of the query when the crate is built, expanding to the formatted query
+/// as a string literal. The query may start with `#[bindings = N]`, stating how many
+/// parameters the values it is run with bind, which fails the build if the query takes a
+/// different number of parameters.
 #[proc_macro]
 pub fn sql(tokens: TokenStream) -> TokenStream {
+    let (bindings, tokens) = match split_bindings(tokens) {
+        Ok(split) => split,
+        Err(error) => return TokenStream::from(error.into_compile_error()),
+    };
     let (spans, sql) = make_sql(tokens);
 
     let error = SQLITE.sql_has_syntax_error(sql.trim());
     let formatted_sql = sqlformat::format(&sql, &sqlformat::QueryParams::None, Default::default());
 
     if let Some((error, error_offset)) = error {
-        create_error(spans, error_offset, error, &formatted_sql)
+        return create_error(spans, error_offset, error, &formatted_sql);
+    }
+    if let Some((bindings, span)) = bindings {
+        let parameters = sql_parameter_count(&sql);
+        if parameters != bindings {
+            let error_text = format!(
+                "Query takes {} parameters, but {} are bound\nFor Query: {}",
+                parameters, bindings, formatted_sql
+            );
+            return TokenStream::from(Error::new(span.into(), error_text).into_compile_error());
+        }
+    }
+    format!("r#\"{}\"#", &formatted_sql).parse().unwrap()
+}
+
+// Splits a leading `#[bindings = N]` off of the query's tokens, returning N and its span
+fn split_bindings(tokens: TokenStream) -> Result<(Option<(usize, Span)>, TokenStream), Error> {
+    let mut trees = tokens.into_iter().peekable();
+    if !matches!(trees.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '#') {
+        return Ok((None, trees.collect()));
+    }
+    let pound = trees.next().unwrap();
+
+    let attribute = match trees.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket => group,
+        _ => {
+            return Err(Error::new(
+                pound.span().into(),
+                "Expected `#[bindings = N]`",
+            ))
+        }
+    };
+    let parts = attribute.stream().into_iter().collect::<Vec<_>>();
+    let bindings = match parts.as_slice() {
+        [TokenTree::Ident(name), TokenTree::Punct(equals), TokenTree::Literal(count)]
+            if name.to_string() == "bindings" && equals.as_char() == '=' =>
+        {
+            count.to_string().parse::<usize>().ok()
+        }
+        _ => None,
+    };
+    match bindings {
+        Some(bindings) => Ok((Some((bindings, attribute.span())), trees.collect())),
+        None => Err(Error::new(
+            attribute.span().into(),
+            "Expected `#[bindings = N]`",
+        )),
+    }
+}
+
+/// Binds a struct's fields to consecutive parameters, in declaration order. Fields may be any
+/// `Bind` type, including `Option`s and other derived structs, whose columns are flattened
+/// into the parent's.
+///
+/// The generated code refers to `sqlez` by name, so crates which only depend on it through
+/// `db` should `use db::sqlez;`.
+#[proc_macro_derive(Bind)]
+pub fn derive_bind(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let fields = match struct_fields(&input) {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    add_field_bounds(&mut input, &fields, quote!(sqlez::bindable::Bind));
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let accessors = fields.iter().map(|field| &field.accessor);
+
+    quote! {
+        impl #impl_generics sqlez::bindable::Bind for #name #type_generics #where_clause {
+            fn bind(
+                &self,
+                statement: &sqlez::statement::Statement,
+                start_index: i32,
+            ) -> sqlez::anyhow::Result<i32> {
+                let mut next_index = start_index;
+                #(next_index = sqlez::bindable::Bind::bind(&self.#accessors, statement, next_index)?;)*
+                Ok(next_index)
+            }
+        }
+    }
+    .into()
+}
+
+/// Reads a struct's fields from consecutive columns, in declaration order. Fields may be any
+/// `Column` type, including `Option`s and other derived structs, whose columns are flattened
+/// into the parent's.
+///
+/// The generated code refers to `sqlez` by name, so crates which only depend on it through
+/// `db` should `use db::sqlez;`.
+#[proc_macro_derive(Column)]
+pub fn derive_column(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let fields = match struct_fields(&input) {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    add_field_bounds(&mut input, &fields, quote!(sqlez::bindable::Column));
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let types = fields.iter().map(|field| &field.ty);
+    let locals = (0..fields.len())
+        .map(|ix| format_ident!("field_{}", ix))
+        .collect::<Vec<_>>();
+    let construct = if matches!(fields.first(), Some(Field { named: true, .. })) {
+        let accessors = fields.iter().map(|field| &field.accessor);
+        quote!(Self { #(#accessors: #locals),* })
+    } else if fields.is_empty() {
+        quote!(Self)
     } else {
-        format!("r#\"{}\"#", &formatted_sql).parse().unwrap()
+        quote!(Self(#(#locals),*))
+    };
+
+    quote! {
+        impl #impl_generics sqlez::bindable::Column for #name #type_generics #where_clause {
+            fn column(
+                statement: &mut sqlez::statement::Statement,
+                start_index: i32,
+            ) -> sqlez::anyhow::Result<(Self, i32)> {
+                let next_index = start_index;
+                #(let (#locals, next_index) =
+                    <#types as sqlez::bindable::Column>::column(statement, next_index)?;)*
+                Ok((#construct, next_index))
+            }
+        }
+    }
+    .into()
+}
+
+/// Counts the columns of a struct's fields, which is needed to bind or read it as an
+/// `Option`.
+#[proc_macro_derive(StaticColumnCount)]
+pub fn derive_static_column_count(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    let fields = match struct_fields(&input) {
+        Ok(fields) => fields,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    add_field_bounds(
+        &mut input,
+        &fields,
+        quote!(sqlez::bindable::StaticColumnCount),
+    );
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let types = fields.iter().map(|field| &field.ty);
+
+    quote! {
+        impl #impl_generics sqlez::bindable::StaticColumnCount for #name #type_generics #where_clause {
+            fn column_count() -> usize {
+                0 #(+ <#types as sqlez::bindable::StaticColumnCount>::column_count())*
+            }
+        }
+    }
+    .into()
+}
+
+struct Field {
+    // The field's name, or its index for tuple structs
+    accessor: proc_macro2::TokenStream,
+    named: bool,
+    ty: syn::Type,
+}
+
+fn struct_fields(input: &DeriveInput) -> Result<Vec<Field>, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "sqlez derives are only supported on structs",
+        ));
+    };
+
+    Ok(match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                Field {
+                    accessor: quote!(#ident),
+                    named: true,
+                    ty: field.ty.clone(),
+                }
+            })
+            .collect(),
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(ix, field)| {
+                let index = Index::from(ix);
+                Field {
+                    accessor: quote!(#index),
+                    named: false,
+                    ty: field.ty.clone(),
+                }
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    })
+}
+
+// Bounding the field types rather than the type parameters supports generic structs
+// whose fields wrap their parameters, such as `Option<T>`
+fn add_field_bounds(input: &mut DeriveInput, fields: &[Field], bound: proc_macro2::TokenStream) {
+    if input.generics.params.is_empty() {
+        return;
+    }
+    let where_clause = input.generics.make_where_clause();
+    for field in fields {
+        let ty = &field.ty;
+        where_clause.predicates.push(parse_quote!(#ty: #bound));
     }
 }
 
@@ -34,6 +34,7 @@ pub fn init(cx: &mut AppContext) {
         |workspace: &mut Workspace, _: &mut ViewContext<Workspace>| {
             workspace.register_action(TerminalPanel::new_terminal);
             workspace.register_action(TerminalPanel::open_terminal);
+            workspace.register_action(TerminalPanel::spawn_in_terminal);
             workspace.register_action(|workspace, _: &ToggleFocus, cx| {
                 workspace.toggle_panel_focus::<TerminalPanel>(cx);
             });
@@ -75,7 +76,7 @@ impl TerminalPanel {
                             .icon_size(IconSize::Small)
                             .on_click(move |_, cx| {
                                 terminal_panel
-                                    .update(cx, |panel, cx| panel.add_terminal(None, cx))
+                                    .update(cx, |panel, cx| panel.add_terminal(None, None, cx))
                                     .log_err();
                             })
                             .tooltip(|cx| Tooltip::text("New Terminal", cx)),
@@ -266,7 +267,25 @@ impl TerminalPanel {
         };
 
         this.update(cx, |this, cx| {
-            this.add_terminal(Some(action.working_directory.clone()), cx)
+            this.add_terminal(Some(action.working_directory.clone()), None, cx)
+        })
+    }
+
+    fn spawn_in_terminal(
+        workspace: &mut Workspace,
+        action: &workspace::SpawnInTerminal,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(this) = workspace.focus_panel::<Self>(cx) else {
+            return;
+        };
+
+        this.update(cx, |this, cx| {
+            this.add_terminal(
+                action.working_directory.clone(),
+                Some(action.command.clone()),
+                cx,
+            )
         })
     }
 
@@ -280,10 +299,15 @@ impl TerminalPanel {
             return;
         };
 
-        this.update(cx, |this, cx| this.add_terminal(None, cx))
+        this.update(cx, |this, cx| this.add_terminal(None, None, cx))
     }
 
-    fn add_terminal(&mut self, working_directory: Option<PathBuf>, cx: &mut ViewContext<Self>) {
+    fn add_terminal(
+        &mut self,
+        working_directory: Option<PathBuf>,
+        command: Option<String>,
+        cx: &mut ViewContext<Self>,
+    ) {
         let workspace = self.workspace.clone();
         cx.spawn(|this, mut cx| async move {
             let pane = this.update(&mut cx, |this, _| this.pane.clone())?;
@@ -302,6 +326,9 @@ impl TerminalPanel {
                         .create_terminal(working_directory, window, cx)
                         .log_err()
                 }) {
+                    if let Some(command) = command {
+                        terminal.update(cx, |terminal, _| terminal.input(format!("{command}\n")));
+                    }
                     let terminal = Box::new(cx.new_view(|cx| {
                         TerminalView::new(
                             terminal,
@@ -451,7 +478,7 @@ impl Panel for TerminalPanel {
 
     fn set_active(&mut self, active: bool, cx: &mut ViewContext<Self>) {
         if active && self.pane.read(cx).items_len() == 0 {
-            self.add_terminal(None, cx)
+            self.add_terminal(None, None, cx)
         }
     }
 
@@ -1,13 +1,18 @@
 use crate::{
-    h_flex, prelude::*, v_flex, Icon, IconName, KeyBinding, Label, List, ListItem, ListSeparator,
+    h_flex, prelude::*, v_flex, Icon, IconName, KeyBinding, Label, ListItem, ListSeparator,
     ListSubHeader,
 };
 use gpui::{
-    px, Action, AnyElement, AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView,
-    IntoElement, Render, Subscription, View, VisualContext,
+    canvas, overlay, px, Action, AnchorCorner, AnyElement, AppContext, Bounds, DismissEvent,
+    EventEmitter, FocusHandle, FocusableView, IntoElement, KeyDownEvent, MouseDownEvent, Pixels,
+    Point, Render, ScrollHandle, Subscription, Task, View, VisualContext,
 };
-use menu::{SelectFirst, SelectLast, SelectNext, SelectPrev};
-use std::{rc::Rc, time::Duration};
+use menu::{SelectChild, SelectFirst, SelectLast, SelectNext, SelectParent, SelectPrev};
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+/// How long after the last typed character the next one starts a new search, rather than
+/// extending the typed prefix.
+pub const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
 
 enum ContextMenuItem {
     Separator,
@@ -15,13 +20,25 @@ enum ContextMenuItem {
     Entry {
         label: SharedString,
         icon: Option<IconName>,
+        leading_icon: Option<IconName>,
         handler: Rc<dyn Fn(&mut WindowContext)>,
         action: Option<Box<dyn Action>>,
+        disabled: bool,
     },
     CustomEntry {
         entry_render: Box<dyn Fn(&mut WindowContext) -> AnyElement>,
         handler: Rc<dyn Fn(&mut WindowContext)>,
     },
+    Submenu {
+        label: SharedString,
+        builder: Rc<dyn Fn(ContextMenu, &mut WindowContext) -> ContextMenu>,
+    },
+}
+
+struct OpenSubmenu {
+    index: usize,
+    menu: View<ContextMenu>,
+    _subscription: Subscription,
 }
 
 pub struct ContextMenu {
@@ -31,6 +48,17 @@ pub struct ContextMenu {
     selected_index: Option<usize>,
     delayed: bool,
     clicked: bool,
+    confirmed: bool,
+    submenu: Option<OpenSubmenu>,
+    is_submenu: bool,
+    // Where the menu was last painted, so that submenus can open on whichever side of it
+    // has room
+    bounds: Rc<Cell<Bounds<Pixels>>>,
+    // Scrolls the items of menus that are taller than the window
+    scroll_handle: ScrollHandle,
+    // The characters typed to jump to an item, which are forgotten after a pause
+    type_ahead: String,
+    _clear_type_ahead: Option<Task<()>>,
     _on_blur_subscription: Subscription,
 }
 
@@ -51,9 +79,12 @@ impl ContextMenu {
     ) -> View<Self> {
         cx.new_view(|cx| {
             let focus_handle = cx.focus_handle();
-            let _on_blur_subscription = cx.on_blur(&focus_handle, |this: &mut ContextMenu, cx| {
-                this.cancel(&menu::Cancel, cx)
-            });
+            // Submenus are rendered within their parent menu, so focusing one leaves the
+            // parent open
+            let _on_blur_subscription = cx
+                .on_focus_out(&focus_handle, |this: &mut ContextMenu, cx| {
+                    this.cancel(&menu::Cancel, cx)
+                });
             cx.refresh();
             f(
                 Self {
@@ -63,6 +94,13 @@ impl ContextMenu {
                     selected_index: None,
                     delayed: false,
                     clicked: false,
+                    confirmed: false,
+                    submenu: None,
+                    is_submenu: false,
+                    bounds: Default::default(),
+                    scroll_handle: ScrollHandle::new(),
+                    type_ahead: String::new(),
+                    _clear_type_ahead: None,
                     _on_blur_subscription,
                 },
                 cx,
@@ -95,7 +133,9 @@ impl ContextMenu {
             label: label.into(),
             handler: Rc::new(handler),
             icon: None,
+            leading_icon: None,
             action,
+            disabled: false,
         });
         self
     }
@@ -118,6 +158,25 @@ impl ContextMenu {
             action: Some(action.boxed_clone()),
             handler: Rc::new(move |cx| cx.dispatch_action(action.boxed_clone())),
             icon: None,
+            leading_icon: None,
+            disabled: false,
+        });
+        self
+    }
+
+    pub fn action_with_icon(
+        mut self,
+        label: impl Into<SharedString>,
+        icon: IconName,
+        action: Box<dyn Action>,
+    ) -> Self {
+        self.items.push(ContextMenuItem::Entry {
+            label: label.into(),
+            action: Some(action.boxed_clone()),
+            handler: Rc::new(move |cx| cx.dispatch_action(action.boxed_clone())),
+            icon: None,
+            leading_icon: Some(icon),
+            disabled: false,
         });
         self
     }
@@ -128,29 +187,206 @@ impl ContextMenu {
             action: Some(action.boxed_clone()),
             handler: Rc::new(move |cx| cx.dispatch_action(action.boxed_clone())),
             icon: Some(IconName::Link),
+            leading_icon: None,
+            disabled: false,
         });
         self
     }
 
+    /// Adds an entry for `action` that is shown greyed out, and can't be selected or run.
+    pub fn disabled_action(
+        mut self,
+        label: impl Into<SharedString>,
+        action: Box<dyn Action>,
+    ) -> Self {
+        self.items.push(ContextMenuItem::Entry {
+            label: label.into(),
+            action: Some(action),
+            handler: Rc::new(|_| {}),
+            icon: None,
+            leading_icon: None,
+            disabled: true,
+        });
+        self
+    }
+
+    /// Adds an entry which opens a nested menu holding the items that `builder` adds, when
+    /// it is hovered or chosen.
+    pub fn submenu(
+        mut self,
+        label: impl Into<SharedString>,
+        builder: impl Fn(ContextMenu, &mut WindowContext) -> ContextMenu + 'static,
+    ) -> Self {
+        self.items.push(ContextMenuItem::Submenu {
+            label: label.into(),
+            builder: Rc::new(builder),
+        });
+        self
+    }
+
+    /// Like `disabled_action`, with an icon shown before the label.
+    pub fn disabled_action_with_icon(
+        mut self,
+        label: impl Into<SharedString>,
+        icon: IconName,
+        action: Box<dyn Action>,
+    ) -> Self {
+        self.items.push(ContextMenuItem::Entry {
+            label: label.into(),
+            action: Some(action),
+            handler: Rc::new(|_| {}),
+            icon: None,
+            leading_icon: Some(icon),
+            disabled: true,
+        });
+        self
+    }
+
+    /// Inserts the items that `build` adds before the item at `ix` of the open menu, keeping
+    /// the selection, the typed prefix and the open submenu on the items they were on.
+    pub fn insert_items(
+        &mut self,
+        ix: usize,
+        build: impl FnOnce(ContextMenu, &mut WindowContext) -> ContextMenu,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let built = ContextMenu::build(cx, build);
+        let items = built.update(cx, |menu, _| std::mem::take(&mut menu.items));
+        let ix = ix.min(self.items.len());
+        let count = items.len();
+        self.items.splice(ix..ix, items);
+        if let Some(selected_index) = self.selected_index.as_mut() {
+            if *selected_index >= ix {
+                *selected_index += count;
+            }
+        }
+        if let Some(submenu) = self.submenu.as_mut() {
+            if submenu.index >= ix {
+                submenu.index += count;
+            }
+        }
+        cx.notify();
+    }
+
     pub fn confirm(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
-        match self.selected_index.and_then(|ix| self.items.get(ix)) {
-            Some(
+        match self
+            .selected_index
+            .and_then(|ix| Some((ix, self.items.get(ix)?)))
+        {
+            Some((
+                _,
                 ContextMenuItem::Entry { handler, .. }
                 | ContextMenuItem::CustomEntry { handler, .. },
-            ) => (handler)(cx),
+            )) => {
+                self.confirmed = true;
+                (handler)(cx)
+            }
+            Some((ix, ContextMenuItem::Submenu { .. })) => {
+                self.open_submenu(ix, cx);
+                return;
+            }
             _ => {}
         }
 
         cx.emit(DismissEvent);
     }
 
+    /// Opens the submenu at `ix` and moves the focus into it, as choosing it with the
+    /// keyboard does.
+    pub fn open_submenu(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.show_submenu(ix, cx);
+        if let Some(submenu) = &self.submenu {
+            let submenu = submenu.menu.clone();
+            cx.focus_view(&submenu);
+            submenu.update(cx, |submenu, cx| submenu.select_first(&SelectFirst, cx));
+        }
+    }
+
+    /// Returns the open submenu, if there is one.
+    pub fn submenu_view(&self) -> Option<View<ContextMenu>> {
+        self.submenu.as_ref().map(|submenu| submenu.menu.clone())
+    }
+
+    // Opens the submenu at `ix` without focusing it, as hovering it does
+    fn show_submenu(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let Some(ContextMenuItem::Submenu { builder, .. }) = self.items.get(ix) else {
+            return;
+        };
+        self.selected_index = Some(ix);
+        cx.notify();
+        if matches!(&self.submenu, Some(submenu) if submenu.index == ix) {
+            return;
+        }
+
+        let builder = builder.clone();
+        let action_context = self.action_context.clone();
+        let menu = ContextMenu::build(cx, move |mut menu, cx| {
+            menu.is_submenu = true;
+            menu.action_context = action_context;
+            builder(menu, cx)
+        });
+        let _subscription = cx.subscribe(&menu, |this, submenu, _: &DismissEvent, cx| {
+            let (confirmed, focused) = {
+                let submenu = submenu.read(cx);
+                (submenu.confirmed, submenu.focus_handle.contains_focused(cx))
+            };
+            if confirmed {
+                this.confirmed = true;
+                cx.emit(DismissEvent);
+                return;
+            }
+            // Only the submenu closes when it's cancelled with the keyboard
+            this.submenu = None;
+            if focused {
+                cx.focus(&this.focus_handle);
+            }
+            cx.notify();
+        });
+        self.submenu = Some(OpenSubmenu {
+            index: ix,
+            menu,
+            _subscription,
+        });
+    }
+
+    fn close_submenu(&mut self, cx: &mut ViewContext<Self>) {
+        if self.submenu.take().is_some() {
+            cx.notify();
+        }
+    }
+
+    fn submenu_contains(&self, position: Point<Pixels>, cx: &AppContext) -> bool {
+        self.submenu.as_ref().map_or(false, |submenu| {
+            let submenu = submenu.menu.read(cx);
+            submenu.bounds.get().contains(&position) || submenu.submenu_contains(position, cx)
+        })
+    }
+
+    fn select_child(&mut self, _: &SelectChild, cx: &mut ViewContext<Self>) {
+        match self.selected_index {
+            Some(ix) if matches!(self.items.get(ix), Some(ContextMenuItem::Submenu { .. })) => {
+                self.open_submenu(ix, cx)
+            }
+            _ => cx.propagate(),
+        }
+    }
+
+    fn select_parent(&mut self, _: &SelectParent, cx: &mut ViewContext<Self>) {
+        if self.is_submenu {
+            self.cancel(&menu::Cancel, cx)
+        } else {
+            cx.propagate()
+        }
+    }
+
     pub fn cancel(&mut self, _: &menu::Cancel, cx: &mut ViewContext<Self>) {
         cx.emit(DismissEvent);
         cx.emit(DismissEvent);
     }
 
-    fn select_first(&mut self, _: &SelectFirst, cx: &mut ViewContext<Self>) {
+    pub fn select_first(&mut self, _: &SelectFirst, cx: &mut ViewContext<Self>) {
         self.selected_index = self.items.iter().position(|item| item.is_selectable());
+        self.scroll_to_selected();
         cx.notify();
     }
 
@@ -158,6 +394,7 @@ impl ContextMenu {
         for (ix, item) in self.items.iter().enumerate().rev() {
             if item.is_selectable() {
                 self.selected_index = Some(ix);
+                self.scroll_to_selected();
                 return Some(ix);
             }
         }
@@ -170,18 +407,18 @@ impl ContextMenu {
         }
     }
 
-    fn select_next(&mut self, _: &SelectNext, cx: &mut ViewContext<Self>) {
+    pub fn select_next(&mut self, _: &SelectNext, cx: &mut ViewContext<Self>) {
         if let Some(ix) = self.selected_index {
             for (ix, item) in self.items.iter().enumerate().skip(ix + 1) {
                 if item.is_selectable() {
                     self.selected_index = Some(ix);
+                    self.scroll_to_selected();
                     cx.notify();
-                    break;
+                    return;
                 }
             }
-        } else {
-            self.select_first(&Default::default(), cx);
         }
+        self.select_first(&Default::default(), cx);
     }
 
     pub fn select_prev(&mut self, _: &SelectPrev, cx: &mut ViewContext<Self>) {
@@ -189,15 +426,127 @@ impl ContextMenu {
             for (ix, item) in self.items.iter().enumerate().take(ix).rev() {
                 if item.is_selectable() {
                     self.selected_index = Some(ix);
+                    self.scroll_to_selected();
                     cx.notify();
-                    break;
+                    return;
                 }
             }
-        } else {
-            self.handle_select_last(&Default::default(), cx);
+        }
+        self.handle_select_last(&Default::default(), cx);
+    }
+
+    fn handle_key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        let modifiers = keystroke.modifiers;
+        if modifiers.control || modifiers.alt || modifiers.command || modifiers.function {
+            return;
+        }
+        let typed = match keystroke.ime_key.as_deref() {
+            Some(ime_key) => ime_key,
+            None if keystroke.key == "space" => " ",
+            None if keystroke.key.chars().count() == 1 => keystroke.key.as_str(),
+            None => return,
+        };
+        // Spaces can only continue a prefix, as items don't start with them
+        if typed.trim().is_empty() && self.type_ahead.is_empty() {
+            return;
+        }
+
+        self.type_ahead.push_str(&typed.to_lowercase());
+        self.select_type_ahead_match(cx);
+        self._clear_type_ahead = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(TYPE_AHEAD_TIMEOUT).await;
+            this.update(&mut cx, |this, _| this.type_ahead.clear()).ok();
+        }));
+        cx.stop_propagation();
+    }
+
+    /// Selects the next item whose label starts with the typed characters. Typing one
+    /// character, or the same one repeatedly, cycles through the items starting with it.
+    fn select_type_ahead_match(&mut self, cx: &mut ViewContext<Self>) {
+        let mut typed_chars = self.type_ahead.chars();
+        let first_char = typed_chars.next();
+        let repeats_first_char = typed_chars.all(|c| Some(c) == first_char);
+
+        // Longer prefixes can still match the selected item, while single characters move
+        // on from it
+        let (selected, after_selected) = match self.selected_index {
+            Some(ix) => (ix, ix + 1),
+            None => (0, 0),
+        };
+        let item_count = self.items.len();
+        let find_from = |start: usize, prefix: &str| {
+            (0..item_count)
+                .map(|offset| (start + offset) % item_count)
+                .find(|&ix| {
+                    self.items[ix].is_selectable()
+                        && self.items[ix]
+                            .label()
+                            .map_or(false, |label| label.to_lowercase().starts_with(prefix))
+                })
+        };
+
+        let mut found = None;
+        if self.type_ahead.chars().count() > 1 {
+            found = find_from(selected, &self.type_ahead);
+        }
+        if found.is_none() && repeats_first_char {
+            if let Some(first_char) = first_char {
+                found = find_from(after_selected, &first_char.to_string());
+            }
+        }
+        if let Some(ix) = found {
+            self.selected_index = Some(ix);
+            self.scroll_to_selected();
+            cx.notify();
         }
     }
 
+    // Keeps the item selected with the keyboard in view when the menu scrolls
+    fn scroll_to_selected(&self) {
+        if let Some(ix) = self.selected_index {
+            self.scroll_handle.scroll_to_item(ix);
+        }
+    }
+
+    /// Returns whether one of the menu's items was chosen, as opposed to the menu
+    /// being dismissed without running anything.
+    pub fn confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected_index
+    }
+
+    /// Returns the label of each item in the menu, or `None` for separators
+    /// and custom entries.
+    pub fn item_labels(&self) -> Vec<Option<SharedString>> {
+        self.items
+            .iter()
+            .map(|item| item.label().cloned())
+            .collect()
+    }
+
+    /// Returns the icon shown before each item's label, if it has one.
+    pub fn item_icons(&self) -> Vec<Option<IconName>> {
+        self.items
+            .iter()
+            .map(|item| match item {
+                ContextMenuItem::Entry { leading_icon, .. } => *leading_icon,
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns whether each item in the menu is disabled.
+    pub fn item_disabled(&self) -> Vec<bool> {
+        self.items
+            .iter()
+            .map(|item| matches!(item, ContextMenuItem::Entry { disabled: true, .. }))
+            .collect()
+    }
+
     pub fn on_action_dispatch(&mut self, dispatched: &Box<dyn Action>, cx: &mut ViewContext<Self>) {
         if self.clicked {
             cx.propagate();
@@ -207,6 +556,7 @@ impl ContextMenu {
         if let Some(ix) = self.items.iter().position(|item| {
             if let ContextMenuItem::Entry {
                 action: Some(action),
+                disabled: false,
                 ..
             } = item
             {
@@ -224,6 +574,7 @@ impl ContextMenu {
                     .timer(Duration::from_millis(50))
                     .await;
                 this.update(&mut cx, |this, cx| {
+                    this.confirmed = true;
                     cx.dispatch_action(action);
                     this.cancel(&menu::Cancel, cx)
                 })
@@ -236,119 +587,318 @@ impl ContextMenu {
 }
 
 impl ContextMenuItem {
+    fn label(&self) -> Option<&SharedString> {
+        match self {
+            Self::Header(label) | Self::Entry { label, .. } | Self::Submenu { label, .. } => {
+                Some(label)
+            }
+            Self::Separator | Self::CustomEntry { .. } => None,
+        }
+    }
+
     fn is_selectable(&self) -> bool {
-        matches!(self, Self::Entry { .. } | Self::CustomEntry { .. })
+        matches!(
+            self,
+            Self::Entry {
+                disabled: false,
+                ..
+            } | Self::CustomEntry { .. }
+                | Self::Submenu { .. }
+        )
     }
 }
 
 impl Render for ContextMenu {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        div().elevation_2(cx).flex().flex_row().child(
-            v_flex()
-                .min_w(px(200.))
-                .track_focus(&self.focus_handle)
-                .on_mouse_down_out(cx.listener(|this, _, cx| this.cancel(&menu::Cancel, cx)))
-                .key_context("menu")
-                .on_action(cx.listener(ContextMenu::select_first))
-                .on_action(cx.listener(ContextMenu::handle_select_last))
-                .on_action(cx.listener(ContextMenu::select_next))
-                .on_action(cx.listener(ContextMenu::select_prev))
-                .on_action(cx.listener(ContextMenu::confirm))
-                .on_action(cx.listener(ContextMenu::cancel))
-                .when(!self.delayed, |mut el| {
-                    for item in self.items.iter() {
-                        if let ContextMenuItem::Entry {
-                            action: Some(action),
-                            ..
-                        } = item
-                        {
-                            el = el.on_boxed_action(
-                                &**action,
-                                cx.listener(ContextMenu::on_action_dispatch),
-                            );
+        let has_leading_icons = self.items.iter().any(|item| {
+            matches!(
+                item,
+                ContextMenuItem::Entry {
+                    leading_icon: Some(_),
+                    ..
+                }
+            )
+        });
+
+        let bounds = self.bounds.clone();
+        div()
+            .elevation_2(cx)
+            .flex()
+            .flex_row()
+            .child(
+                canvas(move |painted_bounds, _| bounds.set(*painted_bounds))
+                    .absolute()
+                    .size_full(),
+            )
+            .child(
+                v_flex()
+                    .min_w(px(200.))
+                    .track_focus(&self.focus_handle)
+                    .on_mouse_down_out(cx.listener(|this, event: &MouseDownEvent, cx| {
+                        // Clicks in a submenu are outside of its parent, but are handled by it
+                        if !this.submenu_contains(event.position, cx) {
+                            this.cancel(&menu::Cancel, cx)
                         }
-                    }
-                    el
-                })
-                .flex_none()
-                .child(List::new().children(self.items.iter_mut().enumerate().map(
-                    |(ix, item)| match item {
-                        ContextMenuItem::Separator => ListSeparator.into_any_element(),
-                        ContextMenuItem::Header(header) => {
-                            ListSubHeader::new(header.clone()).into_any_element()
+                    }))
+                    .key_context("menu ContextMenu")
+                    .on_action(cx.listener(ContextMenu::select_first))
+                    .on_action(cx.listener(ContextMenu::handle_select_last))
+                    .on_action(cx.listener(ContextMenu::select_next))
+                    .on_action(cx.listener(ContextMenu::select_prev))
+                    .on_action(cx.listener(ContextMenu::select_child))
+                    .on_action(cx.listener(ContextMenu::select_parent))
+                    .on_action(cx.listener(ContextMenu::confirm))
+                    .on_action(cx.listener(ContextMenu::cancel))
+                    .on_key_down(cx.listener(ContextMenu::handle_key_down))
+                    .when(!self.delayed, |mut el| {
+                        for item in self.items.iter() {
+                            if let ContextMenuItem::Entry {
+                                action: Some(action),
+                                disabled: false,
+                                ..
+                            } = item
+                            {
+                                el = el.on_boxed_action(
+                                    &**action,
+                                    cx.listener(ContextMenu::on_action_dispatch),
+                                );
+                            }
                         }
-                        ContextMenuItem::Entry {
-                            label,
-                            handler,
-                            icon,
-                            action,
-                        } => {
-                            let handler = handler.clone();
-                            let menu = cx.view().downgrade();
-
-                            let label_element = if let Some(icon) = icon {
-                                h_flex()
-                                    .gap_1()
-                                    .child(Label::new(label.clone()))
-                                    .child(Icon::new(*icon))
-                                    .into_any_element()
-                            } else {
-                                Label::new(label.clone()).into_any_element()
-                            };
-
-                            ListItem::new(ix)
-                                .inset(true)
-                                .selected(Some(ix) == self.selected_index)
-                                .on_click(move |_, cx| {
-                                    handler(cx);
-                                    menu.update(cx, |menu, cx| {
-                                        menu.clicked = true;
-                                        cx.emit(DismissEvent);
-                                    })
-                                    .ok();
-                                })
-                                .child(
-                                    h_flex()
-                                        .w_full()
-                                        .justify_between()
-                                        .child(label_element)
-                                        .debug_selector(|| format!("MENU_ITEM-{}", label))
-                                        .children(action.as_ref().and_then(|action| {
-                                            self.action_context
-                                                .as_ref()
-                                                .map(|focus| {
-                                                    KeyBinding::for_action_in(&**action, focus, cx)
-                                                })
-                                                .unwrap_or_else(|| {
-                                                    KeyBinding::for_action(&**action, cx)
+                        el
+                    })
+                    .flex_none()
+                    .id("context-menu")
+                    // Menus taller than the window are pinned to its top edge by their
+                    // overlay, so scroll their items rather than letting them clip.
+                    .max_h(cx.viewport_size().height)
+                    .overflow_y_scroll()
+                    .py_1()
+                    .track_scroll(&self.scroll_handle)
+                    .children(
+                        self.items
+                            .iter_mut()
+                            .enumerate()
+                            .map(|(ix, item)| match item {
+                                ContextMenuItem::Separator => ListSeparator.into_any_element(),
+                                ContextMenuItem::Header(header) => {
+                                    ListSubHeader::new(header.clone()).into_any_element()
+                                }
+                                ContextMenuItem::Entry {
+                                    label,
+                                    handler,
+                                    icon,
+                                    leading_icon,
+                                    action,
+                                    disabled,
+                                } => {
+                                    let handler = handler.clone();
+                                    let menu = cx.view().downgrade();
+                                    let disabled = *disabled;
+                                    let label_color = if disabled {
+                                        Color::Disabled
+                                    } else {
+                                        Color::Default
+                                    };
+
+                                    let label_element = if let Some(icon) = icon {
+                                        h_flex()
+                                            .gap_1()
+                                            .child(Label::new(label.clone()).color(label_color))
+                                            .child(Icon::new(*icon))
+                                            .into_any_element()
+                                    } else {
+                                        Label::new(label.clone())
+                                            .color(label_color)
+                                            .into_any_element()
+                                    };
+
+                                    // Reserve the icon's space on entries without one, so that all
+                                    // labels stay aligned once any entry has a leading icon.
+                                    let label_element = if has_leading_icons {
+                                        h_flex()
+                                            .gap_1p5()
+                                            .child(match leading_icon {
+                                                Some(icon) => Icon::new(*icon)
+                                                    .size(IconSize::Small)
+                                                    .color(if disabled {
+                                                        Color::Disabled
+                                                    } else {
+                                                        Color::Muted
+                                                    })
+                                                    .into_any_element(),
+                                                None => div()
+                                                    .flex_none()
+                                                    .size(IconSize::Small.rems())
+                                                    .into_any_element(),
+                                            })
+                                            .child(label_element)
+                                            .into_any_element()
+                                    } else {
+                                        label_element
+                                    };
+
+                                    ListItem::new(ix)
+                                        .inset(true)
+                                        .disabled(disabled)
+                                        .selected(Some(ix) == self.selected_index)
+                                        .when(self.submenu.is_some(), |item| {
+                                            item.on_hover(cx.listener(
+                                                |this, hovered: &bool, cx| {
+                                                    if *hovered {
+                                                        this.close_submenu(cx)
+                                                    }
+                                                },
+                                            ))
+                                        })
+                                        .when(!disabled, |item| {
+                                            item.on_click(move |_, cx| {
+                                                handler(cx);
+                                                menu.update(cx, |menu, cx| {
+                                                    menu.clicked = true;
+                                                    menu.confirmed = true;
+                                                    cx.emit(DismissEvent);
                                                 })
-                                                .map(|binding| div().ml_1().child(binding))
-                                        })),
-                                )
-                                .into_any_element()
-                        }
-                        ContextMenuItem::CustomEntry {
-                            entry_render,
-                            handler,
-                        } => {
-                            let handler = handler.clone();
-                            let menu = cx.view().downgrade();
-                            ListItem::new(ix)
-                                .inset(true)
-                                .selected(Some(ix) == self.selected_index)
-                                .on_click(move |_, cx| {
-                                    handler(cx);
-                                    menu.update(cx, |menu, cx| {
-                                        menu.clicked = true;
-                                        cx.emit(DismissEvent);
-                                    })
-                                    .ok();
-                                })
-                                .child(entry_render(cx))
-                                .into_any_element()
-                        }
-                    },
-                ))),
-        )
+                                                .ok();
+                                            })
+                                        })
+                                        .child(
+                                            h_flex()
+                                                .w_full()
+                                                .justify_between()
+                                                .child(label_element)
+                                                .debug_selector(|| format!("MENU_ITEM-{}", label))
+                                                .children(action.as_ref().and_then(|action| {
+                                                    self.action_context
+                                                        .as_ref()
+                                                        .map(|focus| {
+                                                            KeyBinding::for_action_in(
+                                                                &**action, focus, cx,
+                                                            )
+                                                        })
+                                                        .unwrap_or_else(|| {
+                                                            KeyBinding::for_action(&**action, cx)
+                                                        })
+                                                        .map(|binding| {
+                                                            div()
+                                                                .ml_1()
+                                                                .child(binding.disabled(disabled))
+                                                        })
+                                                })),
+                                        )
+                                        .into_any_element()
+                                }
+                                ContextMenuItem::CustomEntry {
+                                    entry_render,
+                                    handler,
+                                } => {
+                                    let handler = handler.clone();
+                                    let menu = cx.view().downgrade();
+                                    ListItem::new(ix)
+                                        .inset(true)
+                                        .selected(Some(ix) == self.selected_index)
+                                        .on_click(move |_, cx| {
+                                            handler(cx);
+                                            menu.update(cx, |menu, cx| {
+                                                menu.clicked = true;
+                                                menu.confirmed = true;
+                                                cx.emit(DismissEvent);
+                                            })
+                                            .ok();
+                                        })
+                                        .child(entry_render(cx))
+                                        .into_any_element()
+                                }
+                                ContextMenuItem::Submenu { label, .. } => {
+                                    let label_element = Label::new(label.clone());
+                                    let label_element = if has_leading_icons {
+                                        h_flex()
+                                            .gap_1p5()
+                                            .child(div().flex_none().size(IconSize::Small.rems()))
+                                            .child(label_element)
+                                            .into_any_element()
+                                    } else {
+                                        label_element.into_any_element()
+                                    };
+                                    let open_submenu = self
+                                        .submenu
+                                        .as_ref()
+                                        .filter(|submenu| submenu.index == ix)
+                                        .map(|submenu| submenu.menu.clone());
+
+                                    div()
+                                        .relative()
+                                        .child(
+                                            ListItem::new(ix)
+                                                .inset(true)
+                                                .selected(
+                                                    Some(ix) == self.selected_index
+                                                        || open_submenu.is_some(),
+                                                )
+                                                .on_click(cx.listener(move |this, _, cx| {
+                                                    this.open_submenu(ix, cx)
+                                                }))
+                                                .on_hover(cx.listener(
+                                                    move |this, hovered: &bool, cx| {
+                                                        if *hovered {
+                                                            this.show_submenu(ix, cx)
+                                                        }
+                                                    },
+                                                ))
+                                                .child(
+                                                    h_flex()
+                                                        .w_full()
+                                                        .justify_between()
+                                                        .child(label_element)
+                                                        .debug_selector(|| {
+                                                            format!("MENU_ITEM-{}", label)
+                                                        })
+                                                        .child(
+                                                            Icon::new(IconName::ChevronRight)
+                                                                .size(IconSize::Small)
+                                                                .color(Color::Muted),
+                                                        ),
+                                                ),
+                                        )
+                                        .when_some(open_submenu, |item, submenu| {
+                                            // Open to the right of the menu, unless that would run
+                                            // off the window
+                                            let submenu_width =
+                                                submenu.read(cx).bounds.get().size.width;
+                                            let submenu_width = if submenu_width > Pixels::ZERO {
+                                                submenu_width
+                                            } else {
+                                                px(200.)
+                                            };
+                                            let opens_left = self.bounds.get().right()
+                                                + submenu_width
+                                                > cx.viewport_size().width;
+                                            item.child(
+                                                div()
+                                                    .absolute()
+                                                    .top_0()
+                                                    .map(|holder| {
+                                                        if opens_left {
+                                                            holder.left_0()
+                                                        } else {
+                                                            holder.right_0()
+                                                        }
+                                                    })
+                                                    .child(
+                                                        overlay()
+                                                            .anchor(if opens_left {
+                                                                AnchorCorner::TopRight
+                                                            } else {
+                                                                AnchorCorner::TopLeft
+                                                            })
+                                                            .snap_to_window()
+                                                            .child(submenu),
+                                                    ),
+                                            )
+                                        })
+                                        .into_any_element()
+                                }
+                            }),
+                    ),
+            )
     }
 }
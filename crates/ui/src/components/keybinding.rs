@@ -8,6 +8,7 @@ pub struct KeyBinding {
     ///
     /// This should always contain at least one element.
     key_binding: gpui::KeyBinding,
+    disabled: bool,
 }
 
 impl RenderOnce for KeyBinding {
@@ -17,29 +18,38 @@ impl RenderOnce for KeyBinding {
             .gap_2()
             .children(self.key_binding.keystrokes().iter().map(|keystroke| {
                 let key_icon = Self::icon_for_key(keystroke);
+                let color = if self.disabled {
+                    Color::Disabled
+                } else {
+                    Color::Muted
+                };
 
                 h_flex()
                     .flex_none()
                     .gap_0p5()
                     .p_0p5()
                     .rounded_sm()
-                    .text_color(cx.theme().colors().text_muted)
-                    .when(keystroke.modifiers.function, |el| el.child(Key::new("fn")))
+                    .text_color(color.color(cx))
+                    .when(keystroke.modifiers.function, |el| {
+                        el.child(Key::new("fn").color(color))
+                    })
                     .when(keystroke.modifiers.control, |el| {
-                        el.child(KeyIcon::new(IconName::Control))
+                        el.child(KeyIcon::new(IconName::Control).color(color))
                     })
                     .when(keystroke.modifiers.alt, |el| {
-                        el.child(KeyIcon::new(IconName::Option))
+                        el.child(KeyIcon::new(IconName::Option).color(color))
                     })
                     .when(keystroke.modifiers.command, |el| {
-                        el.child(KeyIcon::new(IconName::Command))
+                        el.child(KeyIcon::new(IconName::Command).color(color))
                     })
                     .when(keystroke.modifiers.shift, |el| {
-                        el.child(KeyIcon::new(IconName::Shift))
+                        el.child(KeyIcon::new(IconName::Shift).color(color))
+                    })
+                    .when_some(key_icon, |el, icon| {
+                        el.child(KeyIcon::new(icon).color(color))
                     })
-                    .when_some(key_icon, |el, icon| el.child(KeyIcon::new(icon)))
                     .when(key_icon.is_none(), |el| {
-                        el.child(Key::new(keystroke.key.to_uppercase().clone()))
+                        el.child(Key::new(keystroke.key.to_uppercase().clone()).color(color))
                     })
             }))
     }
@@ -82,13 +92,25 @@ impl KeyBinding {
     }
 
     pub fn new(key_binding: gpui::KeyBinding) -> Self {
-        Self { key_binding }
+        Self {
+            key_binding,
+            disabled: false,
+        }
+    }
+}
+
+impl Disableable for KeyBinding {
+    /// Greys out the keys, such as for the binding of an action which can't currently be run.
+    fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
     }
 }
 
 #[derive(IntoElement)]
 pub struct Key {
     key: SharedString,
+    color: Color,
 }
 
 impl RenderOnce for Key {
@@ -107,34 +129,49 @@ impl RenderOnce for Key {
             .h(rems(14. / 16.))
             .text_ui()
             .line_height(relative(1.))
-            .text_color(cx.theme().colors().text_muted)
+            .text_color(self.color.color(cx))
             .child(self.key.clone())
     }
 }
 
 impl Key {
     pub fn new(key: impl Into<SharedString>) -> Self {
-        Self { key: key.into() }
+        Self {
+            key: key.into(),
+            color: Color::Muted,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
     }
 }
 
 #[derive(IntoElement)]
 pub struct KeyIcon {
     icon: IconName,
+    color: Color,
 }
 
 impl RenderOnce for KeyIcon {
     fn render(self, _cx: &mut WindowContext) -> impl IntoElement {
-        div().w(rems(14. / 16.)).child(
-            Icon::new(self.icon)
-                .size(IconSize::Small)
-                .color(Color::Muted),
-        )
+        div()
+            .w(rems(14. / 16.))
+            .child(Icon::new(self.icon).size(IconSize::Small).color(self.color))
     }
 }
 
 impl KeyIcon {
     pub fn new(icon: IconName) -> Self {
-        Self { icon }
+        Self {
+            icon,
+            color: Color::Muted,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
     }
 }
@@ -31,6 +31,7 @@ pub struct ListItem {
     toggle: Option<bool>,
     inset: bool,
     on_click: Option<Box<dyn Fn(&ClickEvent, &mut WindowContext) + 'static>>,
+    on_hover: Option<Box<dyn Fn(&bool, &mut WindowContext) + 'static>>,
     on_toggle: Option<Arc<dyn Fn(&ClickEvent, &mut WindowContext) + 'static>>,
     tooltip: Option<Box<dyn Fn(&mut WindowContext) -> AnyView + 'static>>,
     on_secondary_mouse_down: Option<Box<dyn Fn(&MouseDownEvent, &mut WindowContext) + 'static>>,
@@ -52,6 +53,7 @@ impl ListItem {
             toggle: None,
             inset: false,
             on_click: None,
+            on_hover: None,
             on_secondary_mouse_down: None,
             on_toggle: None,
             tooltip: None,
@@ -69,6 +71,12 @@ impl ListItem {
         self
     }
 
+    /// Called with true when the pointer starts hovering the item, and false when it stops.
+    pub fn on_hover(mut self, handler: impl Fn(&bool, &mut WindowContext) + 'static) -> Self {
+        self.on_hover = Some(Box::new(handler));
+        self
+    }
+
     pub fn on_secondary_mouse_down(
         mut self,
         handler: impl Fn(&MouseDownEvent, &mut WindowContext) + 'static,
@@ -198,6 +206,7 @@ impl RenderOnce for ListItem {
                     .when_some(self.on_click, |this, on_click| {
                         this.cursor_pointer().on_click(on_click)
                     })
+                    .when_some(self.on_hover, |this, on_hover| this.on_hover(on_hover))
                     .when_some(self.on_secondary_mouse_down, |this, on_mouse_down| {
                         this.on_mouse_down(MouseButton::Right, move |event, cx| {
                             (on_mouse_down)(event, cx)
@@ -143,6 +143,11 @@ pub trait Item: FocusableView + EventEmitter<Self::Event> {
     fn has_conflict(&self, _: &AppContext) -> bool {
         false
     }
+    /// Whether the item's unsaved changes are kept across restarts, so that they don't
+    /// need to be saved before quitting.
+    fn persists_unsaved_changes(&self, _: &AppContext) -> bool {
+        false
+    }
     fn can_save(&self, _cx: &AppContext) -> bool {
         false
     }
@@ -257,6 +262,7 @@ pub trait ItemHandle: 'static + Send {
     fn to_any(&self) -> AnyView;
     fn is_dirty(&self, cx: &AppContext) -> bool;
     fn has_conflict(&self, cx: &AppContext) -> bool;
+    fn persists_unsaved_changes(&self, cx: &AppContext) -> bool;
     fn can_save(&self, cx: &AppContext) -> bool;
     fn save(&self, project: Model<Project>, cx: &mut WindowContext) -> Task<Result<()>>;
     fn save_as(
@@ -562,6 +568,10 @@ impl<T: Item> ItemHandle for View<T> {
         self.read(cx).has_conflict(cx)
     }
 
+    fn persists_unsaved_changes(&self, cx: &AppContext) -> bool {
+        self.read(cx).persists_unsaved_changes(cx)
+    }
+
     fn can_save(&self, cx: &AppContext) -> bool {
         self.read(cx).can_save(cx)
     }
@@ -768,7 +778,7 @@ impl<T: FollowableItem> FollowableItemHandle for View<T> {
 
 #[cfg(any(test, feature = "test-support"))]
 pub mod test {
-    use super::{Item, ItemEvent};
+    use super::{Item, ItemEvent, ProjectItem};
     use crate::{ItemId, ItemNavHistory, Pane, Workspace, WorkspaceId};
     use gpui::{
         AnyElement, AppContext, Context as _, EntityId, EventEmitter, FocusableView,
@@ -793,6 +803,7 @@ pub mod test {
         pub is_dirty: bool,
         pub is_singleton: bool,
         pub has_conflict: bool,
+        pub persists_unsaved_changes: bool,
         pub project_items: Vec<Model<TestProjectItem>>,
         pub nav_history: Option<ItemNavHistory>,
         pub tab_descriptions: Option<Vec<&'static str>>,
@@ -835,6 +846,18 @@ pub mod test {
         }
     }
 
+    impl ProjectItem for TestItem {
+        type Item = TestProjectItem;
+
+        fn for_project_item(
+            _: Model<Project>,
+            item: Model<TestProjectItem>,
+            cx: &mut ViewContext<Self>,
+        ) -> Self {
+            Self::new(cx).with_project_items(&[item])
+        }
+    }
+
     impl TestItem {
         pub fn new(cx: &mut ViewContext<Self>) -> Self {
             Self {
@@ -845,6 +868,7 @@ pub mod test {
                 reload_count: 0,
                 is_dirty: false,
                 has_conflict: false,
+                persists_unsaved_changes: false,
                 project_items: Vec::new(),
                 is_singleton: true,
                 nav_history: None,
@@ -881,6 +905,11 @@ pub mod test {
             self
         }
 
+        pub fn with_persisted_unsaved_changes(mut self, persists: bool) -> Self {
+            self.persists_unsaved_changes = persists;
+            self
+        }
+
         pub fn with_project_items(mut self, items: &[Model<TestProjectItem>]) -> Self {
             self.project_items.clear();
             self.project_items.extend(items.iter().cloned());
@@ -990,6 +1019,7 @@ pub mod test {
                 is_dirty: self.is_dirty,
                 is_singleton: self.is_singleton,
                 has_conflict: self.has_conflict,
+                persists_unsaved_changes: self.persists_unsaved_changes,
                 project_items: self.project_items.clone(),
                 nav_history: None,
                 tab_descriptions: None,
@@ -1007,6 +1037,10 @@ pub mod test {
             self.has_conflict
         }
 
+        fn persists_unsaved_changes(&self, _: &AppContext) -> bool {
+            self.persists_unsaved_changes
+        }
+
         fn can_save(&self, cx: &AppContext) -> bool {
             !self.project_items.is_empty()
                 && self
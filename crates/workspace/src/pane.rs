@@ -9,9 +9,9 @@ use collections::{HashMap, HashSet, VecDeque};
 use futures::{stream::FuturesUnordered, StreamExt};
 use gpui::{
     actions, impl_actions, overlay, prelude::*, Action, AnchorCorner, AnyElement, AppContext,
-    AsyncWindowContext, DismissEvent, Div, DragMoveEvent, EntityId, EventEmitter, ExternalPaths,
-    FocusHandle, FocusableView, Model, MouseButton, NavigationDirection, Pixels, Point,
-    PromptLevel, Render, ScrollHandle, Subscription, Task, View, ViewContext, VisualContext,
+    AsyncWindowContext, ClipboardItem, DismissEvent, Div, DragMoveEvent, EntityId, EventEmitter,
+    ExternalPaths, FocusHandle, FocusableView, Model, MouseButton, NavigationDirection, Pixels,
+    Point, PromptLevel, Render, ScrollHandle, Subscription, Task, View, ViewContext, VisualContext,
     WeakView, WindowContext,
 };
 use parking_lot::Mutex;
@@ -98,6 +98,7 @@ actions!(
         CloseCleanItems,
         CloseItemsToTheLeft,
         CloseItemsToTheRight,
+        CopyPath,
         GoBack,
         DeploySearch,
         GoForward,
@@ -850,6 +851,19 @@ impl Pane {
         })
     }
 
+    /// Copies the absolute path of the item's file to the clipboard, if it has one.
+    pub fn copy_path_by_id(&mut self, item_id: EntityId, cx: &mut ViewContext<Self>) {
+        let Some(item) = self.items.iter().find(|item| item.item_id() == item_id) else {
+            return;
+        };
+        let path = item
+            .project_path(cx)
+            .and_then(|project_path| self.project.read(cx).absolute_path(&project_path, cx));
+        if let Some(path) = path {
+            cx.write_to_clipboard(ClipboardItem::new(path.to_string_lossy().to_string()));
+        }
+    }
+
     pub fn close_all_items(
         &mut self,
         action: &CloseAllItems,
@@ -1377,6 +1391,8 @@ impl Pane {
             }
         };
 
+        let has_path = self.items[ix].project_path(cx).is_some();
+
         let pane = cx.view().downgrade();
         right_click_menu(ix).trigger(tab).menu(move |cx| {
             let pane = pane.clone();
@@ -1434,9 +1450,21 @@ impl Pane {
                             }),
                         );
 
+                    if has_path || single_entry_to_resolve.is_some() {
+                        menu = menu.separator();
+                    }
+                    if has_path {
+                        menu = menu.entry(
+                            "Copy Path",
+                            Some(Box::new(CopyPath)),
+                            cx.handler_for(&pane, move |pane, cx| {
+                                pane.copy_path_by_id(item_id, cx);
+                            }),
+                        );
+                    }
                     if let Some(entry) = single_entry_to_resolve {
                         let entry_id = entry.to_proto();
-                        menu = menu.separator().entry(
+                        menu = menu.entry(
                             "Reveal In Project Panel",
                             Some(Box::new(RevealInProjectPanel { entry_id })),
                             cx.handler_for(&pane, move |pane, cx| {
@@ -1801,6 +1829,11 @@ impl Render for Pane {
                         .map(|task| task.detach_and_log_err(cx));
                 }),
             )
+            .on_action(cx.listener(|pane: &mut Self, _: &CopyPath, cx| {
+                if let Some(item) = pane.active_item() {
+                    pane.copy_path_by_id(item.item_id(), cx);
+                }
+            }))
             .on_action(
                 cx.listener(|pane: &mut Self, action: &RevealInProjectPanel, cx| {
                     pane.project.update(cx, |_, cx| {
@@ -2526,6 +2559,46 @@ mod tests {
         assert_item_labels(&pane, [], cx);
     }
 
+    #[gpui::test]
+    async fn test_copy_path(cx: &mut TestAppContext) {
+        init_test(cx);
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/root", serde_json::json!({ "one.txt": "" }))
+            .await;
+
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let pane = workspace.update(cx, |workspace, _| workspace.active_pane().clone());
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        let untitled = add_labeled_item(&pane, "A", false, cx);
+        let item = pane.update(cx, |pane, cx| {
+            let project_item = cx.new_model(|_| TestProjectItem {
+                entry_id: None,
+                project_path: Some(ProjectPath {
+                    worktree_id,
+                    path: Path::new("one.txt").into(),
+                }),
+            });
+            let item =
+                Box::new(cx.new_view(|cx| TestItem::new(cx).with_project_items(&[project_item])));
+            pane.add_item(item.clone(), false, false, None, cx);
+            item
+        });
+
+        // Items without a file leave the clipboard alone
+        pane.update(cx, |pane, cx| pane.copy_path_by_id(untitled.item_id(), cx));
+        assert_eq!(cx.read_from_clipboard(), None);
+
+        pane.update(cx, |pane, cx| pane.copy_path_by_id(item.item_id(), cx));
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().to_owned()),
+            Some("/root/one.txt".to_owned())
+        );
+    }
+
     fn init_test(cx: &mut TestAppContext) {
         cx.update(|cx| {
             let settings_store = SettingsStore::test(cx);
@@ -155,6 +155,8 @@ define_connection! {
     //   window_width: Option<f32>, // WindowBounds::Fixed RectF width
     //   window_height: Option<f32>, // WindowBounds::Fixed RectF height
     //   display: Option<Uuid>, // Display id
+    //   open_count: usize, // How many times the workspace was opened
+    //   last_opened: Option<String>, // UTC YYYY-MM-DD HH:MM:SS
     // )
     //
     // pane_groups(
@@ -291,6 +293,11 @@ define_connection! {
     // Add pane group flex data
     sql!(
         ALTER TABLE pane_groups ADD COLUMN flexes TEXT;
+    ),
+    // Add the open history used to rank recent projects
+    sql!(
+        ALTER TABLE workspaces ADD COLUMN open_count INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE workspaces ADD COLUMN last_opened TEXT;
     )
     ];
 }
@@ -429,6 +436,28 @@ impl WorkspaceDb {
         }
     }
 
+    query! {
+        fn recent_workspaces_with_open_history() -> Result<Vec<(WorkspaceId, WorkspaceLocation, usize, f64)>> {
+            SELECT
+                workspace_id,
+                workspace_location,
+                open_count,
+                julianday(CURRENT_TIMESTAMP) - julianday(COALESCE(last_opened, timestamp))
+            FROM workspaces
+            WHERE workspace_location IS NOT NULL
+            ORDER BY timestamp DESC
+        }
+    }
+
+    query! {
+        pub(crate) async fn record_workspace_opened(workspace_id: WorkspaceId) -> Result<()> {
+            UPDATE workspaces
+            SET open_count = open_count + 1,
+                last_opened = CURRENT_TIMESTAMP
+            WHERE workspace_id = ?
+        }
+    }
+
     query! {
         async fn delete_stale_workspace(id: WorkspaceId) -> Result<()> {
             DELETE FROM workspaces
@@ -439,9 +468,34 @@ impl WorkspaceDb {
     // Returns the recent locations which are still valid on disk and deletes ones which no longer
     // exist.
     pub async fn recent_workspaces_on_disk(&self) -> Result<Vec<(WorkspaceId, WorkspaceLocation)>> {
+        Ok(self
+            .retain_workspaces_on_disk(self.recent_workspaces()?)
+            .await)
+    }
+
+    // Returns the recent locations which are still valid on disk, ranked by how often and how
+    // recently they were opened, and deletes ones which no longer exist.
+    pub async fn frecent_workspaces_on_disk(
+        &self,
+    ) -> Result<Vec<(WorkspaceId, WorkspaceLocation)>> {
+        let mut workspaces = self.recent_workspaces_with_open_history()?;
+        // The sort is stable, so equally ranked workspaces stay ordered by recency
+        workspaces.sort_by(|(_, _, a_count, a_age), (_, _, b_count, b_age)| {
+            frecency(*b_count, *b_age).total_cmp(&frecency(*a_count, *a_age))
+        });
+        let workspaces = workspaces
+            .into_iter()
+            .map(|(id, location, _, _)| (id, location));
+        Ok(self.retain_workspaces_on_disk(workspaces).await)
+    }
+
+    async fn retain_workspaces_on_disk(
+        &self,
+        workspaces: impl IntoIterator<Item = (WorkspaceId, WorkspaceLocation)>,
+    ) -> Vec<(WorkspaceId, WorkspaceLocation)> {
         let mut result = Vec::new();
         let mut delete_tasks = Vec::new();
-        for (id, location) in self.recent_workspaces()? {
+        for (id, location) in workspaces {
             if location.paths().iter().all(|path| path.exists())
                 && location.paths().iter().any(|path| path.is_dir())
             {
@@ -452,7 +506,7 @@ impl WorkspaceDb {
         }
 
         futures::future::join_all(delete_tasks).await;
-        Ok(result)
+        result
     }
 
     pub async fn last_workspace(&self) -> Result<Option<WorkspaceLocation>> {
@@ -667,6 +721,19 @@ impl WorkspaceDb {
     }
 }
 
+/// Ranks a workspace by how many times it was opened, weighted by how many days ago it was
+/// last opened. Workspaces opened before their opens were counted rank by recency alone.
+fn frecency(open_count: usize, days_since_opened: f64) -> f64 {
+    let recency_weight = match days_since_opened {
+        days if days < 4. => 100.,
+        days if days < 14. => 70.,
+        days if days < 31. => 50.,
+        days if days < 90. => 30.,
+        _ => 10.,
+    };
+    open_count.max(1) as f64 * recency_weight
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1088,4 +1155,45 @@ mod tests {
 
         assert_eq!(workspace.center_group, new_workspace.center_group);
     }
+
+    #[gpui::test]
+    async fn test_frecent_workspaces() {
+        env_logger::try_init().ok();
+
+        let db = WorkspaceDb(open_test_db("test_frecent_workspaces").await);
+
+        let dir = std::env::temp_dir().join("test_frecent_workspaces");
+        let (often_opened, rarely_opened) = (dir.join("often"), dir.join("rarely"));
+        std::fs::create_dir_all(&often_opened).unwrap();
+        std::fs::create_dir_all(&rarely_opened).unwrap();
+
+        for (id, path) in [
+            (1, often_opened.clone()),
+            (2, rarely_opened.clone()),
+            (3, dir.join("deleted")),
+        ] {
+            db.save_workspace(SerializedWorkspace {
+                id,
+                location: [path].into(),
+                center_group: Default::default(),
+                bounds: Default::default(),
+                display: Default::default(),
+                docks: Default::default(),
+            })
+            .await;
+        }
+        for _ in 0..3 {
+            db.record_workspace_opened(1).await.unwrap();
+        }
+        db.record_workspace_opened(2).await.unwrap();
+        db.record_workspace_opened(3).await.unwrap();
+
+        // Workspaces that no longer exist on disk are pruned
+        let frecent = db.frecent_workspaces_on_disk().await.unwrap();
+        assert_eq!(
+            frecent,
+            [(1, [often_opened].into()), (2, [rarely_opened].into())]
+        );
+        assert!(db.workspace_for_roots(&[dir.join("deleted")]).is_none());
+    }
 }
@@ -164,6 +164,7 @@ impl_actions!(
         OpenTerminal,
         Save,
         SaveAll,
+        SpawnInTerminal,
         SwapPaneInDirection,
     ]
 );
@@ -218,6 +219,13 @@ pub struct OpenTerminal {
     pub working_directory: PathBuf,
 }
 
+/// Opens a new terminal and runs the command in it.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct SpawnInTerminal {
+    pub command: String,
+    pub working_directory: Option<PathBuf>,
+}
+
 pub type WorkspaceId = i64;
 
 pub fn init_settings(cx: &mut AppContext) {
@@ -426,6 +434,7 @@ impl DelayedDebouncedEditAction {
 
 pub enum Event {
     PaneAdded(View<Pane>),
+    ActiveItemChanged,
     ContactRequestedJoin(u64),
     WorkspaceCreated(WeakView<Workspace>),
 }
@@ -756,6 +765,7 @@ impl Workspace {
             } else {
                 DB.next_id().await.unwrap_or(0)
             };
+            DB.record_workspace_opened(workspace_id).await.log_err();
 
             let window = if let Some(window) = requesting_window {
                 cx.update_window(window.into(), |_, cx| {
@@ -1177,31 +1187,34 @@ impl Workspace {
 
             Ok(this
                 .update(&mut cx, |this, cx| {
-                    this.save_all_internal(SaveIntent::Close, cx)
+                    this.save_all_internal(SaveIntent::Close, quitting, cx)
                 })?
                 .await?)
         })
     }
 
     fn save_all(&mut self, action: &SaveAll, cx: &mut ViewContext<Self>) {
-        self.save_all_internal(action.save_intent.unwrap_or(SaveIntent::SaveAll), cx)
+        self.save_all_internal(action.save_intent.unwrap_or(SaveIntent::SaveAll), false, cx)
             .detach_and_log_err(cx);
     }
 
     fn save_all_internal(
         &mut self,
         mut save_intent: SaveIntent,
+        quitting: bool,
         cx: &mut ViewContext<Self>,
     ) -> Task<Result<bool>> {
         if self.project.read(cx).is_disconnected() {
             return Task::ready(Ok(true));
         }
+        // Items that keep their unsaved changes across restarts don't need to be saved
+        // before quitting
         let dirty_items = self
             .panes
             .iter()
             .flat_map(|pane| {
                 pane.read(cx).items().filter_map(|item| {
-                    if item.is_dirty(cx) {
+                    if item.is_dirty(cx) && !(quitting && item.persists_unsaved_changes(cx)) {
                         Some((pane.downgrade(), item.boxed_clone()))
                     } else {
                         None
@@ -1962,6 +1975,9 @@ impl Workspace {
         item
     }
 
+    /// Opens the item in the pane to the right of the active one, splitting the active pane
+    /// when there's no such pane. The active pane is left as it was, even when the item is
+    /// already open in it.
     pub fn split_project_item<T>(
         &mut self,
         project_item: Model<T::Item>,
@@ -1973,11 +1989,23 @@ impl Workspace {
         use project::Item as _;
 
         let entry_id = project_item.read(cx).entry_id(cx);
-        if let Some(item) = entry_id
-            .and_then(|entry_id| self.active_pane().read(cx).item_for_entry(entry_id, cx))
-            .and_then(|item| item.downcast())
-        {
-            self.activate_item(&item, cx);
+        let adjacent_pane = self
+            .find_pane_in_direction(SplitDirection::Right, cx)
+            .cloned();
+        if let Some(pane) = adjacent_pane {
+            if let Some(item) = entry_id
+                .and_then(|entry_id| pane.read(cx).item_for_entry(entry_id, cx))
+                .and_then(|item| item.downcast())
+            {
+                self.activate_item(&item, cx);
+                return item;
+            }
+
+            let item =
+                cx.new_view(|cx| T::for_project_item(self.project().clone(), project_item, cx));
+            pane.update(cx, |pane, cx| {
+                pane.add_item(Box::new(item.clone()), true, true, None, cx)
+            });
             return item;
         }
 
@@ -2497,11 +2525,12 @@ impl Workspace {
             .any(|state| state.leader_id == peer_id)
     }
 
-    fn active_item_path_changed(&mut self, cx: &mut WindowContext) {
+    fn active_item_path_changed(&mut self, cx: &mut ViewContext<Self>) {
         let active_entry = self.active_project_path(cx);
         self.project
             .update(cx, |project, cx| project.set_active_path(active_entry, cx));
         self.update_window_title(cx);
+        cx.emit(Event::ActiveItemChanged);
     }
 
     fn update_window_title(&mut self, cx: &mut WindowContext) {
@@ -3008,29 +3037,33 @@ impl Workspace {
     }
 
     fn serialize_workspace(&self, cx: &mut WindowContext) {
-        fn serialize_pane_handle(pane_handle: &View<Pane>, cx: &WindowContext) -> SerializedPane {
-            let (items, active) = {
+        fn serialize_pane_handle(
+            pane_handle: &View<Pane>,
+            active_pane: &View<Pane>,
+            cx: &WindowContext,
+        ) -> SerializedPane {
+            let items = {
                 let pane = pane_handle.read(cx);
                 let active_item_id = pane.active_item().map(|item| item.item_id());
-                (
-                    pane.items()
-                        .filter_map(|item_handle| {
-                            Some(SerializedItem {
-                                kind: Arc::from(item_handle.serialized_item_kind()?),
-                                item_id: item_handle.item_id().as_u64(),
-                                active: Some(item_handle.item_id()) == active_item_id,
-                            })
+                pane.items()
+                    .filter_map(|item_handle| {
+                        Some(SerializedItem {
+                            kind: Arc::from(item_handle.serialized_item_kind()?),
+                            item_id: item_handle.item_id().as_u64(),
+                            active: Some(item_handle.item_id()) == active_item_id,
                         })
-                        .collect::<Vec<_>>(),
-                    pane.has_focus(cx),
-                )
+                    })
+                    .collect::<Vec<_>>()
             };
 
-            SerializedPane::new(items, active)
+            // The active pane is kept even while focus is elsewhere, such as in a dock, so that
+            // it's the one that's active again once the workspace is restored
+            SerializedPane::new(items, pane_handle == active_pane)
         }
 
         fn build_serialized_pane_group(
             pane_group: &Member,
+            active_pane: &View<Pane>,
             cx: &WindowContext,
         ) -> SerializedPaneGroup {
             match pane_group {
@@ -3043,12 +3076,12 @@ impl Workspace {
                     axis: SerializedAxis(*axis),
                     children: members
                         .iter()
-                        .map(|member| build_serialized_pane_group(member, cx))
+                        .map(|member| build_serialized_pane_group(member, active_pane, cx))
                         .collect::<Vec<_>>(),
                     flexes: Some(flexes.lock().clone()),
                 },
                 Member::Pane(pane_handle) => {
-                    SerializedPaneGroup::Pane(serialize_pane_handle(pane_handle, cx))
+                    SerializedPaneGroup::Pane(serialize_pane_handle(pane_handle, active_pane, cx))
                 }
             }
         }
@@ -3108,7 +3141,8 @@ impl Workspace {
             //  - with_local_workspace() relies on this to not have other stuff open
             //    when you open your log
             if !location.paths().is_empty() {
-                let center_group = build_serialized_pane_group(&self.center.root, cx);
+                let center_group =
+                    build_serialized_pane_group(&self.center.root, &self.active_pane, cx);
                 let docks = build_serialized_docks(self, cx);
 
                 let serialized_workspace = SerializedWorkspace {
@@ -4544,6 +4578,36 @@ mod tests {
         assert!(!task.await.unwrap());
     }
 
+    #[gpui::test]
+    async fn test_quit_with_persisted_unsaved_changes(cx: &mut TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, None, cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        let item = cx.new_view(|cx| {
+            TestItem::new(cx)
+                .with_dirty(true)
+                .with_persisted_unsaved_changes(true)
+        });
+        workspace.update(cx, |w, cx| w.add_item(Box::new(item.clone()), cx));
+
+        // Quitting doesn't prompt to save changes that are kept across restarts
+        let task = workspace.update(cx, |w, cx| w.prepare_to_close(true, cx));
+        cx.executor().run_until_parked();
+        assert!(!cx.has_pending_prompt());
+        assert!(task.await.unwrap());
+
+        // Closing the window does
+        let task = workspace.update(cx, |w, cx| w.prepare_to_close(false, cx));
+        cx.executor().run_until_parked();
+        assert!(cx.has_pending_prompt());
+        cx.simulate_prompt_answer(2);
+        cx.executor().run_until_parked();
+        assert!(!task.await.unwrap());
+    }
+
     #[gpui::test]
     async fn test_close_pane_items(cx: &mut TestAppContext) {
         init_test(cx);
@@ -4931,6 +4995,87 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_split_project_item(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        let project = Project::test(fs, [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let first_pane = workspace.update(cx, |workspace, _| workspace.active_pane().clone());
+
+        // With no pane to the right, one is split off
+        let project_item_1 = cx.update(|cx| TestProjectItem::new(1, "1.txt", cx));
+        workspace.update(cx, |workspace, cx| {
+            workspace.split_project_item::<TestItem>(project_item_1.clone(), cx);
+        });
+        let second_pane = workspace.update(cx, |workspace, _| {
+            assert_eq!(workspace.panes().len(), 2);
+            workspace.active_pane().clone()
+        });
+        assert_ne!(second_pane, first_pane);
+
+        first_pane.update(cx, |pane, cx| pane.focus(cx));
+        cx.run_until_parked();
+
+        // Once there is one, it's reused
+        let project_item_2 = cx.update(|cx| TestProjectItem::new(2, "2.txt", cx));
+        workspace.update(cx, |workspace, cx| {
+            assert_eq!(workspace.active_pane(), &first_pane);
+            workspace.split_project_item::<TestItem>(project_item_2, cx);
+            assert_eq!(workspace.panes().len(), 2);
+            assert_eq!(workspace.active_pane(), &second_pane);
+        });
+        assert_eq!(cx.update(|cx| second_pane.read(cx).items_len()), 2);
+
+        // Items which are already open in it are activated rather than opened again
+        first_pane.update(cx, |pane, cx| pane.focus(cx));
+        cx.run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            workspace.split_project_item::<TestItem>(project_item_1, cx);
+            assert_eq!(workspace.active_pane(), &second_pane);
+        });
+        second_pane.update(cx, |pane, cx| {
+            assert_eq!(pane.items_len(), 2);
+            assert_eq!(pane.active_item_index(), 0);
+            assert_eq!(pane.active_item().unwrap().project_entry_ids(cx).len(), 1);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_serialize_active_pane_without_focus(cx: &mut gpui::TestAppContext) {
+        init_test(cx);
+
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/active_pane", json!({})).await;
+        let project = Project::test(fs, ["/active_pane".as_ref()], cx).await;
+        let workspace_id = DB.next_id().await.unwrap();
+        let (workspace, cx) =
+            cx.add_window_view(|cx| Workspace::new(workspace_id, project, AppState::test(cx), cx));
+
+        let first_pane = workspace.update(cx, |workspace, _| workspace.active_pane().clone());
+        workspace.update(cx, |workspace, cx| {
+            workspace.split_pane(first_pane.clone(), SplitDirection::Right, cx);
+        });
+        first_pane.update(cx, |pane, cx| pane.focus(cx));
+        cx.run_until_parked();
+
+        // Focus moving out of the center, such as to a dock, keeps the pane active
+        cx.update(|cx| cx.blur());
+        workspace.update(cx, |workspace, cx| workspace.serialize_workspace(cx));
+        cx.run_until_parked();
+
+        let serialized = DB.workspace_for_roots(&["/active_pane"]).unwrap();
+        let SerializedPaneGroup::Group { children, .. } = serialized.center_group else {
+            panic!("expected the center to be split");
+        };
+        let active = children
+            .iter()
+            .map(|child| matches!(child, SerializedPaneGroup::Pane(pane) if pane.active))
+            .collect::<Vec<_>>();
+        assert_eq!(active, [true, false]);
+    }
+
     #[gpui::test]
     async fn test_toggle_docks_and_panels(cx: &mut gpui::TestAppContext) {
         init_test(cx);
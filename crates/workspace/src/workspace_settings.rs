@@ -6,6 +6,7 @@ use settings::Settings;
 pub struct WorkspaceSettings {
     pub active_pane_magnification: f32,
     pub confirm_quit: bool,
+    pub hot_exit: bool,
     pub show_call_status_icon: bool,
     pub autosave: AutosaveSetting,
 }
@@ -22,6 +23,11 @@ pub struct WorkspaceSettingsContent {
     ///
     /// Default: false
     pub confirm_quit: Option<bool>,
+    /// Whether to keep the unsaved changes of editors when quitting, and restore them
+    /// along with the workspace, rather than prompting to save them.
+    ///
+    /// Default: true
+    pub hot_exit: Option<bool>,
     /// Whether or not to show the call status icon in the status bar.
     ///
     /// Default: true
@@ -59,6 +59,7 @@ pub fn app_menus() -> Vec<Menu<'static>> {
                 MenuItem::os_action("Cut", editor::actions::Cut, OsAction::Cut),
                 MenuItem::os_action("Copy", editor::actions::Copy, OsAction::Copy),
                 MenuItem::os_action("Paste", editor::actions::Paste, OsAction::Paste),
+                MenuItem::action("Paste From History", editor::actions::PasteFromHistory),
                 MenuItem::separator(),
                 MenuItem::action("Find", search::buffer_search::Deploy { focus: true }),
                 MenuItem::action("Find In Project", workspace::NewSearch),
@@ -132,7 +133,9 @@ pub fn app_menus() -> Vec<Menu<'static>> {
                 // MenuItem::action("Go to Symbol in Project", project_symbols::Toggle),
                 MenuItem::action("Go to Symbol in Editor", outline::Toggle),
                 MenuItem::action("Go to Definition", editor::actions::GoToDefinition),
+                MenuItem::action("Go to Declaration", editor::actions::GoToDeclaration),
                 MenuItem::action("Go to Type Definition", editor::actions::GoToTypeDefinition),
+                MenuItem::action("Go to Implementation", editor::actions::GoToImplementation),
                 MenuItem::action("Find All References", editor::actions::FindAllReferences),
                 MenuItem::action("Go to Line/Column", go_to_line::Toggle),
                 MenuItem::separator(),
@@ -0,0 +1,3 @@
+(function_declaration
+    name: (identifier) @name
+    (#match? @name "^Test")) @run
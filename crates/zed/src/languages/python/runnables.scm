@@ -0,0 +1,3 @@
+(function_definition
+    name: (identifier) @name
+    (#match? @name "^test")) @run
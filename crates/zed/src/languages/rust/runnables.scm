@@ -0,0 +1,9 @@
+(
+    (attribute_item) @_attribute
+    .
+    (attribute_item)*
+    .
+    (function_item
+        name: (_) @name) @run
+    (#match? @_attribute "^#\\[(\\w+::)*test(\\(.*\\))?\\]$")
+)
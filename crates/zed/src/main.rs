@@ -67,6 +67,9 @@ fn main() {
         return;
     }
 
+    // Must happen before anything opens the database, and never while another instance uses it
+    db::maintenance::reset_if_requested(&db::DB_DIR, &db::RELEASE_CHANNEL).log_err();
+
     log::info!("========== starting zed ==========");
     let app = App::new().with_assets(Assets);
 
@@ -149,6 +152,7 @@ fn main() {
         cx.set_global(client.clone());
 
         zed::init(cx);
+        db::maintenance::init(cx);
         theme::init(theme::LoadThemes::All, cx);
         project::Project::init(&client, cx);
         client::init(&client, cx);
@@ -206,6 +210,7 @@ fn main() {
         recent_projects::init(cx);
 
         go_to_line::init(cx);
+        clipboard_history::init(cx);
         file_finder::init(cx);
         outline::init(cx);
         project_symbols::init(cx);
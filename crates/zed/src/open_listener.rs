@@ -298,6 +298,23 @@ pub async fn handle_cli_connection(
                     })
                     .log_err();
             }
+            CliRequest::ListRecentProjects => {
+                let workspaces = workspace::WORKSPACE_DB
+                    .frecent_workspaces_on_disk()
+                    .await
+                    .unwrap_or_default();
+                // Paths are separated by tabs, which unlike spaces rarely appear in them
+                for (_, location) in workspaces {
+                    let message = location
+                        .paths()
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\t");
+                    responses.send(CliResponse::Stdout { message }).log_err();
+                }
+                responses.send(CliResponse::Exit { status: 0 }).log_err();
+            }
         }
     }
 }
@@ -18,6 +18,7 @@ pub use open_listener::*;
 use anyhow::{anyhow, Context as _};
 use assets::Assets;
 use futures::{channel::mpsc, select_biased, StreamExt};
+use outline::outline_panel::OutlinePanel;
 use project_panel::ProjectPanel;
 use quick_action_bar::QuickActionBar;
 use rope::Rope;
@@ -36,7 +37,7 @@ use welcome::BaseKeymap;
 use workspace::Pane;
 use workspace::{
     create_and_open_local_file, notifications::simple_message_notification::MessageNotification,
-    open_new, AppState, NewFile, NewWindow, Workspace, WorkspaceSettings,
+    open_new, AppState, NewFile, NewWindow, Restart, Workspace, WorkspaceSettings,
 };
 use zed_actions::{OpenBrowser, OpenSettings, OpenZedURL, Quit};
 
@@ -162,6 +163,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace_handle.clone(),
                 cx.clone(),
             );
+            let outline_panel = OutlinePanel::load(workspace_handle.clone(), cx.clone());
             let (
                 project_panel,
                 terminal_panel,
@@ -169,6 +171,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                outline_panel,
             ) = futures::try_join!(
                 project_panel,
                 terminal_panel,
@@ -176,6 +179,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                outline_panel,
             )?;
 
             workspace_handle.update(&mut cx, |workspace, cx| {
@@ -185,6 +189,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace.add_panel(channels_panel, cx);
                 workspace.add_panel(chat_panel, cx);
                 workspace.add_panel(notification_panel, cx);
+                workspace.add_panel(outline_panel, cx);
                 cx.focus_self();
             })
         })
@@ -221,6 +226,25 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 })
                 .detach_and_log_err(cx);
             })
+            .register_action(|_, _: &ResetDatabase, cx| {
+                let prompt = cx.prompt(
+                    PromptLevel::Warning,
+                    "Reset the database?",
+                    Some(
+                        "Zed will forget its open workspaces, recent projects and other saved \
+                        state. The database is deleted when Zed restarts.",
+                    ),
+                    &["Reset and Restart", "Cancel"],
+                );
+                cx.spawn(|_, mut cx| async move {
+                    if prompt.await? != 0 {
+                        return Ok(());
+                    }
+                    db::maintenance::request_reset(&db::DB_DIR, &db::RELEASE_CHANNEL)?;
+                    cx.update(|cx| workspace::restart(&Restart, cx))
+                })
+                .detach_and_log_err(cx);
+            })
             .register_action(|workspace, _: &OpenLog, cx| {
                 open_log_file(workspace, cx);
             })
@@ -350,7 +374,9 @@ fn initialize_pane(workspace: &mut Workspace, pane: &View<Pane>, cx: &mut ViewCo
         pane.toolbar().update(cx, |toolbar, cx| {
             let breadcrumbs = cx.new_view(|_| Breadcrumbs::new());
             toolbar.add_item(breadcrumbs, cx);
-            let buffer_search_bar = cx.new_view(search::BufferSearchBar::new);
+            let buffer_search_bar = cx.new_view(|cx| {
+                search::BufferSearchBar::new(cx).with_persisted_history(workspace.database_id())
+            });
             toolbar.add_item(buffer_search_bar.clone(), cx);
 
             let quick_action_bar =